@@ -0,0 +1,30 @@
+use std::env;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=MOLTENVK_LIB_DIR");
+
+    if env::var_os("CARGO_FEATURE_MOLTENVK_STATIC").is_some() {
+        let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+        if target_os != "macos" && target_os != "ios" {
+            panic!("the moltenvk-static feature is only supported on macOS and iOS");
+        }
+
+        // Directory containing libMoltenVK.a (e.g. MoltenVK/MoltenVK.xcframework/macos-arm64_x86_64)
+        if let Some(lib_dir) = env::var_os("MOLTENVK_LIB_DIR") {
+            println!("cargo:rustc-link-search=native={}", lib_dir.to_string_lossy());
+        }
+
+        println!("cargo:rustc-link-lib=static=MoltenVK");
+        println!("cargo:rustc-link-lib=dylib=c++");
+        for framework in &["Metal", "Foundation", "QuartzCore", "IOSurface", "CoreGraphics"] {
+            println!("cargo:rustc-link-lib=framework={}", framework);
+        }
+        if target_os == "macos" {
+            println!("cargo:rustc-link-lib=framework=IOKit");
+            println!("cargo:rustc-link-lib=framework=AppKit");
+        } else {
+            println!("cargo:rustc-link-lib=framework=UIKit");
+        }
+    }
+}
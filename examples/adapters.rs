@@ -20,6 +20,33 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("Device Type:    {:?}", properties.device_type);
         println!("Device ID:      {:?}", properties.device_id);
         println!("Vendor ID:      {:?}", properties.vender_id);
+        let info = adapter.info();
+        if let Some(pci_bus_info) = info.pci_bus_info {
+            println!(
+                "PCI Bus:        {:04x}:{:02x}:{:02x}.{}",
+                pci_bus_info.domain, pci_bus_info.bus, pci_bus_info.device, pci_bus_info.function
+            );
+        }
+        if let Some(device_luid) = info.device_luid {
+            println!("Device LUID:    {:02x?}", device_luid);
+        }
+        if let Some(driver) = properties.driver {
+            println!("Driver:         {} ({})", driver.driver_name, driver.driver_info);
+        }
+        if let Some(portability_features) = adapter.portability_features() {
+            println!(
+                "Portability:    unsupported: {:?}",
+                portability_features.unsupported_features()
+            );
+        }
+        for (index, heap) in adapter.memory_properties().memory_heaps.iter().enumerate() {
+            println!(
+                "Memory Heap {}:  {} MiB {:?}",
+                index,
+                heap.size / (1024 * 1024),
+                heap.flags
+            );
+        }
         println!();
     }
 
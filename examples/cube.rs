@@ -8,16 +8,16 @@ use cgmath::SquareMatrix;
 
 use std::borrow::Cow;
 
-use crate::util::{App, EventHandlers};
+use crate::util::{App, Camera, EventHandlers};
 
 use vki::{
-    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
-    BlendDescriptor, BufferUsage, Color, ColorStateDescriptor, ColorWrite, CompareFunction, CullMode,
-    DepthStencilStateDescriptor, FrontFace, IndexFormat, InputStepMode, LoadOp, PipelineLayoutDescriptor,
-    PipelineStageDescriptor, PolygonMode, PrimitiveTopology, RasterizationStateDescriptor,
-    RenderPassColorAttachmentDescriptor, RenderPassDepthStencilAttachmentDescriptor, RenderPassDescriptor,
-    RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderStage, StencilStateFaceDescriptor, StoreOp, SwapchainError,
-    VertexAttributeDescriptor, VertexBufferLayoutDescriptor, VertexFormat, VertexStateDescriptor,
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType,
+    Buffer, BufferUsage, Color, ColorStateDescriptor, CommandEncoder, DepthStencilStateDescriptor, Device, IndexFormat,
+    InputStepMode, LoadOp, PipelineLayoutDescriptor, PipelineStageDescriptor, Pod, PrimitiveTopology,
+    RasterizationStateDescriptor, RenderPassColorAttachmentDescriptor, RenderPassDepthStencilAttachmentDescriptor,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderStage,
+    SwapchainError, TypedBuffer, VertexAttributeDescriptor, VertexBufferLayoutDescriptor, VertexFormat,
+    VertexStateDescriptor, Zeroable,
 };
 
 #[repr(C)]
@@ -27,231 +27,63 @@ struct PositionColor {
     pub color: [f32; 4],
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let _ = pretty_env_logger::try_init();
-
-    let mut app: App<()> = App::init("cube.rs", 800, 600, EventHandlers::Default)?;
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, Pod, Zeroable)]
+pub struct Uniforms {
+    pub projection: [[f32; 4]; 4],
+    pub view: [[f32; 4]; 4],
+    pub model: [[f32; 4]; 4],
+    pub time: f32,
+    pub _pad0: [f32; 3],
+}
 
-    app.set_sample_count(8)?;
+const CAMERA_EYE: Point3<f32> = Point3 {
+    x: 2.0,
+    y: 2.0,
+    z: -2.0,
+};
 
-    app.camera.eye = Point3 {
-        x: 2.0,
-        y: 2.0,
-        z: -2.0,
-    };
-
-    // Note that we use a clip space correction matrix with the projection so that these vertices
-    // can be specified in a right handed coordinate system (with positive `y`) and counter-clockwise
-    // wind ordering.
-    #[rustfmt::skip]
-    let vertices = vec![
-        PositionColor { position: [-0.5,  0.5,  0.5], color: [0.0, 1.0, 0.0, 1.0] }, // 0 - green
-        PositionColor { position: [-0.5, -0.5,  0.5], color: [0.0, 0.0, 0.0, 1.0] }, // 1 - black
-        PositionColor { position: [ 0.5,  0.5,  0.5], color: [0.0, 1.0, 1.0, 1.0] }, // 2 - cyan
-        PositionColor { position: [ 0.5, -0.5,  0.5], color: [0.0, 0.0, 1.0, 1.0] }, // 3 - blue
-
-        PositionColor { position: [-0.5,  0.5, -0.5], color: [1.0, 1.0, 0.0, 1.0] }, // 4 - yellow
-        PositionColor { position: [-0.5, -0.5, -0.5], color: [1.0, 0.0, 0.0, 1.0] }, // 5 - red
-        PositionColor { position: [ 0.5,  0.5, -0.5], color: [1.0, 1.0, 1.0, 1.0] }, // 6 - white
-        PositionColor { position: [ 0.5, -0.5, -0.5], color: [1.0, 0.0, 1.0, 1.0] }, // 7 - magenta
-    ];
-
-    #[rustfmt::skip]
-    let indices: &[u16] = &[
-        // front
-        0, 1, 2,
-        2, 1, 3,
-
-        // back
-        7, 5, 6,
-        6, 5, 4,
-
-        // right
-        3, 7, 2,
-        2, 7, 6,
-
-        // left
-        4, 5, 0,
-        0, 5, 1,
-
-        // top
-        4, 0, 6,
-        6, 0, 2,
-
-        // bottom
-        3, 1, 7,
-        7, 1, 5,
-    ];
-
-    #[repr(C)]
-    #[derive(Debug, Default, Copy, Clone)]
-    pub struct Uniforms {
-        pub projection: [[f32; 4]; 4],
-        pub view: [[f32; 4]; 4],
-        pub model: [[f32; 4]; 4],
-        pub time: f32,
-        pub _pad0: [f32; 3],
-    }
+const CLEAR_COLOR: Color = Color {
+    r: 0.2,
+    g: 0.6,
+    b: 0.8,
+    a: 1.0,
+};
 
-    let mut uniforms = vec![Uniforms::default(); 1];
-
-    let mut encoder = app.device.create_command_encoder()?;
-
-    let vertex_buffer = util::create_buffer_with_data(&app.device, &mut encoder, BufferUsage::VERTEX, &vertices)?;
-    let index_buffer = util::create_buffer_with_data(&app.device, &mut encoder, BufferUsage::INDEX, &indices)?;
-    let uniform_buffer = util::create_buffer_with_data(
-        &app.device,
-        &mut encoder,
-        BufferUsage::UNIFORM | BufferUsage::COPY_DST,
-        &uniforms,
-    )?;
-
-    app.device.get_queue().submit(&[encoder.finish()?])?;
-
-    #[rustfmt::skip]
-    let bind_group_layout = app.device.create_bind_group_layout(BindGroupLayoutDescriptor {
-        entries: vec![
-            BindGroupLayoutEntry {
-                binding: 0,
-                binding_type: BindingType::UniformBuffer,
-                visibility: ShaderStage::FRAGMENT | ShaderStage::VERTEX,
-            }
-        ],
-    })?;
+/// Set `HEADLESS_FRAMES` to render the given number of frames offscreen, without a window, and
+/// report the frame time.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let _ = pretty_env_logger::try_init();
 
-    #[rustfmt::skip]
-    let bind_group = app.device.create_bind_group(BindGroupDescriptor {
-        layout: bind_group_layout.clone(),
-        entries: vec![
-            BindGroupEntry {
-                binding: 0,
-                resource: BindingResource::Buffer(uniform_buffer.clone(), 0..util::byte_length(&uniforms)),
-            }
-        ],
-    })?;
+    if let Ok(frames) = std::env::var("HEADLESS_FRAMES") {
+        return run_headless(frames.parse()?);
+    }
 
-    let pipeline_layout = app.device.create_pipeline_layout(PipelineLayoutDescriptor {
-        bind_group_layouts: vec![bind_group_layout],
-        push_constant_ranges: vec![],
-    })?;
+    let mut app: App<()> = App::init("cube.rs", 800, 600, EventHandlers::Default)?;
 
-    let vs = app.device.create_shader_module(ShaderModuleDescriptor {
-        code: include_bytes!("shaders/cube.vert.spv"),
-    })?;
+    app.set_sample_count(8)?;
 
-    let fs = app.device.create_shader_module(ShaderModuleDescriptor {
-        code: include_bytes!("shaders/cube.frag.spv"),
-    })?;
+    app.camera.eye = CAMERA_EYE;
 
-    #[rustfmt::skip]
-    let render_pipeline = app.device.create_render_pipeline(RenderPipelineDescriptor {
-        layout: pipeline_layout,
-        vertex_stage: PipelineStageDescriptor { module: vs, entry_point: Cow::Borrowed("main") },
-        fragment_stage: PipelineStageDescriptor { module: fs, entry_point: Cow::Borrowed("main") },
-        rasterization_state: RasterizationStateDescriptor {
-            front_face: FrontFace::Ccw,
-            cull_mode: CullMode::Back,
-            polygon_mode: PolygonMode::Fill,
-            depth_bias: 0,
-            depth_bias_slope_scale: 0.0,
-            depth_bias_clamp: 0.0,
-        },
-        primitive_topology: PrimitiveTopology::TriangleList,
-        color_states: vec![
-            ColorStateDescriptor {
-                format: util::DEFAULT_COLOR_FORMAT,
-                color_blend: BlendDescriptor::OPAQUE,
-                alpha_blend: BlendDescriptor::OPAQUE,
-                write_mask: ColorWrite::ALL,
-            }
-        ],
-        depth_stencil_state: Some(DepthStencilStateDescriptor {
-            format: util::DEFAULT_DEPTH_FORMAT,
-            depth_write_enabled: true,
-            depth_compare: CompareFunction::Less,
-            stencil_back: StencilStateFaceDescriptor::IGNORE,
-            stencil_front: StencilStateFaceDescriptor::IGNORE,
-            stencil_read_mask: 0,
-            stencil_write_mask: 0,
-        }),
-        vertex_state: VertexStateDescriptor {
-            index_format: IndexFormat::U16,
-            vertex_buffers: vec![
-                VertexBufferLayoutDescriptor {
-                    input_slot: 0,
-                    stride: util::byte_stride(&vertices),
-                    step_mode: InputStepMode::Vertex,
-                    attributes: vec![
-                        VertexAttributeDescriptor {
-                            format: VertexFormat::Float3,
-                            offset: offset_of!(PositionColor, position),
-                            shader_location: 0,
-                        },
-                        VertexAttributeDescriptor {
-                            format: VertexFormat::Float4,
-                            offset: offset_of!(PositionColor, color),
-                            shader_location: 1,
-                        }
-                    ],
-                }
-            ],
-        },
-        sample_count: app.get_sample_count(),
-        alpha_to_coverage_enabled: false,
-    })?;
+    let mut cube = Cube::new(&app.device, app.get_sample_count())?;
 
     app.run(move |app| {
-        let model = cgmath::Matrix4::identity();
-
-        uniforms[0].time = 0.0;
-        uniforms[0].projection = app.camera.projection.into();
-        uniforms[0].model = model.into();
-        uniforms[0].view = app.camera.view.into();
-
-        uniform_buffer.set_sub_data(0, &uniforms)?;
+        cube.update(&app.camera)?;
 
         let frame = match app.swapchain.acquire_next_image() {
             Ok(frame) => frame,
             Err(SwapchainError::OutOfDate) => return Ok(()),
+            Err(SwapchainError::SurfaceLost) => return Ok(app.recreate_surface()?),
             Err(e) => return Err(e)?,
         };
 
         let mut encoder = app.device.create_command_encoder()?;
 
-        let (attachment, resolve_target) = if app.get_sample_count() == 1 {
-            (&frame.view, None)
-        } else {
-            (&app.color_view, Some(&frame.view))
-        };
-
-        #[rustfmt::skip]
-        let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
-            color_attachments: &[
-                RenderPassColorAttachmentDescriptor {
-                    attachment,
-                    resolve_target,
-                    store_op: StoreOp::Store,
-                    load_op: LoadOp::Clear,
-                    clear_color: Color { r: 0.2, g: 0.6, b: 0.8, a: 1.0 },
-                }
-            ],
-            depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
-                attachment: &app.depth_view,
-                clear_depth: 1.0,
-                clear_stencil: 0,
-                depth_load_op: LoadOp::Clear,
-                depth_store_op: StoreOp::Store,
-                stencil_load_op: LoadOp::Clear,
-                stencil_store_op: StoreOp::Store,
-            }),
-        });
-
-        render_pass.set_pipeline(&render_pipeline);
-        render_pass.set_bind_group(0, &bind_group, None);
-        render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0]);
-        render_pass.set_index_buffer(&index_buffer, 0);
-        render_pass.draw_indexed(indices.len() as u32, 1, 0, 0, 0);
-        render_pass.end_pass();
+        cube.draw(
+            &mut encoder,
+            frame.color_attachment(LoadOp::Clear, CLEAR_COLOR),
+            frame.depth_stencil_attachment(LoadOp::Clear, 1.0, 0),
+        )?;
 
         let command_buffer = encoder.finish()?;
 
@@ -268,3 +100,219 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     })
 }
+
+fn run_headless(frames: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let mut app = App::<()>::init_headless(800, 600)?;
+
+    app.camera.eye = CAMERA_EYE;
+
+    let mut cube = Cube::new(&app.device, 1)?;
+
+    app.run(frames, |app| {
+        cube.update(&app.camera)?;
+
+        let mut encoder = app.device.create_command_encoder()?;
+
+        cube.draw(
+            &mut encoder,
+            app.color_attachment(LoadOp::Clear, CLEAR_COLOR),
+            Some(app.depth_stencil_attachment(LoadOp::Clear, 1.0, 0)),
+        )?;
+
+        app.device.get_queue().submit(&[encoder.finish()?])?;
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
+
+struct Cube {
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    index_count: u32,
+    uniforms: Vec<Uniforms>,
+    uniform_buffer: TypedBuffer<Uniforms>,
+    bind_group: BindGroup,
+    render_pipeline: RenderPipeline,
+}
+
+impl Cube {
+    fn new(device: &Device, sample_count: u32) -> Result<Cube, Box<dyn std::error::Error>> {
+        // Note that we use a clip space correction matrix with the projection so that these vertices
+        // can be specified in a right handed coordinate system (with positive `y`) and counter-clockwise
+        // wind ordering.
+        #[rustfmt::skip]
+        let vertices = vec![
+            PositionColor { position: [-0.5,  0.5,  0.5], color: [0.0, 1.0, 0.0, 1.0] }, // 0 - green
+            PositionColor { position: [-0.5, -0.5,  0.5], color: [0.0, 0.0, 0.0, 1.0] }, // 1 - black
+            PositionColor { position: [ 0.5,  0.5,  0.5], color: [0.0, 1.0, 1.0, 1.0] }, // 2 - cyan
+            PositionColor { position: [ 0.5, -0.5,  0.5], color: [0.0, 0.0, 1.0, 1.0] }, // 3 - blue
+
+            PositionColor { position: [-0.5,  0.5, -0.5], color: [1.0, 1.0, 0.0, 1.0] }, // 4 - yellow
+            PositionColor { position: [-0.5, -0.5, -0.5], color: [1.0, 0.0, 0.0, 1.0] }, // 5 - red
+            PositionColor { position: [ 0.5,  0.5, -0.5], color: [1.0, 1.0, 1.0, 1.0] }, // 6 - white
+            PositionColor { position: [ 0.5, -0.5, -0.5], color: [1.0, 0.0, 1.0, 1.0] }, // 7 - magenta
+        ];
+
+        #[rustfmt::skip]
+        let indices: &[u16] = &[
+            // front
+            0, 1, 2,
+            2, 1, 3,
+
+            // back
+            7, 5, 6,
+            6, 5, 4,
+
+            // right
+            3, 7, 2,
+            2, 7, 6,
+
+            // left
+            4, 5, 0,
+            0, 5, 1,
+
+            // top
+            4, 0, 6,
+            6, 0, 2,
+
+            // bottom
+            3, 1, 7,
+            7, 1, 5,
+        ];
+
+        let uniforms = vec![Uniforms::default(); 1];
+
+        let mut encoder = device.create_command_encoder()?;
+
+        let vertex_buffer = util::create_buffer_with_data(device, &mut encoder, BufferUsage::VERTEX, &vertices)?;
+        let index_buffer = util::create_buffer_with_data(device, &mut encoder, BufferUsage::INDEX, &indices)?;
+        let uniform_buffer =
+            device.create_typed_buffer::<Uniforms>(uniforms.len(), BufferUsage::UNIFORM | BufferUsage::COPY_DST)?;
+        uniform_buffer.write(0, &uniforms)?;
+
+        device.get_queue().submit(&[encoder.finish()?])?;
+
+        #[rustfmt::skip]
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                    visibility: ShaderStage::FRAGMENT | ShaderStage::VERTEX,
+                }
+            ],
+        })?;
+
+        #[rustfmt::skip]
+        let bind_group = device.create_bind_group(BindGroupDescriptor {
+            layout: bind_group_layout.clone(),
+            entries: vec![
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.binding_resource(0..uniforms.len()),
+                }
+            ],
+        })?;
+
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout],
+            push_constant_ranges: vec![],
+        })?;
+
+        let vs = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/cube.vert.spv"),
+        })?;
+
+        let fs = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/cube.frag.spv"),
+        })?;
+
+        #[rustfmt::skip]
+        let render_pipeline = device.create_render_pipeline(RenderPipelineDescriptor {
+            layout: pipeline_layout,
+            vertex_stage: PipelineStageDescriptor { module: vs, entry_point: Cow::Borrowed("main") },
+            fragment_stage: Some(PipelineStageDescriptor { module: fs, entry_point: Cow::Borrowed("main") }),
+            rasterization_state: RasterizationStateDescriptor::CULL_BACK,
+            primitive_topology: PrimitiveTopology::TriangleList,
+            color_states: vec![ColorStateDescriptor::simple(util::DEFAULT_COLOR_FORMAT)],
+            depth_stencil_state: Some(DepthStencilStateDescriptor::LESS_WRITE.with_format(util::DEFAULT_DEPTH_FORMAT)),
+            vertex_state: VertexStateDescriptor {
+                index_format: IndexFormat::U16,
+                primitive_restart_enabled: false,
+                vertex_buffers: vec![
+                    VertexBufferLayoutDescriptor {
+                        input_slot: 0,
+                        stride: util::byte_stride(&vertices),
+                        step_mode: InputStepMode::Vertex,
+                        step_rate: 1,
+                        attributes: vec![
+                            VertexAttributeDescriptor {
+                                format: VertexFormat::Float3,
+                                offset: offset_of!(PositionColor, position),
+                                shader_location: 0,
+                            },
+                            VertexAttributeDescriptor {
+                                format: VertexFormat::Float4,
+                                offset: offset_of!(PositionColor, color),
+                                shader_location: 1,
+                            }
+                        ],
+                    }
+                ],
+            },
+            sample_count: sample_count,
+            alpha_to_coverage_enabled: false,
+            blend_color: None,
+        })?;
+
+        Ok(Cube {
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+            uniforms,
+            uniform_buffer,
+            bind_group,
+            render_pipeline,
+        })
+    }
+
+    fn update(&mut self, camera: &Camera) -> Result<(), vki::Error> {
+        let model = cgmath::Matrix4::identity();
+
+        self.uniforms[0].time = 0.0;
+        self.uniforms[0].projection = camera.projection.into();
+        self.uniforms[0].model = model.into();
+        self.uniforms[0].view = camera.view.into();
+
+        self.uniform_buffer.write(0, &self.uniforms)
+    }
+
+    fn draw(
+        &self,
+        encoder: &mut CommandEncoder,
+        color_attachment: RenderPassColorAttachmentDescriptor,
+        depth_stencil_attachment: Option<RenderPassDepthStencilAttachmentDescriptor>,
+    ) -> Result<(), vki::Error> {
+        let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
+            color_attachments: &[color_attachment],
+            depth_stencil_attachment,
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
+        });
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, None);
+        render_pass.set_vertex_buffers(0, &[self.vertex_buffer.clone()], &[0])?;
+        render_pass.set_index_buffer(&self.index_buffer, 0);
+        render_pass.draw_indexed(self.index_count, 1, 0, 0, 0);
+        render_pass.end_pass();
+
+        Ok(())
+    }
+}
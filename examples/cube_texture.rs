@@ -15,14 +15,13 @@ use crate::util::{App, EventHandlers};
 use std::time::Instant;
 use vki::{
     AddressMode, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource,
-    BindingType, BlendDescriptor, BufferCopyView, BufferUsage, Color, ColorStateDescriptor, ColorWrite,
+    BindingType, BlendDescriptor, BorderColor, BufferCopyView, BufferUsage, Color, ColorStateDescriptor, ColorWrite,
     CompareFunction, CullMode, DepthStencilStateDescriptor, Extent3d, FilterMode, FrontFace, IndexFormat,
     InputStepMode, LoadOp, Origin3d, PipelineLayoutDescriptor, PipelineStageDescriptor, PolygonMode, PrimitiveTopology,
-    RasterizationStateDescriptor, RenderPassColorAttachmentDescriptor, RenderPassDepthStencilAttachmentDescriptor,
-    RenderPassDescriptor, RenderPipelineDescriptor, SamplerDescriptor, ShaderModuleDescriptor, ShaderStage,
-    StencilStateFaceDescriptor, StoreOp, SwapchainError, TextureBlitView, TextureCopyView, TextureDescriptor,
-    TextureDimension, TextureFormat, TextureUsage, VertexAttributeDescriptor, VertexBufferLayoutDescriptor,
-    VertexFormat, VertexStateDescriptor,
+    RasterizationStateDescriptor, RenderPassDescriptor, RenderPipelineDescriptor, SamplerDescriptor,
+    ShaderModuleDescriptor, ShaderStage, StencilStateFaceDescriptor, SwapchainError, TextureBlitView, TextureCopyView,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsage, VertexAttributeDescriptor,
+    VertexBufferLayoutDescriptor, VertexFormat, VertexStateDescriptor,
 };
 
 #[repr(C)]
@@ -114,6 +113,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         min_filter: FilterMode::Linear,
         mag_filter: FilterMode::Linear,
         compare_function: CompareFunction::Never,
+        border_color: BorderColor::TransparentBlack,
+        max_anisotropy: 1,
     })?;
 
     // create texture
@@ -134,14 +135,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let container_texture_view = container_texture.create_default_view()?;
 
-    let texture_buffer = util::create_staging_buffer(&app.device, &image)?;
+    let image_size = (texture_size.width * texture_size.height * 4) as usize;
+    let (texture_buffer, texture_layout) =
+        util::create_texture_staging_buffer(&app.device, &image[..image_size], texture_size)?;
 
     encoder.copy_buffer_to_texture(
         BufferCopyView {
-            offset: 0,
             buffer: &texture_buffer,
-            image_height: texture_size.height,
-            row_length: texture_size.width,
+            layout: texture_layout,
         },
         TextureCopyView {
             texture: &container_texture,
@@ -150,7 +151,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             array_layer: 0,
         },
         texture_size,
-    );
+    )?;
 
     // generate mipmaps
 
@@ -205,16 +206,22 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             BindGroupLayoutEntry {
                 binding: 0,
                 binding_type: BindingType::UniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT | ShaderStage::VERTEX,
             },
             BindGroupLayoutEntry {
                 binding: 1,
                 binding_type: BindingType::Sampler,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT,
             },
             BindGroupLayoutEntry {
                 binding: 2,
                 binding_type: BindingType::SampledTexture,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT,
             }
         ],
@@ -256,7 +263,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let render_pipeline = app.device.create_render_pipeline(RenderPipelineDescriptor {
         layout: pipeline_layout,
         vertex_stage: PipelineStageDescriptor { module: vs, entry_point: Cow::Borrowed("main") },
-        fragment_stage: PipelineStageDescriptor { module: fs, entry_point: Cow::Borrowed("main") },
+        fragment_stage: Some(PipelineStageDescriptor { module: fs, entry_point: Cow::Borrowed("main") }),
         rasterization_state: RasterizationStateDescriptor {
             front_face: FrontFace::Ccw,
             cull_mode: CullMode::Back,
@@ -285,11 +292,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }),
         vertex_state: VertexStateDescriptor {
             index_format: IndexFormat::U16,
+            primitive_restart_enabled: false,
             vertex_buffers: vec![
                 VertexBufferLayoutDescriptor {
                     input_slot: 0,
                     stride: util::byte_stride(&vertices),
                     step_mode: InputStepMode::Vertex,
+                    step_rate: 1,
                     attributes: vec![
                         VertexAttributeDescriptor {
                             format: VertexFormat::Float3,
@@ -307,6 +316,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         },
         sample_count: app.get_sample_count(),
         alpha_to_coverage_enabled: false,
+        blend_color: None,
     })?;
 
     let start = Instant::now();
@@ -324,42 +334,27 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let frame = match app.swapchain.acquire_next_image() {
             Ok(frame) => frame,
             Err(SwapchainError::OutOfDate) => return Ok(()),
+            Err(SwapchainError::SurfaceLost) => return Ok(app.recreate_surface()?),
             Err(e) => return Err(e)?,
         };
 
         let mut encoder = app.device.create_command_encoder()?;
 
-        let (attachment, resolve_target) = if app.get_sample_count() == 1 {
-            (&frame.view, None)
-        } else {
-            (&app.color_view, Some(&frame.view))
-        };
-
         #[rustfmt::skip]
         let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
             color_attachments: &[
-                RenderPassColorAttachmentDescriptor {
-                    attachment,
-                    resolve_target,
-                    store_op: StoreOp::Store,
-                    load_op: LoadOp::Clear,
-                    clear_color: Color { r: 0.2, g: 0.6, b: 0.8, a: 1.0 },
-                }
+                frame.color_attachment(LoadOp::Clear, Color { r: 0.2, g: 0.6, b: 0.8, a: 1.0 }),
             ],
-            depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
-                attachment: &app.depth_view,
-                clear_depth: 1.0,
-                clear_stencil: 0,
-                depth_load_op: LoadOp::Clear,
-                depth_store_op: StoreOp::Store,
-                stencil_load_op: LoadOp::Clear,
-                stencil_store_op: StoreOp::Store,
-            }),
+            depth_stencil_attachment: frame.depth_stencil_attachment(LoadOp::Clear, 1.0, 0),
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
         });
 
         render_pass.set_pipeline(&render_pipeline);
         render_pass.set_bind_group(0, &bind_group, None);
-        render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0]);
+        render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0])?;
         render_pass.set_index_buffer(&index_buffer, 0);
         render_pass.draw_indexed(indices.len() as u32, 1, 0, 0, 0);
         render_pass.end_pass();
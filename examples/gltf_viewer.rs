@@ -1,12 +1,10 @@
-#[macro_use]
-extern crate memoffset;
-
 pub mod util;
 
 use cgmath::{
     EuclideanSpace, InnerSpace, Matrix, Matrix4, Point3, Quaternion, SquareMatrix, Vector1, Vector3, VectorSpace,
 };
 
+use crate::util::camera_controller::OrbitController;
 use crate::util::{App, EventHandlers};
 
 use std::collections::{HashMap, HashSet};
@@ -19,13 +17,12 @@ use std::borrow::Cow;
 use std::time::{Duration, Instant};
 use vki::{
     AddressMode, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource,
-    BindingType, BlendDescriptor, Buffer, BufferUsage, Color, ColorStateDescriptor, ColorWrite, CompareFunction,
-    CullMode, DepthStencilStateDescriptor, FilterMode, FrontFace, IndexFormat, InputStepMode, LoadOp,
+    BindingType, BlendDescriptor, BorderColor, Buffer, BufferUsage, Color, ColorStateDescriptor, ColorWrite,
+    CompareFunction, CullMode, DepthStencilStateDescriptor, FilterMode, FrontFace, IndexFormat, InputStepMode, LoadOp,
     PipelineLayoutDescriptor, PipelineStageDescriptor, PolygonMode, PrimitiveTopology, PushConstantRange,
-    RasterizationStateDescriptor, RenderPassColorAttachmentDescriptor, RenderPassDepthStencilAttachmentDescriptor,
-    RenderPassDescriptor, RenderPipelineDescriptor, Sampler, SamplerDescriptor, ShaderModuleDescriptor, ShaderStage,
-    StencilStateFaceDescriptor, StoreOp, SwapchainError, TextureFormat, TextureView, VertexAttributeDescriptor,
-    VertexBufferLayoutDescriptor, VertexFormat, VertexStateDescriptor,
+    RasterizationStateDescriptor, RenderPassDescriptor, RenderPipelineDescriptor, Sampler, SamplerDescriptor,
+    ShaderModuleDescriptor, ShaderStage, StencilStateFaceDescriptor, SwapchainError, TextureFormat, TextureView,
+    VertexLayout, VertexStateDescriptor,
 };
 
 const MAX_MORPH_TARGETS: usize = 2;
@@ -625,8 +622,6 @@ impl Channel {
     }
 }
 
-// http://vulkan.gpuinfo.org/displaydevicelimit.php?name=minUniformBufferOffsetAlignment
-#[repr(align(256))]
 #[derive(Copy, Clone, Debug, Default)]
 struct CameraAndLightSettings {
     scale_diff_base_mr: [f32; 4],
@@ -645,7 +640,6 @@ struct CameraAndLightSettings {
     specular_env_mip_count: f32,
 }
 
-#[repr(align(256))]
 #[derive(Copy, Clone, Debug, Default)]
 struct MeshSettings {
     mvp_matrix: [[f32; 4]; 4],
@@ -671,7 +665,6 @@ struct PrimitiveSettings {
     has_morph_tangents: [Bool32; 2],
 }
 
-#[repr(align(256))]
 #[derive(Copy, Clone)]
 struct SkinSettings {
     joint_matrix: [[[f32; 4]; 4]; MAX_JOINTS],
@@ -683,7 +676,6 @@ impl Default for SkinSettings {
     }
 }
 
-#[repr(align(256))]
 #[derive(Copy, Clone, Debug, Default)]
 struct MaterialSettings {
     pbr_base_color_factor: [f32; 4],
@@ -805,7 +797,7 @@ impl util::EventHandler<State> for AnimationHandler {
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, VertexLayout)]
 struct Vertex {
     position: [f32; 3],
     normal: [f32; 3],
@@ -842,6 +834,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut app: App<State> = App::init("gltf_viewer.rs", 800, 600, EventHandlers::Custom(event_handlers))?;
 
     app.set_sample_count(8)?;
+    app.set_camera_controller(OrbitController::default());
 
     let window = &app.window;
 
@@ -997,6 +990,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             min_filter,
             mipmap_filter,
             compare_function: CompareFunction::Never,
+            border_color: BorderColor::TransparentBlack,
+            max_anisotropy: 1,
         })?);
     }
 
@@ -1010,6 +1005,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         min_filter: FilterMode::Linear,
         mag_filter: FilterMode::Linear,
         compare_function: CompareFunction::Never,
+        border_color: BorderColor::TransparentBlack,
+        max_anisotropy: 1,
     })?;
 
     for texture in import.doc.textures() {
@@ -1375,13 +1372,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         &[camera_and_light_settings],
     )?;
 
+    // The settings that are selected with dynamic offsets are laid out at the device's uniform
+    // buffer offset alignment
+    let uniform_alignment = app.device.limits().min_uniform_buffer_offset_alignment;
+    let mesh_settings_stride = vki::align_to(std::mem::size_of::<MeshSettings>(), uniform_alignment);
+    let skin_settings_stride = vki::align_to(std::mem::size_of::<SkinSettings>(), uniform_alignment);
+    let material_settings_stride = vki::align_to(std::mem::size_of::<MaterialSettings>(), uniform_alignment);
+
     // set 1, binding 0
     let mut mesh_settings: Vec<MeshSettings> = meshes.iter().map(|_| MeshSettings::default()).collect();
     let mesh_settings_buffer = util::create_buffer_with_data(
         &app.device,
         &mut encoder,
         BufferUsage::UNIFORM | BufferUsage::COPY_DST,
-        &mesh_settings,
+        &util::pack_with_stride(&mesh_settings, mesh_settings_stride),
     )?;
 
     // set 1, binding 1
@@ -1402,7 +1406,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         &app.device,
         &mut encoder,
         BufferUsage::UNIFORM | BufferUsage::COPY_DST,
-        &skin_settings,
+        &util::pack_with_stride(&skin_settings, skin_settings_stride),
     )?;
 
     // set 2, binding 0
@@ -1411,7 +1415,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         &app.device,
         &mut encoder,
         BufferUsage::UNIFORM | BufferUsage::COPY_DST,
-        &material_settings,
+        &util::pack_with_stride(&material_settings, material_settings_stride),
     )?;
 
     println!("materials.len(): {}", materials.len());
@@ -1480,6 +1484,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             BindGroupLayoutEntry {
                 binding: 0,
                 binding_type: BindingType::UniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::VERTEX | ShaderStage::FRAGMENT,
             }
         ]
@@ -1492,66 +1498,88 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             BindGroupLayoutEntry {
                 binding: 0,
                 binding_type: BindingType::DynamicUniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT,
             },
             // u_BaseColorSampler
             BindGroupLayoutEntry {
                 binding: 1,
                 binding_type: BindingType::Sampler,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT,
             },
             // u_BaseColorTexture
             BindGroupLayoutEntry {
                 binding: 2,
                 binding_type: BindingType::SampledTexture,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT,
             },
             // u_MetallicRoughnessSampler
             BindGroupLayoutEntry {
                 binding: 3,
                 binding_type: BindingType::Sampler,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT,
             },
             // u_MetallicRoughnessTexture
             BindGroupLayoutEntry {
                 binding: 4,
                 binding_type: BindingType::SampledTexture,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT,
             },
             // u_NormalSampler
             BindGroupLayoutEntry {
                 binding: 5,
                 binding_type: BindingType::Sampler,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT,
             },
             // u_NormalTexture
             BindGroupLayoutEntry {
                 binding: 6,
                 binding_type: BindingType::SampledTexture,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT,
             },
             // u_OcclusionSampler
             BindGroupLayoutEntry {
                 binding: 7,
                 binding_type: BindingType::Sampler,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT,
             },
             // u_OcclusionTexture
             BindGroupLayoutEntry {
                 binding: 8,
                 binding_type: BindingType::SampledTexture,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT,
             },
             // u_EmissiveSampler
             BindGroupLayoutEntry {
                 binding: 9,
                 binding_type: BindingType::Sampler,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT,
             },
             // u_EmissiveTexture
             BindGroupLayoutEntry {
                 binding: 10,
                 binding_type: BindingType::SampledTexture,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::FRAGMENT,
             },
         ]
@@ -1564,12 +1592,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             BindGroupLayoutEntry {
                 binding: 0,
                 binding_type: BindingType::DynamicUniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::VERTEX,
             },
             // SkinSettings
             BindGroupLayoutEntry {
                 binding: 1,
                 binding_type: BindingType::DynamicUniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::VERTEX,
             }
         ]
@@ -1723,10 +1755,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 module: vs.clone(),
                 entry_point: Cow::Borrowed("main"),
             },
-            fragment_stage: PipelineStageDescriptor {
+            fragment_stage: Some(PipelineStageDescriptor {
                 module: fs.clone(),
                 entry_point: Cow::Borrowed("main"),
-            },
+            }),
             rasterization_state: RasterizationStateDescriptor {
                 front_face: FrontFace::Ccw,
                 cull_mode: material_pipeline_key.cull_mode,
@@ -1757,88 +1789,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             ),
             vertex_state: VertexStateDescriptor {
                 index_format: mesh_pipeline_key.index_format.unwrap_or(IndexFormat::U16),
-                vertex_buffers: vec![
-                    VertexBufferLayoutDescriptor {
-                        input_slot: 0,
-                        step_mode: InputStepMode::Vertex,
-                        stride: std::mem::size_of::<Vertex>(),
-                        attributes: vec![
-                            VertexAttributeDescriptor {
-                                shader_location: 0,
-                                offset: offset_of!(Vertex, position),
-                                format: VertexFormat::Float3,
-                            },
-                            VertexAttributeDescriptor {
-                                shader_location: 1,
-                                offset: offset_of!(Vertex, normal),
-                                format: VertexFormat::Float3,
-                            },
-                            VertexAttributeDescriptor {
-                                shader_location: 2,
-                                offset: offset_of!(Vertex, tangent),
-                                format: VertexFormat::Float4,
-                            },
-                            VertexAttributeDescriptor {
-                                shader_location: 3,
-                                offset: offset_of!(Vertex, texcoord0),
-                                format: VertexFormat::Float2,
-                            },
-                            VertexAttributeDescriptor {
-                                shader_location: 4,
-                                offset: offset_of!(Vertex, texcoord1),
-                                format: VertexFormat::Float2,
-                            },
-                            VertexAttributeDescriptor {
-                                shader_location: 5,
-                                offset: offset_of!(Vertex, color),
-                                format: VertexFormat::Float4,
-                            },
-                            VertexAttributeDescriptor {
-                                shader_location: 6,
-                                offset: offset_of!(Vertex, joint),
-                                format: VertexFormat::UShort4,
-                            },
-                            VertexAttributeDescriptor {
-                                shader_location: 7,
-                                offset: offset_of!(Vertex, weight),
-                                format: VertexFormat::Float4,
-                            },
-                            VertexAttributeDescriptor {
-                                shader_location: 8,
-                                offset: offset_of!(Vertex, morph_position0),
-                                format: VertexFormat::Float3,
-                            },
-                            VertexAttributeDescriptor {
-                                shader_location: 9,
-                                offset: offset_of!(Vertex, morph_position1),
-                                format: VertexFormat::Float3,
-                            },
-                            VertexAttributeDescriptor {
-                                shader_location: 10,
-                                offset: offset_of!(Vertex, morph_normal0),
-                                format: VertexFormat::Float3,
-                            },
-                            VertexAttributeDescriptor {
-                                shader_location: 11,
-                                offset: offset_of!(Vertex, morph_normal1),
-                                format: VertexFormat::Float3,
-                            },
-                            VertexAttributeDescriptor {
-                                shader_location: 12,
-                                offset: offset_of!(Vertex, morph_tangent0),
-                                format: VertexFormat::Float3,
-                            },
-                            VertexAttributeDescriptor {
-                                shader_location: 13,
-                                offset: offset_of!(Vertex, morph_tangent1),
-                                format: VertexFormat::Float3,
-                            },
-                        ],
-                    }
-                ],
+                primitive_restart_enabled: false,
+                vertex_buffers: vec![Vertex::vertex_buffer_layout(0, InputStepMode::Vertex)],
             },
             sample_count: app.get_sample_count(),
             alpha_to_coverage_enabled: true,
+            blend_color: None,
         };
 
         let pipeline = app.device.create_render_pipeline(render_pipeline_descriptor)?;
@@ -1866,6 +1822,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let frame = match app.swapchain.acquire_next_image() {
             Ok(frame) => frame,
             Err(SwapchainError::OutOfDate) => return Ok(()),
+            Err(SwapchainError::SurfaceLost) => return Ok(app.recreate_surface()?),
             Err(e) => return Err(e)?,
         };
 
@@ -1921,38 +1878,29 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             node.animate_morph_weights = None;
         }
 
-        util::copy_to_buffer(&app.device, &mut encoder, &mesh_settings, &mesh_settings_buffer)?;
-        util::copy_to_buffer(&app.device, &mut encoder, &skin_settings, &skin_settings_buffer)?;
-
-        let (attachment, resolve_target) = if app.get_sample_count() == 1 {
-            (&frame.view, None)
-        } else {
-            (&app.color_view, Some(&frame.view))
-        };
+        util::copy_to_buffer(
+            &app.device,
+            &mut encoder,
+            &util::pack_with_stride(&mesh_settings, mesh_settings_stride),
+            &mesh_settings_buffer,
+        )?;
+        util::copy_to_buffer(
+            &app.device,
+            &mut encoder,
+            &util::pack_with_stride(&skin_settings, skin_settings_stride),
+            &skin_settings_buffer,
+        )?;
 
         #[rustfmt::skip]
         let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
             color_attachments: &[
-                RenderPassColorAttachmentDescriptor {
-                    attachment,
-                    resolve_target,
-                    store_op: StoreOp::Store,
-                    load_op: LoadOp::Clear,
-                    clear_color: Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 },
-                    //clear_color: Color { r: 0.2, g: 0.6, b: 0.8, a: 1.0 },
-                }
+                frame.color_attachment(LoadOp::Clear, Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 }),
             ],
-            depth_stencil_attachment: Some(
-                RenderPassDepthStencilAttachmentDescriptor {
-                    attachment: &app.depth_view,
-                    clear_depth: 1.0,
-                    clear_stencil: 0,
-                    depth_load_op: LoadOp::Clear,
-                    depth_store_op: StoreOp::Store,
-                    stencil_load_op: LoadOp::Clear,
-                    stencil_store_op: StoreOp::Store,
-                }
-            ),
+            depth_stencil_attachment: frame.depth_stencil_attachment(LoadOp::Clear, 1.0, 0),
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
         });
 
         let mut last_pipeline_key = None;
@@ -1961,7 +1909,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         for material_index in material_indices_sorted_by_pipeline_key.iter().cloned() {
             let mesh_primitives = &material_primitive_map[&material_index];
-            let material_settings_offset = util::byte_stride(&material_settings) * material_index;
+            let material_settings_offset = material_settings_stride * material_index;
             let material = &materials[material_index];
             let material_name = material
                 .name
@@ -1984,10 +1932,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     .unwrap_or_else(|| format!("mesh-{}", mesh_index));
                 render_pass.insert_debug_marker(&mesh_name);
                 let primitive = &mesh.primitives[primitive_index];
-                let mesh_settings_offset = util::byte_stride(&mesh_settings) * mesh_index;
+                let mesh_settings_offset = mesh_settings_stride * mesh_index;
                 let mesh_pipeline_key = primitive.mesh_pipeline_key;
                 let skin_index = nodes[mesh.node_index].skin_index.unwrap_or(skin_settings.len() - 1);
-                let skin_settings_offset = util::byte_stride(&skin_settings) * skin_index;
+                let skin_settings_offset = skin_settings_stride * skin_index;
                 let dynamic_offsets = &[mesh_settings_offset, skin_settings_offset];
                 render_pass.set_bind_group(2, &bind_group_2, Some(dynamic_offsets));
 
@@ -2004,12 +1952,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                 let stages = ShaderStage::VERTEX | ShaderStage::FRAGMENT;
                 render_pass.set_push_constants(stages, 0, primitive.settings)?;
-                render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[primitive.vertex_buffer_offset]);
+                render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[primitive.vertex_buffer_offset])?;
                 match primitive.index_buffer_offset {
                     Some(index_buffer_offset) => {
                         match primitive.mesh_pipeline_key.index_format.unwrap() {
                             IndexFormat::U16 => render_pass.set_index_buffer(&index_buffer_u16, index_buffer_offset),
                             IndexFormat::U32 => render_pass.set_index_buffer(&index_buffer_u32, index_buffer_offset),
+                            IndexFormat::U8 => unreachable!("u8 indices are converted to u16"),
                         }
                         let index_count = primitive.index_count as u32;
                         render_pass.draw_indexed(index_count, 1, 0, 0, 0);
@@ -0,0 +1,352 @@
+pub mod util;
+
+use cgmath::SquareMatrix;
+use cgmath::{Matrix4, Point3};
+
+use std::borrow::Cow;
+
+use crate::util::{App, EventHandlers};
+
+use vki::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
+    BlendDescriptor, BlendFactor, BlendOperation, BufferDescriptor, BufferUsage, Color, ColorStateDescriptor,
+    ColorWrite, ComputePipelineDescriptor, CullMode, DrawIndirectCommand, FrontFace, IndexFormat, InputStepMode,
+    LoadOp, PipelineLayoutDescriptor, PipelineStageDescriptor, Pod, PolygonMode, PrimitiveTopology,
+    RasterizationStateDescriptor, RenderPassDescriptor, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderStage,
+    SwapchainError, VertexAttributeDescriptor, VertexBufferLayoutDescriptor, VertexFormat, VertexStateDescriptor,
+    Zeroable,
+};
+
+use std::time::Instant;
+
+const PARTICLE_GROUP_SIZE: usize = 256;
+const PARTICLE_GROUP_COUNT: usize = 1024;
+const PARTICLE_COUNT: usize = PARTICLE_GROUP_SIZE * PARTICLE_GROUP_COUNT;
+
+// The compute pass simulates the particles and appends the live ones to a vertex buffer. The
+// number of live particles is accumulated in the indirect buffer, which is then consumed by
+// `draw_indirect` without reading the count back to the CPU.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let _ = pretty_env_logger::try_init();
+
+    let mut app: App<()> = App::init("particle_indirect.rs", 800, 600, EventHandlers::Default)?;
+
+    app.set_sample_count(4)?;
+
+    app.camera.eye = Point3 {
+        x: 0.0,
+        y: 20.0,
+        z: -120.0,
+    };
+    app.camera.center = Point3 {
+        x: 0.0,
+        y: 20.0,
+        z: 0.0,
+    };
+    app.camera.update_view_matrix();
+
+    #[repr(C)]
+    #[derive(Debug, Default, Copy, Clone, Pod, Zeroable)]
+    pub struct Particle {
+        pub position: [f32; 4],
+        pub velocity: [f32; 4],
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Default, Copy, Clone, Pod, Zeroable)]
+    pub struct MvpBlock {
+        pub mvp: [[f32; 4]; 4],
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Default, Copy, Clone, Pod, Zeroable)]
+    pub struct SimulationBlock {
+        pub emitter: [f32; 4],
+        pub gravity: [f32; 4],
+        pub dt: f32,
+        pub time: f32,
+        pub particle_count: u32,
+        pub _pad0: f32,
+    }
+
+    // All of the particles start out dead (zero life) and are spawned by the simulation
+    let particle_data = vec![Particle::default(); PARTICLE_COUNT];
+
+    let mut encoder = app.device.create_command_encoder()?;
+
+    let particle_buffer =
+        util::create_buffer_with_data(&app.device, &mut encoder, BufferUsage::STORAGE, &particle_data)?;
+
+    app.device.get_queue().submit(&[encoder.finish()?])?;
+
+    let vertex_buffer = app.device.create_buffer(BufferDescriptor {
+        size: util::byte_stride(&[[0f32; 4]]) * PARTICLE_COUNT,
+        usage: BufferUsage::STORAGE | BufferUsage::VERTEX,
+        memory_hint: None,
+    })?;
+
+    let indirect_buffer = app.device.create_buffer(BufferDescriptor {
+        size: std::mem::size_of::<DrawIndirectCommand>(),
+        usage: BufferUsage::STORAGE | BufferUsage::INDIRECT | BufferUsage::COPY_DST,
+        memory_hint: None,
+    })?;
+
+    let simulation_buffer = app.device.create_buffer(BufferDescriptor {
+        size: std::mem::size_of::<SimulationBlock>(),
+        usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
+        memory_hint: None,
+    })?;
+
+    let mvp_buffer = app.device.create_buffer(BufferDescriptor {
+        size: std::mem::size_of::<MvpBlock>(),
+        usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
+        memory_hint: None,
+    })?;
+
+    #[rustfmt::skip]
+    let compute_bind_group_layout = app.device.create_bind_group_layout(BindGroupLayoutDescriptor {
+        entries: vec![
+            BindGroupLayoutEntry {
+                binding: 0,
+                binding_type: BindingType::StorageBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
+                visibility: ShaderStage::COMPUTE,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                binding_type: BindingType::StorageBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
+                visibility: ShaderStage::COMPUTE,
+            },
+            BindGroupLayoutEntry {
+                binding: 2,
+                binding_type: BindingType::StorageBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
+                visibility: ShaderStage::COMPUTE,
+            },
+            BindGroupLayoutEntry {
+                binding: 3,
+                binding_type: BindingType::UniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
+                visibility: ShaderStage::COMPUTE,
+            },
+        ],
+    })?;
+
+    #[rustfmt::skip]
+    let compute_bind_group = app.device.create_bind_group(BindGroupDescriptor {
+        layout: compute_bind_group_layout.clone(),
+        entries: vec![
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::buffer(particle_buffer.clone()),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::buffer(vertex_buffer.clone()),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: BindingResource::buffer(indirect_buffer.clone()),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: BindingResource::buffer(simulation_buffer.clone()),
+            },
+        ],
+    })?;
+
+    #[rustfmt::skip]
+    let render_bind_group_layout = app.device.create_bind_group_layout(BindGroupLayoutDescriptor {
+        entries: vec![
+            BindGroupLayoutEntry {
+                binding: 0,
+                binding_type: BindingType::UniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
+                visibility: ShaderStage::VERTEX,
+            },
+        ],
+    })?;
+
+    #[rustfmt::skip]
+    let render_bind_group = app.device.create_bind_group(BindGroupDescriptor {
+        layout: render_bind_group_layout.clone(),
+        entries: vec![
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::buffer(mvp_buffer.clone()),
+            }
+        ],
+    })?;
+
+    let compute_pipeline_layout = app.device.create_pipeline_layout(PipelineLayoutDescriptor {
+        bind_group_layouts: vec![compute_bind_group_layout],
+        push_constant_ranges: vec![],
+    })?;
+
+    let render_pipeline_layout = app.device.create_pipeline_layout(PipelineLayoutDescriptor {
+        bind_group_layouts: vec![render_bind_group_layout],
+        push_constant_ranges: vec![],
+    })?;
+
+    // The particles are drawn with the particle simulator's shaders
+    let vs = app.device.create_shader_module(ShaderModuleDescriptor {
+        code: include_bytes!("shaders/particle_simulator.vert.spv"),
+    })?;
+
+    let fs = app.device.create_shader_module(ShaderModuleDescriptor {
+        code: include_bytes!("shaders/particle_simulator.frag.spv"),
+    })?;
+
+    let cs = app.device.create_shader_module(ShaderModuleDescriptor {
+        code: include_bytes!("shaders/particle_indirect.comp.spv"),
+    })?;
+
+    let compute_pipeline = app.device.create_compute_pipeline(ComputePipelineDescriptor {
+        layout: compute_pipeline_layout,
+        compute_stage: PipelineStageDescriptor {
+            module: cs,
+            entry_point: Cow::Borrowed("main"),
+        },
+        required_subgroup_size: None,
+    })?;
+
+    #[rustfmt::skip]
+    let render_pipeline = app.device.create_render_pipeline(RenderPipelineDescriptor {
+        layout: render_pipeline_layout,
+        vertex_stage: PipelineStageDescriptor { module: vs, entry_point: Cow::Borrowed("main") },
+        fragment_stage: Some(PipelineStageDescriptor { module: fs, entry_point: Cow::Borrowed("main") }),
+        rasterization_state: RasterizationStateDescriptor {
+            front_face: FrontFace::Ccw,
+            cull_mode: CullMode::None,
+            polygon_mode: PolygonMode::Fill,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+        },
+        primitive_topology: PrimitiveTopology::PointList,
+        color_states: vec![
+            ColorStateDescriptor {
+                format: util::DEFAULT_COLOR_FORMAT,
+                color_blend: BlendDescriptor {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                alpha_blend: BlendDescriptor::OPAQUE,
+                write_mask: ColorWrite::ALL,
+            }
+        ],
+        depth_stencil_state: None,
+        vertex_state: VertexStateDescriptor {
+            index_format: IndexFormat::U16,
+            primitive_restart_enabled: false,
+            vertex_buffers: vec![
+                VertexBufferLayoutDescriptor {
+                    input_slot: 0,
+                    stride: util::byte_stride(&[[0f32; 4]]),
+                    step_mode: InputStepMode::Vertex,
+                    step_rate: 1,
+                    attributes: vec![
+                        VertexAttributeDescriptor {
+                            format: VertexFormat::Float4,
+                            offset: 0,
+                            shader_location: 0,
+                        }
+                    ],
+                }
+            ],
+        },
+        sample_count: app.get_sample_count(),
+        alpha_to_coverage_enabled: false,
+        blend_color: None,
+    })?;
+
+    let start_time = Instant::now();
+    let mut last_frame_time_secs = 0.0;
+
+    app.run(move |app| {
+        let model = Matrix4::identity();
+
+        let time = util::to_float_secs(start_time.elapsed());
+        let delta = (time - last_frame_time_secs).min(0.1);
+        last_frame_time_secs = time;
+
+        let queue = app.device.get_queue();
+
+        let mvp_block = MvpBlock {
+            mvp: (app.camera.projection * app.camera.view * model).into(),
+        };
+
+        let simulation_block = SimulationBlock {
+            emitter: [0.0, 0.0, 0.0, 0.01],
+            gravity: [0.0, -9.8, 0.0, 0.0],
+            dt: delta,
+            time,
+            particle_count: PARTICLE_COUNT as u32,
+            _pad0: 0.0,
+        };
+
+        // The vertex count is reset before each simulation step and incremented by the compute
+        // shader for every live particle
+        let draw_command = DrawIndirectCommand {
+            vertex_count: 0,
+            instance_count: 1,
+            first_vertex: 0,
+            first_instance: 0,
+        };
+
+        queue.write_buffer(&mvp_buffer, 0, &[mvp_block])?;
+        queue.write_buffer(&simulation_buffer, 0, &[simulation_block])?;
+        queue.write_buffer(&indirect_buffer, 0, &[draw_command])?;
+
+        let frame = match app.swapchain.acquire_next_image() {
+            Ok(frame) => frame,
+            Err(SwapchainError::OutOfDate) => return Ok(()),
+            Err(SwapchainError::SurfaceLost) => return Ok(app.recreate_surface()?),
+            Err(e) => return Err(e)?,
+        };
+
+        let mut encoder = app.device.create_command_encoder()?;
+
+        let mut compute_pass = encoder.begin_compute_pass();
+        compute_pass.set_pipeline(&compute_pipeline);
+        compute_pass.set_bind_group(0, &compute_bind_group, None);
+        compute_pass.dispatch(PARTICLE_GROUP_COUNT as u32, 1, 1);
+        compute_pass.end_pass();
+
+        #[rustfmt::skip]
+        let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
+            color_attachments: &[
+                frame.color_attachment(LoadOp::Clear, Color { r: 0.05, g: 0.05, b: 0.05, a: 1.0 }),
+            ],
+            depth_stencil_attachment: None,
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
+        });
+
+        render_pass.set_pipeline(&render_pipeline);
+        render_pass.set_bind_group(0, &render_bind_group, None);
+        render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0])?;
+        render_pass.draw_indirect(&indirect_buffer, 0);
+        render_pass.end_pass();
+
+        queue.submit(&[encoder.finish()?])?;
+
+        match queue.present(frame) {
+            Ok(frame) => frame,
+            Err(SwapchainError::OutOfDate) => return Ok(()),
+            Err(e) => return Err(e)?,
+        }
+
+        Ok(())
+    })
+}
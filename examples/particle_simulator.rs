@@ -14,9 +14,9 @@ use vki::{
     BlendDescriptor, BlendFactor, BlendOperation, BufferUsage, BufferViewDescriptor, BufferViewFormat, Color,
     ColorStateDescriptor, ColorWrite, ComputePipelineDescriptor, CullMode, Fence, FrontFace, IndexFormat,
     InputStepMode, LoadOp, PipelineLayoutDescriptor, PipelineStageDescriptor, PolygonMode, PrimitiveTopology,
-    RasterizationStateDescriptor, RenderPassColorAttachmentDescriptor, RenderPassDescriptor, RenderPipelineDescriptor,
-    ShaderModuleDescriptor, ShaderStage, StoreOp, SwapchainError, TextureFormat, VertexAttributeDescriptor,
-    VertexBufferLayoutDescriptor, VertexFormat, VertexStateDescriptor,
+    RasterizationStateDescriptor, RenderPassDescriptor, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderStage,
+    SwapchainError, TextureFormat, VertexAttributeDescriptor, VertexBufferLayoutDescriptor, VertexFormat,
+    VertexStateDescriptor,
 };
 
 use rand::Rng;
@@ -191,16 +191,22 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             BindGroupLayoutEntry {
                 binding: 0,
                 binding_type: BindingType::StorageTexelBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::COMPUTE,
             },
             BindGroupLayoutEntry {
                 binding: 1,
                 binding_type: BindingType::StorageTexelBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::COMPUTE,
             },
             BindGroupLayoutEntry {
                 binding: 2,
                 binding_type: BindingType::UniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::COMPUTE,
             }
         ],
@@ -231,6 +237,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             BindGroupLayoutEntry {
                 binding: 0,
                 binding_type: BindingType::UniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
                 visibility: ShaderStage::VERTEX,
             },
         ],
@@ -275,13 +283,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             module: cs,
             entry_point: Cow::Borrowed("main"),
         },
+        required_subgroup_size: None,
     })?;
 
     #[rustfmt::skip]
     let render_pipeline = app.device.create_render_pipeline(RenderPipelineDescriptor {
         layout: render_pipeline_layout,
         vertex_stage: PipelineStageDescriptor { module: vs, entry_point: Cow::Borrowed("main") },
-        fragment_stage: PipelineStageDescriptor { module: fs, entry_point: Cow::Borrowed("main") },
+        fragment_stage: Some(PipelineStageDescriptor { module: fs, entry_point: Cow::Borrowed("main") }),
         rasterization_state: RasterizationStateDescriptor {
             front_face: FrontFace::Ccw,
             cull_mode: CullMode::None,
@@ -306,11 +315,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         depth_stencil_state: None,
         vertex_state: VertexStateDescriptor {
             index_format: IndexFormat::U16,
+            primitive_restart_enabled: false,
             vertex_buffers: vec![
                 VertexBufferLayoutDescriptor {
                     input_slot: 0,
                     stride: util::byte_stride(&position_data),
                     step_mode: InputStepMode::Vertex,
+                    step_rate: 1,
                     attributes: vec![
                         VertexAttributeDescriptor {
                             format: VertexFormat::Float4,
@@ -323,6 +334,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         },
         sample_count: app.get_sample_count(),
         alpha_to_coverage_enabled: false,
+        blend_color: None,
     })?;
 
     let start_time = Instant::now();
@@ -381,6 +393,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let frame = match app.swapchain.acquire_next_image() {
             Ok(frame) => frame,
             Err(SwapchainError::OutOfDate) => return Ok(()),
+            Err(SwapchainError::SurfaceLost) => return Ok(app.recreate_surface()?),
             Err(e) => return Err(e)?,
         };
 
@@ -397,12 +410,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             app.state.reset2 = false;
         }
 
-        let (attachment, resolve_target) = if app.get_sample_count() == 1 {
-            (&frame.view, None)
-        } else {
-            (&app.color_view, Some(&frame.view))
-        };
-
         let mut compute_pass = encoder.begin_compute_pass();
         compute_pass.set_pipeline(&compute_pipeline);
         compute_pass.set_bind_group(0, &compute_bind_group, None);
@@ -412,20 +419,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         #[rustfmt::skip]
         let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
             color_attachments: &[
-                RenderPassColorAttachmentDescriptor {
-                    attachment,
-                    resolve_target,
-                    store_op: StoreOp::Store,
-                    load_op: LoadOp::Clear,
-                    clear_color: Color { r: 0.1, g: 0.1, b: 0.1, a: 1.0 },
-                }
+                frame.color_attachment(LoadOp::Clear, Color { r: 0.1, g: 0.1, b: 0.1, a: 1.0 }),
             ],
             depth_stencil_attachment: None,
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
         });
 
         render_pass.set_pipeline(&render_pipeline);
         render_pass.set_bind_group(0, &render_bind_group, None);
-        render_pass.set_vertex_buffers(0, &[position_buffer.clone()], &[0]);
+        render_pass.set_vertex_buffers(0, &[position_buffer.clone()], &[0])?;
         render_pass.draw(PARTICLE_COUNT as u32, 1, 0, 0);
         render_pass.end_pass();
 
@@ -0,0 +1,72 @@
+#version 450
+
+layout (local_size_x = 256) in;
+
+struct Particle {
+    vec4 position; // xyz = position, w = remaining life
+    vec4 velocity;
+};
+
+layout (set = 0, binding = 0, std430) buffer particle_block {
+    Particle particles[];
+};
+
+layout (set = 0, binding = 1, std430) writeonly buffer vertex_block {
+    vec4 vertices[]; // xyz = position, w = intensity
+};
+
+layout (set = 0, binding = 2, std430) buffer draw_indirect_block {
+    uint vertex_count;
+    uint instance_count;
+    uint first_vertex;
+    uint first_instance;
+};
+
+layout (set = 0, binding = 3, std140) uniform simulation_block {
+    vec4 emitter; // xyz = position, w = spawn probability
+    vec4 gravity;
+    float dt;
+    float time;
+    uint particle_count;
+};
+
+float hash(uint x) {
+    x ^= x >> 16;
+    x *= 0x7feb352du;
+    x ^= x >> 15;
+    x *= 0x846ca68bu;
+    x ^= x >> 16;
+    return float(x) / 4294967295.0;
+}
+
+void main(void)
+{
+    uint index = gl_GlobalInvocationID.x;
+    if (index >= particle_count) {
+        return;
+    }
+
+    Particle particle = particles[index];
+
+    if (particle.position.w > 0.0) {
+        particle.velocity.xyz += gravity.xyz * dt;
+        particle.position.xyz += particle.velocity.xyz * dt;
+        particle.position.w -= dt;
+    } else {
+        uint seed = index * 5u + floatBitsToUint(time);
+        if (hash(seed) < emitter.w) {
+            vec3 direction = vec3(hash(seed + 1u), hash(seed + 2u), hash(seed + 3u)) * 2.0 - 1.0;
+            particle.position = vec4(emitter.xyz, 2.0 + hash(seed + 4u) * 2.0);
+            particle.velocity = vec4(direction.x * 10.0, 25.0 + direction.y * 10.0, direction.z * 10.0, 0.0);
+        }
+    }
+
+    particles[index] = particle;
+
+    // Only the live particles are appended to the vertex buffer. The count becomes the vertex
+    // count of the indirect draw.
+    if (particle.position.w > 0.0) {
+        uint vertex_index = atomicAdd(vertex_count, 1u);
+        vertices[vertex_index] = vec4(particle.position.xyz, clamp(particle.position.w * 0.5, 0.0, 1.0));
+    }
+}
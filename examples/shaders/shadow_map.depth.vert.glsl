@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec3 a_Position;
+
+out gl_PerVertex {
+    vec4 gl_Position;
+};
+
+layout(set = 0, binding = 0, std140) uniform Uniforms {
+    mat4 u_ViewProjection;
+    mat4 u_LightViewProjection;
+    vec4 u_LightDirection;
+};
+
+void main() {
+    gl_Position = u_LightViewProjection * vec4(a_Position, 1.0);
+}
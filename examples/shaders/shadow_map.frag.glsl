@@ -0,0 +1,44 @@
+#version 450
+
+layout(location = 0) in vec3 v_Normal;
+layout(location = 1) in vec4 v_Color;
+layout(location = 2) in vec4 v_LightPosition;
+
+layout(location = 0) out vec4 fragColor;
+
+layout(set = 0, binding = 0, std140) uniform Uniforms {
+    mat4 u_ViewProjection;
+    mat4 u_LightViewProjection;
+    vec4 u_LightDirection;
+};
+
+layout(set = 0, binding = 1) uniform texture2D u_ShadowMap;
+layout(set = 0, binding = 2) uniform samplerShadow u_ShadowSampler;
+
+// Percentage closer filtering over a 3x3 texel neighborhood. Each lookup is a hardware depth
+// comparison against the shadow map.
+float shadow(vec4 light_position) {
+    vec3 ndc = light_position.xyz / light_position.w;
+    vec2 uv = ndc.xy * 0.5 + 0.5;
+    vec2 texel_size = 1.0 / vec2(textureSize(sampler2DShadow(u_ShadowMap, u_ShadowSampler), 0));
+
+    float visibility = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            vec2 offset = vec2(x, y) * texel_size;
+            visibility += texture(sampler2DShadow(u_ShadowMap, u_ShadowSampler), vec3(uv + offset, ndc.z));
+        }
+    }
+
+    return visibility / 9.0;
+}
+
+void main() {
+    vec3 normal = normalize(v_Normal);
+    vec3 light_direction = normalize(-u_LightDirection.xyz);
+
+    float diffuse = max(dot(normal, light_direction), 0.0);
+    float ambient = 0.2;
+
+    fragColor = vec4(v_Color.rgb * (ambient + diffuse * shadow(v_LightPosition)), v_Color.a);
+}
@@ -0,0 +1,26 @@
+#version 450
+
+layout(location = 0) in vec3 a_Position;
+layout(location = 1) in vec3 a_Normal;
+layout(location = 2) in vec4 a_Color;
+
+out gl_PerVertex {
+    vec4 gl_Position;
+};
+
+layout(set = 0, binding = 0, std140) uniform Uniforms {
+    mat4 u_ViewProjection;
+    mat4 u_LightViewProjection;
+    vec4 u_LightDirection;
+};
+
+layout(location = 0) out vec3 v_Normal;
+layout(location = 1) out vec4 v_Color;
+layout(location = 2) out vec4 v_LightPosition;
+
+void main() {
+    v_Normal = a_Normal;
+    v_Color = a_Color;
+    v_LightPosition = u_LightViewProjection * vec4(a_Position, 1.0);
+    gl_Position = u_ViewProjection * vec4(a_Position, 1.0);
+}
@@ -0,0 +1,427 @@
+#[macro_use]
+extern crate memoffset;
+
+pub mod util;
+
+use cgmath::{vec3, InnerSpace, Matrix4, Point3, Vector3};
+
+use std::borrow::Cow;
+
+use crate::util::{App, EventHandler, EventHandlers};
+
+use vki::{
+    AddressMode, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource,
+    BindingType, BorderColor, BufferDescriptor, BufferUsage, Color, ColorStateDescriptor, CompareFunction,
+    DepthStencilStateDescriptor, Extent3d, IndexFormat, InputStepMode, LoadOp, PipelineLayoutDescriptor,
+    PipelineStageDescriptor, Pod, PrimitiveTopology, RasterizationStateDescriptor,
+    RenderPassDepthStencilAttachmentDescriptor, RenderPassDescriptor, RenderPipelineDescriptor, SamplerDescriptor,
+    ShaderModuleDescriptor, ShaderStage, StoreOp, SwapchainError, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsage, VertexAttributeDescriptor, VertexBufferLayoutDescriptor, VertexFormat, VertexStateDescriptor,
+    Zeroable,
+};
+
+use std::time::Instant;
+use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+const SHADOW_MAP_SIZE: u32 = 2048;
+const SHADOW_MAP_FORMAT: TextureFormat = TextureFormat::D32Float;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub color: [f32; 4],
+}
+
+struct State {
+    depth_bias_enabled: bool,
+}
+
+impl Default for State {
+    fn default() -> State {
+        State {
+            depth_bias_enabled: true,
+        }
+    }
+}
+
+struct DepthBiasHandler;
+
+impl EventHandler<State> for DepthBiasHandler {
+    fn on_event(&mut self, app: &mut App<State>, event: &Event<()>) -> bool {
+        if let Event::WindowEvent {
+            event:
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            virtual_keycode: Some(VirtualKeyCode::F2),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                },
+            ..
+        } = event
+        {
+            app.state.depth_bias_enabled = !app.state.depth_bias_enabled;
+            println!("depth bias enabled: {}", app.state.depth_bias_enabled);
+        }
+
+        false
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let _ = pretty_env_logger::try_init();
+
+    let mut event_handlers = EventHandlers::default_event_handlers();
+
+    event_handlers.push(Box::new(DepthBiasHandler));
+
+    let mut app: App<State> = App::init("shadow_map.rs", 800, 600, EventHandlers::Custom(event_handlers))?;
+
+    app.set_sample_count(4)?;
+
+    app.camera.eye = Point3 {
+        x: 12.0,
+        y: 10.0,
+        z: -12.0,
+    };
+    app.camera.update_view_matrix();
+
+    let mut vertices = Vec::new();
+    let mut indices: Vec<u16> = Vec::new();
+
+    #[rustfmt::skip]
+    let ground = [
+        vec3(-10.0, 0.0, -10.0),
+        vec3( 10.0, 0.0, -10.0),
+        vec3( 10.0, 0.0,  10.0),
+        vec3(-10.0, 0.0,  10.0),
+    ];
+
+    for position in ground.iter() {
+        vertices.push(Vertex {
+            position: (*position).into(),
+            normal: [0.0, 1.0, 0.0],
+            color: [0.8, 0.8, 0.8, 1.0],
+        });
+    }
+    indices.extend_from_slice(&[0, 1, 2, 0, 2, 3]);
+
+    #[rustfmt::skip]
+    let cubes = [
+        (vec3( 0.0, 1.0,  0.0), 1.0, [0.8, 0.2, 0.2, 1.0]),
+        (vec3( 4.0, 0.5,  3.0), 0.5, [0.2, 0.8, 0.2, 1.0]),
+        (vec3(-4.0, 2.0,  2.0), 0.75, [0.2, 0.2, 0.8, 1.0]),
+        (vec3( 3.0, 1.5, -4.0), 1.5, [0.8, 0.8, 0.2, 1.0]),
+    ];
+
+    for &(center, extent, color) in cubes.iter() {
+        let cube = util::shape::Cube {
+            center,
+            x_extent: extent,
+            y_extent: extent,
+            z_extent: extent,
+        };
+        let base_index = vertices.len() as u16;
+        for (position, normal) in cube.positions().iter().zip(cube.normals().iter()) {
+            vertices.push(Vertex {
+                position: (*position).into(),
+                normal: (*normal).into(),
+                color,
+            });
+        }
+        indices.extend(cube.indices().iter().map(|index| base_index + index));
+    }
+
+    #[repr(C)]
+    #[derive(Debug, Default, Copy, Clone, Pod, Zeroable)]
+    pub struct Uniforms {
+        pub view_projection: [[f32; 4]; 4],
+        pub light_view_projection: [[f32; 4]; 4],
+        pub light_direction: [f32; 4],
+    }
+
+    let mut encoder = app.device.create_command_encoder()?;
+
+    let vertex_buffer = util::create_buffer_with_data(&app.device, &mut encoder, BufferUsage::VERTEX, &vertices)?;
+    let index_buffer = util::create_buffer_with_data(&app.device, &mut encoder, BufferUsage::INDEX, &indices)?;
+
+    app.device.get_queue().submit(&[encoder.finish()?])?;
+
+    let uniform_buffer = app.device.create_buffer(BufferDescriptor {
+        size: std::mem::size_of::<Uniforms>(),
+        usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
+        memory_hint: None,
+    })?;
+
+    let shadow_map = app.device.create_texture(TextureDescriptor {
+        size: Extent3d {
+            width: SHADOW_MAP_SIZE,
+            height: SHADOW_MAP_SIZE,
+            depth: 1,
+        },
+        array_layer_count: 1,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: SHADOW_MAP_FORMAT,
+        usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::SAMPLED,
+    })?;
+
+    let shadow_map_view = shadow_map.create_default_view()?;
+
+    // Lookups outside of the light's frustum compare against the white border (depth 1.0) and
+    // are always lit
+    let shadow_sampler = app.device.create_sampler(
+        SamplerDescriptor::linear()
+            .with_address_mode(AddressMode::ClampToBorder)
+            .with_border_color(BorderColor::OpaqueWhite)
+            .with_compare_function(CompareFunction::LessEqual),
+    )?;
+
+    #[rustfmt::skip]
+    let shadow_bind_group_layout = app.device.create_bind_group_layout(BindGroupLayoutDescriptor {
+        entries: vec![
+            BindGroupLayoutEntry {
+                binding: 0,
+                binding_type: BindingType::UniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
+                visibility: ShaderStage::VERTEX,
+            },
+        ],
+    })?;
+
+    #[rustfmt::skip]
+    let shadow_bind_group = app.device.create_bind_group(BindGroupDescriptor {
+        layout: shadow_bind_group_layout.clone(),
+        entries: vec![
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::buffer(uniform_buffer.clone()),
+            },
+        ],
+    })?;
+
+    #[rustfmt::skip]
+    let bind_group_layout = app.device.create_bind_group_layout(BindGroupLayoutDescriptor {
+        entries: vec![
+            BindGroupLayoutEntry {
+                binding: 0,
+                binding_type: BindingType::UniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
+                visibility: ShaderStage::VERTEX | ShaderStage::FRAGMENT,
+            },
+            BindGroupLayoutEntry {
+                binding: 1,
+                binding_type: BindingType::SampledTexture,
+                storage_texture_format: None,
+                min_binding_size: None,
+                visibility: ShaderStage::FRAGMENT,
+            },
+            BindGroupLayoutEntry {
+                binding: 2,
+                binding_type: BindingType::ComparisonSampler,
+                storage_texture_format: None,
+                min_binding_size: None,
+                visibility: ShaderStage::FRAGMENT,
+            },
+        ],
+    })?;
+
+    #[rustfmt::skip]
+    let bind_group = app.device.create_bind_group(BindGroupDescriptor {
+        layout: bind_group_layout.clone(),
+        entries: vec![
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::buffer(uniform_buffer.clone()),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::TextureView(shadow_map_view.clone()),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: BindingResource::Sampler(shadow_sampler),
+            },
+        ],
+    })?;
+
+    let shadow_pipeline_layout = app.device.create_pipeline_layout(PipelineLayoutDescriptor {
+        bind_group_layouts: vec![shadow_bind_group_layout],
+        push_constant_ranges: vec![],
+    })?;
+
+    let pipeline_layout = app.device.create_pipeline_layout(PipelineLayoutDescriptor {
+        bind_group_layouts: vec![bind_group_layout],
+        push_constant_ranges: vec![],
+    })?;
+
+    let depth_vs = app.device.create_shader_module(ShaderModuleDescriptor {
+        code: include_bytes!("shaders/shadow_map.depth.vert.spv"),
+    })?;
+
+    let vs = app.device.create_shader_module(ShaderModuleDescriptor {
+        code: include_bytes!("shaders/shadow_map.vert.spv"),
+    })?;
+
+    let fs = app.device.create_shader_module(ShaderModuleDescriptor {
+        code: include_bytes!("shaders/shadow_map.frag.spv"),
+    })?;
+
+    let vertex_state = VertexStateDescriptor {
+        index_format: IndexFormat::U16,
+        primitive_restart_enabled: false,
+        vertex_buffers: vec![VertexBufferLayoutDescriptor {
+            input_slot: 0,
+            stride: util::byte_stride(&vertices),
+            step_mode: InputStepMode::Vertex,
+            step_rate: 1,
+            attributes: vec![
+                VertexAttributeDescriptor {
+                    format: VertexFormat::Float3,
+                    offset: offset_of!(Vertex, position),
+                    shader_location: 0,
+                },
+                VertexAttributeDescriptor {
+                    format: VertexFormat::Float3,
+                    offset: offset_of!(Vertex, normal),
+                    shader_location: 1,
+                },
+                VertexAttributeDescriptor {
+                    format: VertexFormat::Float4,
+                    offset: offset_of!(Vertex, color),
+                    shader_location: 2,
+                },
+            ],
+        }],
+    };
+
+    // The shadow pass only writes depth, so the pipeline has no fragment stage or color states.
+    // The depth bias is set dynamically with `set_depth_bias`.
+    #[rustfmt::skip]
+    let shadow_pipeline = app.device.create_render_pipeline(RenderPipelineDescriptor {
+        layout: shadow_pipeline_layout,
+        vertex_stage: PipelineStageDescriptor { module: depth_vs, entry_point: Cow::Borrowed("main") },
+        fragment_stage: None,
+        rasterization_state: RasterizationStateDescriptor::CULL_NONE,
+        primitive_topology: PrimitiveTopology::TriangleList,
+        color_states: vec![],
+        depth_stencil_state: Some(DepthStencilStateDescriptor::LESS_EQUAL_WRITE.with_format(SHADOW_MAP_FORMAT)),
+        vertex_state: vertex_state.clone(),
+        sample_count: 1,
+        alpha_to_coverage_enabled: false,
+        blend_color: None,
+    })?;
+
+    #[rustfmt::skip]
+    let render_pipeline = app.device.create_render_pipeline(RenderPipelineDescriptor {
+        layout: pipeline_layout,
+        vertex_stage: PipelineStageDescriptor { module: vs, entry_point: Cow::Borrowed("main") },
+        fragment_stage: Some(PipelineStageDescriptor { module: fs, entry_point: Cow::Borrowed("main") }),
+        rasterization_state: RasterizationStateDescriptor::CULL_NONE,
+        primitive_topology: PrimitiveTopology::TriangleList,
+        color_states: vec![ColorStateDescriptor::simple(util::DEFAULT_COLOR_FORMAT)],
+        depth_stencil_state: Some(DepthStencilStateDescriptor::LESS_WRITE.with_format(util::DEFAULT_DEPTH_FORMAT)),
+        vertex_state,
+        sample_count: app.get_sample_count(),
+        alpha_to_coverage_enabled: false,
+        blend_color: None,
+    })?;
+
+    let start_time = Instant::now();
+
+    let light_projection = util::clip_correction_matrix() * cgmath::ortho(-15.0, 15.0, -15.0, 15.0, 1.0, 50.0);
+
+    app.run(move |app| {
+        let time = util::to_float_secs(start_time.elapsed());
+
+        let light_direction = vec3((time * 0.3).cos() * 0.6, -1.0, (time * 0.3).sin() * 0.6).normalize();
+        let light_view = Matrix4::look_at(
+            Point3::new(0.0, 0.0, 0.0) - light_direction * 25.0,
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::unit_y(),
+        );
+
+        let uniforms = Uniforms {
+            view_projection: (app.camera.projection * app.camera.view).into(),
+            light_view_projection: (light_projection * light_view).into(),
+            light_direction: light_direction.extend(0.0).into(),
+        };
+
+        let queue = app.device.get_queue();
+
+        queue.write_buffer(&uniform_buffer, 0, &[uniforms])?;
+
+        let frame = match app.swapchain.acquire_next_image() {
+            Ok(frame) => frame,
+            Err(SwapchainError::OutOfDate) => return Ok(()),
+            Err(SwapchainError::SurfaceLost) => return Ok(app.recreate_surface()?),
+            Err(e) => return Err(e)?,
+        };
+
+        let mut encoder = app.device.create_command_encoder()?;
+
+        let mut shadow_pass = encoder.begin_render_pass(RenderPassDescriptor {
+            color_attachments: &[],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
+                attachment: &shadow_map_view,
+                depth_load_op: LoadOp::Clear,
+                depth_store_op: StoreOp::Store,
+                clear_depth: 1.0,
+                stencil_load_op: LoadOp::Clear,
+                stencil_store_op: StoreOp::Store,
+                clear_stencil: 0,
+            }),
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
+        });
+
+        shadow_pass.set_pipeline(&shadow_pipeline);
+        if app.state.depth_bias_enabled {
+            // Without the bias, surfaces facing the light shadow themselves ("shadow acne")
+            shadow_pass.set_depth_bias(1.25, 0.0, 1.75);
+        }
+        shadow_pass.set_bind_group(0, &shadow_bind_group, None);
+        shadow_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0])?;
+        shadow_pass.set_index_buffer(&index_buffer, 0);
+        shadow_pass.draw_indexed(indices.len() as u32, 1, 0, 0, 0);
+        shadow_pass.end_pass();
+
+        #[rustfmt::skip]
+        let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
+            color_attachments: &[
+                frame.color_attachment(LoadOp::Clear, Color { r: 0.2, g: 0.3, b: 0.4, a: 1.0 }),
+            ],
+            depth_stencil_attachment: frame.depth_stencil_attachment(LoadOp::Clear, 1.0, 0),
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
+        });
+
+        render_pass.set_pipeline(&render_pipeline);
+        render_pass.set_bind_group(0, &bind_group, None);
+        render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0])?;
+        render_pass.set_index_buffer(&index_buffer, 0);
+        render_pass.draw_indexed(indices.len() as u32, 1, 0, 0, 0);
+        render_pass.end_pass();
+
+        queue.submit(&[encoder.finish()?])?;
+
+        match queue.present(frame) {
+            Ok(frame) => frame,
+            Err(SwapchainError::OutOfDate) => return Ok(()),
+            Err(e) => return Err(e)?,
+        }
+
+        Ok(())
+    })
+}
@@ -46,6 +46,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         format: swapchain_format,
         usage: TextureUsage::OUTPUT_ATTACHMENT,
         present_mode: PresentMode::Mailbox,
+        sample_count: 1,
+        depth_format: None,
+        view_formats: &[],
     };
 
     let mut swapchain = device.create_swapchain(swapchain_desc, None)?;
@@ -59,6 +59,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         format: swapchain_format,
         usage: TextureUsage::OUTPUT_ATTACHMENT,
         present_mode: PresentMode::Mailbox,
+        sample_count: 1,
+        depth_format: None,
+        view_formats: &[],
     };
 
     let mut swapchain = device.create_swapchain(swapchain_desc, None)?;
@@ -77,6 +80,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             binding: 0,
             visibility: ShaderStage::VERTEX,
             binding_type: BindingType::UniformBuffer,
+            storage_texture_format: None,
+            min_binding_size: None,
         }],
     })?;
 
@@ -97,6 +102,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let uniform_buffer = device.create_buffer(BufferDescriptor {
         size: uniforms_size_bytes,
         usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
+        memory_hint: None,
     })?;
 
     #[rustfmt::skip]
@@ -141,11 +147,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let vertex_buffer = device.create_buffer(BufferDescriptor {
         size: vertices_size_bytes,
         usage: BufferUsage::VERTEX | BufferUsage::COPY_DST,
+        memory_hint: None,
     })?;
 
     let staging_vertex_buffer = device.create_buffer_mapped(BufferDescriptor {
         size: vertices_size_bytes,
         usage: BufferUsage::COPY_SRC | BufferUsage::MAP_WRITE,
+        memory_hint: None,
     })?;
 
     staging_vertex_buffer.copy_from_slice(vertices)?;
@@ -175,15 +183,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             entry_point: Cow::Borrowed("main"),
             module: vertex_shader,
         },
-        fragment_stage: PipelineStageDescriptor {
+        fragment_stage: Some(PipelineStageDescriptor {
             entry_point: Cow::Borrowed("main"),
             module: fragment_shader,
-        },
+        }),
         vertex_state: VertexStateDescriptor {
             index_format: IndexFormat::U16,
+            primitive_restart_enabled: false,
             vertex_buffers: vec![VertexBufferLayoutDescriptor {
                 input_slot: 0,
                 step_mode: InputStepMode::Vertex,
+                step_rate: 1,
                 stride: std::mem::size_of::<Vertex>(),
                 attributes: vec![
                     VertexAttributeDescriptor {
@@ -216,6 +226,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         },
         sample_count: 1,
         alpha_to_coverage_enabled: false,
+        blend_color: None,
     };
 
     let pipeline = device.create_render_pipeline(render_pipeline_descriptor)?;
@@ -279,21 +290,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
                         color_attachments: &[RenderPassColorAttachmentDescriptor {
                             attachment: &frame.view,
-                            clear_color: Color {
+                            clear_value: Color {
                                 r: 0.1,
                                 g: 0.1,
                                 b: 0.1,
                                 a: 1.0,
-                            },
+                            }
+                            .into(),
                             load_op: LoadOp::Clear,
                             store_op: StoreOp::Store,
                             resolve_target: None,
                         }],
                         depth_stencil_attachment: None,
+                        flip_viewport: false,
+                        viewport: None,
+                        scissor_rect: None,
+                        attachmentless: None,
                     });
 
                     render_pass.set_pipeline(&pipeline);
-                    render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0]);
+                    render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0])?;
                     render_pass.set_bind_group(0, &bind_group, None);
                     render_pass.draw(3, 1, 0, 1);
                     render_pass.end_pass();
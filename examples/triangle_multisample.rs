@@ -4,12 +4,11 @@ extern crate memoffset;
 use vki::{
     AdapterOptions, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
     BindingResource, BindingType, BlendDescriptor, BlendFactor, BlendOperation, BufferDescriptor, BufferUsage, Color,
-    ColorStateDescriptor, ColorWrite, CullMode, DeviceDescriptor, Extent3d, FrontFace, IndexFormat, InputStepMode,
-    Instance, LoadOp, PipelineLayoutDescriptor, PipelineStageDescriptor, PolygonMode, PresentMode, PrimitiveTopology,
-    RasterizationStateDescriptor, RenderPassColorAttachmentDescriptor, RenderPassDescriptor, RenderPipelineDescriptor,
-    ShaderModuleDescriptor, ShaderStage, StoreOp, SwapchainDescriptor, SwapchainError, TextureDescriptor,
-    TextureDimension, TextureFormat, TextureUsage, VertexAttributeDescriptor, VertexBufferLayoutDescriptor,
-    VertexFormat, VertexStateDescriptor,
+    ColorStateDescriptor, ColorWrite, CullMode, DeviceDescriptor, FrontFace, IndexFormat, InputStepMode, Instance,
+    LoadOp, PipelineLayoutDescriptor, PipelineStageDescriptor, PolygonMode, PresentMode, PrimitiveTopology,
+    RasterizationStateDescriptor, RenderPassDescriptor, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderStage,
+    SwapchainDescriptor, SwapchainError, TextureFormat, TextureUsage, VertexAttributeDescriptor,
+    VertexBufferLayoutDescriptor, VertexFormat, VertexStateDescriptor,
 };
 
 use winit::dpi::{LogicalSize, PhysicalSize};
@@ -58,6 +57,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         format: swapchain_format,
         usage: TextureUsage::OUTPUT_ATTACHMENT,
         present_mode: PresentMode::Mailbox,
+        sample_count: 8,
+        depth_format: None,
+        view_formats: &[],
     };
 
     let mut swapchain = device.create_swapchain(swapchain_desc, None)?;
@@ -75,6 +77,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             binding: 0,
             visibility: ShaderStage::VERTEX,
             binding_type: BindingType::UniformBuffer,
+            storage_texture_format: None,
+            min_binding_size: None,
         }],
     })?;
 
@@ -95,6 +99,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let uniform_buffer = device.create_buffer(BufferDescriptor {
         size: uniforms_size_bytes,
         usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
+        memory_hint: None,
     })?;
 
     #[rustfmt::skip]
@@ -139,11 +144,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let vertex_buffer = device.create_buffer(BufferDescriptor {
         size: vertices_size_bytes,
         usage: BufferUsage::VERTEX | BufferUsage::COPY_DST,
+        memory_hint: None,
     })?;
 
     let staging_vertex_buffer = device.create_buffer_mapped(BufferDescriptor {
         size: vertices_size_bytes,
         usage: BufferUsage::COPY_SRC | BufferUsage::MAP_WRITE,
+        memory_hint: None,
     })?;
 
     staging_vertex_buffer.copy_from_slice(vertices)?;
@@ -160,23 +167,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     device.get_queue().submit(&[encoder.finish()?])?;
 
-    let mut output_texture_descriptor = TextureDescriptor {
-        sample_count: 8,
-        usage: TextureUsage::OUTPUT_ATTACHMENT,
-        format: swapchain_format,
-        dimension: TextureDimension::D2,
-        array_layer_count: 1,
-        mip_level_count: 1,
-        size: Extent3d {
-            width: window_width,
-            height: window_height,
-            depth: 1,
-        },
-    };
-
-    let mut output_texture = device.create_texture(output_texture_descriptor)?;
-    let mut output_texture_view = output_texture.create_default_view()?;
-
     let color_replace = BlendDescriptor {
         src_factor: BlendFactor::One,
         dst_factor: BlendFactor::Zero,
@@ -190,15 +180,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             entry_point: Cow::Borrowed("main"),
             module: vertex_shader,
         },
-        fragment_stage: PipelineStageDescriptor {
+        fragment_stage: Some(PipelineStageDescriptor {
             entry_point: Cow::Borrowed("main"),
             module: fragment_shader,
-        },
+        }),
         vertex_state: VertexStateDescriptor {
             index_format: IndexFormat::U16,
+            primitive_restart_enabled: false,
             vertex_buffers: vec![VertexBufferLayoutDescriptor {
                 input_slot: 0,
                 step_mode: InputStepMode::Vertex,
+                step_rate: 1,
                 stride: std::mem::size_of::<Vertex>(),
                 attributes: vec![
                     VertexAttributeDescriptor {
@@ -229,8 +221,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             depth_bias_slope_scale: 0.0,
             depth_bias_clamp: 0.0,
         },
-        sample_count: output_texture_descriptor.sample_count,
+        sample_count: swapchain.sample_count(),
         alpha_to_coverage_enabled: false,
+        blend_color: None,
     };
 
     let pipeline = device.create_render_pipeline(render_pipeline_descriptor)?;
@@ -280,10 +273,6 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     window_width = width as _;
                     window_height = height as _;
                     if width > 0 && height > 0 {
-                        output_texture_descriptor.size.width = width;
-                        output_texture_descriptor.size.height = height;
-                        output_texture = device.create_texture(output_texture_descriptor)?;
-                        output_texture_view = output_texture.create_default_view()?;
                         swapchain = device.create_swapchain(swapchain_desc, Some(&swapchain))?;
                     }
                 }
@@ -314,23 +303,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                     let mut encoder = device.create_command_encoder()?;
                     let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
-                        color_attachments: &[RenderPassColorAttachmentDescriptor {
-                            attachment: &output_texture_view,
-                            clear_color: Color {
+                        color_attachments: &[frame.color_attachment(
+                            LoadOp::Clear,
+                            Color {
                                 r: 0.1,
                                 g: 0.1,
                                 b: 0.1,
                                 a: 1.0,
                             },
-                            load_op: LoadOp::Clear,
-                            store_op: StoreOp::Store,
-                            resolve_target: Some(&frame.view),
-                        }],
+                        )],
                         depth_stencil_attachment: None,
+                        flip_viewport: false,
+                        viewport: None,
+                        scissor_rect: None,
+                        attachmentless: None,
                     });
 
                     render_pass.set_pipeline(&pipeline);
-                    render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0]);
+                    render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0])?;
                     render_pass.set_bind_group(0, &bind_group, None);
                     render_pass.draw(3, 1, 0, 1);
                     render_pass.end_pass();
@@ -0,0 +1,419 @@
+use cgmath::prelude::*;
+use cgmath::{Deg, Matrix4, Point3, Vector3};
+
+use std::collections::HashSet;
+use std::time::Duration;
+use winit::event::{
+    DeviceEvent, ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode,
+    WindowEvent,
+};
+
+use super::{to_float_secs, Camera};
+
+/// Controls the `App` camera in response to input events. See `App::set_camera_controller`.
+pub trait CameraController {
+    /// Called once per event. Return `true` to consume the event.
+    fn on_event(&mut self, camera: &mut Camera, event: &Event<()>) -> bool;
+
+    /// Called once per frame with the time elapsed since the previous frame
+    fn on_frame(&mut self, _camera: &mut Camera, _frame_time: Duration) {}
+}
+
+#[derive(Default, Debug, Clone)]
+struct MouseState {
+    motion_deltas: Vec<(f64, f64)>,
+    button_down: bool,
+    right_button_down: bool,
+    cursor_left: bool,
+}
+
+impl MouseState {
+    fn on_event(&mut self, event: &Event<()>) -> bool {
+        let mut consume = false;
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CursorLeft { .. },
+                ..
+            } => {
+                self.cursor_left = true;
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorEntered { .. },
+                ..
+            } => {
+                self.cursor_left = false;
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { .. },
+                ..
+            } => {
+                self.cursor_left = false;
+            }
+            Event::WindowEvent {
+                event: WindowEvent::MouseInput { button, state, .. },
+                ..
+            } => match (button, state) {
+                (MouseButton::Left, ElementState::Pressed) => {
+                    self.button_down = true;
+                    consume = true;
+                }
+                (MouseButton::Left, ElementState::Released) => {
+                    self.button_down = false;
+                    consume = true;
+                }
+                (MouseButton::Right, ElementState::Pressed) => {
+                    self.right_button_down = true;
+                    consume = true;
+                }
+                (MouseButton::Right, ElementState::Released) => {
+                    self.right_button_down = false;
+                    consume = true;
+                }
+                _ => {}
+            },
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta, .. },
+                ..
+            } => {
+                if (self.button_down || self.right_button_down) && !self.cursor_left {
+                    let (x, y) = delta;
+                    self.motion_deltas.push((x.round() as _, y.round() as _));
+                    consume = true;
+                }
+            }
+            _ => {}
+        }
+        consume
+    }
+}
+
+/// Rotates `offset` by `yaw` degrees around `up` and by `pitch` degrees around `right`. The pitch
+/// is skipped if it would align the offset with `up`.
+fn rotate(offset: Vector3<f32>, up: Vector3<f32>, right: Vector3<f32>, yaw: f32, pitch: f32) -> Vector3<f32> {
+    let offset = Matrix4::from_axis_angle(up, Deg(yaw)).transform_vector(offset);
+    let pitched = Matrix4::from_axis_angle(right.normalize(), Deg(pitch)).transform_vector(offset);
+    let angle = Deg::from(pitched.angle(up)).0;
+    if angle < 179.0 && angle > 1.0 {
+        pitched
+    } else {
+        offset
+    }
+}
+
+/// Rotates the eye around the center while dragging with the left mouse button. `W`, `A`, `S`,
+/// `D`, `PageUp`, and `PageDown` move the camera (`Shift` moves only the eye vertically), `C`
+/// re-centers the camera on the origin, and the mouse wheel zooms.
+#[derive(Default, Debug, Clone)]
+pub struct ArcBallController {
+    mouse_state: MouseState,
+    modifiers: ModifiersState,
+}
+
+impl CameraController for ArcBallController {
+    fn on_event(&mut self, camera: &mut Camera, event: &Event<()>) -> bool {
+        let consume = self.mouse_state.on_event(event);
+
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(modifiers),
+                ..
+            } => {
+                self.modifiers = modifiers.clone();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(virtual_keycode),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => {
+                let transform = camera.view.invert().unwrap();
+                let left = transform.transform_vector(Vector3::unit_x());
+                let forward = -left.cross(Vector3::unit_y());
+
+                match virtual_keycode {
+                    VirtualKeyCode::W => {
+                        camera.eye += forward;
+                        camera.center += forward;
+                    }
+                    VirtualKeyCode::S => {
+                        camera.eye -= forward;
+                        camera.center -= forward;
+                    }
+                    VirtualKeyCode::C => {
+                        camera.center = Point3::new(0.0, 0.0, 0.0);
+                    }
+                    VirtualKeyCode::A => {
+                        camera.eye -= left;
+                        camera.center -= left;
+                    }
+                    VirtualKeyCode::D => {
+                        camera.eye += left;
+                        camera.center += left;
+                    }
+                    VirtualKeyCode::PageUp => {
+                        camera.eye += Vector3::unit_y();
+                        if !self.modifiers.shift() {
+                            camera.center += Vector3::unit_y();
+                        }
+                    }
+                    VirtualKeyCode::PageDown => {
+                        camera.eye -= Vector3::unit_y();
+                        if !self.modifiers.shift() {
+                            camera.center -= Vector3::unit_y();
+                        }
+                    }
+                    _ => {}
+                }
+
+                camera.update_view_matrix();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseWheel {
+                        delta: MouseScrollDelta::LineDelta(_, y),
+                        ..
+                    },
+                ..
+            } => {
+                let v: Vector3<f32> = camera.eye - camera.center;
+                let y = *y;
+                if v.magnitude() - y > 0.0 {
+                    let dir: Vector3<f32> = v.normalize();
+                    camera.eye += dir * -y;
+                    camera.update_view_matrix();
+                }
+            }
+            _ => {}
+        }
+
+        for (dx, dy) in self.mouse_state.motion_deltas.drain(..) {
+            if !self.mouse_state.button_down {
+                continue;
+            }
+
+            let (dx, dy) = (dx as f32, dy as f32);
+
+            let transform = camera.view.invert().unwrap();
+            let right = transform.transform_vector(Vector3::unit_x());
+            let forward = transform.transform_vector(-Vector3::unit_z());
+            let up = camera.up;
+
+            let angle = Deg::from(forward.angle(up)).0 + dy;
+            if angle < 179.0 && angle > 1.0 {
+                let m1 = Matrix4::from_axis_angle(up, Deg(-dx));
+                let m2 = Matrix4::from_axis_angle(right, Deg(-dy));
+
+                let center_offset: Vector3<f32> = camera.center - Point3::origin();
+
+                let mut eye = camera.eye - center_offset;
+
+                eye = m1.transform_point(eye);
+                eye = m2.transform_point(eye);
+
+                camera.eye = eye + center_offset;
+
+                camera.update_view_matrix();
+            }
+        }
+
+        consume
+    }
+}
+
+/// First person controls. Dragging with the left mouse button looks around, `W`, `A`, `S`, and
+/// `D` move, `E` and `Q` move up and down, and the mouse wheel changes the speed. Holding `Shift`
+/// moves faster.
+#[derive(Debug, Clone)]
+pub struct FlyController {
+    mouse_state: MouseState,
+    modifiers: ModifiersState,
+    pressed_keys: HashSet<VirtualKeyCode>,
+    /// Movement speed in units per second
+    pub speed: f32,
+    /// Rotation in degrees per pixel of mouse motion
+    pub sensitivity: f32,
+}
+
+impl Default for FlyController {
+    fn default() -> FlyController {
+        FlyController {
+            mouse_state: Default::default(),
+            modifiers: Default::default(),
+            pressed_keys: Default::default(),
+            speed: 10.0,
+            sensitivity: 0.2,
+        }
+    }
+}
+
+impl CameraController for FlyController {
+    fn on_event(&mut self, camera: &mut Camera, event: &Event<()>) -> bool {
+        let consume = self.mouse_state.on_event(event);
+
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(modifiers),
+                ..
+            } => {
+                self.modifiers = modifiers.clone();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state,
+                                virtual_keycode: Some(virtual_keycode),
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } => match state {
+                ElementState::Pressed => {
+                    self.pressed_keys.insert(*virtual_keycode);
+                }
+                ElementState::Released => {
+                    self.pressed_keys.remove(virtual_keycode);
+                }
+            },
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseWheel {
+                        delta: MouseScrollDelta::LineDelta(_, y),
+                        ..
+                    },
+                ..
+            } => {
+                self.speed = (self.speed * 1.1f32.powf(*y)).max(0.1);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Focused(false),
+                ..
+            } => {
+                self.pressed_keys.clear();
+            }
+            _ => {}
+        }
+
+        for (dx, dy) in self.mouse_state.motion_deltas.drain(..) {
+            if !self.mouse_state.button_down {
+                continue;
+            }
+
+            let forward = camera.center - camera.eye;
+            let right = forward.cross(camera.up);
+            let yaw = -dx as f32 * self.sensitivity;
+            let pitch = -dy as f32 * self.sensitivity;
+
+            camera.center = camera.eye + rotate(forward, camera.up, right, yaw, pitch);
+            camera.update_view_matrix();
+        }
+
+        consume
+    }
+
+    fn on_frame(&mut self, camera: &mut Camera, frame_time: Duration) {
+        let forward = (camera.center - camera.eye).normalize();
+        let right = forward.cross(camera.up).normalize();
+        let up = camera.up;
+
+        let mut direction = Vector3::zero();
+        for key in self.pressed_keys.iter() {
+            match key {
+                VirtualKeyCode::W => direction += forward,
+                VirtualKeyCode::S => direction -= forward,
+                VirtualKeyCode::D => direction += right,
+                VirtualKeyCode::A => direction -= right,
+                VirtualKeyCode::E => direction += up,
+                VirtualKeyCode::Q => direction -= up,
+                _ => {}
+            }
+        }
+
+        if direction.magnitude2() > 0.0 {
+            let speed = if self.modifiers.shift() {
+                self.speed * 4.0
+            } else {
+                self.speed
+            };
+            let translation = direction.normalize() * speed * to_float_secs(frame_time);
+            camera.eye += translation;
+            camera.center += translation;
+            camera.update_view_matrix();
+        }
+    }
+}
+
+/// Orbits a target point. Dragging with the left mouse button orbits the target, dragging with
+/// the right mouse button pans the target across the view plane, and the mouse wheel zooms.
+#[derive(Debug, Clone)]
+pub struct OrbitController {
+    mouse_state: MouseState,
+    /// Rotation in degrees per pixel of mouse motion
+    pub orbit_sensitivity: f32,
+    /// Panning per pixel of mouse motion, relative to the distance from the target
+    pub pan_sensitivity: f32,
+}
+
+impl Default for OrbitController {
+    fn default() -> OrbitController {
+        OrbitController {
+            mouse_state: Default::default(),
+            orbit_sensitivity: 0.3,
+            pan_sensitivity: 0.002,
+        }
+    }
+}
+
+impl CameraController for OrbitController {
+    fn on_event(&mut self, camera: &mut Camera, event: &Event<()>) -> bool {
+        let consume = self.mouse_state.on_event(event);
+
+        if let Event::WindowEvent {
+            event:
+                WindowEvent::MouseWheel {
+                    delta: MouseScrollDelta::LineDelta(_, y),
+                    ..
+                },
+            ..
+        } = event
+        {
+            let offset = camera.eye - camera.center;
+            let distance = (offset.magnitude() * 0.9f32.powf(*y)).max(camera.near);
+            camera.eye = camera.center + offset.normalize() * distance;
+            camera.update_view_matrix();
+        }
+
+        for (dx, dy) in self.mouse_state.motion_deltas.drain(..) {
+            let (dx, dy) = (dx as f32, dy as f32);
+
+            let transform = camera.view.invert().unwrap();
+            let right = transform.transform_vector(Vector3::unit_x());
+            let view_up = transform.transform_vector(Vector3::unit_y());
+            let offset = camera.eye - camera.center;
+
+            if self.mouse_state.right_button_down {
+                let scale = offset.magnitude() * self.pan_sensitivity;
+                let translation = (-right * dx + view_up * dy) * scale;
+                camera.eye += translation;
+                camera.center += translation;
+            } else if self.mouse_state.button_down {
+                let yaw = -dx * self.orbit_sensitivity;
+                let pitch = -dy * self.orbit_sensitivity;
+                camera.eye = camera.center + rotate(offset, camera.up, right, yaw, pitch);
+            }
+
+            camera.update_view_matrix();
+        }
+
+        consume
+    }
+}
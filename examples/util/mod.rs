@@ -1,45 +1,48 @@
 use cgmath::prelude::*;
 use cgmath::{Deg, Matrix4, Point3, Vector3};
 
+pub mod camera_controller;
 pub mod shape;
 
 use ash::vk;
 
-use std::slice;
-
 use vki::{
-    Adapter, AdapterOptions, Buffer, BufferCopyView, BufferDescriptor, BufferUsage, CommandEncoder, Device,
-    DeviceDescriptor, Error, Extensions, Extent3d, FilterMode, Instance, Origin3d, PowerPreference, PresentMode,
-    Surface, Swapchain, SwapchainDescriptor, Texture, TextureBlitView, TextureCopyView, TextureDescriptor,
-    TextureDimension, TextureFormat, TextureUsage, TextureView,
+    Adapter, AdapterOptions, Buffer, BufferCopyView, BufferDescriptor, BufferUsage, Color, CommandEncoder, Device,
+    DeviceDescriptor, Error, Extensions, Extent3d, FilterMode, Instance, LoadOp, Origin3d, PowerPreference,
+    PresentMode, RenderPassColorAttachmentDescriptor, RenderPassDepthStencilAttachmentDescriptor, StoreOp, Surface,
+    SurfaceDescriptor, Swapchain, SwapchainDescriptor, Texture, TextureBlitView, TextureCopyView, TextureDataLayout,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsage, TextureView, COPY_BYTES_PER_ROW_ALIGNMENT,
 };
 
+use camera_controller::{ArcBallController, CameraController};
+
+use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 use winit::dpi::{LogicalPosition, LogicalSize, PhysicalPosition};
-use winit::event::{
-    DeviceEvent, ElementState, Event, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent,
-};
+use winit::event::{ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::window::{Fullscreen, Window, WindowBuilder};
 
 pub const DEFAULT_DEPTH_FORMAT: TextureFormat = TextureFormat::D32FloatS8Uint;
 pub const DEFAULT_COLOR_FORMAT: TextureFormat = TextureFormat::B8G8R8A8Unorm;
 
-fn create_swapchain_and_depth_view_and_color_view(
+fn create_swapchain(
     device: &Device,
     surface: &Surface,
     sample_count: u32,
-    width: u32,
-    height: u32,
+    present_mode: PresentMode,
     old_swapchain: Option<&Swapchain>,
-) -> Result<(Swapchain, TextureView, TextureView), Error> {
-    let swap_chain = device
+) -> Result<Swapchain, Error> {
+    let swapchain = device
         .create_swapchain(
             SwapchainDescriptor {
                 surface,
                 usage: TextureUsage::OUTPUT_ATTACHMENT,
                 format: DEFAULT_COLOR_FORMAT,
-                present_mode: PresentMode::Mailbox,
+                present_mode,
+                sample_count,
+                depth_format: Some(DEFAULT_DEPTH_FORMAT),
+                view_formats: &[],
             },
             old_swapchain,
         )
@@ -48,39 +51,7 @@ fn create_swapchain_and_depth_view_and_color_view(
             vk::Result::ERROR_INITIALIZATION_FAILED
         })?;
 
-    let depth_texture = device.create_texture(TextureDescriptor {
-        size: Extent3d {
-            width,
-            height,
-            depth: 1,
-        },
-        array_layer_count: 1,
-        mip_level_count: 1,
-        sample_count,
-        dimension: TextureDimension::D2,
-        usage: TextureUsage::OUTPUT_ATTACHMENT,
-        format: DEFAULT_DEPTH_FORMAT,
-    })?;
-
-    let depth_view = depth_texture.create_default_view()?;
-
-    let color_texture = device.create_texture(TextureDescriptor {
-        size: Extent3d {
-            width,
-            height,
-            depth: 1,
-        },
-        array_layer_count: 1,
-        mip_level_count: 1,
-        sample_count,
-        dimension: TextureDimension::D2,
-        usage: TextureUsage::OUTPUT_ATTACHMENT,
-        format: DEFAULT_COLOR_FORMAT,
-    })?;
-
-    let color_view = color_texture.create_default_view()?;
-
-    Ok((swap_chain, depth_view, color_view))
+    Ok(swapchain)
 }
 
 pub enum EventHandlers<T> {
@@ -94,7 +65,7 @@ impl<T: 'static> EventHandlers<T> {
             Box::new(CloseRequestedHandler),
             Box::new(WindowResizedHandler::default()),
             Box::new(CameraViewportHandler),
-            Box::new(ArcBallCameraControlHandler::default()),
+            Box::new(CameraControlHandler::default()),
         ]
     }
 }
@@ -108,6 +79,63 @@ impl<T: 'static> Into<Vec<Box<dyn EventHandler<T>>>> for EventHandlers<T> {
     }
 }
 
+/// The number of frames that `FrameStats` are computed over
+const FRAME_STATS_WINDOW: usize = 120;
+
+/// Frame time statistics over the most recently rendered frames
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    frame_times: VecDeque<Duration>,
+    frame_count: u64,
+    last_frame_start: Option<Instant>,
+}
+
+impl FrameStats {
+    fn begin_frame(&mut self) {
+        let now = Instant::now();
+        if let Some(last_frame_start) = self.last_frame_start {
+            if self.frame_times.len() == FRAME_STATS_WINDOW {
+                self.frame_times.pop_front();
+            }
+            self.frame_times.push_back(now - last_frame_start);
+        }
+        self.last_frame_start = Some(now);
+        self.frame_count += 1;
+    }
+
+    /// The total number of frames rendered
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// The time between the start of the previous frame and the start of the current frame
+    pub fn last_frame_time(&self) -> Option<Duration> {
+        self.frame_times.back().cloned()
+    }
+
+    pub fn average_frame_time(&self) -> Option<Duration> {
+        if self.frame_times.is_empty() {
+            return None;
+        }
+        let total: Duration = self.frame_times.iter().sum();
+        Some(total / self.frame_times.len() as u32)
+    }
+
+    pub fn min_frame_time(&self) -> Option<Duration> {
+        self.frame_times.iter().min().cloned()
+    }
+
+    pub fn max_frame_time(&self) -> Option<Duration> {
+        self.frame_times.iter().max().cloned()
+    }
+
+    /// Frames per second, computed from the average frame time
+    pub fn fps(&self) -> Option<f32> {
+        self.average_frame_time()
+            .map(|frame_time| 1.0 / to_float_secs(frame_time))
+    }
+}
+
 enum WindowMode {
     Fullscreen {
         last_position: LogicalPosition<f32>,
@@ -122,18 +150,15 @@ pub struct App<T> {
     pub adapter: Adapter,
     pub device: Device,
     pub swapchain: Swapchain,
-    pub depth_view: TextureView,
-    pub color_view: TextureView,
     pub window: Window,
     pub should_close: bool,
     pub camera: Camera,
     pub state: T,
-    /// Throttle the frame rate to reduce CPU usage for low complexity scenes. Values greater than
-    /// `1000` are interpreted as `unlimited`. The default is `60`.
-    pub max_fps: u64,
-    last_frame_time: Instant,
+    camera_controller: Box<dyn CameraController>,
     window_mode: WindowMode,
     sample_count: u32,
+    present_mode: PresentMode,
+    frame_stats: FrameStats,
     event_handlers: Option<Vec<Box<dyn EventHandler<T>>>>,
     event_loop: Option<EventLoop<()>>,
 }
@@ -175,44 +200,43 @@ impl<T: 'static> App<T> {
             surface_support: Some(&surface),
             extensions: Extensions {
                 anisotropic_filtering: false,
+                texture_compression_bc: false,
+                texture_compression_etc2: false,
+                texture_compression_astc_ldr: false,
+                timeline_semaphore: false,
+                transform_feedback: false,
+                subgroup_size_control: false,
+                shader_float16: false,
+                shader_int8: false,
+                storage_buffer_8bit_access: false,
+                storage_buffer_16bit_access: false,
+                index_type_uint8: false,
+                vertex_attribute_divisor: false,
+                pipeline_creation_feedback: false,
             },
+            queue_priority: None,
+            global_priority: None,
         })?;
 
         let sample_count = 1;
 
-        let (swapchain, depth_view, color_view) = create_swapchain_and_depth_view_and_color_view(
-            &device,
-            &surface,
-            sample_count,
-            window_width,
-            window_height,
-            None,
-        )?;
+        // Frames are paced by presentation rather than by sleeping. Fifo waits for the vertical
+        // blank, while Mailbox renders as fast as possible without tearing.
+        let present_mode = match std::env::var("VSYNC").as_ref().map(|s| s.as_str()) {
+            Ok("0") | Ok("false") => PresentMode::Mailbox,
+            Ok(_) | Err(_) => PresentMode::Fifo,
+        };
+
+        let swapchain = create_swapchain(&device, &surface, sample_count, present_mode, None)?;
 
         let state = Default::default();
         let should_close = false;
         let event_loop = Some(event_loop);
         let event_handlers = Some(event_handlers.into());
         let camera = Camera::new(window_width, window_height);
+        let camera_controller = Box::new(ArcBallController::default());
         let window_mode = WindowMode::Windowed;
-        let last_frame_time = Instant::now();
-        let max_fps = match std::env::var("MAX_FPS") {
-            Ok(val) => val.parse().expect("Invalid MAX_FPS"),
-            Err(_) => {
-                if cfg!(target_os = "linux") {
-                    // Winit on X11 is eating events with eventloop-2.0.
-                    // Limiting the FPS seems to help mitigate the issue.
-                    let max_fps = 30;
-                    println!(
-                        "Limiting FPS: {} ({})",
-                        max_fps, "https://github.com/rust-windowing/winit/issues/865"
-                    );
-                    max_fps
-                } else {
-                    60
-                }
-            }
-        };
+        let frame_stats = FrameStats::default();
 
         log::debug!("{:#?}", adapter);
 
@@ -222,18 +246,17 @@ impl<T: 'static> App<T> {
             adapter,
             device,
             swapchain,
-            depth_view,
-            color_view,
             window,
             state,
             should_close,
             camera,
+            camera_controller,
             event_loop,
             event_handlers,
             sample_count,
             window_mode,
-            last_frame_time,
-            max_fps,
+            present_mode,
+            frame_stats,
         })
     }
 
@@ -292,25 +315,77 @@ impl<T: 'static> App<T> {
         }
     }
 
+    /// Re-creates the swapchain with the sample count, clamped to the largest count supported by
+    /// both the color and depth formats.
     pub fn set_sample_count(&mut self, sample_count: u32) -> Result<(), Error> {
-        let (window_width, window_height): (u32, u32) = self.window.inner_size().into();
+        let usage = TextureUsage::OUTPUT_ATTACHMENT;
+        let color_sample_counts = self.adapter.supported_sample_counts(DEFAULT_COLOR_FORMAT, usage);
+        let depth_sample_counts = self.adapter.supported_sample_counts(DEFAULT_DEPTH_FORMAT, usage);
+        let requested_sample_count = sample_count;
+        let sample_count = color_sample_counts
+            .into_iter()
+            .filter(|count| *count <= requested_sample_count && depth_sample_counts.contains(count))
+            .max()
+            .unwrap_or(1);
+        if sample_count != requested_sample_count {
+            log::warn!(
+                "sample_count {} is not supported, using: {}",
+                requested_sample_count,
+                sample_count
+            );
+        }
         if self.sample_count != sample_count {
-            let (swapchain, depth_view, color_view) = create_swapchain_and_depth_view_and_color_view(
+            self.swapchain = create_swapchain(
                 &self.device,
                 &self.surface,
                 sample_count,
-                window_width,
-                window_height,
+                self.present_mode,
                 Some(&self.swapchain),
             )?;
-            self.swapchain = swapchain;
-            self.depth_view = depth_view;
-            self.color_view = color_view;
         }
         self.sample_count = sample_count;
         Ok(())
     }
 
+    /// Re-creates the swapchain with the present mode, which paces the frame rate. If the mode is
+    /// not supported by the surface, `PresentMode::Fifo` is used.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) -> Result<(), Error> {
+        if self.present_mode != present_mode {
+            self.swapchain = create_swapchain(
+                &self.device,
+                &self.surface,
+                self.sample_count,
+                present_mode,
+                Some(&self.swapchain),
+            )?;
+        }
+        self.present_mode = present_mode;
+        Ok(())
+    }
+
+    pub fn get_present_mode(&self) -> PresentMode {
+        self.present_mode
+    }
+
+    /// Replaces the controller that moves the camera in response to input. The default is an
+    /// `ArcBallController`.
+    pub fn set_camera_controller<C: CameraController + 'static>(&mut self, camera_controller: C) {
+        self.camera_controller = Box::new(camera_controller);
+    }
+
+    /// Frame time statistics for the most recently rendered frames
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    /// Re-creates the surface and swapchain after `SwapchainError::SurfaceLost`
+    pub fn recreate_surface(&mut self) -> Result<(), Error> {
+        let descriptor = SurfaceDescriptor::from_window(&self.window);
+        self.surface = self.instance.recreate_surface(&self.surface, &descriptor)?;
+        self.swapchain = create_swapchain(&self.device, &self.surface, self.sample_count, self.present_mode, None)?;
+        Ok(())
+    }
+
     pub fn get_sample_count(&self) -> u32 {
         self.sample_count
     }
@@ -327,6 +402,8 @@ impl<T: 'static> App<T> {
 
         self.window.set_visible(true);
 
+        let print_frame_stats = std::env::var("FRAME_STATS").is_ok();
+
         event_loop.run(move |event, _, control_flow| {
             for event_handler in event_handlers.iter_mut() {
                 let consume = event_handler.on_event(&mut self, &event);
@@ -335,18 +412,25 @@ impl<T: 'static> App<T> {
                 }
             }
 
-            let min_duration = Duration::from_millis(1000 / self.max_fps);
-
             match event {
                 Event::MainEventsCleared => {
-                    if Instant::now() - min_duration >= self.last_frame_time {
-                        self.window.request_redraw();
-                    }
+                    // The frame rate is limited by presentation, which blocks when the swapchain
+                    // images are all in use.
+                    self.window.request_redraw();
                 }
                 Event::RedrawRequested(_) => {
-                    self.last_frame_time = Instant::now();
-
-                    *control_flow = ControlFlow::WaitUntil(self.last_frame_time + min_duration);
+                    self.frame_stats.begin_frame();
+
+                    if print_frame_stats && self.frame_stats.frame_count() % FRAME_STATS_WINDOW as u64 == 0 {
+                        let stats = &self.frame_stats;
+                        println!(
+                            "fps: {:.1}, frame time (avg/min/max): {:?} / {:?} / {:?}",
+                            stats.fps().unwrap_or(0.0),
+                            stats.average_frame_time().unwrap_or_default(),
+                            stats.min_frame_time().unwrap_or_default(),
+                            stats.max_frame_time().unwrap_or_default()
+                        );
+                    }
 
                     for event_handler in event_handlers.iter_mut() {
                         event_handler.on_frame(&mut self);
@@ -364,6 +448,151 @@ impl<T: 'static> App<T> {
     }
 }
 
+/// An offscreen variant of `App` that renders into a color and depth target without creating
+/// a window, surface, or swapchain.
+pub struct HeadlessApp<T> {
+    pub instance: Instance,
+    pub adapter: Adapter,
+    pub device: Device,
+    pub color_texture: Texture,
+    pub color_view: TextureView,
+    pub depth_view: TextureView,
+    pub camera: Camera,
+    pub state: T,
+    /// The number of frames rendered by `run`
+    pub frame_count: u64,
+}
+
+impl<T: 'static> App<T> {
+    /// Initializes a `HeadlessApp` with a `width` by `height` offscreen color and depth target.
+    pub fn init_headless(width: u32, height: u32) -> Result<HeadlessApp<T>, Error>
+    where
+        T: Default,
+    {
+        let instance = Instance::new().map_err(|e| {
+            log::error!("Failed to initialize instance: {:?}", e);
+            vk::Result::ERROR_INITIALIZATION_FAILED
+        })?;
+
+        let adapter = instance.request_adapter(AdapterOptions {
+            power_preference: match std::env::var("LOW_POWER").as_ref().map(|s| s.as_str()) {
+                Ok("1") | Ok("true") => PowerPreference::LowPower,
+                Ok(_) | Err(_) => PowerPreference::HighPerformance,
+            },
+        })?;
+
+        let device = adapter.create_device(DeviceDescriptor::default())?;
+
+        let size = Extent3d {
+            width,
+            height,
+            depth: 1,
+        };
+
+        let color_texture = device.create_texture(TextureDescriptor {
+            size,
+            array_layer_count: 1,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::COPY_SRC,
+            format: DEFAULT_COLOR_FORMAT,
+        })?;
+
+        let color_view = color_texture.create_default_view()?;
+
+        let depth_texture = device.create_texture(TextureDescriptor {
+            size,
+            array_layer_count: 1,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            usage: TextureUsage::OUTPUT_ATTACHMENT,
+            format: DEFAULT_DEPTH_FORMAT,
+        })?;
+
+        let depth_view = depth_texture.create_default_view()?;
+
+        log::debug!("{:#?}", adapter);
+
+        Ok(HeadlessApp {
+            instance,
+            adapter,
+            device,
+            color_texture,
+            color_view,
+            depth_view,
+            camera: Camera::new(width, height),
+            state: Default::default(),
+            frame_count: 0,
+        })
+    }
+}
+
+impl<T: 'static> HeadlessApp<T> {
+    /// Returns a color attachment that renders into the offscreen color target
+    pub fn color_attachment(&self, load_op: LoadOp, clear_color: Color) -> RenderPassColorAttachmentDescriptor {
+        RenderPassColorAttachmentDescriptor {
+            attachment: &self.color_view,
+            resolve_target: None,
+            load_op,
+            store_op: StoreOp::Store,
+            clear_value: clear_color.into(),
+        }
+    }
+
+    /// Returns a depth stencil attachment for the offscreen depth target
+    pub fn depth_stencil_attachment(
+        &self,
+        load_op: LoadOp,
+        clear_depth: f32,
+        clear_stencil: u32,
+    ) -> RenderPassDepthStencilAttachmentDescriptor {
+        RenderPassDepthStencilAttachmentDescriptor {
+            attachment: &self.depth_view,
+            depth_load_op: load_op,
+            depth_store_op: StoreOp::Store,
+            clear_depth,
+            stencil_load_op: load_op,
+            stencil_store_op: StoreOp::Store,
+            clear_stencil,
+        }
+    }
+
+    /// Calls `on_frame` for the given number of frames and waits for the device to finish
+    /// the submitted work. Returns the total elapsed time.
+    pub fn run<F>(&mut self, frames: u64, mut on_frame: F) -> Result<Duration, Box<dyn std::error::Error>>
+    where
+        F: FnMut(&mut HeadlessApp<T>) -> Result<(), Box<dyn std::error::Error>>,
+    {
+        self.camera.update_view_matrix();
+        self.camera.update_projection_matrix();
+
+        let start = Instant::now();
+
+        for _ in 0..frames {
+            on_frame(self)?;
+            self.frame_count += 1;
+        }
+
+        let fence = self.device.get_queue().create_fence()?;
+        fence.wait(Duration::from_secs(60))?;
+
+        let elapsed = Instant::now() - start;
+
+        if frames > 0 {
+            log::info!(
+                "rendered {} frames in {:?} ({:?} per frame)",
+                frames,
+                elapsed,
+                elapsed / frames as u32
+            );
+        }
+
+        Ok(elapsed)
+    }
+}
+
 /// Convenience function for submitting a command buffer and creating a new encoder
 pub fn submit(device: &Device, encoder: CommandEncoder) -> Result<CommandEncoder, vki::Error> {
     device.get_queue().submit(&[encoder.finish()?])?;
@@ -388,6 +617,7 @@ pub fn create_buffer_with_data<U: Copy + 'static>(
     let descriptor = BufferDescriptor {
         usage,
         size: size_bytes,
+        memory_hint: None,
     };
 
     if is_write_mapped {
@@ -405,12 +635,40 @@ pub fn create_staging_buffer<U: Copy + 'static>(device: &Device, data: &[U]) ->
     let descriptor = BufferDescriptor {
         usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
         size: byte_length(data),
+        memory_hint: None,
     };
     let mapped_buffer = device.create_buffer_mapped(descriptor)?;
     mapped_buffer.copy_from_slice(data)?;
     Ok(mapped_buffer.unmap())
 }
 
+/// Creates a staging buffer for tightly packed texel data. The rows are padded to
+/// `COPY_BYTES_PER_ROW_ALIGNMENT` as required for buffer to texture copies.
+pub fn create_texture_staging_buffer(
+    device: &Device,
+    data: &[u8],
+    size: Extent3d,
+) -> Result<(Buffer, TextureDataLayout), Error> {
+    let row_count = (size.height * size.depth) as usize;
+    let row_size = data.len() / row_count;
+    let alignment = COPY_BYTES_PER_ROW_ALIGNMENT as usize;
+    let bytes_per_row = (row_size + alignment - 1) / alignment * alignment;
+    let mut padded_data = vec![0; bytes_per_row * row_count];
+    for (src, dst) in data
+        .chunks_exact(row_size)
+        .zip(padded_data.chunks_exact_mut(bytes_per_row))
+    {
+        dst[..row_size].copy_from_slice(src);
+    }
+    let buffer = create_staging_buffer(device, &padded_data)?;
+    let layout = TextureDataLayout {
+        offset: 0,
+        bytes_per_row: bytes_per_row as u32,
+        rows_per_image: size.height,
+    };
+    Ok((buffer, layout))
+}
+
 /// Copies the data to the destination using a staging buffer
 pub fn copy_to_buffer<T: Copy + 'static>(
     device: &Device,
@@ -457,14 +715,12 @@ pub fn create_texture_with_data(
 
     let texture = device.create_texture(descriptor)?;
 
-    let buffer = create_staging_buffer(&device, data)?;
+    let (buffer, layout) = create_texture_staging_buffer(&device, data, size)?;
 
     encoder.copy_buffer_to_texture(
         BufferCopyView {
-            offset: 0,
             buffer: &buffer,
-            image_height: size.height,
-            row_length: size.width,
+            layout,
         },
         TextureCopyView {
             texture: &texture,
@@ -473,7 +729,7 @@ pub fn create_texture_with_data(
             array_layer: 0,
         },
         size,
-    );
+    )?;
 
     Ok(texture)
 }
@@ -700,82 +956,20 @@ impl<T> EventHandler<T> for WindowResizedHandler {
     fn on_frame(&mut self, app: &mut App<T>) {
         let ready_to_rebuild = self.new_window_height > 0 && self.new_window_width > 0;
         if self.rebuild_swapchain_and_views && ready_to_rebuild {
-            let (swapchain, new_depth_view, new_color_view) = create_swapchain_and_depth_view_and_color_view(
+            let swapchain = create_swapchain(
                 &app.device,
                 &app.surface,
                 app.sample_count,
-                self.new_window_width,
-                self.new_window_height,
+                app.present_mode,
                 Some(&app.swapchain),
             )
-            .expect("failed to re-create swapchain or textures views");
+            .expect("failed to re-create swapchain");
             app.swapchain = swapchain;
-            app.depth_view = new_depth_view;
-            app.color_view = new_color_view;
             self.rebuild_swapchain_and_views = false;
         }
     }
 }
 
-#[derive(Default, Debug, Clone)]
-struct MouseState {
-    motion_deltas: Vec<(f64, f64)>,
-    button_down: bool,
-    cursor_left: bool,
-}
-
-impl<T> EventHandler<T> for MouseState {
-    fn on_event(&mut self, _: &mut App<T>, event: &Event<()>) -> bool {
-        let mut consume = false;
-        match event {
-            Event::WindowEvent {
-                event: WindowEvent::CursorLeft { .. },
-                ..
-            } => {
-                self.cursor_left = true;
-            }
-            Event::WindowEvent {
-                event: WindowEvent::CursorEntered { .. },
-                ..
-            } => {
-                self.cursor_left = false;
-            }
-            Event::WindowEvent {
-                event: WindowEvent::CursorMoved { .. },
-                ..
-            } => {
-                self.cursor_left = false;
-            }
-            Event::WindowEvent {
-                event: WindowEvent::MouseInput { button, state, .. },
-                ..
-            } => match (button, state) {
-                (MouseButton::Left, ElementState::Pressed) => {
-                    self.button_down = true;
-                    consume = true;
-                }
-                (MouseButton::Left, ElementState::Released) => {
-                    self.button_down = false;
-                    consume = true;
-                }
-                _ => {}
-            },
-            Event::DeviceEvent {
-                event: DeviceEvent::MouseMotion { delta, .. },
-                ..
-            } => {
-                if self.button_down && !self.cursor_left {
-                    let (x, y) = delta;
-                    self.motion_deltas.push((x.round() as _, y.round() as _));
-                    consume = true;
-                }
-            }
-            _ => {}
-        }
-        consume
-    }
-}
-
 #[derive(Default, Debug, Clone)]
 pub struct CameraViewportHandler;
 
@@ -800,33 +994,31 @@ impl<T> EventHandler<T> for CameraViewportHandler {
     }
 }
 
+/// Forwards events to the `App` camera controller and hides the cursor while dragging with the
+/// left mouse button. `F11` toggles fullscreen.
 #[derive(Debug, Clone)]
-pub struct ArcBallCameraControlHandler {
-    mouse_state: MouseState,
+pub struct CameraControlHandler {
     show_cursor_position: PhysicalPosition<f64>,
-    modifiers: winit::event::ModifiersState,
+    button_down: bool,
 }
 
-impl Default for ArcBallCameraControlHandler {
-    fn default() -> ArcBallCameraControlHandler {
-        ArcBallCameraControlHandler {
-            mouse_state: Default::default(),
+impl Default for CameraControlHandler {
+    fn default() -> CameraControlHandler {
+        CameraControlHandler {
             show_cursor_position: PhysicalPosition { x: 0.0, y: 0.0 },
-            modifiers: Default::default(),
+            button_down: false,
         }
     }
 }
 
-impl<T: 'static> EventHandler<T> for ArcBallCameraControlHandler {
+impl<T: 'static> EventHandler<T> for CameraControlHandler {
     fn on_event(&mut self, app: &mut App<T>, event: &Event<()>) -> bool {
-        let consume = self.mouse_state.on_event(app, event);
-
         match event {
             Event::WindowEvent {
                 event: WindowEvent::CursorMoved { position, .. },
                 ..
             } => {
-                if !self.mouse_state.button_down {
+                if !self.button_down {
                     self.show_cursor_position = *position;
                 }
             }
@@ -834,8 +1026,12 @@ impl<T: 'static> EventHandler<T> for ArcBallCameraControlHandler {
                 event: WindowEvent::MouseInput { button, state, .. },
                 ..
             } => match (button, state) {
-                (MouseButton::Left, ElementState::Pressed) => app.window.set_cursor_visible(false),
+                (MouseButton::Left, ElementState::Pressed) => {
+                    self.button_down = true;
+                    app.window.set_cursor_visible(false);
+                }
                 (MouseButton::Left, ElementState::Released) => {
+                    self.button_down = false;
                     app.window
                         .set_cursor_position(self.show_cursor_position)
                         .expect("failed to set cursor position");
@@ -843,115 +1039,30 @@ impl<T: 'static> EventHandler<T> for ArcBallCameraControlHandler {
                 }
                 _ => {}
             },
-            Event::WindowEvent {
-                event: WindowEvent::ModifiersChanged(modifiers),
-                ..
-            } => {
-                self.modifiers = modifiers.clone();
-            }
             Event::WindowEvent {
                 event:
                     WindowEvent::KeyboardInput {
                         input:
                             KeyboardInput {
                                 state: ElementState::Pressed,
-                                virtual_keycode: Some(virtual_keycode),
+                                virtual_keycode: Some(VirtualKeyCode::F11),
                                 ..
                             },
                         ..
                     },
                 ..
             } => {
-                let transform = app.camera.view.invert().unwrap();
-                let left = transform.transform_vector(Vector3::unit_x());
-                let forward = -left.cross(Vector3::unit_y());
-
-                match virtual_keycode {
-                    VirtualKeyCode::W => {
-                        app.camera.eye += forward;
-                        app.camera.center += forward;
-                    }
-                    VirtualKeyCode::S => {
-                        app.camera.eye -= forward;
-                        app.camera.center -= forward;
-                    }
-                    VirtualKeyCode::C => {
-                        app.camera.center = Point3::new(0.0, 0.0, 0.0);
-                    }
-                    VirtualKeyCode::A => {
-                        app.camera.eye -= left;
-                        app.camera.center -= left;
-                    }
-                    VirtualKeyCode::D => {
-                        app.camera.eye += left;
-                        app.camera.center += left;
-                    }
-                    VirtualKeyCode::PageUp => {
-                        app.camera.eye += Vector3::unit_y();
-                        if !self.modifiers.shift() {
-                            app.camera.center += Vector3::unit_y();
-                        }
-                    }
-                    VirtualKeyCode::PageDown => {
-                        app.camera.eye -= Vector3::unit_y();
-                        if !self.modifiers.shift() {
-                            app.camera.center -= Vector3::unit_y();
-                        }
-                    }
-                    VirtualKeyCode::F11 => {
-                        app.toggle_window_mode();
-                    }
-                    _ => {}
-                }
-
-                app.camera.update_view_matrix();
-            }
-            Event::WindowEvent {
-                event:
-                    WindowEvent::MouseWheel {
-                        delta: MouseScrollDelta::LineDelta(_, y),
-                        ..
-                    },
-                ..
-            } => {
-                let v: Vector3<f32> = app.camera.eye - app.camera.center;
-                let y = *y;
-                if v.magnitude() - y > 0.0 {
-                    let dir: Vector3<f32> = v.normalize();
-                    app.camera.eye += dir * -y;
-                    app.camera.update_view_matrix();
-                }
+                app.toggle_window_mode();
             }
             _ => {}
         }
 
-        for (dx, dy) in self.mouse_state.motion_deltas.drain(..) {
-            let (dx, dy) = (dx as f32, dy as f32);
-
-            let transform = app.camera.view.invert().unwrap();
-            let right = transform.transform_vector(Vector3::unit_x());
-            let forward = transform.transform_vector(-Vector3::unit_z());
-            let up = app.camera.up;
-
-            let angle = Deg::from(forward.angle(up)).0 + dy;
-            if angle < 179.0 && angle > 1.0 {
-                let m1 = Matrix4::from_axis_angle(up, Deg(-dx));
-                let m2 = Matrix4::from_axis_angle(right, Deg(-dy));
-
-                let center_offset: Vector3<f32> = app.camera.center - Point3::origin();
-
-                let mut eye = app.camera.eye - center_offset;
-
-                eye = m1.transform_point(eye);
-                eye = m2.transform_point(eye);
-
-                app.camera.eye = eye + center_offset;
-
-                app.camera.update_view_matrix();
-            }
-        }
+        app.camera_controller.on_event(&mut app.camera, event)
+    }
 
-        consume
+    fn on_frame(&mut self, app: &mut App<T>) {
+        let frame_time = app.frame_stats.last_frame_time().unwrap_or_default();
+        app.camera_controller.on_frame(&mut app.camera, frame_time);
     }
 }
 
@@ -967,9 +1078,18 @@ pub fn byte_offset<T: Copy>(count: usize) -> usize {
     std::mem::size_of::<T>() * count
 }
 
-pub fn byte_cast<T: Copy>(values: &[T]) -> &[u8] {
-    let len = byte_length(values) as usize;
-    unsafe { slice::from_raw_parts(values.as_ptr() as *const _, len) }
+/// Copies the elements of `data` into a byte vector with each element starting at a multiple of
+/// `stride`. Used for uniform data that is selected with dynamic offsets, where the stride is
+/// `vki::align_to(size_of::<T>(), limits.min_uniform_buffer_offset_alignment)`.
+pub fn pack_with_stride<T: Copy>(data: &[T], stride: usize) -> Vec<u8> {
+    let element_size = std::mem::size_of::<T>();
+    assert!(stride >= element_size, "stride is less than the element size");
+    let mut packed = vec![0u8; stride * data.len()];
+    for (i, element) in data.iter().enumerate() {
+        let bytes = unsafe { std::slice::from_raw_parts(element as *const T as *const u8, element_size) };
+        packed[i * stride..i * stride + element_size].copy_from_slice(bytes);
+    }
+    packed
 }
 
 pub fn to_float_secs(d: std::time::Duration) -> f32 {
@@ -982,6 +1102,9 @@ pub fn to_float_secs(d: std::time::Duration) -> f32 {
 ///
 /// Vulkan clip space has inverted Y and half Z.
 ///
+/// The Y inversion isn't needed when the render pass is created with
+/// `RenderPassDescriptor::flip_viewport`.
+///
 /// https://github.com/LunarG/VulkanSamples/commit/0dd36179880238014512c0637b0ba9f41febe803
 ///
 /// https://matthewwellings.com/blog/the-new-vulkan-coordinate-system/
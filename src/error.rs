@@ -127,11 +127,30 @@ impl Into<vk::Result> for Error {
         match self.kind() {
             ErrorKind::Message(_) => vk::Result::ERROR_VALIDATION_FAILED_EXT,
             ErrorKind::Code(code) => *code,
+            ErrorKind::OutOfMemory {
+                location: MemoryLocation::Host,
+                ..
+            } => vk::Result::ERROR_OUT_OF_HOST_MEMORY,
+            ErrorKind::OutOfMemory {
+                location: MemoryLocation::Device,
+                ..
+            } => vk::Result::ERROR_OUT_OF_DEVICE_MEMORY,
         }
     }
 }
 
 impl Error {
+    pub(crate) fn out_of_memory(location: MemoryLocation, requested: u64, budget: u64) -> Error {
+        Error {
+            kind: ErrorKind::OutOfMemory {
+                location,
+                requested,
+                budget,
+            },
+            backtrace: backtrace(),
+        }
+    }
+
     pub fn kind(&self) -> &ErrorKind {
         &self.kind
     }
@@ -154,11 +173,29 @@ impl Display for Error {
 pub enum ErrorKind {
     Code(vk::Result),
     Message(String),
+    /// A memory allocation failed.
+    OutOfMemory {
+        location: MemoryLocation,
+        /// The size of the failed allocation, in bytes.
+        requested: u64,
+        /// The combined size of the memory heaps at `location`, in bytes.
+        budget: u64,
+    },
+}
+
+/// The memory that was exhausted by a failed allocation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MemoryLocation {
+    Host,
+    Device,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum SwapchainError {
     OutOfDate,
+    /// The surface is no longer usable (e.g. after a driver reset or when the monitor was
+    /// disconnected). The surface must be re-created with `Instance::recreate_surface`.
+    SurfaceLost,
     Other(Error),
 }
 
@@ -166,6 +203,7 @@ impl From<SwapchainError> for Error {
     fn from(e: SwapchainError) -> Error {
         match e {
             SwapchainError::OutOfDate => Error::from(vk::Result::ERROR_OUT_OF_DATE_KHR),
+            SwapchainError::SurfaceLost => Error::from(vk::Result::ERROR_SURFACE_LOST_KHR),
             SwapchainError::Other(e) => e,
         }
     }
@@ -175,8 +213,10 @@ impl From<Error> for SwapchainError {
     fn from(e: Error) -> SwapchainError {
         match e.kind {
             ErrorKind::Code(vk::Result::ERROR_OUT_OF_DATE_KHR) => SwapchainError::OutOfDate,
+            ErrorKind::Code(vk::Result::ERROR_SURFACE_LOST_KHR) => SwapchainError::SurfaceLost,
             ErrorKind::Code(code) => SwapchainError::Other(Error::from(code)),
             ErrorKind::Message(msg) => SwapchainError::Other(Error::from(msg)),
+            ErrorKind::OutOfMemory { .. } => SwapchainError::Other(e),
         }
     }
 }
@@ -185,6 +225,7 @@ impl From<vk::Result> for SwapchainError {
     fn from(e: vk::Result) -> SwapchainError {
         match e {
             vk::Result::ERROR_OUT_OF_DATE_KHR => SwapchainError::OutOfDate,
+            vk::Result::ERROR_SURFACE_LOST_KHR => SwapchainError::SurfaceLost,
             code => SwapchainError::Other(Error::from(code)),
         }
     }
@@ -219,6 +260,7 @@ impl From<Error> for FenceError {
             ErrorKind::Code(vk::Result::TIMEOUT) => FenceError::Timeout,
             ErrorKind::Code(code) => FenceError::Other(Error::from(code)),
             ErrorKind::Message(msg) => FenceError::Other(Error::from(msg)),
+            ErrorKind::OutOfMemory { .. } => FenceError::Other(e),
         }
     }
 }
@@ -1,11 +1,16 @@
+use crate::imp::{pipeline, texture};
 use crate::imp::{AdapterInner, DeviceInner, InstanceInner, SurfaceInner};
-use crate::{Adapter, AdapterOptions, Device, DeviceDescriptor, Extensions, PowerPreference};
+use crate::{
+    Adapter, AdapterFeatures, AdapterGroup, AdapterOptions, Device, DeviceDescriptor, Extensions, PowerPreference,
+    ShaderStage, SubgroupOperations, SubgroupProperties, TextureFormat, TextureFormatFeatures, TextureUsage,
+    VertexFormat,
+};
 
 use crate::error::Error;
 
 use ash::vk;
 
-use std::ffi::CStr;
+use std::ffi::{c_void, CStr};
 use std::fmt::{self, Debug};
 use std::sync::Arc;
 
@@ -14,20 +19,116 @@ impl Adapter {
         &self.inner.name
     }
 
+    /// Returns the extensions supported by the adapter. Same as `supported_extensions`.
     pub fn extensions(&self) -> &Extensions {
         &self.inner.extensions
     }
 
+    /// Returns the extensions supported by the adapter, which may be requested with
+    /// `DeviceDescriptor::extensions`
+    pub fn supported_extensions(&self) -> Extensions {
+        self.inner.extensions.clone()
+    }
+
+    /// Returns the optional features supported by the adapter
+    pub fn supported_features(&self) -> AdapterFeatures {
+        self.inner.supported_features()
+    }
+
     pub fn properties(&self) -> AdapterProperties {
         self.inner.properties()
     }
 
+    /// Returns the identifiers of the adapter, which may be used to match it with adapters of
+    /// other APIs (e.g. DXGI) or with the display outputs of the system.
+    pub fn info(&self) -> AdapterInfo {
+        self.inner.info
+    }
+
+    /// Returns the features of a portability implementation (e.g. MoltenVK), or `None` if the
+    /// adapter is a fully conformant Vulkan implementation.
+    pub fn portability_features(&self) -> Option<PortabilityFeatures> {
+        self.inner.portability_features
+    }
+
+    /// Returns the memory heaps and memory types of the adapter
+    pub fn memory_properties(&self) -> MemoryProperties {
+        self.inner.memory_properties()
+    }
+
+    /// Returns the supported operations for optimally tiled textures of the given format
+    pub fn get_texture_format_features(&self, format: TextureFormat) -> TextureFormatFeatures {
+        self.inner.get_texture_format_features(format)
+    }
+
+    /// Returns `true` if the format can be used in a `VertexAttributeDescriptor`
+    pub fn supports_vertex_format(&self, format: VertexFormat) -> bool {
+        self.inner.supports_vertex_format(format)
+    }
+
+    /// Returns the sample counts, in ascending order, of 2D textures with the given format and
+    /// usage. The list is empty if the format does not support the usage.
+    ///
+    /// Use this to clamp a requested `sample_count` (e.g. `8`) to a count that the adapter
+    /// supports before creating a multisampled texture or swapchain.
+    pub fn supported_sample_counts(&self, format: TextureFormat, usage: TextureUsage) -> Vec<u32> {
+        self.inner.supported_sample_counts(format, usage)
+    }
+
+    /// Returns `true` if the color states of a render pipeline may differ in blending and write mask
+    pub fn supports_independent_blend(&self) -> bool {
+        self.inner.physical_device_features.independent_blend == vk::TRUE
+    }
+
+    /// Returns `true` if the device local memory is host visible (Resizable BAR). See
+    /// `MemoryHint::DeviceWithHostAccess`.
+    pub fn supports_resizable_bar(&self) -> bool {
+        self.inner.supports_resizable_bar()
+    }
+
+    /// Returns `true` if sparse textures may be created. See `Device::create_sparse_texture`.
+    pub fn supports_sparse_residency(&self) -> bool {
+        self.inner.supports_sparse_residency()
+    }
+
+    /// Returns `true` if `DeviceDescriptor::global_priority` is supported by the adapter.
+    pub fn supports_global_priority(&self) -> bool {
+        self.inner.supports_global_priority()
+    }
+
+    /// Returns the subgroup properties, or `None` if the adapter does not support Vulkan 1.1.
+    pub fn subgroup_properties(&self) -> Option<SubgroupProperties> {
+        self.inner.subgroup_properties
+    }
+
     pub fn create_device(&self, descriptor: DeviceDescriptor) -> Result<Device, Error> {
         let device = DeviceInner::new(self.inner.clone(), descriptor)?;
         Ok(device.into())
     }
 }
 
+impl AdapterGroup {
+    /// Returns the adapters of the group. The device index used in device masks corresponds to
+    /// the index of the adapter.
+    pub fn adapters(&self) -> &[Adapter] {
+        &self.adapters
+    }
+
+    /// Returns `true` if memory may be allocated on a subset of the adapters in the group
+    pub fn supports_subset_allocation(&self) -> bool {
+        self.subset_allocation
+    }
+
+    /// Creates a device that spans all of the adapters in the group. The extensions and surface
+    /// support are validated against the first adapter. See `Queue::submit_with_device_mask`.
+    pub fn create_device(&self, descriptor: DeviceDescriptor) -> Result<Device, Error> {
+        let physical_devices: Vec<_> = self.adapters.iter().map(|a| a.inner.physical_device).collect();
+        let device =
+            DeviceInner::new_with_physical_devices(self.adapters[0].inner.clone(), descriptor, &physical_devices)?;
+        Ok(device.into())
+    }
+}
+
 impl Into<Adapter> for AdapterInner {
     fn into(self) -> Adapter {
         Adapter { inner: Arc::new(self) }
@@ -35,9 +136,19 @@ impl Into<Adapter> for AdapterInner {
 }
 
 impl AdapterInner {
-    fn new(instance: &Arc<InstanceInner>, physical_device: vk::PhysicalDevice) -> Result<AdapterInner, Error> {
+    pub fn new(instance: &Arc<InstanceInner>, physical_device: vk::PhysicalDevice) -> Result<AdapterInner, Error> {
         let instance = Arc::clone(instance);
-        let (name, extensions, physical_device_features, physical_device_properties) = unsafe {
+        let (
+            name,
+            extensions,
+            physical_device_features,
+            physical_device_properties,
+            device_extension_names,
+            subgroup_properties,
+            driver_properties,
+            info,
+            portability_features,
+        ) = unsafe {
             let physical_device_properties = instance.raw.get_physical_device_properties(physical_device);
 
             let name = CStr::from_ptr(physical_device_properties.device_name.as_ptr())
@@ -57,7 +168,7 @@ impl AdapterInner {
                 physical_device_properties.vendor_id,
             );
 
-            // TODO: capture these
+            let mut device_extension_names = Vec::new();
             for p in instance
                 .raw
                 .enumerate_device_extension_properties(physical_device)?
@@ -65,6 +176,7 @@ impl AdapterInner {
             {
                 let name = CStr::from_ptr(p.extension_name.as_ptr());
                 log::debug!("found physical device extension: {}", name.to_string_lossy());
+                device_extension_names.push(name.to_owned());
             }
 
             // TODO: capture these
@@ -92,10 +204,67 @@ impl AdapterInner {
             }
 
             let physical_device_features = instance.raw.get_physical_device_features(physical_device);
+            let has_device_extension = |name: &CStr| {
+                device_extension_names
+                    .iter()
+                    .any(|extension_name| extension_name.as_c_str() == name)
+            };
+            let subgroup_size_control = has_device_extension(vk::ExtSubgroupSizeControlFn::name());
+            let subgroup_properties = get_subgroup_properties(
+                &instance,
+                physical_device,
+                &physical_device_properties,
+                subgroup_size_control,
+            );
+            let extension_features = get_extension_features(&instance, physical_device, &has_device_extension);
+            let info = get_adapter_info(
+                &instance,
+                physical_device,
+                &physical_device_properties,
+                has_device_extension(vk::ExtPciBusInfoFn::name()),
+            );
+            let portability_features = extension_features.portability_subset;
+            if let Some(portability_features) = portability_features.as_ref() {
+                log::warn!(
+                    "portability subset adapter: {:?}; unsupported features: {:?}",
+                    name,
+                    portability_features.unsupported_features()
+                );
+            }
+            let driver_properties = if has_device_extension(vk::KhrDriverPropertiesFn::name()) {
+                get_driver_properties(&instance, physical_device)
+            } else {
+                None
+            };
             let extensions = Extensions {
                 anisotropic_filtering: physical_device_features.sampler_anisotropy == vk::TRUE,
+                texture_compression_bc: physical_device_features.texture_compression_bc == vk::TRUE,
+                texture_compression_etc2: physical_device_features.texture_compression_etc2 == vk::TRUE,
+                texture_compression_astc_ldr: physical_device_features.texture_compression_astc_ldr == vk::TRUE,
+                timeline_semaphore: has_device_extension(vk::KhrTimelineSemaphoreFn::name()),
+                transform_feedback: has_device_extension(vk::ExtTransformFeedbackFn::name()),
+                subgroup_size_control: subgroup_size_control && subgroup_properties.is_some(),
+                shader_float16: extension_features.shader_float16,
+                shader_int8: extension_features.shader_int8,
+                storage_buffer_8bit_access: extension_features.storage_buffer_8bit_access,
+                storage_buffer_16bit_access: extension_features.storage_buffer_16bit_access,
+                index_type_uint8: extension_features.index_type_uint8,
+                vertex_attribute_divisor: extension_features.vertex_attribute_divisor,
+                pipeline_creation_feedback: has_device_extension(vk::ExtPipelineCreationFeedbackFn::name()),
+                swapchain_mutable_format: has_device_extension(vk::KhrSwapchainMutableFormatFn::name())
+                    && has_device_extension(vk::KhrImageFormatListFn::name()),
             };
-            (name, extensions, physical_device_features, physical_device_properties)
+            (
+                name,
+                extensions,
+                physical_device_features,
+                physical_device_properties,
+                device_extension_names,
+                subgroup_properties,
+                driver_properties,
+                info,
+                portability_features,
+            )
         };
 
         let mut physical_device_format_properties = Vec::new();
@@ -109,6 +278,9 @@ impl AdapterInner {
         }
         physical_device_format_properties.sort_by(|(a, _), (b, _)| a.cmp(&b));
 
+        let physical_device_memory_properties =
+            unsafe { instance.raw.get_physical_device_memory_properties(physical_device) };
+
         let queue_family_properties = unsafe {
             instance
                 .raw
@@ -122,7 +294,13 @@ impl AdapterInner {
             physical_device_features,
             physical_device_properties,
             physical_device_format_properties,
+            physical_device_memory_properties,
             queue_family_properties,
+            device_extension_names,
+            subgroup_properties,
+            driver_properties,
+            info,
+            portability_features,
             extensions,
         })
     }
@@ -214,6 +392,124 @@ impl AdapterInner {
         }
     }
 
+    pub fn get_texture_format_features(&self, format: TextureFormat) -> TextureFormatFeatures {
+        let format = texture::image_format(format);
+        match self
+            .physical_device_format_properties
+            .binary_search_by(|(f, _)| f.cmp(&format))
+        {
+            Ok(index) => {
+                let (_, format_properties) = self.physical_device_format_properties[index];
+                texture::texture_format_features(format_properties.optimal_tiling_features)
+            }
+            Err(_) => TextureFormatFeatures::NONE,
+        }
+    }
+
+    pub fn supports_vertex_format(&self, format: VertexFormat) -> bool {
+        let format = pipeline::vertex_format(format);
+        match self
+            .physical_device_format_properties
+            .binary_search_by(|(f, _)| f.cmp(&format))
+        {
+            Ok(index) => {
+                let (_, format_properties) = self.physical_device_format_properties[index];
+                format_properties
+                    .buffer_features
+                    .contains(vk::FormatFeatureFlags::VERTEX_BUFFER)
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn supports_sparse_residency(&self) -> bool {
+        let queue_flags = vk::QueueFlags::GRAPHICS | vk::QueueFlags::SPARSE_BINDING;
+        self.physical_device_features.sparse_binding == vk::TRUE
+            && self.physical_device_features.sparse_residency_image2_d == vk::TRUE
+            && self
+                .queue_family_properties
+                .iter()
+                .any(|queue_family| queue_family.queue_flags.contains(queue_flags))
+    }
+
+    pub fn supported_features(&self) -> AdapterFeatures {
+        let features = &self.physical_device_features;
+        let max_anisotropy = if self.extensions.anisotropic_filtering {
+            self.physical_device_properties.limits.max_sampler_anisotropy as u32
+        } else {
+            1
+        };
+        AdapterFeatures {
+            fill_mode_non_solid: features.fill_mode_non_solid == vk::TRUE,
+            independent_blend: features.independent_blend == vk::TRUE,
+            depth_bias_clamp: features.depth_bias_clamp == vk::TRUE,
+            storage_image_write_without_format: features.shader_storage_image_write_without_format == vk::TRUE,
+            sparse_residency: self.supports_sparse_residency(),
+            global_priority: self.supports_global_priority(),
+            resizable_bar: self.supports_resizable_bar(),
+            negative_viewport_height: self.supports_maintenance1(),
+            draw_indirect_first_instance: features.draw_indirect_first_instance == vk::TRUE,
+            shader_draw_parameters: self.supports_shader_draw_parameters(),
+            max_anisotropy,
+        }
+    }
+
+    pub fn supported_sample_counts(&self, format: TextureFormat, usage: TextureUsage) -> Vec<u32> {
+        let properties = unsafe {
+            self.instance.raw.get_physical_device_image_format_properties(
+                self.physical_device,
+                texture::image_format(format),
+                vk::ImageType::TYPE_2D,
+                vk::ImageTiling::OPTIMAL,
+                texture::image_usage(usage, format),
+                vk::ImageCreateFlags::empty(),
+            )
+        };
+        match properties {
+            // The value of each `vk::SampleCountFlags` bit is the sample count
+            Ok(properties) => [1, 2, 4, 8, 16, 32, 64]
+                .iter()
+                .cloned()
+                .filter(|&count| properties.sample_counts.contains(vk::SampleCountFlags::from_raw(count)))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn supports_global_priority(&self) -> bool {
+        let global_priority = vk::ExtGlobalPriorityFn::name();
+        self.device_extension_names
+            .iter()
+            .any(|name| name.as_c_str() == global_priority)
+    }
+
+    pub fn supports_maintenance1(&self) -> bool {
+        let maintenance1 = vk::KhrMaintenance1Fn::name();
+        self.device_extension_names
+            .iter()
+            .any(|name| name.as_c_str() == maintenance1)
+    }
+
+    pub fn supports_shader_draw_parameters(&self) -> bool {
+        let shader_draw_parameters = vk::KhrShaderDrawParametersFn::name();
+        self.device_extension_names
+            .iter()
+            .any(|name| name.as_c_str() == shader_draw_parameters)
+    }
+
+    pub fn supports_resizable_bar(&self) -> bool {
+        let memory_properties = &self.physical_device_memory_properties;
+        let host_visible_device_local = vk::MemoryPropertyFlags::DEVICE_LOCAL | vk::MemoryPropertyFlags::HOST_VISIBLE;
+
+        // Without Resizable BAR, the host visible device local memory (if any) is limited to a
+        // 256 MiB heap that's separate from the main device local heap.
+        memory_properties.memory_types[..memory_properties.memory_type_count as usize]
+            .iter()
+            .filter(|memory_type| memory_type.property_flags.contains(host_visible_device_local))
+            .map(|memory_type| memory_properties.memory_heaps[memory_type.heap_index as usize].size)
+            .any(|heap_size| heap_size > 256 * 1024 * 1024)
+    }
+
     pub fn properties(&self) -> AdapterProperties {
         let device_name = unsafe {
             std::ffi::CStr::from_ptr(self.physical_device_properties.device_name.as_ptr())
@@ -228,10 +524,353 @@ impl AdapterInner {
             device_type: self.physical_device_properties.device_type,
             device_name,
             limits: self.physical_device_properties.limits,
+            sparse_properties: self.physical_device_properties.sparse_properties,
+            driver: self.driver_properties.as_ref(),
+        }
+    }
+
+    pub fn memory_properties(&self) -> MemoryProperties {
+        let memory_properties = &self.physical_device_memory_properties;
+        let memory_heaps = memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize]
+            .iter()
+            .map(|memory_heap| MemoryHeap {
+                size: memory_heap.size,
+                flags: memory_heap.flags,
+            })
+            .collect();
+        let memory_types = memory_properties.memory_types[..memory_properties.memory_type_count as usize]
+            .iter()
+            .map(|memory_type| MemoryType {
+                heap_index: memory_type.heap_index,
+                property_flags: memory_type.property_flags,
+            })
+            .collect();
+        MemoryProperties {
+            memory_heaps,
+            memory_types,
         }
     }
 }
 
+/// Queries the device identifiers. Requires a Vulkan 1.1 device and
+/// `VK_KHR_get_physical_device_properties2`. The PCI bus info requires `VK_EXT_pci_bus_info`.
+unsafe fn get_adapter_info(
+    instance: &InstanceInner,
+    physical_device: vk::PhysicalDevice,
+    physical_device_properties: &vk::PhysicalDeviceProperties,
+    pci_bus_info: bool,
+) -> AdapterInfo {
+    let mut info = AdapterInfo::default();
+
+    let get_physical_device_properties2 = match instance.raw_ext.get_physical_device_properties2.as_ref() {
+        Some(get_physical_device_properties2) => get_physical_device_properties2,
+        None => return info,
+    };
+
+    let has_id_properties = physical_device_properties.api_version >= vk::make_api_version(0, 1, 1, 0);
+    if !(has_id_properties || pci_bus_info) {
+        return info;
+    }
+
+    let mut id_properties = vk::PhysicalDeviceIDProperties::default();
+    let mut pci_bus_info_properties = vk::PhysicalDevicePCIBusInfoPropertiesEXT::default();
+
+    let mut properties2 = vk::PhysicalDeviceProperties2::builder();
+    if has_id_properties {
+        properties2 = properties2.push_next(&mut id_properties);
+    }
+    if pci_bus_info {
+        properties2 = properties2.push_next(&mut pci_bus_info_properties);
+    }
+
+    get_physical_device_properties2.get_physical_device_properties2_khr(physical_device, &mut properties2);
+
+    if has_id_properties {
+        info.device_uuid = Some(id_properties.device_uuid);
+        info.driver_uuid = Some(id_properties.driver_uuid);
+        if id_properties.device_luid_valid == vk::TRUE {
+            info.device_luid = Some(id_properties.device_luid);
+            info.device_node_mask = id_properties.device_node_mask;
+        }
+    }
+
+    if pci_bus_info {
+        info.pci_bus_info = Some(PciBusInfo {
+            domain: pci_bus_info_properties.pci_domain,
+            bus: pci_bus_info_properties.pci_bus,
+            device: pci_bus_info_properties.pci_device,
+            function: pci_bus_info_properties.pci_function,
+        });
+    }
+
+    log::debug!("adapter info: {:?}", info);
+
+    info
+}
+
+/// Queries the driver properties. Requires `VK_KHR_driver_properties` and
+/// `VK_KHR_get_physical_device_properties2`.
+unsafe fn get_driver_properties(
+    instance: &InstanceInner,
+    physical_device: vk::PhysicalDevice,
+) -> Option<DriverProperties> {
+    let get_physical_device_properties2 = instance.raw_ext.get_physical_device_properties2.as_ref()?;
+
+    let mut driver_properties = vk::PhysicalDeviceDriverProperties::default();
+    let mut properties2 = vk::PhysicalDeviceProperties2::builder().push_next(&mut driver_properties);
+
+    get_physical_device_properties2.get_physical_device_properties2_khr(physical_device, &mut properties2);
+
+    let conformance_version = driver_properties.conformance_version;
+    let properties = DriverProperties {
+        driver_id: driver_properties.driver_id,
+        driver_name: CStr::from_ptr(driver_properties.driver_name.as_ptr())
+            .to_string_lossy()
+            .into_owned(),
+        driver_info: CStr::from_ptr(driver_properties.driver_info.as_ptr())
+            .to_string_lossy()
+            .into_owned(),
+        conformance_version: (
+            conformance_version.major,
+            conformance_version.minor,
+            conformance_version.subminor,
+            conformance_version.patch,
+        ),
+    };
+
+    log::debug!("driver properties: {:?}", properties);
+
+    Some(properties)
+}
+
+/// Queries the subgroup properties. Requires a Vulkan 1.1 device and
+/// `VK_KHR_get_physical_device_properties2`.
+unsafe fn get_subgroup_properties(
+    instance: &InstanceInner,
+    physical_device: vk::PhysicalDevice,
+    physical_device_properties: &vk::PhysicalDeviceProperties,
+    subgroup_size_control: bool,
+) -> Option<SubgroupProperties> {
+    let get_physical_device_properties2 = instance.raw_ext.get_physical_device_properties2.as_ref()?;
+
+    if physical_device_properties.api_version < vk::make_api_version(0, 1, 1, 0) {
+        return None;
+    }
+
+    let mut subgroup_properties = vk::PhysicalDeviceSubgroupProperties::default();
+    let mut size_control_properties = vk::PhysicalDeviceSubgroupSizeControlPropertiesEXT::default();
+
+    if subgroup_size_control {
+        subgroup_properties.p_next = &mut size_control_properties as *mut _ as *mut c_void;
+    }
+
+    let mut properties2 = vk::PhysicalDeviceProperties2 {
+        p_next: &mut subgroup_properties as *mut _ as *mut c_void,
+        ..Default::default()
+    };
+
+    get_physical_device_properties2.get_physical_device_properties2_khr(physical_device, &mut properties2);
+
+    let (min_subgroup_size, max_subgroup_size, required_subgroup_size_stages) = if subgroup_size_control {
+        (
+            size_control_properties.min_subgroup_size,
+            size_control_properties.max_subgroup_size,
+            shader_stage(size_control_properties.required_subgroup_size_stages),
+        )
+    } else {
+        (
+            subgroup_properties.subgroup_size,
+            subgroup_properties.subgroup_size,
+            ShaderStage::NONE,
+        )
+    };
+
+    let properties = SubgroupProperties {
+        subgroup_size: subgroup_properties.subgroup_size,
+        min_subgroup_size,
+        max_subgroup_size,
+        supported_stages: shader_stage(subgroup_properties.supported_stages),
+        supported_operations: SubgroupOperations::from_bits_truncate(subgroup_properties.supported_operations.as_raw()),
+        quad_operations_in_all_stages: subgroup_properties.quad_operations_in_all_stages == vk::TRUE,
+        required_subgroup_size_stages,
+    };
+
+    log::debug!("subgroup properties: {:?}", properties);
+
+    Some(properties)
+}
+
+#[derive(Debug, Default)]
+struct ExtensionFeatures {
+    shader_float16: bool,
+    shader_int8: bool,
+    storage_buffer_8bit_access: bool,
+    storage_buffer_16bit_access: bool,
+    index_type_uint8: bool,
+    vertex_attribute_divisor: bool,
+    portability_subset: Option<PortabilityFeatures>,
+}
+
+/// Queries the features of the device extensions. Requires `VK_KHR_get_physical_device_properties2`.
+unsafe fn get_extension_features(
+    instance: &InstanceInner,
+    physical_device: vk::PhysicalDevice,
+    has_device_extension: &dyn Fn(&CStr) -> bool,
+) -> ExtensionFeatures {
+    let get_physical_device_properties2 = match instance.raw_ext.get_physical_device_properties2.as_ref() {
+        Some(get_physical_device_properties2) => get_physical_device_properties2,
+        None => return ExtensionFeatures::default(),
+    };
+
+    let has_float16_int8 = has_device_extension(vk::KhrShaderFloat16Int8Fn::name());
+    let has_8bit_storage = has_device_extension(vk::Khr8bitStorageFn::name());
+    let has_16bit_storage = has_device_extension(vk::Khr16bitStorageFn::name());
+    let has_index_type_uint8 = has_device_extension(vk::ExtIndexTypeUint8Fn::name());
+    let has_vertex_attribute_divisor = has_device_extension(vk::ExtVertexAttributeDivisorFn::name());
+    let has_portability_subset = has_device_extension(vk::KhrPortabilitySubsetFn::name());
+
+    let mut float16_int8_features = vk::PhysicalDeviceShaderFloat16Int8Features::default();
+    let mut storage_8bit_features = vk::PhysicalDevice8BitStorageFeatures::default();
+    let mut storage_16bit_features = vk::PhysicalDevice16BitStorageFeatures::default();
+    let mut index_type_uint8_features = vk::PhysicalDeviceIndexTypeUint8FeaturesEXT::default();
+    let mut vertex_attribute_divisor_features = vk::PhysicalDeviceVertexAttributeDivisorFeaturesEXT::default();
+    let mut portability_subset_features = vk::PhysicalDevicePortabilitySubsetFeaturesKHR::default();
+
+    let mut features2 = vk::PhysicalDeviceFeatures2::builder();
+    if has_float16_int8 {
+        features2 = features2.push_next(&mut float16_int8_features);
+    }
+    if has_8bit_storage {
+        features2 = features2.push_next(&mut storage_8bit_features);
+    }
+    if has_16bit_storage {
+        features2 = features2.push_next(&mut storage_16bit_features);
+    }
+    if has_index_type_uint8 {
+        features2 = features2.push_next(&mut index_type_uint8_features);
+    }
+    if has_vertex_attribute_divisor {
+        features2 = features2.push_next(&mut vertex_attribute_divisor_features);
+    }
+    if has_portability_subset {
+        features2 = features2.push_next(&mut portability_subset_features);
+    }
+
+    get_physical_device_properties2.get_physical_device_features2_khr(physical_device, &mut *features2);
+
+    ExtensionFeatures {
+        shader_float16: has_float16_int8 && float16_int8_features.shader_float16 == vk::TRUE,
+        shader_int8: has_float16_int8 && float16_int8_features.shader_int8 == vk::TRUE,
+        storage_buffer_8bit_access: has_8bit_storage && storage_8bit_features.storage_buffer8_bit_access == vk::TRUE,
+        storage_buffer_16bit_access: has_16bit_storage
+            && storage_16bit_features.storage_buffer16_bit_access == vk::TRUE,
+        index_type_uint8: has_index_type_uint8 && index_type_uint8_features.index_type_uint8 == vk::TRUE,
+        vertex_attribute_divisor: has_vertex_attribute_divisor
+            && vertex_attribute_divisor_features.vertex_attribute_instance_rate_divisor == vk::TRUE,
+        portability_subset: if has_portability_subset {
+            Some(PortabilityFeatures::from(&portability_subset_features))
+        } else {
+            None
+        },
+    }
+}
+
+impl<'a> From<&'a vk::PhysicalDevicePortabilitySubsetFeaturesKHR> for PortabilityFeatures {
+    fn from(features: &'a vk::PhysicalDevicePortabilitySubsetFeaturesKHR) -> PortabilityFeatures {
+        PortabilityFeatures {
+            constant_alpha_color_blend_factors: features.constant_alpha_color_blend_factors == vk::TRUE,
+            events: features.events == vk::TRUE,
+            image_view_format_reinterpretation: features.image_view_format_reinterpretation == vk::TRUE,
+            image_view_format_swizzle: features.image_view_format_swizzle == vk::TRUE,
+            image_view2_d_on3_d_image: features.image_view2_d_on3_d_image == vk::TRUE,
+            multisample_array_image: features.multisample_array_image == vk::TRUE,
+            mutable_comparison_samplers: features.mutable_comparison_samplers == vk::TRUE,
+            point_polygons: features.point_polygons == vk::TRUE,
+            sampler_mip_lod_bias: features.sampler_mip_lod_bias == vk::TRUE,
+            separate_stencil_mask_ref: features.separate_stencil_mask_ref == vk::TRUE,
+            shader_sample_rate_interpolation_functions: features.shader_sample_rate_interpolation_functions == vk::TRUE,
+            tessellation_isolines: features.tessellation_isolines == vk::TRUE,
+            tessellation_point_mode: features.tessellation_point_mode == vk::TRUE,
+            triangle_fans: features.triangle_fans == vk::TRUE,
+            vertex_attribute_access_beyond_stride: features.vertex_attribute_access_beyond_stride == vk::TRUE,
+        }
+    }
+}
+
+impl PortabilityFeatures {
+    /// Returns the names of the features that are not supported by the adapter
+    pub fn unsupported_features(&self) -> Vec<&'static str> {
+        let features = [
+            (
+                "constant_alpha_color_blend_factors",
+                self.constant_alpha_color_blend_factors,
+            ),
+            ("events", self.events),
+            (
+                "image_view_format_reinterpretation",
+                self.image_view_format_reinterpretation,
+            ),
+            ("image_view_format_swizzle", self.image_view_format_swizzle),
+            ("image_view2_d_on3_d_image", self.image_view2_d_on3_d_image),
+            ("multisample_array_image", self.multisample_array_image),
+            ("mutable_comparison_samplers", self.mutable_comparison_samplers),
+            ("point_polygons", self.point_polygons),
+            ("sampler_mip_lod_bias", self.sampler_mip_lod_bias),
+            ("separate_stencil_mask_ref", self.separate_stencil_mask_ref),
+            (
+                "shader_sample_rate_interpolation_functions",
+                self.shader_sample_rate_interpolation_functions,
+            ),
+            ("tessellation_isolines", self.tessellation_isolines),
+            ("tessellation_point_mode", self.tessellation_point_mode),
+            ("triangle_fans", self.triangle_fans),
+            (
+                "vertex_attribute_access_beyond_stride",
+                self.vertex_attribute_access_beyond_stride,
+            ),
+        ];
+        features
+            .iter()
+            .filter(|(_, supported)| !supported)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    pub(crate) fn to_vk(&self) -> vk::PhysicalDevicePortabilitySubsetFeaturesKHR {
+        vk::PhysicalDevicePortabilitySubsetFeaturesKHR::builder()
+            .constant_alpha_color_blend_factors(self.constant_alpha_color_blend_factors)
+            .events(self.events)
+            .image_view_format_reinterpretation(self.image_view_format_reinterpretation)
+            .image_view_format_swizzle(self.image_view_format_swizzle)
+            .image_view2_d_on3_d_image(self.image_view2_d_on3_d_image)
+            .multisample_array_image(self.multisample_array_image)
+            .mutable_comparison_samplers(self.mutable_comparison_samplers)
+            .point_polygons(self.point_polygons)
+            .sampler_mip_lod_bias(self.sampler_mip_lod_bias)
+            .separate_stencil_mask_ref(self.separate_stencil_mask_ref)
+            .shader_sample_rate_interpolation_functions(self.shader_sample_rate_interpolation_functions)
+            .tessellation_isolines(self.tessellation_isolines)
+            .tessellation_point_mode(self.tessellation_point_mode)
+            .triangle_fans(self.triangle_fans)
+            .vertex_attribute_access_beyond_stride(self.vertex_attribute_access_beyond_stride)
+            .build()
+    }
+}
+
+fn shader_stage(flags: vk::ShaderStageFlags) -> ShaderStage {
+    let mut stage = ShaderStage::NONE;
+    if flags.contains(vk::ShaderStageFlags::VERTEX) {
+        stage |= ShaderStage::VERTEX;
+    }
+    if flags.contains(vk::ShaderStageFlags::FRAGMENT) {
+        stage |= ShaderStage::FRAGMENT;
+    }
+    if flags.contains(vk::ShaderStageFlags::COMPUTE) {
+        stage |= ShaderStage::COMPUTE;
+    }
+    stage
+}
+
 fn version(v: u32) -> (u32, u32, u32) {
     (
         vk::api_version_major(v),
@@ -249,6 +888,83 @@ pub struct AdapterProperties<'a> {
     pub device_id: u32,
     pub device_type: vk::PhysicalDeviceType,
     pub limits: vk::PhysicalDeviceLimits,
+    pub sparse_properties: vk::PhysicalDeviceSparseProperties,
+    /// The driver properties, or `None` if `VK_KHR_driver_properties` is not supported.
+    pub driver: Option<&'a DriverProperties>,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct AdapterInfo {
+    /// Universally unique identifier of the device, or `None` if the device does not support Vulkan 1.1
+    pub device_uuid: Option<[u8; vk::UUID_SIZE]>,
+    /// Universally unique identifier of the driver build, or `None` if the device does not support Vulkan 1.1
+    pub driver_uuid: Option<[u8; vk::UUID_SIZE]>,
+    /// Locally unique identifier of the device (Windows only). Matches `DXGI_ADAPTER_DESC1::AdapterLuid`.
+    pub device_luid: Option<[u8; vk::LUID_SIZE]>,
+    /// The node of the linked device adapter that the device corresponds to. Only valid when
+    /// `device_luid` is `Some`.
+    pub device_node_mask: u32,
+    /// The PCI bus location of the device, or `None` if `VK_EXT_pci_bus_info` is not supported.
+    pub pci_bus_info: Option<PciBusInfo>,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PciBusInfo {
+    pub domain: u32,
+    pub bus: u32,
+    pub device: u32,
+    pub function: u32,
+}
+
+/// The features of a portability implementation such as MoltenVK (`VK_KHR_portability_subset`).
+/// Features that are `false` are not available on the adapter.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct PortabilityFeatures {
+    pub constant_alpha_color_blend_factors: bool,
+    pub events: bool,
+    pub image_view_format_reinterpretation: bool,
+    pub image_view_format_swizzle: bool,
+    pub image_view2_d_on3_d_image: bool,
+    pub multisample_array_image: bool,
+    pub mutable_comparison_samplers: bool,
+    pub point_polygons: bool,
+    pub sampler_mip_lod_bias: bool,
+    pub separate_stencil_mask_ref: bool,
+    pub shader_sample_rate_interpolation_functions: bool,
+    pub tessellation_isolines: bool,
+    pub tessellation_point_mode: bool,
+    pub triangle_fans: bool,
+    pub vertex_attribute_access_beyond_stride: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriverProperties {
+    pub driver_id: vk::DriverId,
+    pub driver_name: String,
+    pub driver_info: String,
+    /// The version of the Vulkan conformance test suite that the driver passed
+    /// (`major`, `minor`, `subminor`, `patch`)
+    pub conformance_version: (u8, u8, u8, u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryProperties {
+    pub memory_heaps: Vec<MemoryHeap>,
+    pub memory_types: Vec<MemoryType>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryHeap {
+    /// The size of the heap, in bytes
+    pub size: u64,
+    pub flags: vk::MemoryHeapFlags,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MemoryType {
+    /// The index into `MemoryProperties::memory_heaps`
+    pub heap_index: u32,
+    pub property_flags: vk::MemoryPropertyFlags,
 }
 
 impl<'a> AdapterProperties<'a> {
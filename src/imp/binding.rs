@@ -4,8 +4,8 @@ use crate::error::Error;
 use crate::imp::fenced_deleter::DeleteWhenUnused;
 use crate::imp::{BindGroupInner, BindGroupLayoutInner, DeviceInner};
 use crate::{
-    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
-    BindingResource, BindingType, ShaderStage,
+    align_to, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Buffer, CompareFunction, ShaderStage, TextureFormatFeatures,
 };
 
 use std::collections::HashMap;
@@ -13,7 +13,7 @@ use std::sync::Arc;
 
 pub fn descriptor_type(binding_type: BindingType) -> vk::DescriptorType {
     match binding_type {
-        BindingType::Sampler => vk::DescriptorType::SAMPLER,
+        BindingType::Sampler | BindingType::ComparisonSampler => vk::DescriptorType::SAMPLER,
         BindingType::DynamicStorageBuffer => vk::DescriptorType::STORAGE_BUFFER_DYNAMIC,
         BindingType::SampledTexture => vk::DescriptorType::SAMPLED_IMAGE,
         BindingType::DynamicUniformBuffer => vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
@@ -38,8 +38,85 @@ pub fn shader_stage_flags(visibility: ShaderStage) -> vk::ShaderStageFlags {
     flags
 }
 
+fn is_storage_texture(binding_type: BindingType) -> bool {
+    match binding_type {
+        BindingType::ReadOnlyStorageTexture | BindingType::WriteOnlyStorageTexture => true,
+        _ => false,
+    }
+}
+
+fn validate_storage_texture_format(device: &DeviceInner, entry: &BindGroupLayoutEntry) -> Result<(), Error> {
+    match (is_storage_texture(entry.binding_type), entry.storage_texture_format) {
+        (true, Some(format)) => {
+            let features = device.adapter.get_texture_format_features(format);
+            if !features.contains(TextureFormatFeatures::STORAGE) {
+                log::error!("storage textures are not supported for format: {:?}", format);
+                return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+            }
+        }
+        (true, None) => {
+            log::error!(
+                "storage texture bindings require a storage_texture_format (binding: {})",
+                entry.binding
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+        (false, Some(_)) => {
+            log::error!(
+                "storage_texture_format is only valid for storage texture bindings (binding: {})",
+                entry.binding
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+        (false, None) => {}
+    }
+    Ok(())
+}
+
+fn is_buffer(binding_type: BindingType) -> bool {
+    match binding_type {
+        BindingType::UniformBuffer
+        | BindingType::DynamicUniformBuffer
+        | BindingType::StorageBuffer
+        | BindingType::DynamicStorageBuffer => true,
+        _ => false,
+    }
+}
+
+fn validate_min_binding_size(entry: &BindGroupLayoutEntry) -> Result<(), Error> {
+    if entry.min_binding_size.is_some() && !is_buffer(entry.binding_type) {
+        log::error!(
+            "min_binding_size is only valid for buffer bindings (binding: {})",
+            entry.binding
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+    Ok(())
+}
+
+/// Returns the buffer, offset, and size of a buffer binding resource. A `BufferBinding` without
+/// a size binds the rest of the buffer after the offset.
+pub fn buffer_binding_region(resource: &BindingResource) -> Option<(&Buffer, usize, usize)> {
+    match resource {
+        BindingResource::Buffer(buffer, range) => Some((buffer, range.start, range.end.saturating_sub(range.start))),
+        BindingResource::BufferBinding(binding) => {
+            let size = match binding.size {
+                Some(size) => size.get() as usize,
+                None => binding.buffer.size().saturating_sub(binding.offset),
+            };
+            Some((&binding.buffer, binding.offset, size))
+        }
+        _ => None,
+    }
+}
+
 impl BindGroupLayoutInner {
     pub fn new(device: Arc<DeviceInner>, descriptor: BindGroupLayoutDescriptor) -> Result<BindGroupLayoutInner, Error> {
+        for entry in descriptor.entries.iter() {
+            validate_storage_texture_format(&device, entry)?;
+            validate_min_binding_size(entry)?;
+        }
+
         let bindings: Vec<_> = descriptor
             .entries
             .iter()
@@ -55,7 +132,11 @@ impl BindGroupLayoutInner {
 
         let create_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
 
-        let handle = unsafe { device.raw.create_descriptor_set_layout(&create_info, None)? };
+        let handle = unsafe {
+            device
+                .raw
+                .create_descriptor_set_layout(&create_info, device.allocation_callbacks())?
+        };
 
         Ok(BindGroupLayoutInner {
             handle,
@@ -63,6 +144,27 @@ impl BindGroupLayoutInner {
             layout_bindings: descriptor.entries.to_vec(),
         })
     }
+
+    /// Layouts are compatible if they are the same layout or if they were created by the same
+    /// device with identically defined entries. Only the properties of the entries that define
+    /// the underlying descriptor set layout are compared, and the order of the entries is ignored.
+    pub fn is_compatible_with(&self, other: &BindGroupLayoutInner) -> bool {
+        if self.handle == other.handle {
+            return true;
+        }
+
+        if self.device != other.device || self.layout_bindings.len() != other.layout_bindings.len() {
+            return false;
+        }
+
+        self.layout_bindings.iter().all(|entry| {
+            other.layout_bindings.iter().any(|other_entry| {
+                entry.binding == other_entry.binding
+                    && entry.visibility == other_entry.visibility
+                    && entry.binding_type == other_entry.binding_type
+            })
+        })
+    }
 }
 
 impl Drop for BindGroupLayoutInner {
@@ -79,6 +181,16 @@ impl Into<BindGroupLayout> for BindGroupLayoutInner {
     }
 }
 
+impl BindGroupLayout {
+    /// Returns `true` if bind groups created with either layout can be used interchangeably.
+    ///
+    /// Bind groups that were set prior to changing pipelines remain bound if the bind group
+    /// layouts at the same index (and all lower indices) of the pipeline layouts are compatible.
+    pub fn is_compatible_with(&self, other: &BindGroupLayout) -> bool {
+        self.inner.is_compatible_with(&other.inner)
+    }
+}
+
 /// Finds the corresponding `BindGroupLayoutBinding`. The `bind_group_binding_descriptor_index` identifies
 /// the index in `BindGroupDescriptor::bindings`. If the corresponding binding is found at this index
 /// in `layout_bindings`, it's returned. Otherwise, a linear search is performed.
@@ -136,7 +248,7 @@ impl BindGroupInner {
             let result = device.raw.fp_v1_0().create_descriptor_pool(
                 device.raw.handle(),
                 &create_info,
-                std::ptr::null(),
+                device.allocation_callbacks_ptr(),
                 &mut bind_group.descriptor_pool,
             );
             if result != vk::Result::SUCCESS {
@@ -189,16 +301,52 @@ impl BindGroupInner {
             write.descriptor_type = descriptor_type(layout_binding.binding_type);
 
             match (&binding.resource, layout_binding.binding_type) {
-                (&BindingResource::Buffer(ref buffer, ref range), BindingType::UniformBuffer)
-                | (&BindingResource::Buffer(ref buffer, ref range), BindingType::DynamicUniformBuffer)
-                | (&BindingResource::Buffer(ref buffer, ref range), BindingType::StorageBuffer)
-                | (&BindingResource::Buffer(ref buffer, ref range), BindingType::DynamicStorageBuffer) => {
+                (resource, binding_type) if is_buffer(binding_type) && resource.bound_buffer().is_some() => {
+                    let (buffer, offset, size) = buffer_binding_region(resource).expect("buffer binding resource");
+                    let alignment = match binding_type {
+                        BindingType::UniformBuffer | BindingType::DynamicUniformBuffer => {
+                            device.limits.min_uniform_buffer_offset_alignment
+                        }
+                        _ => device.limits.min_storage_buffer_offset_alignment,
+                    };
+                    if offset != align_to(offset, alignment) {
+                        let msg = format!(
+                            "Buffer offset is not a multiple of the minimum offset alignment (binding: {}, index: {}): offset: {}, alignment: {}",
+                            binding.binding, index, offset, alignment
+                        );
+                        return Err(Error::from(msg));
+                    }
+                    if size == 0 || offset + size > buffer.size() {
+                        let msg = format!(
+                            "Buffer binding range is empty or exceeds the buffer size (binding: {}, index: {}): offset: {}, size: {}, buffer_size: {}",
+                            binding.binding, index, offset, size, buffer.size()
+                        );
+                        return Err(Error::from(msg));
+                    }
+                    if let Some(min_binding_size) = layout_binding.min_binding_size {
+                        if (size as u64) < min_binding_size.get() {
+                            let msg = format!(
+                                "Buffer binding size is less than the min_binding_size (binding: {}, index: {}): size: {}, min_binding_size: {}",
+                                binding.binding, index, size, min_binding_size
+                            );
+                            return Err(Error::from(msg));
+                        }
+                    }
                     buffer_infos[num_writes].buffer = buffer.inner.handle;
-                    buffer_infos[num_writes].offset = range.start as u64;
-                    buffer_infos[num_writes].range = range.end as u64;
+                    buffer_infos[num_writes].offset = offset as u64;
+                    buffer_infos[num_writes].range = size as u64;
                     write.p_buffer_info = &buffer_infos[num_writes];
                 }
-                (&BindingResource::Sampler(ref sampler), BindingType::Sampler) => {
+                (&BindingResource::Sampler(ref sampler), BindingType::Sampler)
+                | (&BindingResource::Sampler(ref sampler), BindingType::ComparisonSampler) => {
+                    let is_comparison = sampler.inner.descriptor.compare_function != CompareFunction::Never;
+                    if is_comparison != (layout_binding.binding_type == BindingType::ComparisonSampler) {
+                        let msg = format!(
+                            "Sampler compare_function does not match the BindingType (binding: {}, index: {}): BindingType: {:?}, compare_function: {:?}",
+                            binding.binding, index, layout_binding.binding_type, sampler.inner.descriptor.compare_function
+                        );
+                        return Err(Error::from(msg));
+                    }
                     image_infos[num_writes].sampler = sampler.inner.handle;
                     write.p_image_info = &image_infos[num_writes];
                 }
@@ -212,12 +360,17 @@ impl BindGroupInner {
                     texel_buffer_views[num_writes] = buffer_view.inner.handle;
                     write.p_texel_buffer_view = &texel_buffer_views[num_writes];
                 }
-                (&BindingResource::TextureView(ref texture_view), BindingType::ReadOnlyStorageTexture) => {
-                    image_infos[num_writes].image_view = texture_view.inner.handle;
-                    image_infos[num_writes].image_layout = vk::ImageLayout::GENERAL;
-                    write.p_image_info = &image_infos[num_writes];
-                }
-                (&BindingResource::TextureView(ref texture_view), BindingType::WriteOnlyStorageTexture) => {
+                (&BindingResource::TextureView(ref texture_view), BindingType::ReadOnlyStorageTexture)
+                | (&BindingResource::TextureView(ref texture_view), BindingType::WriteOnlyStorageTexture) => {
+                    let view_format = texture_view.inner.descriptor.format;
+                    if Some(view_format) != layout_binding.storage_texture_format {
+                        let msg = format!(
+                            "TextureView format does not match the storage_texture_format (binding: {}, index: {}): expected: {:?}, found: {:?}",
+                            binding.binding, index, layout_binding.storage_texture_format, view_format
+                        );
+                        return Err(Error::from(msg));
+                    }
+                    // Storage images must be in the GENERAL layout. See `texture::image_layout`.
                     image_infos[num_writes].image_view = texture_view.inner.handle;
                     image_infos[num_writes].image_layout = vk::ImageLayout::GENERAL;
                     write.p_image_info = &image_infos[num_writes];
@@ -227,6 +380,7 @@ impl BindGroupInner {
                         BindingResource::TextureView(_) => "TextureView",
                         BindingResource::Sampler(_) => "Sampler",
                         BindingResource::Buffer(_, _) => "Buffer",
+                        BindingResource::BufferBinding(_) => "BufferBinding",
                         BindingResource::BufferView(_) => "BufferView",
                     };
                     let msg = format!("BindingType is not valid for the BindingResource (binding: {}, index: {}): BindingType: {:?}, BindingResource: {:?}",
@@ -4,26 +4,38 @@ use ash::vk::{DependencyFlags, MemoryPropertyFlags};
 use vk_mem::{AllocationCreateFlags, AllocationCreateInfo, MemoryUsage};
 
 use crate::imp::fenced_deleter::DeleteWhenUnused;
-use crate::imp::{pipeline, texture, BufferInner, BufferState, BufferViewInner, DeviceInner};
+use crate::imp::{pipeline, texture, util, BufferInner, BufferState, BufferViewInner, DeviceInner};
 use crate::{
-    Buffer, BufferDescriptor, BufferUsage, BufferView, BufferViewDescriptor, BufferViewFormat, Error, MappedBuffer,
-    WriteData,
+    align_to, BindingResource, Buffer, BufferDescriptor, BufferUsage, BufferView, BufferViewDescriptor,
+    BufferViewFormat, CommandEncoder, Device, DynamicUniformVec, Error, MappedBuffer, MemoryHint, MemoryLocation, Pod,
+    TypedBuffer, WriteData,
 };
 
 use parking_lot::Mutex;
+use smallvec::SmallVec;
 
+use std::fmt::{self, Debug};
 use std::marker::PhantomData;
-use std::ops::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut, Range};
 use std::sync::atomic::AtomicPtr;
 use std::sync::Arc;
 use std::{mem, ptr, slice};
 
 pub fn read_only_buffer_usages() -> BufferUsage {
-    BufferUsage::MAP_READ | BufferUsage::COPY_SRC | BufferUsage::INDEX | BufferUsage::VERTEX | BufferUsage::UNIFORM
+    BufferUsage::MAP_READ
+        | BufferUsage::COPY_SRC
+        | BufferUsage::INDEX
+        | BufferUsage::VERTEX
+        | BufferUsage::UNIFORM
+        | BufferUsage::INDIRECT
 }
 
 pub fn writable_buffer_usages() -> BufferUsage {
-    BufferUsage::MAP_WRITE | BufferUsage::COPY_DST | BufferUsage::STORAGE
+    BufferUsage::MAP_WRITE
+        | BufferUsage::COPY_DST
+        | BufferUsage::STORAGE
+        | BufferUsage::TRANSFORM_FEEDBACK
+        | BufferUsage::TRANSFORM_FEEDBACK_COUNTER
 }
 
 /// https://gpuopen-librariesandsdks.github.io/VulkanMemoryAllocator/html/usage_patterns.html
@@ -57,6 +69,37 @@ pub fn memory_usage(usage: BufferUsage) -> MemoryUsage {
     MemoryUsage::GpuOnly
 }
 
+fn allocation_create_info(device: &DeviceInner, descriptor: &BufferDescriptor) -> AllocationCreateInfo {
+    let mut required_flags = MemoryPropertyFlags::empty();
+
+    let usage = match descriptor.memory_hint {
+        None => memory_usage(descriptor.usage),
+        Some(MemoryHint::Upload) => MemoryUsage::CpuOnly,
+        Some(MemoryHint::Readback) => MemoryUsage::GpuToCpu,
+        Some(MemoryHint::DeviceOnly) => MemoryUsage::GpuOnly,
+        Some(MemoryHint::DeviceWithHostAccess) if device.adapter.supports_resizable_bar() => {
+            required_flags = MemoryPropertyFlags::DEVICE_LOCAL | MemoryPropertyFlags::HOST_VISIBLE;
+            MemoryUsage::GpuOnly
+        }
+        Some(MemoryHint::DeviceWithHostAccess) => MemoryUsage::CpuToGpu,
+    };
+
+    let mut flags = AllocationCreateFlags::NONE;
+    if descriptor.size as u64 >= util::DEDICATED_ALLOCATION_THRESHOLD {
+        flags |= AllocationCreateFlags::DEDICATED_MEMORY;
+    }
+
+    AllocationCreateInfo {
+        usage,
+        preferred_flags: MemoryPropertyFlags::empty(),
+        required_flags,
+        flags,
+        user_data: None,
+        pool: None,
+        memory_type_bits: 0,
+    }
+}
+
 pub fn usage_flags(usage: BufferUsage) -> vk::BufferUsageFlags {
     let mut flags = vk::BufferUsageFlags::empty();
 
@@ -93,6 +136,15 @@ pub fn usage_flags(usage: BufferUsage) -> vk::BufferUsageFlags {
         flags |= vk::BufferUsageFlags::INDIRECT_BUFFER;
     }
 
+    if usage.intersects(BufferUsage::TRANSFORM_FEEDBACK) {
+        flags |= vk::BufferUsageFlags::TRANSFORM_FEEDBACK_BUFFER_EXT;
+    }
+
+    // The counter is also consumed as an indirect argument by `vkCmdDrawIndirectByteCountEXT`
+    if usage.intersects(BufferUsage::TRANSFORM_FEEDBACK_COUNTER) {
+        flags |= vk::BufferUsageFlags::TRANSFORM_FEEDBACK_COUNTER_BUFFER_EXT | vk::BufferUsageFlags::INDIRECT_BUFFER;
+    }
+
     flags
 }
 
@@ -121,6 +173,14 @@ pub fn pipeline_stage(usage: BufferUsage) -> vk::PipelineStageFlags {
         flags |= vk::PipelineStageFlags::DRAW_INDIRECT;
     }
 
+    if usage.intersects(BufferUsage::TRANSFORM_FEEDBACK) {
+        flags |= vk::PipelineStageFlags::TRANSFORM_FEEDBACK_EXT;
+    }
+
+    if usage.intersects(BufferUsage::TRANSFORM_FEEDBACK_COUNTER) {
+        flags |= vk::PipelineStageFlags::TRANSFORM_FEEDBACK_EXT | vk::PipelineStageFlags::DRAW_INDIRECT;
+    }
+
     flags
 }
 
@@ -159,6 +219,16 @@ pub fn access_flags(usage: BufferUsage) -> vk::AccessFlags {
         flags |= vk::AccessFlags::INDIRECT_COMMAND_READ
     }
 
+    if usage.intersects(BufferUsage::TRANSFORM_FEEDBACK) {
+        flags |= vk::AccessFlags::TRANSFORM_FEEDBACK_WRITE_EXT
+    }
+
+    if usage.intersects(BufferUsage::TRANSFORM_FEEDBACK_COUNTER) {
+        flags |= vk::AccessFlags::TRANSFORM_FEEDBACK_COUNTER_READ_EXT
+            | vk::AccessFlags::TRANSFORM_FEEDBACK_COUNTER_WRITE_EXT
+            | vk::AccessFlags::INDIRECT_COMMAND_READ
+    }
+
     // TODO: The read-only and write-only flags should probably be considered here
     if usage.intersects(BufferUsage::STORAGE) {
         flags |= vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE
@@ -167,8 +237,81 @@ pub fn access_flags(usage: BufferUsage) -> vk::AccessFlags {
     flags
 }
 
+fn validate_buffer_descriptor(descriptor: &BufferDescriptor) -> Result<(), Error> {
+    if descriptor.size == 0 {
+        log::error!("buffer size must not be zero");
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    if descriptor.usage == BufferUsage::NONE {
+        log::error!("buffer usage must not be NONE");
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    // A buffer is either a staging/dynamic buffer that's written by the CPU or a readback buffer
+    // that's read by the CPU; there's no memory type preference that satisfies both.
+    if descriptor
+        .usage
+        .contains(BufferUsage::MAP_READ | BufferUsage::MAP_WRITE)
+    {
+        log::error!(
+            "buffer usage may not contain both MAP_READ and MAP_WRITE: {:?}",
+            descriptor.usage
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    let map_usages = BufferUsage::MAP_READ | BufferUsage::MAP_WRITE;
+    if descriptor.memory_hint == Some(MemoryHint::DeviceOnly) && descriptor.usage.intersects(map_usages) {
+        log::error!(
+            "buffers with MemoryHint::DeviceOnly cannot be mapped: {:?}",
+            descriptor.usage
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    Ok(())
+}
+
+/// Classifies allocation failures so that callers can respond to memory exhaustion.
+fn allocation_error(device: &DeviceInner, e: vk_mem::Error, requested: u64) -> Error {
+    let location = match e.kind() {
+        vk_mem::ErrorKind::Vulkan(vk::Result::ERROR_OUT_OF_HOST_MEMORY) => MemoryLocation::Host,
+        vk_mem::ErrorKind::Vulkan(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY) => MemoryLocation::Device,
+        _ => return Error::from(e),
+    };
+
+    let memory_properties = &device.adapter.physical_device_memory_properties;
+
+    let budget = memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize]
+        .iter()
+        .filter(|heap| {
+            let device_local = heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL);
+            device_local == (location == MemoryLocation::Device)
+        })
+        .map(|heap| heap.size)
+        .sum();
+
+    log::error!(
+        "failed to allocate {} bytes of {:?} memory (budget: {} bytes)",
+        requested,
+        location,
+        budget
+    );
+
+    Error::out_of_memory(location, requested, budget)
+}
+
 impl BufferInner {
     pub fn new(device: Arc<DeviceInner>, descriptor: BufferDescriptor) -> Result<BufferInner, Error> {
+        validate_buffer_descriptor(&descriptor)?;
+
+        let transform_feedback_usages = BufferUsage::TRANSFORM_FEEDBACK | BufferUsage::TRANSFORM_FEEDBACK_COUNTER;
+        if descriptor.usage.intersects(transform_feedback_usages) && !device.extensions.transform_feedback {
+            log::error!("transform feedback buffer usages require Extensions::transform_feedback");
+            return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+        }
+
         let create_info = vk::BufferCreateInfo {
             size: descriptor.size as u64,
             usage: usage_flags(descriptor.usage),
@@ -176,15 +319,7 @@ impl BufferInner {
             ..Default::default()
         };
 
-        let allocation_create_info = AllocationCreateInfo {
-            usage: memory_usage(descriptor.usage),
-            preferred_flags: MemoryPropertyFlags::empty(),
-            required_flags: MemoryPropertyFlags::empty(),
-            flags: AllocationCreateFlags::NONE,
-            user_data: None,
-            pool: None,
-            memory_type_bits: 0,
-        };
+        let allocation_create_info = allocation_create_info(&device, &descriptor);
 
         log::trace!(
             "buffer create_info: {:?}, allocation_create_info: {:?}",
@@ -196,18 +331,24 @@ impl BufferInner {
 
         let result = allocator.create_buffer(&create_info, &allocation_create_info);
 
-        // See TextureInner::new
+        // VMA does some basic pre-checks on the input, but doesn't provide any info around why the
+        // input was invalid and just returns `VK_ERROR_VALIDAITON_FAILED_EXT` without triggering
+        // any validation messages (it doesn't call `vkCreateBuffer` if it's pre-checks fail).
+        // So, in the event of a validation error, we'll create a dummy buffer that triggers the
+        // validation message (so we can see what exactly the problem was) and then destroy it
+        // immediately.
         if let Err(ref e) = &result {
             if let vk_mem::ErrorKind::Vulkan(vk::Result::ERROR_VALIDATION_FAILED_EXT) = e.kind() {
                 unsafe {
-                    let dummy = device.raw.create_buffer(&create_info, None)?;
-                    device.raw.destroy_buffer(dummy, None);
+                    let dummy = device.raw.create_buffer(&create_info, device.allocation_callbacks())?;
+                    device.raw.destroy_buffer(dummy, device.allocation_callbacks());
                     return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
                 }
             }
         }
 
-        let (buffer, allocation, allocation_info) = result.expect("failed to create buffer"); // TODO
+        let (buffer, allocation, allocation_info) =
+            result.map_err(|e| allocation_error(&device, e, create_info.size))?;
 
         log::trace!("created buffer: {:?}, allocation_info: {:?}", buffer, allocation_info);
 
@@ -216,70 +357,99 @@ impl BufferInner {
             allocation,
             allocation_info,
             device,
-            last_usage: Mutex::new(BufferUsage::NONE),
+            range_usage: Mutex::new(RangeUsageTracker::new(create_info.size)),
             buffer_state: Mutex::new(BufferState::Unmapped),
             handle: buffer,
         })
     }
 
     pub fn transition_usage_now(&self, command_buffer: vk::CommandBuffer, usage: BufferUsage) -> Result<(), Error> {
-        let mut last_usage = self.last_usage.lock();
+        self.transition_range_usage_now(command_buffer, usage, 0, vk::WHOLE_SIZE)
+    }
+
+    /// Transitions the bytes in `offset..offset + size` to `usage`. A `size` of `vk::WHOLE_SIZE`
+    /// transitions the remainder of the buffer. The range is widened to `RANGE_USAGE_BLOCK_SIZE`
+    /// boundaries and only the blocks whose last usage requires it are included in the barrier.
+    pub fn transition_range_usage_now(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        usage: BufferUsage,
+        offset: u64,
+        size: u64,
+    ) -> Result<(), Error> {
+        let mut range_usage = self.range_usage.lock();
 
         log::trace!(
-            "transition_usage_now buffer: {:?}, last_usage: {:?}, usage: {:?}",
+            "transition_range_usage_now buffer: {:?}, offset: {}, size: {}, usage: {:?}",
             self.handle,
-            *last_usage,
+            offset,
+            size,
             usage
         );
 
-        let last_includes_target = (*last_usage & usage) == usage;
-        let last_read_only = (*last_usage & read_only_buffer_usages()) == *last_usage;
+        let mut src_stage_mask = vk::PipelineStageFlags::empty();
+        let dst_stage_mask = pipeline_stage(usage);
 
-        if last_includes_target && last_read_only {
-            return Ok(());
-        }
+        let mut buffer_memory_barriers: SmallVec<[vk::BufferMemoryBarrier; 4]> = SmallVec::new();
 
-        // initial transition
-        if *last_usage == BufferUsage::NONE {
-            *last_usage = usage;
-            return Ok(());
+        for (range, range_last_usage) in range_usage.usage_mut(offset, size) {
+            let last_includes_target = (*range_last_usage & usage) == usage;
+            let last_read_only = (*range_last_usage & read_only_buffer_usages()) == *range_last_usage;
+
+            if last_includes_target && last_read_only {
+                continue;
+            }
+
+            // initial transition
+            if *range_last_usage == BufferUsage::NONE {
+                *range_last_usage = usage;
+                continue;
+            }
+
+            src_stage_mask |= pipeline_stage(*range_last_usage);
+
+            let src_access_mask = access_flags(*range_last_usage);
+            let dst_access_mask = access_flags(usage);
+
+            log::trace!(
+                "usage: {:?}, last_usage: {:?}, range: {:?}, src_access_mask: {:?}, dst_access_mask: {:?}",
+                usage,
+                *range_last_usage,
+                range,
+                src_access_mask,
+                dst_access_mask
+            );
+
+            buffer_memory_barriers.push(vk::BufferMemoryBarrier {
+                src_access_mask,
+                dst_access_mask,
+                src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                buffer: self.handle,
+                offset: range.start,
+                size: range.end - range.start,
+                ..Default::default()
+            });
+
+            *range_last_usage = usage;
         }
 
-        let src_stage_mask = pipeline_stage(*last_usage);
-        let dst_stage_mask = pipeline_stage(usage);
+        range_usage.coalesce();
 
-        let src_access_mask = access_flags(*last_usage);
-        let dst_access_mask = access_flags(usage);
+        if buffer_memory_barriers.is_empty() {
+            return Ok(());
+        }
 
         log::trace!(
-            "usage: {:?}, last_usage: {:?}, src_stage_mask: {:?}, src_access_mask: {:?}",
+            "usage: {:?}, src_stage_mask: {:?}, dst_stage_mask: {:?}, barriers: {}",
             usage,
-            *last_usage,
             src_stage_mask,
-            src_access_mask
-        );
-        log::trace!(
-            "usage: {:?}, last_usage: {:?}, dst_stage_mask: {:?}, dst_access_mask: {:?}",
-            usage,
-            *last_usage,
             dst_stage_mask,
-            dst_access_mask
+            buffer_memory_barriers.len()
         );
 
-        let buffer_memory_barrier = vk::BufferMemoryBarrier {
-            src_access_mask,
-            dst_access_mask,
-            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
-            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
-            buffer: self.handle,
-            offset: 0,
-            size: self.descriptor.size as u64,
-            ..Default::default()
-        };
-
         let dependency_flags = DependencyFlags::empty();
         let memory_barriers = &[];
-        let buffer_memory_barriers = &[buffer_memory_barrier];
         let image_memory_barriers = &[];
 
         unsafe {
@@ -289,16 +459,21 @@ impl BufferInner {
                 dst_stage_mask,
                 dependency_flags,
                 memory_barriers,
-                buffer_memory_barriers,
+                &buffer_memory_barriers,
                 image_memory_barriers,
             );
         }
 
-        *last_usage = usage;
-
         Ok(())
     }
 
+    /// Returns `true` if every block in `offset..offset + size` was last used as exactly `usage`.
+    pub fn range_last_used_as(&self, offset: u64, size: u64, usage: BufferUsage) -> bool {
+        self.range_usage
+            .lock()
+            .all(offset, size, |range_last_usage| range_last_usage == usage)
+    }
+
     pub unsafe fn get_mapped_ptr(&self) -> Result<*mut u8, Error> {
         let mut buffer_state = self.buffer_state.lock();
         match *buffer_state {
@@ -549,11 +724,20 @@ impl Buffer {
         let mut state = self.inner.device.state.lock();
 
         let command_buffer = state.get_pending_command_buffer(&self.inner.device)?;
-        if BufferUsage::COPY_DST != *self.inner.last_usage.lock() {
-            self.inner.transition_usage_now(command_buffer, BufferUsage::COPY_DST)?;
+        let offset_bytes = offset_bytes as u64;
+        let data_size_bytes = data_size as u64;
+        if !self
+            .inner
+            .range_last_used_as(offset_bytes, data_size_bytes, BufferUsage::COPY_DST)
+        {
+            self.inner.transition_range_usage_now(
+                command_buffer,
+                BufferUsage::COPY_DST,
+                offset_bytes,
+                data_size_bytes,
+            )?;
         }
         unsafe {
-            let offset_bytes = offset_bytes as u64;
             let data: &[u8] = slice::from_raw_parts(data.as_ptr() as *const u8, data_size);
             self.inner
                 .device
@@ -603,6 +787,225 @@ impl Buffer {
     }
 }
 
+impl<T: Pod> TypedBuffer<T> {
+    /// Wraps an existing buffer. The buffer size must be a multiple of the size of `T`.
+    pub fn from_buffer(buffer: Buffer) -> Result<TypedBuffer<T>, Error> {
+        let element_size = mem::size_of::<T>();
+        if element_size == 0 || buffer.size() % element_size != 0 {
+            log::error!(
+                "buffer size is not a multiple of the element size; buffer_size: {}, element_size: {}",
+                buffer.size(),
+                element_size
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+        Ok(TypedBuffer {
+            buffer,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Returns the underlying untyped buffer.
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn into_buffer(self) -> Buffer {
+        self.buffer
+    }
+
+    /// Returns the number of elements `T` that fit in the buffer.
+    pub fn len(&self) -> usize {
+        self.buffer.size() / mem::size_of::<T>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Converts a range of elements into a range of bytes.
+    pub fn byte_range(&self, elements: Range<usize>) -> Range<usize> {
+        let element_size = mem::size_of::<T>();
+        (elements.start * element_size)..(elements.end * element_size)
+    }
+
+    /// Returns a binding resource for the `elements` of the buffer.
+    pub fn binding_resource(&self, elements: Range<usize>) -> BindingResource {
+        BindingResource::Buffer(self.buffer.clone(), self.byte_range(elements))
+    }
+
+    /// Writes `data` into the buffer starting at element `offset`. The buffer is mapped if it was
+    /// created with `MAP_WRITE`; otherwise, the upload is deferred as with `Buffer::set_sub_data`.
+    pub fn write(&self, offset: usize, data: &[T]) -> Result<(), Error> {
+        if offset + data.len() > self.len() {
+            log::error!(
+                "write range exceeds buffer length; offset: {}, data_len: {}, buffer_len: {}",
+                offset,
+                data.len(),
+                self.len()
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+        if self.buffer.usage().contains(BufferUsage::MAP_WRITE) {
+            let mut mapped = self.buffer.map_write()?;
+            mapped.write::<T>(offset, data.len())?.copy_from_slice(data);
+            Ok(())
+        } else {
+            self.buffer.set_sub_data(offset, data)
+        }
+    }
+}
+
+impl<T: Pod> Clone for TypedBuffer<T> {
+    fn clone(&self) -> TypedBuffer<T> {
+        TypedBuffer {
+            buffer: self.buffer.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: Pod> Debug for TypedBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TypedBuffer")
+            .field("buffer", &self.buffer)
+            .field("element_type", &std::any::type_name::<T>())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<T: Pod> DynamicUniformVec<T> {
+    pub fn new(device: &Device) -> Result<DynamicUniformVec<T>, Error> {
+        DynamicUniformVec::with_capacity(device, 1)
+    }
+
+    /// Creates an empty array with a buffer that holds `capacity` elements.
+    pub fn with_capacity(device: &Device, capacity: usize) -> Result<DynamicUniformVec<T>, Error> {
+        let element_size = mem::size_of::<T>();
+        if element_size == 0 {
+            log::error!("DynamicUniformVec element type must not be zero sized");
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+        let stride = align_to(element_size, device.limits().min_uniform_buffer_offset_alignment);
+        let buffer = Self::create_buffer(device, stride * capacity.max(1))?;
+        Ok(DynamicUniformVec {
+            device: device.clone(),
+            buffer,
+            data: Vec::with_capacity(stride * capacity),
+            stride,
+            _phantom: PhantomData,
+        })
+    }
+
+    fn create_buffer(device: &Device, size: usize) -> Result<Buffer, Error> {
+        device.create_buffer(BufferDescriptor {
+            size,
+            usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
+            memory_hint: None,
+        })
+    }
+
+    /// Returns the number of elements in the array.
+    pub fn len(&self) -> usize {
+        self.data.len() / self.stride
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the number of elements that fit in the current buffer.
+    pub fn capacity(&self) -> usize {
+        self.buffer.size() / self.stride
+    }
+
+    /// Returns the distance in bytes between consecutive elements.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Appends an element and returns its index.
+    pub fn push(&mut self, value: T) -> usize {
+        let index = self.len();
+        self.data.resize(self.data.len() + self.stride, 0);
+        self.set(index, value);
+        index
+    }
+
+    /// Replaces the element at `index`.
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: T) {
+        assert!(index < self.len(), "index out of bounds: {} >= {}", index, self.len());
+        let offset = index * self.stride;
+        let bytes = bytemuck::bytes_of(&value);
+        self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    /// Removes all elements. The buffer is kept for reuse.
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
+    /// Returns the dynamic offset of the element at `index` for `set_bind_group`.
+    pub fn dynamic_offset(&self, index: usize) -> usize {
+        index * self.stride
+    }
+
+    /// Returns the buffer that holds the uploaded elements.
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Returns a binding resource for a single element. Bind it with
+    /// `BindingType::DynamicUniformBuffer` and select the element with `dynamic_offset`.
+    pub fn binding_resource(&self) -> BindingResource {
+        BindingResource::Buffer(self.buffer.clone(), 0..mem::size_of::<T>())
+    }
+
+    /// Records a copy of the elements into the buffer.
+    ///
+    /// Returns `true` if the buffer was reallocated to fit the elements. Bind groups created from
+    /// the previous `binding_resource` must be recreated in that case.
+    pub fn upload(&mut self, encoder: &mut CommandEncoder) -> Result<bool, Error> {
+        let mut reallocated = false;
+        if self.data.len() > self.buffer.size() {
+            let capacity = self.len().next_power_of_two();
+            self.buffer = Self::create_buffer(&self.device, self.stride * capacity)?;
+            reallocated = true;
+        }
+        if !self.data.is_empty() {
+            let staging_buffer = self.device.create_buffer_mapped(BufferDescriptor {
+                size: self.data.len(),
+                usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
+                memory_hint: Some(MemoryHint::Upload),
+            })?;
+            staging_buffer.copy_from_slice(&self.data)?;
+            let staging_buffer = staging_buffer.unmap();
+            encoder.copy_buffer_to_buffer(&staging_buffer, 0, &self.buffer, 0, self.data.len());
+        }
+        Ok(reallocated)
+    }
+}
+
+impl<T: Pod> Debug for DynamicUniformVec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DynamicUniformVec")
+            .field("buffer", &self.buffer)
+            .field("element_type", &std::any::type_name::<T>())
+            .field("len", &self.len())
+            .field("stride", &self.stride)
+            .finish()
+    }
+}
+
+impl<T: Pod> Into<Buffer> for TypedBuffer<T> {
+    fn into(self) -> Buffer {
+        self.buffer
+    }
+}
+
 impl From<BufferViewFormat> for vk::Format {
     fn from(f: BufferViewFormat) -> vk::Format {
         match f {
@@ -622,7 +1025,12 @@ impl BufferViewInner {
             format,
             ..Default::default()
         };
-        let handle = unsafe { buffer.device.raw.create_buffer_view(&create_info, None)? };
+        let handle = unsafe {
+            buffer
+                .device
+                .raw
+                .create_buffer_view(&create_info, buffer.device.allocation_callbacks())?
+        };
         Ok(BufferViewInner { handle, buffer })
     }
 }
@@ -649,3 +1057,90 @@ impl BufferView {
         }
     }
 }
+
+/// Buffer ranges are tracked in blocks of this many bytes. Usages within the same block are
+/// transitioned together, which bounds the number of ranges that a buffer can be split into.
+pub const RANGE_USAGE_BLOCK_SIZE: u64 = 256;
+
+/// Returns the block aligned range for `offset..offset + size`, clamped to `buffer_size`. A `size`
+/// of `vk::WHOLE_SIZE` extends the range to the end of the buffer.
+pub fn block_range(offset: u64, size: u64, buffer_size: u64) -> Range<u64> {
+    let start = (offset - offset % RANGE_USAGE_BLOCK_SIZE).min(buffer_size);
+    let end = if size == vk::WHOLE_SIZE {
+        buffer_size
+    } else {
+        let end = offset.saturating_add(size);
+        match end % RANGE_USAGE_BLOCK_SIZE {
+            0 => end,
+            remainder => end.saturating_add(RANGE_USAGE_BLOCK_SIZE - remainder),
+        }
+        .min(buffer_size)
+    };
+    start..end.max(start)
+}
+
+/// Tracks the last usage of each range of a buffer. The ranges are sorted, contiguous and cover
+/// the entire buffer. Adjacent ranges with the same usage are merged by `coalesce`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RangeUsageTracker {
+    ranges: Vec<(Range<u64>, BufferUsage)>,
+    size: u64,
+}
+
+impl RangeUsageTracker {
+    pub fn new(size: u64) -> RangeUsageTracker {
+        let mut ranges = Vec::with_capacity(1);
+        if size > 0 {
+            ranges.push((0..size, BufferUsage::NONE));
+        }
+        RangeUsageTracker { ranges, size }
+    }
+
+    /// Splits the range containing `at`, so that a range starts at `at`.
+    fn split_at(&mut self, at: u64) {
+        if at == 0 || at >= self.size {
+            return;
+        }
+        let index = self.ranges.iter().position(|(range, _)| range.end > at);
+        if let Some(index) = index {
+            let (range, usage) = self.ranges[index].clone();
+            if range.start < at {
+                self.ranges[index].0.end = at;
+                self.ranges.insert(index + 1, (at..range.end, usage));
+            }
+        }
+    }
+
+    /// Returns the ranges covering `offset..offset + size`, split at the block aligned boundaries.
+    pub fn usage_mut(&mut self, offset: u64, size: u64) -> impl Iterator<Item = (Range<u64>, &mut BufferUsage)> {
+        let aligned = block_range(offset, size, self.size);
+        self.split_at(aligned.start);
+        self.split_at(aligned.end);
+        self.ranges
+            .iter_mut()
+            .filter(move |(range, _)| range.start >= aligned.start && range.end <= aligned.end)
+            .map(|(range, usage)| (range.clone(), usage))
+    }
+
+    /// Returns `true` if `f` returns `true` for the usage of every range overlapping
+    /// `offset..offset + size`.
+    pub fn all<F: Fn(BufferUsage) -> bool>(&self, offset: u64, size: u64, f: F) -> bool {
+        let aligned = block_range(offset, size, self.size);
+        self.ranges
+            .iter()
+            .filter(|(range, _)| range.start < aligned.end && range.end > aligned.start)
+            .all(|(_, usage)| f(*usage))
+    }
+
+    /// Merges adjacent ranges that have the same usage.
+    pub fn coalesce(&mut self) {
+        let mut merged: Vec<(Range<u64>, BufferUsage)> = Vec::with_capacity(self.ranges.len());
+        for (range, usage) in self.ranges.drain(..) {
+            match merged.last_mut() {
+                Some((last_range, last_usage)) if *last_usage == usage => last_range.end = range.end,
+                _ => merged.push((range, usage)),
+            }
+        }
+        self.ranges = merged;
+    }
+}
@@ -2,9 +2,9 @@ use std::sync::Arc;
 
 use smallvec::SmallVec;
 
-use crate::imp::command_buffer::MAX_BIND_GROUPS;
+use crate::imp::command_buffer::INLINE_BIND_GROUPS;
 use crate::imp::command_encoder::{RenderPassColorAttachmentInfo, RenderPassDepthStencilAttachmentInfo};
-use crate::imp::{BindGroupInner, BufferInner, ComputePipelineInner, RenderPipelineInner, TextureInner};
+use crate::imp::{BindGroupInner, BufferInner, ComputePipelineInner, QuerySetInner, RenderPipelineInner, TextureInner};
 use crate::{Buffer, Color, Extent3d, FilterMode, Origin3d, ShaderStage};
 
 #[derive(Debug, Clone)]
@@ -20,6 +20,7 @@ pub struct TextureCopy {
     pub texture: Arc<TextureInner>,
     pub mip_level: u32,
     pub array_layer: u32,
+    pub array_layer_count: u32,
     pub origin_texels: Origin3d,
 }
 
@@ -31,16 +32,32 @@ pub struct TextureBlit {
     pub bounds_texels: [Origin3d; 2],
 }
 
+#[derive(Debug, Clone)]
+pub struct TransformFeedbackBuffer {
+    pub buffer: Arc<BufferInner>,
+    pub offset: u64,
+    pub size: u64,
+    pub counter: Option<(Arc<BufferInner>, u64)>,
+}
+
 #[derive(Debug, Clone)]
 pub enum Command {
     BeginComputePass,
+    BeginTransformFeedback {
+        /// The length of `1` is the max that we can use without increasing the size of the enum
+        buffers: SmallVec<[TransformFeedbackBuffer; 1]>,
+        resume: bool,
+    },
     BeginRenderPass {
-        color_attachments: Vec<RenderPassColorAttachmentInfo>,
+        /// Most render passes have a single color attachment. Additional attachments spill to
+        /// the heap.
+        color_attachments: SmallVec<[RenderPassColorAttachmentInfo; 1]>,
         depth_stencil_attachment: Option<RenderPassDepthStencilAttachmentInfo>,
         //
         width: u32,
         height: u32,
         sample_count: u32,
+        flip_viewport: bool,
     },
     CopyBufferToBuffer {
         src: BufferCopy,
@@ -93,12 +110,20 @@ pub enum Command {
         buffer: Buffer,
         indirect_offset: usize,
     },
+    DrawIndirectByteCount {
+        instance_count: u32,
+        first_instance: u32,
+        counter_buffer: Arc<BufferInner>,
+        counter_offset: u64,
+        vertex_stride: u32,
+    },
     DispatchIndirect {
         buffer: Buffer,
         indirect_offset: usize,
     },
     EndComputePass,
     EndRenderPass,
+    EndTransformFeedback,
     InsertDebugMarker {
         data_offset: usize,
         label_name_with_nul_len: usize,
@@ -108,6 +133,13 @@ pub enum Command {
         data_offset: usize,
         label_name_with_nul_len: usize,
     },
+    ResolveQuerySet {
+        query_set: Arc<QuerySetInner>,
+        first_query: u32,
+        query_count: u32,
+        destination: Arc<BufferInner>,
+        destination_offset: usize,
+    },
     SetComputePipeline {
         pipeline: Arc<ComputePipelineInner>,
     },
@@ -123,6 +155,12 @@ pub enum Command {
     SetStencilReference {
         reference: u32,
     },
+    SetStencilCompareMask {
+        mask: u32,
+    },
+    SetStencilWriteMask {
+        mask: u32,
+    },
     SetScissorRect {
         x: u32,
         y: u32,
@@ -132,10 +170,15 @@ pub enum Command {
     SetBlendColor {
         color: Color,
     },
+    SetDepthBias {
+        constant: f32,
+        clamp: f32,
+        slope: f32,
+    },
     SetBindGroup {
         index: u32,
         bind_group: Arc<BindGroupInner>,
-        dynamic_offsets: Option<SmallVec<[u32; MAX_BIND_GROUPS]>>,
+        dynamic_offsets: Option<SmallVec<[u32; INLINE_BIND_GROUPS]>>,
     },
     SetIndexBuffer {
         buffer: Arc<BufferInner>,
@@ -155,11 +198,15 @@ pub enum Command {
         min_depth: f32,
         max_depth: f32,
     },
+    WriteTimestamp {
+        query_set: Arc<QuerySetInner>,
+        query_index: u32,
+    },
 }
 
 #[test]
 fn command_size() {
     // The command size can balloon if we embed a SmallVec or fixed sized array. This just
     // raises awareness..
-    assert_eq!(88, std::mem::size_of::<Command>());
+    assert_eq!(112, std::mem::size_of::<Command>());
 }
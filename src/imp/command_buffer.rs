@@ -4,43 +4,66 @@ use smallvec::SmallVec;
 use crate::imp::command::{BufferCopy, Command, TextureBlit, TextureCopy};
 use crate::imp::fenced_deleter::DeleteWhenUnused;
 use crate::imp::render_pass::{ColorInfo, DepthStencilInfo, RenderPassCacheQuery};
-use crate::imp::{binding, pipeline};
+use crate::imp::{binding, compute_blit, debug};
 use crate::imp::{render_pass, sampler, texture, util, DeviceInner, PipelineLayoutInner};
-use crate::imp::{CommandBufferInner, RenderPipelineInner};
-use crate::{BufferUsage, DrawIndirectCommand, Error, Extent3d, IndexFormat, ShaderStage, TextureUsage};
-
-use crate::imp::command_encoder::{
-    CommandEncoderState, RenderPassColorAttachmentInfo, RenderPassDepthStencilAttachmentInfo,
+use crate::imp::{CommandBufferInner, RenderPipelineInner, TextureViewInner};
+use crate::{
+    BufferUsage, ClearValue, CommandBuffer, DrawIndirectCommand, Error, Extent3d, IndexFormat, ShaderStage,
+    TextureFormatFeatures, TextureUsage,
 };
-use crate::imp::device::DeviceState;
+
+use crate::imp::command_encoder::{RenderPassColorAttachmentInfo, RenderPassDepthStencilAttachmentInfo};
+use crate::imp::device::{CommandPoolAndBuffer, DeviceState};
+use crate::imp::fenced_deleter::FencedDeleter;
+use crate::imp::pass_resource_usage::ResourceUsageSnapshot;
+use crate::imp::serial::Serial;
 
 use std::ffi::CStr;
+use std::fmt::Write;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 pub const MAX_VERTEX_INPUTS: usize = 16;
-pub const MAX_BIND_GROUPS: usize = 4;
+/// The number of bind groups that are tracked without allocating. The actual limit is
+/// `Limits::max_bind_groups`.
+pub const INLINE_BIND_GROUPS: usize = 4;
+
+/// Flips the viewport vertically within its bounds by moving the origin to the bottom edge and
+/// negating the height (`VK_KHR_maintenance1`).
+fn viewport_with_flip(viewport: vk::Viewport, flip: bool) -> vk::Viewport {
+    if flip {
+        vk::Viewport {
+            y: viewport.y + viewport.height,
+            height: -viewport.height,
+            ..viewport
+        }
+    } else {
+        viewport
+    }
+}
 
 fn index_type(format: IndexFormat) -> vk::IndexType {
     match format {
         IndexFormat::U16 => vk::IndexType::UINT16,
         IndexFormat::U32 => vk::IndexType::UINT32,
+        IndexFormat::U8 => vk::IndexType::UINT8_EXT,
     }
 }
 
-fn buffer_image_copy(
+pub fn buffer_image_copy(
     buffer_copy: &BufferCopy,
     texture_copy: &TextureCopy,
     size_texels: Extent3d,
 ) -> vk::BufferImageCopy {
     vk::BufferImageCopy {
         buffer_offset: buffer_copy.offset as vk::DeviceSize,
-        buffer_row_length: buffer_copy.row_length, // TODO: row_pitch
+        buffer_row_length: buffer_copy.row_length,
         buffer_image_height: buffer_copy.image_height,
         image_subresource: vk::ImageSubresourceLayers {
             aspect_mask: texture::aspect_mask(texture_copy.texture.descriptor.format),
             mip_level: texture_copy.mip_level,
             base_array_layer: texture_copy.array_layer,
-            layer_count: 1,
+            layer_count: texture_copy.array_layer_count,
         },
         image_offset: vk::Offset3D {
             x: texture_copy.origin_texels.x,
@@ -157,15 +180,222 @@ fn pop_debug_group(device: &DeviceInner, command_buffer: vk::CommandBuffer) {
     }
 }
 
+/// Validates the framebuffer of a render pass without attachments
+fn validate_attachmentless(device: &DeviceInner, width: u32, height: u32, sample_count: u32) -> Result<(), Error> {
+    let limits = &device.adapter.physical_device_properties.limits;
+    if width == 0 || height == 0 || width > limits.max_framebuffer_width || height > limits.max_framebuffer_height {
+        log::error!(
+            "attachmentless render pass size must be between 1x1 and {}x{}: {}x{}",
+            limits.max_framebuffer_width,
+            limits.max_framebuffer_height,
+            width,
+            height
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+    let samples = render_pass::sample_count_flags(sample_count)?;
+    if !limits.framebuffer_no_attachments_sample_counts.contains(samples) {
+        log::error!(
+            "attachmentless render pass sample_count is not supported: {}",
+            sample_count
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+    Ok(())
+}
+
+fn validate_resolve_target(
+    device: &DeviceInner,
+    index: usize,
+    attachment: &TextureViewInner,
+    resolve_target: &TextureViewInner,
+) -> Result<(), Error> {
+    let format = attachment.texture.descriptor.format;
+    let resolve_format = resolve_target.texture.descriptor.format;
+    if attachment.texture.descriptor.sample_count == 1 {
+        log::error!(
+            "color attachment {} has a resolve target but is not multisampled",
+            index
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+    if resolve_target.texture.descriptor.sample_count != 1 {
+        log::error!("resolve target for color attachment {} must not be multisampled", index);
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+    if format != resolve_format {
+        log::error!(
+            "resolve target format does not match color attachment {}: expected: {:?}, found: {:?}",
+            index,
+            format,
+            resolve_format
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+    if texture::is_integer(format) {
+        log::error!(
+            "integer formats cannot be resolved (color attachment {}): {:?}",
+            index,
+            format
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+    let features = device.adapter.get_texture_format_features(format);
+    if !features.contains(TextureFormatFeatures::COLOR_ATTACHMENT) {
+        log::error!("format cannot be resolved (color attachment {}): {:?}", index, format);
+        return Err(Error::from(vk::Result::ERROR_FORMAT_NOT_SUPPORTED));
+    }
+    Ok(())
+}
+
+/// Validates that the command buffers may be submitted. A command buffer created with
+/// `one_time_submit` may only be submitted once.
+pub fn validate_submit(command_buffers: &[CommandBuffer]) -> Result<(), Error> {
+    for command_buffer in command_buffers.iter() {
+        let inner = &command_buffer.inner;
+        if inner.one_time_submit && inner.submitted.load(Ordering::Acquire) {
+            log::error!("command buffer was created with one_time_submit and has already been submitted");
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+    }
+    Ok(())
+}
+
+/// Objects created while recording that must outlive the execution of the recorded commands
+#[derive(Debug, Default)]
+pub struct RecordedObjects {
+    pub framebuffers: Vec<vk::Framebuffer>,
+    pub image_views: Vec<vk::ImageView>,
+    pub descriptor_pools: Vec<vk::DescriptorPool>,
+}
+
+impl RecordedObjects {
+    pub fn delete_when_unused(self, fenced_deleter: &mut FencedDeleter, serial: Serial) {
+        for framebuffer in self.framebuffers {
+            fenced_deleter.delete_when_unused(framebuffer, serial);
+        }
+        for image_view in self.image_views {
+            fenced_deleter.delete_when_unused(image_view, serial);
+        }
+        for descriptor_pool in self.descriptor_pools {
+            fenced_deleter.delete_when_unused(descriptor_pool, serial);
+        }
+    }
+}
+
+/// The Vulkan command buffer recorded for a reusable `CommandBuffer`. It's submitted again without
+/// being recorded for as long as the resources that it uses are in the state it was recorded for.
+#[derive(Debug)]
+pub struct Recording {
+    commands: CommandPoolAndBuffer,
+    objects: RecordedObjects,
+    initial_usage: ResourceUsageSnapshot,
+    final_usage: ResourceUsageSnapshot,
+    render_pass_cache_generation: u64,
+}
+
+impl Recording {
+    fn is_valid(&self, state: &DeviceState) -> bool {
+        self.render_pass_cache_generation == state.render_pass_cache_generation() && self.initial_usage.is_current()
+    }
+
+    /// Destroys the recording after the pending submission completes
+    pub fn retire(self, state: &mut DeviceState) {
+        state.retire_reusable_commands(self.commands);
+        let serial = state.get_next_pending_serial();
+        self.objects.delete_when_unused(state.get_fenced_deleter(), serial);
+    }
+}
+
 impl CommandBufferInner {
-    pub fn record_commands(&self, command_buffer: vk::CommandBuffer, state: &mut DeviceState) -> Result<(), Error> {
+    /// Marks the command buffer as submitted once the submission has succeeded
+    pub fn mark_submitted(&self) {
+        self.submitted.store(true, Ordering::Release);
+    }
+
+    /// Adds the commands to the pending submission.
+    ///
+    /// The first submission records the commands into the pending command buffer. Once a reusable
+    /// command buffer is submitted again, the commands are recorded into a separate Vulkan command
+    /// buffer, which is reused by later submissions until the usage of one of its resources no
+    /// longer matches the usage that it was recorded for.
+    pub fn record_submission(&self, state: &mut DeviceState) -> Result<(), Error> {
+        if self.one_time_submit || !self.submitted.load(Ordering::Acquire) {
+            let command_buffer = state.get_pending_command_buffer(&self.device)?;
+            let mut objects = RecordedObjects::default();
+            let result = self.record_commands(command_buffer, state, &mut objects);
+            let serial = state.get_next_pending_serial();
+            objects.delete_when_unused(state.get_fenced_deleter(), serial);
+            return result;
+        }
+
+        let mut recording = self.recording.lock();
+
+        if let Some(recording) = recording.as_ref().filter(|recording| recording.is_valid(state)) {
+            log::trace!("reusing recorded command buffer: {:?}", recording.commands);
+            recording.final_usage.restore();
+            return state.push_command_buffer(&self.device, recording.commands.command_buffer());
+        }
+
+        if let Some(recording) = recording.take() {
+            recording.retire(state);
+        }
+
+        let initial_usage = self.state.resource_usages.snapshot();
+        let commands = state.begin_reusable_commands(&self.device)?;
+        let command_buffer = commands.command_buffer();
+        let mut objects = RecordedObjects::default();
+        let result = self
+            .record_commands(command_buffer, state, &mut objects)
+            .and_then(|()| unsafe { self.device.raw.end_command_buffer(command_buffer) }.map_err(Error::from));
+
+        let new_recording = Recording {
+            commands,
+            objects,
+            initial_usage,
+            final_usage: self.state.resource_usages.snapshot(),
+            render_pass_cache_generation: state.render_pass_cache_generation(),
+        };
+
+        if let Err(e) = result.and_then(|()| state.push_command_buffer(&self.device, command_buffer)) {
+            new_recording.retire(state);
+            return Err(e);
+        }
+
+        *recording = Some(new_recording);
+
+        Ok(())
+    }
+
+    fn record_commands(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        state: &mut DeviceState,
+        objects: &mut RecordedObjects,
+    ) -> Result<(), Error> {
+        if let Some(label) = self.label.as_ref() {
+            debug::set_object_name(&self.device, command_buffer, label);
+            push_debug_group(&self.device, command_buffer, label);
+        }
+
         let mut pass = 0;
         let mut command_iter = self.state.iter();
         while let Some(command) = command_iter.next() {
             match command {
                 Command::CopyBufferToBuffer { src, dst, size_bytes } => {
-                    src.buffer.transition_usage_now(command_buffer, BufferUsage::COPY_SRC)?;
-                    dst.buffer.transition_usage_now(command_buffer, BufferUsage::COPY_DST)?;
+                    let size = *size_bytes as vk::DeviceSize;
+                    src.buffer.transition_range_usage_now(
+                        command_buffer,
+                        BufferUsage::COPY_SRC,
+                        src.offset as u64,
+                        size,
+                    )?;
+                    dst.buffer.transition_range_usage_now(
+                        command_buffer,
+                        BufferUsage::COPY_DST,
+                        dst.offset as u64,
+                        size,
+                    )?;
                     let region = vk::BufferCopy {
                         size: *size_bytes as vk::DeviceSize,
                         src_offset: src.offset as vk::DeviceSize,
@@ -181,7 +411,13 @@ impl CommandBufferInner {
                     }
                 }
                 Command::CopyBufferToTexture { src, dst, size_texels } => {
-                    src.buffer.transition_usage_now(command_buffer, BufferUsage::COPY_SRC)?;
+                    // The texel data extends from the offset to at most the end of the buffer
+                    src.buffer.transition_range_usage_now(
+                        command_buffer,
+                        BufferUsage::COPY_SRC,
+                        src.offset as u64,
+                        vk::WHOLE_SIZE,
+                    )?;
                     dst.texture
                         .transition_usage_now(command_buffer, TextureUsage::COPY_DST, None)?;
                     let region = buffer_image_copy(src, dst, *size_texels);
@@ -198,7 +434,12 @@ impl CommandBufferInner {
                 Command::CopyTextureToBuffer { src, dst, size_texels } => {
                     src.texture
                         .transition_usage_now(command_buffer, TextureUsage::COPY_SRC, None)?;
-                    dst.buffer.transition_usage_now(command_buffer, BufferUsage::COPY_DST)?;
+                    dst.buffer.transition_range_usage_now(
+                        command_buffer,
+                        BufferUsage::COPY_DST,
+                        dst.offset as u64,
+                        vk::WHOLE_SIZE,
+                    )?;
                     let region = buffer_image_copy(dst, src, *size_texels);
                     unsafe {
                         self.device.raw.cmd_copy_image_to_buffer(
@@ -240,6 +481,26 @@ impl CommandBufferInner {
                         );
                     }
                 }
+                Command::BlitTextureToTexture { src, dst, filter }
+                    if !compute_blit::is_blit_supported(&self.device, src, dst, *filter)
+                        && compute_blit::is_compute_blit_supported(&self.device, src, dst, *filter) =>
+                {
+                    let src_subresource = Some(texture::Subresource {
+                        array_layer: src.array_layer,
+                        mip_level: src.mip_level,
+                    });
+                    src.texture
+                        .transition_usage_now(command_buffer, TextureUsage::SAMPLED, src_subresource)?;
+
+                    let dst_subresource = Some(texture::Subresource {
+                        array_layer: dst.array_layer,
+                        mip_level: dst.mip_level,
+                    });
+                    dst.texture
+                        .transition_usage_now(command_buffer, TextureUsage::STORAGE, dst_subresource)?;
+
+                    compute_blit::record_compute_blit(&self.device, state, objects, command_buffer, src, dst, *filter)?;
+                }
                 Command::BlitTextureToTexture { src, dst, filter } => {
                     let src_usage = TextureUsage::COPY_SRC;
                     let src_subresource = Some(texture::Subresource {
@@ -277,6 +538,7 @@ impl CommandBufferInner {
                     width,
                     height,
                     sample_count,
+                    flip_viewport,
                 } => {
                     self.state.resource_usages.per_pass[pass].transition_for_pass(command_buffer)?;
                     command_iter = self.record_render_pass(
@@ -287,7 +549,9 @@ impl CommandBufferInner {
                         *width,
                         *height,
                         *sample_count,
+                        *flip_viewport,
                         state,
+                        objects,
                     )?;
                     pass += 1;
                 }
@@ -313,10 +577,55 @@ impl CommandBufferInner {
                     insert_debug_marker(&self.device, command_buffer, label_name)
                 }
                 Command::PopDebugGroup => pop_debug_group(&self.device, command_buffer),
+                &Command::WriteTimestamp {
+                    ref query_set,
+                    query_index,
+                } => unsafe {
+                    self.device
+                        .raw
+                        .cmd_reset_query_pool(command_buffer, query_set.handle, query_index, 1);
+                    self.device.raw.cmd_write_timestamp(
+                        command_buffer,
+                        vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                        query_set.handle,
+                        query_index,
+                    );
+                },
+                &Command::ResolveQuerySet {
+                    ref query_set,
+                    first_query,
+                    query_count,
+                    ref destination,
+                    destination_offset,
+                } => {
+                    let stride = std::mem::size_of::<u64>() as vk::DeviceSize;
+                    destination.transition_range_usage_now(
+                        command_buffer,
+                        BufferUsage::COPY_DST,
+                        destination_offset as vk::DeviceSize,
+                        stride * query_count as vk::DeviceSize,
+                    )?;
+                    unsafe {
+                        self.device.raw.cmd_copy_query_pool_results(
+                            command_buffer,
+                            query_set.handle,
+                            first_query,
+                            query_count,
+                            destination.handle,
+                            destination_offset as vk::DeviceSize,
+                            stride,
+                            vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT,
+                        );
+                    }
+                }
                 _ => unreachable!("command: {:?}", command),
             }
         }
 
+        if self.label.is_some() {
+            pop_debug_group(&self.device, command_buffer);
+        }
+
         Ok(())
     }
 
@@ -329,13 +638,17 @@ impl CommandBufferInner {
         width: u32,
         height: u32,
         state: &mut DeviceState,
+        objects: &mut RecordedObjects,
         sample_count: u32,
     ) -> Result<(), Error> {
         let mut query = RenderPassCacheQuery::default();
 
         query.set_sample_count(sample_count);
 
-        for color_attachment in color_attachments.iter() {
+        for (index, color_attachment) in color_attachments.iter().enumerate() {
+            if let Some(ref resolve_target) = color_attachment.resolve_target {
+                validate_resolve_target(&self.device, index, &color_attachment.attachment, resolve_target)?;
+            }
             query.add_color(ColorInfo {
                 format: color_attachment.attachment.texture.descriptor.format,
                 load_op: color_attachment.load_op,
@@ -357,16 +670,12 @@ impl CommandBufferInner {
         let mut attachments = SmallVec::<[vk::ImageView; 1 + render_pass::MAX_COLOR_ATTACHMENTS * 2]>::new();
 
         for color_attachment in color_attachments.iter() {
-            clear_values.push(vk::ClearValue {
-                color: vk::ClearColorValue {
-                    float32: [
-                        color_attachment.clear_color.r,
-                        color_attachment.clear_color.g,
-                        color_attachment.clear_color.b,
-                        color_attachment.clear_color.a,
-                    ],
-                },
-            });
+            let color = match color_attachment.clear_value {
+                ClearValue::Float(float32) => vk::ClearColorValue { float32 },
+                ClearValue::Uint(uint32) => vk::ClearColorValue { uint32 },
+                ClearValue::Sint(int32) => vk::ClearColorValue { int32 },
+            };
+            clear_values.push(vk::ClearValue { color });
             attachments.push(color_attachment.attachment.handle);
         }
 
@@ -397,11 +706,15 @@ impl CommandBufferInner {
             ..Default::default()
         };
         log::trace!("framebuffer: width: {}, height: {}", width, height);
-        let framebuffer = unsafe { self.device.raw.create_framebuffer(&create_info, None)? };
-        let serial = state.get_next_pending_serial();
-        state.get_fenced_deleter().delete_when_unused(framebuffer, serial);
-
-        //drop(state);
+        let framebuffer = unsafe {
+            self.device
+                .raw
+                .create_framebuffer(&create_info, self.device.allocation_callbacks())?
+        };
+        if let Some(label) = self.label.as_ref() {
+            debug::set_object_name(&self.device, framebuffer, label);
+        }
+        objects.framebuffers.push(framebuffer);
 
         let begin_info = vk::RenderPassBeginInfo {
             render_pass,
@@ -424,28 +737,40 @@ impl CommandBufferInner {
         Ok(())
     }
 
-    fn record_render_pass_dynamic_state_defaults(&self, command_buffer: vk::CommandBuffer, width: u32, height: u32) {
+    fn record_render_pass_dynamic_state_defaults(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        width: u32,
+        height: u32,
+        flip_viewport: bool,
+    ) {
         unsafe {
             self.device.raw.cmd_set_line_width(command_buffer, 1.0);
             self.device.raw.cmd_set_depth_bounds(command_buffer, 0.0, 1.0);
+            self.device.raw.cmd_set_depth_bias(command_buffer, 0.0, 0.0, 0.0);
             self.device
                 .raw
                 .cmd_set_stencil_reference(command_buffer, vk::StencilFaceFlags::FRONT_AND_BACK, 0);
+            self.device
+                .raw
+                .cmd_set_stencil_compare_mask(command_buffer, vk::StencilFaceFlags::FRONT_AND_BACK, 0);
+            self.device
+                .raw
+                .cmd_set_stencil_write_mask(command_buffer, vk::StencilFaceFlags::FRONT_AND_BACK, 0);
             self.device
                 .raw
                 .cmd_set_blend_constants(command_buffer, &[0.0, 0.0, 0.0, 0.0]);
-            self.device.raw.cmd_set_viewport(
-                command_buffer,
-                0,
-                &[vk::Viewport {
-                    x: 0.0,
-                    y: 0.0,
-                    width: width as f32,
-                    height: height as f32,
-                    min_depth: 0.0,
-                    max_depth: 1.0,
-                }],
-            );
+            let viewport = vk::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+                min_depth: 0.0,
+                max_depth: 1.0,
+            };
+            self.device
+                .raw
+                .cmd_set_viewport(command_buffer, 0, &[viewport_with_flip(viewport, flip_viewport)]);
             self.device.raw.cmd_set_scissor(
                 command_buffer,
                 0,
@@ -467,8 +792,19 @@ impl CommandBufferInner {
         width: u32,
         height: u32,
         sample_count: u32,
+        flip_viewport: bool,
         state: &mut DeviceState,
+        objects: &mut RecordedObjects,
     ) -> Result<I, Error> {
+        if flip_viewport && !self.device.adapter.supports_maintenance1() {
+            log::error!("RenderPassDescriptor::flip_viewport requires AdapterFeatures::negative_viewport_height");
+            return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+        }
+
+        if color_attachments.is_empty() && depth_stencil_attachment.is_none() {
+            validate_attachmentless(&self.device, width, height, sample_count)?;
+        }
+
         self.record_render_pass_begin(
             command_buffer,
             color_attachments,
@@ -476,15 +812,19 @@ impl CommandBufferInner {
             width,
             height,
             state,
+            objects,
             sample_count,
         )?;
 
-        self.record_render_pass_dynamic_state_defaults(command_buffer, width, height);
+        self.record_render_pass_dynamic_state_defaults(command_buffer, width, height, flip_viewport);
 
         let mut last_pipeline: Option<&Arc<RenderPipelineInner>> = None;
 
         let mut descriptor_sets = DescriptorSetTracker::default();
 
+        // The counter buffers from the most recent `BeginTransformFeedback`
+        let mut transform_feedback_counters: Vec<(vk::Buffer, vk::DeviceSize)> = Vec::new();
+
         while let Some(command) = command_iter.next() {
             match command {
                 Command::EndRenderPass => unsafe {
@@ -561,6 +901,88 @@ impl CommandBufferInner {
                         )
                     }
                 }
+                &Command::DrawIndirectByteCount {
+                    instance_count,
+                    first_instance,
+                    ref counter_buffer,
+                    counter_offset,
+                    vertex_stride,
+                } => {
+                    let bind_point = vk::PipelineBindPoint::GRAPHICS;
+                    descriptor_sets.flush(&self.device, command_buffer, bind_point);
+                    unsafe {
+                        self.device.raw_ext.transform_feedback.cmd_draw_indirect_byte_count_ext(
+                            command_buffer,
+                            instance_count,
+                            first_instance,
+                            counter_buffer.handle,
+                            counter_offset,
+                            0,
+                            vertex_stride,
+                        );
+                    }
+                }
+                &Command::BeginTransformFeedback { ref buffers, resume } => {
+                    let handles: SmallVec<[vk::Buffer; 4]> = buffers.iter().map(|b| b.buffer.handle).collect();
+                    let offsets: SmallVec<[vk::DeviceSize; 4]> = buffers.iter().map(|b| b.offset).collect();
+                    let sizes: SmallVec<[vk::DeviceSize; 4]> = buffers.iter().map(|b| b.size).collect();
+
+                    // A null counter buffer starts writing at the beginning of the binding
+                    transform_feedback_counters.clear();
+                    transform_feedback_counters.extend(buffers.iter().map(|b| match b.counter {
+                        Some((ref counter_buffer, counter_offset)) => (counter_buffer.handle, counter_offset),
+                        None => (vk::Buffer::null(), 0),
+                    }));
+                    let counter_handles: SmallVec<[vk::Buffer; 4]> =
+                        transform_feedback_counters.iter().map(|c| c.0).collect();
+                    let counter_offsets: SmallVec<[vk::DeviceSize; 4]> =
+                        transform_feedback_counters.iter().map(|c| c.1).collect();
+
+                    let transform_feedback = &self.device.raw_ext.transform_feedback;
+                    unsafe {
+                        transform_feedback.cmd_bind_transform_feedback_buffers_ext(
+                            command_buffer,
+                            0,
+                            handles.len() as u32,
+                            handles.as_ptr(),
+                            offsets.as_ptr(),
+                            sizes.as_ptr(),
+                        );
+                        if resume {
+                            transform_feedback.cmd_begin_transform_feedback_ext(
+                                command_buffer,
+                                0,
+                                counter_handles.len() as u32,
+                                counter_handles.as_ptr(),
+                                counter_offsets.as_ptr(),
+                            );
+                        } else {
+                            transform_feedback.cmd_begin_transform_feedback_ext(
+                                command_buffer,
+                                0,
+                                0,
+                                std::ptr::null(),
+                                std::ptr::null(),
+                            );
+                        }
+                    }
+                }
+                Command::EndTransformFeedback => {
+                    let counter_handles: SmallVec<[vk::Buffer; 4]> =
+                        transform_feedback_counters.iter().map(|c| c.0).collect();
+                    let counter_offsets: SmallVec<[vk::DeviceSize; 4]> =
+                        transform_feedback_counters.iter().map(|c| c.1).collect();
+                    unsafe {
+                        self.device.raw_ext.transform_feedback.cmd_end_transform_feedback_ext(
+                            command_buffer,
+                            0,
+                            counter_handles.len() as u32,
+                            counter_handles.as_ptr(),
+                            counter_offsets.as_ptr(),
+                        );
+                    }
+                    transform_feedback_counters.clear();
+                }
                 &Command::SetPushConstants {
                     stages,
                     offset_bytes,
@@ -593,6 +1015,11 @@ impl CommandBufferInner {
                             .cmd_set_blend_constants(command_buffer, &blend_constants);
                     }
                 }
+                Command::SetDepthBias { constant, clamp, slope } => unsafe {
+                    self.device
+                        .raw
+                        .cmd_set_depth_bias(command_buffer, *constant, *clamp, *slope);
+                },
                 Command::SetIndexBuffer { buffer, offset } => {
                     // TODO: set_index_buffer / set_pipeline error handling
                     let pipeline = last_pipeline.expect("RenderPass: set_index_buffer called before set_pipeline");
@@ -626,6 +1053,24 @@ impl CommandBufferInner {
                         self.device
                             .raw
                             .cmd_bind_pipeline(command_buffer, bind_point, pipeline.handle);
+                        if let Some(color) = pipeline.blend_color {
+                            let blend_constants = [color.r, color.g, color.b, color.a];
+                            self.device
+                                .raw
+                                .cmd_set_blend_constants(command_buffer, &blend_constants);
+                        }
+                        let (constant, clamp, slope) = pipeline.depth_bias;
+                        self.device
+                            .raw
+                            .cmd_set_depth_bias(command_buffer, constant, clamp, slope);
+                        let (read_mask, write_mask) = pipeline.stencil_masks;
+                        let front_face = vk::StencilFaceFlags::FRONT_AND_BACK;
+                        self.device
+                            .raw
+                            .cmd_set_stencil_compare_mask(command_buffer, front_face, read_mask);
+                        self.device
+                            .raw
+                            .cmd_set_stencil_write_mask(command_buffer, front_face, write_mask);
                     }
                     descriptor_sets.on_pipeline_layout_change(&pipeline.layout);
                 }
@@ -637,6 +1082,22 @@ impl CommandBufferInner {
                             .cmd_set_stencil_reference(command_buffer, front_face, *reference);
                     }
                 }
+                Command::SetStencilCompareMask { mask } => {
+                    let front_face = vk::StencilFaceFlags::FRONT_AND_BACK;
+                    unsafe {
+                        self.device
+                            .raw
+                            .cmd_set_stencil_compare_mask(command_buffer, front_face, *mask);
+                    }
+                }
+                Command::SetStencilWriteMask { mask } => {
+                    let front_face = vk::StencilFaceFlags::FRONT_AND_BACK;
+                    unsafe {
+                        self.device
+                            .raw
+                            .cmd_set_stencil_write_mask(command_buffer, front_face, *mask);
+                    }
+                }
                 Command::SetScissorRect { x, y, width, height } => {
                     let (x, y, width, height) = (*x as i32, *y as i32, *width, *height);
                     unsafe {
@@ -666,6 +1127,7 @@ impl CommandBufferInner {
                         min_depth: *min_depth,
                         max_depth: *max_depth,
                     };
+                    let viewport = viewport_with_flip(viewport, flip_viewport);
                     unsafe {
                         self.device.raw.cmd_set_viewport(command_buffer, 0, &[viewport]);
                     }
@@ -786,14 +1248,19 @@ impl CommandBufferInner {
 #[derive(Default)]
 struct DescriptorSetTracker<'a> {
     current_layout: Option<Arc<PipelineLayoutInner>>,
-    sets: [vk::DescriptorSet; MAX_BIND_GROUPS],
-    dirty_sets: [bool; MAX_BIND_GROUPS],
-    dynamic_offsets: [Option<&'a [u32]>; MAX_BIND_GROUPS],
+    sets: SmallVec<[vk::DescriptorSet; INLINE_BIND_GROUPS]>,
+    dirty_sets: SmallVec<[bool; INLINE_BIND_GROUPS]>,
+    dynamic_offsets: SmallVec<[Option<&'a [u32]>; INLINE_BIND_GROUPS]>,
 }
 
 impl<'a> DescriptorSetTracker<'a> {
     fn on_set_bind_group(&mut self, index: u32, set: vk::DescriptorSet, dynamic_offsets: Option<&'a [u32]>) {
         let index = index as usize;
+        if index >= self.sets.len() {
+            self.sets.resize(index + 1, vk::DescriptorSet::default());
+            self.dirty_sets.resize(index + 1, false);
+            self.dynamic_offsets.resize(index + 1, None);
+        }
         self.dirty_sets[index] = true;
         self.sets[index] = set;
         self.dynamic_offsets[index] = dynamic_offsets;
@@ -825,9 +1292,12 @@ impl<'a> DescriptorSetTracker<'a> {
             let current_layout = self.current_layout.as_ref().unwrap();
             for (index, old_bind_group_layout) in current_layout.bind_group_layouts.iter().enumerate() {
                 if let Some(new_bind_group_layout) = layout.bind_group_layouts.get(index) {
-                    // The spec states identically defined sets are compatible, but we'll only consider them compatible
-                    // if they used the same DescriptorSetLayout, as this is cheaper to compare.
-                    if new_bind_group_layout.inner.handle != old_bind_group_layout.inner.handle {
+                    // The spec states identically defined sets are compatible, so distinct layouts with
+                    // identical entries don't disturb the bound sets.
+                    if !new_bind_group_layout
+                        .inner
+                        .is_compatible_with(&old_bind_group_layout.inner)
+                    {
                         disturbed_index = Some(index);
                         break;
                     }
@@ -890,9 +1360,9 @@ impl<'a> DescriptorSetTracker<'a> {
         values: &[u8],
     ) {
         debug_assert!(
-            (size_bytes + offset_bytes) <= pipeline::MAX_PUSH_CONSTANTS_SIZE as u32,
+            (size_bytes + offset_bytes) <= device.limits.max_push_constants_size,
             "push_constants size (and offset) cannot exceed {} bytes",
-            pipeline::MAX_PUSH_CONSTANTS_SIZE
+            device.limits.max_push_constants_size
         );
         let layout = self
             .current_layout
@@ -909,12 +1379,307 @@ impl<'a> DescriptorSetTracker<'a> {
     }
 }
 
+impl CommandBuffer {
+    /// Returns a human readable listing of the recorded commands, one command per line. Passes and
+    /// debug groups are indented and resources are identified by their Vulkan handles.
+    ///
+    /// This is intended for debugging only and the format is not stable.
+    pub fn debug_dump(&self) -> String {
+        self.inner.debug_dump()
+    }
+}
+
+impl CommandBufferInner {
+    fn debug_label(&self, data_offset: usize, label_name_with_nul_len: usize) -> String {
+        let bytes = &self.state.data[data_offset..data_offset + label_name_with_nul_len];
+        let label = unsafe { CStr::from_bytes_with_nul_unchecked(bytes) };
+        label.to_string_lossy().into_owned()
+    }
+
+    fn debug_dump(&self) -> String {
+        let mut out = String::new();
+        match self.label.as_ref() {
+            Some(label) => writeln!(out, "CommandBuffer {:?}", label.to_string_lossy()),
+            None => writeln!(out, "CommandBuffer"),
+        }
+        .expect("write to String");
+
+        let mut depth = 1;
+        for command in self.state.iter() {
+            match command {
+                Command::EndComputePass | Command::EndRenderPass | Command::PopDebugGroup => {
+                    depth = usize::max(depth, 2) - 1;
+                }
+                _ => {}
+            }
+            let indent = "  ".repeat(depth);
+            self.debug_dump_command(&mut out, &indent, command)
+                .expect("write to String");
+            match command {
+                Command::BeginComputePass | Command::BeginRenderPass { .. } | Command::PushDebugGroup { .. } => {
+                    depth += 1;
+                }
+                _ => {}
+            }
+        }
+
+        out
+    }
+
+    fn debug_dump_command(&self, out: &mut String, indent: &str, command: &Command) -> std::fmt::Result {
+        write!(out, "{}", indent)?;
+        match command {
+            Command::BeginComputePass => writeln!(out, "BeginComputePass"),
+            Command::BeginTransformFeedback { buffers, resume } => {
+                writeln!(out, "BeginTransformFeedback resume: {}", resume)?;
+                for (index, binding) in buffers.iter().enumerate() {
+                    write!(
+                        out,
+                        "{}  buffer[{}]: {:?} offset: {} size: {}",
+                        indent, index, binding.buffer.handle, binding.offset, binding.size
+                    )?;
+                    if let Some((counter_buffer, counter_offset)) = binding.counter.as_ref() {
+                        write!(out, " counter: {:?} offset: {}", counter_buffer.handle, counter_offset)?;
+                    }
+                    writeln!(out)?;
+                }
+                Ok(())
+            }
+            Command::BeginRenderPass {
+                color_attachments,
+                depth_stencil_attachment,
+                width,
+                height,
+                sample_count,
+                flip_viewport,
+            } => {
+                writeln!(
+                    out,
+                    "BeginRenderPass width: {} height: {} sample_count: {} flip_viewport: {}",
+                    width, height, sample_count, flip_viewport
+                )?;
+                for (index, color_attachment) in color_attachments.iter().enumerate() {
+                    write!(
+                        out,
+                        "{}  color[{}]: {:?} load_op: {:?} store_op: {:?}",
+                        indent, index, color_attachment.attachment.handle, color_attachment.load_op, color_attachment.store_op
+                    )?;
+                    if let Some(resolve_target) = color_attachment.resolve_target.as_ref() {
+                        write!(out, " resolve_target: {:?}", resolve_target.handle)?;
+                    }
+                    writeln!(out)?;
+                }
+                if let Some(depth_stencil_attachment) = depth_stencil_attachment.as_ref() {
+                    writeln!(
+                        out,
+                        "{}  depth_stencil: {:?} depth_load_op: {:?} depth_store_op: {:?} stencil_load_op: {:?} stencil_store_op: {:?}",
+                        indent,
+                        depth_stencil_attachment.attachment.handle,
+                        depth_stencil_attachment.depth_load_op,
+                        depth_stencil_attachment.depth_store_op,
+                        depth_stencil_attachment.stencil_load_op,
+                        depth_stencil_attachment.stencil_store_op
+                    )?;
+                }
+                Ok(())
+            }
+            Command::CopyBufferToBuffer { src, dst, size_bytes } => writeln!(
+                out,
+                "CopyBufferToBuffer src: {:?} offset: {} dst: {:?} offset: {} size_bytes: {}",
+                src.buffer.handle, src.offset, dst.buffer.handle, dst.offset, size_bytes
+            ),
+            Command::CopyBufferToTexture { src, dst, size_texels } => writeln!(
+                out,
+                "CopyBufferToTexture src: {:?} offset: {} dst: {:?} mip_level: {} array_layer: {} size_texels: {:?}",
+                src.buffer.handle, src.offset, dst.texture.handle, dst.mip_level, dst.array_layer, size_texels
+            ),
+            Command::CopyTextureToBuffer { src, dst, size_texels } => writeln!(
+                out,
+                "CopyTextureToBuffer src: {:?} mip_level: {} array_layer: {} dst: {:?} offset: {} size_texels: {:?}",
+                src.texture.handle, src.mip_level, src.array_layer, dst.buffer.handle, dst.offset, size_texels
+            ),
+            Command::CopyTextureToTexture { src, dst, size_texels } => writeln!(
+                out,
+                "CopyTextureToTexture src: {:?} mip_level: {} dst: {:?} mip_level: {} size_texels: {:?}",
+                src.texture.handle, src.mip_level, dst.texture.handle, dst.mip_level, size_texels
+            ),
+            Command::BlitTextureToTexture { src, dst, filter } => writeln!(
+                out,
+                "BlitTextureToTexture src: {:?} mip_level: {} dst: {:?} mip_level: {} filter: {:?}",
+                src.texture.handle, src.mip_level, dst.texture.handle, dst.mip_level, filter
+            ),
+            Command::Dispatch { x, y, z } => writeln!(out, "Dispatch x: {} y: {} z: {}", x, y, z),
+            Command::DispatchIndirect {
+                buffer,
+                indirect_offset,
+            } => writeln!(
+                out,
+                "DispatchIndirect buffer: {:?} indirect_offset: {}",
+                buffer.inner.handle, indirect_offset
+            ),
+            Command::Draw {
+                vertex_count,
+                instance_count,
+                first_vertex,
+                first_instance,
+            } => writeln!(
+                out,
+                "Draw vertex_count: {} instance_count: {} first_vertex: {} first_instance: {}",
+                vertex_count, instance_count, first_vertex, first_instance
+            ),
+            Command::DrawIndexed {
+                index_count,
+                instance_count,
+                first_index,
+                base_vertex,
+                first_instance,
+            } => writeln!(
+                out,
+                "DrawIndexed index_count: {} instance_count: {} first_index: {} base_vertex: {} first_instance: {}",
+                index_count, instance_count, first_index, base_vertex, first_instance
+            ),
+            Command::DrawIndirect {
+                buffer,
+                indirect_offset,
+            } => writeln!(
+                out,
+                "DrawIndirect buffer: {:?} indirect_offset: {}",
+                buffer.inner.handle, indirect_offset
+            ),
+            Command::DrawIndexedIndirect {
+                buffer,
+                indirect_offset,
+            } => writeln!(
+                out,
+                "DrawIndexedIndirect buffer: {:?} indirect_offset: {}",
+                buffer.inner.handle, indirect_offset
+            ),
+            Command::DrawIndirectByteCount {
+                instance_count,
+                first_instance,
+                counter_buffer,
+                counter_offset,
+                vertex_stride,
+            } => writeln!(
+                out,
+                "DrawIndirectByteCount instance_count: {} first_instance: {} counter_buffer: {:?} counter_offset: {} vertex_stride: {}",
+                instance_count, first_instance, counter_buffer.handle, counter_offset, vertex_stride
+            ),
+            Command::EndComputePass => writeln!(out, "EndComputePass"),
+            Command::EndRenderPass => writeln!(out, "EndRenderPass"),
+            Command::EndTransformFeedback => writeln!(out, "EndTransformFeedback"),
+            &Command::InsertDebugMarker {
+                data_offset,
+                label_name_with_nul_len,
+            } => writeln!(
+                out,
+                "InsertDebugMarker {:?}",
+                self.debug_label(data_offset, label_name_with_nul_len)
+            ),
+            Command::PopDebugGroup => writeln!(out, "PopDebugGroup"),
+            &Command::PushDebugGroup {
+                data_offset,
+                label_name_with_nul_len,
+            } => writeln!(
+                out,
+                "PushDebugGroup {:?}",
+                self.debug_label(data_offset, label_name_with_nul_len)
+            ),
+            Command::ResolveQuerySet {
+                query_set,
+                first_query,
+                query_count,
+                destination,
+                destination_offset,
+            } => writeln!(
+                out,
+                "ResolveQuerySet query_set: {:?} first_query: {} query_count: {} destination: {:?} destination_offset: {}",
+                query_set.handle, first_query, query_count, destination.handle, destination_offset
+            ),
+            Command::SetComputePipeline { pipeline } => {
+                writeln!(out, "SetComputePipeline pipeline: {:?}", pipeline.handle)
+            }
+            Command::SetRenderPipeline { pipeline } => writeln!(out, "SetRenderPipeline pipeline: {:?}", pipeline.handle),
+            Command::SetPushConstants {
+                stages,
+                offset_bytes,
+                size_bytes,
+                ..
+            } => writeln!(
+                out,
+                "SetPushConstants stages: {:?} offset_bytes: {} size_bytes: {}",
+                stages, offset_bytes, size_bytes
+            ),
+            Command::SetStencilReference { reference } => writeln!(out, "SetStencilReference reference: {}", reference),
+            Command::SetStencilCompareMask { mask } => writeln!(out, "SetStencilCompareMask mask: {:#x}", mask),
+            Command::SetStencilWriteMask { mask } => writeln!(out, "SetStencilWriteMask mask: {:#x}", mask),
+            Command::SetScissorRect { x, y, width, height } => writeln!(
+                out,
+                "SetScissorRect x: {} y: {} width: {} height: {}",
+                x, y, width, height
+            ),
+            Command::SetBlendColor { color } => writeln!(out, "SetBlendColor color: {:?}", color),
+            Command::SetDepthBias { constant, clamp, slope } => writeln!(
+                out,
+                "SetDepthBias constant: {} clamp: {} slope: {}",
+                constant, clamp, slope
+            ),
+            Command::SetBindGroup {
+                index,
+                bind_group,
+                dynamic_offsets,
+            } => writeln!(
+                out,
+                "SetBindGroup index: {} bind_group: {:?} dynamic_offsets: {:?}",
+                index,
+                bind_group.handle,
+                dynamic_offsets.as_ref().map(|offsets| &offsets[..])
+            ),
+            Command::SetIndexBuffer { buffer, offset } => {
+                writeln!(out, "SetIndexBuffer buffer: {:?} offset: {}", buffer.handle, offset)
+            }
+            Command::SetVertexBuffers { start_slot, buffers } => {
+                writeln!(out, "SetVertexBuffers start_slot: {}", start_slot)?;
+                for (index, (buffer, offset)) in buffers.iter().enumerate() {
+                    writeln!(
+                        out,
+                        "{}  slot[{}]: {:?} offset: {}",
+                        indent,
+                        *start_slot as usize + index,
+                        buffer.handle,
+                        offset
+                    )?;
+                }
+                Ok(())
+            }
+            Command::SetViewport {
+                x,
+                y,
+                width,
+                height,
+                min_depth,
+                max_depth,
+            } => writeln!(
+                out,
+                "SetViewport x: {} y: {} width: {} height: {} min_depth: {} max_depth: {}",
+                x, y, width, height, min_depth, max_depth
+            ),
+            Command::WriteTimestamp { query_set, query_index } => writeln!(
+                out,
+                "WriteTimestamp query_set: {:?} query_index: {}",
+                query_set.handle, query_index
+            ),
+        }
+    }
+}
+
 impl Drop for CommandBufferInner {
     fn drop(&mut self) {
-        let mut state = CommandEncoderState::default();
-        std::mem::swap(&mut state, &mut self.state);
-        state.reset();
-        let mut command_encoder_pool = self.device.command_encoder_pool.lock();
-        command_encoder_pool.push(state);
+        if let Some(recording) = self.recording.get_mut().take() {
+            let mut state = self.device.state.lock();
+            recording.retire(&mut state);
+        }
+        let state = std::mem::take(&mut self.state);
+        state.release(&self.device);
     }
 }
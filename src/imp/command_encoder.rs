@@ -1,22 +1,29 @@
 use ash::vk;
+use parking_lot::Mutex;
 
 use std::convert::TryFrom;
 
 use crate::{
-    BindGroup, BindingType, Buffer, BufferCopyView, BufferUsage, Color, CommandBuffer, CommandEncoder,
-    ComputePassEncoder, ComputePipeline, Extent3d, FilterMode, LoadOp, RenderPassColorAttachmentDescriptor,
+    AttachmentlessDescriptor, BindGroup, BindingType, Buffer, BufferCopyView, BufferUsage, ClearValue, Color,
+    CommandBuffer, CommandEncoder, CommandEncoderDescriptor, ComputePassDescriptor, ComputePassEncoder,
+    ComputePipeline, DebugGroupEncoder, DebugScopeGuard, DispatchIndirectCommand, DrawIndexedIndirectCommand,
+    DrawIndirectCommand, Extent3d, FilterMode, LoadOp, QuerySet, RenderPassColorAttachmentDescriptor,
     RenderPassDepthStencilAttachmentDescriptor, RenderPassDescriptor, RenderPassEncoder, RenderPipeline, ShaderStage,
-    StoreOp, TextureBlitView, TextureCopyView, TextureUsage,
+    StoreOp, TextureBlitView, TextureCopyView, TextureDataLayout, TextureFormat, TextureUsage,
+    COPY_BYTES_PER_ROW_ALIGNMENT,
 };
 
+use std::ffi::CString;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
-use crate::imp::command::{BufferCopy, Command, TextureBlit, TextureCopy};
+use crate::imp::command::{BufferCopy, Command, TextureBlit, TextureCopy, TransformFeedbackBuffer};
 use crate::imp::pass_resource_usage::{CommandBufferResourceUsage, PassResourceUsageTracker};
-use crate::imp::{binding, pipeline};
+use crate::imp::{binding, texture};
 use crate::imp::{
-    CommandBufferInner, CommandEncoderInner, ComputePassEncoderInner, DeviceInner, RenderPassEncoderInner,
-    TextureViewInner,
+    BufferInner, CommandBufferInner, CommandEncoderInner, ComputePassEncoderInner, DeviceInner, RenderPassEncoderInner,
+    TextureInner, TextureViewInner,
 };
 
 use crate::error::Error;
@@ -27,7 +34,7 @@ pub struct RenderPassColorAttachmentInfo {
     pub resolve_target: Option<Arc<TextureViewInner>>,
     pub load_op: LoadOp,
     pub store_op: StoreOp,
-    pub clear_color: Color,
+    pub clear_value: ClearValue,
 }
 
 impl<'a> From<&RenderPassColorAttachmentDescriptor<'a>> for RenderPassColorAttachmentInfo {
@@ -37,7 +44,7 @@ impl<'a> From<&RenderPassColorAttachmentDescriptor<'a>> for RenderPassColorAttac
             resolve_target: descriptor.resolve_target.map(|v| Arc::clone(&v.inner)),
             load_op: descriptor.load_op,
             store_op: descriptor.store_op,
-            clear_color: descriptor.clear_color,
+            clear_value: descriptor.clear_value,
         }
     }
 }
@@ -67,6 +74,10 @@ impl<'a> From<RenderPassDepthStencilAttachmentDescriptor<'a>> for RenderPassDept
     }
 }
 
+/// The recorded commands are stored in a plain `Vec` and can be iterated in order with `iter`,
+/// e.g. for validation before the commands are recorded into a `vk::CommandBuffer`. Commands with
+/// a variable number of resources keep them inline in a `SmallVec`, so recording a command does
+/// not allocate in the common case (see the `command_size` test).
 #[derive(Debug, Default)]
 pub struct CommandEncoderState {
     pub commands: Vec<Command>,
@@ -74,6 +85,141 @@ pub struct CommandEncoderState {
     pub data: Vec<u8>,
 }
 
+/// Validates the layout of the texture data in the buffer and converts it to texels, as expected
+/// by `VkBufferImageCopy`.
+fn buffer_copy(
+    view: &BufferCopyView,
+    texture: &TextureInner,
+    copy_size: Extent3d,
+    array_layer_count: u32,
+) -> Result<BufferCopy, Error> {
+    let format = texture.descriptor.format;
+    let required_size = required_bytes_in_copy(view.layout, format, copy_size, array_layer_count)?;
+    let buffer_size = view.buffer.inner.descriptor.size;
+    if required_size > buffer_size {
+        log::error!(
+            "buffer is too small for the copy: required size: {}, buffer size: {}",
+            required_size,
+            buffer_size
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    Ok(buffer_copy_with_layout(
+        Arc::clone(&view.buffer.inner),
+        view.layout,
+        format,
+    ))
+}
+
+/// Validates the layout of the texel data for a copy and returns the number of bytes, including
+/// the layout offset, that the data spans
+pub fn required_bytes_in_copy(
+    layout: TextureDataLayout,
+    format: TextureFormat,
+    copy_size: Extent3d,
+    array_layer_count: u32,
+) -> Result<usize, Error> {
+    if copy_size.width == 0 || copy_size.height == 0 || copy_size.depth == 0 {
+        log::error!("copy size must not be zero: {:?}", copy_size);
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    let (block_width, block_height) = texture::block_dimensions(format);
+    let block_size = texture::pixel_size(format);
+    let blocks_wide = (copy_size.width + block_width - 1) / block_width;
+    let blocks_high = (copy_size.height + block_height - 1) / block_height;
+    let row_size = blocks_wide * block_size;
+    let image_count = copy_size.depth * array_layer_count;
+
+    if layout.bytes_per_row == 0 {
+        if blocks_high > 1 || image_count > 1 {
+            log::error!("bytes_per_row must be specified when copying more than one row");
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+    } else if layout.bytes_per_row % COPY_BYTES_PER_ROW_ALIGNMENT != 0
+        || layout.bytes_per_row % block_size != 0
+        || layout.bytes_per_row < row_size
+    {
+        log::error!(
+            "bytes_per_row ({}) must be a multiple of {} and at least the size of a row ({}) for format: {:?}",
+            layout.bytes_per_row,
+            COPY_BYTES_PER_ROW_ALIGNMENT,
+            row_size,
+            format
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    if layout.rows_per_image != 0 && layout.rows_per_image < blocks_high {
+        log::error!(
+            "rows_per_image ({}) must be at least the height of the copy ({} rows)",
+            layout.rows_per_image,
+            blocks_high
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    if layout.offset % 4 != 0 || (!texture::is_depth_or_stencil(format) && layout.offset % block_size as usize != 0) {
+        log::error!(
+            "offset ({}) must be a multiple of 4 and of the texel block size ({}) for format: {:?}",
+            layout.offset,
+            block_size,
+            format
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    let bytes_per_row = if layout.bytes_per_row == 0 {
+        row_size
+    } else {
+        layout.bytes_per_row
+    } as usize;
+    let rows_per_image = if layout.rows_per_image == 0 {
+        blocks_high
+    } else {
+        layout.rows_per_image
+    } as usize;
+    let image_size = bytes_per_row * rows_per_image;
+    let required_size = layout.offset
+        + (image_count as usize - 1) * image_size
+        + (blocks_high as usize - 1) * bytes_per_row
+        + row_size as usize;
+
+    Ok(required_size)
+}
+
+/// Validates that the queries in the range `first_query..first_query + query_count` exist in the
+/// query set
+fn validate_query_range(query_set: &QuerySet, first_query: u32, query_count: u32) -> Result<(), Error> {
+    let count = query_set.count();
+    if first_query.checked_add(query_count).map_or(true, |end| end > count) {
+        log::error!(
+            "invalid query range: {}..{}, query set count: {}",
+            first_query,
+            u64::from(first_query) + u64::from(query_count),
+            count
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+    Ok(())
+}
+
+pub fn buffer_copy_with_layout(
+    buffer: Arc<BufferInner>,
+    layout: TextureDataLayout,
+    format: TextureFormat,
+) -> BufferCopy {
+    let (block_width, block_height) = texture::block_dimensions(format);
+    let block_size = texture::pixel_size(format);
+    BufferCopy {
+        buffer,
+        offset: layout.offset,
+        row_length: layout.bytes_per_row / block_size * block_width,
+        image_height: layout.rows_per_image * block_height,
+    }
+}
+
 impl CommandEncoderState {
     pub fn new() -> CommandEncoderState {
         let commands = Vec::new();
@@ -99,43 +245,87 @@ impl CommandEncoderState {
         self.resource_usages.clear();
         self.data.clear();
     }
+
+    /// The capacity in bytes of the command and data storage
+    pub fn retained_bytes(&self) -> usize {
+        self.commands.capacity() * std::mem::size_of::<Command>() + self.data.capacity()
+    }
+
+    /// Clears the state and returns it to the device's pool for reuse by `create_command_encoder`.
+    pub fn release(mut self, device: &DeviceInner) {
+        // The resource references are dropped before the pool is locked because dropping them may
+        // lock the device state
+        self.reset();
+        let mut command_encoder_pool = device.command_encoder_pool.lock();
+        command_encoder_pool.push(self);
+    }
 }
 
 impl CommandEncoderInner {
-    pub fn new(device: Arc<DeviceInner>) -> Result<CommandEncoderInner, Error> {
+    pub fn new(device: Arc<DeviceInner>, descriptor: CommandEncoderDescriptor) -> Result<CommandEncoderInner, Error> {
         let state = CommandEncoderState::new();
-        Ok(CommandEncoderInner { device, state })
+        CommandEncoderInner::with_device_and_state(device, state, descriptor)
     }
 
     pub fn with_device_and_state(
         device: Arc<DeviceInner>,
         state: CommandEncoderState,
+        descriptor: CommandEncoderDescriptor,
     ) -> Result<CommandEncoderInner, Error> {
-        Ok(CommandEncoderInner { device, state })
+        let label = match descriptor.label {
+            Some(label) => Some(CString::new(label).map_err(|e| {
+                log::error!("invalid command encoder label: {:?}", e);
+                Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT)
+            })?),
+            None => None,
+        };
+        Ok(CommandEncoderInner {
+            device,
+            state,
+            label,
+            one_time_submit: descriptor.one_time_submit,
+        })
     }
 
     fn push(&mut self, command: Command) {
         self.state.push(command)
     }
 
+    fn push_debug_group(&mut self, group_label: &str) {
+        let data_offset = self.state.data.len();
+        let label_name_with_nul_len = 1 + group_label.len();
+        self.state.data.extend(group_label.as_bytes());
+        self.state.data.push(0);
+        self.push(Command::PushDebugGroup {
+            label_name_with_nul_len,
+            data_offset,
+        });
+    }
+
     fn set_push_constants<T: Copy>(&mut self, stages: ShaderStage, offset_bytes: usize, value: T) -> Result<(), Error> {
         let size_bytes = std::mem::size_of::<T>();
-        if size_bytes + offset_bytes > pipeline::MAX_PUSH_CONSTANTS_SIZE {
+        let value = unsafe { std::slice::from_raw_parts(&value as *const T as *const u8, size_bytes) };
+        self.set_push_constants_bytes(stages, offset_bytes, value)
+    }
+
+    fn set_push_constants_bytes(&mut self, stages: ShaderStage, offset_bytes: usize, data: &[u8]) -> Result<(), Error> {
+        let size_bytes = data.len();
+        let max_push_constants_size = self.device.limits.max_push_constants_size as usize;
+        if size_bytes + offset_bytes > max_push_constants_size {
             log::error!(
                 "push constants offset + value size may not exceed {} bytes",
-                pipeline::MAX_PUSH_CONSTANTS_SIZE
+                max_push_constants_size
             );
             Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT))
         } else if offset_bytes % 4 != 0 {
             log::error!("push constants offset_bytes must be a multiple of 4",);
             Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT))
-        } else if size_bytes % 4 != 0 {
-            log::error!("push constants size_of::<T> must be a multiple of 4",);
+        } else if size_bytes % 4 != 0 || size_bytes == 0 {
+            log::error!("push constants size must be a non-zero multiple of 4",);
             Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT))
         } else {
             let data_offset = self.state.data.len();
-            let value = unsafe { std::slice::from_raw_parts(&value as *const _ as *const u8, size_bytes) };
-            self.state.data.extend_from_slice(value);
+            self.state.data.extend_from_slice(data);
             self.push(Command::SetPushConstants {
                 size_bytes: size_bytes as u32,
                 offset_bytes: offset_bytes as u32,
@@ -167,18 +357,39 @@ impl CommandEncoderInner {
                 });
 
             match layout_binding.binding_type {
-                BindingType::UniformBuffer | BindingType::DynamicUniformBuffer => {
-                    let (buffer, _) = binding
+                BindingType::UniformBuffer => {
+                    let (buffer, offset, size) = binding::buffer_binding_region(&binding.resource)
+                        .expect("BindingType::UniformBuffer => BindingResource::Buffer");
+                    usage_tracker.buffer_range_used_as(
+                        buffer.inner.clone(),
+                        offset as u64,
+                        size as u64,
+                        BufferUsage::UNIFORM,
+                    );
+                }
+                BindingType::StorageBuffer => {
+                    let (buffer, offset, size) = binding::buffer_binding_region(&binding.resource)
+                        .expect("BindingType::StorageBuffer => BindingResource::Buffer");
+                    usage_tracker.buffer_range_used_as(
+                        buffer.inner.clone(),
+                        offset as u64,
+                        size as u64,
+                        BufferUsage::STORAGE,
+                    );
+                }
+                // The dynamic offsets may select any range of the buffer
+                BindingType::DynamicUniformBuffer => {
+                    let buffer = binding
                         .resource
-                        .as_buffer()
-                        .expect("BindingType::[Dynamic]UniformBuffer => BindingResource::Buffer");
+                        .bound_buffer()
+                        .expect("BindingType::DynamicUniformBuffer => BindingResource::Buffer");
                     usage_tracker.buffer_used_as(buffer.inner.clone(), BufferUsage::UNIFORM);
                 }
-                BindingType::StorageBuffer | BindingType::DynamicStorageBuffer => {
-                    let (buffer, _) = binding
+                BindingType::DynamicStorageBuffer => {
+                    let buffer = binding
                         .resource
-                        .as_buffer()
-                        .expect("BindingType::[Dynamic]StorageBuffer => BindingResource::Buffer");
+                        .bound_buffer()
+                        .expect("BindingType::DynamicStorageBuffer => BindingResource::Buffer");
                     usage_tracker.buffer_used_as(buffer.inner.clone(), BufferUsage::STORAGE);
                 }
                 BindingType::SampledTexture => {
@@ -209,7 +420,7 @@ impl CommandEncoderInner {
                         .expect("BindingType::WriteOnlyStorageTexture => BindingResource::TextureView");
                     usage_tracker.texture_used_as(texture_view.inner.texture.clone(), TextureUsage::STORAGE);
                 }
-                BindingType::Sampler => {
+                BindingType::Sampler | BindingType::ComparisonSampler => {
                     // no usage to track
                 }
             }
@@ -247,7 +458,84 @@ impl CommandEncoder {
     }
 
     pub fn begin_compute_pass<'a>(&'a mut self) -> ComputePassEncoder<'a> {
-        ComputePassEncoder::begin_compute_pass(&mut self.inner)
+        ComputePassEncoder::begin_compute_pass(&mut self.inner, ComputePassDescriptor::default())
+    }
+
+    /// Begins a compute pass with an optional label and timestamp writes. The timestamp query
+    /// indices must be less than the count of the query set.
+    pub fn begin_compute_pass_with_descriptor<'a>(
+        &'a mut self,
+        descriptor: ComputePassDescriptor,
+    ) -> Result<ComputePassEncoder<'a>, Error> {
+        if let Some(timestamp_writes) = descriptor.timestamp_writes {
+            let query_indices = timestamp_writes
+                .beginning_of_pass_write_index
+                .iter()
+                .chain(timestamp_writes.end_of_pass_write_index.iter());
+            for &query_index in query_indices {
+                validate_query_range(timestamp_writes.query_set, query_index, 1)?;
+            }
+        }
+        Ok(ComputePassEncoder::begin_compute_pass(&mut self.inner, descriptor))
+    }
+
+    /// Copies the results of the queries in the range `first_query..first_query + query_count` into
+    /// the `destination` buffer as tightly packed `u64` values. The buffer must have been created
+    /// with `BufferUsage::COPY_DST` and the offset must be a multiple of 8.
+    ///
+    /// The queries must have been written before they are resolved.
+    pub fn resolve_query_set(
+        &mut self,
+        query_set: &QuerySet,
+        first_query: u32,
+        query_count: u32,
+        destination: &Buffer,
+        destination_offset: usize,
+    ) -> Result<(), Error> {
+        validate_query_range(query_set, first_query, query_count)?;
+
+        let destination_descriptor = &destination.inner.descriptor;
+        if !destination_descriptor.usage.contains(BufferUsage::COPY_DST) {
+            log::error!(
+                "query resolve destination requires BufferUsage::COPY_DST: {:?}",
+                destination_descriptor.usage
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        let stride = std::mem::size_of::<u64>();
+        if destination_offset % stride != 0 {
+            log::error!(
+                "query resolve destination offset must be a multiple of {}: {}",
+                stride,
+                destination_offset
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        let required_size = destination_offset as u64 + stride as u64 * u64::from(query_count);
+        if required_size > destination_descriptor.size as u64 {
+            log::error!(
+                "query resolve destination is too small: required size: {}, buffer size: {}",
+                required_size,
+                destination_descriptor.size
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        self.inner.push(Command::ResolveQuerySet {
+            query_set: Arc::clone(&query_set.inner),
+            first_query,
+            query_count,
+            destination: Arc::clone(&destination.inner),
+            destination_offset,
+        });
+
+        let top_level_buffers = &mut self.inner.state.resource_usages.top_level_buffers;
+
+        top_level_buffers.insert(destination.inner.clone());
+
+        Ok(())
     }
 
     pub fn copy_buffer_to_buffer(
@@ -281,20 +569,48 @@ impl CommandEncoder {
         top_level_buffers.insert(dst.inner.clone());
     }
 
-    // TODO: row_pitch bytes vs texels
-    pub fn copy_buffer_to_texture(&mut self, src: BufferCopyView, dst: TextureCopyView, copy_size: Extent3d) {
+    pub fn copy_buffer_to_texture(
+        &mut self,
+        src: BufferCopyView,
+        dst: TextureCopyView,
+        copy_size: Extent3d,
+    ) -> Result<(), Error> {
+        self.copy_buffer_to_texture_layers(src, dst, copy_size, 1)
+    }
+
+    /// Copies `array_layer_count` consecutive array layers, starting at `dst.array_layer`, with a
+    /// single command. This is typically used to upload all six faces of a cube map.
+    ///
+    /// The layers are read back to back from the buffer, with each layer occupying
+    /// `rows_per_image` rows of `bytes_per_row` bytes.
+    pub fn copy_buffer_to_texture_layers(
+        &mut self,
+        src: BufferCopyView,
+        dst: TextureCopyView,
+        copy_size: Extent3d,
+        array_layer_count: u32,
+    ) -> Result<(), Error> {
+        let texture_descriptor = &dst.texture.inner.descriptor;
+        if array_layer_count == 0 || dst.array_layer + array_layer_count > texture_descriptor.array_layer_count {
+            log::error!(
+                "invalid array layer range: {}..{}, array_layer_count: {}",
+                dst.array_layer,
+                dst.array_layer + array_layer_count,
+                texture_descriptor.array_layer_count
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        let buffer_copy = buffer_copy(&src, &dst.texture.inner, copy_size, array_layer_count)?;
+
         self.inner.push(Command::CopyBufferToTexture {
-            src: BufferCopy {
-                buffer: Arc::clone(&src.buffer.inner),
-                row_length: src.row_length,
-                image_height: src.image_height,
-                offset: src.offset,
-            },
+            src: buffer_copy,
             dst: TextureCopy {
                 texture: Arc::clone(&dst.texture.inner),
                 mip_level: dst.mip_level,
                 origin_texels: dst.origin,
-                array_layer: dst.array_layer, // TODO: slice ?
+                array_layer: dst.array_layer,
+                array_layer_count,
             },
             size_texels: copy_size,
         });
@@ -304,9 +620,10 @@ impl CommandEncoder {
 
         top_level_buffers.insert(src.buffer.inner.clone());
         top_level_textures.insert(dst.texture.inner.clone());
+
+        Ok(())
     }
 
-    // TODO: row_pitch bytes vs texels
     pub fn copy_texture_to_texture(&mut self, src: TextureCopyView, dst: TextureCopyView, copy_size: Extent3d) {
         self.inner.push(Command::CopyTextureToTexture {
             src: TextureCopy {
@@ -314,12 +631,14 @@ impl CommandEncoder {
                 mip_level: src.mip_level,
                 origin_texels: src.origin,
                 array_layer: src.array_layer, // TODO: slice ?
+                array_layer_count: 1,
             },
             dst: TextureCopy {
                 texture: Arc::clone(&dst.texture.inner),
                 mip_level: dst.mip_level,
                 origin_texels: dst.origin,
                 array_layer: dst.array_layer, // TODO: slice ?
+                array_layer_count: 1,
             },
             size_texels: copy_size,
         });
@@ -330,21 +649,23 @@ impl CommandEncoder {
         top_level_textures.insert(dst.texture.inner.clone());
     }
 
-    // TODO: row_pitch bytes vs texels
-    pub fn copy_texture_to_buffer(&mut self, src: TextureCopyView, dst: BufferCopyView, copy_size: Extent3d) {
+    pub fn copy_texture_to_buffer(
+        &mut self,
+        src: TextureCopyView,
+        dst: BufferCopyView,
+        copy_size: Extent3d,
+    ) -> Result<(), Error> {
+        let buffer_copy = buffer_copy(&dst, &src.texture.inner, copy_size, 1)?;
+
         self.inner.push(Command::CopyTextureToBuffer {
             src: TextureCopy {
                 texture: Arc::clone(&src.texture.inner),
                 mip_level: src.mip_level,
                 origin_texels: src.origin,
                 array_layer: src.array_layer, // TODO: slice ?
+                array_layer_count: 1,
             },
-            dst: BufferCopy {
-                buffer: Arc::clone(&dst.buffer.inner),
-                row_length: dst.row_length,
-                image_height: dst.image_height,
-                offset: dst.offset,
-            },
+            dst: buffer_copy,
             size_texels: copy_size,
         });
 
@@ -353,8 +674,16 @@ impl CommandEncoder {
 
         top_level_textures.insert(src.texture.inner.clone());
         top_level_buffers.insert(dst.buffer.inner.clone());
+
+        Ok(())
     }
 
+    /// Copies a region of `src` to `dst`, scaling and converting the texels as needed.
+    ///
+    /// If the formats do not support `vkCmdBlitImage` with the requested filter, a compute shader
+    /// is used instead when the `compute-blit` feature is enabled. This requires the textures to be
+    /// single-sampled 2D textures, with `src` created with `TextureUsage::SAMPLED` and `dst` created
+    /// with `TextureUsage::STORAGE`.
     pub fn blit_texture_to_texture(&mut self, src: TextureBlitView, dst: TextureBlitView, filter: FilterMode) {
         self.inner.push(Command::BlitTextureToTexture {
             src: TextureBlit {
@@ -379,14 +708,7 @@ impl CommandEncoder {
     }
 
     pub fn push_debug_group(&mut self, group_label: &str) {
-        let data_offset = self.inner.state.data.len();
-        let label_name_with_nul_len = 1 + group_label.len();
-        self.inner.state.data.extend(group_label.as_bytes());
-        self.inner.state.data.push(0);
-        self.inner.push(Command::PushDebugGroup {
-            label_name_with_nul_len,
-            data_offset,
-        });
+        self.inner.push_debug_group(group_label)
     }
 
     pub fn insert_debug_marker(&mut self, marker_label: &str) {
@@ -404,14 +726,40 @@ impl CommandEncoder {
         self.inner.push(Command::PopDebugGroup)
     }
 
-    pub fn finish(self) -> Result<CommandBuffer, Error> {
+    /// Pushes a debug group that is popped when the returned guard is dropped.
+    pub fn debug_scope(&mut self, group_label: &str) -> DebugScopeGuard<Self> {
+        DebugScopeGuard::new(self, group_label)
+    }
+
+    pub fn finish(mut self) -> Result<CommandBuffer, Error> {
         // TODO: Validation?
         let command_buffer = CommandBufferInner {
-            state: self.inner.state,
-            device: self.inner.device,
+            state: std::mem::take(&mut self.inner.state),
+            device: self.inner.device.clone(),
+            label: self.inner.label.take(),
+            one_time_submit: self.inner.one_time_submit,
+            submitted: AtomicBool::new(false),
+            recording: Mutex::new(None),
         };
         Ok(CommandBuffer { inner: command_buffer })
     }
+
+    /// Abandons the recorded commands without submitting them. The encoder's storage is returned to
+    /// the device's pool, as it is when the encoder is dropped.
+    pub fn discard(self) {
+        drop(self)
+    }
+}
+
+impl Drop for CommandEncoderInner {
+    fn drop(&mut self) {
+        // `finish` moves the state into the command buffer and leaves an empty state behind, which
+        // isn't worth keeping
+        let state = std::mem::take(&mut self.state);
+        if state.retained_bytes() > 0 {
+            state.release(&self.device);
+        }
+    }
 }
 
 impl<'a> Drop for ComputePassEncoderInner<'a> {
@@ -424,17 +772,52 @@ impl<'a> Drop for ComputePassEncoderInner<'a> {
             .push(pass_resource_usage);
 
         self.top_level_encoder.push(Command::EndComputePass);
+
+        if let Some((query_set, query_index)) = self.end_of_pass_timestamp.take() {
+            self.top_level_encoder
+                .push(Command::WriteTimestamp { query_set, query_index });
+        }
+
+        if self.has_label {
+            self.top_level_encoder.push(Command::PopDebugGroup);
+        }
     }
 }
 
 impl<'a> ComputePassEncoder<'a> {
-    fn begin_compute_pass(top_level_encoder: &'a mut CommandEncoderInner) -> ComputePassEncoder<'a> {
+    fn begin_compute_pass(
+        top_level_encoder: &'a mut CommandEncoderInner,
+        descriptor: ComputePassDescriptor,
+    ) -> ComputePassEncoder<'a> {
+        // Timestamps and labels are recorded outside of the pass so that the pass boundaries
+        // remain unchanged for the resource usage tracking
+        if let Some(label) = descriptor.label {
+            top_level_encoder.push_debug_group(label);
+        }
+
+        let mut end_of_pass_timestamp = None;
+
+        if let Some(timestamp_writes) = descriptor.timestamp_writes {
+            let query_set = &timestamp_writes.query_set.inner;
+            if let Some(query_index) = timestamp_writes.beginning_of_pass_write_index {
+                top_level_encoder.push(Command::WriteTimestamp {
+                    query_set: Arc::clone(query_set),
+                    query_index,
+                });
+            }
+            if let Some(query_index) = timestamp_writes.end_of_pass_write_index {
+                end_of_pass_timestamp = Some((Arc::clone(query_set), query_index));
+            }
+        }
+
         top_level_encoder.push(Command::BeginComputePass);
 
         ComputePassEncoder {
             inner: ComputePassEncoderInner {
                 top_level_encoder,
                 usage_tracker: PassResourceUsageTracker::default(),
+                end_of_pass_timestamp,
+                has_label: descriptor.label.is_some(),
             },
         }
     }
@@ -468,11 +851,32 @@ impl<'a> ComputePassEncoder<'a> {
             .set_push_constants(stages, offset_bytes, value)
     }
 
+    /// Updates the push constants in the range `offset_bytes..offset_bytes + data.len()`. The
+    /// offset and length must be multiples of 4.
+    pub fn set_push_constants_bytes(
+        &mut self,
+        stages: ShaderStage,
+        offset_bytes: usize,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        self.inner
+            .top_level_encoder
+            .set_push_constants_bytes(stages, offset_bytes, data)
+    }
+
     pub fn dispatch(&mut self, x: u32, y: u32, z: u32) {
         self.inner.top_level_encoder.push(Command::Dispatch { x, y, z });
     }
 
+    /// Dispatches work with the arguments read from a `DispatchIndirectCommand` in `buffer` at
+    /// `indirect_offset`. The buffer requires the `INDIRECT` usage.
     pub fn dispatch_indirect(&mut self, buffer: &Buffer, indirect_offset: usize) {
+        self.inner.usage_tracker.buffer_range_used_as(
+            Arc::clone(&buffer.inner),
+            indirect_offset as u64,
+            std::mem::size_of::<DispatchIndirectCommand>() as u64,
+            BufferUsage::INDIRECT,
+        );
         self.inner.top_level_encoder.push(Command::DispatchIndirect {
             buffer: buffer.clone(),
             indirect_offset,
@@ -504,6 +908,11 @@ impl<'a> ComputePassEncoder<'a> {
     pub fn pop_debug_group(&mut self) {
         self.inner.top_level_encoder.push(Command::PopDebugGroup)
     }
+
+    /// Pushes a debug group that is popped when the returned guard is dropped.
+    pub fn debug_scope(&mut self, group_label: &str) -> DebugScopeGuard<Self> {
+        DebugScopeGuard::new(self, group_label)
+    }
 }
 
 impl<'a> Drop for RenderPassEncoderInner<'a> {
@@ -547,18 +956,14 @@ impl<'a> RenderPassEncoder<'a> {
         // images (that are used as resolve textures) could mismatch.
 
         let mut sample_count = 1;
-        let mut width = 1;
-        let mut height = 1;
+        let mut width = u32::max_value();
+        let mut height = u32::max_value();
 
         for descriptor in descriptor.color_attachments.iter() {
             let (a_sample_count, size) = descriptor.attachment.inner.get_sample_count_and_mipmap_size();
             sample_count = sample_count.max(a_sample_count);
-            width = if width == 1 { size.width } else { width.min(size.width) };
-            height = if height == 1 {
-                size.height
-            } else {
-                height.min(size.height)
-            };
+            width = width.min(size.width);
+            height = height.min(size.height);
         }
 
         if let Some(ref a) = descriptor.depth_stencil_attachment {
@@ -579,8 +984,20 @@ impl<'a> RenderPassEncoder<'a> {
             height = height.min(size.height);
         }
 
-        width = width.max(1);
-        height = height.max(1);
+        if width == u32::max_value() || height == u32::max_value() {
+            // No attachments. The size is validated when the pass is recorded.
+            let attachmentless = descriptor.attachmentless.unwrap_or(AttachmentlessDescriptor {
+                width: 1,
+                height: 1,
+                sample_count: 1,
+            });
+            width = attachmentless.width;
+            height = attachmentless.height;
+            sample_count = attachmentless.sample_count;
+        } else {
+            width = width.max(1);
+            height = height.max(1);
+        }
 
         log::trace!(
             "begin_render_pass; sample_count: {}, width: {}, height: {}",
@@ -595,8 +1012,29 @@ impl<'a> RenderPassEncoder<'a> {
             sample_count,
             width,
             height,
+            flip_viewport: descriptor.flip_viewport,
         });
 
+        if let Some(viewport) = descriptor.viewport {
+            top_level_encoder.push(Command::SetViewport {
+                x: viewport.x,
+                y: viewport.y,
+                width: viewport.width,
+                height: viewport.height,
+                min_depth: viewport.min_depth,
+                max_depth: viewport.max_depth,
+            });
+        }
+
+        if let Some(scissor_rect) = descriptor.scissor_rect {
+            top_level_encoder.push(Command::SetScissorRect {
+                x: scissor_rect.x,
+                y: scissor_rect.y,
+                width: scissor_rect.width,
+                height: scissor_rect.height,
+            });
+        }
+
         RenderPassEncoder {
             inner: RenderPassEncoderInner {
                 top_level_encoder,
@@ -619,9 +1057,12 @@ impl<'a> RenderPassEncoder<'a> {
     pub fn set_index_buffer(&mut self, buffer: &Buffer, offset: usize) {
         // TODO: If the pipeline isn't set first, this will fail in the recording phase
         // state.set_index_buffer
-        self.inner
-            .usage_tracker
-            .buffer_used_as(Arc::clone(&buffer.inner), BufferUsage::INDEX);
+        self.inner.usage_tracker.buffer_range_used_as(
+            Arc::clone(&buffer.inner),
+            offset as u64,
+            vk::WHOLE_SIZE,
+            BufferUsage::INDEX,
+        );
 
         self.inner.top_level_encoder.push(Command::SetIndexBuffer {
             buffer: Arc::clone(&buffer.inner),
@@ -631,27 +1072,47 @@ impl<'a> RenderPassEncoder<'a> {
 
     /// Set the vertex buffers, starting at the `start_slot` binding index.
     ///
+    /// Returns an error if an offset is not less than the size of the corresponding buffer.
+    ///
     /// ## Panics
     ///
     /// Panics if the length of `buffers` is not equal to the length of `offsets`.
-    pub fn set_vertex_buffers(&mut self, start_slot: u32, buffers: &[Buffer], offsets: &[usize]) {
+    pub fn set_vertex_buffers(&mut self, start_slot: u32, buffers: &[Buffer], offsets: &[usize]) -> Result<(), Error> {
         // state.set_vertex_buffers
 
         assert_eq!(buffers.len(), offsets.len(), "buffers.len() != offsets.len()");
 
+        for (index, (buffer, &offset)) in buffers.iter().zip(offsets.iter()).enumerate() {
+            let size = buffer.inner.descriptor.size;
+            if offset >= size {
+                log::error!(
+                    "vertex buffer offset must be less than the buffer size: slot: {}, offset: {}, size: {}",
+                    start_slot as usize + index,
+                    offset,
+                    size
+                );
+                return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+            }
+        }
+
         let mut buffers_vec = smallvec::SmallVec::with_capacity(buffers.len());
 
         for (index, buffer) in buffers.iter().enumerate() {
             buffers_vec.push((Arc::clone(&buffer.inner), offsets[index] as u64));
-            self.inner
-                .usage_tracker
-                .buffer_used_as(Arc::clone(&buffer.inner), BufferUsage::VERTEX);
+            self.inner.usage_tracker.buffer_range_used_as(
+                Arc::clone(&buffer.inner),
+                offsets[index] as u64,
+                vk::WHOLE_SIZE,
+                BufferUsage::VERTEX,
+            );
         }
 
         self.inner.top_level_encoder.push(Command::SetVertexBuffers {
             buffers: buffers_vec,
             start_slot,
         });
+
+        Ok(())
     }
 
     pub fn set_pipeline(&mut self, pipeline: &RenderPipeline) {
@@ -665,12 +1126,32 @@ impl<'a> RenderPassEncoder<'a> {
         self.inner.top_level_encoder.push(Command::SetBlendColor { color });
     }
 
+    /// Overrides the depth bias of the current pipeline until the next call to `set_pipeline`.
+    /// A non-zero `clamp` requires `depthBiasClamp` support on the adapter.
+    pub fn set_depth_bias(&mut self, constant: f32, clamp: f32, slope: f32) {
+        self.inner
+            .top_level_encoder
+            .push(Command::SetDepthBias { constant, clamp, slope });
+    }
+
     pub fn set_stencil_reference(&mut self, reference: u32) {
         self.inner
             .top_level_encoder
             .push(Command::SetStencilReference { reference });
     }
 
+    /// Overrides the stencil read mask of the current pipeline until the next call to `set_pipeline`.
+    pub fn set_stencil_compare_mask(&mut self, mask: u32) {
+        self.inner
+            .top_level_encoder
+            .push(Command::SetStencilCompareMask { mask });
+    }
+
+    /// Overrides the stencil write mask of the current pipeline until the next call to `set_pipeline`.
+    pub fn set_stencil_write_mask(&mut self, mask: u32) {
+        self.inner.top_level_encoder.push(Command::SetStencilWriteMask { mask });
+    }
+
     pub fn set_viewport(&mut self, x: f32, y: f32, width: f32, height: f32, min_depth: f32, max_depth: f32) {
         self.inner.top_level_encoder.push(Command::SetViewport {
             x,
@@ -714,20 +1195,109 @@ impl<'a> RenderPassEncoder<'a> {
         })
     }
 
+    /// Draws with the arguments read from a `DrawIndirectCommand` in `buffer` at `indirect_offset`.
+    /// The buffer requires the `INDIRECT` usage.
+    ///
+    /// The arguments may be written by a previous pass, e.g. by a compute shader with the buffer
+    /// bound as a storage buffer. A non-zero `first_instance` requires
+    /// `AdapterFeatures::draw_indirect_first_instance`. The arguments are not validated.
     pub fn draw_indirect(&mut self, buffer: &Buffer, indirect_offset: usize) {
+        self.inner.usage_tracker.buffer_range_used_as(
+            Arc::clone(&buffer.inner),
+            indirect_offset as u64,
+            std::mem::size_of::<DrawIndirectCommand>() as u64,
+            BufferUsage::INDIRECT,
+        );
         self.inner.top_level_encoder.push(Command::DrawIndirect {
             buffer: buffer.clone(),
             indirect_offset,
         })
     }
 
+    /// Draws with the arguments read from a `DrawIndexedIndirectCommand` in `buffer` at
+    /// `indirect_offset`. The buffer requires the `INDIRECT` usage. A non-zero `first_instance`
+    /// requires `AdapterFeatures::draw_indirect_first_instance`. As with `draw_indirect`, the
+    /// arguments are not validated.
     pub fn draw_indexed_indirect(&mut self, buffer: &Buffer, indirect_offset: usize) {
+        self.inner.usage_tracker.buffer_range_used_as(
+            Arc::clone(&buffer.inner),
+            indirect_offset as u64,
+            std::mem::size_of::<DrawIndexedIndirectCommand>() as u64,
+            BufferUsage::INDIRECT,
+        );
         self.inner.top_level_encoder.push(Command::DrawIndexedIndirect {
             buffer: buffer.clone(),
             indirect_offset,
         })
     }
 
+    /// Draws the vertices captured by transform feedback. The vertex count is the value of the
+    /// counter divided by `vertex_stride`.
+    ///
+    /// The counter must have been written in a previous pass. Requires `Extensions::transform_feedback`.
+    pub fn draw_indirect_byte_count(
+        &mut self,
+        instance_count: u32,
+        first_instance: u32,
+        counter_buffer: &Buffer,
+        counter_offset: usize,
+        vertex_stride: u32,
+    ) {
+        self.inner.usage_tracker.buffer_used_as(
+            Arc::clone(&counter_buffer.inner),
+            BufferUsage::TRANSFORM_FEEDBACK_COUNTER,
+        );
+        self.inner.top_level_encoder.push(Command::DrawIndirectByteCount {
+            instance_count,
+            first_instance,
+            counter_buffer: Arc::clone(&counter_buffer.inner),
+            counter_offset: counter_offset as u64,
+            vertex_stride,
+        })
+    }
+
+    /// Begins capturing the vertex outputs of subsequent draws into the buffers. The binding
+    /// index of each buffer is its index in `buffers`.
+    ///
+    /// If `resume` is `true`, writing continues at the positions stored in the counter buffers by
+    /// a previous `end_transform_feedback`. Otherwise writing starts at the beginning of each buffer.
+    ///
+    /// Requires `Extensions::transform_feedback`.
+    pub fn begin_transform_feedback(&mut self, buffers: &[TransformFeedbackBinding], resume: bool) {
+        let mut buffers_vec = smallvec::SmallVec::with_capacity(buffers.len());
+
+        for binding in buffers.iter() {
+            self.inner
+                .usage_tracker
+                .buffer_used_as(Arc::clone(&binding.buffer.inner), BufferUsage::TRANSFORM_FEEDBACK);
+
+            let counter = binding.counter_buffer.map(|counter_buffer| {
+                self.inner.usage_tracker.buffer_used_as(
+                    Arc::clone(&counter_buffer.inner),
+                    BufferUsage::TRANSFORM_FEEDBACK_COUNTER,
+                );
+                (Arc::clone(&counter_buffer.inner), binding.counter_offset as u64)
+            });
+
+            buffers_vec.push(TransformFeedbackBuffer {
+                buffer: Arc::clone(&binding.buffer.inner),
+                offset: binding.offset as u64,
+                size: binding.size.map(|size| size as u64).unwrap_or(vk::WHOLE_SIZE),
+                counter,
+            });
+        }
+
+        self.inner.top_level_encoder.push(Command::BeginTransformFeedback {
+            buffers: buffers_vec,
+            resume,
+        });
+    }
+
+    /// Ends the transform feedback and writes the number of bytes captured to the counter buffers.
+    pub fn end_transform_feedback(&mut self) {
+        self.inner.top_level_encoder.push(Command::EndTransformFeedback);
+    }
+
     pub fn set_push_constants<T: Copy>(
         &mut self,
         stages: ShaderStage,
@@ -739,6 +1309,19 @@ impl<'a> RenderPassEncoder<'a> {
             .set_push_constants(stages, offset_bytes, value)
     }
 
+    /// Updates the push constants in the range `offset_bytes..offset_bytes + data.len()`. The
+    /// offset and length must be multiples of 4.
+    pub fn set_push_constants_bytes(
+        &mut self,
+        stages: ShaderStage,
+        offset_bytes: usize,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        self.inner
+            .top_level_encoder
+            .set_push_constants_bytes(stages, offset_bytes, data)
+    }
+
     pub fn push_debug_group(&mut self, group_label: &str) {
         let data_offset = self.inner.top_level_encoder.state.data.len();
         let label_name_with_nul_len = 1 + group_label.len();
@@ -764,4 +1347,66 @@ impl<'a> RenderPassEncoder<'a> {
     pub fn pop_debug_group(&mut self) {
         self.inner.top_level_encoder.push(Command::PopDebugGroup)
     }
+
+    /// Pushes a debug group that is popped when the returned guard is dropped.
+    pub fn debug_scope(&mut self, group_label: &str) -> DebugScopeGuard<Self> {
+        DebugScopeGuard::new(self, group_label)
+    }
+}
+
+impl DebugGroupEncoder for CommandEncoder {
+    fn push_debug_group(&mut self, group_label: &str) {
+        CommandEncoder::push_debug_group(self, group_label)
+    }
+
+    fn pop_debug_group(&mut self) {
+        CommandEncoder::pop_debug_group(self)
+    }
+}
+
+impl<'a> DebugGroupEncoder for ComputePassEncoder<'a> {
+    fn push_debug_group(&mut self, group_label: &str) {
+        ComputePassEncoder::push_debug_group(self, group_label)
+    }
+
+    fn pop_debug_group(&mut self) {
+        ComputePassEncoder::pop_debug_group(self)
+    }
+}
+
+impl<'a> DebugGroupEncoder for RenderPassEncoder<'a> {
+    fn push_debug_group(&mut self, group_label: &str) {
+        RenderPassEncoder::push_debug_group(self, group_label)
+    }
+
+    fn pop_debug_group(&mut self) {
+        RenderPassEncoder::pop_debug_group(self)
+    }
+}
+
+impl<'a, E: DebugGroupEncoder> DebugScopeGuard<'a, E> {
+    fn new(encoder: &'a mut E, group_label: &str) -> DebugScopeGuard<'a, E> {
+        encoder.push_debug_group(group_label);
+        DebugScopeGuard { encoder }
+    }
+}
+
+impl<'a, E: DebugGroupEncoder> Deref for DebugScopeGuard<'a, E> {
+    type Target = E;
+
+    fn deref(&self) -> &E {
+        self.encoder
+    }
+}
+
+impl<'a, E: DebugGroupEncoder> DerefMut for DebugScopeGuard<'a, E> {
+    fn deref_mut(&mut self) -> &mut E {
+        self.encoder
+    }
+}
+
+impl<'a, E: DebugGroupEncoder> Drop for DebugScopeGuard<'a, E> {
+    fn drop(&mut self) {
+        self.encoder.pop_debug_group();
+    }
 }
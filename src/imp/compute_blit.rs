@@ -0,0 +1,424 @@
+//! A compute shader fallback for `CommandEncoder::blit_texture_to_texture`, used when `vkCmdBlitImage`
+//! is not supported for the source or destination format (e.g. storage-only destination formats or
+//! linear filtering of formats without `SAMPLED_IMAGE_FILTER_LINEAR`).
+//!
+//! The embedded shader is only available with the `compute-blit` feature.
+
+use ash::vk;
+
+use crate::imp::command::TextureBlit;
+use crate::imp::command_buffer::RecordedObjects;
+use crate::imp::device::DeviceState;
+use crate::imp::{sampler, texture, DeviceInner, TextureInner};
+use crate::{Error, FilterMode, TextureDimension, TextureFormatFeatures, TextureUsage};
+
+use std::{mem, ptr};
+
+#[cfg(feature = "compute-blit")]
+const COMPUTE_BLIT_SPV: Option<&[u8]> = Some(include_bytes!("shaders/compute_blit.comp.spv"));
+
+#[cfg(not(feature = "compute-blit"))]
+const COMPUTE_BLIT_SPV: Option<&[u8]> = None;
+
+/// Must match `local_size_x` and `local_size_y` in the compute shader
+const WORKGROUP_SIZE: u32 = 8;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct PushConstants {
+    src_offset: [f32; 2],
+    src_extent: [f32; 2],
+    dst_offset: [i32; 2],
+    dst_extent: [i32; 2],
+}
+
+/// Returns `true` if `vkCmdBlitImage` supports the source and destination formats with the given filter.
+pub fn is_blit_supported(device: &DeviceInner, src: &TextureBlit, dst: &TextureBlit, filter: FilterMode) -> bool {
+    let src_features = device
+        .adapter
+        .get_texture_format_features(src.texture.descriptor.format);
+    let dst_features = device
+        .adapter
+        .get_texture_format_features(dst.texture.descriptor.format);
+    src_features.contains(TextureFormatFeatures::BLIT_SRC)
+        && dst_features.contains(TextureFormatFeatures::BLIT_DST)
+        && (filter == FilterMode::Nearest || src_features.contains(TextureFormatFeatures::SAMPLED_LINEAR_FILTER))
+}
+
+/// Returns `true` if the blit can be performed with the compute shader fallback.
+pub fn is_compute_blit_supported(
+    device: &DeviceInner,
+    src: &TextureBlit,
+    dst: &TextureBlit,
+    filter: FilterMode,
+) -> bool {
+    if COMPUTE_BLIT_SPV.is_none() {
+        return false;
+    }
+    if device
+        .adapter
+        .physical_device_features
+        .shader_storage_image_write_without_format
+        != vk::TRUE
+    {
+        return false;
+    }
+    let is_single_sampled_2d = |texture: &TextureInner| {
+        texture.descriptor.dimension == TextureDimension::D2 && texture.descriptor.sample_count == 1
+    };
+    if !is_single_sampled_2d(&src.texture) || !is_single_sampled_2d(&dst.texture) {
+        return false;
+    }
+    let src_features = device
+        .adapter
+        .get_texture_format_features(src.texture.descriptor.format);
+    let dst_features = device
+        .adapter
+        .get_texture_format_features(dst.texture.descriptor.format);
+    src_features.contains(TextureFormatFeatures::SAMPLED)
+        && (filter == FilterMode::Nearest || src_features.contains(TextureFormatFeatures::SAMPLED_LINEAR_FILTER))
+        && dst_features.contains(TextureFormatFeatures::STORAGE)
+        && src.texture.descriptor.usage.contains(TextureUsage::SAMPLED)
+        && dst.texture.descriptor.usage.contains(TextureUsage::STORAGE)
+}
+
+/// The lazily created pipeline objects shared by all compute blits on a device.
+#[derive(Debug, Default)]
+pub struct ComputeBlitCache {
+    resources: Option<ComputeBlitResources>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct ComputeBlitResources {
+    shader_module: vk::ShaderModule,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    nearest_sampler: vk::Sampler,
+    linear_sampler: vk::Sampler,
+}
+
+impl ComputeBlitResources {
+    unsafe fn new(device: &DeviceInner) -> Result<ComputeBlitResources, Error> {
+        let mut resources = ComputeBlitResources::default();
+        if let Err(e) = resources.create(device) {
+            resources.destroy(device);
+            return Err(e);
+        }
+        Ok(resources)
+    }
+
+    unsafe fn create(&mut self, device: &DeviceInner) -> Result<(), Error> {
+        let code = COMPUTE_BLIT_SPV.expect("compute-blit feature is not enabled");
+
+        // Copy the code to a temp buffer to guarantee alignment
+        let mut words: Vec<u32> = vec![0; code.len() / mem::size_of::<u32>()];
+        ptr::copy_nonoverlapping(code.as_ptr(), words.as_mut_ptr() as *mut u8, words.len() * 4);
+        let create_info = vk::ShaderModuleCreateInfo::builder().code(&words);
+        self.shader_module = device
+            .raw
+            .create_shader_module(&create_info, device.allocation_callbacks())?;
+
+        let bindings = [
+            vk::DescriptorSetLayoutBinding {
+                binding: 0,
+                descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..Default::default()
+            },
+            vk::DescriptorSetLayoutBinding {
+                binding: 1,
+                descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
+                descriptor_count: 1,
+                stage_flags: vk::ShaderStageFlags::COMPUTE,
+                ..Default::default()
+            },
+        ];
+        let create_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
+        self.descriptor_set_layout = device
+            .raw
+            .create_descriptor_set_layout(&create_info, device.allocation_callbacks())?;
+
+        let set_layouts = [self.descriptor_set_layout];
+        let push_constant_ranges = [vk::PushConstantRange {
+            stage_flags: vk::ShaderStageFlags::COMPUTE,
+            offset: 0,
+            size: mem::size_of::<PushConstants>() as u32,
+        }];
+        let create_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(&set_layouts)
+            .push_constant_ranges(&push_constant_ranges);
+        self.pipeline_layout = device
+            .raw
+            .create_pipeline_layout(&create_info, device.allocation_callbacks())?;
+
+        let entry_point = b"main\0";
+        let create_info = vk::ComputePipelineCreateInfo::builder()
+            .layout(self.pipeline_layout)
+            .stage(vk::PipelineShaderStageCreateInfo {
+                stage: vk::ShaderStageFlags::COMPUTE,
+                module: self.shader_module,
+                p_name: entry_point.as_ptr() as *const _,
+                ..Default::default()
+            })
+            .build();
+        let ret = device.raw.fp_v1_0().create_compute_pipelines(
+            device.raw.handle(),
+            vk::PipelineCache::null(),
+            1,
+            &create_info,
+            device.allocation_callbacks_ptr(),
+            &mut self.pipeline,
+        );
+        if ret != vk::Result::SUCCESS {
+            return Err(Error::from(ret));
+        }
+
+        let create_sampler = |filter: FilterMode| {
+            let filter = sampler::filter_mode(filter);
+            let create_info = vk::SamplerCreateInfo {
+                mag_filter: filter,
+                min_filter: filter,
+                mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+                address_mode_u: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_v: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                address_mode_w: vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                max_anisotropy: 1.0,
+                ..Default::default()
+            };
+            device.raw.create_sampler(&create_info, device.allocation_callbacks())
+        };
+        self.nearest_sampler = create_sampler(FilterMode::Nearest)?;
+        self.linear_sampler = create_sampler(FilterMode::Linear)?;
+
+        Ok(())
+    }
+
+    unsafe fn destroy(&mut self, device: &DeviceInner) {
+        if self.linear_sampler != vk::Sampler::null() {
+            device
+                .raw
+                .destroy_sampler(self.linear_sampler, device.allocation_callbacks());
+        }
+        if self.nearest_sampler != vk::Sampler::null() {
+            device
+                .raw
+                .destroy_sampler(self.nearest_sampler, device.allocation_callbacks());
+        }
+        if self.pipeline != vk::Pipeline::null() {
+            device
+                .raw
+                .destroy_pipeline(self.pipeline, device.allocation_callbacks());
+        }
+        if self.pipeline_layout != vk::PipelineLayout::null() {
+            device
+                .raw
+                .destroy_pipeline_layout(self.pipeline_layout, device.allocation_callbacks());
+        }
+        if self.descriptor_set_layout != vk::DescriptorSetLayout::null() {
+            device
+                .raw
+                .destroy_descriptor_set_layout(self.descriptor_set_layout, device.allocation_callbacks());
+        }
+        if self.shader_module != vk::ShaderModule::null() {
+            device
+                .raw
+                .destroy_shader_module(self.shader_module, device.allocation_callbacks());
+        }
+        *self = ComputeBlitResources::default();
+    }
+}
+
+impl ComputeBlitCache {
+    fn get_resources(&mut self, device: &DeviceInner) -> Result<ComputeBlitResources, Error> {
+        match self.resources {
+            Some(resources) => Ok(resources),
+            None => {
+                let resources = unsafe { ComputeBlitResources::new(device)? };
+                self.resources = Some(resources);
+                Ok(resources)
+            }
+        }
+    }
+
+    pub fn drain(&mut self, device: &DeviceInner) {
+        if let Some(mut resources) = self.resources.take() {
+            log::trace!("destroying compute blit resources: {:?}", resources);
+            unsafe {
+                resources.destroy(device);
+            }
+        }
+    }
+}
+
+impl Drop for ComputeBlitCache {
+    fn drop(&mut self) {
+        if self.resources.is_some() {
+            log::error!("ComputeBlitCache dropped without being drained")
+        }
+    }
+}
+
+fn create_image_view(device: &DeviceInner, blit: &TextureBlit) -> Result<vk::ImageView, Error> {
+    let format = blit.texture.descriptor.format;
+    let create_info = vk::ImageViewCreateInfo {
+        image: blit.texture.handle,
+        view_type: vk::ImageViewType::TYPE_2D,
+        format: texture::image_format(format),
+        subresource_range: vk::ImageSubresourceRange {
+            aspect_mask: texture::aspect_mask(format),
+            base_mip_level: blit.mip_level,
+            level_count: 1,
+            base_array_layer: blit.array_layer,
+            layer_count: 1,
+        },
+        ..Default::default()
+    };
+    unsafe {
+        Ok(device
+            .raw
+            .create_image_view(&create_info, device.allocation_callbacks())?)
+    }
+}
+
+fn push_constants(src: &TextureBlit, dst: &TextureBlit) -> PushConstants {
+    let src_width = (src.texture.descriptor.size.width >> src.mip_level).max(1) as f32;
+    let src_height = (src.texture.descriptor.size.height >> src.mip_level).max(1) as f32;
+
+    let (mut src_min, mut src_max) = (src.bounds_texels[0], src.bounds_texels[1]);
+    let (mut dst_min, mut dst_max) = (dst.bounds_texels[0], dst.bounds_texels[1]);
+
+    // Mirrored blits are expressed by reversing the source region instead of the destination region
+    if dst_min.x > dst_max.x {
+        mem::swap(&mut dst_min.x, &mut dst_max.x);
+        mem::swap(&mut src_min.x, &mut src_max.x);
+    }
+    if dst_min.y > dst_max.y {
+        mem::swap(&mut dst_min.y, &mut dst_max.y);
+        mem::swap(&mut src_min.y, &mut src_max.y);
+    }
+
+    PushConstants {
+        src_offset: [src_min.x as f32 / src_width, src_min.y as f32 / src_height],
+        src_extent: [
+            (src_max.x - src_min.x) as f32 / src_width,
+            (src_max.y - src_min.y) as f32 / src_height,
+        ],
+        dst_offset: [dst_min.x, dst_min.y],
+        dst_extent: [dst_max.x - dst_min.x, dst_max.y - dst_min.y],
+    }
+}
+
+/// Records the blit with the compute shader. The source must already be transitioned to `SAMPLED`
+/// and the destination to `STORAGE`.
+pub fn record_compute_blit(
+    device: &DeviceInner,
+    state: &mut DeviceState,
+    objects: &mut RecordedObjects,
+    command_buffer: vk::CommandBuffer,
+    src: &TextureBlit,
+    dst: &TextureBlit,
+    filter: FilterMode,
+) -> Result<(), Error> {
+    let resources = state.get_compute_blit_cache().get_resources(device)?;
+
+    // The transient objects are deleted after the recorded commands have completed
+    let src_view = create_image_view(device, src)?;
+    objects.image_views.push(src_view);
+
+    let dst_view = create_image_view(device, dst)?;
+    objects.image_views.push(dst_view);
+
+    let pool_sizes = [
+        vk::DescriptorPoolSize {
+            ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            descriptor_count: 1,
+        },
+        vk::DescriptorPoolSize {
+            ty: vk::DescriptorType::STORAGE_IMAGE,
+            descriptor_count: 1,
+        },
+    ];
+    let create_info = vk::DescriptorPoolCreateInfo::builder()
+        .max_sets(1)
+        .pool_sizes(&pool_sizes);
+    let descriptor_pool = unsafe {
+        device
+            .raw
+            .create_descriptor_pool(&create_info, device.allocation_callbacks())?
+    };
+    objects.descriptor_pools.push(descriptor_pool);
+
+    let set_layouts = [resources.descriptor_set_layout];
+    let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(descriptor_pool)
+        .set_layouts(&set_layouts);
+    let descriptor_set = unsafe { device.raw.allocate_descriptor_sets(&allocate_info)?[0] };
+
+    let sampler = match filter {
+        FilterMode::Nearest => resources.nearest_sampler,
+        FilterMode::Linear => resources.linear_sampler,
+    };
+
+    let src_image_info = [vk::DescriptorImageInfo {
+        sampler,
+        image_view: src_view,
+        image_layout: texture::image_layout(TextureUsage::SAMPLED, src.texture.descriptor.format),
+    }];
+    let dst_image_info = [vk::DescriptorImageInfo {
+        sampler: vk::Sampler::null(),
+        image_view: dst_view,
+        image_layout: texture::image_layout(TextureUsage::STORAGE, dst.texture.descriptor.format),
+    }];
+    let writes = [
+        vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&src_image_info)
+            .build(),
+        vk::WriteDescriptorSet::builder()
+            .dst_set(descriptor_set)
+            .dst_binding(1)
+            .descriptor_type(vk::DescriptorType::STORAGE_IMAGE)
+            .image_info(&dst_image_info)
+            .build(),
+    ];
+
+    let push_constants = push_constants(src, dst);
+    let push_constants_bytes = unsafe {
+        std::slice::from_raw_parts(
+            &push_constants as *const PushConstants as *const u8,
+            mem::size_of::<PushConstants>(),
+        )
+    };
+
+    let group_count_x = (push_constants.dst_extent[0].max(0) as u32 + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+    let group_count_y = (push_constants.dst_extent[1].max(0) as u32 + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+
+    unsafe {
+        device.raw.update_descriptor_sets(&writes, &[]);
+        device
+            .raw
+            .cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, resources.pipeline);
+        device.raw.cmd_bind_descriptor_sets(
+            command_buffer,
+            vk::PipelineBindPoint::COMPUTE,
+            resources.pipeline_layout,
+            0,
+            &[descriptor_set],
+            &[],
+        );
+        device.raw.cmd_push_constants(
+            command_buffer,
+            resources.pipeline_layout,
+            vk::ShaderStageFlags::COMPUTE,
+            0,
+            push_constants_bytes,
+        );
+        device.raw.cmd_dispatch(command_buffer, group_count_x, group_count_y, 1);
+    }
+
+    Ok(())
+}
@@ -1,6 +1,5 @@
 use std::collections::HashMap;
 use std::ffi::{c_void, CStr};
-use std::mem;
 use std::sync::atomic::AtomicBool;
 
 use ash::vk;
@@ -8,11 +7,127 @@ use ash::vk;
 use parking_lot::Mutex;
 use std::fmt::{Debug, Display};
 
-use crate::Instance;
+use crate::imp::DeviceInner;
+use crate::{Instance, InstanceDescriptor, ValidationSeverity};
 use std::sync::atomic::Ordering;
 
-#[allow(dead_code)]
-#[allow(unused_variables)]
+/// Validation message filtering shared with the debug callbacks. The minimum severity and the
+/// suppressed message IDs can be extended with the `VKI_VALIDATION_SEVERITY` (`verbose`, `info`,
+/// `warning`, or `error`) and `VKI_VALIDATION_SUPPRESS` (comma separated message IDs) environment
+/// variables.
+#[derive(Debug)]
+pub struct DebugCallbackData {
+    pub instance: vk::Instance,
+    min_severity: ValidationSeverity,
+    suppressed_message_ids: Vec<i32>,
+}
+
+impl DebugCallbackData {
+    pub fn new(descriptor: &InstanceDescriptor) -> DebugCallbackData {
+        let mut min_severity = descriptor.validation_severity;
+        let mut suppressed_message_ids = descriptor.suppressed_validation_messages.to_vec();
+
+        if let Ok(severity) = std::env::var("VKI_VALIDATION_SEVERITY") {
+            match severity.to_lowercase().as_str() {
+                "verbose" => min_severity = ValidationSeverity::Verbose,
+                "info" => min_severity = ValidationSeverity::Info,
+                "warning" => min_severity = ValidationSeverity::Warning,
+                "error" => min_severity = ValidationSeverity::Error,
+                _ => log::warn!("invalid VKI_VALIDATION_SEVERITY: {}", severity),
+            }
+        }
+
+        if let Ok(message_ids) = std::env::var("VKI_VALIDATION_SUPPRESS") {
+            for message_id in message_ids.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+                let parsed = if message_id.starts_with("0x") {
+                    u32::from_str_radix(&message_id[2..], 16).map(|id| id as i32).ok()
+                } else {
+                    message_id.parse::<i32>().ok()
+                };
+                match parsed {
+                    Some(id) => suppressed_message_ids.push(id),
+                    None => log::warn!("invalid VKI_VALIDATION_SUPPRESS message id: {}", message_id),
+                }
+            }
+        }
+
+        DebugCallbackData {
+            instance: vk::Instance::null(),
+            min_severity,
+            suppressed_message_ids,
+        }
+    }
+
+    fn is_suppressed(&self, severity: ValidationSeverity, message_id: i32) -> bool {
+        severity < self.min_severity || self.suppressed_message_ids.contains(&message_id)
+    }
+
+    pub fn debug_report_flags(&self) -> vk::DebugReportFlagsEXT {
+        let mut flags = vk::DebugReportFlagsEXT::ERROR;
+        if self.min_severity <= ValidationSeverity::Warning {
+            flags |= vk::DebugReportFlagsEXT::WARNING | vk::DebugReportFlagsEXT::PERFORMANCE_WARNING;
+        }
+        if self.min_severity <= ValidationSeverity::Info {
+            flags |= vk::DebugReportFlagsEXT::INFORMATION;
+        }
+        if self.min_severity <= ValidationSeverity::Verbose {
+            flags |= vk::DebugReportFlagsEXT::DEBUG;
+        }
+        flags
+    }
+
+    pub fn message_severity(&self) -> vk::DebugUtilsMessageSeverityFlagsEXT {
+        let mut severity = vk::DebugUtilsMessageSeverityFlagsEXT::ERROR;
+        if self.min_severity <= ValidationSeverity::Warning {
+            severity |= vk::DebugUtilsMessageSeverityFlagsEXT::WARNING;
+        }
+        if self.min_severity <= ValidationSeverity::Info {
+            severity |= vk::DebugUtilsMessageSeverityFlagsEXT::INFO;
+        }
+        if self.min_severity <= ValidationSeverity::Verbose {
+            severity |= vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE;
+        }
+        severity
+    }
+}
+
+fn debug_report_severity(flags: vk::DebugReportFlagsEXT) -> ValidationSeverity {
+    if flags.contains(vk::DebugReportFlagsEXT::ERROR) {
+        ValidationSeverity::Error
+    } else if flags.intersects(vk::DebugReportFlagsEXT::WARNING | vk::DebugReportFlagsEXT::PERFORMANCE_WARNING) {
+        ValidationSeverity::Warning
+    } else if flags.contains(vk::DebugReportFlagsEXT::INFORMATION) {
+        ValidationSeverity::Info
+    } else {
+        ValidationSeverity::Verbose
+    }
+}
+
+/// Names a Vulkan object so that it can be identified in validation messages and captures.
+/// Does nothing if `VK_EXT_debug_utils` is not enabled.
+pub fn set_object_name<H: vk::Handle>(device: &DeviceInner, handle: H, name: &CStr) {
+    let instance = &device.adapter.instance;
+    if !instance.has_debug_utils {
+        return;
+    }
+    let name_info = vk::DebugUtilsObjectNameInfoEXT {
+        s_type: vk::StructureType::DEBUG_UTILS_OBJECT_NAME_INFO_EXT,
+        p_next: std::ptr::null(),
+        object_type: H::TYPE,
+        object_handle: handle.as_raw(),
+        p_object_name: name.as_ptr(),
+    };
+    let result = unsafe {
+        instance
+            .raw_ext
+            .debug_utils
+            .debug_utils_set_object_name(device.raw.handle(), &name_info)
+    };
+    if let Err(e) = result {
+        log::warn!("failed to set object name {:?}: {:?}", name, e);
+    }
+}
+
 pub unsafe extern "system" fn debug_utils_messenger_callback(
     severity: vk::DebugUtilsMessageSeverityFlagsEXT,
     ty: vk::DebugUtilsMessageTypeFlagsEXT,
@@ -23,25 +138,25 @@ pub unsafe extern "system" fn debug_utils_messenger_callback(
         log::warn!("debug message callback was null");
     } else {
         let callback = *callback;
+        let callback_data = &*(userdata as *const DebugCallbackData);
 
         let message_id = callback.message_id_number;
-        let message_id_name = CStr::from_ptr(callback.p_message_id_name).to_string_lossy();
-
         let message = CStr::from_ptr(callback.p_message).to_string_lossy();
 
-        let level = match severity {
-            vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE => log::Level::Trace,
-            vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => log::Level::Warn,
-            vk::DebugUtilsMessageSeverityFlagsEXT::INFO => log::Level::Info,
-            vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => log::Level::Error,
-            _ => log::Level::Debug,
+        let (validation_severity, level) = match severity {
+            vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE => (ValidationSeverity::Verbose, log::Level::Trace),
+            vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => (ValidationSeverity::Warning, log::Level::Warn),
+            vk::DebugUtilsMessageSeverityFlagsEXT::INFO => (ValidationSeverity::Info, log::Level::Info),
+            vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => (ValidationSeverity::Error, log::Level::Error),
+            _ => (ValidationSeverity::Verbose, log::Level::Debug),
         };
 
-        log::log!(level, "[{:?}] {}", ty, message);
+        if !callback_data.is_suppressed(validation_severity, message_id) {
+            log::log!(level, "[{:?}] {}", ty, message);
+        }
     }
 
-    unimplemented!()
-    //vk::FALSE
+    vk::FALSE
 }
 
 #[allow(dead_code)]
@@ -117,10 +232,13 @@ pub unsafe extern "system" fn debug_report_callback_test(
     message: *const libc::c_char,
     userdata: *mut libc::c_void,
 ) -> u32 {
+    let callback_data = &*(userdata as *const DebugCallbackData);
+    if callback_data.is_suppressed(debug_report_severity(flags), message_code) {
+        return vk::FALSE;
+    }
     let message = CStr::from_ptr(message).to_string_lossy().to_string();
-    let handle: vk::Instance = mem::transmute(userdata);
     let mut errors = ERRORS.lock();
-    let errors = errors.entry(handle).or_default();
+    let errors = errors.entry(callback_data.instance).or_default();
     errors.push(ValidationError { message, flags });
     vk::FALSE
 }
@@ -4,30 +4,36 @@ use ash::vk;
 use parking_lot::Mutex;
 use vk_mem::{Allocator, AllocatorCreateInfo};
 
-use crate::error::Error;
+use crate::error::{Error, FenceError};
 
+use crate::imp::compute_blit::ComputeBlitCache;
 use crate::imp::fenced_deleter::{DeleteWhenUnused, FencedDeleter};
+use crate::imp::queue::PendingWrites;
 use crate::imp::render_pass::{RenderPassCache, RenderPassCacheQuery};
 use crate::imp::serial::{Serial, SerialQueue};
 use crate::imp::{swapchain, texture};
 
 use crate::imp::{
     AdapterInner, BindGroupInner, BindGroupLayoutInner, BufferInner, CommandEncoderInner, ComputePipelineInner,
-    DeviceExt, DeviceInner, PipelineLayoutInner, QueueInfo, QueueInner, RenderPipelineInner, SamplerInner,
-    ShaderModuleInner, SurfaceInner, SwapchainInner, TextureInner,
+    DeviceExt, DeviceInner, PipelineLayoutInner, QuerySetInner, QueueInfo, QueueInner, RenderPipelineInner,
+    SamplerInner, ShaderModuleInner, SurfaceInner, SwapchainInner, TextureInner,
 };
 
 use crate::{
     Adapter, BindGroup, BindGroupDescriptor, BindGroupLayout, BindGroupLayoutDescriptor, Buffer, BufferDescriptor,
-    CommandEncoder, ComputePipeline, ComputePipelineDescriptor, Device, DeviceDescriptor, Limits, MappedBuffer,
-    PipelineLayout, PipelineLayoutDescriptor, Queue, RenderPipeline, RenderPipelineDescriptor, Sampler,
-    SamplerDescriptor, ShaderModule, ShaderModuleDescriptor, Surface, Swapchain, SwapchainDescriptor, Texture,
-    TextureDescriptor, TextureFormat,
+    BufferUsage, CommandEncoder, CommandEncoderDescriptor, ComputePipeline, ComputePipelineDescriptor, Device,
+    DeviceDescriptor, Extensions, Limits, MappedBuffer, PendingCreation, PipelineLayout, PipelineLayoutDescriptor, Pod,
+    PoolStatistics, QuerySet, QuerySetDescriptor, Queue, QueueGlobalPriority, RenderPipeline, RenderPipelineDescriptor,
+    ResourceCreator, Sampler, SamplerDescriptor, ShaderModule, ShaderModuleDescriptor, SubmissionIndex, Surface,
+    Swapchain, SwapchainDescriptor, Texture, TextureDescriptor, TextureFormat, TextureUsage, TypedBuffer,
 };
 
+use std::collections::HashMap;
 use std::fmt::{self, Debug};
-use std::mem::ManuallyDrop;
+use std::mem::{self, ManuallyDrop};
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 pub struct DeviceState {
     // the fences in flight for our single queue
@@ -45,18 +51,37 @@ pub struct DeviceState {
     pending_commands: Option<CommandPoolAndBuffer>,
     unused_commands: Vec<CommandPoolAndBuffer>,
 
+    // command buffers that are submitted ahead of the pending commands, in submission order
+    pending_command_buffers: Vec<vk::CommandBuffer>,
+    // pending commands that were ended early to preserve the submission order
+    ended_commands: Vec<CommandPoolAndBuffer>,
+
     fenced_deleter: FencedDeleter,
 
     renderpass_cache: RenderPassCache,
+
+    compute_blit_cache: ComputeBlitCache,
 }
 
 #[derive(Copy, Clone, Debug, Default)]
-struct CommandPoolAndBuffer {
+pub struct CommandPoolAndBuffer {
     pool: vk::CommandPool,
     command_buffer: vk::CommandBuffer,
 }
 
+impl CommandPoolAndBuffer {
+    pub fn command_buffer(&self) -> vk::CommandBuffer {
+        self.command_buffer
+    }
+}
+
 impl Device {
+    /// Returns the number of physical devices that the device spans. This is greater than `1`
+    /// only for devices created with `AdapterGroup::create_device`.
+    pub fn physical_device_count(&self) -> u32 {
+        self.inner.physical_device_count
+    }
+
     pub fn create_swapchain(
         &self,
         descriptor: SwapchainDescriptor,
@@ -83,12 +108,31 @@ impl Device {
         Ok(formats)
     }
 
+    /// Returns the usage that swapchains with the given format can be created with on the surface.
+    /// Usages other than `OUTPUT_ATTACHMENT` (e.g. `STORAGE` for post-processing in a compute shader)
+    /// are optional.
+    pub fn get_supported_swapchain_usage(
+        &self,
+        surface: &Surface,
+        format: TextureFormat,
+    ) -> Result<TextureUsage, Error> {
+        let surface_caps = unsafe {
+            self.inner
+                .adapter
+                .instance
+                .raw_ext
+                .surface
+                .get_physical_device_surface_capabilities(self.inner.adapter.physical_device, surface.inner.handle)?
+        };
+        Ok(swapchain::supported_usage(&self.inner.adapter, &surface_caps, format))
+    }
+
     pub fn get_queue(&self) -> Queue {
         Queue {
-            inner: QueueInner {
+            inner: Arc::new(QueueInner {
                 device: Arc::clone(&self.inner),
                 queue: self.inner.queue,
-            },
+            }),
         }
     }
 
@@ -98,11 +142,62 @@ impl Device {
         }
     }
 
+    /// Returns the extensions that were enabled when the device was created
+    pub fn extensions(&self) -> &Extensions {
+        &self.inner.extensions
+    }
+
+    /// Returns the resource binding limits of the device
+    pub fn limits(&self) -> &Limits {
+        &self.inner.limits
+    }
+
+    /// Returns `true` if the submission has completed execution.
+    pub fn is_submission_complete(&self, index: SubmissionIndex) -> bool {
+        let state = self.inner.state.lock();
+        index.serial <= state.get_last_completed_serial().get()
+    }
+
+    /// Waits for the submission to complete execution.
+    pub fn wait_for_submission(&self, index: SubmissionIndex, timeout: Duration) -> Result<(), FenceError> {
+        {
+            let state = self.inner.state.lock();
+            if index.serial > state.get_last_submitted_serial().get() {
+                log::error!("submission index has not been submitted: {:?}", index);
+                return Err(FenceError::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+            }
+        }
+        let timeout = Instant::now() + timeout;
+        let mut stalled = false;
+        while !self.is_submission_complete(index) {
+            if Instant::now() >= timeout {
+                return Err(FenceError::Timeout);
+            }
+            if stalled {
+                std::thread::yield_now();
+            } else {
+                stalled = true;
+            }
+            self.inner.tick()?;
+        }
+        Ok(())
+    }
+
     pub fn create_buffer(&self, descriptor: BufferDescriptor) -> Result<Buffer, Error> {
         let buffer = BufferInner::new(self.inner.clone(), descriptor)?;
         Ok(buffer.into())
     }
 
+    /// Creates a buffer that holds `len` elements of type `T`.
+    pub fn create_typed_buffer<T: Pod>(&self, len: usize, usage: BufferUsage) -> Result<TypedBuffer<T>, Error> {
+        let buffer = self.create_buffer(BufferDescriptor {
+            size: len * mem::size_of::<T>(),
+            usage,
+            memory_hint: None,
+        })?;
+        TypedBuffer::from_buffer(buffer)
+    }
+
     pub fn create_buffer_mapped(&self, descriptor: BufferDescriptor) -> Result<MappedBuffer, Error> {
         let buffer = BufferInner::new(self.inner.clone(), descriptor)?;
         let data = unsafe { buffer.get_mapped_ptr()? };
@@ -117,6 +212,20 @@ impl Device {
         Ok(texture.into())
     }
 
+    /// Creates a sparse texture whose tiles are bound to memory with `Queue::bind_sparse`. Only
+    /// single-sampled 2D color textures are supported.
+    ///
+    /// Requires `Adapter::supports_sparse_residency`.
+    pub fn create_sparse_texture(&self, descriptor: TextureDescriptor) -> Result<Texture, Error> {
+        let texture = TextureInner::new_sparse(self.inner.clone(), descriptor)?;
+        Ok(texture.into())
+    }
+
+    pub fn create_query_set(&self, descriptor: QuerySetDescriptor) -> Result<QuerySet, Error> {
+        let query_set = QuerySetInner::new(self.inner.clone(), descriptor)?;
+        Ok(query_set.into())
+    }
+
     pub fn create_sampler(&self, descriptor: SamplerDescriptor) -> Result<Sampler, Error> {
         let sampler = SamplerInner::new(self.inner.clone(), descriptor)?;
         Ok(sampler.into())
@@ -147,37 +256,272 @@ impl Device {
         Ok(compute_pipeline.into())
     }
 
+    /// Creates a render pipeline. If a pipeline created from an identical descriptor is still alive,
+    /// it is returned instead of creating a new one.
     pub fn create_render_pipeline(&self, descriptor: RenderPipelineDescriptor) -> Result<RenderPipeline, Error> {
-        let render_pipeline = RenderPipelineInner::new(self.inner.clone(), descriptor)?;
-        Ok(render_pipeline.into())
+        let inner = RenderPipelineInner::get_or_create(self.inner.clone(), descriptor)?;
+        Ok(RenderPipeline { inner })
     }
 
     pub fn create_command_encoder(&self) -> Result<CommandEncoder, Error> {
+        self.create_command_encoder_with_descriptor(CommandEncoderDescriptor::default())
+    }
+
+    pub fn create_command_encoder_with_descriptor(
+        &self,
+        descriptor: CommandEncoderDescriptor,
+    ) -> Result<CommandEncoder, Error> {
         let mut command_encoder_pool = self.inner.command_encoder_pool.lock();
         let command_encoder = if let Some(state) = command_encoder_pool.pop() {
-            CommandEncoderInner::with_device_and_state(self.inner.clone(), state)?
+            CommandEncoderInner::with_device_and_state(self.inner.clone(), state, descriptor)?
         } else {
-            CommandEncoderInner::new(self.inner.clone())?
+            CommandEncoderInner::new(self.inner.clone(), descriptor)?
         };
         drop(command_encoder_pool);
         Ok(command_encoder.into())
     }
+
+    /// Returns the sizes of the internal pools. Command encoder states are returned to the pool
+    /// when a `CommandBuffer` is dropped, or when a `CommandEncoder` is dropped or discarded
+    /// without being finished, and are reused by `create_command_encoder`.
+    pub fn pool_statistics(&self) -> PoolStatistics {
+        let render_pass_count = self.inner.state.lock().render_pass_cache_len();
+        let command_encoder_pool = self.inner.command_encoder_pool.lock();
+        PoolStatistics {
+            command_encoder_count: command_encoder_pool.len(),
+            command_encoder_bytes: command_encoder_pool.iter().map(|state| state.retained_bytes()).sum(),
+            render_pass_count,
+        }
+    }
+
+    /// Releases the memory retained by the internal pools. Long running applications can call this
+    /// periodically, e.g. after a burst of recording, to bound the memory held for reuse.
+    ///
+    /// Cached render passes are evicted and destroyed once the submissions that use them complete.
+    pub fn trim_internal_pools(&self) {
+        self.inner.state.lock().evict_render_passes();
+
+        // The states are dropped after the lock is released
+        let command_encoder_pool = mem::take(&mut *self.inner.command_encoder_pool.lock());
+        drop(command_encoder_pool);
+    }
+
+    /// Runs `f` on a new thread with a `ResourceCreator` for this device. Use this to create
+    /// pipelines, shader modules, and textures without stalling the thread that records and
+    /// submits commands.
+    pub fn spawn_creation<T, F>(&self, f: F) -> PendingCreation<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&ResourceCreator) -> Result<T, Error> + Send + 'static,
+    {
+        let creator = ResourceCreator { device: self.clone() };
+        let thread = thread::spawn(move || f(&creator));
+        PendingCreation { thread }
+    }
+}
+
+impl ResourceCreator {
+    pub fn limits(&self) -> &Limits {
+        self.device.limits()
+    }
+
+    pub fn extensions(&self) -> &Extensions {
+        self.device.extensions()
+    }
+
+    pub fn create_buffer(&self, descriptor: BufferDescriptor) -> Result<Buffer, Error> {
+        self.device.create_buffer(descriptor)
+    }
+
+    pub fn create_typed_buffer<T: Pod>(&self, len: usize, usage: BufferUsage) -> Result<TypedBuffer<T>, Error> {
+        self.device.create_typed_buffer(len, usage)
+    }
+
+    pub fn create_texture(&self, descriptor: TextureDescriptor) -> Result<Texture, Error> {
+        self.device.create_texture(descriptor)
+    }
+
+    pub fn create_sampler(&self, descriptor: SamplerDescriptor) -> Result<Sampler, Error> {
+        self.device.create_sampler(descriptor)
+    }
+
+    pub fn create_bind_group_layout(&self, descriptor: BindGroupLayoutDescriptor) -> Result<BindGroupLayout, Error> {
+        self.device.create_bind_group_layout(descriptor)
+    }
+
+    pub fn create_bind_group(&self, descriptor: BindGroupDescriptor) -> Result<BindGroup, Error> {
+        self.device.create_bind_group(descriptor)
+    }
+
+    pub fn create_shader_module(&self, descriptor: ShaderModuleDescriptor) -> Result<ShaderModule, Error> {
+        self.device.create_shader_module(descriptor)
+    }
+
+    pub fn create_pipeline_layout(&self, descriptor: PipelineLayoutDescriptor) -> Result<PipelineLayout, Error> {
+        self.device.create_pipeline_layout(descriptor)
+    }
+
+    pub fn create_compute_pipeline(&self, descriptor: ComputePipelineDescriptor) -> Result<ComputePipeline, Error> {
+        self.device.create_compute_pipeline(descriptor)
+    }
+
+    pub fn create_render_pipeline(&self, descriptor: RenderPipelineDescriptor) -> Result<RenderPipeline, Error> {
+        self.device.create_render_pipeline(descriptor)
+    }
+}
+
+impl<T> PendingCreation<T> {
+    /// Returns `true` if the creation has finished, either normally or by panicking, and `wait`
+    /// will not block.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Blocks until the creation has finished and returns the result. A panic on the creation
+    /// thread is resumed on the calling thread.
+    pub fn wait(self) -> Result<T, Error> {
+        match self.thread.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
 }
 
 impl DeviceInner {
     pub fn new(adapter: Arc<AdapterInner>, descriptor: DeviceDescriptor) -> Result<DeviceInner, Error> {
+        DeviceInner::new_with_physical_devices(adapter, descriptor, &[])
+    }
+
+    /// Creates a device from a device group if more than one physical device is provided
+    pub fn new_with_physical_devices(
+        adapter: Arc<AdapterInner>,
+        descriptor: DeviceDescriptor,
+        physical_devices: &[vk::PhysicalDevice],
+    ) -> Result<DeviceInner, Error> {
         log::info!("requesting device from adapter: {}", adapter.name);
-        let extension_names = if descriptor.surface_support.is_some() {
+        let mut extension_names = if descriptor.surface_support.is_some() {
             vec![c_str!("VK_KHR_swapchain")]
         } else {
             vec![]
         };
 
+        if descriptor.extensions.timeline_semaphore {
+            extension_names.push(vk::KhrTimelineSemaphoreFn::name().as_ptr());
+        }
+
+        if descriptor.extensions.transform_feedback {
+            extension_names.push(vk::ExtTransformFeedbackFn::name().as_ptr());
+        }
+
+        if descriptor.extensions.subgroup_size_control {
+            extension_names.push(vk::ExtSubgroupSizeControlFn::name().as_ptr());
+        }
+
+        if descriptor.extensions.index_type_uint8 {
+            extension_names.push(vk::ExtIndexTypeUint8Fn::name().as_ptr());
+        }
+
+        if descriptor.extensions.vertex_attribute_divisor {
+            extension_names.push(vk::ExtVertexAttributeDivisorFn::name().as_ptr());
+        }
+
+        if descriptor.extensions.pipeline_creation_feedback {
+            extension_names.push(vk::ExtPipelineCreationFeedbackFn::name().as_ptr());
+        }
+
+        if descriptor.extensions.swapchain_mutable_format {
+            extension_names.push(vk::KhrSwapchainMutableFormatFn::name().as_ptr());
+            extension_names.push(vk::KhrImageFormatListFn::name().as_ptr());
+            // Required by the mutable format extension on Vulkan 1.0 devices
+            let maintenance2 = vk::KhrMaintenance2Fn::name();
+            if adapter
+                .device_extension_names
+                .iter()
+                .any(|name| name.as_c_str() == maintenance2)
+            {
+                extension_names.push(maintenance2.as_ptr());
+            }
+        }
+
+        let shader_float16_int8 = descriptor.extensions.shader_float16 || descriptor.extensions.shader_int8;
+
+        if shader_float16_int8 {
+            extension_names.push(vk::KhrShaderFloat16Int8Fn::name().as_ptr());
+        }
+
+        if descriptor.extensions.storage_buffer_8bit_access {
+            extension_names.push(vk::Khr8bitStorageFn::name().as_ptr());
+        }
+
+        if descriptor.extensions.storage_buffer_16bit_access {
+            extension_names.push(vk::Khr16bitStorageFn::name().as_ptr());
+        }
+
+        // Required by the 8-bit and 16-bit storage extensions on Vulkan 1.0 devices
+        let storage_buffer_storage_class = vk::KhrStorageBufferStorageClassFn::name();
+        if (descriptor.extensions.storage_buffer_8bit_access || descriptor.extensions.storage_buffer_16bit_access)
+            && adapter
+                .device_extension_names
+                .iter()
+                .any(|name| name.as_c_str() == storage_buffer_storage_class)
+        {
+            extension_names.push(storage_buffer_storage_class.as_ptr());
+        }
+
+        // Enables negative viewport heights on Vulkan 1.0 devices (see `RenderPassDescriptor::flip_viewport`)
+        if adapter.supports_maintenance1() {
+            extension_names.push(vk::KhrMaintenance1Fn::name().as_ptr());
+        }
+
+        // Enables `gl_BaseInstance`, `gl_BaseVertex`, and `gl_DrawID` (see `AdapterFeatures::shader_draw_parameters`)
+        if adapter.supports_shader_draw_parameters() {
+            extension_names.push(vk::KhrShaderDrawParametersFn::name().as_ptr());
+        }
+
+        // Portability implementations (e.g. MoltenVK) require the extension to be enabled
+        if adapter.portability_features.is_some() {
+            extension_names.push(vk::KhrPortabilitySubsetFn::name().as_ptr());
+        }
+
+        let global_priority = match descriptor.global_priority {
+            Some(priority) if adapter.supports_global_priority() => {
+                extension_names.push(vk::ExtGlobalPriorityFn::name().as_ptr());
+                Some(priority)
+            }
+            Some(priority) => {
+                log::warn!("global queue priority not supported; ignoring: {:?}", priority);
+                None
+            }
+            None => None,
+        };
+
+        let queue_priority = descriptor.queue_priority.unwrap_or(1.0);
+        if !(0.0..=1.0).contains(&queue_priority) {
+            log::error!("queue priority must be in the range [0.0, 1.0]: {}", queue_priority);
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        let is_device_group = physical_devices.len() > 1;
+        if is_device_group {
+            let device_group = vk::KhrDeviceGroupFn::name();
+            if !adapter
+                .device_extension_names
+                .iter()
+                .any(|name| name.as_c_str() == device_group)
+            {
+                log::error!("device groups are not supported by adapter: {}", adapter.name);
+                return Err(Error::from(vk::Result::ERROR_EXTENSION_NOT_PRESENT));
+            }
+            extension_names.push(device_group.as_ptr());
+        }
+
         for name in extension_names.iter() {
             let name = unsafe { std::ffi::CStr::from_ptr(*name).to_string_lossy() };
             log::info!("requesting device extension: {}", name);
         }
 
+        validate_extensions(&descriptor.extensions, &adapter.extensions)?;
+
         let surface = descriptor.surface_support.map(|v| v.inner.as_ref());
         let queue_flags = vk::QueueFlags::COMPUTE | vk::QueueFlags::GRAPHICS | vk::QueueFlags::TRANSFER;
         let queue_family_index = select_queue_family_index(&adapter, queue_flags, surface)?;
@@ -186,24 +530,141 @@ impl DeviceInner {
             assert!(adapter.queue_family_properties[queue_family_index as usize].queue_count > 0);
             let features = vk::PhysicalDeviceFeatures::builder()
                 .fill_mode_non_solid(adapter.physical_device_features.fill_mode_non_solid > 0)
+                .independent_blend(adapter.physical_device_features.independent_blend > 0)
+                .sampler_anisotropy(descriptor.extensions.anisotropic_filtering)
+                .depth_bias_clamp(adapter.physical_device_features.depth_bias_clamp > 0)
+                .draw_indirect_first_instance(adapter.physical_device_features.draw_indirect_first_instance > 0)
+                .sparse_binding(adapter.supports_sparse_residency())
+                .sparse_residency_image2_d(adapter.supports_sparse_residency())
+                .shader_storage_image_write_without_format(
+                    adapter
+                        .physical_device_features
+                        .shader_storage_image_write_without_format
+                        > 0,
+                )
+                .texture_compression_bc(descriptor.extensions.texture_compression_bc)
+                .texture_compression_etc2(descriptor.extensions.texture_compression_etc2)
+                .texture_compression_astc_ldr(descriptor.extensions.texture_compression_astc_ldr)
                 .build();
-            let queue_priorities = [1.0];
-            let queue_create_infos = [vk::DeviceQueueCreateInfo::builder()
+            let queue_priorities = [queue_priority];
+            let mut global_priority_create_info = vk::DeviceQueueGlobalPriorityCreateInfoEXT::builder()
+                .global_priority(vk::QueueGlobalPriorityEXT::from_raw(
+                    global_priority.unwrap_or(QueueGlobalPriority::Medium) as i32,
+                ))
+                .build();
+            let mut queue_create_info = vk::DeviceQueueCreateInfo::builder()
                 .queue_family_index(queue_family_index)
-                .queue_priorities(&queue_priorities)
-                .build()];
+                .queue_priorities(&queue_priorities);
+            if global_priority.is_some() {
+                queue_create_info = queue_create_info.push_next(&mut global_priority_create_info);
+            }
+            let queue_create_infos = [queue_create_info.build()];
+
+            let mut timeline_semaphore_features = vk::PhysicalDeviceTimelineSemaphoreFeatures::builder()
+                .timeline_semaphore(true)
+                .build();
+
+            let mut transform_feedback_features = vk::PhysicalDeviceTransformFeedbackFeaturesEXT::builder()
+                .transform_feedback(true)
+                .build();
+
+            let mut subgroup_size_control_features = vk::PhysicalDeviceSubgroupSizeControlFeaturesEXT::builder()
+                .subgroup_size_control(true)
+                .build();
+
+            let mut float16_int8_features = vk::PhysicalDeviceShaderFloat16Int8Features::builder()
+                .shader_float16(descriptor.extensions.shader_float16)
+                .shader_int8(descriptor.extensions.shader_int8)
+                .build();
+
+            let mut storage_8bit_features = vk::PhysicalDevice8BitStorageFeatures::builder()
+                .storage_buffer8_bit_access(true)
+                .build();
+
+            let mut storage_16bit_features = vk::PhysicalDevice16BitStorageFeatures::builder()
+                .storage_buffer16_bit_access(true)
+                .build();
 
-            let create_info = vk::DeviceCreateInfo::builder()
+            let mut index_type_uint8_features = vk::PhysicalDeviceIndexTypeUint8FeaturesEXT::builder()
+                .index_type_uint8(true)
+                .build();
+
+            let mut vertex_attribute_divisor_features = vk::PhysicalDeviceVertexAttributeDivisorFeaturesEXT::builder()
+                .vertex_attribute_instance_rate_divisor(true)
+                .build();
+
+            let mut portability_subset_features = adapter.portability_features.unwrap_or_default().to_vk();
+
+            let mut device_group_create_info =
+                vk::DeviceGroupDeviceCreateInfo::builder().physical_devices(physical_devices);
+
+            let mut create_info = vk::DeviceCreateInfo::builder()
                 .queue_create_infos(&queue_create_infos)
                 .enabled_features(&features)
                 .enabled_extension_names(&extension_names);
 
-            let raw = adapter
-                .instance
-                .raw
-                .create_device(adapter.physical_device, &create_info, None)?;
+            if descriptor.extensions.timeline_semaphore {
+                create_info = create_info.push_next(&mut timeline_semaphore_features);
+            }
+
+            if descriptor.extensions.transform_feedback {
+                create_info = create_info.push_next(&mut transform_feedback_features);
+            }
+
+            if descriptor.extensions.subgroup_size_control {
+                create_info = create_info.push_next(&mut subgroup_size_control_features);
+            }
+
+            if shader_float16_int8 {
+                create_info = create_info.push_next(&mut float16_int8_features);
+            }
 
-            let limits = Limits { max_bind_groups: 0 };
+            if descriptor.extensions.storage_buffer_8bit_access {
+                create_info = create_info.push_next(&mut storage_8bit_features);
+            }
+
+            if descriptor.extensions.storage_buffer_16bit_access {
+                create_info = create_info.push_next(&mut storage_16bit_features);
+            }
+
+            if descriptor.extensions.index_type_uint8 {
+                create_info = create_info.push_next(&mut index_type_uint8_features);
+            }
+
+            if descriptor.extensions.vertex_attribute_divisor {
+                create_info = create_info.push_next(&mut vertex_attribute_divisor_features);
+            }
+
+            if is_device_group {
+                create_info = create_info.push_next(&mut device_group_create_info);
+            }
+
+            if adapter.portability_features.is_some() {
+                create_info = create_info.push_next(&mut portability_subset_features);
+            }
+
+            let raw = adapter.instance.raw.create_device(
+                adapter.physical_device,
+                &create_info,
+                adapter.instance.allocation_callbacks(),
+            )?;
+
+            let device_limits = &adapter.physical_device_properties.limits;
+            let limits = Limits {
+                max_bind_groups: device_limits.max_bound_descriptor_sets,
+                max_dynamic_uniform_buffers_per_pipeline_layout: device_limits
+                    .max_descriptor_set_uniform_buffers_dynamic,
+                max_dynamic_storage_buffers_per_pipeline_layout: device_limits
+                    .max_descriptor_set_storage_buffers_dynamic,
+                max_samplers_per_shader_stage: device_limits.max_per_stage_descriptor_samplers,
+                max_sampled_textures_per_shader_stage: device_limits.max_per_stage_descriptor_sampled_images,
+                max_storage_textures_per_shader_stage: device_limits.max_per_stage_descriptor_storage_images,
+                max_uniform_buffers_per_shader_stage: device_limits.max_per_stage_descriptor_uniform_buffers,
+                max_storage_buffers_per_shader_stage: device_limits.max_per_stage_descriptor_storage_buffers,
+                max_push_constants_size: device_limits.max_push_constants_size,
+                min_uniform_buffer_offset_alignment: device_limits.min_uniform_buffer_offset_alignment as usize,
+                min_storage_buffer_offset_alignment: device_limits.min_storage_buffer_offset_alignment as usize,
+            };
             let extensions = descriptor.extensions.clone();
 
             let queue_index = 0;
@@ -214,7 +675,17 @@ impl DeviceInner {
             };
 
             let swapchain = khr::Swapchain::new(&adapter.instance.raw, &raw);
-            let raw_ext = DeviceExt { swapchain };
+            let timeline_semaphore = vk::KhrTimelineSemaphoreFn::load(|name| {
+                std::mem::transmute(adapter.instance.raw.get_device_proc_addr(raw.handle(), name.as_ptr()))
+            });
+            let transform_feedback = vk::ExtTransformFeedbackFn::load(|name| {
+                std::mem::transmute(adapter.instance.raw.get_device_proc_addr(raw.handle(), name.as_ptr()))
+            });
+            let raw_ext = DeviceExt {
+                swapchain,
+                timeline_semaphore,
+                transform_feedback,
+            };
 
             let allocator_create_info = AllocatorCreateInfo {
                 device: raw.clone(),
@@ -238,12 +709,17 @@ impl DeviceInner {
                 last_submitted_serial: Serial::one(),
                 pending_commands: None,
                 unused_commands: Vec::new(),
+                pending_command_buffers: Vec::new(),
+                ended_commands: Vec::new(),
                 fenced_deleter: FencedDeleter::default(),
                 renderpass_cache: RenderPassCache::default(),
+                compute_blit_cache: ComputeBlitCache::default(),
             };
 
             let state = Mutex::new(state);
             let command_encoder_pool = Mutex::new(Vec::new());
+            let render_pipeline_cache = Mutex::new(HashMap::default());
+            let pending_writes = Mutex::new(PendingWrites::default());
 
             let inner = DeviceInner {
                 raw,
@@ -252,8 +728,11 @@ impl DeviceInner {
                 limits,
                 adapter,
                 queue,
+                physical_device_count: physical_devices.len().max(1) as u32,
                 state,
                 command_encoder_pool,
+                render_pipeline_cache,
+                pending_writes,
                 allocator: ManuallyDrop::new(allocator),
             };
 
@@ -261,11 +740,30 @@ impl DeviceInner {
         }
     }
 
+    /// The callbacks passed as `pAllocator` to every create and destroy call. See
+    /// `InstanceDescriptor::track_host_allocations`.
+    pub fn allocation_callbacks(&self) -> Option<&vk::AllocationCallbacks> {
+        self.adapter.instance.allocation_callbacks()
+    }
+
+    /// `allocation_callbacks` for the raw function pointers
+    pub fn allocation_callbacks_ptr(&self) -> *const vk::AllocationCallbacks {
+        self.allocation_callbacks()
+            .map_or(std::ptr::null(), |callbacks| callbacks as *const _)
+    }
+
     pub fn tick(&self) -> Result<(), Error> {
         let mut state = self.state.lock();
         state.tick(self)?;
         Ok(())
     }
+
+    /// Returns `true` if sparse 2D textures are supported by the device and its queue.
+    pub fn supports_sparse_residency(&self) -> bool {
+        let queue_family_index = self.queue.queue_family_index as usize;
+        let queue_family = &self.adapter.queue_family_properties[queue_family_index];
+        self.adapter.supports_sparse_residency() && queue_family.queue_flags.contains(vk::QueueFlags::SPARSE_BINDING)
+    }
 }
 
 impl Debug for DeviceInner {
@@ -332,34 +830,42 @@ impl Drop for DeviceInner {
             }
 
             for (fence, _) in state.fences_in_flight.drain(..) {
-                self.raw.destroy_fence(fence, None);
+                self.raw.destroy_fence(fence, self.allocation_callbacks());
             }
             for fence in state.unused_fences.drain(..) {
-                self.raw.destroy_fence(fence, None);
+                self.raw.destroy_fence(fence, self.allocation_callbacks());
             }
 
             for (commands, _) in state.commands_in_flight.drain(..) {
-                self.raw.destroy_command_pool(commands.pool, None);
+                self.raw
+                    .destroy_command_pool(commands.pool, self.allocation_callbacks());
             }
             for commands in state.unused_commands.drain(..) {
-                self.raw.destroy_command_pool(commands.pool, None);
+                self.raw
+                    .destroy_command_pool(commands.pool, self.allocation_callbacks());
             }
             if let Some(commands) = state.pending_commands.take() {
-                self.raw.destroy_command_pool(commands.pool, None);
+                self.raw
+                    .destroy_command_pool(commands.pool, self.allocation_callbacks());
+            }
+            for commands in state.ended_commands.drain(..) {
+                self.raw
+                    .destroy_command_pool(commands.pool, self.allocation_callbacks());
             }
 
             for semaphore in state.wait_semaphores.drain(..) {
-                self.raw.destroy_semaphore(semaphore, None);
+                self.raw.destroy_semaphore(semaphore, self.allocation_callbacks());
             }
 
             state.renderpass_cache.drain(&self);
+            state.compute_blit_cache.drain(&self);
 
             ManuallyDrop::drop(&mut self.allocator);
 
             drop(state);
 
             log::debug!("destroying device: {:?}", self.raw.handle());
-            self.raw.destroy_device(None);
+            self.raw.destroy_device(self.allocation_callbacks());
         }
     }
 }
@@ -461,43 +967,132 @@ impl DeviceState {
             .ok_or_else(|| unreachable!())
     }
 
+    /// Appends a command buffer to the pending submission. Any pending commands are ended first so
+    /// that they execute before `command_buffer`.
+    pub fn push_command_buffer(
+        &mut self,
+        device: &DeviceInner,
+        command_buffer: vk::CommandBuffer,
+    ) -> Result<(), Error> {
+        self.end_pending_commands(device)?;
+        self.pending_command_buffers.push(command_buffer);
+        Ok(())
+    }
+
+    fn end_pending_commands(&mut self, device: &DeviceInner) -> Result<(), Error> {
+        if let Some(pending_commands) = self.pending_commands.take() {
+            unsafe {
+                device.raw.end_command_buffer(pending_commands.command_buffer)?;
+            }
+            self.pending_command_buffers.push(pending_commands.command_buffer);
+            self.ended_commands.push(pending_commands);
+        }
+        Ok(())
+    }
+
+    /// Begins a command buffer that may be submitted multiple times. The command buffer is owned by
+    /// the caller until it's returned with `retire_reusable_commands`.
+    pub fn begin_reusable_commands(&mut self, device: &DeviceInner) -> Result<CommandPoolAndBuffer, Error> {
+        let commands = self.get_unused_commands(device)?;
+        let begin_info = vk::CommandBufferBeginInfo {
+            flags: vk::CommandBufferUsageFlags::SIMULTANEOUS_USE,
+            ..Default::default()
+        };
+        unsafe {
+            device.raw.begin_command_buffer(commands.command_buffer, &begin_info)?;
+        }
+        Ok(commands)
+    }
+
+    /// Recycles a reusable command buffer after the pending submission completes, since it may still
+    /// be executing or be part of the pending submission.
+    pub fn retire_reusable_commands(&mut self, commands: CommandPoolAndBuffer) {
+        let serial = self.get_next_pending_serial();
+        self.commands_in_flight.enqueue(commands, serial);
+    }
+
     pub fn get_fenced_deleter(&mut self) -> &mut FencedDeleter {
         &mut self.fenced_deleter
     }
 
+    pub fn get_compute_blit_cache(&mut self) -> &mut ComputeBlitCache {
+        &mut self.compute_blit_cache
+    }
+
     pub fn submit_pending_commands(&mut self, device: &DeviceInner, queue: &QueueInfo) -> Result<(), Error> {
-        let pending_commands = match self.pending_commands.take() {
-            None => {
-                // If there are no pending commands and everything in flight has resolved,
-                // artificially increment the serials. This allows for pending deletes to
-                // resolve even if no new commands have been submitted.
-                if self.last_submitted_serial == self.last_completed_serial {
-                    self.last_submitted_serial.increment();
-                    self.last_completed_serial.increment();
-                    log::trace!(
-                        "all commands complete: incremented serials: {:?}",
-                        self.last_submitted_serial
-                    );
-                }
-                return Ok(());
+        self.submit_pending_commands_with(device, queue, &[], &[], None)
+    }
+
+    /// Submits the pending commands with additional timeline semaphore waits and signals. The
+    /// `device_mask` selects the physical devices of a device group that execute the commands.
+    pub fn submit_pending_commands_with(
+        &mut self,
+        device: &DeviceInner,
+        queue: &QueueInfo,
+        timeline_waits: &[(vk::Semaphore, u64)],
+        timeline_signals: &[(vk::Semaphore, u64)],
+        device_mask: Option<u32>,
+    ) -> Result<(), Error> {
+        self.end_pending_commands(device)?;
+
+        if self.pending_command_buffers.is_empty() {
+            // If there are no pending commands and everything in flight has resolved,
+            // artificially increment the serials. This allows for pending deletes to
+            // resolve even if no new commands have been submitted.
+            if self.last_submitted_serial == self.last_completed_serial {
+                self.last_submitted_serial.increment();
+                self.last_completed_serial.increment();
+                log::trace!(
+                    "all commands complete: incremented serials: {:?}",
+                    self.last_submitted_serial
+                );
             }
-            Some(pending_commands) => pending_commands,
-        };
+            return Ok(());
+        }
 
-        unsafe {
-            device.raw.end_command_buffer(pending_commands.command_buffer)?;
+        // Binary semaphores ignore the timeline values, but the value arrays must match the
+        // semaphore counts if any timeline semaphores are present
+        let mut wait_semaphores = self.wait_semaphores.clone();
+        let mut wait_values = vec![0; wait_semaphores.len()];
+        for &(semaphore, value) in timeline_waits.iter() {
+            wait_semaphores.push(semaphore);
+            wait_values.push(value);
         }
+        let signal_semaphores: Vec<_> = timeline_signals.iter().map(|&(semaphore, _)| semaphore).collect();
+        let signal_values: Vec<_> = timeline_signals.iter().map(|&(_, value)| value).collect();
 
-        let wait_dst_stage_masks = vec![vk::PipelineStageFlags::ALL_COMMANDS; self.wait_semaphores.len()];
-        let pending_command_buffers = [pending_commands.command_buffer];
+        let wait_dst_stage_masks = vec![vk::PipelineStageFlags::ALL_COMMANDS; wait_semaphores.len()];
+        let pending_command_buffers = mem::take(&mut self.pending_command_buffers);
 
         let fence = self.get_unused_fence(device)?;
 
-        let submit_info = vk::SubmitInfo::builder()
-            .wait_semaphores(&self.wait_semaphores)
+        let mut timeline_submit_info = vk::TimelineSemaphoreSubmitInfo::builder()
+            .wait_semaphore_values(&wait_values)
+            .signal_semaphore_values(&signal_values);
+
+        let mut submit_info = vk::SubmitInfo::builder()
+            .wait_semaphores(&wait_semaphores)
             .wait_dst_stage_mask(&wait_dst_stage_masks)
+            .signal_semaphores(&signal_semaphores)
             .command_buffers(&pending_command_buffers);
 
+        if !timeline_waits.is_empty() || !timeline_signals.is_empty() {
+            submit_info = submit_info.push_next(&mut timeline_submit_info);
+        }
+
+        // Semaphores are waited on and signaled by the first physical device
+        let command_buffer_device_masks = vec![device_mask.unwrap_or(0); pending_command_buffers.len()];
+        let wait_semaphore_device_indices = vec![0; wait_semaphores.len()];
+        let signal_semaphore_device_indices = vec![0; signal_semaphores.len()];
+        let mut device_group_submit_info = vk::DeviceGroupSubmitInfo::builder()
+            .wait_semaphore_device_indices(&wait_semaphore_device_indices)
+            .command_buffer_device_masks(&command_buffer_device_masks)
+            .signal_semaphore_device_indices(&signal_semaphore_device_indices);
+
+        if device_mask.is_some() && device.physical_device_count > 1 {
+            submit_info = submit_info.push_next(&mut device_group_submit_info);
+        }
+
         let serial = self.last_submitted_serial.increment();
 
         log::trace!("queue_submit: {:?}", self.last_submitted_serial);
@@ -506,7 +1101,9 @@ impl DeviceState {
         }
 
         self.fences_in_flight.enqueue(fence, serial);
-        self.commands_in_flight.enqueue(pending_commands, serial);
+        for commands in self.ended_commands.drain(..) {
+            self.commands_in_flight.enqueue(commands, serial);
+        }
 
         self.delete_when_unused_wait_semaphores();
 
@@ -542,7 +1139,11 @@ impl DeviceState {
             ..Default::default()
         };
 
-        commands.pool = unsafe { device.raw.create_command_pool(&create_info, None)? };
+        commands.pool = unsafe {
+            device
+                .raw
+                .create_command_pool(&create_info, device.allocation_callbacks())?
+        };
 
         let allocate_info = vk::CommandBufferAllocateInfo {
             command_pool: commands.pool,
@@ -571,7 +1172,7 @@ impl DeviceState {
             Some(fence) => Ok(fence),
             None => {
                 let create_info = vk::FenceCreateInfo::default();
-                let fence = unsafe { device.raw.create_fence(&create_info, None)? };
+                let fence = unsafe { device.raw.create_fence(&create_info, device.allocation_callbacks())? };
                 Ok(fence)
             }
         }
@@ -598,13 +1199,111 @@ impl DeviceState {
         query: RenderPassCacheQuery,
         device: &DeviceInner,
     ) -> Result<vk::RenderPass, Error> {
-        self.renderpass_cache.get_render_pass(query, device)
+        let serial = self.get_next_pending_serial();
+        self.renderpass_cache
+            .get_render_pass(query, serial, &mut self.fenced_deleter, device)
+    }
+
+    /// The generation of the render pass cache, which changes whenever a render pass is evicted
+    pub fn render_pass_cache_generation(&self) -> u64 {
+        self.renderpass_cache.generation()
+    }
+
+    pub fn render_pass_cache_len(&self) -> usize {
+        self.renderpass_cache.len()
+    }
+
+    /// Evicts all cached render passes. They're destroyed after the pending submission completes.
+    pub fn evict_render_passes(&mut self) {
+        let serial = self.get_next_pending_serial();
+        self.renderpass_cache.evict_all(serial, &mut self.fenced_deleter);
     }
 }
 
 /// Recipe: _Selecting a queue family that supports presentation to a given surface_ (page `81`)
 ///
 /// Selects a queue family with the requested `queue_flags` and support for surface presentation.
+fn validate_extensions(requested: &Extensions, supported: &Extensions) -> Result<(), Error> {
+    let extensions = [
+        (
+            "anisotropic_filtering",
+            requested.anisotropic_filtering,
+            supported.anisotropic_filtering,
+        ),
+        (
+            "texture_compression_bc",
+            requested.texture_compression_bc,
+            supported.texture_compression_bc,
+        ),
+        (
+            "texture_compression_etc2",
+            requested.texture_compression_etc2,
+            supported.texture_compression_etc2,
+        ),
+        (
+            "texture_compression_astc_ldr",
+            requested.texture_compression_astc_ldr,
+            supported.texture_compression_astc_ldr,
+        ),
+        (
+            "timeline_semaphore",
+            requested.timeline_semaphore,
+            supported.timeline_semaphore,
+        ),
+        (
+            "transform_feedback",
+            requested.transform_feedback,
+            supported.transform_feedback,
+        ),
+        (
+            "subgroup_size_control",
+            requested.subgroup_size_control,
+            supported.subgroup_size_control,
+        ),
+        ("shader_float16", requested.shader_float16, supported.shader_float16),
+        ("shader_int8", requested.shader_int8, supported.shader_int8),
+        (
+            "storage_buffer_8bit_access",
+            requested.storage_buffer_8bit_access,
+            supported.storage_buffer_8bit_access,
+        ),
+        (
+            "storage_buffer_16bit_access",
+            requested.storage_buffer_16bit_access,
+            supported.storage_buffer_16bit_access,
+        ),
+        (
+            "index_type_uint8",
+            requested.index_type_uint8,
+            supported.index_type_uint8,
+        ),
+        (
+            "vertex_attribute_divisor",
+            requested.vertex_attribute_divisor,
+            supported.vertex_attribute_divisor,
+        ),
+        (
+            "pipeline_creation_feedback",
+            requested.pipeline_creation_feedback,
+            supported.pipeline_creation_feedback,
+        ),
+        (
+            "swapchain_mutable_format",
+            requested.swapchain_mutable_format,
+            supported.swapchain_mutable_format,
+        ),
+    ];
+
+    for &(name, requested, supported) in extensions.iter() {
+        if requested && !supported {
+            log::error!("extension is not supported by the adapter: {}", name);
+            return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+        }
+    }
+
+    Ok(())
+}
+
 pub fn select_queue_family_index(
     adapter: &AdapterInner,
     queue_flags: vk::QueueFlags,
@@ -1,7 +1,9 @@
+use crate::imp::fenced_deleter::DeleteWhenUnused;
 use crate::imp::serial::Serial;
 use crate::imp::{DeviceInner, FenceInner};
 use crate::{Error, Fence, FenceError};
 
+use ash::vk;
 use parking_lot::Mutex;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -38,12 +40,109 @@ impl Fence {
         let serial = *self.inner.serial.lock();
         serial <= get_last_completed_serial(&self.inner.device)
     }
+
+    /// Returns the current timeline value.
+    ///
+    /// Requires `Extensions::timeline_semaphore`.
+    pub fn completed_value(&self) -> Result<u64, Error> {
+        let semaphore = self.inner.timeline_semaphore()?;
+        let mut value = 0;
+        unsafe {
+            let result = self
+                .inner
+                .device
+                .raw_ext
+                .timeline_semaphore
+                .get_semaphore_counter_value_khr(self.inner.device.raw.handle(), semaphore, &mut value);
+            if result != vk::Result::SUCCESS {
+                return Err(Error::from(result));
+            }
+        }
+        Ok(value)
+    }
+
+    /// Waits for the timeline value to reach at least `value`.
+    ///
+    /// Requires `Extensions::timeline_semaphore`.
+    pub fn wait_for_value(&self, value: u64, timeout: Duration) -> Result<(), FenceError> {
+        let semaphore = self.inner.timeline_semaphore()?;
+        let semaphores = [semaphore];
+        let values = [value];
+        let wait_info = vk::SemaphoreWaitInfo::builder().semaphores(&semaphores).values(&values);
+        let result = unsafe {
+            self.inner.device.raw_ext.timeline_semaphore.wait_semaphores_khr(
+                self.inner.device.raw.handle(),
+                &*wait_info,
+                timeout.as_nanos() as u64,
+            )
+        };
+        match result {
+            vk::Result::SUCCESS => Ok(()),
+            vk::Result::TIMEOUT => Err(FenceError::Timeout),
+            result => Err(FenceError::from(result)),
+        }
+    }
+
+    /// Sets the timeline value from the host. The value must be greater than the current value
+    /// and any pending signal operations.
+    ///
+    /// Requires `Extensions::timeline_semaphore`.
+    pub fn signal_value(&self, value: u64) -> Result<(), Error> {
+        let semaphore = self.inner.timeline_semaphore()?;
+        let signal_info = vk::SemaphoreSignalInfo::builder().semaphore(semaphore).value(value);
+        let result = unsafe {
+            self.inner
+                .device
+                .raw_ext
+                .timeline_semaphore
+                .signal_semaphore_khr(self.inner.device.raw.handle(), &*signal_info)
+        };
+        match result {
+            vk::Result::SUCCESS => Ok(()),
+            result => Err(Error::from(result)),
+        }
+    }
 }
 
 impl FenceInner {
     pub fn new(device: Arc<DeviceInner>) -> Result<FenceInner, Error> {
         let serial = { Mutex::new(get_last_submitted_serial(&device)) };
-        Ok(FenceInner { serial, device })
+        let timeline = if device.extensions.timeline_semaphore {
+            let mut type_create_info = vk::SemaphoreTypeCreateInfo::builder()
+                .semaphore_type(vk::SemaphoreType::TIMELINE)
+                .initial_value(0);
+            let create_info = vk::SemaphoreCreateInfo::builder().push_next(&mut type_create_info);
+            let semaphore = unsafe {
+                device
+                    .raw
+                    .create_semaphore(&create_info, device.allocation_callbacks())?
+            };
+            Some(semaphore)
+        } else {
+            None
+        };
+        Ok(FenceInner {
+            serial,
+            device,
+            timeline,
+        })
+    }
+
+    pub fn timeline_semaphore(&self) -> Result<vk::Semaphore, Error> {
+        self.timeline.ok_or_else(|| {
+            log::error!("timeline fence operations require Extensions::timeline_semaphore");
+            Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT)
+        })
+    }
+}
+
+impl Drop for FenceInner {
+    fn drop(&mut self) {
+        if let Some(semaphore) = self.timeline.take() {
+            let mut state = self.device.state.lock();
+            let serial = state.get_next_pending_serial();
+            state.get_fenced_deleter().delete_when_unused(semaphore, serial);
+        }
     }
 }
 
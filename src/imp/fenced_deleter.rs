@@ -15,6 +15,8 @@ pub struct FencedDeleter {
     buffers: SerialQueue<(vk::Buffer, Allocation)>,
     buffer_views: SerialQueue<vk::BufferView>,
     images: SerialQueue<(vk::Image, Allocation)>,
+    sparse_images: SerialQueue<vk::Image>,
+    allocations: SerialQueue<Allocation>,
     image_views: SerialQueue<vk::ImageView>,
     samplers: SerialQueue<vk::Sampler>,
     descriptor_set_layouts: SerialQueue<vk::DescriptorSetLayout>,
@@ -23,6 +25,8 @@ pub struct FencedDeleter {
     pipeline_layouts: SerialQueue<vk::PipelineLayout>,
     pipelines: SerialQueue<vk::Pipeline>,
     framebuffers: SerialQueue<vk::Framebuffer>,
+    render_passes: SerialQueue<vk::RenderPass>,
+    query_pools: SerialQueue<vk::QueryPool>,
     surface_keepalive: SerialQueue<Arc<SurfaceInner>>,
     // NOTE: Update is_empty(&self) when adding to this list
 }
@@ -31,7 +35,10 @@ impl FencedDeleter {
     pub unsafe fn purge_swapchains(&mut self, device: &DeviceInner) {
         for ((handle, surface), serial) in self.swapchains.drain(..) {
             log::debug!("destroy swapchain (purged): {:?}, completed: {:?}", handle, serial);
-            device.raw_ext.swapchain.destroy_swapchain(handle, None);
+            device
+                .raw_ext
+                .swapchain
+                .destroy_swapchain(handle, device.allocation_callbacks());
             drop(surface); // assert that the surface lives longer than the swapchain
         }
     }
@@ -45,6 +52,8 @@ impl FencedDeleter {
             log::trace!(" buffers:                {}", self.buffers.len());
             log::trace!(" buffer_views:           {}", self.buffer_views.len());
             log::trace!(" images:                 {}", self.images.len());
+            log::trace!(" sparse_images:          {}", self.sparse_images.len());
+            log::trace!(" allocations:            {}", self.allocations.len());
             log::trace!(" image_views:            {}", self.image_views.len());
             log::trace!(" descriptor_set_layouts: {}", self.descriptor_set_layouts.len());
             log::trace!(" descriptor_pools:       {}", self.descriptor_pools.len());
@@ -52,12 +61,17 @@ impl FencedDeleter {
             log::trace!(" pipeline_layouts:       {}", self.pipeline_layouts.len());
             log::trace!(" pipelines:              {}", self.pipelines.len());
             log::trace!(" framebuffers:           {}", self.framebuffers.len());
+            log::trace!(" render_passes:          {}", self.render_passes.len());
+            log::trace!(" query_pools:            {}", self.query_pools.len());
         }
 
         for ((handle, surface), serial) in self.swapchains.drain_up_to(last_completed_serial) {
             log::debug!("destroy swapchain: {:?}, completed: {:?}", handle, serial);
             unsafe {
-                device.raw_ext.swapchain.destroy_swapchain(handle, None);
+                device
+                    .raw_ext
+                    .swapchain
+                    .destroy_swapchain(handle, device.allocation_callbacks());
             }
             drop(surface); // the surface must kept alive at least as long as the swapchain
         }
@@ -65,7 +79,7 @@ impl FencedDeleter {
         for (handle, serial) in self.semaphores.drain_up_to(last_completed_serial) {
             log::trace!("destroy semaphore: {:?}, completed: {:?}", handle, serial);
             unsafe {
-                device.raw.destroy_semaphore(handle, None);
+                device.raw.destroy_semaphore(handle, device.allocation_callbacks());
             }
         }
 
@@ -79,66 +93,98 @@ impl FencedDeleter {
             allocator.destroy_image(handle, &allocation);
         }
 
+        for (handle, serial) in self.sparse_images.drain_up_to(last_completed_serial) {
+            log::trace!("destroy sparse image: {:?}, completed: {:?}", handle, serial);
+            unsafe {
+                device.raw.destroy_image(handle, device.allocation_callbacks());
+            }
+        }
+
+        for (allocation, serial) in self.allocations.drain_up_to(last_completed_serial) {
+            log::trace!("free allocation: {:?}, completed: {:?}", allocation, serial);
+            allocator.free_memory(&allocation);
+        }
+
         for (handle, serial) in self.image_views.drain_up_to(last_completed_serial) {
             log::trace!("destroy image_view: {:?}, completed: {:?}", handle, serial);
             unsafe {
-                device.raw.destroy_image_view(handle, None);
+                device.raw.destroy_image_view(handle, device.allocation_callbacks());
             }
         }
 
         for (handle, serial) in self.buffer_views.drain_up_to(last_completed_serial) {
             log::trace!("destroy buffer_view: {:?}, completed: {:?}", handle, serial);
             unsafe {
-                device.raw.destroy_buffer_view(handle, None);
+                device.raw.destroy_buffer_view(handle, device.allocation_callbacks());
             }
         }
 
         for (handle, serial) in self.samplers.drain_up_to(last_completed_serial) {
             log::trace!("destroy sampler: {:?}, completed: {:?}", handle, serial);
             unsafe {
-                device.raw.destroy_sampler(handle, None);
+                device.raw.destroy_sampler(handle, device.allocation_callbacks());
             }
         }
 
         for (handle, serial) in self.descriptor_set_layouts.drain_up_to(last_completed_serial) {
             log::trace!("destroy descriptor set layout: {:?}, completed: {:?}", handle, serial);
             unsafe {
-                device.raw.destroy_descriptor_set_layout(handle, None);
+                device
+                    .raw
+                    .destroy_descriptor_set_layout(handle, device.allocation_callbacks());
             }
         }
 
         for (handle, serial) in self.descriptor_pools.drain_up_to(last_completed_serial) {
             log::trace!("destroy descriptor pool: {:?}, completed: {:?}", handle, serial);
             unsafe {
-                device.raw.destroy_descriptor_pool(handle, None);
+                device
+                    .raw
+                    .destroy_descriptor_pool(handle, device.allocation_callbacks());
             }
         }
 
         for (handle, serial) in self.shader_modules.drain_up_to(last_completed_serial) {
             log::trace!("destroy shader module: {:?}, completed: {:?}", handle, serial);
             unsafe {
-                device.raw.destroy_shader_module(handle, None);
+                device.raw.destroy_shader_module(handle, device.allocation_callbacks());
             }
         }
 
         for (handle, serial) in self.pipeline_layouts.drain_up_to(last_completed_serial) {
             log::trace!("destroy pipeline layout: {:?}, completed: {:?}", handle, serial);
             unsafe {
-                device.raw.destroy_pipeline_layout(handle, None);
+                device
+                    .raw
+                    .destroy_pipeline_layout(handle, device.allocation_callbacks());
             }
         }
 
         for (handle, serial) in self.pipelines.drain_up_to(last_completed_serial) {
             log::trace!("destroy pipeline: {:?}, completed: {:?}", handle, serial);
             unsafe {
-                device.raw.destroy_pipeline(handle, None);
+                device.raw.destroy_pipeline(handle, device.allocation_callbacks());
             }
         }
 
         for (handle, serial) in self.framebuffers.drain_up_to(last_completed_serial) {
             log::trace!("destroy framebuffers: {:?}, completed: {:?}", handle, serial);
             unsafe {
-                device.raw.destroy_framebuffer(handle, None);
+                device.raw.destroy_framebuffer(handle, device.allocation_callbacks());
+            }
+        }
+
+        for (handle, serial) in self.render_passes.drain_up_to(last_completed_serial) {
+            log::trace!("destroy render pass: {:?}, completed: {:?}", handle, serial);
+            unsafe {
+                device.raw.destroy_render_pass(handle, device.allocation_callbacks());
+            }
+        }
+
+        for (handle, serial) in self.query_pools.drain_up_to(last_completed_serial) {
+            log::trace!("destroy query pool: {:?}, completed: {:?}", handle, serial);
+            unsafe {
+                device.raw.destroy_query_pool(handle, device.allocation_callbacks());
             }
         }
     }
@@ -149,6 +195,8 @@ impl FencedDeleter {
             && self.buffers.is_empty()
             && self.buffer_views.is_empty()
             && self.images.is_empty()
+            && self.sparse_images.is_empty()
+            && self.allocations.is_empty()
             && self.image_views.is_empty()
             && self.samplers.is_empty()
             && self.descriptor_set_layouts.is_empty()
@@ -157,6 +205,8 @@ impl FencedDeleter {
             && self.pipeline_layouts.is_empty()
             && self.pipelines.is_empty()
             && self.framebuffers.is_empty()
+            && self.render_passes.is_empty()
+            && self.query_pools.is_empty()
             && self.surface_keepalive.is_empty()
     }
 }
@@ -211,6 +261,18 @@ impl DeleteWhenUnused<(vk::Image, Allocation)> for FencedDeleter {
     }
 }
 
+impl DeleteWhenUnused<vk::Image> for FencedDeleter {
+    fn get_serial_queue(&mut self) -> &mut SerialQueue<vk::Image> {
+        &mut self.sparse_images
+    }
+}
+
+impl DeleteWhenUnused<Allocation> for FencedDeleter {
+    fn get_serial_queue(&mut self) -> &mut SerialQueue<Allocation> {
+        &mut self.allocations
+    }
+}
+
 impl DeleteWhenUnused<vk::ImageView> for FencedDeleter {
     fn get_serial_queue(&mut self) -> &mut SerialQueue<vk::ImageView> {
         &mut self.image_views
@@ -258,3 +320,15 @@ impl DeleteWhenUnused<vk::Framebuffer> for FencedDeleter {
         &mut self.framebuffers
     }
 }
+
+impl DeleteWhenUnused<vk::RenderPass> for FencedDeleter {
+    fn get_serial_queue(&mut self) -> &mut SerialQueue<vk::RenderPass> {
+        &mut self.render_passes
+    }
+}
+
+impl DeleteWhenUnused<vk::QueryPool> for FencedDeleter {
+    fn get_serial_queue(&mut self) -> &mut SerialQueue<vk::QueryPool> {
+        &mut self.query_pools
+    }
+}
@@ -0,0 +1,147 @@
+use ash::vk;
+
+use std::mem;
+use std::time::Duration;
+
+use crate::imp::FrameResources;
+use crate::{
+    Buffer, BufferDescriptor, BufferUsage, Device, Error, Frame, FrameScheduler, FrameSchedulerDescriptor, Pod,
+    SubmissionIndex, Swapchain, SwapchainError,
+};
+
+/// Staging writes are aligned to the largest texel block so that the staged data can also be
+/// copied into textures.
+const STAGING_ALIGNMENT: usize = 16;
+
+/// The maximum time that `begin_frame` waits for a frame in flight to complete
+const FRAME_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl FrameScheduler {
+    pub fn new(device: &Device, descriptor: FrameSchedulerDescriptor) -> Result<FrameScheduler, Error> {
+        if descriptor.frames_in_flight == 0 {
+            log::error!("frames_in_flight must be greater than zero");
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        let mut frames = Vec::with_capacity(descriptor.frames_in_flight as usize);
+        for _ in 0..descriptor.frames_in_flight {
+            let staging_buffer = if descriptor.staging_buffer_size > 0 {
+                Some(device.create_buffer(BufferDescriptor {
+                    size: descriptor.staging_buffer_size,
+                    usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
+                    memory_hint: None,
+                })?)
+            } else {
+                None
+            };
+            frames.push(FrameResources {
+                submission: None,
+                staging_buffer,
+            });
+        }
+
+        Ok(FrameScheduler {
+            queue: device.get_queue(),
+            frames,
+            frame_index: 0,
+        })
+    }
+
+    /// The number of frames that may be in flight
+    pub fn frames_in_flight(&self) -> u32 {
+        self.frames.len() as u32
+    }
+
+    /// Waits for the GPU to finish with the next frame's resources and acquires the next
+    /// swapchain image.
+    ///
+    /// The frame must be passed to `end_frame`. If the frame is dropped instead, the acquired
+    /// image is never presented and the frame's commands are discarded.
+    pub fn begin_frame(&mut self, swapchain: &Swapchain) -> Result<Frame, SwapchainError> {
+        let device = self.queue.device();
+        let frame_resources = &mut self.frames[self.frame_index];
+
+        if let Some(submission) = frame_resources.submission {
+            device
+                .wait_for_submission(submission, FRAME_TIMEOUT)
+                .map_err(Error::from)?;
+            frame_resources.submission = None;
+        }
+
+        let image = swapchain.acquire_next_image()?;
+        let encoder = device.create_command_encoder()?;
+
+        Ok(Frame {
+            image,
+            encoder,
+            staging_buffer: frame_resources.staging_buffer.clone(),
+            staging_offset: 0,
+            frame_index: self.frame_index,
+        })
+    }
+
+    /// Submits the frame's commands and presents its swapchain image. Returns the index of the
+    /// submission.
+    pub fn end_frame(&mut self, frame: Frame) -> Result<SubmissionIndex, SwapchainError> {
+        let Frame {
+            image,
+            encoder,
+            frame_index,
+            ..
+        } = frame;
+
+        let submission = self.queue.submit(&[encoder.finish()?])?;
+        self.frames[frame_index].submission = Some(submission);
+        self.frame_index = (frame_index + 1) % self.frames.len();
+
+        self.queue.present(image)?;
+
+        Ok(submission)
+    }
+}
+
+impl Frame {
+    /// The index of the frame's slot, in `0..frames_in_flight`. Resources that are written by the
+    /// CPU each frame can be indexed by the slot.
+    pub fn index(&self) -> usize {
+        self.frame_index
+    }
+
+    /// Writes `data` into the frame's staging buffer and records a copy into `dst` at
+    /// `dst_offset_bytes`. The buffer requires the `COPY_DST` usage.
+    ///
+    /// Fails if the staging buffer does not have enough space left for `data`.
+    pub fn write_buffer<T: Pod>(&mut self, dst: &Buffer, dst_offset_bytes: usize, data: &[T]) -> Result<(), Error> {
+        let staging_buffer = match self.staging_buffer.as_ref() {
+            Some(staging_buffer) => staging_buffer,
+            None => {
+                log::error!("frame scheduler was created without a staging buffer");
+                return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+            }
+        };
+
+        let size_bytes = mem::size_of::<T>() * data.len();
+        let offset_bytes = (self.staging_offset + STAGING_ALIGNMENT - 1) & !(STAGING_ALIGNMENT - 1);
+        if offset_bytes + size_bytes > staging_buffer.size() {
+            log::error!(
+                "staging buffer exhausted: offset_bytes: {}, size_bytes: {}, staging_buffer_size: {}",
+                offset_bytes,
+                size_bytes,
+                staging_buffer.size()
+            );
+            return Err(Error::from(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY));
+        }
+
+        {
+            let mut mapped = staging_buffer.map_write()?;
+            let bytes: &[u8] = bytemuck::cast_slice(data);
+            mapped.write::<u8>(offset_bytes, size_bytes)?.copy_from_slice(bytes);
+        }
+
+        self.encoder
+            .copy_buffer_to_buffer(staging_buffer, offset_bytes, dst, dst_offset_bytes, size_bytes);
+        self.staging_offset = offset_bytes + size_bytes;
+
+        Ok(())
+    }
+}
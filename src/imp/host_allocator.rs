@@ -0,0 +1,173 @@
+use ash::vk;
+
+use crate::HostMemoryUsage;
+
+use std::alloc::{self, Layout};
+use std::ffi::c_void;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The size and alignment of each allocation are stored in front of the memory returned to the
+/// driver so that `free` can reconstruct the layout
+const HEADER_SIZE: usize = 2 * mem::size_of::<usize>();
+
+/// Forwards the host memory allocations of the Vulkan implementation to the global allocator and
+/// counts them. See `InstanceDescriptor::track_host_allocations`.
+#[derive(Debug)]
+pub struct HostAllocator {
+    allocated_bytes: AtomicUsize,
+    allocation_count: AtomicUsize,
+    internal_allocated_bytes: AtomicUsize,
+    /// References the allocator itself through `p_user_data`, which is why the allocator is boxed
+    callbacks: vk::AllocationCallbacks,
+}
+
+// The callbacks only access the atomic counters through `p_user_data`
+unsafe impl Send for HostAllocator {}
+unsafe impl Sync for HostAllocator {}
+
+impl HostAllocator {
+    pub fn new() -> Box<HostAllocator> {
+        let mut host_allocator = Box::new(HostAllocator {
+            allocated_bytes: AtomicUsize::new(0),
+            allocation_count: AtomicUsize::new(0),
+            internal_allocated_bytes: AtomicUsize::new(0),
+            callbacks: vk::AllocationCallbacks::default(),
+        });
+        let user_data = &*host_allocator as *const HostAllocator as *mut c_void;
+        host_allocator.callbacks = vk::AllocationCallbacks {
+            p_user_data: user_data,
+            pfn_allocation: Some(allocation_callback),
+            pfn_reallocation: Some(reallocation_callback),
+            pfn_free: Some(free_callback),
+            pfn_internal_allocation: Some(internal_allocation_callback),
+            pfn_internal_free: Some(internal_free_callback),
+        };
+        host_allocator
+    }
+
+    /// The allocator must outlive every object created with the callbacks
+    pub fn callbacks(&self) -> &vk::AllocationCallbacks {
+        &self.callbacks
+    }
+
+    pub fn usage(&self) -> HostMemoryUsage {
+        HostMemoryUsage {
+            allocated_bytes: self.allocated_bytes.load(Ordering::Relaxed),
+            allocation_count: self.allocation_count.load(Ordering::Relaxed),
+            internal_allocated_bytes: self.internal_allocated_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    unsafe fn allocate(&self, size: usize, alignment: usize) -> *mut c_void {
+        if size == 0 {
+            return ptr::null_mut();
+        }
+        let alignment = alignment.max(mem::align_of::<usize>());
+        let offset = alignment.max(HEADER_SIZE);
+        // A huge request must not wrap around to a small layout
+        let total_size = match offset.checked_add(size) {
+            Some(total_size) => total_size,
+            None => return ptr::null_mut(),
+        };
+        let layout = match Layout::from_size_align(total_size, alignment) {
+            Ok(layout) => layout,
+            Err(_) => return ptr::null_mut(),
+        };
+        let base = alloc::alloc(layout);
+        if base.is_null() {
+            return ptr::null_mut();
+        }
+        let memory = base.add(offset);
+        let header = memory as *mut usize;
+        header.sub(1).write(size);
+        header.sub(2).write(alignment);
+        self.allocated_bytes.fetch_add(size, Ordering::Relaxed);
+        self.allocation_count.fetch_add(1, Ordering::Relaxed);
+        memory as *mut c_void
+    }
+
+    /// Returns the size of the allocation
+    unsafe fn size_of(memory: *mut c_void) -> usize {
+        (memory as *mut usize).sub(1).read()
+    }
+
+    unsafe fn free(&self, memory: *mut c_void) {
+        if memory.is_null() {
+            return;
+        }
+        let header = memory as *mut usize;
+        let size = header.sub(1).read();
+        let alignment = header.sub(2).read();
+        let offset = alignment.max(HEADER_SIZE);
+        let layout = Layout::from_size_align_unchecked(offset + size, alignment);
+        alloc::dealloc((memory as *mut u8).sub(offset), layout);
+        self.allocated_bytes.fetch_sub(size, Ordering::Relaxed);
+        self.allocation_count.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+unsafe extern "system" fn allocation_callback(
+    user_data: *mut c_void,
+    size: usize,
+    alignment: usize,
+    _scope: vk::SystemAllocationScope,
+) -> *mut c_void {
+    let host_allocator = &*(user_data as *const HostAllocator);
+    host_allocator.allocate(size, alignment)
+}
+
+unsafe extern "system" fn reallocation_callback(
+    user_data: *mut c_void,
+    original: *mut c_void,
+    size: usize,
+    alignment: usize,
+    _scope: vk::SystemAllocationScope,
+) -> *mut c_void {
+    let host_allocator = &*(user_data as *const HostAllocator);
+    if original.is_null() {
+        return host_allocator.allocate(size, alignment);
+    }
+    if size == 0 {
+        host_allocator.free(original);
+        return ptr::null_mut();
+    }
+    // The original allocation must be left unchanged if the new allocation fails
+    let memory = host_allocator.allocate(size, alignment);
+    if !memory.is_null() {
+        let copy_size = HostAllocator::size_of(original).min(size);
+        ptr::copy_nonoverlapping(original as *const u8, memory as *mut u8, copy_size);
+        host_allocator.free(original);
+    }
+    memory
+}
+
+unsafe extern "system" fn free_callback(user_data: *mut c_void, memory: *mut c_void) {
+    let host_allocator = &*(user_data as *const HostAllocator);
+    host_allocator.free(memory);
+}
+
+unsafe extern "system" fn internal_allocation_callback(
+    user_data: *mut c_void,
+    size: usize,
+    _allocation_type: vk::InternalAllocationType,
+    _scope: vk::SystemAllocationScope,
+) {
+    let host_allocator = &*(user_data as *const HostAllocator);
+    host_allocator
+        .internal_allocated_bytes
+        .fetch_add(size, Ordering::Relaxed);
+}
+
+unsafe extern "system" fn internal_free_callback(
+    user_data: *mut c_void,
+    size: usize,
+    _allocation_type: vk::InternalAllocationType,
+    _scope: vk::SystemAllocationScope,
+) {
+    let host_allocator = &*(user_data as *const HostAllocator);
+    host_allocator
+        .internal_allocated_bytes
+        .fetch_sub(size, Ordering::Relaxed);
+}
@@ -1,7 +1,9 @@
-use std::ffi::CStr;
+use std::any::Any;
+use std::ffi::{c_void, CStr, CString, OsStr};
 use std::fmt;
 use std::fmt::Debug;
 use std::mem;
+use std::os::raw::c_char;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
@@ -13,25 +15,120 @@ use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 
 use lazy_static::lazy_static;
 
+use crate::imp::host_allocator::HostAllocator;
 use crate::imp::{debug, AdapterInner, InstanceExt, InstanceInner, SurfaceInner};
-use crate::{Adapter, AdapterOptions, Error, Instance, Surface};
+use crate::{
+    Adapter, AdapterGroup, AdapterOptions, Error, HostMemoryUsage, Instance, InstanceDescriptor, Surface,
+    SurfaceDescriptor, ValidationFeatures,
+};
+
+#[cfg(target_os = "macos")]
+use crate::MoltenVkConfig;
+
+/// `VK_KHR_portability_enumeration`
+const KHR_PORTABILITY_ENUMERATION: &str = "VK_KHR_portability_enumeration";
+
+/// `VK_INSTANCE_CREATE_ENUMERATE_PORTABILITY_BIT_KHR`
+const INSTANCE_CREATE_ENUMERATE_PORTABILITY: vk::InstanceCreateFlags = vk::InstanceCreateFlags::from_raw(0x1);
+
+/// Vulkan entry points. The library that provides them is type-erased so that the default
+/// loader, a statically linked implementation, or a user supplied loader are interchangeable.
+/// The library is kept alive for as long as any instance references the entry.
+pub(crate) type Entry = ash::EntryCustom<Arc<dyn Any + Send + Sync>>;
+
+#[cfg(feature = "moltenvk-static")]
+extern "system" {
+    // Provided by the statically linked MoltenVK library (see build.rs)
+    fn vkGetInstanceProcAddr(instance: vk::Instance, name: *const c_char) -> vk::PFN_vkVoidFunction;
+}
+
+unsafe fn entry_from_proc_addr(
+    library: Arc<dyn Any + Send + Sync>,
+    get_instance_proc_addr: vk::PFN_vkGetInstanceProcAddr,
+) -> Result<Entry, Error> {
+    ash::EntryCustom::new_custom(library, |_, name| {
+        mem::transmute(get_instance_proc_addr(vk::Instance::null(), name.as_ptr()))
+    })
+    .map_err(|e| Error::from(format!("Failed to load Vulkan entry points: {}", e)))
+}
+
+unsafe fn entry_from_ash(entry: ash::Entry) -> Result<Entry, Error> {
+    let get_instance_proc_addr = entry.static_fn().get_instance_proc_addr;
+    entry_from_proc_addr(Arc::new(entry), get_instance_proc_addr)
+}
+
+#[cfg(not(feature = "moltenvk-static"))]
+unsafe fn load_entry() -> Result<Entry, Error> {
+    entry_from_ash(ash::Entry::new()?)
+}
+
+#[cfg(feature = "moltenvk-static")]
+unsafe fn load_entry() -> Result<Entry, Error> {
+    entry_from_proc_addr(Arc::new(()), vkGetInstanceProcAddr)
+}
 
 lazy_static! {
-    static ref ENTRY: RwLock<Result<ash::Entry, Error>> = {
+    static ref ENTRY: RwLock<Result<Entry, Error>> = {
         unsafe {
             extern "C" fn unload() {
                 let mut entry_guard = ENTRY.write();
                 *entry_guard = Err(Error::from(String::from("Vulkan library unloaded")));
             }
             libc::atexit(unload);
-            RwLock::new(ash::Entry::new().map_err(Into::into))
+            RwLock::new(load_entry())
         }
     };
 }
 
 impl Instance {
     pub fn new() -> Result<Instance, Error> {
-        let inner = InstanceInner::new()?;
+        Instance::with_descriptor(InstanceDescriptor::default())
+    }
+
+    pub fn with_descriptor(descriptor: InstanceDescriptor) -> Result<Instance, Error> {
+        let entry = {
+            let entry_guard: RwLockReadGuard<Result<Entry, Error>> = ENTRY.read();
+            entry_guard.as_ref()?.clone()
+        };
+        let inner = InstanceInner::new(&descriptor, entry)?;
+        Ok(inner.into())
+    }
+
+    /// Creates an instance from an already loaded Vulkan library instead of the default loader
+    /// search. The library remains loaded for the lifetime of the instance.
+    pub fn new_with_entry(entry: ash::Entry) -> Result<Instance, Error> {
+        Instance::with_descriptor_and_entry(InstanceDescriptor::default(), entry)
+    }
+
+    pub fn with_descriptor_and_entry(descriptor: InstanceDescriptor, entry: ash::Entry) -> Result<Instance, Error> {
+        let entry = unsafe { entry_from_ash(entry)? };
+        let inner = InstanceInner::new(&descriptor, entry)?;
+        Ok(inner.into())
+    }
+
+    /// Creates an instance from the Vulkan loader (or ICD) at `path`, e.g. when running inside
+    /// a container runtime where the loader is not in the default library search path.
+    pub fn with_descriptor_and_loader_path<P: AsRef<OsStr>>(
+        descriptor: InstanceDescriptor,
+        path: P,
+    ) -> Result<Instance, Error> {
+        let entry = unsafe { ash::Entry::with_library(&path)? };
+        Instance::with_descriptor_and_entry(descriptor, entry)
+    }
+
+    /// Creates an instance from a `vkGetInstanceProcAddr` function, e.g. one exported by a
+    /// statically linked implementation.
+    ///
+    /// # Safety
+    ///
+    /// `get_instance_proc_addr` must remain valid for the lifetime of the instance and every
+    /// object created from it.
+    pub unsafe fn with_descriptor_and_get_instance_proc_addr(
+        descriptor: InstanceDescriptor,
+        get_instance_proc_addr: vk::PFN_vkGetInstanceProcAddr,
+    ) -> Result<Instance, Error> {
+        let entry = entry_from_proc_addr(Arc::new(()), get_instance_proc_addr)?;
+        let inner = InstanceInner::new(&descriptor, entry)?;
         Ok(inner.into())
     }
 
@@ -39,11 +136,76 @@ impl Instance {
         self.inner.instance_version
     }
 
+    /// Returns the host memory currently allocated by the Vulkan implementation for the instance
+    /// and its devices, or `None` if `InstanceDescriptor::track_host_allocations` was not set.
+    pub fn host_memory_usage(&self) -> Option<HostMemoryUsage> {
+        self.inner
+            .host_allocator
+            .as_ref()
+            .map(|host_allocator| host_allocator.usage())
+    }
+
     pub fn request_adapter(&self, options: AdapterOptions) -> Result<Adapter, Error> {
         let adapter = AdapterInner::request(self.inner.clone(), options)?;
         Ok(adapter.into())
     }
 
+    /// Returns the groups of adapters that can be combined into a single `Device`. Every adapter
+    /// belongs to exactly one group. Requires `VK_KHR_device_group_creation`; otherwise each
+    /// adapter is returned in its own group.
+    pub fn enumerate_adapter_groups(&self) -> Result<Vec<AdapterGroup>, Error> {
+        let device_group_creation = match self.inner.raw_ext.device_group_creation.as_ref() {
+            Some(device_group_creation) => device_group_creation,
+            None => {
+                let adapter_groups = self
+                    .enumerate_adapters()?
+                    .drain(..)
+                    .map(|adapter| AdapterGroup {
+                        adapters: vec![adapter],
+                        subset_allocation: false,
+                    })
+                    .collect();
+                return Ok(adapter_groups);
+            }
+        };
+
+        let instance = self.inner.raw.handle();
+        let group_properties = unsafe {
+            let mut count = 0;
+            let ret =
+                device_group_creation.enumerate_physical_device_groups_khr(instance, &mut count, std::ptr::null_mut());
+            if ret != vk::Result::SUCCESS {
+                return Err(Error::from(ret));
+            }
+            let mut group_properties = vec![vk::PhysicalDeviceGroupProperties::default(); count as usize];
+            let ret = device_group_creation.enumerate_physical_device_groups_khr(
+                instance,
+                &mut count,
+                group_properties.as_mut_ptr(),
+            );
+            if ret != vk::Result::SUCCESS {
+                return Err(Error::from(ret));
+            }
+            group_properties
+        };
+
+        let mut adapter_groups = Vec::with_capacity(group_properties.len());
+        for properties in group_properties.iter() {
+            let physical_devices = &properties.physical_devices[..properties.physical_device_count as usize];
+            let mut adapters = Vec::with_capacity(physical_devices.len());
+            for physical_device in physical_devices.iter() {
+                adapters.push(AdapterInner::new(&self.inner, *physical_device)?.into());
+            }
+            log::debug!("found adapter group: {:?}", physical_devices);
+            adapter_groups.push(AdapterGroup {
+                adapters,
+                subset_allocation: properties.subset_allocation == vk::TRUE,
+            });
+        }
+
+        Ok(adapter_groups)
+    }
+
     pub fn enumerate_adapters(&self) -> Result<Vec<Adapter>, Error> {
         let adapters = AdapterInner::enumerate(&self.inner)?
             .drain(..)
@@ -57,19 +219,33 @@ impl Instance {
     }
 
     pub fn create_surface_from_raw_window_handle(&self, raw_window_handle: RawWindowHandle) -> Result<Surface, Error> {
-        let surface = SurfaceInner::from_raw_window_handle(self.inner.clone(), raw_window_handle)?;
+        self.create_surface_from_descriptor(SurfaceDescriptor { raw_window_handle })
+    }
+
+    pub fn create_surface_from_descriptor(&self, descriptor: SurfaceDescriptor) -> Result<Surface, Error> {
+        let surface = SurfaceInner::new(self.inner.clone(), descriptor)?;
         Ok(surface.into())
     }
+
+    /// Creates a new surface to replace one that was lost (`SwapchainError::SurfaceLost`) or whose
+    /// window was moved to a different display. The swapchain must be re-created with the new surface.
+    ///
+    /// The `descriptor` may be the previous `Surface::descriptor` if the window itself is unchanged.
+    pub fn recreate_surface(&self, surface: &Surface, descriptor: &SurfaceDescriptor) -> Result<Surface, Error> {
+        if !Arc::ptr_eq(&surface.inner.instance, &self.inner) {
+            log::error!("surface was not created by this instance: {:?}", surface.inner.handle);
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+        log::debug!("re-creating surface: {:?}", surface.inner.handle);
+        self.create_surface_from_descriptor(*descriptor)
+    }
 }
 
 impl InstanceInner {
-    fn new() -> Result<InstanceInner, Error> {
+    fn new(descriptor: &InstanceDescriptor, entry: Entry) -> Result<InstanceInner, Error> {
         let test_validation_hook = debug::TEST_VALIDATION_HOOK.load(Ordering::Acquire);
 
         unsafe {
-            let entry_guard: RwLockReadGuard<Result<ash::Entry, Error>> = ENTRY.read();
-            let entry: &ash::Entry = entry_guard.as_ref()?;
-
             let instance_version = entry
                 .try_enumerate_instance_version()?
                 .map(|v| {
@@ -101,6 +277,19 @@ impl InstanceInner {
                 if name_cow == "VK_EXT_debug_utils" {
                     include_extension = true;
                 }
+                if name == vk::KhrGetPhysicalDeviceProperties2Fn::name() {
+                    include_extension = true;
+                }
+                if name == vk::KhrDeviceGroupCreationFn::name() {
+                    include_extension = true;
+                }
+                // Required to enumerate portability implementations such as MoltenVK
+                if name_cow == KHR_PORTABILITY_ENUMERATION {
+                    include_extension = true;
+                }
+                if descriptor.extra_extensions.iter().any(|extra| *extra == name_cow) {
+                    include_extension = true;
+                }
                 if include_extension {
                     log::info!("requesting instance extension: {}", name_cow);
                     extension_names.push(name.to_owned());
@@ -114,16 +303,44 @@ impl InstanceInner {
                 log::debug!("found instance layer: {}", name.to_string_lossy());
             }
 
-            let app_info = vk::ApplicationInfo::builder().api_version(vk::make_api_version(0, 1, 0, 0));
+            for extra_extension in descriptor.extra_extensions.iter() {
+                if !extension_names
+                    .iter()
+                    .any(|name| name.to_string_lossy() == *extra_extension)
+                {
+                    log::error!("requested instance extension unavailable: {}", extra_extension);
+                    return Err(Error::from(vk::Result::ERROR_EXTENSION_NOT_PRESENT));
+                }
+            }
+
+            let c_string = |name: &str| {
+                CString::new(name).map_err(|e| {
+                    log::error!("invalid instance descriptor name: {:?}", e);
+                    Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT)
+                })
+            };
 
-            let requested_layer_names = vec![
-                #[cfg(debug_assertions)]
-                c_str!("VK_LAYER_KHRONOS_validation"),
-            ];
+            let app_name = c_string(descriptor.app_name)?;
+            let engine_name = c_string(descriptor.engine_name)?;
+            let version = |(major, minor, patch)| vk::make_api_version(0, major, minor, patch);
+            let app_info = vk::ApplicationInfo::builder()
+                .application_name(&app_name)
+                .application_version(version(descriptor.app_version))
+                .engine_name(&engine_name)
+                .engine_version(version(descriptor.engine_version))
+                .api_version(version(descriptor.api_version));
+
+            let mut requested_layer_names = Vec::new();
+            if descriptor.enable_validation {
+                requested_layer_names.push(c_string("VK_LAYER_KHRONOS_validation")?);
+            }
+            for extra_layer in descriptor.extra_layers.iter() {
+                requested_layer_names.push(c_string(extra_layer)?);
+            }
 
             let layer_names = requested_layer_names
                 .iter()
-                .cloned()
+                .map(|layer_name| layer_name.as_ptr())
                 .filter(|layer_name| {
                     let requested_layer_name = CStr::from_ptr(*layer_name);
                     let is_available = instance_layer_properties.iter().any(|p| {
@@ -143,7 +360,7 @@ impl InstanceInner {
                 .collect::<Vec<_>>();
 
             // Make missing layers a hard error when the unit test hook is set.
-            if requested_layer_names != layer_names && test_validation_hook {
+            if requested_layer_names.len() != layer_names.len() && test_validation_hook {
                 log::error!(
                     "not all requested layers are available. requested: {:?}; available: {:?}",
                     requested_layer_names,
@@ -152,46 +369,141 @@ impl InstanceInner {
                 return Err(Error::from("Missing required layers"));
             }
 
+            let mut enabled_validation_features = Vec::new();
+            if !descriptor.validation_features.is_empty() {
+                let validation_layer_name = CStr::from_bytes_with_nul_unchecked(b"VK_LAYER_KHRONOS_validation\0");
+                let validation_layer_enabled = layer_names
+                    .iter()
+                    .any(|layer_name| CStr::from_ptr(*layer_name) == validation_layer_name);
+                let validation_features = vk::ExtValidationFeaturesFn::name();
+                if !validation_layer_enabled {
+                    log::warn!("validation features require the validation layer to be enabled");
+                } else if !layer_has_extension(&entry, validation_layer_name, validation_features)? {
+                    log::warn!(
+                        "validation features are not supported by the validation layer: {}",
+                        validation_features.to_string_lossy()
+                    );
+                } else {
+                    log::info!(
+                        "requesting instance extension: {}",
+                        validation_features.to_string_lossy()
+                    );
+                    extension_names.push(validation_features.to_owned());
+                    enabled_validation_features = validation_feature_enables(descriptor.validation_features);
+                }
+            }
+
+            let mut validation_features =
+                vk::ValidationFeaturesEXT::builder().enabled_validation_features(&enabled_validation_features);
+
+            let flags = if extension_names
+                .iter()
+                .any(|name| name.to_string_lossy() == KHR_PORTABILITY_ENUMERATION)
+            {
+                INSTANCE_CREATE_ENUMERATE_PORTABILITY
+            } else {
+                vk::InstanceCreateFlags::empty()
+            };
+
+            #[cfg(target_os = "macos")]
+            apply_moltenvk_config(&descriptor.moltenvk);
+
             let extension_names_ptrs: Vec<_> = extension_names.iter().map(|name| name.as_ptr()).collect();
 
-            let create_info = vk::InstanceCreateInfo::builder()
+            let mut create_info = vk::InstanceCreateInfo::builder()
+                .flags(flags)
                 .application_info(&app_info)
                 .enabled_extension_names(&extension_names_ptrs)
                 .enabled_layer_names(&layer_names);
 
-            let raw = entry.create_instance(&create_info, None)?;
+            if !enabled_validation_features.is_empty() {
+                create_info = create_info.push_next(&mut validation_features);
+            }
+
+            let host_allocator = if descriptor.track_host_allocations {
+                Some(HostAllocator::new())
+            } else {
+                None
+            };
+            let allocation_callbacks = host_allocator.as_ref().map(|host_allocator| host_allocator.callbacks());
+
+            let raw = entry.create_instance(&create_info, allocation_callbacks)?;
 
-            let surface = khr::Surface::new(entry, &raw);
+            let surface = khr::Surface::new(&entry, &raw);
 
             #[cfg(windows)]
-            let surface_win32 = khr::Win32Surface::new(entry, &raw);
+            let surface_win32 = khr::Win32Surface::new(&entry, &raw);
 
             #[cfg(all(unix, not(target_os = "android"), not(target_os = "macos")))]
-            let surface_xlib = khr::XlibSurface::new(entry, &raw);
+            let surface_xlib = khr::XlibSurface::new(&entry, &raw);
 
             #[cfg(all(unix, not(target_os = "android"), not(target_os = "macos")))]
-            let surface_xcb = khr::XcbSurface::new(entry, &raw);
+            let surface_xcb = khr::XcbSurface::new(&entry, &raw);
 
             #[cfg(all(unix, not(target_os = "android"), not(target_os = "macos")))]
-            let surface_wayland = khr::WaylandSurface::new(entry, &raw);
+            let surface_wayland = khr::WaylandSurface::new(&entry, &raw);
 
             #[cfg(all(unix, target_os = "macos"))]
-            let surface_macos = ash::extensions::mvk::MacOSSurface::new(entry, &raw);
+            let surface_macos = ash::extensions::mvk::MacOSSurface::new(&entry, &raw);
+
+            let get_physical_device_properties2 = if extension_names
+                .iter()
+                .any(|name| name.as_c_str() == vk::KhrGetPhysicalDeviceProperties2Fn::name())
+            {
+                Some(vk::KhrGetPhysicalDeviceProperties2Fn::load(|name| {
+                    mem::transmute(entry.get_instance_proc_addr(raw.handle(), name.as_ptr()))
+                }))
+            } else {
+                None
+            };
 
-            let debug_utils = ext::DebugUtils::new(entry, &raw);
+            let device_group_creation = if extension_names
+                .iter()
+                .any(|name| name.as_c_str() == vk::KhrDeviceGroupCreationFn::name())
+            {
+                Some(vk::KhrDeviceGroupCreationFn::load(|name| {
+                    mem::transmute(entry.get_instance_proc_addr(raw.handle(), name.as_ptr()))
+                }))
+            } else {
+                None
+            };
+
+            let debug_utils = ext::DebugUtils::new(&entry, &raw);
             #[allow(deprecated)]
-            let debug_report = ext::DebugReport::new(entry, &raw);
+            let debug_report = ext::DebugReport::new(&entry, &raw);
+            let mut debug_callback_data = Box::new(debug::DebugCallbackData::new(descriptor));
+            debug_callback_data.instance = raw.handle();
+            let debug_callback_user_data = &*debug_callback_data as *const debug::DebugCallbackData as *mut c_void;
+
             let debug_report_callback = if test_validation_hook {
                 let debug_report_create_info = vk::DebugReportCallbackCreateInfoEXT::builder()
-                    .flags(
-                        vk::DebugReportFlagsEXT::ERROR
-                            | vk::DebugReportFlagsEXT::WARNING
-                            | vk::DebugReportFlagsEXT::PERFORMANCE_WARNING,
-                    )
-                    .user_data(mem::transmute(raw.handle()))
+                    .flags(debug_callback_data.debug_report_flags())
+                    .user_data(debug_callback_user_data)
                     .pfn_callback(Some(debug::debug_report_callback_test));
                 #[allow(deprecated)]
-                Some(debug_report.create_debug_report_callback(&debug_report_create_info, None)?)
+                Some(debug_report.create_debug_report_callback(&debug_report_create_info, allocation_callbacks)?)
+            } else {
+                None
+            };
+
+            // Route the validation messages through the logger so that they can be filtered
+            let has_debug_utils = extension_names
+                .iter()
+                .any(|name| name.as_c_str() == ext::DebugUtils::name());
+            let debug_utils_messenger = if !test_validation_hook && descriptor.enable_validation && has_debug_utils {
+                let debug_utils_messenger_create_info = vk::DebugUtilsMessengerCreateInfoEXT::builder()
+                    .message_severity(debug_callback_data.message_severity())
+                    .message_type(
+                        vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
+                            | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
+                            | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
+                    )
+                    .user_data(debug_callback_user_data)
+                    .pfn_user_callback(Some(debug::debug_utils_messenger_callback));
+                Some(
+                    debug_utils
+                        .create_debug_utils_messenger(&debug_utils_messenger_create_info, allocation_callbacks)?,
+                )
             } else {
                 None
             };
@@ -214,20 +526,36 @@ impl InstanceInner {
                 #[cfg(all(unix, target_os = "macos"))]
                 surface_macos,
 
+                get_physical_device_properties2,
+
+                device_group_creation,
+
                 debug_utils,
                 debug_report,
             };
 
             Ok(InstanceInner {
+                entry,
                 raw,
                 raw_ext,
                 extension_properties,
                 debug_report_callback,
+                debug_utils_messenger,
+                has_debug_utils,
+                debug_callback_data,
+                host_allocator,
                 instance_version,
             })
         }
     }
 
+    /// The callbacks passed as `pAllocator` to every create and destroy call
+    pub fn allocation_callbacks(&self) -> Option<&vk::AllocationCallbacks> {
+        self.host_allocator
+            .as_ref()
+            .map(|host_allocator| host_allocator.callbacks())
+    }
+
     pub fn has_extension(&self, name: &str) -> bool {
         for extension_properties in self.extension_properties.iter() {
             let ext_name = unsafe { CStr::from_ptr(extension_properties.extension_name.as_ptr()) };
@@ -239,6 +567,68 @@ impl InstanceInner {
     }
 }
 
+/// MoltenVK reads its configuration from the environment when the instance is created.
+/// Variables that were already set by the user take precedence.
+#[cfg(target_os = "macos")]
+fn apply_moltenvk_config(config: &MoltenVkConfig) {
+    let settings = [
+        (
+            "MVK_CONFIG_USE_METAL_ARGUMENT_BUFFERS",
+            config.use_metal_argument_buffers,
+        ),
+        ("MVK_CONFIG_SYNCHRONOUS_QUEUE_SUBMITS", config.synchronous_queue_submits),
+        ("MVK_CONFIG_FULL_IMAGE_VIEW_SWIZZLE", config.full_image_view_swizzle),
+    ];
+    for (name, value) in settings.iter() {
+        if let Some(value) = value {
+            if std::env::var_os(name).is_none() {
+                let value = if *value { "1" } else { "0" };
+                log::debug!("setting MoltenVK config: {}={}", name, value);
+                std::env::set_var(name, value);
+            }
+        }
+    }
+}
+
+/// Returns `true` if the instance extension is provided by the layer
+unsafe fn layer_has_extension(entry: &Entry, layer_name: &CStr, extension_name: &CStr) -> Result<bool, Error> {
+    let mut count = 0;
+    let ret =
+        entry
+            .fp_v1_0()
+            .enumerate_instance_extension_properties(layer_name.as_ptr(), &mut count, std::ptr::null_mut());
+    if ret != vk::Result::SUCCESS {
+        return Err(Error::from(ret));
+    }
+    let mut extension_properties = vec![vk::ExtensionProperties::default(); count as usize];
+    let ret = entry.fp_v1_0().enumerate_instance_extension_properties(
+        layer_name.as_ptr(),
+        &mut count,
+        extension_properties.as_mut_ptr(),
+    );
+    if ret != vk::Result::SUCCESS {
+        return Err(Error::from(ret));
+    }
+    Ok(extension_properties
+        .iter()
+        .any(|p| CStr::from_ptr(p.extension_name.as_ptr()) == extension_name))
+}
+
+fn validation_feature_enables(features: ValidationFeatures) -> Vec<vk::ValidationFeatureEnableEXT> {
+    let mut enables = Vec::new();
+    if features.contains(ValidationFeatures::GPU_ASSISTED) {
+        enables.push(vk::ValidationFeatureEnableEXT::GPU_ASSISTED);
+        enables.push(vk::ValidationFeatureEnableEXT::GPU_ASSISTED_RESERVE_BINDING_SLOT);
+    }
+    if features.contains(ValidationFeatures::BEST_PRACTICES) {
+        enables.push(vk::ValidationFeatureEnableEXT::BEST_PRACTICES);
+    }
+    if features.contains(ValidationFeatures::SYNCHRONIZATION) {
+        enables.push(vk::ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION);
+    }
+    enables
+}
+
 impl Into<Instance> for InstanceInner {
     fn into(self) -> Instance {
         Instance { inner: Arc::new(self) }
@@ -258,9 +648,14 @@ impl Drop for InstanceInner {
             if let Some(debug_report_callback) = self.debug_report_callback {
                 self.raw_ext
                     .debug_report
-                    .destroy_debug_report_callback(debug_report_callback, None);
+                    .destroy_debug_report_callback(debug_report_callback, self.allocation_callbacks());
+            }
+            if let Some(debug_utils_messenger) = self.debug_utils_messenger {
+                self.raw_ext
+                    .debug_utils
+                    .destroy_debug_utils_messenger(debug_utils_messenger, self.allocation_callbacks());
             }
-            self.raw.destroy_instance(None);
+            self.raw.destroy_instance(self.allocation_callbacks());
         }
     }
 }
@@ -4,7 +4,7 @@ use ash::vk::{self, Handle};
 use parking_lot::Mutex;
 use vk_mem::{Allocation, AllocationInfo, Allocator};
 
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
 
 mod adapter;
 mod binding;
@@ -12,35 +12,49 @@ mod buffer;
 mod command;
 mod command_buffer;
 mod command_encoder;
+mod compute_blit;
 mod debug;
 mod device;
 mod fence;
 mod fenced_deleter;
+mod frame;
+mod host_allocator;
 mod instance;
 mod pass_resource_usage;
 mod pipeline;
+mod query_set;
 mod queue;
 mod render_pass;
 mod sampler;
 mod serial;
 mod shader;
+mod sparse;
+mod spirv;
 mod surface;
 mod swapchain;
 mod texture;
 mod util;
 mod vec;
 
+pub use crate::imp::adapter::{
+    AdapterInfo, AdapterProperties, DriverProperties, MemoryHeap, MemoryProperties, MemoryType, PciBusInfo,
+    PortabilityFeatures,
+};
 pub use crate::imp::debug::validate;
+pub use crate::imp::texture::{block_dimensions, pixel_size, texture_format};
 
 use crate::{
-    BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, BufferDescriptor, BufferUsage, Extensions, IndexFormat,
-    Limits, SamplerDescriptor, TextureDescriptor, TextureViewDescriptor,
+    BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry, Buffer, BufferDescriptor, BufferUsage, Color,
+    CompilationInfo, Extensions, Extent3d, IndexFormat, Limits, QuerySetDescriptor, RenderPipelineDescriptor,
+    SamplerDescriptor, SubgroupProperties, SubmissionIndex, SurfaceDescriptor, TextureDescriptor, TextureFormat,
+    TextureViewDescriptor,
 };
 
 use std::collections::HashMap;
+use std::ffi::CString;
 use std::hash::{Hash, Hasher};
 use std::mem::ManuallyDrop;
-use std::sync::atomic::AtomicPtr;
+use std::sync::atomic::{AtomicBool, AtomicPtr};
 
 macro_rules! handle_traits {
     ($Name:ident) => {
@@ -73,10 +87,19 @@ macro_rules! handle_traits {
 }
 
 pub struct InstanceInner {
+    /// Keeps the Vulkan library loaded for the lifetime of the instance
+    entry: instance::Entry,
     raw: ash::Instance,
     raw_ext: InstanceExt,
     extension_properties: Vec<vk::ExtensionProperties>,
     debug_report_callback: Option<vk::DebugReportCallbackEXT>,
+    debug_utils_messenger: Option<vk::DebugUtilsMessengerEXT>,
+    /// `VK_EXT_debug_utils` is enabled and objects can be named
+    has_debug_utils: bool,
+    /// Referenced by the debug callbacks; must outlive them
+    debug_callback_data: Box<debug::DebugCallbackData>,
+    /// Referenced by the allocation callbacks; must outlive the instance and its devices
+    host_allocator: Option<Box<host_allocator::HostAllocator>>,
     instance_version: (u32, u32, u32),
 }
 
@@ -113,6 +136,12 @@ struct InstanceExt {
     #[cfg(all(unix, target_os = "macos"))]
     surface_macos: ash::extensions::mvk::MacOSSurface,
 
+    /// `VK_KHR_get_physical_device_properties2`, if available
+    get_physical_device_properties2: Option<vk::KhrGetPhysicalDeviceProperties2Fn>,
+
+    /// `VK_KHR_device_group_creation`, if available
+    device_group_creation: Option<vk::KhrDeviceGroupCreationFn>,
+
     debug_utils: ext::DebugUtils,
 
     #[allow(deprecated)]
@@ -126,7 +155,13 @@ pub struct AdapterInner {
     physical_device_features: vk::PhysicalDeviceFeatures,
     physical_device_properties: vk::PhysicalDeviceProperties,
     physical_device_format_properties: Vec<(vk::Format, vk::FormatProperties)>,
+    physical_device_memory_properties: vk::PhysicalDeviceMemoryProperties,
     queue_family_properties: Vec<vk::QueueFamilyProperties>,
+    device_extension_names: Vec<CString>,
+    subgroup_properties: Option<SubgroupProperties>,
+    driver_properties: Option<DriverProperties>,
+    info: AdapterInfo,
+    portability_features: Option<PortabilityFeatures>,
     name: String,
     extensions: Extensions,
 }
@@ -153,8 +188,16 @@ pub struct DeviceInner {
     extensions: Extensions,
     limits: Limits,
     queue: QueueInfo,
+    /// The number of physical devices in the device group (`1` if the device was not created
+    /// from an `AdapterGroup`)
+    physical_device_count: u32,
     state: Mutex<device::DeviceState>,
     command_encoder_pool: Mutex<Vec<command_encoder::CommandEncoderState>>,
+    /// Render pipelines keyed by their descriptor. Entries are removed when the pipeline is dropped.
+    render_pipeline_cache: Mutex<HashMap<RenderPipelineDescriptor, Weak<RenderPipelineInner>, ahash::RandomState>>,
+    /// Writes made with `Queue::write_buffer` and `Queue::write_texture` that are uploaded before
+    /// the next submission
+    pending_writes: Mutex<queue::PendingWrites>,
     allocator: ManuallyDrop<Allocator>,
 }
 
@@ -180,6 +223,8 @@ pub struct QueueInner {
 /// Device extension functions
 struct DeviceExt {
     swapchain: khr::Swapchain,
+    timeline_semaphore: vk::KhrTimelineSemaphoreFn,
+    transform_feedback: vk::ExtTransformFeedbackFn,
 }
 
 // Note: Do not make this cloneable
@@ -191,6 +236,11 @@ pub struct SwapchainInner {
     //images: Vec<vk::Image>,
     textures: Vec<Arc<TextureInner>>,
     views: Vec<Arc<TextureViewInner>>,
+    /// Views of the images for each of `SwapchainDescriptor::view_formats`
+    format_views: Vec<(TextureFormat, Vec<Arc<TextureViewInner>>)>,
+    multisampled_view: Option<Arc<TextureViewInner>>,
+    depth_view: Option<Arc<TextureViewInner>>,
+    sample_count: u32,
 }
 
 handle_traits!(SwapchainInner);
@@ -199,6 +249,7 @@ handle_traits!(SwapchainInner);
 pub struct SurfaceInner {
     handle: vk::SurfaceKHR,
     instance: Arc<InstanceInner>,
+    descriptor: SurfaceDescriptor,
     supported_formats: Mutex<HashMap<vk::PhysicalDevice, Vec<vk::SurfaceFormatKHR>, ahash::RandomState>>,
 }
 
@@ -217,13 +268,38 @@ pub struct TextureInner {
     device: Arc<DeviceInner>,
     descriptor: TextureDescriptor,
     subresource_usage: Mutex<texture::SubresourceUsageTracker>,
-    // if the allocation is None, the image is owned by the swapchain
+    // if the allocation is None, the image is owned by the swapchain (or is sparse)
     allocation: Option<Allocation>,
     allocation_info: Option<AllocationInfo>,
+    sparse: Option<SparseTextureInfo>,
 }
 
 handle_traits!(TextureInner);
 
+#[derive(Debug)]
+pub struct SparseTextureInfo {
+    tile_size: Extent3d,
+    mip_tail_first_level: u32,
+    memory_requirements: vk::MemoryRequirements,
+    // the memory bound to the mip tail, if the texture has one
+    mip_tail_allocation: Option<Allocation>,
+    // keeps the page pools alive while their pages may be bound to the texture
+    page_pools: Mutex<Vec<Arc<SparsePagePoolInner>>>,
+}
+
+#[derive(Debug)]
+pub struct SparsePagePoolInner {
+    handle: vk::DeviceMemory,
+    device: Arc<DeviceInner>,
+    allocation: Allocation,
+    memory_offset: vk::DeviceSize,
+    memory_type_index: u32,
+    page_size: vk::DeviceSize,
+    page_count: u32,
+}
+
+handle_traits!(SparsePagePoolInner);
+
 #[derive(Debug)]
 pub struct TextureViewInner {
     handle: vk::ImageView,
@@ -246,7 +322,7 @@ pub struct BufferInner {
     descriptor: BufferDescriptor,
     allocation: Allocation,
     allocation_info: AllocationInfo,
-    last_usage: Mutex<BufferUsage>,
+    range_usage: Mutex<buffer::RangeUsageTracker>,
     buffer_state: Mutex<BufferState>,
 }
 
@@ -269,6 +345,15 @@ pub struct SamplerInner {
 
 handle_traits!(SamplerInner);
 
+#[derive(Debug)]
+pub struct QuerySetInner {
+    handle: vk::QueryPool,
+    device: Arc<DeviceInner>,
+    descriptor: QuerySetDescriptor,
+}
+
+handle_traits!(QuerySetInner);
+
 #[derive(Debug)]
 pub struct BindGroupLayoutInner {
     handle: vk::DescriptorSetLayout,
@@ -293,6 +378,7 @@ handle_traits!(BindGroupInner);
 pub struct ShaderModuleInner {
     handle: vk::ShaderModule,
     device: Arc<DeviceInner>,
+    reflection: Option<spirv::ShaderReflection>,
 }
 
 handle_traits!(ShaderModuleInner);
@@ -310,6 +396,7 @@ handle_traits!(PipelineLayoutInner);
 pub struct ComputePipelineInner {
     handle: vk::Pipeline,
     layout: Arc<PipelineLayoutInner>,
+    compilation_info: Option<CompilationInfo>,
 }
 
 handle_traits!(ComputePipelineInner);
@@ -319,6 +406,14 @@ pub struct RenderPipelineInner {
     handle: vk::Pipeline,
     layout: Arc<PipelineLayoutInner>,
     index_format: IndexFormat,
+    blend_color: Option<Color>,
+    // (constant, clamp, slope)
+    depth_bias: (f32, f32, f32),
+    // (read, write)
+    stencil_masks: (u32, u32),
+    compilation_info: Option<CompilationInfo>,
+    // The key of the pipeline in the device's render pipeline cache
+    cache_key: Option<RenderPipelineDescriptor>,
 }
 
 handle_traits!(RenderPipelineInner);
@@ -327,18 +422,26 @@ handle_traits!(RenderPipelineInner);
 pub struct CommandEncoderInner {
     state: command_encoder::CommandEncoderState,
     device: Arc<DeviceInner>,
+    label: Option<CString>,
+    one_time_submit: bool,
 }
 
 #[derive(Debug)]
 pub struct CommandBufferInner {
     state: command_encoder::CommandEncoderState,
     device: Arc<DeviceInner>,
+    label: Option<CString>,
+    one_time_submit: bool,
+    submitted: AtomicBool,
+    recording: Mutex<Option<command_buffer::Recording>>,
 }
 
 #[derive(Debug)]
 pub struct ComputePassEncoderInner<'a> {
     top_level_encoder: &'a mut CommandEncoderInner,
     usage_tracker: pass_resource_usage::PassResourceUsageTracker,
+    end_of_pass_timestamp: Option<(Arc<QuerySetInner>, u32)>,
+    has_label: bool,
 }
 
 #[derive(Debug)]
@@ -347,9 +450,17 @@ pub struct RenderPassEncoderInner<'a> {
     usage_tracker: pass_resource_usage::PassResourceUsageTracker,
 }
 
+#[derive(Debug)]
+pub struct FrameResources {
+    /// The most recent submission that used the frame's resources
+    submission: Option<SubmissionIndex>,
+    staging_buffer: Option<Buffer>,
+}
+
 #[derive(Debug)]
 pub struct FenceInner {
     //    handle: vk::Fence,
     device: Arc<DeviceInner>,
     serial: Mutex<serial::Serial>,
+    timeline: Option<vk::Semaphore>,
 }
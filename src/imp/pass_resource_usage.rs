@@ -1,21 +1,25 @@
+use crate::imp::buffer::{block_range, RangeUsageTracker};
+use crate::imp::texture::SubresourceUsageTracker;
 use crate::imp::{BufferInner, TextureInner};
 use crate::{BufferUsage, Error, TextureUsage};
 
 use ash::vk;
+use smallvec::SmallVec;
+use std::ops::Range;
 use std::sync::Arc;
 
 use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Default, Clone)]
 pub struct PassResourceUsage {
-    pub buffers: Vec<(Arc<BufferInner>, BufferUsage)>,
+    pub buffers: Vec<(Arc<BufferInner>, Range<u64>, BufferUsage)>,
     pub textures: Vec<(Arc<TextureInner>, TextureUsage)>,
 }
 
 impl PassResourceUsage {
     pub fn transition_for_pass(&self, command_buffer: vk::CommandBuffer) -> Result<(), Error> {
-        for (buffer, usage) in self.buffers.iter() {
-            buffer.transition_usage_now(command_buffer, *usage)?;
+        for (buffer, range, usage) in self.buffers.iter() {
+            buffer.transition_range_usage_now(command_buffer, *usage, range.start, range.end - range.start)?;
         }
         for (texture, usage) in self.textures.iter() {
             texture.transition_usage_now(command_buffer, *usage, None)?;
@@ -37,6 +41,56 @@ impl CommandBufferResourceUsage {
         self.top_level_buffers.clear();
         self.top_level_textures.clear();
     }
+
+    /// Captures the current usage of every resource used by the command buffer
+    pub fn snapshot(&self) -> ResourceUsageSnapshot {
+        let mut buffers: HashSet<&Arc<BufferInner>, ahash::RandomState> = self.top_level_buffers.iter().collect();
+        let mut textures: HashSet<&Arc<TextureInner>, ahash::RandomState> = self.top_level_textures.iter().collect();
+        for pass in self.per_pass.iter() {
+            buffers.extend(pass.buffers.iter().map(|(buffer, _, _)| buffer));
+            textures.extend(pass.textures.iter().map(|(texture, _)| texture));
+        }
+        ResourceUsageSnapshot {
+            buffers: buffers
+                .into_iter()
+                .map(|buffer| (Arc::clone(buffer), buffer.range_usage.lock().clone()))
+                .collect(),
+            textures: textures
+                .into_iter()
+                .map(|texture| (Arc::clone(texture), texture.subresource_usage.lock().clone()))
+                .collect(),
+        }
+    }
+}
+
+/// The tracked usage of a set of resources at a point in time
+#[derive(Debug, Default)]
+pub struct ResourceUsageSnapshot {
+    buffers: Vec<(Arc<BufferInner>, RangeUsageTracker)>,
+    textures: Vec<(Arc<TextureInner>, SubresourceUsageTracker)>,
+}
+
+impl ResourceUsageSnapshot {
+    /// Returns `true` if every resource is still used as it was when the snapshot was captured
+    pub fn is_current(&self) -> bool {
+        self.buffers
+            .iter()
+            .all(|(buffer, range_usage)| *buffer.range_usage.lock() == *range_usage)
+            && self
+                .textures
+                .iter()
+                .all(|(texture, subresource_usage)| *texture.subresource_usage.lock() == *subresource_usage)
+    }
+
+    /// Sets the usage of every resource to the usage captured by the snapshot
+    pub fn restore(&self) {
+        for (buffer, range_usage) in self.buffers.iter() {
+            *buffer.range_usage.lock() = range_usage.clone();
+        }
+        for (texture, subresource_usage) in self.textures.iter() {
+            *texture.subresource_usage.lock() = subresource_usage.clone();
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -47,18 +101,44 @@ pub enum PassType {
 
 #[derive(Debug, Default)]
 pub struct PassResourceUsageTracker {
-    buffer_usages: HashMap<Arc<BufferInner>, BufferUsage, ahash::RandomState>,
+    buffer_usages: HashMap<Arc<BufferInner>, SmallVec<[(Range<u64>, BufferUsage); 2]>, ahash::RandomState>,
     texture_usages: HashMap<Arc<TextureInner>, TextureUsage, ahash::RandomState>,
     storage_used_multiple_times: bool,
 }
 
 impl PassResourceUsageTracker {
     pub fn buffer_used_as(&mut self, buffer: Arc<BufferInner>, usage: BufferUsage) {
-        let existing_usage = self.buffer_usages.entry(buffer.clone()).or_insert(BufferUsage::NONE);
-        if usage == BufferUsage::STORAGE && existing_usage.intersects(BufferUsage::STORAGE) {
-            self.storage_used_multiple_times = true;
+        self.buffer_range_used_as(buffer, 0, vk::WHOLE_SIZE, usage);
+    }
+
+    /// Records `usage` for the bytes in `offset..offset + size` of the buffer. A `size` of
+    /// `vk::WHOLE_SIZE` records the remainder of the buffer. Overlapping ranges are merged and
+    /// their usages combined, while disjoint ranges are transitioned independently.
+    pub fn buffer_range_used_as(&mut self, buffer: Arc<BufferInner>, offset: u64, size: u64, usage: BufferUsage) {
+        // Ranges are widened to the blocks used by the buffer's usage tracker, so that usages
+        // that share a block are always transitioned together
+        let mut range = block_range(offset, size, buffer.descriptor.size as u64);
+        let storage = usage == BufferUsage::STORAGE;
+        let mut usage = usage;
+
+        let existing_ranges = self.buffer_usages.entry(buffer).or_insert_with(SmallVec::new);
+        let mut index = 0;
+        while index < existing_ranges.len() {
+            let (existing_range, existing_usage) = &existing_ranges[index];
+            if existing_range.start < range.end && existing_range.end > range.start {
+                if storage && existing_usage.intersects(BufferUsage::STORAGE) {
+                    self.storage_used_multiple_times = true;
+                }
+                range = range.start.min(existing_range.start)..range.end.max(existing_range.end);
+                usage.insert(*existing_usage);
+                existing_ranges.swap_remove(index);
+                // The merged range may now overlap ranges that were already visited
+                index = 0;
+            } else {
+                index += 1;
+            }
         }
-        existing_usage.insert(usage);
+        existing_ranges.push((range, usage));
     }
 
     pub fn texture_used_as(&mut self, texture: Arc<TextureInner>, usage: TextureUsage) {
@@ -86,8 +166,10 @@ impl PassResourceUsageTracker {
         let mut result = PassResourceUsage::default();
         result.buffers.reserve(self.buffer_usages.len());
         result.textures.reserve(self.texture_usages.len());
-        for (buffer, usage) in self.buffer_usages.drain() {
-            result.buffers.push((buffer, usage));
+        for (buffer, ranges) in self.buffer_usages.drain() {
+            for (range, usage) in ranges {
+                result.buffers.push((Arc::clone(&buffer), range, usage));
+            }
         }
         for (texture, usage) in self.texture_usages.drain() {
             result.textures.push((texture, usage));
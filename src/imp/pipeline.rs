@@ -1,25 +1,230 @@
 use ash::vk;
 
+use smallvec::SmallVec;
+
 use std::convert::TryFrom;
 use std::ffi::CString;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
 
 use crate::imp::fenced_deleter::DeleteWhenUnused;
 use crate::imp::render_pass::{self, ColorInfo, DepthStencilInfo, RenderPassCacheQuery};
-use crate::imp::{binding, sampler};
+use crate::imp::{binding, sampler, texture};
 use crate::imp::{ComputePipelineInner, DeviceInner, PipelineLayoutInner, RenderPipelineInner};
 use crate::{
-    BlendFactor, BlendOperation, ColorStateDescriptor, ColorWrite, CompareFunction, ComputePipeline,
-    ComputePipelineDescriptor, CullMode, DepthStencilStateDescriptor, Error, FrontFace, InputStepMode, LoadOp,
-    PipelineLayout, PipelineLayoutDescriptor, PolygonMode, PrimitiveTopology, RasterizationStateDescriptor,
-    RenderPipeline, RenderPipelineDescriptor, StencilOperation, StencilStateFaceDescriptor, TextureFormat,
-    VertexAttributeDescriptor, VertexBufferLayoutDescriptor, VertexFormat,
+    BindingType, BlendFactor, BlendOperation, ColorStateDescriptor, ColorWrite, CompareFunction, CompilationInfo,
+    ComputePipeline, ComputePipelineDescriptor, CullMode, DepthStencilStateDescriptor, Error, FrontFace, IndexFormat,
+    InputStepMode, LoadOp, PipelineLayout, PipelineLayoutDescriptor, PipelineStageDescriptor, PolygonMode,
+    PrimitiveTopology, RasterizationStateDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderStage,
+    StencilOperation, StencilStateFaceDescriptor, TextureFormat, VertexAttributeDescriptor, VertexAttributeFormat,
+    VertexBufferLayoutDescriptor, VertexFormat,
 };
 
-pub const MAX_PUSH_CONSTANTS_SIZE: usize = 128;
+fn validate_pipeline_layout_limits(device: &DeviceInner, descriptor: &PipelineLayoutDescriptor) -> Result<(), Error> {
+    let limits = &device.limits;
+
+    if descriptor.bind_group_layouts.len() > limits.max_bind_groups as usize {
+        log::error!(
+            "pipeline layout exceeds max_bind_groups: {} > {}",
+            descriptor.bind_group_layouts.len(),
+            limits.max_bind_groups
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    let entries = || {
+        descriptor
+            .bind_group_layouts
+            .iter()
+            .flat_map(|bind_group_layout| bind_group_layout.inner.layout_bindings.iter())
+    };
+
+    let count = |binding_types: &[BindingType], stage: Option<ShaderStage>| {
+        entries()
+            .filter(|entry| binding_types.contains(&entry.binding_type))
+            .filter(|entry| stage.map_or(true, |stage| entry.visibility.contains(stage)))
+            .count() as u32
+    };
+
+    let per_pipeline_layout = [
+        (
+            "max_dynamic_uniform_buffers_per_pipeline_layout",
+            count(&[BindingType::DynamicUniformBuffer], None),
+            limits.max_dynamic_uniform_buffers_per_pipeline_layout,
+        ),
+        (
+            "max_dynamic_storage_buffers_per_pipeline_layout",
+            count(&[BindingType::DynamicStorageBuffer], None),
+            limits.max_dynamic_storage_buffers_per_pipeline_layout,
+        ),
+    ];
+
+    for &(name, count, limit) in per_pipeline_layout.iter() {
+        if count > limit {
+            log::error!("pipeline layout exceeds {}: {} > {}", name, count, limit);
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+    }
+
+    for &stage in [ShaderStage::VERTEX, ShaderStage::FRAGMENT, ShaderStage::COMPUTE].iter() {
+        let per_stage = [
+            (
+                "max_samplers_per_shader_stage",
+                count(&[BindingType::Sampler, BindingType::ComparisonSampler], Some(stage)),
+                limits.max_samplers_per_shader_stage,
+            ),
+            (
+                "max_sampled_textures_per_shader_stage",
+                count(&[BindingType::SampledTexture], Some(stage)),
+                limits.max_sampled_textures_per_shader_stage,
+            ),
+            (
+                "max_storage_textures_per_shader_stage",
+                count(
+                    &[
+                        BindingType::ReadOnlyStorageTexture,
+                        BindingType::WriteOnlyStorageTexture,
+                        BindingType::StorageTexelBuffer,
+                    ],
+                    Some(stage),
+                ),
+                limits.max_storage_textures_per_shader_stage,
+            ),
+            (
+                "max_uniform_buffers_per_shader_stage",
+                count(
+                    &[BindingType::UniformBuffer, BindingType::DynamicUniformBuffer],
+                    Some(stage),
+                ),
+                limits.max_uniform_buffers_per_shader_stage,
+            ),
+            (
+                "max_storage_buffers_per_shader_stage",
+                count(
+                    &[BindingType::StorageBuffer, BindingType::DynamicStorageBuffer],
+                    Some(stage),
+                ),
+                limits.max_storage_buffers_per_shader_stage,
+            ),
+        ];
+
+        for &(name, count, limit) in per_stage.iter() {
+            if count > limit {
+                log::error!("pipeline layout exceeds {} ({:?}): {} > {}", name, stage, count, limit);
+                return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_push_constant_ranges(device: &DeviceInner, descriptor: &PipelineLayoutDescriptor) -> Result<(), Error> {
+    let max_push_constants_size = device.limits.max_push_constants_size as usize;
+    for range in descriptor.push_constant_ranges.iter() {
+        if range.offset % 4 != 0 || range.size % 4 != 0 || range.size == 0 {
+            log::error!(
+                "push constant range offset and size must be multiples of 4 and size must not be zero: {:?}",
+                range
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+        if range.offset + range.size > max_push_constants_size {
+            log::error!(
+                "push constant range exceeds max_push_constants_size ({} bytes): {:?}",
+                max_push_constants_size,
+                range
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+    }
+    Ok(())
+}
+
+/// Validates that every binding statically used by the shader stage is declared in the pipeline
+/// layout with a compatible binding type and a visibility that includes the stage
+fn validate_shader_bindings(
+    stage: ShaderStage,
+    descriptor: &PipelineStageDescriptor,
+    layout: &PipelineLayoutInner,
+) -> Result<(), Error> {
+    let reflection = match descriptor.module.inner.reflection.as_ref() {
+        Some(reflection) => reflection,
+        None => return Ok(()),
+    };
+
+    let entry_point = &*descriptor.entry_point;
+    let shader_bindings = match reflection.bindings(entry_point, stage) {
+        Some(shader_bindings) => shader_bindings,
+        None => {
+            log::error!("shader module has no {:?} entry point named `{}`", stage, entry_point);
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+    };
+
+    for shader_binding in shader_bindings {
+        let layout_binding = layout
+            .bind_group_layouts
+            .get(shader_binding.set as usize)
+            .and_then(|bind_group_layout| {
+                bind_group_layout
+                    .inner
+                    .layout_bindings
+                    .iter()
+                    .find(|layout_binding| layout_binding.binding == shader_binding.binding)
+            });
+
+        let layout_binding = match layout_binding {
+            Some(layout_binding) => layout_binding,
+            None => {
+                log::error!(
+                    "{:?} entry point `{}` uses binding {} of bind group {}, which is missing from the pipeline layout",
+                    stage,
+                    entry_point,
+                    shader_binding.binding,
+                    shader_binding.set
+                );
+                return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+            }
+        };
+
+        if !shader_binding
+            .descriptor_type
+            .is_compatible(layout_binding.binding_type)
+        {
+            log::error!(
+                "{:?} entry point `{}` uses binding {} of bind group {} as {:?}, but the layout declares {:?}",
+                stage,
+                entry_point,
+                shader_binding.binding,
+                shader_binding.set,
+                shader_binding.descriptor_type,
+                layout_binding.binding_type
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        if !layout_binding.visibility.contains(stage) {
+            log::error!(
+                "{:?} entry point `{}` uses binding {} of bind group {}, but its visibility is {:?}",
+                stage,
+                entry_point,
+                shader_binding.binding,
+                shader_binding.set,
+                layout_binding.visibility
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+    }
+
+    Ok(())
+}
 
 impl PipelineLayoutInner {
     pub fn new(device: Arc<DeviceInner>, descriptor: PipelineLayoutDescriptor) -> Result<PipelineLayoutInner, Error> {
+        validate_pipeline_layout_limits(&device, &descriptor)?;
+        validate_push_constant_ranges(&device, &descriptor)?;
+
         let push_constant_ranges: Vec<_> = descriptor
             .push_constant_ranges
             .iter()
@@ -41,7 +246,11 @@ impl PipelineLayoutInner {
             .set_layouts(&descriptor_set_layouts)
             .build();
 
-        let handle = unsafe { device.raw.create_pipeline_layout(&create_info, None)? };
+        let handle = unsafe {
+            device
+                .raw
+                .create_pipeline_layout(&create_info, device.allocation_callbacks())?
+        };
 
         Ok(PipelineLayoutInner {
             handle,
@@ -69,22 +278,47 @@ impl ComputePipelineInner {
     pub fn new(device: Arc<DeviceInner>, descriptor: ComputePipelineDescriptor) -> Result<ComputePipelineInner, Error> {
         // TODO: inspect push constants
 
+        validate_shader_bindings(
+            ShaderStage::COMPUTE,
+            &descriptor.compute_stage,
+            &descriptor.layout.inner,
+        )?;
+
         let entry_point = CString::new(&*descriptor.compute_stage.entry_point).map_err(|e| {
             log::error!("invalid entry point: {:?}", e);
             vk::Result::ERROR_VALIDATION_FAILED_EXT
         })?;
 
-        let create_info = vk::ComputePipelineCreateInfo {
-            layout: descriptor.layout.inner.handle,
-            base_pipeline_handle: vk::Pipeline::null(),
-            base_pipeline_index: -1,
-            stage: vk::PipelineShaderStageCreateInfo::builder()
-                .name(entry_point.as_c_str())
-                .stage(vk::ShaderStageFlags::COMPUTE)
-                .module(descriptor.compute_stage.module.inner.handle)
-                .build(),
-            ..Default::default()
-        };
+        let mut required_subgroup_size_info = vk::PipelineShaderStageRequiredSubgroupSizeCreateInfoEXT::default();
+
+        let mut stage = vk::PipelineShaderStageCreateInfo::builder()
+            .name(entry_point.as_c_str())
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(descriptor.compute_stage.module.inner.handle);
+
+        if let Some(required_subgroup_size) = descriptor.required_subgroup_size {
+            validate_required_subgroup_size(&device, required_subgroup_size)?;
+            required_subgroup_size_info.required_subgroup_size = required_subgroup_size;
+            stage = stage.push_next(&mut required_subgroup_size_info);
+        }
+
+        let mut feedback = vk::PipelineCreationFeedbackEXT::default();
+        let mut stage_feedbacks = [vk::PipelineCreationFeedbackEXT::default()];
+        let mut feedback_create_info = vk::PipelineCreationFeedbackCreateInfoEXT::builder()
+            .pipeline_creation_feedback(&mut feedback)
+            .pipeline_stage_creation_feedbacks(&mut stage_feedbacks);
+
+        let mut create_info = vk::ComputePipelineCreateInfo::builder()
+            .layout(descriptor.layout.inner.handle)
+            .base_pipeline_handle(vk::Pipeline::null())
+            .base_pipeline_index(-1)
+            .stage(stage.build());
+
+        if device.extensions.pipeline_creation_feedback {
+            create_info = create_info.push_next(&mut feedback_create_info);
+        }
+
+        let create_info = create_info.build();
 
         let pipeline_cache = vk::PipelineCache::null();
         let mut handle = vk::Pipeline::null();
@@ -95,7 +329,7 @@ impl ComputePipelineInner {
                 pipeline_cache,
                 1,
                 &create_info,
-                std::ptr::null(),
+                device.allocation_callbacks_ptr(),
                 &mut handle,
             );
             if ret != vk::Result::SUCCESS {
@@ -105,7 +339,68 @@ impl ComputePipelineInner {
 
         let layout = descriptor.layout.inner.clone();
 
-        Ok(ComputePipelineInner { handle, layout })
+        let compilation_info = if device.extensions.pipeline_creation_feedback {
+            Some(compilation_info(&feedback))
+        } else {
+            None
+        };
+
+        Ok(ComputePipelineInner {
+            handle,
+            layout,
+            compilation_info,
+        })
+    }
+}
+
+fn compilation_info(feedback: &vk::PipelineCreationFeedbackEXT) -> CompilationInfo {
+    let valid = feedback.flags.contains(vk::PipelineCreationFeedbackFlagsEXT::VALID);
+    CompilationInfo {
+        duration: if valid {
+            Some(Duration::from_nanos(feedback.duration))
+        } else {
+            None
+        },
+        cache_hit: valid
+            && feedback
+                .flags
+                .contains(vk::PipelineCreationFeedbackFlagsEXT::APPLICATION_PIPELINE_CACHE_HIT),
+    }
+}
+
+fn validate_required_subgroup_size(device: &DeviceInner, required_subgroup_size: u32) -> Result<(), Error> {
+    if !device.extensions.subgroup_size_control {
+        log::error!("required_subgroup_size requires Extensions::subgroup_size_control");
+        return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+    }
+    let properties = device
+        .adapter
+        .subgroup_properties
+        .expect("subgroup_size_control is enabled without subgroup properties");
+    if !properties.required_subgroup_size_stages.contains(ShaderStage::COMPUTE) {
+        log::error!("required_subgroup_size is not supported for compute shaders");
+        return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+    }
+    if !required_subgroup_size.is_power_of_two()
+        || required_subgroup_size < properties.min_subgroup_size
+        || required_subgroup_size > properties.max_subgroup_size
+    {
+        log::error!(
+            "required_subgroup_size must be a power of two between {} and {}: {}",
+            properties.min_subgroup_size,
+            properties.max_subgroup_size,
+            required_subgroup_size
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+    Ok(())
+}
+
+impl ComputePipeline {
+    /// Returns the creation feedback of the pipeline, or `None` if
+    /// `Extensions::pipeline_creation_feedback` was not enabled.
+    pub fn compilation_info(&self) -> Option<CompilationInfo> {
+        self.inner.compilation_info
     }
 }
 
@@ -293,7 +588,9 @@ pub fn rasterization_state_create_info(
         polygon_mode: polygon_mode(descriptor.polygon_mode),
         cull_mode: cull_mode(descriptor.cull_mode),
         front_face: front_face(descriptor.front_face),
-        depth_bias_enable: (descriptor.depth_bias != 0) as vk::Bool32,
+        // The bias is dynamic state and is applied when the pipeline is bound, which allows
+        // RenderPassEncoder::set_depth_bias to override it
+        depth_bias_enable: vk::TRUE,
         depth_bias_clamp: descriptor.depth_bias_clamp,
         depth_bias_slope_factor: descriptor.depth_bias_slope_scale,
         depth_bias_constant_factor: descriptor.depth_bias as f32,
@@ -326,7 +623,8 @@ pub fn front_face(face: FrontFace) -> vk::FrontFace {
 
 pub fn color_blend_attachment_state(descriptor: &ColorStateDescriptor) -> vk::PipelineColorBlendAttachmentState {
     vk::PipelineColorBlendAttachmentState {
-        blend_enable: blend_enabled(&descriptor) as vk::Bool32,
+        // Integer attachments do not support blending
+        blend_enable: (blend_enabled(&descriptor) && !texture::is_integer(descriptor.format)) as vk::Bool32,
         src_color_blend_factor: blend_factor(descriptor.color_blend.src_factor),
         dst_color_blend_factor: blend_factor(descriptor.color_blend.dst_factor),
         color_blend_op: blend_operation(descriptor.color_blend.operation),
@@ -337,6 +635,42 @@ pub fn color_blend_attachment_state(descriptor: &ColorStateDescriptor) -> vk::Pi
     }
 }
 
+macro_rules! impl_vertex_attribute_format {
+    ($($ty:ty => $format:ident),* $(,)?) => {
+        $(
+            impl VertexAttributeFormat for $ty {
+                const FORMAT: VertexFormat = VertexFormat::$format;
+            }
+        )*
+    };
+}
+
+impl_vertex_attribute_format! {
+    [u8; 2] => UChar2,
+    [u8; 4] => UChar4,
+    [i8; 2] => Char2,
+    [i8; 4] => Char4,
+    [u16; 2] => UShort2,
+    [u16; 4] => UShort4,
+    [i16; 2] => Short2,
+    [i16; 4] => Short4,
+    f32 => Float,
+    [f32; 1] => Float,
+    [f32; 2] => Float2,
+    [f32; 3] => Float3,
+    [f32; 4] => Float4,
+    u32 => UInt,
+    [u32; 1] => UInt,
+    [u32; 2] => UInt2,
+    [u32; 3] => UInt3,
+    [u32; 4] => UInt4,
+    i32 => Int,
+    [i32; 1] => Int,
+    [i32; 2] => Int2,
+    [i32; 3] => Int3,
+    [i32; 4] => Int4,
+}
+
 pub fn vertex_format(format: VertexFormat) -> vk::Format {
     match format {
         VertexFormat::UChar2 => vk::Format::R8G8_UINT,
@@ -356,6 +690,13 @@ pub fn vertex_format(format: VertexFormat) -> vk::Format {
         VertexFormat::UShort4Norm => vk::Format::R16G16B16A16_UNORM,
         VertexFormat::Short2Norm => vk::Format::R16G16_SNORM,
         VertexFormat::Short4Norm => vk::Format::R16G16B16A16_SNORM,
+        VertexFormat::UShortNorm => vk::Format::R16_UNORM,
+        VertexFormat::UShort3Norm => vk::Format::R16G16B16_UNORM,
+        VertexFormat::ShortNorm => vk::Format::R16_SNORM,
+        VertexFormat::Short3Norm => vk::Format::R16G16B16_SNORM,
+
+        VertexFormat::UInt1010102Norm => vk::Format::A2B10G10R10_UNORM_PACK32,
+        VertexFormat::Int1010102Norm => vk::Format::A2B10G10R10_SNORM_PACK32,
 
         VertexFormat::Half2 => vk::Format::R16G16_SFLOAT,
         VertexFormat::Half4 => vk::Format::R16G16B16A16_SFLOAT,
@@ -375,6 +716,13 @@ pub fn vertex_format(format: VertexFormat) -> vk::Format {
     }
 }
 
+fn uses_blend_color(factor: BlendFactor) -> bool {
+    match factor {
+        BlendFactor::BlendColor | BlendFactor::OneMinusBlendColor => true,
+        _ => false,
+    }
+}
+
 pub fn input_rate(mode: InputStepMode) -> vk::VertexInputRate {
     match mode {
         InputStepMode::Instance => vk::VertexInputRate::INSTANCE,
@@ -404,44 +752,182 @@ pub fn vertex_input_binding_description(
     }
 }
 
+fn validate_step_rate(device: &DeviceInner, descriptor: &VertexBufferLayoutDescriptor) -> Result<(), Error> {
+    if descriptor.step_rate == 1 {
+        return Ok(());
+    }
+    if descriptor.step_mode != InputStepMode::Instance {
+        log::error!(
+            "step_rate must be 1 for InputStepMode::{:?} (input_slot: {})",
+            descriptor.step_mode,
+            descriptor.input_slot
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+    if descriptor.step_rate == 0 {
+        log::error!(
+            "step_rate must be greater than zero (input_slot: {})",
+            descriptor.input_slot
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+    if !device.extensions.vertex_attribute_divisor {
+        log::error!("step_rate other than 1 requires Extensions::vertex_attribute_divisor");
+        return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+    }
+    Ok(())
+}
+
 impl RenderPipelineInner {
+    /// Returns the live pipeline that was created with an identical descriptor or creates a new one.
+    pub fn get_or_create(
+        device: Arc<DeviceInner>,
+        descriptor: RenderPipelineDescriptor,
+    ) -> Result<Arc<RenderPipelineInner>, Error> {
+        {
+            let cache = device.render_pipeline_cache.lock();
+            if let Some(render_pipeline) = cache.get(&descriptor).and_then(Weak::upgrade) {
+                log::trace!("render pipeline cache hit: {:?}", render_pipeline.handle);
+                return Ok(render_pipeline);
+            }
+        }
+
+        // The cache is not locked during creation so that pipelines can be created in parallel
+        let mut render_pipeline = RenderPipelineInner::new(device.clone(), descriptor.clone())?;
+        render_pipeline.cache_key = Some(descriptor.clone());
+        let render_pipeline = Arc::new(render_pipeline);
+
+        // The lookup is repeated while the cache is locked for the insert. If another thread created
+        // an identical pipeline in the meantime, it is returned and the new one is destroyed.
+        let mut cache = device.render_pipeline_cache.lock();
+        if let Some(existing) = cache.get(&descriptor).and_then(Weak::upgrade) {
+            drop(cache);
+            return Ok(existing);
+        }
+        cache.insert(descriptor, Arc::downgrade(&render_pipeline));
+
+        Ok(render_pipeline)
+    }
+
     pub fn new(device: Arc<DeviceInner>, descriptor: RenderPipelineDescriptor) -> Result<RenderPipelineInner, Error> {
         // TODO: inspect push constants
 
+        if descriptor.vertex_state.index_format == IndexFormat::U8 && !device.extensions.index_type_uint8 {
+            log::error!("IndexFormat::U8 requires Extensions::index_type_uint8");
+            return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+        }
+
+        let limits = &device.adapter.physical_device_properties.limits;
+        for vertex_buffer in descriptor.vertex_state.vertex_buffers.iter() {
+            validate_step_rate(&device, vertex_buffer)?;
+            if vertex_buffer.stride > limits.max_vertex_input_binding_stride as usize {
+                log::error!(
+                    "vertex buffer stride exceeds the maximum of {}: {}",
+                    limits.max_vertex_input_binding_stride,
+                    vertex_buffer.stride
+                );
+                return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+            }
+            for attribute in vertex_buffer.attributes.iter() {
+                if !device.adapter.supports_vertex_format(attribute.format) {
+                    log::error!("vertex format is not supported by the adapter: {:?}", attribute.format);
+                    return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+                }
+                if attribute.offset > limits.max_vertex_input_attribute_offset as usize {
+                    log::error!(
+                        "vertex attribute offset exceeds the maximum of {}: {}",
+                        limits.max_vertex_input_attribute_offset,
+                        attribute.offset
+                    );
+                    return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+                }
+            }
+        }
+
+        validate_shader_bindings(ShaderStage::VERTEX, &descriptor.vertex_stage, &descriptor.layout.inner)?;
+        if let Some(ref fragment_stage) = descriptor.fragment_stage {
+            validate_shader_bindings(ShaderStage::FRAGMENT, fragment_stage, &descriptor.layout.inner)?;
+        }
+
         let vertex_entry_point = CString::new(&*descriptor.vertex_stage.entry_point).map_err(|e| {
             log::error!("invalid vertex entry point: {:?}", e);
             vk::Result::ERROR_VALIDATION_FAILED_EXT
         })?;
 
-        let fragment_entry_point = CString::new(&*descriptor.fragment_stage.entry_point).map_err(|e| {
-            log::error!("invalid fragment entry point: {:?}", e);
-            vk::Result::ERROR_VALIDATION_FAILED_EXT
-        })?;
+        let uses_blend_color = descriptor.color_states.iter().any(|color_state| {
+            [color_state.color_blend, color_state.alpha_blend]
+                .iter()
+                .any(|blend| uses_blend_color(blend.src_factor) || uses_blend_color(blend.dst_factor))
+        });
 
-        let shader_stages_create_info = &[
-            vk::PipelineShaderStageCreateInfo {
-                stage: vk::ShaderStageFlags::VERTEX,
-                module: descriptor.vertex_stage.module.inner.handle,
-                p_name: vertex_entry_point.as_ptr(),
-                ..Default::default()
-            },
-            vk::PipelineShaderStageCreateInfo {
+        let independent_blend = descriptor.color_states.windows(2).any(|pair| {
+            let (a, b) = (&pair[0], &pair[1]);
+            a.color_blend != b.color_blend || a.alpha_blend != b.alpha_blend || a.write_mask != b.write_mask
+        });
+
+        if independent_blend && device.adapter.physical_device_features.independent_blend != vk::TRUE {
+            log::error!("color states with different blending or write masks require independent blending");
+            return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+        }
+
+        if uses_blend_color && descriptor.blend_color.is_none() {
+            log::error!("BlendFactor::BlendColor and BlendFactor::OneMinusBlendColor require a blend_color");
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        if descriptor.fragment_stage.is_none() && !descriptor.color_states.is_empty() {
+            log::error!("color_states must be empty when fragment_stage is None");
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        let fragment_entry_point = match descriptor.fragment_stage {
+            Some(ref fragment_stage) => Some(CString::new(&*fragment_stage.entry_point).map_err(|e| {
+                log::error!("invalid fragment entry point: {:?}", e);
+                vk::Result::ERROR_VALIDATION_FAILED_EXT
+            })?),
+            None => None,
+        };
+
+        let mut shader_stages_create_info: SmallVec<[vk::PipelineShaderStageCreateInfo; 2]> = SmallVec::new();
+
+        shader_stages_create_info.push(vk::PipelineShaderStageCreateInfo {
+            stage: vk::ShaderStageFlags::VERTEX,
+            module: descriptor.vertex_stage.module.inner.handle,
+            p_name: vertex_entry_point.as_ptr(),
+            ..Default::default()
+        });
+
+        if let (Some(fragment_stage), Some(fragment_entry_point)) =
+            (descriptor.fragment_stage.as_ref(), fragment_entry_point.as_ref())
+        {
+            shader_stages_create_info.push(vk::PipelineShaderStageCreateInfo {
                 stage: vk::ShaderStageFlags::FRAGMENT,
-                module: descriptor.fragment_stage.module.inner.handle,
+                module: fragment_stage.module.inner.handle,
                 p_name: fragment_entry_point.as_ptr(),
                 ..Default::default()
-            },
-        ];
+            });
+        }
+
+        let is_strip_topology = match descriptor.primitive_topology {
+            PrimitiveTopology::LineStrip | PrimitiveTopology::TriangleStrip => true,
+            PrimitiveTopology::TriangleList | PrimitiveTopology::PointList | PrimitiveTopology::LineList => false,
+        };
+
+        // Vulkan forbids primitive restart for 'list' topologies
+        if descriptor.vertex_state.primitive_restart_enabled && !is_strip_topology {
+            log::error!(
+                "primitive_restart_enabled requires a strip topology: {:?}",
+                descriptor.primitive_topology
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
 
         let input_assembly_state_create_info = vk::PipelineInputAssemblyStateCreateInfo {
             topology: primitive_topology(descriptor.primitive_topology),
-            // Dawn notes that this must always be enabled because of Metal, but Vulkan forbids
-            // it for 'list' topologies.
-            primitive_restart_enable: match descriptor.primitive_topology {
-                PrimitiveTopology::LineStrip | PrimitiveTopology::TriangleStrip => vk::TRUE,
-                PrimitiveTopology::TriangleList | PrimitiveTopology::PointList | PrimitiveTopology::LineList => {
-                    vk::FALSE
-                }
+            primitive_restart_enable: if descriptor.vertex_state.primitive_restart_enabled {
+                vk::TRUE
+            } else {
+                vk::FALSE
             },
             ..Default::default()
         };
@@ -470,11 +956,11 @@ impl RenderPipelineInner {
             .alpha_to_coverage_enable(descriptor.alpha_to_coverage_enabled)
             .build();
 
-        let depth_stencil_state_create_info = depth_stencil_state_create_info(
-            descriptor
-                .depth_stencil_state
-                .unwrap_or_else(disable_depth_stencil_test),
-        );
+        let depth_stencil_state = descriptor
+            .depth_stencil_state
+            .unwrap_or_else(disable_depth_stencil_test);
+
+        let depth_stencil_state_create_info = depth_stencil_state_create_info(depth_stencil_state);
 
         let rasterization_state_create_info = rasterization_state_create_info(&descriptor.rasterization_state);
 
@@ -489,7 +975,7 @@ impl RenderPipelineInner {
             logic_op: vk::LogicOp::CLEAR,
             p_attachments: color_blend_attachment_states.as_ptr(),
             attachment_count: color_blend_attachment_states.len() as u32,
-            blend_constants: [0.0, 0.0, 0.0, 0.0], // dummy values
+            blend_constants: [0.0, 0.0, 0.0, 0.0], // dummy values; set dynamically when the pipeline is bound
             ..Default::default()
         };
 
@@ -508,11 +994,32 @@ impl RenderPipelineInner {
             .map(vertex_input_binding_description)
             .collect();
 
-        let vertex_input_state_create_info = vk::PipelineVertexInputStateCreateInfo::builder()
-            .vertex_attribute_descriptions(&vertex_attribute_descriptions)
-            .vertex_binding_descriptions(&vertex_binding_descriptions)
+        let vertex_binding_divisor_descriptions: Vec<vk::VertexInputBindingDivisorDescriptionEXT> = descriptor
+            .vertex_state
+            .vertex_buffers
+            .iter()
+            .filter(|vb| vb.step_rate != 1)
+            .map(|vb| vk::VertexInputBindingDivisorDescriptionEXT {
+                binding: vb.input_slot,
+                divisor: vb.step_rate,
+            })
+            .collect();
+
+        let mut vertex_input_divisor_state_create_info = vk::PipelineVertexInputDivisorStateCreateInfoEXT::builder()
+            .vertex_binding_divisors(&vertex_binding_divisor_descriptions)
             .build();
 
+        let mut vertex_input_state_create_info = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_attribute_descriptions(&vertex_attribute_descriptions)
+            .vertex_binding_descriptions(&vertex_binding_descriptions);
+
+        if !vertex_binding_divisor_descriptions.is_empty() {
+            vertex_input_state_create_info =
+                vertex_input_state_create_info.push_next(&mut vertex_input_divisor_state_create_info);
+        }
+
+        let vertex_input_state_create_info = vertex_input_state_create_info.build();
+
         let dynamic_states = &[
             vk::DynamicState::VIEWPORT,
             vk::DynamicState::SCISSOR,
@@ -521,6 +1028,8 @@ impl RenderPipelineInner {
             vk::DynamicState::BLEND_CONSTANTS,
             vk::DynamicState::DEPTH_BOUNDS,
             vk::DynamicState::STENCIL_REFERENCE,
+            vk::DynamicState::STENCIL_COMPARE_MASK,
+            vk::DynamicState::STENCIL_WRITE_MASK,
         ];
 
         let dynamic_state_create_info = vk::PipelineDynamicStateCreateInfo::builder()
@@ -557,7 +1066,7 @@ impl RenderPipelineInner {
         let create_info = vk::GraphicsPipelineCreateInfo::builder()
             .layout(descriptor.layout.inner.handle)
             .render_pass(render_pass)
-            .stages(shader_stages_create_info)
+            .stages(&shader_stages_create_info)
             .vertex_input_state(&vertex_input_state_create_info)
             .input_assembly_state(&input_assembly_state_create_info)
             .viewport_state(&viewport_state_create_info)
@@ -567,8 +1076,23 @@ impl RenderPipelineInner {
             .color_blend_state(&color_blend_state_create_info)
             .dynamic_state(&dynamic_state_create_info)
             .base_pipeline_handle(vk::Pipeline::null())
-            .base_pipeline_index(-1)
-            .build();
+            .base_pipeline_index(-1);
+
+        // The stage feedback count must match the stage count
+        let mut feedback = vk::PipelineCreationFeedbackEXT::default();
+        let mut stage_feedbacks: SmallVec<[vk::PipelineCreationFeedbackEXT; 2]> = SmallVec::from_elem(
+            vk::PipelineCreationFeedbackEXT::default(),
+            shader_stages_create_info.len(),
+        );
+        let mut feedback_create_info = vk::PipelineCreationFeedbackCreateInfoEXT::builder()
+            .pipeline_creation_feedback(&mut feedback)
+            .pipeline_stage_creation_feedbacks(&mut stage_feedbacks);
+
+        let create_info = if device.extensions.pipeline_creation_feedback {
+            create_info.push_next(&mut feedback_create_info).build()
+        } else {
+            create_info.build()
+        };
 
         let pipeline_cache = vk::PipelineCache::null();
 
@@ -580,7 +1104,7 @@ impl RenderPipelineInner {
                 pipeline_cache,
                 1,
                 &create_info,
-                std::ptr::null(),
+                device.allocation_callbacks_ptr(),
                 &mut handle,
             );
             if ret != vk::Result::SUCCESS {
@@ -590,14 +1114,41 @@ impl RenderPipelineInner {
 
         let layout = descriptor.layout.inner.clone();
 
+        let compilation_info = if device.extensions.pipeline_creation_feedback {
+            Some(compilation_info(&feedback))
+        } else {
+            None
+        };
+
         Ok(RenderPipelineInner {
             handle,
             layout,
             index_format: descriptor.vertex_state.index_format,
+            blend_color: descriptor.blend_color,
+            depth_bias: (
+                descriptor.rasterization_state.depth_bias as f32,
+                descriptor.rasterization_state.depth_bias_clamp,
+                descriptor.rasterization_state.depth_bias_slope_scale,
+            ),
+            stencil_masks: (
+                depth_stencil_state.stencil_read_mask,
+                depth_stencil_state.stencil_write_mask,
+            ),
+            compilation_info,
+            cache_key: None,
         })
     }
 }
 
+impl RenderPipeline {
+    /// Returns the creation feedback of the pipeline, or `None` if
+    /// `Extensions::pipeline_creation_feedback` was not enabled. Pipelines returned from the
+    /// device's render pipeline cache report the feedback of their original creation.
+    pub fn compilation_info(&self) -> Option<CompilationInfo> {
+        self.inner.compilation_info
+    }
+}
+
 impl Into<RenderPipeline> for RenderPipelineInner {
     fn into(self) -> RenderPipeline {
         RenderPipeline { inner: Arc::new(self) }
@@ -606,6 +1157,21 @@ impl Into<RenderPipeline> for RenderPipelineInner {
 
 impl Drop for RenderPipelineInner {
     fn drop(&mut self) {
+        // Remove this pipeline from the cache. The cache keys reference the pipeline layout, and
+        // therefore the device, so they must not outlive the pipeline. The entry is only removed if
+        // it still refers to this pipeline; it may have been replaced after the last strong reference
+        // was dropped.
+        if let Some(cache_key) = self.cache_key.take() {
+            let this: *const RenderPipelineInner = self;
+            let mut cache = self.layout.device.render_pipeline_cache.lock();
+            let is_cached = cache
+                .get(&cache_key)
+                .map_or(false, |render_pipeline| render_pipeline.as_ptr() == this);
+            if is_cached {
+                cache.remove(&cache_key);
+            }
+        }
+
         let mut state = self.layout.device.state.lock();
         let serial = state.get_next_pending_serial();
         state.get_fenced_deleter().delete_when_unused(self.handle, serial);
@@ -0,0 +1,76 @@
+use ash::vk;
+
+use crate::imp::fenced_deleter::DeleteWhenUnused;
+use crate::imp::{DeviceInner, QuerySetInner};
+use crate::{Error, QuerySet, QuerySetDescriptor, QueryType};
+
+use std::sync::Arc;
+
+pub fn query_type(query_type: QueryType) -> vk::QueryType {
+    match query_type {
+        QueryType::Timestamp => vk::QueryType::TIMESTAMP,
+    }
+}
+
+impl QuerySetInner {
+    pub fn new(device: Arc<DeviceInner>, descriptor: QuerySetDescriptor) -> Result<QuerySetInner, Error> {
+        if descriptor.count == 0 {
+            log::error!("query set count must be greater than zero");
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        if descriptor.query_type == QueryType::Timestamp {
+            let queue_family_index = device.queue.queue_family_index as usize;
+            let queue_family = &device.adapter.queue_family_properties[queue_family_index];
+            if queue_family.timestamp_valid_bits == 0 {
+                log::error!(
+                    "timestamps are not supported by the queue family: {}",
+                    queue_family_index
+                );
+                return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+            }
+        }
+
+        let create_info = vk::QueryPoolCreateInfo {
+            query_type: query_type(descriptor.query_type),
+            query_count: descriptor.count,
+            ..Default::default()
+        };
+
+        let handle = unsafe {
+            device
+                .raw
+                .create_query_pool(&create_info, device.allocation_callbacks())?
+        };
+
+        Ok(QuerySetInner {
+            handle,
+            device,
+            descriptor,
+        })
+    }
+}
+
+impl QuerySet {
+    pub fn query_type(&self) -> QueryType {
+        self.inner.descriptor.query_type
+    }
+
+    pub fn count(&self) -> u32 {
+        self.inner.descriptor.count
+    }
+}
+
+impl Drop for QuerySetInner {
+    fn drop(&mut self) {
+        let mut state = self.device.state.lock();
+        let serial = state.get_next_pending_serial();
+        state.get_fenced_deleter().delete_when_unused(self.handle, serial);
+    }
+}
+
+impl Into<QuerySet> for QuerySetInner {
+    fn into(self) -> QuerySet {
+        QuerySet { inner: Arc::new(self) }
+    }
+}
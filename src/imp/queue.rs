@@ -1,7 +1,201 @@
 use ash::vk;
 
-use crate::imp::FenceInner;
-use crate::{CommandBuffer, Error, Fence, Queue, SwapchainError, SwapchainImage};
+use crate::imp::command::TextureCopy;
+use crate::imp::command_buffer::{self, buffer_image_copy};
+use crate::imp::command_encoder::{buffer_copy_with_layout, required_bytes_in_copy};
+use crate::imp::{sparse, BufferInner, DeviceInner, FenceInner, TextureInner};
+use crate::{
+    align_to, Buffer, BufferDescriptor, BufferUsage, CommandBuffer, Device, Error, Extent3d, Fence, Origin3d, Pod,
+    Queue, SparseTextureBind, SubmissionIndex, SwapchainError, SwapchainImage, TextureCopyView, TextureDataLayout,
+    TextureUsage,
+};
+
+use std::mem;
+use std::sync::{Arc, Weak};
+
+/// Pending writes are aligned to the largest texel block so that the staged data satisfies the
+/// offset requirements of both buffer and texture copies.
+const STAGING_ALIGNMENT: usize = 16;
+
+/// Writes made with `Queue::write_buffer` and `Queue::write_texture` that have not been submitted.
+///
+/// The destinations are weak references so that pending writes don't keep their resources (and
+/// therefore the device) alive. Writes to resources that are dropped before the next submission
+/// are discarded.
+#[derive(Debug, Default)]
+pub struct PendingWrites {
+    data: Vec<u8>,
+    buffer_writes: Vec<PendingBufferWrite>,
+    texture_writes: Vec<PendingTextureWrite>,
+}
+
+#[derive(Debug)]
+struct PendingBufferWrite {
+    buffer: Weak<BufferInner>,
+    region: vk::BufferCopy,
+}
+
+#[derive(Debug)]
+struct PendingTextureWrite {
+    texture: Weak<TextureInner>,
+    mip_level: u32,
+    array_layer: u32,
+    origin: Origin3d,
+    layout: TextureDataLayout,
+    size_texels: Extent3d,
+}
+
+/// Pending writes that were copied into a staging buffer, with the copies grouped by destination
+struct StagedWrites {
+    staging_buffer: Buffer,
+    buffer_copies: Vec<(Arc<BufferInner>, Vec<vk::BufferCopy>)>,
+    texture_copies: Vec<(Arc<TextureInner>, Vec<vk::BufferImageCopy>)>,
+}
+
+impl PendingWrites {
+    fn push_data(&mut self, data: &[u8]) -> usize {
+        let offset = align_to(self.data.len(), STAGING_ALIGNMENT);
+        self.data.resize(offset, 0);
+        self.data.extend_from_slice(data);
+        offset
+    }
+
+    fn stage(self, device: &Arc<DeviceInner>) -> Result<Option<StagedWrites>, Error> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+
+        let staging_buffer: Buffer = BufferInner::new(
+            device.clone(),
+            BufferDescriptor {
+                size: self.data.len(),
+                usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
+                memory_hint: None,
+            },
+        )?
+        .into();
+
+        staging_buffer.map_write()?.copy_from_slice(&self.data)?;
+
+        let mut buffer_copies = Vec::new();
+        for write in self.buffer_writes {
+            if let Some(buffer) = write.buffer.upgrade() {
+                push_region(&mut buffer_copies, buffer, write.region, buffer_regions_overlap);
+            }
+        }
+
+        let mut texture_copies = Vec::new();
+        for write in self.texture_writes {
+            if let Some(texture) = write.texture.upgrade() {
+                let format = texture.descriptor.format;
+                let buffer_copy = buffer_copy_with_layout(staging_buffer.inner.clone(), write.layout, format);
+                let texture_copy = TextureCopy {
+                    texture: texture.clone(),
+                    mip_level: write.mip_level,
+                    array_layer: write.array_layer,
+                    array_layer_count: 1,
+                    origin_texels: write.origin,
+                };
+                let region = buffer_image_copy(&buffer_copy, &texture_copy, write.size_texels);
+                push_region(&mut texture_copies, texture, region, texture_regions_overlap);
+            }
+        }
+
+        Ok(Some(StagedWrites {
+            staging_buffer,
+            buffer_copies,
+            texture_copies,
+        }))
+    }
+}
+
+/// Adds the region to the most recent copy into the resource, unless it overlaps a region of
+/// that copy. The regions of a single copy command must not overlap, so overlapping writes start
+/// a new copy, which preserves the order of the writes.
+fn push_region<R, T>(copies: &mut Vec<(Arc<R>, Vec<T>)>, resource: Arc<R>, region: T, overlaps: fn(&T, &T) -> bool) {
+    if let Some((_, regions)) = copies.iter_mut().rev().find(|(r, _)| Arc::ptr_eq(r, &resource)) {
+        if !regions.iter().any(|r| overlaps(r, &region)) {
+            regions.push(region);
+            return;
+        }
+    }
+    copies.push((resource, vec![region]));
+}
+
+fn ranges_overlap(a_start: i64, a_len: i64, b_start: i64, b_len: i64) -> bool {
+    a_start < b_start + b_len && b_start < a_start + a_len
+}
+
+fn buffer_regions_overlap(a: &vk::BufferCopy, b: &vk::BufferCopy) -> bool {
+    ranges_overlap(a.dst_offset as i64, a.size as i64, b.dst_offset as i64, b.size as i64)
+}
+
+fn texture_regions_overlap(a: &vk::BufferImageCopy, b: &vk::BufferImageCopy) -> bool {
+    let (a_sub, b_sub) = (&a.image_subresource, &b.image_subresource);
+    a_sub.mip_level == b_sub.mip_level
+        && ranges_overlap(
+            a_sub.base_array_layer as i64,
+            a_sub.layer_count as i64,
+            b_sub.base_array_layer as i64,
+            b_sub.layer_count as i64,
+        )
+        && ranges_overlap(
+            a.image_offset.x as i64,
+            a.image_extent.width as i64,
+            b.image_offset.x as i64,
+            b.image_extent.width as i64,
+        )
+        && ranges_overlap(
+            a.image_offset.y as i64,
+            a.image_extent.height as i64,
+            b.image_offset.y as i64,
+            b.image_extent.height as i64,
+        )
+        && ranges_overlap(
+            a.image_offset.z as i64,
+            a.image_extent.depth as i64,
+            b.image_offset.z as i64,
+            b.image_extent.depth as i64,
+        )
+}
+
+impl StagedWrites {
+    fn record(&self, device: &DeviceInner, command_buffer: vk::CommandBuffer) -> Result<(), Error> {
+        let staging_buffer = &self.staging_buffer.inner;
+        staging_buffer.transition_usage_now(command_buffer, BufferUsage::COPY_SRC)?;
+
+        for (buffer, regions) in self.buffer_copies.iter() {
+            // The regions are transitioned together, so only the span of the regions is included in the barrier
+            let start = regions.iter().map(|region| region.dst_offset).min().unwrap_or(0);
+            let end = regions
+                .iter()
+                .map(|region| region.dst_offset + region.size)
+                .max()
+                .unwrap_or(0);
+            buffer.transition_range_usage_now(command_buffer, BufferUsage::COPY_DST, start, end - start)?;
+            unsafe {
+                device
+                    .raw
+                    .cmd_copy_buffer(command_buffer, staging_buffer.handle, buffer.handle, regions);
+            }
+        }
+
+        for (texture, regions) in self.texture_copies.iter() {
+            texture.transition_usage_now(command_buffer, TextureUsage::COPY_DST, None)?;
+            unsafe {
+                device.raw.cmd_copy_buffer_to_image(
+                    command_buffer,
+                    staging_buffer.handle,
+                    texture.handle,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    regions,
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
 
 impl Queue {
     pub fn present(&self, frame: SwapchainImage) -> Result<(), SwapchainError> {
@@ -10,7 +204,7 @@ impl Queue {
             let mut state = frame.swapchain.device.state.lock();
             let command_buffer = state.get_pending_command_buffer(&device)?;
             let texture = &frame.swapchain.textures[frame.image_index as usize];
-            texture.transition_usage_now(command_buffer, texture.descriptor.usage, None)?;
+            texture.transition_usage_now(command_buffer, TextureUsage::PRESENT, None)?;
             state.submit_pending_commands(&frame.swapchain.device, &self.inner.queue)?;
 
             // these should always be empty after pending commands were submitted
@@ -40,22 +234,273 @@ impl Queue {
         Ok(())
     }
 
-    pub fn submit(&self, command_buffers: &[CommandBuffer]) -> Result<(), Error> {
+    /// Submits the command buffers and returns the index of the submission.
+    ///
+    /// If no command buffers are provided, nothing is submitted and the index of the most recent
+    /// submission is returned.
+    pub fn submit(&self, command_buffers: &[CommandBuffer]) -> Result<SubmissionIndex, Error> {
         let device = &self.inner.device;
 
+        command_buffer::validate_submit(command_buffers)?;
+
         device.tick()?;
 
-        if !command_buffers.is_empty() {
-            let mut state = self.inner.device.state.lock();
-            let vk_command_buffer = state.get_pending_command_buffer(&device)?;
+        let staged_writes = self.stage_pending_writes()?;
+
+        let mut state = self.inner.device.state.lock();
+
+        if !command_buffers.is_empty() || staged_writes.is_some() {
+            if let Some(ref staged_writes) = staged_writes {
+                let vk_command_buffer = state.get_pending_command_buffer(&device)?;
+                staged_writes.record(device, vk_command_buffer)?;
+            }
+
+            for command_buffer in command_buffers.iter() {
+                command_buffer.inner.record_submission(&mut state)?;
+            }
+
+            state.submit_pending_commands(&device, &self.inner.queue)?;
 
             for command_buffer in command_buffers.iter() {
-                command_buffer.inner.record_commands(vk_command_buffer, &mut state)?;
+                command_buffer.inner.mark_submitted();
             }
+        }
+
+        Ok(SubmissionIndex {
+            serial: state.get_last_submitted_serial().get(),
+        })
+    }
+
+    /// Submits the command buffers to the physical devices selected by `device_mask`. Bit `i`
+    /// corresponds to the adapter at index `i` of the `AdapterGroup` that the device was
+    /// created from.
+    pub fn submit_with_device_mask(
+        &self,
+        command_buffers: &[CommandBuffer],
+        device_mask: u32,
+    ) -> Result<SubmissionIndex, Error> {
+        let device = &self.inner.device;
+
+        let valid_device_mask = (1u64 << device.physical_device_count) - 1;
+        if device_mask == 0 || u64::from(device_mask) & !valid_device_mask != 0 {
+            log::error!(
+                "invalid device mask: {:#b}; physical_device_count: {}",
+                device_mask,
+                device.physical_device_count
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        command_buffer::validate_submit(command_buffers)?;
+
+        device.tick()?;
+
+        let staged_writes = self.stage_pending_writes()?;
+
+        let mut state = self.inner.device.state.lock();
+
+        let vk_command_buffer = state.get_pending_command_buffer(&device)?;
+
+        if let Some(ref staged_writes) = staged_writes {
+            staged_writes.record(device, vk_command_buffer)?;
+        }
+
+        for command_buffer in command_buffers.iter() {
+            command_buffer.inner.record_submission(&mut state)?;
+        }
+
+        state.submit_pending_commands_with(&device, &self.inner.queue, &[], &[], Some(device_mask))?;
+
+        for command_buffer in command_buffers.iter() {
+            command_buffer.inner.mark_submitted();
+        }
+
+        Ok(SubmissionIndex {
+            serial: state.get_last_submitted_serial().get(),
+        })
+    }
+
+    /// Submits the command buffers after the `wait` fences reach their timeline values. The
+    /// `signal` fences are set to their timeline values once the command buffers have completed.
+    ///
+    /// Requires `Extensions::timeline_semaphore`.
+    pub fn submit_with(
+        &self,
+        command_buffers: &[CommandBuffer],
+        wait: &[(&Fence, u64)],
+        signal: &[(&Fence, u64)],
+    ) -> Result<SubmissionIndex, Error> {
+        let device = &self.inner.device;
+
+        let mut timeline_waits = Vec::with_capacity(wait.len());
+        for &(fence, value) in wait.iter() {
+            timeline_waits.push((fence.inner.timeline_semaphore()?, value));
+        }
+
+        let mut timeline_signals = Vec::with_capacity(signal.len());
+        for &(fence, value) in signal.iter() {
+            timeline_signals.push((fence.inner.timeline_semaphore()?, value));
+        }
+
+        command_buffer::validate_submit(command_buffers)?;
+
+        device.tick()?;
+
+        let staged_writes = self.stage_pending_writes()?;
+
+        let mut state = self.inner.device.state.lock();
+
+        // Always create the pending command buffer so that a submission occurs even when only
+        // waiting or signaling
+        let vk_command_buffer = state.get_pending_command_buffer(&device)?;
+
+        if let Some(ref staged_writes) = staged_writes {
+            staged_writes.record(device, vk_command_buffer)?;
+        }
+
+        for command_buffer in command_buffers.iter() {
+            command_buffer.inner.record_submission(&mut state)?;
+        }
+
+        state.submit_pending_commands_with(&device, &self.inner.queue, &timeline_waits, &timeline_signals, None)?;
+
+        for command_buffer in command_buffers.iter() {
+            command_buffer.inner.mark_submitted();
+        }
+
+        Ok(SubmissionIndex {
+            serial: state.get_last_submitted_serial().get(),
+        })
+    }
+
+    /// Writes `data` into the buffer at `offset_bytes`. The buffer requires the `COPY_DST` usage.
+    ///
+    /// The content of `data` is read immediately, but the write is deferred until the next
+    /// submission. All writes made before a submission are uploaded with a single staging buffer
+    /// and complete before the submitted command buffers execute.
+    pub fn write_buffer<T: Pod>(&self, buffer: &Buffer, offset_bytes: usize, data: &[T]) -> Result<(), Error> {
+        let data: &[u8] = bytemuck::cast_slice(data);
+
+        if !buffer.inner.descriptor.usage.contains(BufferUsage::COPY_DST) {
+            log::error!("write_buffer requires BufferUsage::COPY_DST");
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        let buffer_size = buffer.inner.descriptor.size;
+        if offset_bytes + data.len() > buffer_size {
+            log::error!(
+                "write_buffer range exceeds buffer size; offset_bytes: {}, data_size: {}, buffer_size: {}",
+                offset_bytes,
+                data.len(),
+                buffer_size
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let mut pending_writes = self.inner.device.pending_writes.lock();
+        let data_offset = pending_writes.push_data(data);
+        pending_writes.buffer_writes.push(PendingBufferWrite {
+            buffer: Arc::downgrade(&buffer.inner),
+            region: vk::BufferCopy {
+                src_offset: data_offset as vk::DeviceSize,
+                dst_offset: offset_bytes as vk::DeviceSize,
+                size: data.len() as vk::DeviceSize,
+            },
+        });
+
+        Ok(())
+    }
+
+    /// Writes `data`, arranged according to `layout`, into a region of the texture. The texture
+    /// requires the `COPY_DST` usage.
+    ///
+    /// Like `write_buffer`, the write is deferred until the next submission.
+    pub fn write_texture(
+        &self,
+        dst: TextureCopyView,
+        data: &[u8],
+        layout: TextureDataLayout,
+        copy_size: Extent3d,
+    ) -> Result<(), Error> {
+        let descriptor = &dst.texture.inner.descriptor;
+
+        if !descriptor.usage.contains(TextureUsage::COPY_DST) {
+            log::error!("write_texture requires TextureUsage::COPY_DST");
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        if dst.mip_level >= descriptor.mip_level_count || dst.array_layer >= descriptor.array_layer_count {
+            log::error!(
+                "invalid subresource: mip_level: {}, array_layer: {}, mip_level_count: {}, array_layer_count: {}",
+                dst.mip_level,
+                dst.array_layer,
+                descriptor.mip_level_count,
+                descriptor.array_layer_count
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        let required_size = required_bytes_in_copy(layout, descriptor.format, copy_size, 1)?;
+        if required_size > data.len() {
+            log::error!(
+                "data is too small for the write: required size: {}, data size: {}",
+                required_size,
+                data.len()
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        let mut pending_writes = self.inner.device.pending_writes.lock();
+        let data_offset = pending_writes.push_data(&data[layout.offset..required_size]);
+        pending_writes.texture_writes.push(PendingTextureWrite {
+            texture: Arc::downgrade(&dst.texture.inner),
+            mip_level: dst.mip_level,
+            array_layer: dst.array_layer,
+            origin: dst.origin,
+            layout: TextureDataLayout {
+                offset: data_offset,
+                ..layout
+            },
+            size_texels: copy_size,
+        });
+
+        Ok(())
+    }
+
+    /// Copies the pending writes into a staging buffer. The staged writes hold references to
+    /// resources that lock the device state when dropped, so they must outlive the state lock.
+    fn stage_pending_writes(&self) -> Result<Option<StagedWrites>, Error> {
+        let pending_writes = mem::take(&mut *self.inner.device.pending_writes.lock());
+        pending_writes.stage(&self.inner.device)
+    }
+
+    /// Binds (or unbinds) the tiles of sparse textures to pages of a `SparsePagePool`. Commands
+    /// submitted after the call observe the new bindings.
+    ///
+    /// The caller is responsible for ensuring that the affected tiles and pages are no longer in
+    /// use by previously submitted command buffers.
+    pub fn bind_sparse(&self, binds: &[SparseTextureBind]) -> Result<(), Error> {
+        sparse::bind_sparse(&self.inner.device, &self.inner.queue, binds)
+    }
+
+    /// Returns the number of nanoseconds per timestamp tick.
+    pub fn timestamp_period(&self) -> f32 {
+        self.inner
+            .device
+            .adapter
+            .physical_device_properties
+            .limits
+            .timestamp_period
+    }
 
-            state.submit_pending_commands(&device, &self.inner.queue)
-        } else {
-            Ok(())
+    /// Returns the device that owns the queue.
+    pub fn device(&self) -> Device {
+        Device {
+            inner: self.inner.device.clone(),
         }
     }
 
@@ -2,6 +2,8 @@ use ash::vk;
 
 use smallvec::SmallVec;
 
+use crate::imp::fenced_deleter::{DeleteWhenUnused, FencedDeleter};
+use crate::imp::serial::Serial;
 use crate::imp::texture;
 use crate::{Error, LoadOp, TextureFormat};
 
@@ -12,6 +14,10 @@ use std::ptr;
 
 pub const MAX_COLOR_ATTACHMENTS: usize = 4;
 
+/// The number of render passes kept in the `RenderPassCache`. The least recently used render
+/// pass is evicted when a new one is created beyond this limit.
+pub const MAX_CACHED_RENDER_PASSES: usize = 64;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct DepthStencilInfo {
     pub format: TextureFormat,
@@ -68,9 +74,22 @@ pub fn attachment_load_op(op: LoadOp) -> vk::AttachmentLoadOp {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+struct CachedRenderPass {
+    handle: vk::RenderPass,
+    /// The serial of the last submission that may have used the render pass
+    last_used_serial: Serial,
+}
+
+/// Evicted render passes are destroyed with the `FencedDeleter` after the last submission that
+/// used them completes. Pipelines only need the render pass while they are created, so a
+/// pipeline remains valid after its render pass is evicted.
 #[derive(Debug, Default)]
 pub struct RenderPassCache {
-    cache: HashMap<RenderPassCacheQuery, vk::RenderPass, ahash::RandomState>,
+    cache: HashMap<RenderPassCacheQuery, CachedRenderPass, ahash::RandomState>,
+    /// Incremented whenever a render pass is evicted. Recorded command buffers that outlive the
+    /// submission they were recorded for are only valid while the generation is unchanged.
+    generation: u64,
 }
 
 pub fn color_attachment_reference(attachment: u32) -> vk::AttachmentReference {
@@ -164,23 +183,72 @@ pub fn sample_count_flags(sample_count: u32) -> Result<vk::SampleCountFlags, Err
 }
 
 impl RenderPassCache {
+    /// Returns the render pass for the query, creating it if needed. `serial` is the serial of
+    /// the pending submission that the render pass may be used in.
     pub fn get_render_pass(
         &mut self,
         query: RenderPassCacheQuery,
+        serial: Serial,
+        fenced_deleter: &mut FencedDeleter,
         device: &DeviceInner,
     ) -> Result<vk::RenderPass, Error> {
-        if let Some(handle) = self.cache.get(&query).cloned() {
-            Ok(handle)
-        } else {
-            self.create_render_pass(query, device)
+        if let Some(cached) = self.cache.get_mut(&query) {
+            cached.last_used_serial = serial;
+            return Ok(cached.handle);
         }
+
+        if self.cache.len() >= MAX_CACHED_RENDER_PASSES {
+            self.evict_least_recently_used(serial, fenced_deleter);
+        }
+
+        let handle = self.create_render_pass(&query, device)?;
+
+        self.cache.insert(
+            query,
+            CachedRenderPass {
+                handle,
+                last_used_serial: serial,
+            },
+        );
+
+        Ok(handle)
     }
 
-    fn create_render_pass(
-        &mut self,
-        query: RenderPassCacheQuery,
-        device: &DeviceInner,
-    ) -> Result<vk::RenderPass, Error> {
+    /// The number of cached render passes
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    fn evict_least_recently_used(&mut self, serial: Serial, fenced_deleter: &mut FencedDeleter) {
+        let least_recently_used = self
+            .cache
+            .iter()
+            .min_by_key(|(_, cached)| cached.last_used_serial)
+            .map(|(query, _)| query.clone());
+        if let Some(query) = least_recently_used {
+            if let Some(cached) = self.cache.remove(&query) {
+                log::trace!("evicting render_pass: {:?}, query: {:?}", cached.handle, query);
+                fenced_deleter.delete_when_unused(cached.handle, serial);
+                self.generation += 1;
+            }
+        }
+    }
+
+    /// Removes all render passes from the cache. They are destroyed after the submission with
+    /// `serial` completes.
+    pub fn evict_all(&mut self, serial: Serial, fenced_deleter: &mut FencedDeleter) {
+        for (_, cached) in self.cache.drain() {
+            fenced_deleter.delete_when_unused(cached.handle, serial);
+        }
+        self.generation += 1;
+    }
+
+    /// The number of times that render passes have been evicted
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn create_render_pass(&self, query: &RenderPassCacheQuery, device: &DeviceInner) -> Result<vk::RenderPass, Error> {
         let sample_count_flag = sample_count_flags(query.sample_count)?;
 
         let color_attachments = query
@@ -202,12 +270,19 @@ impl RenderPassCache {
             total_attachment_count += 1;
         }
 
+        // Only the color attachments with a resolve target have a resolve attachment description,
+        // so the resolve attachment indices are not necessarily contiguous with the color indices
+        let mut next_resolve_attachment = total_attachment_count;
         let resolve_attachments = query
             .color
             .iter()
-            .enumerate()
-            .map(|(attachment, color_info)| (attachment as u32 + total_attachment_count, *color_info))
-            .map(|(attachment, color_info)| resolve_attachment_reference(attachment, color_info))
+            .map(|color_info| {
+                let attachment = next_resolve_attachment;
+                if color_info.has_resolve_target {
+                    next_resolve_attachment += 1;
+                }
+                resolve_attachment_reference(attachment, *color_info)
+            })
             .collect::<SmallVec<[vk::AttachmentReference; MAX_COLOR_ATTACHMENTS]>>();
 
         let mut attachment_descriptions = SmallVec::<[vk::AttachmentDescription; 2 * MAX_COLOR_ATTACHMENTS + 1]>::new();
@@ -240,12 +315,18 @@ impl RenderPassCache {
             .map(|v| v as *const _)
             .unwrap_or_else(ptr::null);
 
+        let resolve_attachments_ptr = if resolve_attachment_count > 0 {
+            resolve_attachments.as_ptr()
+        } else {
+            ptr::null()
+        };
+
         let subpass_description = vk::SubpassDescription {
             flags: vk::SubpassDescriptionFlags::empty(),
             pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
             color_attachment_count: color_and_resolve_attachment_count,
             p_color_attachments: color_attachments.as_ptr(),
-            p_resolve_attachments: resolve_attachments.as_ptr(),
+            p_resolve_attachments: resolve_attachments_ptr,
             p_depth_stencil_attachment: depth_stencil_attachment_ptr,
             ..Default::default()
         };
@@ -260,18 +341,22 @@ impl RenderPassCache {
             .subpasses(&[subpass_description])
             .build();
 
-        let handle = unsafe { device.raw.create_render_pass(&create_info, None)? };
-
-        self.cache.insert(query, handle);
+        let handle = unsafe {
+            device
+                .raw
+                .create_render_pass(&create_info, device.allocation_callbacks())?
+        };
 
         Ok(handle)
     }
 
     pub fn drain(&mut self, device: &DeviceInner) {
-        for (query, handle) in self.cache.drain() {
+        for (query, cached) in self.cache.drain() {
             unsafe {
-                log::trace!("destroying render_pass: {:?}, query: {:?}", handle, query);
-                device.raw.destroy_render_pass(handle, None);
+                log::trace!("destroying render_pass: {:?}, query: {:?}", cached.handle, query);
+                device
+                    .raw
+                    .destroy_render_pass(cached.handle, device.allocation_callbacks());
             }
         }
     }
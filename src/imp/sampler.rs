@@ -2,7 +2,7 @@ use ash::vk;
 
 use crate::imp::fenced_deleter::DeleteWhenUnused;
 use crate::imp::{DeviceInner, SamplerInner};
-use crate::{AddressMode, CompareFunction, Error, FilterMode, Sampler, SamplerDescriptor};
+use crate::{AddressMode, BorderColor, CompareFunction, Error, FilterMode, Sampler, SamplerDescriptor};
 
 use std::sync::Arc;
 
@@ -11,6 +11,15 @@ pub fn address_mode(mode: AddressMode) -> vk::SamplerAddressMode {
         AddressMode::ClampToEdge => vk::SamplerAddressMode::CLAMP_TO_EDGE,
         AddressMode::MirrorRepeat => vk::SamplerAddressMode::MIRRORED_REPEAT,
         AddressMode::Repeat => vk::SamplerAddressMode::REPEAT,
+        AddressMode::ClampToBorder => vk::SamplerAddressMode::CLAMP_TO_BORDER,
+    }
+}
+
+pub fn border_color(color: BorderColor) -> vk::BorderColor {
+    match color {
+        BorderColor::TransparentBlack => vk::BorderColor::FLOAT_TRANSPARENT_BLACK,
+        BorderColor::OpaqueBlack => vk::BorderColor::FLOAT_OPAQUE_BLACK,
+        BorderColor::OpaqueWhite => vk::BorderColor::FLOAT_OPAQUE_WHITE,
     }
 }
 
@@ -43,6 +52,27 @@ pub fn compare_op(func: CompareFunction) -> vk::CompareOp {
 
 impl SamplerInner {
     pub fn new(device: Arc<DeviceInner>, descriptor: SamplerDescriptor) -> Result<SamplerInner, Error> {
+        if descriptor.max_anisotropy == 0 {
+            log::error!("max_anisotropy must be at least 1");
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        if descriptor.max_anisotropy > 1 {
+            if !device.extensions.anisotropic_filtering {
+                log::error!("max_anisotropy greater than 1 requires Extensions::anisotropic_filtering");
+                return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+            }
+            let limit = device.adapter.supported_features().max_anisotropy;
+            if descriptor.max_anisotropy > limit {
+                log::error!(
+                    "max_anisotropy exceeds the adapter limit: {} > {}",
+                    descriptor.max_anisotropy,
+                    limit
+                );
+                return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+            }
+        }
+
         let create_info = vk::SamplerCreateInfo {
             address_mode_u: address_mode(descriptor.address_mode_u),
             address_mode_v: address_mode(descriptor.address_mode_v),
@@ -51,9 +81,8 @@ impl SamplerInner {
             min_filter: filter_mode(descriptor.min_filter),
             mipmap_mode: mipmap_mode(descriptor.mipmap_filter),
             mip_lod_bias: 0.0,
-            // TODO: Inspect device features/properties to set anisotropy values
-            anisotropy_enable: vk::FALSE,
-            max_anisotropy: 1.0,
+            anisotropy_enable: (descriptor.max_anisotropy > 1) as vk::Bool32,
+            max_anisotropy: descriptor.max_anisotropy as f32,
             compare_op: compare_op(descriptor.compare_function),
             compare_enable: if descriptor.compare_function == CompareFunction::Never {
                 vk::FALSE
@@ -62,12 +91,12 @@ impl SamplerInner {
             },
             min_lod: descriptor.lod_min_clamp,
             max_lod: descriptor.lod_max_clamp,
-            border_color: vk::BorderColor::FLOAT_TRANSPARENT_BLACK,
+            border_color: border_color(descriptor.border_color),
             unnormalized_coordinates: vk::FALSE,
             ..Default::default()
         };
 
-        let handle = unsafe { device.raw.create_sampler(&create_info, None)? };
+        let handle = unsafe { device.raw.create_sampler(&create_info, device.allocation_callbacks())? };
 
         Ok(SamplerInner {
             handle,
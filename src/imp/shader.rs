@@ -1,6 +1,7 @@
 use ash::vk;
 
 use crate::imp::fenced_deleter::DeleteWhenUnused;
+use crate::imp::spirv::ShaderReflection;
 use crate::imp::{DeviceInner, ShaderModuleInner};
 use crate::{Error, ShaderModule, ShaderModuleDescriptor};
 
@@ -9,9 +10,7 @@ use std::{mem, ptr};
 
 impl ShaderModuleInner {
     pub fn new(device: Arc<DeviceInner>, descriptor: ShaderModuleDescriptor) -> Result<ShaderModuleInner, Error> {
-        // TODO: Use spirv-cross to reflect binding and attribute info so we can determine
-        //       when a shader module is compatible with a given pipeline layout
-
+        // TODO: Reflect vertex attributes and push constants
         // Copy the code to a temp buffer to guarantee alignment and zero padding
         let byte_count = descriptor.code.len();
         let word_extra = if byte_count % 4 > 0 { 1 } else { 0 };
@@ -28,9 +27,22 @@ impl ShaderModuleInner {
             ..Default::default()
         };
 
-        let handle = unsafe { device.raw.create_shader_module(&create_info, None)? };
+        let handle = unsafe {
+            device
+                .raw
+                .create_shader_module(&create_info, device.allocation_callbacks())?
+        };
+
+        let reflection = ShaderReflection::parse(&words);
+        if reflection.is_none() {
+            log::warn!("failed to reflect shader module; bindings will not be validated against pipeline layouts");
+        }
 
-        Ok(ShaderModuleInner { handle, device })
+        Ok(ShaderModuleInner {
+            handle,
+            device,
+            reflection,
+        })
     }
 }
 
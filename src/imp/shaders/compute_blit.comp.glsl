@@ -0,0 +1,28 @@
+#version 450
+
+layout (local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform sampler2D u_source;
+// The format is omitted so that a single pipeline can write to any storage format.
+// Requires `shaderStorageImageWriteWithoutFormat`.
+layout(set = 0, binding = 1) uniform writeonly image2D u_target;
+
+layout(push_constant) uniform PushConstants {
+    // normalized source region; the extent may be negative for mirrored blits
+    vec2 src_offset;
+    vec2 src_extent;
+    // destination region in texels
+    ivec2 dst_offset;
+    ivec2 dst_extent;
+} pc;
+
+void main() {
+    ivec2 id = ivec2(gl_GlobalInvocationID.xy);
+    if (any(greaterThanEqual(id, pc.dst_extent))) {
+        return;
+    }
+
+    vec2 uv = pc.src_offset + (vec2(id) + 0.5) / vec2(pc.dst_extent) * pc.src_extent;
+
+    imageStore(u_target, pc.dst_offset + id, textureLod(u_source, uv, 0.0));
+}
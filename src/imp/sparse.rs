@@ -0,0 +1,356 @@
+use ash::vk;
+use ash::vk::MemoryPropertyFlags;
+
+use vk_mem::{AllocationCreateFlags, AllocationCreateInfo, MemoryUsage};
+
+use crate::imp::device::DeviceState;
+use crate::imp::fenced_deleter::DeleteWhenUnused;
+use crate::imp::{DeviceInner, QueueInfo, SparsePagePoolInner, SparseTextureInfo, TextureInner};
+use crate::{Error, Extent3d, SparsePagePool, SparseTextureBind, TextureDescriptor};
+
+use parking_lot::Mutex;
+
+use std::sync::Arc;
+
+fn allocation_create_info(flags: AllocationCreateFlags) -> AllocationCreateInfo {
+    AllocationCreateInfo {
+        usage: MemoryUsage::GpuOnly,
+        preferred_flags: MemoryPropertyFlags::empty(),
+        required_flags: MemoryPropertyFlags::empty(),
+        flags,
+        user_data: None,
+        pool: None,
+        memory_type_bits: 0,
+    }
+}
+
+/// Submits the sparse binding operation. Subsequent submissions wait for the binding to complete.
+fn queue_bind_sparse(
+    device: &DeviceInner,
+    state: &mut DeviceState,
+    queue: &QueueInfo,
+    bind_info: vk::BindSparseInfoBuilder,
+) -> Result<(), Error> {
+    unsafe {
+        let create_info = vk::SemaphoreCreateInfo::builder();
+        let semaphore = device
+            .raw
+            .create_semaphore(&create_info, device.allocation_callbacks())?;
+        let signal_semaphores = [semaphore];
+        let bind_info = bind_info.signal_semaphores(&signal_semaphores);
+        if let Err(e) = device
+            .raw
+            .queue_bind_sparse(queue.handle, &[bind_info.build()], vk::Fence::null())
+        {
+            device.raw.destroy_semaphore(semaphore, device.allocation_callbacks());
+            return Err(Error::from(e));
+        }
+        state.add_wait_semaphore(semaphore);
+    }
+    Ok(())
+}
+
+/// Allocates and binds the memory for the mip tail (and metadata, if required) of a sparse image.
+pub fn bind_mip_tail(
+    device: &DeviceInner,
+    image: vk::Image,
+    descriptor: &TextureDescriptor,
+) -> Result<SparseTextureInfo, Error> {
+    let memory_requirements = unsafe { device.raw.get_image_memory_requirements(image) };
+    let sparse_requirements = unsafe { device.raw.get_image_sparse_memory_requirements(image) };
+
+    let color_requirements = sparse_requirements
+        .iter()
+        .find(|r| r.format_properties.aspect_mask.contains(vk::ImageAspectFlags::COLOR))
+        .ok_or_else(|| {
+            log::error!(
+                "sparse image has no color memory requirements: {:?}",
+                sparse_requirements
+            );
+            Error::from(vk::Result::ERROR_FORMAT_NOT_SUPPORTED)
+        })?;
+
+    let granularity = color_requirements.format_properties.image_granularity;
+    let tile_size = Extent3d {
+        width: granularity.width,
+        height: granularity.height,
+        depth: granularity.depth,
+    };
+    let mip_tail_first_level = color_requirements
+        .image_mip_tail_first_lod
+        .min(descriptor.mip_level_count);
+
+    let alignment = memory_requirements.alignment;
+    let align = |size: vk::DeviceSize| (size + alignment - 1) / alignment * alignment;
+
+    // The memory and memory offsets are filled in once the size of the allocation is known
+    let mut binds = Vec::new();
+    let mut size = 0;
+    for requirements in sparse_requirements.iter() {
+        let is_metadata = requirements
+            .format_properties
+            .aspect_mask
+            .contains(vk::ImageAspectFlags::METADATA);
+        let has_mip_tail = requirements.image_mip_tail_first_lod < descriptor.mip_level_count;
+        if !(is_metadata || has_mip_tail) {
+            continue;
+        }
+        let single_mip_tail = requirements
+            .format_properties
+            .flags
+            .contains(vk::SparseImageFormatFlags::SINGLE_MIPTAIL);
+        let mip_tail_count = if single_mip_tail {
+            1
+        } else {
+            descriptor.array_layer_count
+        };
+        let flags = if is_metadata {
+            vk::SparseMemoryBindFlags::METADATA
+        } else {
+            vk::SparseMemoryBindFlags::empty()
+        };
+        for i in 0..mip_tail_count {
+            binds.push(vk::SparseMemoryBind {
+                resource_offset: requirements.image_mip_tail_offset
+                    + vk::DeviceSize::from(i) * requirements.image_mip_tail_stride,
+                size: requirements.image_mip_tail_size,
+                memory: vk::DeviceMemory::null(),
+                memory_offset: size,
+                flags,
+            });
+            size += align(requirements.image_mip_tail_size);
+        }
+    }
+
+    let mut sparse = SparseTextureInfo {
+        tile_size,
+        mip_tail_first_level,
+        memory_requirements,
+        mip_tail_allocation: None,
+        page_pools: Mutex::new(Vec::new()),
+    };
+
+    if binds.is_empty() {
+        return Ok(sparse);
+    }
+
+    let mip_tail_memory_requirements = vk::MemoryRequirements {
+        size,
+        alignment,
+        memory_type_bits: memory_requirements.memory_type_bits,
+    };
+
+    let (allocation, allocation_info) = device.allocator.allocate_memory(
+        &mip_tail_memory_requirements,
+        &allocation_create_info(AllocationCreateFlags::NONE),
+    )?;
+
+    for bind in binds.iter_mut() {
+        bind.memory = allocation_info.get_device_memory();
+        bind.memory_offset += allocation_info.get_offset() as vk::DeviceSize;
+    }
+
+    let opaque_binds = [vk::SparseImageOpaqueMemoryBindInfo::builder()
+        .image(image)
+        .binds(&binds)
+        .build()];
+    let bind_info = vk::BindSparseInfo::builder().image_opaque_binds(&opaque_binds);
+
+    let mut state = device.state.lock();
+    if let Err(e) = queue_bind_sparse(device, &mut state, &device.queue, bind_info) {
+        device.allocator.free_memory(&allocation);
+        return Err(e);
+    }
+
+    sparse.mip_tail_allocation = Some(allocation);
+
+    Ok(sparse)
+}
+
+/// Binds (or unbinds) the tiles of sparse textures.
+pub fn bind_sparse(device: &DeviceInner, queue: &QueueInfo, binds: &[SparseTextureBind]) -> Result<(), Error> {
+    if binds.is_empty() {
+        return Ok(());
+    }
+
+    let mut image_memory_binds = Vec::with_capacity(binds.len());
+
+    for bind in binds.iter() {
+        let texture = &bind.texture.inner;
+        let sparse = texture.sparse.as_ref().ok_or_else(|| {
+            log::error!("texture is not sparse: {:?}", texture.handle);
+            Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT)
+        })?;
+
+        if bind.mip_level >= sparse.mip_tail_first_level || bind.array_layer >= texture.descriptor.array_layer_count {
+            log::error!(
+                "invalid sparse bind subresource (mip_level: {}, array_layer: {}); mip_tail_first_level: {}, array_layer_count: {}",
+                bind.mip_level,
+                bind.array_layer,
+                sparse.mip_tail_first_level,
+                texture.descriptor.array_layer_count
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        let mip_width = (texture.descriptor.size.width >> bind.mip_level).max(1);
+        let mip_height = (texture.descriptor.size.height >> bind.mip_level).max(1);
+        let origin = bind.origin;
+        let tile_size = sparse.tile_size;
+        let is_aligned = origin.x >= 0
+            && origin.y >= 0
+            && origin.z == 0
+            && origin.x as u32 % tile_size.width == 0
+            && origin.y as u32 % tile_size.height == 0;
+        if !is_aligned || origin.x as u32 >= mip_width || origin.y as u32 >= mip_height {
+            log::error!(
+                "sparse bind origin {:?} is not aligned to the tile size {:?} or is outside of mip level {} ({}x{})",
+                origin,
+                tile_size,
+                bind.mip_level,
+                mip_width,
+                mip_height
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        let (memory, memory_offset) = match bind.page {
+            Some(page) => {
+                let pool = &page.pool.inner;
+                let memory_type_bit = 1 << pool.memory_type_index;
+                let requirements = sparse.memory_requirements;
+                if page.index >= pool.page_count
+                    || requirements.memory_type_bits & memory_type_bit == 0
+                    || pool.page_size < requirements.alignment
+                {
+                    log::error!(
+                        "sparse page {} is out of range (page_count: {}) or the page pool is incompatible with the texture",
+                        page.index,
+                        pool.page_count
+                    );
+                    return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+                }
+
+                let mut page_pools = sparse.page_pools.lock();
+                if !page_pools.iter().any(|p| Arc::ptr_eq(p, pool)) {
+                    page_pools.push(Arc::clone(pool));
+                }
+
+                let memory_offset = pool.memory_offset + vk::DeviceSize::from(page.index) * pool.page_size;
+                (pool.handle, memory_offset)
+            }
+            None => (vk::DeviceMemory::null(), 0),
+        };
+
+        image_memory_binds.push(vk::SparseImageMemoryBind {
+            subresource: vk::ImageSubresource {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: bind.mip_level,
+                array_layer: bind.array_layer,
+            },
+            offset: vk::Offset3D {
+                x: origin.x,
+                y: origin.y,
+                z: 0,
+            },
+            extent: vk::Extent3D {
+                width: tile_size.width.min(mip_width - origin.x as u32),
+                height: tile_size.height.min(mip_height - origin.y as u32),
+                depth: 1,
+            },
+            memory,
+            memory_offset,
+            flags: vk::SparseMemoryBindFlags::empty(),
+        });
+    }
+
+    let image_binds: Vec<_> = binds
+        .iter()
+        .zip(image_memory_binds.iter())
+        .map(|(bind, image_memory_bind)| {
+            vk::SparseImageMemoryBindInfo::builder()
+                .image(bind.texture.inner.handle)
+                .binds(std::slice::from_ref(image_memory_bind))
+                .build()
+        })
+        .collect();
+
+    let bind_info = vk::BindSparseInfo::builder().image_binds(&image_binds);
+
+    let mut state = device.state.lock();
+    queue_bind_sparse(device, &mut state, queue, bind_info)
+}
+
+impl SparsePagePoolInner {
+    pub fn new(texture: &TextureInner, page_count: u32) -> Result<SparsePagePoolInner, Error> {
+        let device = &texture.device;
+
+        let sparse = texture.sparse.as_ref().ok_or_else(|| {
+            log::error!("page pools can only be created for sparse textures");
+            Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT)
+        })?;
+
+        if page_count == 0 {
+            log::error!("page_count must be greater than zero");
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+
+        let page_size = sparse.memory_requirements.alignment;
+        let memory_requirements = vk::MemoryRequirements {
+            size: page_size * vk::DeviceSize::from(page_count),
+            alignment: page_size,
+            memory_type_bits: sparse.memory_requirements.memory_type_bits,
+        };
+
+        // The pool gets its own device memory so that the memory handle identifies the pool
+        let allocation_create_info = allocation_create_info(AllocationCreateFlags::DEDICATED_MEMORY);
+
+        let (allocation, allocation_info) = device
+            .allocator
+            .allocate_memory(&memory_requirements, &allocation_create_info)?;
+
+        log::trace!(
+            "created sparse page pool: page_size: {}, page_count: {}, allocation_info: {:?}",
+            page_size,
+            page_count,
+            allocation_info
+        );
+
+        Ok(SparsePagePoolInner {
+            handle: allocation_info.get_device_memory(),
+            device: Arc::clone(device),
+            allocation,
+            memory_offset: allocation_info.get_offset() as vk::DeviceSize,
+            memory_type_index: allocation_info.get_memory_type(),
+            page_size,
+            page_count,
+        })
+    }
+}
+
+impl SparsePagePool {
+    pub fn page_count(&self) -> u32 {
+        self.inner.page_count
+    }
+
+    /// Returns the size of each page, in bytes.
+    pub fn page_size(&self) -> u64 {
+        self.inner.page_size
+    }
+}
+
+impl Drop for SparsePagePoolInner {
+    fn drop(&mut self) {
+        let mut state = self.device.state.lock();
+        let serial = state.get_next_pending_serial();
+        state
+            .get_fenced_deleter()
+            .delete_when_unused(self.allocation.clone(), serial);
+    }
+}
+
+impl Into<SparsePagePool> for SparsePagePoolInner {
+    fn into(self) -> SparsePagePool {
+        SparsePagePool { inner: Arc::new(self) }
+    }
+}
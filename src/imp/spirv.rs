@@ -0,0 +1,346 @@
+//! # Minimal SPIR-V reflection of the resource bindings used by a shader module
+
+use crate::{BindingType, ShaderStage};
+
+use std::collections::{HashMap, HashSet};
+
+const MAGIC_NUMBER: u32 = 0x0723_0203;
+const HEADER_WORD_COUNT: usize = 5;
+const VERSION_1_4: u32 = 0x0001_0400;
+
+mod op {
+    pub const ENTRY_POINT: u32 = 15;
+    pub const TYPE_IMAGE: u32 = 25;
+    pub const TYPE_SAMPLER: u32 = 26;
+    pub const TYPE_SAMPLED_IMAGE: u32 = 27;
+    pub const TYPE_ARRAY: u32 = 28;
+    pub const TYPE_RUNTIME_ARRAY: u32 = 29;
+    pub const TYPE_STRUCT: u32 = 30;
+    pub const TYPE_POINTER: u32 = 32;
+    pub const FUNCTION: u32 = 54;
+    pub const FUNCTION_END: u32 = 56;
+    pub const FUNCTION_CALL: u32 = 57;
+    pub const VARIABLE: u32 = 59;
+    pub const IMAGE_TEXEL_POINTER: u32 = 60;
+    pub const LOAD: u32 = 61;
+    pub const STORE: u32 = 62;
+    pub const COPY_MEMORY: u32 = 63;
+    pub const COPY_MEMORY_SIZED: u32 = 64;
+    pub const ACCESS_CHAIN: u32 = 65;
+    pub const IN_BOUNDS_ACCESS_CHAIN: u32 = 66;
+    pub const PTR_ACCESS_CHAIN: u32 = 67;
+    pub const ARRAY_LENGTH: u32 = 68;
+    pub const IN_BOUNDS_PTR_ACCESS_CHAIN: u32 = 70;
+    pub const DECORATE: u32 = 71;
+    pub const COPY_OBJECT: u32 = 83;
+    pub const ATOMIC_LOAD: u32 = 227;
+    pub const ATOMIC_STORE: u32 = 228;
+    pub const ATOMIC_XOR: u32 = 242;
+}
+
+mod decoration {
+    pub const BUFFER_BLOCK: u32 = 3;
+    pub const BINDING: u32 = 33;
+    pub const DESCRIPTOR_SET: u32 = 34;
+}
+
+mod storage_class {
+    pub const UNIFORM_CONSTANT: u32 = 0;
+    pub const UNIFORM: u32 = 2;
+    pub const STORAGE_BUFFER: u32 = 12;
+}
+
+mod execution_model {
+    pub const VERTEX: u32 = 0;
+    pub const FRAGMENT: u32 = 4;
+    pub const GL_COMPUTE: u32 = 5;
+}
+
+mod dim {
+    pub const BUFFER: u32 = 5;
+    pub const SUBPASS_DATA: u32 = 6;
+}
+
+/// The kind of resource that a shader variable expects to be bound
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DescriptorType {
+    UniformBuffer,
+    StorageBuffer,
+    Sampler,
+    SampledTexture,
+    StorageTexture,
+    UniformTexelBuffer,
+    StorageTexelBuffer,
+    CombinedTextureSampler,
+    InputAttachment,
+}
+
+impl DescriptorType {
+    /// Returns `true` if a bind group layout entry of the given type can be used for the variable
+    pub fn is_compatible(self, binding_type: BindingType) -> bool {
+        match self {
+            DescriptorType::UniformBuffer => {
+                binding_type == BindingType::UniformBuffer || binding_type == BindingType::DynamicUniformBuffer
+            }
+            DescriptorType::StorageBuffer => {
+                binding_type == BindingType::StorageBuffer || binding_type == BindingType::DynamicStorageBuffer
+            }
+            DescriptorType::Sampler => {
+                binding_type == BindingType::Sampler || binding_type == BindingType::ComparisonSampler
+            }
+            DescriptorType::SampledTexture => binding_type == BindingType::SampledTexture,
+            DescriptorType::StorageTexture => {
+                binding_type == BindingType::ReadOnlyStorageTexture
+                    || binding_type == BindingType::WriteOnlyStorageTexture
+            }
+            DescriptorType::StorageTexelBuffer => binding_type == BindingType::StorageTexelBuffer,
+            DescriptorType::UniformTexelBuffer
+            | DescriptorType::CombinedTextureSampler
+            | DescriptorType::InputAttachment => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DescriptorBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub descriptor_type: DescriptorType,
+    variable: u32,
+}
+
+#[derive(Clone, Debug)]
+struct EntryPoint {
+    name: String,
+    execution_model: u32,
+    interface: Vec<u32>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Type {
+    Image { dim: u32, sampled: u32 },
+    Sampler,
+    SampledImage,
+    Array(u32),
+    Struct,
+    Pointer(u32),
+}
+
+/// The descriptor bindings that are statically used by the entry points of a shader module
+#[derive(Clone, Debug)]
+pub struct ShaderReflection {
+    version: u32,
+    entry_points: Vec<EntryPoint>,
+    bindings: Vec<DescriptorBinding>,
+}
+
+impl ShaderReflection {
+    /// Returns `None` if the code is not a valid SPIR-V module
+    pub fn parse(words: &[u32]) -> Option<ShaderReflection> {
+        if words.len() < HEADER_WORD_COUNT {
+            return None;
+        }
+
+        let swapped: Vec<u32>;
+        let words = match words[0] {
+            MAGIC_NUMBER => words,
+            magic if magic == MAGIC_NUMBER.swap_bytes() => {
+                swapped = words.iter().map(|word| word.swap_bytes()).collect();
+                &swapped
+            }
+            _ => return None,
+        };
+
+        let version = words[1];
+        let mut entry_points = Vec::new();
+        let mut descriptor_sets = HashMap::new();
+        let mut bindings = HashMap::new();
+        let mut buffer_blocks = HashSet::new();
+        let mut types = HashMap::new();
+        let mut variables = Vec::new();
+        let mut used = HashSet::new();
+        let mut in_function = false;
+
+        let mut offset = HEADER_WORD_COUNT;
+        while offset < words.len() {
+            let word_count = (words[offset] >> 16) as usize;
+            let opcode = words[offset] & 0xffff;
+            if word_count == 0 || offset + word_count > words.len() {
+                return None;
+            }
+            let operands = &words[offset + 1..offset + word_count];
+            offset += word_count;
+
+            match opcode {
+                op::ENTRY_POINT if operands.len() >= 3 => {
+                    let (name, name_word_count) = parse_string(&operands[2..])?;
+                    entry_points.push(EntryPoint {
+                        name,
+                        execution_model: operands[0],
+                        interface: operands[2 + name_word_count..].to_vec(),
+                    });
+                }
+                op::DECORATE if operands.len() >= 2 => match (operands[1], operands.get(2)) {
+                    (decoration::DESCRIPTOR_SET, Some(&set)) => {
+                        descriptor_sets.insert(operands[0], set);
+                    }
+                    (decoration::BINDING, Some(&binding)) => {
+                        bindings.insert(operands[0], binding);
+                    }
+                    (decoration::BUFFER_BLOCK, _) => {
+                        buffer_blocks.insert(operands[0]);
+                    }
+                    _ => {}
+                },
+                op::TYPE_IMAGE if operands.len() >= 7 => {
+                    let ty = Type::Image {
+                        dim: operands[2],
+                        sampled: operands[6],
+                    };
+                    types.insert(operands[0], ty);
+                }
+                op::TYPE_SAMPLER if !operands.is_empty() => {
+                    types.insert(operands[0], Type::Sampler);
+                }
+                op::TYPE_SAMPLED_IMAGE if !operands.is_empty() => {
+                    types.insert(operands[0], Type::SampledImage);
+                }
+                op::TYPE_ARRAY | op::TYPE_RUNTIME_ARRAY if operands.len() >= 2 => {
+                    types.insert(operands[0], Type::Array(operands[1]));
+                }
+                op::TYPE_STRUCT if !operands.is_empty() => {
+                    types.insert(operands[0], Type::Struct);
+                }
+                op::TYPE_POINTER if operands.len() >= 3 => {
+                    types.insert(operands[0], Type::Pointer(operands[2]));
+                }
+                op::VARIABLE if operands.len() >= 3 => {
+                    // (result type, result id, storage class)
+                    variables.push((operands[1], operands[0], operands[2]));
+                }
+                op::FUNCTION => in_function = true,
+                op::FUNCTION_END => in_function = false,
+                _ if in_function => {
+                    used.extend(pointer_operands(opcode, operands).iter().cloned());
+                }
+                _ => {}
+            }
+        }
+
+        let bindings = variables
+            .into_iter()
+            .filter(|&(variable, _, _)| used.contains(&variable))
+            .filter_map(|(variable, type_id, storage_class)| {
+                let descriptor_type = descriptor_type(&types, &buffer_blocks, type_id, storage_class)?;
+                Some(DescriptorBinding {
+                    set: *descriptor_sets.get(&variable)?,
+                    binding: *bindings.get(&variable)?,
+                    descriptor_type,
+                    variable,
+                })
+            })
+            .collect();
+
+        Some(ShaderReflection {
+            version,
+            entry_points,
+            bindings,
+        })
+    }
+
+    /// The descriptor bindings used by the given entry point, or `None` if the module does not
+    /// contain the entry point for the stage.
+    ///
+    /// Prior to SPIR-V 1.4, entry points do not declare the resource variables that they use, so
+    /// the bindings used by any function in the module are returned.
+    pub fn bindings(&self, entry_point: &str, stage: ShaderStage) -> Option<Vec<DescriptorBinding>> {
+        let execution_model = match stage {
+            ShaderStage::VERTEX => execution_model::VERTEX,
+            ShaderStage::FRAGMENT => execution_model::FRAGMENT,
+            ShaderStage::COMPUTE => execution_model::GL_COMPUTE,
+            _ => return None,
+        };
+
+        let entry_point = self
+            .entry_points
+            .iter()
+            .find(|e| e.name == entry_point && e.execution_model == execution_model)?;
+
+        let bindings = self
+            .bindings
+            .iter()
+            .filter(|binding| self.version < VERSION_1_4 || entry_point.interface.contains(&binding.variable))
+            .cloned()
+            .collect();
+
+        Some(bindings)
+    }
+}
+
+fn parse_string(words: &[u32]) -> Option<(String, usize)> {
+    let mut bytes = Vec::new();
+    for (i, word) in words.iter().enumerate() {
+        for &byte in word.to_le_bytes().iter() {
+            if byte == 0 {
+                return String::from_utf8(bytes).ok().map(|s| (s, i + 1));
+            }
+            bytes.push(byte);
+        }
+    }
+    None
+}
+
+/// The operands of an instruction that may reference a resource variable
+fn pointer_operands(opcode: u32, operands: &[u32]) -> &[u32] {
+    let range = match opcode {
+        op::STORE | op::ATOMIC_STORE => 0..1,
+        op::COPY_MEMORY | op::COPY_MEMORY_SIZED => 0..2,
+        op::FUNCTION_CALL => 3..operands.len(),
+        op::IMAGE_TEXEL_POINTER
+        | op::LOAD
+        | op::ACCESS_CHAIN
+        | op::IN_BOUNDS_ACCESS_CHAIN
+        | op::PTR_ACCESS_CHAIN
+        | op::IN_BOUNDS_PTR_ACCESS_CHAIN
+        | op::ARRAY_LENGTH
+        | op::COPY_OBJECT
+        | op::ATOMIC_LOAD..=op::ATOMIC_XOR => 2..3,
+        _ => 0..0,
+    };
+    operands.get(range).unwrap_or(&[])
+}
+
+fn descriptor_type(
+    types: &HashMap<u32, Type>,
+    buffer_blocks: &HashSet<u32>,
+    mut type_id: u32,
+    storage_class: u32,
+) -> Option<DescriptorType> {
+    loop {
+        match types.get(&type_id) {
+            Some(&Type::Pointer(inner)) | Some(&Type::Array(inner)) => type_id = inner,
+            _ => break,
+        }
+    }
+
+    let descriptor_type = match (types.get(&type_id)?, storage_class) {
+        (Type::Struct, storage_class::STORAGE_BUFFER) => DescriptorType::StorageBuffer,
+        (Type::Struct, storage_class::UNIFORM) if buffer_blocks.contains(&type_id) => DescriptorType::StorageBuffer,
+        (Type::Struct, storage_class::UNIFORM) => DescriptorType::UniformBuffer,
+        (_, storage_class::UNIFORM_CONSTANT) => match types.get(&type_id)? {
+            Type::Sampler => DescriptorType::Sampler,
+            Type::SampledImage => DescriptorType::CombinedTextureSampler,
+            &Type::Image { dim, sampled } => match (dim, sampled) {
+                (dim::SUBPASS_DATA, _) => DescriptorType::InputAttachment,
+                (dim::BUFFER, 2) => DescriptorType::StorageTexelBuffer,
+                (dim::BUFFER, _) => DescriptorType::UniformTexelBuffer,
+                (_, 2) => DescriptorType::StorageTexture,
+                _ => DescriptorType::SampledTexture,
+            },
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some(descriptor_type)
+}
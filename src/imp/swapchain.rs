@@ -1,10 +1,11 @@
 use crate::imp::fenced_deleter::DeleteWhenUnused;
-use crate::imp::texture::SubresourceUsageTracker;
+use crate::imp::texture::{Subresource, SubresourceUsageTracker};
 use crate::imp::{texture, AdapterInner, SurfaceInner, TextureViewInner};
 use crate::imp::{DeviceInner, InstanceInner, SwapchainInner, TextureInner};
 use crate::{
-    Error, Extent3d, Swapchain, SwapchainDescriptor, SwapchainError, SwapchainImage, Texture, TextureDescriptor,
-    TextureDimension, TextureUsage, TextureView,
+    Color, Error, Extent3d, LoadOp, RenderPassColorAttachmentDescriptor, RenderPassDepthStencilAttachmentDescriptor,
+    StoreOp, Swapchain, SwapchainDescriptor, SwapchainError, SwapchainImage, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureFormatFeatures, TextureUsage, TextureView, TextureViewDescriptor,
 };
 
 use ash::prelude::VkResult;
@@ -24,16 +25,154 @@ pub const COLOR_SPACE: vk::ColorSpaceKHR = vk::ColorSpaceKHR::SRGB_NONLINEAR;
 impl Swapchain {
     pub fn acquire_next_image(&self) -> Result<SwapchainImage, SwapchainError> {
         let image_index = self.inner.acquire_next_image_index()?;
-        Ok(SwapchainImage {
-            swapchain: Arc::clone(&self.inner),
-            texture: Texture {
-                inner: Arc::clone(&self.inner.textures[image_index as usize]),
-            },
-            view: TextureView {
-                inner: Arc::clone(&self.inner.views[image_index as usize]),
-            },
-            image_index,
-        })
+        let view = Arc::clone(&self.inner.views[image_index as usize]);
+        Ok(SwapchainInner::swapchain_image(&self.inner, image_index, view))
+    }
+
+    /// Acquires the next image, with `SwapchainImage::view` in `view_format` instead of the
+    /// swapchain format. This allows, e.g. switching between linear and sRGB output without
+    /// recreating the swapchain.
+    ///
+    /// The format must be the swapchain format or one of `SwapchainDescriptor::view_formats`.
+    pub fn acquire_next_image_with_view_format(
+        &self,
+        view_format: TextureFormat,
+    ) -> Result<SwapchainImage, SwapchainError> {
+        let views = if view_format == self.inner.textures[0].descriptor.format {
+            &self.inner.views
+        } else {
+            match self
+                .inner
+                .format_views
+                .iter()
+                .find(|(format, _)| *format == view_format)
+            {
+                Some((_, views)) => views,
+                None => {
+                    log::error!("swapchain was not created with view format: {:?}", view_format);
+                    return Err(SwapchainError::from(vk::Result::ERROR_FORMAT_NOT_SUPPORTED));
+                }
+            }
+        };
+        let image_index = self.inner.acquire_next_image_index()?;
+        let view = Arc::clone(&views[image_index as usize]);
+        Ok(SwapchainInner::swapchain_image(&self.inner, image_index, view))
+    }
+
+    /// The formats that the swapchain images can be viewed as, starting with the swapchain format
+    pub fn view_formats(&self) -> Vec<TextureFormat> {
+        let format = self.inner.textures[0].descriptor.format;
+        std::iter::once(format)
+            .chain(self.inner.format_views.iter().map(|(format, _)| *format))
+            .collect()
+    }
+
+    /// The number of images in the swapchain
+    pub fn image_count(&self) -> u32 {
+        self.inner.textures.len() as u32
+    }
+
+    /// The sample count of the color attachment returned by `SwapchainImage::color_attachment`
+    pub fn sample_count(&self) -> u32 {
+        self.inner.sample_count
+    }
+}
+
+impl SwapchainImage {
+    /// Returns a color attachment that renders into this image.
+    ///
+    /// If the swapchain was created with a `sample_count` greater than `1`, the attachment is the
+    /// swapchain's multisampled color target and this image is used as the resolve target.
+    pub fn color_attachment(&self, load_op: LoadOp, clear_color: Color) -> RenderPassColorAttachmentDescriptor {
+        let (attachment, resolve_target) = match self.multisampled_view.as_ref() {
+            Some(multisampled_view) => (multisampled_view, Some(&self.view)),
+            None => (&self.view, None),
+        };
+        RenderPassColorAttachmentDescriptor {
+            attachment,
+            resolve_target,
+            load_op,
+            store_op: StoreOp::Store,
+            clear_value: clear_color.into(),
+        }
+    }
+
+    /// Returns a depth stencil attachment for the swapchain's depth view, or `None` if the
+    /// swapchain was created without a `depth_format`.
+    pub fn depth_stencil_attachment(
+        &self,
+        load_op: LoadOp,
+        clear_depth: f32,
+        clear_stencil: u32,
+    ) -> Option<RenderPassDepthStencilAttachmentDescriptor> {
+        self.depth_view
+            .as_ref()
+            .map(|depth_view| RenderPassDepthStencilAttachmentDescriptor {
+                attachment: depth_view,
+                depth_load_op: load_op,
+                depth_store_op: StoreOp::Store,
+                clear_depth,
+                stencil_load_op: load_op,
+                stencil_store_op: StoreOp::Store,
+                clear_stencil,
+            })
+    }
+
+    /// Returns the image handle for libraries that record commands into the swapchain image
+    /// directly, e.g. a Vulkan UI backend. The image is owned by the swapchain.
+    ///
+    /// External commands must be submitted to the device queue after the `Queue::submit` that
+    /// waits for the image to be acquired. If they change the layout of the image, report the new
+    /// layout with `set_external_layout` before the image is used with vki or presented.
+    pub fn raw_image(&self) -> vk::Image {
+        self.texture.inner.handle
+    }
+
+    /// Returns the layout that vki expects the image to be in. External commands that use the
+    /// image must transition it from this layout.
+    pub fn current_layout(&self) -> vk::ImageLayout {
+        let subresource = Subresource {
+            mip_level: 0,
+            array_layer: 0,
+        };
+        let usage = self.texture.inner.subresource_usage.lock().usage(subresource);
+        texture::image_layout(usage, self.texture.inner.descriptor.format)
+    }
+
+    /// Informs the usage tracker that external commands transitioned the image to `layout`, so
+    /// that the next barrier recorded by vki transitions the image from it. Fails if vki does not
+    /// use `layout` for any of the usages that the swapchain was created with.
+    pub fn set_external_layout(&self, layout: vk::ImageLayout) -> Result<(), Error> {
+        let texture = &self.texture.inner;
+        let format = texture.descriptor.format;
+        let candidates = [
+            TextureUsage::NONE,
+            TextureUsage::PRESENT,
+            TextureUsage::OUTPUT_ATTACHMENT,
+            TextureUsage::COPY_SRC,
+            TextureUsage::COPY_DST,
+            TextureUsage::SAMPLED,
+            TextureUsage::STORAGE,
+        ];
+        let usage = candidates
+            .iter()
+            .cloned()
+            .filter(|usage| texture.descriptor.usage.contains(*usage))
+            .find(|usage| texture::image_layout(*usage, format) == layout);
+        match usage {
+            Some(usage) => {
+                texture.subresource_usage.lock().set_all(usage);
+                Ok(())
+            }
+            None => {
+                log::error!(
+                    "layout {:?} does not match a usage of the swapchain image: {:?}",
+                    layout,
+                    texture.descriptor.usage
+                );
+                Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT))
+            }
+        }
     }
 }
 
@@ -70,14 +209,48 @@ impl SwapchainInner {
             let surface_present_mode = surface_present_mode(instance, &device.adapter, surface_handle, preferred_mode)?;
 
             surface_format_check(&descriptor.surface.inner, physical_device, surface_format)?;
+            let supported_usage = supported_usage(&device.adapter, &surface_caps, descriptor.format);
+            if !supported_usage.contains(descriptor.usage) {
+                log::error!(
+                    "swapchain usage {:?} is not supported for format {:?}; supported usage: {:?}",
+                    descriptor.usage,
+                    descriptor.format,
+                    supported_usage
+                );
+                return Err(Error::from(vk::Result::ERROR_FORMAT_NOT_SUPPORTED));
+            }
             surface_image_usage_check(&surface_caps, surface_image_usage)?;
             surface_image_transform_check(&surface_caps, surface_image_transform)?;
 
+            validate_view_formats(&device, &descriptor)?;
+
             let old_swapchain_handle = old_swapchain.map(|s| s.handle).unwrap_or_else(vk::SwapchainKHR::null);
 
+            // The swapchain format is included in the list of formats that the images can be viewed as
+            let mut image_view_formats = vec![surface_format.format];
+            image_view_formats.extend(
+                descriptor
+                    .view_formats
+                    .iter()
+                    .map(|&format| texture::image_format(format)),
+            );
+            let image_format_list = vk::ImageFormatListCreateInfo {
+                view_format_count: image_view_formats.len() as u32,
+                p_view_formats: image_view_formats.as_ptr(),
+                ..Default::default()
+            };
+            let (flags, p_next) = if descriptor.view_formats.is_empty() {
+                (vk::SwapchainCreateFlagsKHR::empty(), std::ptr::null())
+            } else {
+                (
+                    vk::SwapchainCreateFlagsKHR::MUTABLE_FORMAT,
+                    &image_format_list as *const vk::ImageFormatListCreateInfo as *const _,
+                )
+            };
+
             let create_info = vk::SwapchainCreateInfoKHR {
                 s_type: StructureType::SWAPCHAIN_CREATE_INFO_KHR,
-                flags: vk::SwapchainCreateFlagsKHR::empty(),
+                flags,
                 surface: surface_handle,
                 min_image_count: surface_image_count,
                 image_format: surface_format.format,
@@ -93,7 +266,7 @@ impl SwapchainInner {
                 composite_alpha: vk::CompositeAlphaFlagsKHR::OPAQUE,
                 clipped: vk::TRUE,
                 old_swapchain: old_swapchain_handle,
-                p_next: std::ptr::null(),
+                p_next,
             };
 
             // TODO: Attempting to create a new swapchain for a surface will fail unless:
@@ -106,7 +279,10 @@ impl SwapchainInner {
                 state.get_fenced_deleter().purge_swapchains(&device);
             }
 
-            let swapchain = device.raw_ext.swapchain.create_swapchain(&create_info, None)?;
+            let swapchain = device
+                .raw_ext
+                .swapchain
+                .create_swapchain(&create_info, device.allocation_callbacks())?;
             log::debug!("created swapchain: {:?}", swapchain);
 
             let images = device.raw_ext.swapchain.get_swapchain_images(swapchain)?;
@@ -122,7 +298,7 @@ impl SwapchainInner {
                 sample_count: 1,
                 dimension: TextureDimension::D2,
                 format: descriptor.format,
-                usage: TextureUsage::PRESENT,
+                usage: TextureUsage::PRESENT | descriptor.usage,
             };
 
             let textures = images.iter().cloned().map(|handle| {
@@ -132,6 +308,7 @@ impl SwapchainInner {
                     device: device.clone(),
                     allocation: None,
                     allocation_info: None,
+                    sparse: None,
                     subresource_usage: Mutex::new(subresource_usage),
                     descriptor: texture_descriptor,
                 })
@@ -145,11 +322,72 @@ impl SwapchainInner {
                 views.push(Arc::new(view));
             }
 
+            let mut format_views = Vec::with_capacity(descriptor.view_formats.len());
+
+            for &view_format in descriptor.view_formats.iter() {
+                let mut views = Vec::with_capacity(textures.len());
+                for texture in textures.iter() {
+                    let view_descriptor = TextureViewDescriptor {
+                        format: view_format,
+                        ..texture::default_texture_view_descriptor(&texture)
+                    };
+                    let view = TextureViewInner::new(texture.clone(), view_descriptor)?;
+                    views.push(Arc::new(view));
+                }
+                format_views.push((view_format, views));
+            }
+
+            let multisampled_view = if descriptor.sample_count > 1 {
+                let multisampled_texture = TextureInner::new(
+                    device.clone(),
+                    TextureDescriptor {
+                        sample_count: descriptor.sample_count,
+                        usage: TextureUsage::OUTPUT_ATTACHMENT,
+                        ..texture_descriptor
+                    },
+                )?;
+                let multisampled_texture = Arc::new(multisampled_texture);
+                let view = TextureViewInner::new(
+                    multisampled_texture.clone(),
+                    texture::default_texture_view_descriptor(&multisampled_texture),
+                )?;
+                log::debug!("created multisampled color target: {:?}", descriptor.sample_count);
+                Some(Arc::new(view))
+            } else {
+                None
+            };
+
+            let depth_view = match descriptor.depth_format {
+                Some(depth_format) => {
+                    if !texture::is_depth_or_stencil(depth_format) {
+                        log::error!("swapchain depth_format is not a depth format: {:?}", depth_format);
+                        return Err(Error::from(vk::Result::ERROR_FORMAT_NOT_SUPPORTED));
+                    }
+                    let depth_texture = TextureInner::new(
+                        device.clone(),
+                        TextureDescriptor {
+                            sample_count: descriptor.sample_count.max(1),
+                            format: depth_format,
+                            usage: TextureUsage::OUTPUT_ATTACHMENT,
+                            ..texture_descriptor
+                        },
+                    )?;
+                    let depth_texture = Arc::new(depth_texture);
+                    let view = TextureViewInner::new(
+                        depth_texture.clone(),
+                        texture::default_texture_view_descriptor(&depth_texture),
+                    )?;
+                    log::debug!("created swapchain depth target: {:?}", depth_format);
+                    Some(Arc::new(view))
+                }
+                None => None,
+            };
+
             // initial transition
             let mut state = device.state.lock();
             let command_buffer = state.get_pending_command_buffer(&device)?;
             for texture in textures.iter() {
-                texture.transition_usage_now(command_buffer, texture.descriptor.usage, None)?;
+                texture.transition_usage_now(command_buffer, TextureUsage::PRESENT, None)?;
             }
             drop(state);
 
@@ -157,19 +395,47 @@ impl SwapchainInner {
                 handle: swapchain,
                 textures,
                 views,
+                format_views,
+                multisampled_view,
+                depth_view,
+                sample_count: descriptor.sample_count.max(1),
                 device,
                 surface: descriptor.surface.inner.clone(),
             })
         }
     }
 
+    fn swapchain_image(
+        swapchain: &Arc<SwapchainInner>,
+        image_index: u32,
+        view: Arc<TextureViewInner>,
+    ) -> SwapchainImage {
+        SwapchainImage {
+            swapchain: Arc::clone(swapchain),
+            texture: Texture {
+                inner: Arc::clone(&swapchain.textures[image_index as usize]),
+            },
+            view: TextureView { inner: view },
+            multisampled_view: swapchain.multisampled_view.as_ref().map(|view| TextureView {
+                inner: Arc::clone(view),
+            }),
+            depth_view: swapchain.depth_view.as_ref().map(|view| TextureView {
+                inner: Arc::clone(view),
+            }),
+            image_index,
+        }
+    }
+
     fn acquire_next_image_index(&self) -> Result<u32, SwapchainError> {
         unsafe {
             let timeout = Duration::from_millis(100);
             let timeout = timeout.as_nanos() as u64;
             let fence = vk::Fence::null();
             let create_info = vk::SemaphoreCreateInfo::builder();
-            let semaphore = self.device.raw.create_semaphore(&create_info, None)?;
+            let semaphore = self
+                .device
+                .raw
+                .create_semaphore(&create_info, self.device.allocation_callbacks())?;
             let result = self
                 .device
                 .raw_ext
@@ -203,7 +469,7 @@ impl SwapchainInner {
                         let mut state = self.device.state.lock();
                         let serial = state.get_next_pending_serial();
                         state.get_fenced_deleter().delete_when_unused(semaphore, serial);
-                        return Err(SwapchainError::Other(Error::from(err)));
+                        return Err(SwapchainError::from(err));
                     }
                 }
             }
@@ -304,6 +570,37 @@ pub fn surface_image_extent(
     extent
 }
 
+/// Returns the usage that swapchain images of the given format support on the surface. The
+/// `STORAGE` and `SAMPLED` usages allow the acquired image to be written by a compute shader or
+/// read for composition without an intermediate texture.
+pub fn supported_usage(
+    adapter: &AdapterInner,
+    surface_caps: &vk::SurfaceCapabilitiesKHR,
+    format: TextureFormat,
+) -> TextureUsage {
+    let flags = surface_caps.supported_usage_flags;
+    let features = adapter.get_texture_format_features(format);
+    let mut usage = TextureUsage::NONE;
+    if flags.contains(vk::ImageUsageFlags::TRANSFER_SRC) {
+        usage |= TextureUsage::COPY_SRC;
+    }
+    if flags.contains(vk::ImageUsageFlags::TRANSFER_DST) {
+        usage |= TextureUsage::COPY_DST;
+    }
+    if flags.contains(vk::ImageUsageFlags::SAMPLED) && features.contains(TextureFormatFeatures::SAMPLED) {
+        usage |= TextureUsage::SAMPLED;
+    }
+    if flags.contains(vk::ImageUsageFlags::STORAGE) && features.contains(TextureFormatFeatures::STORAGE) {
+        usage |= TextureUsage::STORAGE;
+    }
+    if flags.contains(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+        && features.contains(TextureFormatFeatures::COLOR_ATTACHMENT)
+    {
+        usage |= TextureUsage::OUTPUT_ATTACHMENT;
+    }
+    usage
+}
+
 /// Recipe: _Selecting desired usage scenarios of swapchain images_ (page `98`)
 pub fn surface_image_usage_check(
     surface_caps: &vk::SurfaceCapabilitiesKHR,
@@ -359,3 +656,31 @@ fn surface_format_check(
         )))
     }
 }
+
+fn validate_view_formats(device: &DeviceInner, descriptor: &SwapchainDescriptor) -> Result<(), Error> {
+    if descriptor.view_formats.is_empty() {
+        return Ok(());
+    }
+    if !device.extensions.swapchain_mutable_format {
+        log::error!("SwapchainDescriptor::view_formats requires Extensions::swapchain_mutable_format");
+        return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+    }
+    if descriptor.sample_count > 1 {
+        // The multisampled color target would need to be resolved into a view of a different format
+        log::error!("SwapchainDescriptor::view_formats can not be used with a sample_count greater than 1");
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+    for &view_format in descriptor.view_formats.iter() {
+        if texture::is_depth_or_stencil(view_format)
+            || texture::pixel_size(view_format) != texture::pixel_size(descriptor.format)
+        {
+            log::error!(
+                "swapchain view format {:?} is not compatible with format {:?}",
+                view_format,
+                descriptor.format
+            );
+            return Err(Error::from(vk::Result::ERROR_FORMAT_NOT_SUPPORTED));
+        }
+    }
+    Ok(())
+}
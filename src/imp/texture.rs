@@ -1,17 +1,16 @@
 use ash::vk;
 
 use crate::imp::fenced_deleter::DeleteWhenUnused;
-use crate::imp::{render_pass, util};
-use crate::imp::{DeviceInner, TextureInner, TextureViewInner};
+use crate::imp::{render_pass, sparse, util};
+use crate::imp::{DeviceInner, SparsePagePoolInner, TextureInner, TextureViewInner};
 use crate::{
-    Error, Extent3d, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage, TextureView,
-    TextureViewDescriptor, TextureViewDimension,
+    Error, Extensions, Extent3d, SparsePagePool, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureFormatFeatures, TextureUsage, TextureView, TextureViewDescriptor, TextureViewDimension,
 };
 
 use ash::vk::MemoryPropertyFlags;
 use parking_lot::Mutex;
 use smallvec::SmallVec;
-use std::collections::HashMap;
 use std::sync::Arc;
 use vk_mem::{AllocationCreateFlags, AllocationCreateInfo, MemoryUsage};
 
@@ -46,6 +45,27 @@ pub fn is_depth_or_stencil(format: TextureFormat) -> bool {
     is_depth(format) || is_stencil(format)
 }
 
+/// Returns `true` for unsigned and signed integer color formats, which do not support blending.
+pub fn is_integer(format: TextureFormat) -> bool {
+    match format {
+        TextureFormat::R8Uint
+        | TextureFormat::R8Sint
+        | TextureFormat::R8G8Uint
+        | TextureFormat::R16Uint
+        | TextureFormat::R8G8B8A8Sint
+        | TextureFormat::R8G8B8A8Uint
+        | TextureFormat::R32Uint
+        | TextureFormat::R32Sint
+        | TextureFormat::RGBA16Uint
+        | TextureFormat::RGBA16Sint
+        | TextureFormat::RG32Uint
+        | TextureFormat::RG32Sint
+        | TextureFormat::RGBA32Uint
+        | TextureFormat::RGBA32Sint => true,
+        _ => false,
+    }
+}
+
 pub fn image_type(dimension: TextureDimension) -> vk::ImageType {
     // TODO: arrays?
     match dimension {
@@ -116,17 +136,44 @@ pub fn image_format(format: TextureFormat) -> vk::Format {
         TextureFormat::B8G8R8A8Unorm => vk::Format::B8G8R8A8_UNORM,
         TextureFormat::B8G8R8A8UnormSRGB => vk::Format::B8G8R8A8_SRGB,
         TextureFormat::R16G16Unorm => vk::Format::R16G16_UNORM,
+        TextureFormat::R32Uint => vk::Format::R32_UINT,
+        TextureFormat::R32Sint => vk::Format::R32_SINT,
 
         TextureFormat::RGBA16Float => vk::Format::R16G16B16A16_SFLOAT,
         TextureFormat::RGBA16Sint => vk::Format::R16G16B16A16_SINT,
         TextureFormat::RGBA16Uint => vk::Format::R16G16B16A16_UINT,
         TextureFormat::RGBA16Snorm => vk::Format::R16G16B16A16_SNORM,
         TextureFormat::RGBA16Unorm => vk::Format::R16G16B16A16_UNORM,
+        TextureFormat::RG32Uint => vk::Format::R32G32_UINT,
+        TextureFormat::RG32Sint => vk::Format::R32G32_SINT,
 
         TextureFormat::RGBA32Float => vk::Format::R32G32B32A32_SFLOAT,
+        TextureFormat::RGBA32Uint => vk::Format::R32G32B32A32_UINT,
+        TextureFormat::RGBA32Sint => vk::Format::R32G32B32A32_SINT,
 
         TextureFormat::D32Float => vk::Format::D32_SFLOAT,
         TextureFormat::D32FloatS8Uint => vk::Format::D32_SFLOAT_S8_UINT,
+
+        TextureFormat::BC1RGBAUnorm => vk::Format::BC1_RGBA_UNORM_BLOCK,
+        TextureFormat::BC1RGBAUnormSRGB => vk::Format::BC1_RGBA_SRGB_BLOCK,
+        TextureFormat::BC2RGBAUnorm => vk::Format::BC2_UNORM_BLOCK,
+        TextureFormat::BC2RGBAUnormSRGB => vk::Format::BC2_SRGB_BLOCK,
+        TextureFormat::BC3RGBAUnorm => vk::Format::BC3_UNORM_BLOCK,
+        TextureFormat::BC3RGBAUnormSRGB => vk::Format::BC3_SRGB_BLOCK,
+        TextureFormat::BC4RUnorm => vk::Format::BC4_UNORM_BLOCK,
+        TextureFormat::BC4RSnorm => vk::Format::BC4_SNORM_BLOCK,
+        TextureFormat::BC5RGUnorm => vk::Format::BC5_UNORM_BLOCK,
+        TextureFormat::BC5RGSnorm => vk::Format::BC5_SNORM_BLOCK,
+        TextureFormat::BC6HRGBUfloat => vk::Format::BC6H_UFLOAT_BLOCK,
+        TextureFormat::BC6HRGBSfloat => vk::Format::BC6H_SFLOAT_BLOCK,
+        TextureFormat::BC7RGBAUnorm => vk::Format::BC7_UNORM_BLOCK,
+        TextureFormat::BC7RGBAUnormSRGB => vk::Format::BC7_SRGB_BLOCK,
+
+        TextureFormat::ETC2RGBA8Unorm => vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK,
+        TextureFormat::ETC2RGBA8UnormSRGB => vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK,
+
+        TextureFormat::ASTC4x4Unorm => vk::Format::ASTC_4X4_UNORM_BLOCK,
+        TextureFormat::ASTC4x4UnormSRGB => vk::Format::ASTC_4X4_SRGB_BLOCK,
     }
 }
 
@@ -138,6 +185,51 @@ pub fn texture_format(format: vk::Format) -> Option<TextureFormat> {
         vk::Format::R8G8B8A8_UNORM => TextureFormat::R8G8B8A8Unorm,
         vk::Format::R16_UNORM => TextureFormat::R16Unorm,
         vk::Format::R16_UINT => TextureFormat::R16Uint,
+
+        vk::Format::R8_UNORM => TextureFormat::R8Unorm,
+        vk::Format::R8_SRGB => TextureFormat::R8UnormSRGB,
+        vk::Format::R8_SNORM => TextureFormat::R8Snorm,
+        vk::Format::R8_UINT => TextureFormat::R8Uint,
+        vk::Format::R8_SINT => TextureFormat::R8Sint,
+        vk::Format::R8G8_UNORM => TextureFormat::R8G8Unorm,
+        vk::Format::R8G8_UINT => TextureFormat::R8G8Uint,
+        vk::Format::R8G8B8A8_SNORM => TextureFormat::R8G8B8A8Snorm,
+        vk::Format::R8G8B8A8_SINT => TextureFormat::R8G8B8A8Sint,
+        vk::Format::R8G8B8A8_UINT => TextureFormat::R8G8B8A8Uint,
+        vk::Format::R16G16_UNORM => TextureFormat::R16G16Unorm,
+        vk::Format::R32_UINT => TextureFormat::R32Uint,
+        vk::Format::R32_SINT => TextureFormat::R32Sint,
+        vk::Format::R16G16B16A16_SFLOAT => TextureFormat::RGBA16Float,
+        vk::Format::R16G16B16A16_SINT => TextureFormat::RGBA16Sint,
+        vk::Format::R16G16B16A16_UINT => TextureFormat::RGBA16Uint,
+        vk::Format::R16G16B16A16_SNORM => TextureFormat::RGBA16Snorm,
+        vk::Format::R16G16B16A16_UNORM => TextureFormat::RGBA16Unorm,
+        vk::Format::R32G32_UINT => TextureFormat::RG32Uint,
+        vk::Format::R32G32_SINT => TextureFormat::RG32Sint,
+        vk::Format::R32G32B32A32_SFLOAT => TextureFormat::RGBA32Float,
+        vk::Format::R32G32B32A32_UINT => TextureFormat::RGBA32Uint,
+        vk::Format::R32G32B32A32_SINT => TextureFormat::RGBA32Sint,
+        vk::Format::D32_SFLOAT => TextureFormat::D32Float,
+        vk::Format::D32_SFLOAT_S8_UINT => TextureFormat::D32FloatS8Uint,
+
+        vk::Format::BC1_RGBA_UNORM_BLOCK => TextureFormat::BC1RGBAUnorm,
+        vk::Format::BC1_RGBA_SRGB_BLOCK => TextureFormat::BC1RGBAUnormSRGB,
+        vk::Format::BC2_UNORM_BLOCK => TextureFormat::BC2RGBAUnorm,
+        vk::Format::BC2_SRGB_BLOCK => TextureFormat::BC2RGBAUnormSRGB,
+        vk::Format::BC3_UNORM_BLOCK => TextureFormat::BC3RGBAUnorm,
+        vk::Format::BC3_SRGB_BLOCK => TextureFormat::BC3RGBAUnormSRGB,
+        vk::Format::BC4_UNORM_BLOCK => TextureFormat::BC4RUnorm,
+        vk::Format::BC4_SNORM_BLOCK => TextureFormat::BC4RSnorm,
+        vk::Format::BC5_UNORM_BLOCK => TextureFormat::BC5RGUnorm,
+        vk::Format::BC5_SNORM_BLOCK => TextureFormat::BC5RGSnorm,
+        vk::Format::BC6H_UFLOAT_BLOCK => TextureFormat::BC6HRGBUfloat,
+        vk::Format::BC6H_SFLOAT_BLOCK => TextureFormat::BC6HRGBSfloat,
+        vk::Format::BC7_UNORM_BLOCK => TextureFormat::BC7RGBAUnorm,
+        vk::Format::BC7_SRGB_BLOCK => TextureFormat::BC7RGBAUnormSRGB,
+        vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK => TextureFormat::ETC2RGBA8Unorm,
+        vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK => TextureFormat::ETC2RGBA8UnormSRGB,
+        vk::Format::ASTC_4X4_UNORM_BLOCK => TextureFormat::ASTC4x4Unorm,
+        vk::Format::ASTC_4X4_SRGB_BLOCK => TextureFormat::ASTC4x4UnormSRGB,
         _ => {
             log::warn!("missing texture format conversion: {:?}", format);
             return None;
@@ -167,7 +259,9 @@ pub fn pixel_size(format: TextureFormat) -> u32 {
         TextureFormat::R8G8B8A8Uint |
         TextureFormat::B8G8R8A8Unorm |
         TextureFormat::B8G8R8A8UnormSRGB |
-        TextureFormat::R16G16Unorm
+        TextureFormat::R16G16Unorm |
+        TextureFormat::R32Uint |
+        TextureFormat::R32Sint
         => 4,
         TextureFormat::D32Float
         => 4,
@@ -175,7 +269,9 @@ pub fn pixel_size(format: TextureFormat) -> u32 {
         TextureFormat::RGBA16Sint |
         TextureFormat::RGBA16Uint |
         TextureFormat::RGBA16Snorm |
-        TextureFormat::RGBA16Unorm
+        TextureFormat::RGBA16Unorm |
+        TextureFormat::RG32Uint |
+        TextureFormat::RG32Sint
         => 8,
         // TODO: D32FloatS8Uint
         // Dawn has this as "8", but the Vulkan spec states:
@@ -186,8 +282,130 @@ pub fn pixel_size(format: TextureFormat) -> u32 {
         //
         TextureFormat::D32FloatS8Uint
         => 5,
-        TextureFormat::RGBA32Float
-        => 32,
+        TextureFormat::RGBA32Float |
+        TextureFormat::RGBA32Uint |
+        TextureFormat::RGBA32Sint
+        => 16,
+        // Compressed formats report the size of a single block
+        TextureFormat::BC1RGBAUnorm |
+        TextureFormat::BC1RGBAUnormSRGB |
+        TextureFormat::BC4RUnorm |
+        TextureFormat::BC4RSnorm
+        => 8,
+        TextureFormat::BC2RGBAUnorm |
+        TextureFormat::BC2RGBAUnormSRGB |
+        TextureFormat::BC3RGBAUnorm |
+        TextureFormat::BC3RGBAUnormSRGB |
+        TextureFormat::BC5RGUnorm |
+        TextureFormat::BC5RGSnorm |
+        TextureFormat::BC6HRGBUfloat |
+        TextureFormat::BC6HRGBSfloat |
+        TextureFormat::BC7RGBAUnorm |
+        TextureFormat::BC7RGBAUnormSRGB |
+        TextureFormat::ETC2RGBA8Unorm |
+        TextureFormat::ETC2RGBA8UnormSRGB |
+        TextureFormat::ASTC4x4Unorm |
+        TextureFormat::ASTC4x4UnormSRGB
+        => 16,
+    }
+}
+
+pub fn is_bc_compressed(format: TextureFormat) -> bool {
+    match format {
+        TextureFormat::BC1RGBAUnorm
+        | TextureFormat::BC1RGBAUnormSRGB
+        | TextureFormat::BC2RGBAUnorm
+        | TextureFormat::BC2RGBAUnormSRGB
+        | TextureFormat::BC3RGBAUnorm
+        | TextureFormat::BC3RGBAUnormSRGB
+        | TextureFormat::BC4RUnorm
+        | TextureFormat::BC4RSnorm
+        | TextureFormat::BC5RGUnorm
+        | TextureFormat::BC5RGSnorm
+        | TextureFormat::BC6HRGBUfloat
+        | TextureFormat::BC6HRGBSfloat
+        | TextureFormat::BC7RGBAUnorm
+        | TextureFormat::BC7RGBAUnormSRGB => true,
+        _ => false,
+    }
+}
+
+pub fn is_etc2_compressed(format: TextureFormat) -> bool {
+    match format {
+        TextureFormat::ETC2RGBA8Unorm | TextureFormat::ETC2RGBA8UnormSRGB => true,
+        _ => false,
+    }
+}
+
+pub fn is_astc_compressed(format: TextureFormat) -> bool {
+    match format {
+        TextureFormat::ASTC4x4Unorm | TextureFormat::ASTC4x4UnormSRGB => true,
+        _ => false,
+    }
+}
+
+pub fn is_compressed(format: TextureFormat) -> bool {
+    is_bc_compressed(format) || is_etc2_compressed(format) || is_astc_compressed(format)
+}
+
+/// Returns `true` if the extension required by the format (if any) is enabled
+pub fn is_format_enabled(format: TextureFormat, extensions: &Extensions) -> bool {
+    if is_bc_compressed(format) {
+        extensions.texture_compression_bc
+    } else if is_etc2_compressed(format) {
+        extensions.texture_compression_etc2
+    } else if is_astc_compressed(format) {
+        extensions.texture_compression_astc_ldr
+    } else {
+        true
+    }
+}
+
+pub fn texture_format_features(flags: vk::FormatFeatureFlags) -> TextureFormatFeatures {
+    let mut features = TextureFormatFeatures::NONE;
+
+    if flags.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE) {
+        features |= TextureFormatFeatures::SAMPLED;
+    }
+
+    if flags.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR) {
+        features |= TextureFormatFeatures::SAMPLED_LINEAR_FILTER;
+    }
+
+    if flags.contains(vk::FormatFeatureFlags::STORAGE_IMAGE) {
+        features |= TextureFormatFeatures::STORAGE;
+    }
+
+    if flags.contains(vk::FormatFeatureFlags::COLOR_ATTACHMENT) {
+        features |= TextureFormatFeatures::COLOR_ATTACHMENT;
+    }
+
+    if flags.contains(vk::FormatFeatureFlags::COLOR_ATTACHMENT_BLEND) {
+        features |= TextureFormatFeatures::COLOR_ATTACHMENT_BLEND;
+    }
+
+    if flags.contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT) {
+        features |= TextureFormatFeatures::DEPTH_STENCIL_ATTACHMENT;
+    }
+
+    if flags.contains(vk::FormatFeatureFlags::BLIT_SRC) {
+        features |= TextureFormatFeatures::BLIT_SRC;
+    }
+
+    if flags.contains(vk::FormatFeatureFlags::BLIT_DST) {
+        features |= TextureFormatFeatures::BLIT_DST;
+    }
+
+    features
+}
+
+/// Returns the width and height of a single texel block. Uncompressed formats have a block
+/// size of `1x1`.
+pub fn block_dimensions(format: TextureFormat) -> (u32, u32) {
+    if is_compressed(format) {
+        (4, 4)
+    } else {
+        (1, 1)
     }
 }
 
@@ -345,14 +563,208 @@ pub fn default_texture_view_descriptor(texture: &TextureInner) -> TextureViewDes
     }
 }
 
-fn allocation_flags(format: TextureFormat) -> AllocationCreateFlags {
+/// Estimates the size of the first mip level, in bytes.
+fn estimated_size_bytes(descriptor: &TextureDescriptor) -> u64 {
+    let (block_width, block_height) = block_dimensions(descriptor.format);
+    let blocks_wide = (descriptor.size.width + block_width - 1) / block_width;
+    let blocks_high = (descriptor.size.height + block_height - 1) / block_height;
+    u64::from(blocks_wide)
+        * u64::from(blocks_high)
+        * u64::from(descriptor.size.depth)
+        * u64::from(descriptor.array_layer_count)
+        * u64::from(descriptor.sample_count)
+        * u64::from(pixel_size(descriptor.format))
+}
+
+fn allocation_flags(descriptor: &TextureDescriptor) -> AllocationCreateFlags {
     let mut flags = AllocationCreateFlags::NONE;
-    if is_depth_or_stencil(format) {
+    if is_depth_or_stencil(descriptor.format)
+        || estimated_size_bytes(descriptor) >= util::DEDICATED_ALLOCATION_THRESHOLD
+    {
         flags |= AllocationCreateFlags::DEDICATED_MEMORY;
     }
     flags
 }
 
+fn image_create_info(
+    descriptor: &TextureDescriptor,
+    mut flags: vk::ImageCreateFlags,
+) -> Result<vk::ImageCreateInfo, Error> {
+    if descriptor.array_layer_count >= 6 && descriptor.size.width == descriptor.size.height {
+        flags |= vk::ImageCreateFlags::CUBE_COMPATIBLE;
+    }
+
+    Ok(vk::ImageCreateInfo {
+        flags,
+        image_type: image_type(descriptor.dimension),
+        format: image_format(descriptor.format),
+        extent: util::extent_3d(descriptor.size),
+        mip_levels: descriptor.mip_level_count,
+        array_layers: descriptor.array_layer_count,
+        samples: render_pass::sample_count_flags(descriptor.sample_count)?,
+        tiling: vk::ImageTiling::OPTIMAL,
+        usage: image_usage(descriptor.usage, descriptor.format),
+        sharing_mode: vk::SharingMode::EXCLUSIVE,
+        initial_layout: vk::ImageLayout::UNDEFINED,
+        ..Default::default()
+    })
+}
+
+fn max_mip_level_count(size: Extent3d, dimension: TextureDimension) -> u32 {
+    let max_dimension = match dimension {
+        TextureDimension::D1 => size.width,
+        TextureDimension::D2 => size.width.max(size.height),
+        TextureDimension::D3 => size.width.max(size.height).max(size.depth),
+    };
+    32 - max_dimension.leading_zeros()
+}
+
+fn validate_texture_descriptor(
+    device: &DeviceInner,
+    descriptor: &TextureDescriptor,
+    create_info: &vk::ImageCreateInfo,
+) -> Result<(), Error> {
+    let size = descriptor.size;
+
+    if size.width == 0 || size.height == 0 || size.depth == 0 {
+        log::error!("texture size must not be zero: {:?}", size);
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    if descriptor.array_layer_count == 0 || descriptor.mip_level_count == 0 {
+        log::error!(
+            "texture array_layer_count and mip_level_count must not be zero (array_layer_count: {}, mip_level_count: {})",
+            descriptor.array_layer_count,
+            descriptor.mip_level_count
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    match descriptor.dimension {
+        TextureDimension::D1 if size.height != 1 || size.depth != 1 => {
+            log::error!("1D textures must have a height and depth of 1: {:?}", size);
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+        TextureDimension::D2 if size.depth != 1 => {
+            log::error!("2D textures must have a depth of 1: {:?}", size);
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+        TextureDimension::D3 if descriptor.array_layer_count != 1 => {
+            log::error!(
+                "3D textures must have an array_layer_count of 1: {}",
+                descriptor.array_layer_count
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+        _ => {}
+    }
+
+    let max_mip_level_count = max_mip_level_count(size, descriptor.dimension);
+    if descriptor.mip_level_count > max_mip_level_count {
+        log::error!(
+            "texture mip_level_count ({}) exceeds the maximum ({}) for size: {:?}",
+            descriptor.mip_level_count,
+            max_mip_level_count,
+            size
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    if descriptor.sample_count > 1 {
+        if descriptor.dimension != TextureDimension::D2 || descriptor.mip_level_count != 1 {
+            log::error!(
+                "multisampled textures must be 2D with a mip_level_count of 1 (dimension: {:?}, mip_level_count: {})",
+                descriptor.dimension,
+                descriptor.mip_level_count
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+        if is_compressed(descriptor.format) {
+            log::error!("compressed textures cannot be multisampled: {:?}", descriptor.format);
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
+    }
+
+    let features = device.adapter.get_texture_format_features(descriptor.format);
+    let required_features = [
+        (TextureUsage::SAMPLED, TextureFormatFeatures::SAMPLED),
+        (TextureUsage::STORAGE, TextureFormatFeatures::STORAGE),
+        (
+            TextureUsage::OUTPUT_ATTACHMENT,
+            if is_depth_or_stencil(descriptor.format) {
+                TextureFormatFeatures::DEPTH_STENCIL_ATTACHMENT
+            } else {
+                TextureFormatFeatures::COLOR_ATTACHMENT
+            },
+        ),
+    ];
+    for &(usage, required) in required_features.iter() {
+        if descriptor.usage.contains(usage) && !features.contains(required) {
+            log::error!(
+                "texture usage {:?} is not supported for format: {:?}",
+                usage,
+                descriptor.format
+            );
+            return Err(Error::from(vk::Result::ERROR_FORMAT_NOT_SUPPORTED));
+        }
+    }
+
+    let properties = unsafe {
+        device
+            .adapter
+            .instance
+            .raw
+            .get_physical_device_image_format_properties(
+                device.adapter.physical_device,
+                create_info.format,
+                create_info.image_type,
+                create_info.tiling,
+                create_info.usage,
+                create_info.flags,
+            )
+            .map_err(|e| {
+                log::error!(
+                    "texture format {:?} is not supported with usage: {:?} ({:?})",
+                    descriptor.format,
+                    descriptor.usage,
+                    e
+                );
+                Error::from(e)
+            })?
+    };
+
+    let max_extent = properties.max_extent;
+    if size.width > max_extent.width || size.height > max_extent.height || size.depth > max_extent.depth {
+        log::error!(
+            "texture size {:?} exceeds the maximum supported extent: {:?}",
+            size,
+            max_extent
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    if descriptor.array_layer_count > properties.max_array_layers {
+        log::error!(
+            "texture array_layer_count ({}) exceeds the maximum supported: {}",
+            descriptor.array_layer_count,
+            properties.max_array_layers
+        );
+        return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+    }
+
+    if !properties.sample_counts.contains(create_info.samples) {
+        log::error!(
+            "texture sample_count {} is not supported for format {:?} with usage: {:?}",
+            descriptor.sample_count,
+            descriptor.format,
+            descriptor.usage
+        );
+        return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+    }
+
+    Ok(())
+}
+
 impl Texture {
     pub fn create_view(&self, descriptor: TextureViewDescriptor) -> Result<TextureView, Error> {
         let texture_view = TextureViewInner::new(self.inner.clone(), descriptor)?;
@@ -372,30 +784,49 @@ impl Texture {
     pub fn mip_level_count(&self) -> u32 {
         self.inner.descriptor.mip_level_count
     }
+
+    pub fn array_layer_count(&self) -> u32 {
+        self.inner.descriptor.array_layer_count
+    }
+
+    /// Returns the usage flags declared when the texture (or swapchain) was created.
+    pub fn usage(&self) -> TextureUsage {
+        self.inner.descriptor.usage
+    }
+
+    /// Returns the size of a sparse tile in texels, or `None` if the texture isn't sparse.
+    pub fn sparse_tile_size(&self) -> Option<Extent3d> {
+        self.inner.sparse.as_ref().map(|sparse| sparse.tile_size)
+    }
+
+    /// Returns the first mip level of the mip tail, or `None` if the texture isn't sparse.
+    ///
+    /// The mip tail is always resident; only the tiles of the mip levels below it may be bound.
+    pub fn sparse_mip_tail_first_level(&self) -> Option<u32> {
+        self.inner.sparse.as_ref().map(|sparse| sparse.mip_tail_first_level)
+    }
+
+    /// Creates a pool of `page_count` pages, each large enough to back a single tile of this
+    /// texture. The pool may back any sparse texture with the same format and usage.
+    pub fn create_sparse_page_pool(&self, page_count: u32) -> Result<SparsePagePool, Error> {
+        let page_pool = SparsePagePoolInner::new(&self.inner, page_count)?;
+        Ok(page_pool.into())
+    }
 }
 
 impl TextureInner {
     pub fn new(device: Arc<DeviceInner>, descriptor: TextureDescriptor) -> Result<TextureInner, Error> {
-        let flags = if descriptor.array_layer_count >= 6 && descriptor.size.width == descriptor.size.height {
-            vk::ImageCreateFlags::CUBE_COMPATIBLE
-        } else {
-            vk::ImageCreateFlags::empty()
-        };
+        if !is_format_enabled(descriptor.format, &device.extensions) {
+            log::error!(
+                "texture format requires an extension that is not enabled: {:?}",
+                descriptor.format
+            );
+            return Err(Error::from(vk::Result::ERROR_FORMAT_NOT_SUPPORTED));
+        }
 
-        let create_info = vk::ImageCreateInfo {
-            flags,
-            image_type: image_type(descriptor.dimension),
-            format: image_format(descriptor.format),
-            extent: util::extent_3d(descriptor.size),
-            mip_levels: descriptor.mip_level_count,
-            array_layers: descriptor.array_layer_count,
-            samples: render_pass::sample_count_flags(descriptor.sample_count)?,
-            tiling: vk::ImageTiling::OPTIMAL,
-            usage: image_usage(descriptor.usage, descriptor.format),
-            sharing_mode: vk::SharingMode::EXCLUSIVE,
-            initial_layout: vk::ImageLayout::UNDEFINED,
-            ..Default::default()
-        };
+        let create_info = image_create_info(&descriptor, vk::ImageCreateFlags::empty())?;
+
+        validate_texture_descriptor(&device, &descriptor, &create_info)?;
 
         let allocator = &device.allocator;
         let allocation_create_info = AllocationCreateInfo {
@@ -403,7 +834,7 @@ impl TextureInner {
             user_data: None,
             required_flags: MemoryPropertyFlags::empty(),
             preferred_flags: MemoryPropertyFlags::empty(),
-            flags: allocation_flags(descriptor.format),
+            flags: allocation_flags(&descriptor),
             memory_type_bits: 0,
             usage: memory_usage(descriptor.usage),
         };
@@ -414,27 +845,100 @@ impl TextureInner {
             allocation_create_info
         );
 
-        let result = allocator.create_image(&create_info, &allocation_create_info);
+        // The descriptor was validated above, so VMA's opaque pre-check failures shouldn't be
+        // reachable here.
+        let (image, allocation, allocation_info) = allocator.create_image(&create_info, &allocation_create_info)?;
+
+        log::trace!("created image: {:?}, allocation_info: {:?}", image, allocation_info);
+
+        let subresource_usage = SubresourceUsageTracker::new(
+            descriptor.mip_level_count,
+            descriptor.array_layer_count,
+            descriptor.format,
+        );
+
+        Ok(TextureInner {
+            handle: image,
+            device: device.clone(),
+            allocation: Some(allocation),
+            allocation_info: Some(allocation_info),
+            sparse: None,
+            descriptor,
+            subresource_usage: Mutex::new(subresource_usage),
+        })
+    }
+
+    /// Creates a sparse texture. The tiles of the texture aren't backed by memory until they're
+    /// bound to pages with `Queue::bind_sparse`. The mip tail is bound when the texture is created.
+    pub fn new_sparse(device: Arc<DeviceInner>, descriptor: TextureDescriptor) -> Result<TextureInner, Error> {
+        if !device.supports_sparse_residency() {
+            log::error!("sparse textures are not supported by the device");
+            return Err(Error::from(vk::Result::ERROR_FEATURE_NOT_PRESENT));
+        }
+
+        if !is_format_enabled(descriptor.format, &device.extensions) {
+            log::error!(
+                "texture format requires an extension that is not enabled: {:?}",
+                descriptor.format
+            );
+            return Err(Error::from(vk::Result::ERROR_FORMAT_NOT_SUPPORTED));
+        }
+
+        let is_color = !is_depth_or_stencil(descriptor.format);
+        if !is_color || descriptor.dimension != TextureDimension::D2 || descriptor.sample_count != 1 {
+            log::error!(
+                "sparse textures must be single-sampled 2D color textures (format: {:?}, dimension: {:?}, sample_count: {})",
+                descriptor.format,
+                descriptor.dimension,
+                descriptor.sample_count
+            );
+            return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+        }
 
-        // VMA does some basic pre-checks on the input, but doesn't provide any info around why the
-        // input was invalid and just returns `VK_ERROR_VALIDAITON_FAILED_EXT` without triggering
-        // any validation messages (it doesn't call `vkCreateImage` if it's pre-checks fail).
-        // So, in the event of a validation error, we'll create a dummy image that triggers the
-        // validation message (so we can see what exactly the problem was) and then destroy it
-        // immediately.
-        if let Err(ref e) = &result {
-            if let vk_mem::ErrorKind::Vulkan(vk::Result::ERROR_VALIDATION_FAILED_EXT) = e.kind() {
+        let sparse_flags = vk::ImageCreateFlags::SPARSE_BINDING | vk::ImageCreateFlags::SPARSE_RESIDENCY;
+        let create_info = image_create_info(&descriptor, sparse_flags)?;
+
+        validate_texture_descriptor(&device, &descriptor, &create_info)?;
+
+        let sparse_format_properties = unsafe {
+            device
+                .adapter
+                .instance
+                .raw
+                .get_physical_device_sparse_image_format_properties(
+                    device.adapter.physical_device,
+                    create_info.format,
+                    create_info.image_type,
+                    create_info.samples,
+                    create_info.usage,
+                    create_info.tiling,
+                )
+        };
+
+        if sparse_format_properties.is_empty() {
+            log::error!(
+                "sparse textures are not supported for format {:?} with usage: {:?}",
+                descriptor.format,
+                descriptor.usage
+            );
+            return Err(Error::from(vk::Result::ERROR_FORMAT_NOT_SUPPORTED));
+        }
+
+        log::trace!("sparse image create_info: {:?}", create_info);
+
+        let image = unsafe { device.raw.create_image(&create_info, device.allocation_callbacks())? };
+
+        let sparse = match sparse::bind_mip_tail(&device, image, &descriptor) {
+            Ok(sparse) => sparse,
+            Err(e) => {
                 unsafe {
-                    let dummy = device.raw.create_image(&create_info, None)?;
-                    device.raw.destroy_image(dummy, None);
-                    return Err(Error::from(vk::Result::ERROR_VALIDATION_FAILED_EXT));
+                    device.raw.destroy_image(image, device.allocation_callbacks());
                 }
+                return Err(e);
             }
-        }
-
-        let (image, allocation, allocation_info) = result.expect("failed to create image"); // TODO
+        };
 
-        log::trace!("created image: {:?}, allocation_info: {:?}", image, allocation_info);
+        log::trace!("created sparse image: {:?}, sparse: {:?}", image, sparse);
 
         let subresource_usage = SubresourceUsageTracker::new(
             descriptor.mip_level_count,
@@ -445,8 +949,9 @@ impl TextureInner {
         Ok(TextureInner {
             handle: image,
             device: device.clone(),
-            allocation: Some(allocation),
-            allocation_info: Some(allocation_info),
+            allocation: None,
+            allocation_info: None,
+            sparse: Some(sparse),
             descriptor,
             subresource_usage: Mutex::new(subresource_usage),
         })
@@ -468,10 +973,6 @@ impl TextureInner {
         let dst_stage_mask = pipeline_stage(usage, format);
 
         let mut add_image_memory_barrier = |range: vk::ImageSubresourceRange, range_last_usage: &mut TextureUsage| {
-            // TODO: Add a version of this optimization back at the "whole texture" level.
-            //       Example: If we're only repeatedly requesting that the image is SAMPLED,
-            //       there's no need to iterate all of the subresources every time.
-
             let last_read_only = (*range_last_usage & read_only_texture_usage()) == *range_last_usage;
             if last_read_only && *range_last_usage == usage {
                 return;
@@ -530,9 +1031,17 @@ impl TextureInner {
                 add_image_memory_barrier(range, range_last_usage);
             }
             None => {
-                for (range, range_last_usage) in self.subresource_usage.lock().iter_mut() {
-                    add_image_memory_barrier(range, range_last_usage);
+                let mut subresource_usage = self.subresource_usage.lock();
+                // If we're only repeatedly requesting that the image is SAMPLED (or any other read-only
+                // usage), there's no need to iterate all of the subresources every time.
+                if let Some(whole_usage) = subresource_usage.whole_usage {
+                    let whole_read_only = (whole_usage & read_only_texture_usage()) == whole_usage;
+                    if whole_read_only && whole_usage == usage {
+                        return Ok(());
+                    }
                 }
+                subresource_usage.for_each_range_mut(&mut add_image_memory_barrier);
+                subresource_usage.whole_usage = Some(usage);
             }
         }
 
@@ -572,6 +1081,14 @@ impl Drop for TextureInner {
             state
                 .get_fenced_deleter()
                 .delete_when_unused((self.handle, allocation.clone()), serial);
+        } else if let Some(sparse) = self.sparse.as_ref() {
+            let mut state = self.device.state.lock();
+            let serial = state.get_next_pending_serial();
+            let fenced_deleter = state.get_fenced_deleter();
+            fenced_deleter.delete_when_unused(self.handle, serial);
+            if let Some(allocation) = sparse.mip_tail_allocation.as_ref() {
+                fenced_deleter.delete_when_unused(allocation.clone(), serial);
+            }
         }
     }
 }
@@ -608,7 +1125,12 @@ impl TextureViewInner {
 
         log::trace!("image_view create_info: {:?}", create_info);
 
-        let image_view = unsafe { texture.device.raw.create_image_view(&create_info, None)? };
+        let image_view = unsafe {
+            texture
+                .device
+                .raw
+                .create_image_view(&create_info, texture.device.allocation_callbacks())?
+        };
 
         log::trace!("created image_view: {:?}", image_view);
 
@@ -653,11 +1175,19 @@ impl TextureView {
             inner: self.inner.texture.clone(),
         }
     }
+
+    /// Returns the format of the view, which may differ from the format of the texture.
+    pub fn format(&self) -> TextureFormat {
+        self.inner.descriptor.format
+    }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SubresourceUsageTracker {
-    ranges: HashMap<Subresource, TextureUsage, ahash::RandomState>,
+    /// The usage of each subresource, indexed by `array_layer * mip_levels + mip_level`.
+    usages: Vec<TextureUsage>,
+    /// The usage of every subresource, if they all share the same usage.
+    whole_usage: Option<TextureUsage>,
     aspect_mask: vk::ImageAspectFlags,
     mip_levels: u32,
     array_layers: u32,
@@ -672,27 +1202,39 @@ pub struct Subresource {
 impl SubresourceUsageTracker {
     pub fn new(mip_levels: u32, array_layers: u32, format: TextureFormat) -> SubresourceUsageTracker {
         let none = TextureUsage::NONE;
-        let mut ranges = HashMap::with_capacity_and_hasher((mip_levels * array_layers) as usize, Default::default());
-        (0..array_layers).for_each(|array_layer| {
-            (0..mip_levels).for_each(|mip_level| {
-                let subresource = Subresource { mip_level, array_layer };
-                ranges.insert(subresource, none);
-            });
-        });
         SubresourceUsageTracker {
-            ranges,
+            usages: vec![none; (mip_levels * array_layers) as usize],
+            whole_usage: Some(none),
             aspect_mask: aspect_mask(format),
             mip_levels,
             array_layers,
         }
     }
 
+    fn index(&self, subresource: Subresource) -> usize {
+        assert!(
+            subresource.mip_level < self.mip_levels && subresource.array_layer < self.array_layers,
+            "invalid subresource mip_level or array_layer"
+        );
+        (subresource.array_layer * self.mip_levels + subresource.mip_level) as usize
+    }
+
+    pub fn usage(&self, subresource: Subresource) -> TextureUsage {
+        self.usages[self.index(subresource)]
+    }
+
+    /// Sets the usage of every subresource without recording a barrier, e.g. after the texture
+    /// was transitioned outside of vki
+    pub fn set_all(&mut self, usage: TextureUsage) {
+        self.usages.iter_mut().for_each(|u| *u = usage);
+        self.whole_usage = Some(usage);
+    }
+
     fn usage_mut(&mut self, subresource: Subresource) -> (vk::ImageSubresourceRange, &mut TextureUsage) {
         let aspect_mask = self.aspect_mask;
-        let usage = self
-            .ranges
-            .get_mut(&subresource)
-            .expect("invalid subresource mip_level or array_layer");
+        let index = self.index(subresource);
+        // The subresource may diverge from the others
+        self.whole_usage = None;
         let range = vk::ImageSubresourceRange {
             aspect_mask,
             base_mip_level: subresource.mip_level,
@@ -700,20 +1242,50 @@ impl SubresourceUsageTracker {
             base_array_layer: subresource.array_layer,
             layer_count: 1,
         };
-        (range, usage)
+        (range, &mut self.usages[index])
     }
 
-    fn iter_mut(&mut self) -> impl Iterator<Item = (vk::ImageSubresourceRange, &mut TextureUsage)> {
+    /// Calls `f` with each range of subresources that share the same usage. The usage written by `f`
+    /// is applied to the entire range. If all of the subresources share the same usage, the range
+    /// covers the whole texture. Otherwise, consecutive mip levels of each array layer are merged.
+    fn for_each_range_mut<F: FnMut(vk::ImageSubresourceRange, &mut TextureUsage)>(&mut self, mut f: F) {
         let aspect_mask = self.aspect_mask;
-        self.ranges.iter_mut().map(move |(k, usage)| {
+        let mip_levels = self.mip_levels;
+
+        if let Some(mut usage) = self.whole_usage {
             let range = vk::ImageSubresourceRange {
                 aspect_mask,
-                base_mip_level: k.mip_level,
-                level_count: 1,
-                base_array_layer: k.array_layer,
-                layer_count: 1,
+                base_mip_level: 0,
+                level_count: mip_levels,
+                base_array_layer: 0,
+                layer_count: self.array_layers,
             };
-            (range, usage)
-        })
+            f(range, &mut usage);
+            self.usages.iter_mut().for_each(|u| *u = usage);
+            self.whole_usage = Some(usage);
+            return;
+        }
+
+        for (array_layer, layer_usages) in self.usages.chunks_mut(mip_levels as usize).enumerate() {
+            let mut base_mip_level = 0;
+            while base_mip_level < mip_levels {
+                let mut usage = layer_usages[base_mip_level as usize];
+                let level_count = layer_usages[base_mip_level as usize..]
+                    .iter()
+                    .take_while(|u| **u == usage)
+                    .count() as u32;
+                let range = vk::ImageSubresourceRange {
+                    aspect_mask,
+                    base_mip_level,
+                    level_count,
+                    base_array_layer: array_layer as u32,
+                    layer_count: 1,
+                };
+                f(range, &mut usage);
+                let mip_range = base_mip_level as usize..(base_mip_level + level_count) as usize;
+                layer_usages[mip_range].iter_mut().for_each(|u| *u = usage);
+                base_mip_level += level_count;
+            }
+        }
     }
 }
@@ -1,6 +1,10 @@
 use crate::{Extent3d, Origin3d};
 use ash::vk;
 
+/// Resources at least this large are given their own allocation instead of being sub-allocated
+/// from a shared memory block, so that they don't fragment (or force the creation of) large blocks.
+pub const DEDICATED_ALLOCATION_THRESHOLD: u64 = 32 * 1024 * 1024;
+
 pub fn has_zero_or_one_bits(bits: u32) -> bool {
     let bits = bits as i32;
     bits & (bits - 1) == 0
@@ -12,20 +12,147 @@ use std::sync::Arc;
 mod macros;
 mod error;
 mod imp;
+pub mod util;
 
-pub use crate::error::{Error, ErrorKind, FenceError, SwapchainError, VkResult};
+pub use crate::error::{Error, ErrorKind, FenceError, MemoryLocation, SwapchainError, VkResult};
 pub use crate::imp::validate;
+pub use crate::imp::{
+    AdapterInfo, AdapterProperties, DriverProperties, MemoryHeap, MemoryProperties, MemoryType, PciBusInfo,
+    PortabilityFeatures,
+};
+pub use bytemuck::{Pod, Zeroable};
+pub use vki_derive::VertexLayout;
 
 use std::borrow::Cow;
 use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
+use std::num::NonZeroU64;
 use std::ops::Range;
+use std::time::Duration;
 
 #[derive(Clone, Debug)]
 pub struct Instance {
     inner: Arc<imp::InstanceInner>,
 }
 
+bitflags! {
+    /// Optional validation layer features
+    #[repr(transparent)]
+    pub struct ValidationFeatures: u32 {
+        /// GPU-assisted validation of shader resource accesses. This is slow and reserves a
+        /// descriptor set binding slot.
+        const GPU_ASSISTED = 1;
+        /// Warnings for API usage that is valid but may be inefficient
+        const BEST_PRACTICES = 2;
+        /// Validation of missing or incorrect barriers between commands
+        const SYNCHRONIZATION = 4;
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValidationSeverity {
+    Verbose,
+    Info,
+    /// Includes performance warnings
+    Warning,
+    Error,
+}
+
+/// MoltenVK configuration. `None` keeps the MoltenVK default (or the value of the corresponding
+/// `MVK_CONFIG_*` environment variable).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MoltenVkConfig {
+    /// Use Metal argument buffers for descriptor sets. Required for most descriptor indexing
+    /// features (`MVK_CONFIG_USE_METAL_ARGUMENT_BUFFERS`).
+    pub use_metal_argument_buffers: Option<bool>,
+    /// `MVK_CONFIG_SYNCHRONOUS_QUEUE_SUBMITS`
+    pub synchronous_queue_submits: Option<bool>,
+    /// Allow arbitrary texture view swizzles (`MVK_CONFIG_FULL_IMAGE_VIEW_SWIZZLE`)
+    pub full_image_view_swizzle: Option<bool>,
+}
+
+#[derive(Clone, Debug)]
+pub struct InstanceDescriptor<'a> {
+    /// Reported to the driver (and tools) as the application name
+    pub app_name: &'a str,
+    pub app_version: (u32, u32, u32),
+    pub engine_name: &'a str,
+    pub engine_version: (u32, u32, u32),
+    /// The highest Vulkan version that the application uses. The default is `1.0.0`.
+    pub api_version: (u32, u32, u32),
+    /// Enables `VK_LAYER_KHRONOS_validation`. The default is `true` for debug builds.
+    pub enable_validation: bool,
+    /// Additional validation enabled with `VK_EXT_validation_features`. Ignored (with a warning)
+    /// if validation is disabled or the validation layer does not support the extension.
+    pub validation_features: ValidationFeatures,
+    /// Validation messages below this severity are ignored. The default is `Warning`.
+    pub validation_severity: ValidationSeverity,
+    /// Validation message IDs (`MessageID = 0x...` in the message text) that are ignored
+    pub suppressed_validation_messages: &'a [i32],
+    /// Additional instance layers to enable. Unavailable layers are logged and skipped.
+    pub extra_layers: &'a [&'a str],
+    /// Additional instance extensions to enable. Unavailable extensions are an error.
+    pub extra_extensions: &'a [&'a str],
+    /// Only used on macOS
+    pub moltenvk: MoltenVkConfig,
+    /// Routes the host memory allocations of the Vulkan implementation through the global
+    /// allocator so that they can be queried with `Instance::host_memory_usage`. Applies to the
+    /// instance and every device created from it. The default is `false`.
+    pub track_host_allocations: bool,
+}
+
+impl<'a> Default for InstanceDescriptor<'a> {
+    fn default() -> InstanceDescriptor<'a> {
+        InstanceDescriptor {
+            app_name: "",
+            app_version: (0, 0, 0),
+            engine_name: "vki",
+            engine_version: (0, 0, 0),
+            api_version: (1, 0, 0),
+            enable_validation: cfg!(debug_assertions),
+            validation_features: ValidationFeatures::empty(),
+            validation_severity: ValidationSeverity::Warning,
+            suppressed_validation_messages: &[],
+            extra_layers: &[],
+            extra_extensions: &[],
+            moltenvk: MoltenVkConfig::default(),
+            track_host_allocations: false,
+        }
+    }
+}
+
+bitflags! {
+    /// The subgroup operations supported in shaders
+    #[repr(transparent)]
+    pub struct SubgroupOperations: u32 {
+        const BASIC = 1;
+        const VOTE = 2;
+        const ARITHMETIC = 4;
+        const BALLOT = 8;
+        const SHUFFLE = 16;
+        const SHUFFLE_RELATIVE = 32;
+        const CLUSTERED = 64;
+        const QUAD = 128;
+    }
+}
+
+/// See `Adapter::subgroup_properties`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SubgroupProperties {
+    /// The default number of invocations in each subgroup
+    pub subgroup_size: u32,
+    /// The minimum size that may be requested with `Extensions::subgroup_size_control`
+    pub min_subgroup_size: u32,
+    /// The maximum size that may be requested with `Extensions::subgroup_size_control`
+    pub max_subgroup_size: u32,
+    /// The shader stages that support subgroup operations
+    pub supported_stages: ShaderStage,
+    pub supported_operations: SubgroupOperations,
+    pub quad_operations_in_all_stages: bool,
+    /// The shader stages that support a required subgroup size
+    pub required_subgroup_size_stages: ShaderStage,
+}
+
 #[repr(u32)]
 #[derive(Copy, Clone, Debug)]
 pub enum PowerPreference {
@@ -46,7 +173,68 @@ pub struct AdapterOptions {
 
 #[derive(Clone, Debug, Default)]
 pub struct Extensions {
+    /// Enables `SamplerDescriptor::max_anisotropy` values greater than `1`
     pub anisotropic_filtering: bool,
+    /// Enables the `BC*` block compressed texture formats
+    pub texture_compression_bc: bool,
+    /// Enables the `ETC2*` block compressed texture formats
+    pub texture_compression_etc2: bool,
+    /// Enables the `ASTC*` block compressed texture formats
+    pub texture_compression_astc_ldr: bool,
+    /// Enables timeline values on `Fence` and `Queue::submit_with` (`VK_KHR_timeline_semaphore`)
+    pub timeline_semaphore: bool,
+    /// Enables `BufferUsage::TRANSFORM_FEEDBACK` and capturing vertex outputs in a render pass
+    /// (`VK_EXT_transform_feedback`)
+    pub transform_feedback: bool,
+    /// Enables `ComputePipelineDescriptor::required_subgroup_size` (`VK_EXT_subgroup_size_control`)
+    pub subgroup_size_control: bool,
+    /// Enables the `Float16` SPIR-V capability (`VK_KHR_shader_float16_int8`)
+    pub shader_float16: bool,
+    /// Enables the `Int8` SPIR-V capability (`VK_KHR_shader_float16_int8`)
+    pub shader_int8: bool,
+    /// Enables 8-bit types in storage buffers (`VK_KHR_8bit_storage`)
+    pub storage_buffer_8bit_access: bool,
+    /// Enables 16-bit types in storage buffers (`VK_KHR_16bit_storage`)
+    pub storage_buffer_16bit_access: bool,
+    /// Enables `IndexFormat::U8` (`VK_EXT_index_type_uint8`)
+    pub index_type_uint8: bool,
+    /// Enables `VertexBufferLayoutDescriptor::step_rate` values other than `1` (`VK_EXT_vertex_attribute_divisor`)
+    pub vertex_attribute_divisor: bool,
+    /// Enables `RenderPipeline::compilation_info` and `ComputePipeline::compilation_info`
+    /// (`VK_EXT_pipeline_creation_feedback`)
+    pub pipeline_creation_feedback: bool,
+    /// Enables `SwapchainDescriptor::view_formats` (`VK_KHR_swapchain_mutable_format`)
+    pub swapchain_mutable_format: bool,
+}
+
+/// Optional core features of an adapter that are enabled automatically when supported. See
+/// `Adapter::supported_features`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AdapterFeatures {
+    /// `PolygonMode::Line` and `PolygonMode::Point` are supported
+    pub fill_mode_non_solid: bool,
+    /// The color states of a render pipeline may differ in blending and write mask
+    pub independent_blend: bool,
+    /// A non-zero `depth_bias_clamp` is supported
+    pub depth_bias_clamp: bool,
+    /// Storage textures may be written without a format qualifier in the shader
+    pub storage_image_write_without_format: bool,
+    /// Sparse textures may be created. See `Device::create_sparse_texture`.
+    pub sparse_residency: bool,
+    /// `DeviceDescriptor::global_priority` is supported
+    pub global_priority: bool,
+    /// The device local memory is host visible. See `MemoryHint::DeviceWithHostAccess`.
+    pub resizable_bar: bool,
+    /// Viewports may have a negative height (`VK_KHR_maintenance1`). See `RenderPassDescriptor::flip_viewport`.
+    pub negative_viewport_height: bool,
+    /// Indirect draw commands may have a non-zero `first_instance`. The indirect commands are not
+    /// validated; a non-zero value without this feature results in undefined behavior.
+    pub draw_indirect_first_instance: bool,
+    /// Shaders may use `gl_BaseInstance`, `gl_BaseVertex`, and `gl_DrawID` (`VK_KHR_shader_draw_parameters`)
+    pub shader_draw_parameters: bool,
+    /// The maximum value of `SamplerDescriptor::max_anisotropy`, or `1` if
+    /// `Extensions::anisotropic_filtering` is not supported
+    pub max_anisotropy: u32,
 }
 
 #[derive(Clone)]
@@ -54,9 +242,41 @@ pub struct Adapter {
     inner: Arc<imp::AdapterInner>,
 }
 
+/// A set of linked adapters that can be used together by a single `Device`. See
+/// `Instance::enumerate_adapter_groups`.
+#[derive(Clone, Debug)]
+pub struct AdapterGroup {
+    adapters: Vec<Adapter>,
+    subset_allocation: bool,
+}
+
+/// Resource binding limits of a device. See `Device::limits`.
 #[derive(Clone, Debug)]
 pub struct Limits {
+    /// The maximum number of bind groups in a pipeline layout
     pub max_bind_groups: u32,
+    /// The maximum number of dynamic uniform buffers in a pipeline layout
+    pub max_dynamic_uniform_buffers_per_pipeline_layout: u32,
+    /// The maximum number of dynamic storage buffers in a pipeline layout
+    pub max_dynamic_storage_buffers_per_pipeline_layout: u32,
+    /// The maximum number of samplers visible to a single shader stage
+    pub max_samplers_per_shader_stage: u32,
+    /// The maximum number of sampled textures visible to a single shader stage
+    pub max_sampled_textures_per_shader_stage: u32,
+    /// The maximum number of storage textures and storage texel buffers visible to a single shader stage
+    pub max_storage_textures_per_shader_stage: u32,
+    /// The maximum number of uniform buffers visible to a single shader stage
+    pub max_uniform_buffers_per_shader_stage: u32,
+    /// The maximum number of storage buffers visible to a single shader stage
+    pub max_storage_buffers_per_shader_stage: u32,
+    /// The maximum `offset + size` of a `PushConstantRange` in bytes. This is at least `128`.
+    pub max_push_constants_size: u32,
+    /// The required alignment, in bytes, of uniform buffer binding offsets and dynamic offsets.
+    /// See `align_to`.
+    pub min_uniform_buffer_offset_alignment: usize,
+    /// The required alignment, in bytes, of storage buffer binding offsets and dynamic offsets.
+    /// See `align_to`.
+    pub min_storage_buffer_offset_alignment: usize,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -64,6 +284,14 @@ pub struct DeviceDescriptor<'a> {
     pub extensions: Extensions,
     /// The queue created for the device will have support for the provided surface
     pub surface_support: Option<&'a Surface>,
+    /// The priority of the device queue in the range `[0.0, 1.0]`. Defaults to `1.0`.
+    pub queue_priority: Option<f32>,
+    /// The system-wide scheduling priority of the device queue (`VK_EXT_global_priority`).
+    ///
+    /// A device created with `QueueGlobalPriority::Low` can be used for background work (e.g.
+    /// streaming or transcoding) without starving the queue of the rendering device. The
+    /// priority is ignored if the adapter does not support the extension.
+    pub global_priority: Option<QueueGlobalPriority>,
     // pub queue_descriptors: &'a [QueueDescriptor<'a>],
 }
 
@@ -72,6 +300,38 @@ impl<'a> DeviceDescriptor<'a> {
         self.surface_support = Some(surface);
         self
     }
+
+    pub fn with_queue_priority(mut self, priority: f32) -> DeviceDescriptor<'a> {
+        self.queue_priority = Some(priority);
+        self
+    }
+
+    pub fn with_global_priority(mut self, priority: QueueGlobalPriority) -> DeviceDescriptor<'a> {
+        self.global_priority = Some(priority);
+        self
+    }
+}
+
+#[repr(i32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QueueGlobalPriority {
+    Low = 128,       // ash::vk::QueueGlobalPriorityEXT::LOW.as_raw(),
+    Medium = 256,    // ash::vk::QueueGlobalPriorityEXT::MEDIUM.as_raw(),
+    High = 512,      // ash::vk::QueueGlobalPriorityEXT::HIGH.as_raw(),
+    Realtime = 1024, // ash::vk::QueueGlobalPriorityEXT::REALTIME.as_raw(),
+}
+
+/// The host memory allocated by the Vulkan implementation. See `Instance::host_memory_usage`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HostMemoryUsage {
+    /// The number of bytes allocated through the allocation callbacks
+    pub allocated_bytes: usize,
+    /// The number of live allocations made through the allocation callbacks
+    pub allocation_count: usize,
+    /// The number of bytes that the implementation reported as allocated internally, e.g. for
+    /// executable memory, without using the callbacks
+    pub internal_allocated_bytes: usize,
 }
 
 #[derive(Clone)]
@@ -79,12 +339,59 @@ pub struct Device {
     inner: Arc<imp::DeviceInner>,
 }
 
+/// The subset of `Device` operations that are safe to run on a background thread while other
+/// threads record and submit commands. See `Device::spawn_creation`.
+///
+/// Resource creation only holds the device locks briefly, so creation on several threads runs
+/// in parallel. Submission, swapchain presentation, and waiting on submissions are not
+/// exposed because they order work on the queue and belong on the submitting thread.
+#[derive(Clone, Debug)]
+pub struct ResourceCreator {
+    device: Device,
+}
+
+/// The sizes of the internal pools of a device. See `Device::pool_statistics`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStatistics {
+    /// The number of command encoder states retained for reuse by `Device::create_command_encoder`
+    pub command_encoder_count: usize,
+    /// The capacity in bytes of the command and data storage held by the retained command encoder
+    /// states. The storage of the resource usage maps is not included.
+    pub command_encoder_bytes: usize,
+    /// The number of render passes in the render pass cache. The least recently used render pass
+    /// is evicted when the cache is full.
+    pub render_pass_count: usize,
+}
+
+/// A resource being created on a background thread. See `Device::spawn_creation`.
+#[derive(Debug)]
+pub struct PendingCreation<T> {
+    thread: std::thread::JoinHandle<Result<T, Error>>,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct SwapchainDescriptor<'a> {
     pub surface: &'a Surface,
     pub format: TextureFormat,
+    /// The usage of the swapchain images. `STORAGE` and `SAMPLED` allow the acquired image to be
+    /// processed in place, but are optional; see `Device::get_supported_swapchain_usage`.
     pub usage: TextureUsage,
     pub present_mode: PresentMode,
+    /// When greater than `1`, a multisampled color target is allocated with the swapchain and
+    /// resolved into the acquired image at the end of the render pass.
+    ///
+    /// See `SwapchainImage::color_attachment`.
+    pub sample_count: u32,
+    /// When set, a depth texture matching the swapchain extent and `sample_count` is allocated
+    /// with the swapchain and recreated along with it.
+    ///
+    /// See `SwapchainImage::depth_stencil_attachment`.
+    pub depth_format: Option<TextureFormat>,
+    /// Additional formats that the swapchain images can be viewed as, e.g. `B8G8R8A8UnormSRGB`
+    /// for `B8G8R8A8Unorm` images. The formats must have the same texel size as `format`.
+    ///
+    /// Requires `Extensions::swapchain_mutable_format`. See `Swapchain::acquire_next_image_with_view_format`.
+    pub view_formats: &'a [TextureFormat],
 }
 
 #[repr(i32)]
@@ -102,6 +409,9 @@ impl<'a> SwapchainDescriptor<'a> {
             format: TextureFormat::B8G8R8A8UnormSRGB,
             usage: TextureUsage::OUTPUT_ATTACHMENT,
             present_mode: PresentMode::Fifo,
+            sample_count: 1,
+            depth_format: None,
+            view_formats: &[],
         }
     }
 }
@@ -117,19 +427,62 @@ pub struct Surface {
     inner: Arc<imp::SurfaceInner>,
 }
 
+/// Describes the native window of a `Surface`.
+#[derive(Copy, Clone, Debug)]
+pub struct SurfaceDescriptor {
+    pub raw_window_handle: raw_window_handle::RawWindowHandle,
+}
+
+/// A handle to the device queue. Cloning the handle is cheap and the clones submit to the same
+/// queue.
+#[derive(Clone)]
 pub struct Queue {
-    inner: imp::QueueInner,
+    inner: Arc<imp::QueueInner>,
 }
 
 pub struct SwapchainImage {
     // TODO: See if this can still be ergonomic with a reference instead
     swapchain: Arc<imp::SwapchainInner>,
     image_index: u32,
+    multisampled_view: Option<TextureView>,
     pub texture: Texture,
     pub view: TextureView,
+    /// The swapchain's depth view if it was created with a `depth_format`
+    pub depth_view: Option<TextureView>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FrameSchedulerDescriptor {
+    /// The number of frames that may be recorded before the oldest must complete on the GPU.
+    /// Typically `Swapchain::image_count`.
+    pub frames_in_flight: u32,
+    /// The size in bytes of each frame's staging buffer. See `Frame::write_buffer`.
+    pub staging_buffer_size: usize,
+}
+
+/// Owns the command encoders and staging buffers of the frames in flight.
+///
+/// `begin_frame` waits for the GPU to finish the frame that last used the same slot, so the
+/// slot's staging buffer can be rewritten without any further synchronization.
+pub struct FrameScheduler {
+    queue: Queue,
+    frames: Vec<imp::FrameResources>,
+    frame_index: usize,
+}
+
+/// A frame being recorded. See `FrameScheduler::begin_frame`.
+pub struct Frame {
+    /// The acquired swapchain image that is presented by `FrameScheduler::end_frame`
+    pub image: SwapchainImage,
+    /// The encoder that records the frame's commands
+    pub encoder: CommandEncoder,
+    staging_buffer: Option<Buffer>,
+    staging_offset: usize,
+    frame_index: usize,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Extent3d {
     pub width: u32,
     pub height: u32,
@@ -137,6 +490,7 @@ pub struct Extent3d {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Origin3d {
     pub x: i32,
     pub y: i32,
@@ -144,6 +498,7 @@ pub struct Origin3d {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Color {
     pub r: f32,
     pub g: f32,
@@ -151,8 +506,37 @@ pub struct Color {
     pub a: f32,
 }
 
+impl Eq for Color {}
+
+#[allow(clippy::derive_hash_xor_eq)]
+impl Hash for Color {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.r.to_bits().hash(state);
+        self.g.to_bits().hash(state);
+        self.b.to_bits().hash(state);
+        self.a.to_bits().hash(state);
+    }
+}
+
+/// The value a color attachment is cleared to. The variant must match the component type of
+/// the attachment format, e.g. `Uint` for `R32Uint` or `Sint` for `R8G8B8A8Sint`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClearValue {
+    Float([f32; 4]),
+    Uint([u32; 4]),
+    Sint([i32; 4]),
+}
+
+impl From<Color> for ClearValue {
+    fn from(color: Color) -> ClearValue {
+        ClearValue::Float([color.r, color.g, color.b, color.a])
+    }
+}
+
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TextureFormat {
     // 8-bit formats
     R8Unorm,
@@ -178,6 +562,8 @@ pub enum TextureFormat {
     B8G8R8A8Unorm,
     B8G8R8A8UnormSRGB,
     R16G16Unorm,
+    R32Uint,
+    R32Sint,
 
     // TODO: Update 64-bit formats
     RGBA16Float,
@@ -185,16 +571,45 @@ pub enum TextureFormat {
     RGBA16Sint,
     RGBA16Snorm,
     RGBA16Unorm,
+    RG32Uint,
+    RG32Sint,
 
     // TODO: Update 128-bit formats
     RGBA32Float,
+    RGBA32Uint,
+    RGBA32Sint,
 
     D32Float,
     D32FloatS8Uint,
+
+    // Block compressed formats (requires `Extensions::texture_compression_bc`)
+    BC1RGBAUnorm,
+    BC1RGBAUnormSRGB,
+    BC2RGBAUnorm,
+    BC2RGBAUnormSRGB,
+    BC3RGBAUnorm,
+    BC3RGBAUnormSRGB,
+    BC4RUnorm,
+    BC4RSnorm,
+    BC5RGUnorm,
+    BC5RGSnorm,
+    BC6HRGBUfloat,
+    BC6HRGBSfloat,
+    BC7RGBAUnorm,
+    BC7RGBAUnormSRGB,
+
+    // ETC2 compressed formats (requires `Extensions::texture_compression_etc2`)
+    ETC2RGBA8Unorm,
+    ETC2RGBA8UnormSRGB,
+
+    // ASTC compressed formats (requires `Extensions::texture_compression_astc_ldr`)
+    ASTC4x4Unorm,
+    ASTC4x4UnormSRGB,
 }
 
 bitflags! {
     #[repr(transparent)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct TextureUsage: u32 {
         const NONE = 0;
         const COPY_SRC = 1;
@@ -207,8 +622,27 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// The operations supported by a `TextureFormat` for optimally tiled textures.
+    ///
+    /// See `Adapter::get_texture_format_features`.
+    #[repr(transparent)]
+    pub struct TextureFormatFeatures: u32 {
+        const NONE = 0;
+        const SAMPLED = 1;
+        const SAMPLED_LINEAR_FILTER = 2;
+        const STORAGE = 4;
+        const COLOR_ATTACHMENT = 8;
+        const COLOR_ATTACHMENT_BLEND = 16;
+        const DEPTH_STENCIL_ATTACHMENT = 32;
+        const BLIT_SRC = 64;
+        const BLIT_DST = 128;
+    }
+}
+
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TextureDimension {
     D1,
     D2,
@@ -217,6 +651,7 @@ pub enum TextureDimension {
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TextureViewDimension {
     D1,
     D2,
@@ -225,6 +660,7 @@ pub enum TextureViewDimension {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextureDescriptor {
     pub size: Extent3d,
     pub array_layer_count: u32,
@@ -235,8 +671,70 @@ pub struct TextureDescriptor {
     pub usage: TextureUsage,
 }
 
+impl TextureDescriptor {
+    /// A single 2D texture with one mip level, one array layer and no multisampling. The usage
+    /// defaults to `SAMPLED | COPY_DST`.
+    pub fn new_2d(format: TextureFormat, width: u32, height: u32) -> TextureDescriptor {
+        TextureDescriptor {
+            size: Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            array_layer_count: 1,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+        }
+    }
+
+    /// A 2D texture with six array layers that can be viewed as a cube map.
+    pub fn new_cube(format: TextureFormat, size: u32) -> TextureDescriptor {
+        TextureDescriptor::new_2d(format, size, size).with_array_layer_count(6)
+    }
+
+    /// A 3D texture with one mip level. The usage defaults to `SAMPLED | COPY_DST`.
+    pub fn new_3d(format: TextureFormat, width: u32, height: u32, depth: u32) -> TextureDescriptor {
+        TextureDescriptor {
+            size: Extent3d { width, height, depth },
+            dimension: TextureDimension::D3,
+            ..TextureDescriptor::new_2d(format, width, height)
+        }
+    }
+
+    pub fn with_usage(mut self, usage: TextureUsage) -> TextureDescriptor {
+        self.usage = usage;
+        self
+    }
+
+    pub fn with_mip_level_count(mut self, mip_level_count: u32) -> TextureDescriptor {
+        self.mip_level_count = mip_level_count;
+        self
+    }
+
+    /// Sets the mip level count for a full mip chain down to `1x1`.
+    pub fn with_full_mip_chain(self) -> TextureDescriptor {
+        let size = self.size;
+        let max_dimension = size.width.max(size.height).max(size.depth).max(1);
+        self.with_mip_level_count(32 - max_dimension.leading_zeros())
+    }
+
+    pub fn with_array_layer_count(mut self, array_layer_count: u32) -> TextureDescriptor {
+        self.array_layer_count = array_layer_count;
+        self
+    }
+
+    pub fn with_sample_count(mut self, sample_count: u32) -> TextureDescriptor {
+        self.sample_count = sample_count;
+        self
+    }
+}
+
 bitflags! {
     #[repr(transparent)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct TextureAspect: u32 {
         const COLOR = 0b1;      // vk::ImageAspectFlags::COLOR;
         const DEPTH = 0b10;     // vk::ImageAspectFlags::DEPTH;
@@ -245,6 +743,7 @@ bitflags! {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextureViewDescriptor {
     pub format: TextureFormat,
     pub dimension: TextureViewDimension,
@@ -262,6 +761,7 @@ pub struct TextureView {
 
 bitflags! {
     #[repr(transparent)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct BufferUsage: u32 {
         const NONE = 0;
         const MAP_READ = 1;
@@ -273,13 +773,40 @@ bitflags! {
         const UNIFORM = 64;
         const STORAGE = 128;
         const INDIRECT = 256;
+        /// Requires `Extensions::transform_feedback`
+        const TRANSFORM_FEEDBACK = 512;
+        /// Stores the number of bytes written by transform feedback. The counter may be used to
+        /// resume transform feedback or with `RenderPassEncoder::draw_indirect_byte_count`.
+        ///
+        /// Requires `Extensions::transform_feedback`
+        const TRANSFORM_FEEDBACK_COUNTER = 1024;
     }
 }
 
+/// Describes how the memory backing a buffer will be accessed.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MemoryHint {
+    /// Host visible memory that's written by the CPU and copied to other buffers or textures.
+    Upload,
+    /// Host visible memory that's written by the GPU and read by the CPU.
+    Readback,
+    /// Device local memory that's only accessed by the GPU.
+    DeviceOnly,
+    /// Device local memory that's also writable by the CPU, for data that's updated often and
+    /// read directly by the GPU. The memory is allocated from the full device local heap when
+    /// `Adapter::supports_resizable_bar` is `true`; otherwise, it falls back to host visible memory
+    /// that's preferably device local.
+    DeviceWithHostAccess,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BufferDescriptor {
     pub size: usize,
     pub usage: BufferUsage,
+    /// The memory to allocate the buffer from. If `None`, the memory is chosen based on the `usage`.
+    pub memory_hint: Option<MemoryHint>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -287,6 +814,29 @@ pub struct Buffer {
     inner: Arc<imp::BufferInner>,
 }
 
+/// A `Buffer` holding elements of type `T`. Offsets, lengths and ranges are in units of `T`.
+///
+/// The `Pod` bound guarantees that `T` has no padding or invalid bit patterns, so the elements
+/// can be copied to the buffer as raw bytes.
+pub struct TypedBuffer<T: Pod> {
+    buffer: Buffer,
+    _phantom: PhantomData<T>,
+}
+
+/// A growable array of per-object uniform data that is bound with dynamic offsets.
+///
+/// The elements are packed into a single uniform buffer at the device's
+/// `Limits::min_uniform_buffer_offset_alignment`. The buffer is reused across uploads and is
+/// only reallocated when the array outgrows it.
+pub struct DynamicUniformVec<T: Pod> {
+    device: Device,
+    buffer: Buffer,
+    /// The elements packed at `stride`
+    data: Vec<u8>,
+    stride: usize,
+    _phantom: PhantomData<T>,
+}
+
 /// non-standard / not in the gpuweb spec
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct BufferViewDescriptor {
@@ -330,6 +880,13 @@ pub struct Fence {
     inner: imp::FenceInner,
 }
 
+/// Identifies a queue submission. Submission indices increase monotonically and can be
+/// compared to determine the submission order.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SubmissionIndex {
+    serial: u64,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Texture {
     inner: Arc<imp::TextureInner>,
@@ -337,6 +894,7 @@ pub struct Texture {
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FilterMode {
     Nearest,
     Linear,
@@ -344,14 +902,31 @@ pub enum FilterMode {
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AddressMode {
     ClampToEdge,
     Repeat,
     MirrorRepeat,
+    /// Coordinates outside of the texture return the sampler's `border_color`.
+    ///
+    /// Note: clamp-to-border is not part of the WebGPU spec.
+    ClampToBorder,
+}
+
+/// The color returned for coordinates outside of the texture when sampling with
+/// `AddressMode::ClampToBorder`.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BorderColor {
+    TransparentBlack,
+    OpaqueBlack,
+    OpaqueWhite,
 }
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompareFunction {
     Never,
     Less,
@@ -364,6 +939,7 @@ pub enum CompareFunction {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SamplerDescriptor {
     pub address_mode_u: AddressMode,
     pub address_mode_v: AddressMode,
@@ -374,6 +950,11 @@ pub struct SamplerDescriptor {
     pub lod_min_clamp: f32,
     pub lod_max_clamp: f32,
     pub compare_function: CompareFunction,
+    /// Only used by `AddressMode::ClampToBorder`.
+    pub border_color: BorderColor,
+    /// Values greater than `1` require `Extensions::anisotropic_filtering` and must not exceed
+    /// `AdapterFeatures::max_anisotropy`.
+    pub max_anisotropy: u32,
 }
 
 impl Eq for SamplerDescriptor {}
@@ -400,17 +981,87 @@ impl Default for SamplerDescriptor {
             lod_min_clamp: 0.0,
             lod_max_clamp: std::f32::MAX,
             compare_function: CompareFunction::Never,
+            border_color: BorderColor::TransparentBlack,
+            max_anisotropy: 1,
         }
     }
 }
 
+impl SamplerDescriptor {
+    /// Linear filtering for magnification, minification and between mip levels.
+    pub fn linear() -> SamplerDescriptor {
+        SamplerDescriptor::default().with_filter(FilterMode::Linear)
+    }
+
+    /// Nearest filtering for magnification, minification and between mip levels.
+    pub fn nearest() -> SamplerDescriptor {
+        SamplerDescriptor::default()
+    }
+
+    /// Sets the address mode for all three coordinates.
+    pub fn with_address_mode(mut self, address_mode: AddressMode) -> SamplerDescriptor {
+        self.address_mode_u = address_mode;
+        self.address_mode_v = address_mode;
+        self.address_mode_w = address_mode;
+        self
+    }
+
+    /// Sets the magnification, minification and mipmap filters.
+    pub fn with_filter(mut self, filter: FilterMode) -> SamplerDescriptor {
+        self.mag_filter = filter;
+        self.min_filter = filter;
+        self.mipmap_filter = filter;
+        self
+    }
+
+    pub fn with_lod_clamp(mut self, lod_min_clamp: f32, lod_max_clamp: f32) -> SamplerDescriptor {
+        self.lod_min_clamp = lod_min_clamp;
+        self.lod_max_clamp = lod_max_clamp;
+        self
+    }
+
+    pub fn with_compare_function(mut self, compare_function: CompareFunction) -> SamplerDescriptor {
+        self.compare_function = compare_function;
+        self
+    }
+
+    pub fn with_border_color(mut self, border_color: BorderColor) -> SamplerDescriptor {
+        self.border_color = border_color;
+        self
+    }
+
+    pub fn with_max_anisotropy(mut self, max_anisotropy: u32) -> SamplerDescriptor {
+        self.max_anisotropy = max_anisotropy;
+        self
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Sampler {
     inner: Arc<imp::SamplerInner>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum QueryType {
+    /// Each query records a 64-bit GPU timestamp. Multiply the difference between two timestamps
+    /// by `Queue::timestamp_period` to get nanoseconds.
+    Timestamp,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct QuerySetDescriptor {
+    pub query_type: QueryType,
+    pub count: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct QuerySet {
+    inner: Arc<imp::QuerySetInner>,
+}
+
 bitflags! {
     #[repr(transparent)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct ShaderStage: u32 {
         const NONE = 0;
         const VERTEX = 1;
@@ -421,10 +1072,15 @@ bitflags! {
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BindingType {
     UniformBuffer,
     DynamicUniformBuffer,
     Sampler,
+    /// A sampler with a `compare_function` other than `CompareFunction::Never`.
+    ///
+    /// GLSL: `samplerShadow`
+    ComparisonSampler,
     SampledTexture,
     StorageBuffer,
     DynamicStorageBuffer,
@@ -439,13 +1095,21 @@ pub enum BindingType {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BindGroupLayoutEntry {
     pub binding: u32,
     pub visibility: ShaderStage,
     pub binding_type: BindingType,
+    /// The format of the texture views bound to `ReadOnlyStorageTexture` and `WriteOnlyStorageTexture`
+    /// bindings. This is required for storage texture bindings and must be `None` for all other binding types.
+    pub storage_texture_format: Option<TextureFormat>,
+    /// The minimum size in bytes of the buffers bound to uniform and storage buffer bindings. This
+    /// must be `None` for all other binding types.
+    pub min_binding_size: Option<NonZeroU64>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BindGroupLayoutDescriptor {
     pub entries: Vec<BindGroupLayoutEntry>,
 }
@@ -456,21 +1120,43 @@ pub struct BindGroupLayout {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PushConstantRange {
     pub stages: ShaderStage,
     pub offset: usize,
     pub size: usize,
 }
 
+/// A region of a buffer bound to a uniform or storage buffer binding.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BufferBinding {
+    pub buffer: Buffer,
+    /// The offset in bytes from the start of the buffer
+    pub offset: usize,
+    /// The size in bytes of the binding, or `None` to bind the rest of the buffer after `offset`
+    pub size: Option<NonZeroU64>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum BindingResource {
     Sampler(Sampler),
     TextureView(TextureView),
+    /// The byte range of the buffer to bind
     Buffer(Buffer, Range<usize>),
+    BufferBinding(BufferBinding),
     BufferView(BufferView), // not in gpuweb spec
 }
 
 impl BindingResource {
+    /// Binds the whole buffer.
+    pub fn buffer(buffer: Buffer) -> BindingResource {
+        BindingResource::BufferBinding(BufferBinding {
+            buffer,
+            offset: 0,
+            size: None,
+        })
+    }
+
     pub fn as_sampler(&self) -> Option<&Sampler> {
         if let BindingResource::Sampler(ref sampler) = self {
             Some(sampler)
@@ -495,6 +1181,23 @@ impl BindingResource {
         }
     }
 
+    pub fn as_buffer_binding(&self) -> Option<&BufferBinding> {
+        if let BindingResource::BufferBinding(ref buffer_binding) = self {
+            Some(buffer_binding)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the bound buffer of either a `Buffer` or a `BufferBinding` resource.
+    pub fn bound_buffer(&self) -> Option<&Buffer> {
+        match self {
+            BindingResource::Buffer(ref buffer, _) => Some(buffer),
+            BindingResource::BufferBinding(ref buffer_binding) => Some(&buffer_binding.buffer),
+            _ => None,
+        }
+    }
+
     pub fn as_buffer_view(&self) -> Option<&BufferView> {
         if let BindingResource::BufferView(ref buffer_view) = self {
             Some(buffer_view)
@@ -534,6 +1237,7 @@ pub struct PipelineLayout {
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FrontFace {
     Ccw,
     Cw,
@@ -541,6 +1245,7 @@ pub enum FrontFace {
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CullMode {
     None,
     Front,
@@ -549,6 +1254,7 @@ pub enum CullMode {
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PolygonMode {
     Fill = 0,
     Line = 1,
@@ -557,6 +1263,7 @@ pub enum PolygonMode {
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BlendFactor {
     Zero,
     One,
@@ -575,6 +1282,7 @@ pub enum BlendFactor {
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BlendOperation {
     Add,
     Subtract,
@@ -585,6 +1293,7 @@ pub enum BlendOperation {
 
 bitflags! {
     #[repr(transparent)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct ColorWrite: u32 {
         const NONE = 0;
         const RED = 1;
@@ -596,6 +1305,7 @@ bitflags! {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlendDescriptor {
     pub src_factor: BlendFactor,
     pub dst_factor: BlendFactor,
@@ -624,6 +1334,7 @@ impl Default for BlendDescriptor {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ColorStateDescriptor {
     pub format: TextureFormat,
     pub color_blend: BlendDescriptor,
@@ -631,8 +1342,32 @@ pub struct ColorStateDescriptor {
     pub write_mask: ColorWrite,
 }
 
+impl ColorStateDescriptor {
+    /// An opaque color state that writes all components.
+    pub fn simple(format: TextureFormat) -> ColorStateDescriptor {
+        ColorStateDescriptor {
+            format,
+            color_blend: BlendDescriptor::OPAQUE,
+            alpha_blend: BlendDescriptor::OPAQUE,
+            write_mask: ColorWrite::ALL,
+        }
+    }
+
+    /// A color state that blends with `BlendDescriptor::BLEND` and writes all components.
+    pub fn blended(format: TextureFormat) -> ColorStateDescriptor {
+        ColorStateDescriptor::simple(format).with_blend(BlendDescriptor::BLEND, BlendDescriptor::BLEND)
+    }
+
+    pub fn with_blend(mut self, color_blend: BlendDescriptor, alpha_blend: BlendDescriptor) -> ColorStateDescriptor {
+        self.color_blend = color_blend;
+        self.alpha_blend = alpha_blend;
+        self
+    }
+}
+
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StencilOperation {
     Keep,
     Zero,
@@ -645,6 +1380,7 @@ pub enum StencilOperation {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StencilStateFaceDescriptor {
     pub compare: CompareFunction,
     pub fail_op: StencilOperation,
@@ -668,16 +1404,56 @@ impl Default for StencilStateFaceDescriptor {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DepthStencilStateDescriptor {
     pub format: TextureFormat,
     pub depth_write_enabled: bool,
     pub depth_compare: CompareFunction,
     pub stencil_front: StencilStateFaceDescriptor,
     pub stencil_back: StencilStateFaceDescriptor,
+    /// The stencil masks are set when the pipeline is bound and can be overridden with
+    /// `RenderPassEncoder::set_stencil_compare_mask` and `RenderPassEncoder::set_stencil_write_mask`.
     pub stencil_read_mask: u32,
     pub stencil_write_mask: u32,
 }
 
+impl DepthStencilStateDescriptor {
+    /// Depth test with `Less` and depth writes enabled; stencil is ignored. The format is
+    /// `D32Float` and may be overridden with `with_format`.
+    pub const LESS_WRITE: DepthStencilStateDescriptor = DepthStencilStateDescriptor {
+        format: TextureFormat::D32Float,
+        depth_write_enabled: true,
+        depth_compare: CompareFunction::Less,
+        stencil_front: StencilStateFaceDescriptor::IGNORE,
+        stencil_back: StencilStateFaceDescriptor::IGNORE,
+        stencil_read_mask: 0,
+        stencil_write_mask: 0,
+    };
+    /// Depth test with `LessEqual` and depth writes enabled, e.g. for skyboxes drawn at the far plane.
+    pub const LESS_EQUAL_WRITE: DepthStencilStateDescriptor = DepthStencilStateDescriptor {
+        depth_compare: CompareFunction::LessEqual,
+        ..DepthStencilStateDescriptor::LESS_WRITE
+    };
+    /// Depth test with `LessEqual` and depth writes disabled, e.g. for translucent objects or after
+    /// a depth pre-pass.
+    pub const READ_ONLY: DepthStencilStateDescriptor = DepthStencilStateDescriptor {
+        depth_write_enabled: false,
+        depth_compare: CompareFunction::LessEqual,
+        ..DepthStencilStateDescriptor::LESS_WRITE
+    };
+
+    pub fn with_format(mut self, format: TextureFormat) -> DepthStencilStateDescriptor {
+        self.format = format;
+        self
+    }
+}
+
+impl Default for DepthStencilStateDescriptor {
+    fn default() -> DepthStencilStateDescriptor {
+        DepthStencilStateDescriptor::LESS_WRITE
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ShaderModuleDescriptor<'a> {
     pub code: &'a [u8],
@@ -694,10 +1470,32 @@ pub struct PipelineStageDescriptor {
     pub entry_point: Cow<'static, str>,
 }
 
+impl PipelineStageDescriptor {
+    pub fn new<S: Into<Cow<'static, str>>>(module: ShaderModule, entry_point: S) -> PipelineStageDescriptor {
+        PipelineStageDescriptor {
+            module,
+            entry_point: entry_point.into(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ComputePipelineDescriptor {
     pub layout: PipelineLayout,
     pub compute_stage: PipelineStageDescriptor,
+    /// Requires `Extensions::subgroup_size_control`. The size must be a power of two between
+    /// `SubgroupProperties::min_subgroup_size` and `SubgroupProperties::max_subgroup_size`.
+    pub required_subgroup_size: Option<u32>,
+}
+
+/// Feedback reported by the driver when a pipeline is created. See
+/// `Extensions::pipeline_creation_feedback`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CompilationInfo {
+    /// The time spent creating the pipeline, or `None` if the driver did not report it
+    pub duration: Option<Duration>,
+    /// `true` if the pipeline was created from the pipeline cache without compiling the shaders
+    pub cache_hit: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -707,6 +1505,7 @@ pub struct ComputePipeline {
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PrimitiveTopology {
     PointList,
     LineList,
@@ -716,10 +1515,13 @@ pub enum PrimitiveTopology {
 }
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RasterizationStateDescriptor {
     pub front_face: FrontFace,
     pub cull_mode: CullMode,
     pub polygon_mode: PolygonMode,
+    /// The depth bias values are set when the pipeline is bound and can be overridden with
+    /// `RenderPassEncoder::set_depth_bias`.
     pub depth_bias: i32,
     pub depth_bias_slope_scale: f32,
     pub depth_bias_clamp: f32,
@@ -741,6 +1543,7 @@ impl Hash for RasterizationStateDescriptor {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.front_face.hash(state);
         self.cull_mode.hash(state);
+        self.polygon_mode.hash(state);
         self.depth_bias.hash(state);
         self.depth_bias_slope_scale.to_bits().hash(state);
         self.depth_bias_clamp.to_bits().hash(state);
@@ -749,15 +1552,52 @@ impl Hash for RasterizationStateDescriptor {
 
 impl Eq for RasterizationStateDescriptor {}
 
+impl RasterizationStateDescriptor {
+    /// Filled, counter-clockwise front faces without culling or depth bias
+    pub const CULL_NONE: RasterizationStateDescriptor = RasterizationStateDescriptor {
+        front_face: FrontFace::Ccw,
+        cull_mode: CullMode::None,
+        polygon_mode: PolygonMode::Fill,
+        depth_bias: 0,
+        depth_bias_slope_scale: 0.0,
+        depth_bias_clamp: 0.0,
+    };
+    /// Filled, counter-clockwise front faces with back faces culled
+    pub const CULL_BACK: RasterizationStateDescriptor = RasterizationStateDescriptor {
+        cull_mode: CullMode::Back,
+        ..RasterizationStateDescriptor::CULL_NONE
+    };
+    /// Filled, counter-clockwise front faces with front faces culled (e.g. for skyboxes drawn from inside)
+    pub const CULL_FRONT: RasterizationStateDescriptor = RasterizationStateDescriptor {
+        cull_mode: CullMode::Front,
+        ..RasterizationStateDescriptor::CULL_NONE
+    };
+    /// Line rasterization without culling. Requires adapter support for non-solid fill modes
+    pub const WIREFRAME: RasterizationStateDescriptor = RasterizationStateDescriptor {
+        polygon_mode: PolygonMode::Line,
+        ..RasterizationStateDescriptor::CULL_NONE
+    };
+}
+
+impl Default for RasterizationStateDescriptor {
+    fn default() -> RasterizationStateDescriptor {
+        RasterizationStateDescriptor::CULL_NONE
+    }
+}
+
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IndexFormat {
     U16,
     U32,
+    /// Requires `Extensions::index_type_uint8`
+    U8,
 }
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InputStepMode {
     Vertex,
     Instance,
@@ -765,6 +1605,7 @@ pub enum InputStepMode {
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VertexFormat {
     UChar2,
     UChar4,
@@ -783,6 +1624,19 @@ pub enum VertexFormat {
     UShort4Norm,
     Short2Norm,
     Short4Norm,
+    UShortNorm,
+    /// Support is optional. See `Adapter::supports_vertex_format`.
+    UShort3Norm,
+    ShortNorm,
+    /// Support is optional. See `Adapter::supports_vertex_format`.
+    Short3Norm,
+
+    /// Packed `x: 10, y: 10, z: 10, w: 2` unsigned normalized components. Bits `0..10` contain `x`.
+    UInt1010102Norm,
+    /// Packed `x: 10, y: 10, z: 10, w: 2` signed normalized components. Bits `0..10` contain `x`.
+    ///
+    /// Support is optional. See `Adapter::supports_vertex_format`.
+    Int1010102Norm,
 
     Half2,
     Half4,
@@ -802,6 +1656,7 @@ pub enum VertexFormat {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VertexAttributeDescriptor {
     pub format: VertexFormat,
     pub offset: usize,
@@ -809,17 +1664,53 @@ pub struct VertexAttributeDescriptor {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VertexBufferLayoutDescriptor {
     /// The index of the vertex buffer containing these attributes. See `RenderPassEncoder::set_vertex_buffers`.
     pub input_slot: u32,
+    /// The number of bytes between elements. A stride of `0` makes every vertex (or instance) read the
+    /// same element, which can be used to provide a constant attribute value.
     pub stride: usize,
     pub step_mode: InputStepMode,
+    /// The number of instances drawn before advancing to the next element when `step_mode` is
+    /// `InputStepMode::Instance`. Must be `1` for `InputStepMode::Vertex`. Values other than `1`
+    /// require `Extensions::vertex_attribute_divisor`.
+    pub step_rate: u32,
     pub attributes: Vec<VertexAttributeDescriptor>,
 }
 
+/// Maps a vertex attribute field type to its `VertexFormat`. Implemented for the scalar and
+/// array types of the non-normalized formats.
+pub trait VertexAttributeFormat {
+    const FORMAT: VertexFormat;
+}
+
+/// Describes the vertex attributes of a `#[repr(C)]` vertex type. Typically implemented with
+/// `#[derive(VertexLayout)]`.
+pub trait VertexLayout: Sized {
+    /// Returns the attributes of the vertex type with their offsets and shader locations.
+    fn vertex_attributes() -> Vec<VertexAttributeDescriptor>;
+
+    /// Returns a layout for a vertex buffer bound at `input_slot` with a stride of `size_of::<Self>()`.
+    fn vertex_buffer_layout(input_slot: u32, step_mode: InputStepMode) -> VertexBufferLayoutDescriptor {
+        VertexBufferLayoutDescriptor {
+            input_slot,
+            stride: std::mem::size_of::<Self>(),
+            step_mode,
+            step_rate: 1,
+            attributes: Self::vertex_attributes(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VertexStateDescriptor {
+    /// The format of the index buffer. This also determines the restart index for strip topologies.
     pub index_format: IndexFormat,
+    /// When `true`, an index with the maximum value of the `index_format` (e.g. `0xFFFF` for
+    /// `IndexFormat::U16`) restarts the strip. Only valid for strip topologies.
+    pub primitive_restart_enabled: bool,
     pub vertex_buffers: Vec<VertexBufferLayoutDescriptor>,
 }
 
@@ -827,23 +1718,114 @@ pub struct VertexStateDescriptor {
 pub struct RenderPipelineDescriptor {
     pub layout: PipelineLayout,
     pub vertex_stage: PipelineStageDescriptor,
-    pub fragment_stage: PipelineStageDescriptor,
+    /// The fragment shader stage. This may be `None` for depth-only pipelines (e.g. shadow maps and depth
+    /// pre-passes), in which case `color_states` must be empty.
+    pub fragment_stage: Option<PipelineStageDescriptor>,
     pub primitive_topology: PrimitiveTopology,
     pub rasterization_state: RasterizationStateDescriptor,
+    /// Color states that differ in blending or write mask require independent blending.
+    /// See `Adapter::supports_independent_blend`.
     pub color_states: Vec<ColorStateDescriptor>,
     pub depth_stencil_state: Option<DepthStencilStateDescriptor>,
     pub vertex_state: VertexStateDescriptor,
     pub sample_count: u32,
     pub alpha_to_coverage_enabled: bool,
+    /// The blend color that is set when the pipeline is bound. This is required if any color state uses
+    /// `BlendFactor::BlendColor` or `BlendFactor::OneMinusBlendColor`. Calling
+    /// `RenderPassEncoder::set_blend_color` after `set_pipeline` overrides the value.
+    pub blend_color: Option<Color>,
+}
+
+impl RenderPipelineDescriptor {
+    /// A single sampled pipeline that draws a triangle list without vertex buffers or a fragment
+    /// stage. Add a fragment stage and color states to render to color attachments.
+    pub fn new(layout: PipelineLayout, vertex_stage: PipelineStageDescriptor) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            layout,
+            vertex_stage,
+            fragment_stage: None,
+            primitive_topology: PrimitiveTopology::TriangleList,
+            rasterization_state: RasterizationStateDescriptor::default(),
+            color_states: vec![],
+            depth_stencil_state: None,
+            vertex_state: VertexStateDescriptor {
+                index_format: IndexFormat::U16,
+                primitive_restart_enabled: false,
+                vertex_buffers: vec![],
+            },
+            sample_count: 1,
+            alpha_to_coverage_enabled: false,
+            blend_color: None,
+        }
+    }
+
+    pub fn with_fragment_stage(mut self, fragment_stage: PipelineStageDescriptor) -> RenderPipelineDescriptor {
+        self.fragment_stage = Some(fragment_stage);
+        self
+    }
+
+    pub fn with_primitive_topology(mut self, primitive_topology: PrimitiveTopology) -> RenderPipelineDescriptor {
+        self.primitive_topology = primitive_topology;
+        self
+    }
+
+    pub fn with_rasterization_state(
+        mut self,
+        rasterization_state: RasterizationStateDescriptor,
+    ) -> RenderPipelineDescriptor {
+        self.rasterization_state = rasterization_state;
+        self
+    }
+
+    /// Appends a color state for the next color attachment.
+    pub fn with_color_state(mut self, color_state: ColorStateDescriptor) -> RenderPipelineDescriptor {
+        self.color_states.push(color_state);
+        self
+    }
+
+    pub fn with_depth_stencil_state(
+        mut self,
+        depth_stencil_state: DepthStencilStateDescriptor,
+    ) -> RenderPipelineDescriptor {
+        self.depth_stencil_state = Some(depth_stencil_state);
+        self
+    }
+
+    /// Appends a vertex buffer layout.
+    pub fn with_vertex_buffer(mut self, vertex_buffer: VertexBufferLayoutDescriptor) -> RenderPipelineDescriptor {
+        self.vertex_state.vertex_buffers.push(vertex_buffer);
+        self
+    }
+
+    pub fn with_index_format(mut self, index_format: IndexFormat) -> RenderPipelineDescriptor {
+        self.vertex_state.index_format = index_format;
+        self
+    }
+
+    pub fn with_sample_count(mut self, sample_count: u32) -> RenderPipelineDescriptor {
+        self.sample_count = sample_count;
+        self
+    }
+
+    pub fn with_alpha_to_coverage(mut self, alpha_to_coverage_enabled: bool) -> RenderPipelineDescriptor {
+        self.alpha_to_coverage_enabled = alpha_to_coverage_enabled;
+        self
+    }
+
+    pub fn with_blend_color(mut self, blend_color: Color) -> RenderPipelineDescriptor {
+        self.blend_color = Some(blend_color);
+        self
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct RenderPipeline {
     inner: Arc<imp::RenderPipelineInner>,
 }
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LoadOp {
     Clear,
     Load,
@@ -851,6 +1833,7 @@ pub enum LoadOp {
 
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StoreOp {
     Store,
 }
@@ -870,13 +1853,28 @@ pub struct RenderPassEncoder<'a> {
     inner: imp::RenderPassEncoderInner<'a>,
 }
 
+/// Encoders that record debug groups. Implemented for `CommandEncoder`, `ComputePassEncoder`,
+/// and `RenderPassEncoder`.
+pub trait DebugGroupEncoder {
+    fn push_debug_group(&mut self, group_label: &str);
+
+    fn pop_debug_group(&mut self);
+}
+
+/// A debug group that is popped when the guard is dropped. The guard dereferences to the
+/// encoder so that commands can be recorded within the group.
+#[derive(Debug)]
+pub struct DebugScopeGuard<'a, E: DebugGroupEncoder> {
+    encoder: &'a mut E,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct RenderPassColorAttachmentDescriptor<'a> {
     pub attachment: &'a TextureView,
     pub resolve_target: Option<&'a TextureView>,
     pub load_op: LoadOp,
     pub store_op: StoreOp,
-    pub clear_color: Color,
+    pub clear_value: ClearValue,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -894,6 +1892,83 @@ pub struct RenderPassDepthStencilAttachmentDescriptor<'a> {
 pub struct RenderPassDescriptor<'a> {
     pub color_attachments: &'a [RenderPassColorAttachmentDescriptor<'a>],
     pub depth_stencil_attachment: Option<RenderPassDepthStencilAttachmentDescriptor<'a>>,
+    /// When `true`, the viewports of the pass are flipped vertically with a negative height, so
+    /// that the Y axis of clip space points up and projection matrices don't need to invert Y.
+    /// Viewports set with `RenderPassEncoder::set_viewport` are flipped within the same bounds.
+    ///
+    /// Requires `AdapterFeatures::negative_viewport_height`.
+    pub flip_viewport: bool,
+    /// The initial viewport of the pass, or `None` to cover the attachments. Equivalent to calling
+    /// `RenderPassEncoder::set_viewport` at the start of the pass.
+    pub viewport: Option<Viewport>,
+    /// The initial scissor rectangle of the pass, or `None` to cover the attachments. Equivalent to
+    /// calling `RenderPassEncoder::set_scissor_rect` at the start of the pass.
+    pub scissor_rect: Option<ScissorRect>,
+    /// The framebuffer of a pass without attachments, e.g. a pass whose fragment shader only writes
+    /// to storage buffers or storage textures. Ignored if the pass has attachments. If `None`, a
+    /// pass without attachments is `1x1` with a single sample.
+    pub attachmentless: Option<AttachmentlessDescriptor>,
+}
+
+/// See `RenderPassDescriptor::attachmentless`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AttachmentlessDescriptor {
+    /// Must not exceed `VkPhysicalDeviceLimits::maxFramebufferWidth`
+    pub width: u32,
+    /// Must not exceed `VkPhysicalDeviceLimits::maxFramebufferHeight`
+    pub height: u32,
+    /// The rasterization sample count. Render pipelines used in the pass must have the same
+    /// `sample_count`, and must not have color or depth-stencil states.
+    pub sample_count: u32,
+}
+
+/// See `RenderPassEncoder::set_viewport`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub min_depth: f32,
+    pub max_depth: f32,
+}
+
+/// See `RenderPassEncoder::set_scissor_rect`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ScissorRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A buffer that captures vertex outputs. See `RenderPassEncoder::begin_transform_feedback`.
+#[derive(Clone, Copy, Debug)]
+pub struct TransformFeedbackBinding<'a> {
+    /// A buffer created with `BufferUsage::TRANSFORM_FEEDBACK`
+    pub buffer: &'a Buffer,
+    pub offset: usize,
+    /// The maximum number of bytes written. If `None`, the remainder of the buffer is used.
+    pub size: Option<usize>,
+    /// A buffer created with `BufferUsage::TRANSFORM_FEEDBACK_COUNTER` that receives the number
+    /// of bytes written when the transform feedback ends
+    pub counter_buffer: Option<&'a Buffer>,
+    pub counter_offset: usize,
+}
+
+/// The queries written at the beginning and end of a pass
+#[derive(Clone, Copy, Debug)]
+pub struct ComputePassTimestampWrites<'a> {
+    pub query_set: &'a QuerySet,
+    pub beginning_of_pass_write_index: Option<u32>,
+    pub end_of_pass_write_index: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ComputePassDescriptor<'a> {
+    /// When set, the pass is wrapped in a debug group with this label
+    pub label: Option<&'a str>,
+    pub timestamp_writes: Option<ComputePassTimestampWrites<'a>>,
 }
 
 #[derive(Debug)]
@@ -901,7 +1976,54 @@ pub struct CommandBuffer {
     inner: imp::CommandBufferInner,
 }
 
-pub struct CommandEncoderDescriptor {}
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CommandEncoderDescriptor<'a> {
+    /// When set, the commands are wrapped in a debug group with this label when submitted. The
+    /// Vulkan command buffer and the framebuffers created for the render passes are also named
+    /// with the label.
+    pub label: Option<&'a str>,
+    /// When `true`, the finished `CommandBuffer` may only be submitted once.
+    ///
+    /// A reusable command buffer is recorded once more when it's submitted for the second time and
+    /// the recorded Vulkan command buffer is reused by the following submissions. It's recorded
+    /// again if one of the resources that it uses is no longer in the state that it was recorded
+    /// for, e.g. because another command buffer used the resource in between.
+    pub one_time_submit: bool,
+}
+
+/// The required alignment of `TextureDataLayout::bytes_per_row` for copies between buffers and
+/// textures.
+pub const COPY_BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+/// Rounds `value` up to the next multiple of `alignment`, which must be a power of two.
+///
+/// ```
+/// assert_eq!(256, vki::align_to(200, 256));
+/// assert_eq!(512, vki::align_to(512, 256));
+/// ```
+pub fn align_to(value: usize, alignment: usize) -> usize {
+    debug_assert!(
+        alignment.is_power_of_two(),
+        "alignment must be a power of two: {}",
+        alignment
+    );
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+/// Describes the layout of texture data in a buffer.
+///
+/// For compressed formats, a row is a row of texel blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureDataLayout {
+    /// The offset of the first texel, in bytes.
+    pub offset: usize,
+    /// The stride between rows, in bytes. Must be a multiple of `COPY_BYTES_PER_ROW_ALIGNMENT` if
+    /// more than one row is copied. A value of `0` is only valid for single-row copies.
+    pub bytes_per_row: u32,
+    /// The number of rows in each array layer or depth slice. A value of `0` indicates that the
+    /// images are tightly packed according to the copy height.
+    pub rows_per_image: u32,
+}
 
 /// Specifies buffer to texture copy operation
 ///
@@ -909,10 +2031,7 @@ pub struct CommandEncoderDescriptor {}
 #[derive(Clone, Debug)]
 pub struct BufferCopyView<'a> {
     pub buffer: &'a Buffer,
-    pub offset: usize,
-    /// Specified in texels (Note that WebGPU currently uses rowPitch in bytes)
-    pub row_length: u32,
-    pub image_height: u32,
+    pub layout: TextureDataLayout,
 }
 
 #[derive(Clone, Debug)]
@@ -923,6 +2042,33 @@ pub struct TextureCopyView<'a> {
     pub origin: Origin3d,
 }
 
+/// A pool of device memory pages that back the tiles of sparse textures.
+///
+/// See `Texture::create_sparse_page_pool`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SparsePagePool {
+    inner: Arc<imp::SparsePagePoolInner>,
+}
+
+/// A page in a `SparsePagePool`.
+#[derive(Clone, Copy, Debug)]
+pub struct SparsePage<'a> {
+    pub pool: &'a SparsePagePool,
+    pub index: u32,
+}
+
+/// Binds (or unbinds) a single tile of a sparse texture. See `Queue::bind_sparse`.
+#[derive(Clone, Copy, Debug)]
+pub struct SparseTextureBind<'a> {
+    pub texture: &'a Texture,
+    pub mip_level: u32,
+    pub array_layer: u32,
+    /// The origin of the tile, in texels. Must be a multiple of `Texture::sparse_tile_size`.
+    pub origin: Origin3d,
+    /// The page that backs the tile, or `None` to unbind it.
+    pub page: Option<SparsePage<'a>>,
+}
+
 /// Not in the GPUWeb spec as of 2019/04/13
 #[derive(Clone, Debug)]
 pub struct TextureBlitView<'a> {
@@ -938,11 +2084,12 @@ pub struct TextureBlitView<'a> {
 ///
 /// Metal: `MTLDrawPrimitivesIndirectArguments`
 #[repr(C)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Pod, Zeroable)]
 pub struct DrawIndirectCommand {
     pub vertex_count: u32,
     pub instance_count: u32,
     pub first_vertex: u32,
+    /// Must be `0` unless `AdapterFeatures::draw_indirect_first_instance` is supported
     pub first_instance: i32,
 }
 
@@ -952,12 +2099,13 @@ pub struct DrawIndirectCommand {
 ///
 /// Metal: `MTLDrawIndexedPrimitivesIndirectArguments`
 #[repr(C)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Pod, Zeroable)]
 pub struct DrawIndexedIndirectCommand {
     pub vertex_count: u32,
     pub instance_count: u32,
     pub first_vertex: u32,
     pub base_vertex: i32,
+    /// Must be `0` unless `AdapterFeatures::draw_indirect_first_instance` is supported
     pub first_instance: i32,
 }
 
@@ -967,9 +2115,37 @@ pub struct DrawIndexedIndirectCommand {
 ///
 /// Metal: `MTLDispatchThreadgroupsIndirectArguments`
 #[repr(C)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Pod, Zeroable)]
 pub struct DispatchIndirectCommand {
     pub x: u32,
     pub y: u32,
     pub z: u32,
 }
+
+/// Compile-time assertions that the handle types can be shared between threads and that the
+/// encoders can be sent to other threads for recording.
+const _: fn() = || {
+    fn send_sync<T: Send + Sync>() {}
+    fn send<T: Send>() {}
+
+    send_sync::<Instance>();
+    send_sync::<Adapter>();
+    send_sync::<Device>();
+    send_sync::<Queue>();
+    send_sync::<Buffer>();
+    send_sync::<Texture>();
+    send_sync::<TextureView>();
+    send_sync::<Sampler>();
+    send_sync::<BindGroupLayout>();
+    send_sync::<BindGroup>();
+    send_sync::<ShaderModule>();
+    send_sync::<PipelineLayout>();
+    send_sync::<ComputePipeline>();
+    send_sync::<RenderPipeline>();
+    send_sync::<CommandBuffer>();
+    send_sync::<ResourceCreator>();
+
+    send::<CommandEncoder>();
+    send::<ComputePassEncoder>();
+    send::<RenderPassEncoder>();
+};
@@ -0,0 +1,303 @@
+//! Immediate mode drawing of lines and wireframe shapes for debugging.
+//!
+//! Shapes are queued each frame, copied into a vertex buffer that is reused between frames, and
+//! drawn as a line list with a single draw call. Matrices are column-major `[[f32; 4]; 4]` arrays,
+//! which is the layout used by `cgmath` and `glam`, e.g. `cgmath::Matrix4::into`.
+//!
+//! ```no_run
+//! # fn example(
+//! #     device: &vki::Device,
+//! #     render_pass: &mut vki::RenderPassEncoder,
+//! #     view_projection: [[f32; 4]; 4],
+//! # ) -> Result<(), vki::Error> {
+//! use vki::util::debug_draw::DebugDraw;
+//! use vki::{Color, TextureFormat};
+//!
+//! let mut debug_draw = DebugDraw::new(device, TextureFormat::B8G8R8A8UnormSRGB, Some(TextureFormat::D32Float), 1)?;
+//!
+//! let red = Color { r: 1.0, g: 0.0, b: 0.0, a: 1.0 };
+//! debug_draw.aabb([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0], red);
+//! debug_draw.axes(IDENTITY, 2.0);
+//! debug_draw.upload(device, view_projection)?;
+//! // ... before submitting the command buffer that contains the render pass ...
+//! debug_draw.render(render_pass)?;
+//! # Ok(())
+//! # }
+//! # const IDENTITY: [[f32; 4]; 4] = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+//! ```
+
+use crate::{
+    BlendDescriptor, BlendFactor, BlendOperation, Buffer, BufferDescriptor, BufferUsage, Color, ColorStateDescriptor,
+    ColorWrite, DepthStencilStateDescriptor, Device, Error, InputStepMode, PipelineLayoutDescriptor,
+    PipelineStageDescriptor, PrimitiveTopology, PushConstantRange, RenderPassEncoder, RenderPipeline,
+    RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderStage, TextureFormat, VertexAttributeDescriptor,
+    VertexBufferLayoutDescriptor, VertexFormat,
+};
+
+use bytemuck::{Pod, Zeroable};
+
+use std::borrow::Cow;
+
+const VERTEX_SPV: &[u8] = include_bytes!("shaders/debug_draw.vert.spv");
+const FRAGMENT_SPV: &[u8] = include_bytes!("shaders/debug_draw.frag.spv");
+
+/// The colors of the x, y, and z axes drawn by `DebugDraw::axes`
+const AXIS_COLORS: [Color; 3] = [
+    Color {
+        r: 1.0,
+        g: 0.0,
+        b: 0.0,
+        a: 1.0,
+    },
+    Color {
+        r: 0.0,
+        g: 1.0,
+        b: 0.0,
+        a: 1.0,
+    },
+    Color {
+        r: 0.0,
+        g: 0.0,
+        b: 1.0,
+        a: 1.0,
+    },
+];
+
+/// The pairs of box corners that form its edges. The corners are indexed by their bits:
+/// bit 0 selects the max x, bit 1 the max y, and bit 2 the max z.
+const BOX_EDGES: [(usize, usize); 12] = [
+    (0b000, 0b001),
+    (0b010, 0b011),
+    (0b100, 0b101),
+    (0b110, 0b111),
+    (0b000, 0b010),
+    (0b001, 0b011),
+    (0b100, 0b110),
+    (0b101, 0b111),
+    (0b000, 0b100),
+    (0b001, 0b101),
+    (0b010, 0b110),
+    (0b011, 0b111),
+];
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct Vertex {
+    position: [f32; 3],
+    color: [f32; 4],
+}
+
+/// Draws lines and wireframe shapes. See the module documentation for an example.
+#[derive(Debug)]
+pub struct DebugDraw {
+    pipeline: RenderPipeline,
+    vertex_buffer: Option<Buffer>,
+    /// Line vertices queued since the last `upload`
+    vertices: Vec<Vertex>,
+    /// The number of vertices copied by the last `upload`
+    vertex_count: u32,
+    view_projection: [[f32; 4]; 4],
+}
+
+impl DebugDraw {
+    /// Creates a debug draw pipeline for a render pass with a single color attachment of
+    /// `color_format`. When `depth_format` is `Some`, lines are depth tested against the scene but
+    /// do not write depth.
+    pub fn new(
+        device: &Device,
+        color_format: TextureFormat,
+        depth_format: Option<TextureFormat>,
+        sample_count: u32,
+    ) -> Result<DebugDraw, Error> {
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![],
+            push_constant_ranges: vec![PushConstantRange {
+                stages: ShaderStage::VERTEX,
+                offset: 0,
+                size: std::mem::size_of::<[[f32; 4]; 4]>(),
+            }],
+        })?;
+
+        let vertex_stage = PipelineStageDescriptor {
+            module: device.create_shader_module(ShaderModuleDescriptor { code: VERTEX_SPV })?,
+            entry_point: Cow::Borrowed("main"),
+        };
+
+        let fragment_stage = PipelineStageDescriptor {
+            module: device.create_shader_module(ShaderModuleDescriptor { code: FRAGMENT_SPV })?,
+            entry_point: Cow::Borrowed("main"),
+        };
+
+        let color_state = ColorStateDescriptor {
+            format: color_format,
+            color_blend: BlendDescriptor {
+                src_factor: BlendFactor::SrcAlpha,
+                dst_factor: BlendFactor::OneMinusSrcAlpha,
+                operation: BlendOperation::Add,
+            },
+            alpha_blend: BlendDescriptor {
+                src_factor: BlendFactor::One,
+                dst_factor: BlendFactor::OneMinusSrcAlpha,
+                operation: BlendOperation::Add,
+            },
+            write_mask: ColorWrite::ALL,
+        };
+
+        let vertex_buffer = VertexBufferLayoutDescriptor {
+            input_slot: 0,
+            stride: std::mem::size_of::<Vertex>(),
+            step_mode: InputStepMode::Vertex,
+            step_rate: 1,
+            attributes: vec![
+                VertexAttributeDescriptor {
+                    format: VertexFormat::Float3,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                VertexAttributeDescriptor {
+                    format: VertexFormat::Float4,
+                    offset: 12,
+                    shader_location: 1,
+                },
+            ],
+        };
+
+        let mut descriptor = RenderPipelineDescriptor::new(pipeline_layout, vertex_stage)
+            .with_fragment_stage(fragment_stage)
+            .with_primitive_topology(PrimitiveTopology::LineList)
+            .with_color_state(color_state)
+            .with_vertex_buffer(vertex_buffer)
+            .with_sample_count(sample_count);
+
+        if let Some(depth_format) = depth_format {
+            descriptor =
+                descriptor.with_depth_stencil_state(DepthStencilStateDescriptor::READ_ONLY.with_format(depth_format));
+        }
+
+        let pipeline = device.create_render_pipeline(descriptor)?;
+
+        Ok(DebugDraw {
+            pipeline,
+            vertex_buffer: None,
+            vertices: Vec::new(),
+            vertex_count: 0,
+            view_projection: [[0.0; 4]; 4],
+        })
+    }
+
+    /// Queues a line from `a` to `b`.
+    pub fn line(&mut self, a: [f32; 3], b: [f32; 3], color: Color) {
+        let color = [color.r, color.g, color.b, color.a];
+        self.vertices.push(Vertex { position: a, color });
+        self.vertices.push(Vertex { position: b, color });
+    }
+
+    /// Queues the edges of an axis aligned bounding box.
+    pub fn aabb(&mut self, min: [f32; 3], max: [f32; 3], color: Color) {
+        let corners = box_corners(|corner| corner, min, max);
+        self.box_edges(&corners, color);
+    }
+
+    /// Queues the edges of a bounding box in its local space, e.g. the bounds of a mesh, after
+    /// transforming it by `transform`.
+    pub fn oriented_box(&mut self, transform: [[f32; 4]; 4], min: [f32; 3], max: [f32; 3], color: Color) {
+        let corners = box_corners(|corner| transform_point(&transform, corner), min, max);
+        self.box_edges(&corners, color);
+    }
+
+    /// Queues the x (red), y (green), and z (blue) axes of `transform`, each `length` units long in
+    /// the local space of the transform.
+    pub fn axes(&mut self, transform: [[f32; 4]; 4], length: f32) {
+        let origin = transform_point(&transform, [0.0, 0.0, 0.0]);
+        for (axis, color) in AXIS_COLORS.iter().enumerate() {
+            let mut end = [0.0; 3];
+            end[axis] = length;
+            self.line(origin, transform_point(&transform, end), *color);
+        }
+    }
+
+    /// Queues the edges of the view frustum of a camera. `inverse_view_projection` is the inverse
+    /// of the camera's view-projection matrix, which maps clip space with a `0..1` depth range back
+    /// to world space.
+    pub fn frustum(&mut self, inverse_view_projection: [[f32; 4]; 4], color: Color) {
+        let corners = box_corners(
+            |corner| transform_point(&inverse_view_projection, corner),
+            [-1.0, -1.0, 0.0],
+            [1.0, 1.0, 1.0],
+        );
+        self.box_edges(&corners, color);
+    }
+
+    /// Copies the queued lines into the vertex buffer and clears the queue. The lines are drawn
+    /// with `view_projection`. The copy completes with the next submission, so this must be called
+    /// before submitting the command buffer that contains the `render` pass.
+    pub fn upload(&mut self, device: &Device, view_projection: [[f32; 4]; 4]) -> Result<(), Error> {
+        self.view_projection = view_projection;
+        self.vertex_count = self.vertices.len() as u32;
+
+        if !self.vertices.is_empty() {
+            let size = std::mem::size_of_val(self.vertices.as_slice());
+            let too_small = self
+                .vertex_buffer
+                .as_ref()
+                .map(|buffer| buffer.size() < size)
+                .unwrap_or(true);
+            if too_small {
+                self.vertex_buffer = Some(device.create_buffer(BufferDescriptor {
+                    size: size.next_power_of_two(),
+                    usage: BufferUsage::VERTEX | BufferUsage::COPY_DST,
+                    memory_hint: None,
+                })?);
+            }
+            let vertex_buffer = self.vertex_buffer.as_ref().expect("vertex_buffer");
+            device.get_queue().write_buffer(vertex_buffer, 0, &self.vertices)?;
+        }
+
+        self.vertices.clear();
+
+        Ok(())
+    }
+
+    /// Records the lines of the last `upload` into the render pass.
+    pub fn render(&self, render_pass: &mut RenderPassEncoder) -> Result<(), Error> {
+        let vertex_buffer = match self.vertex_buffer.as_ref() {
+            Some(vertex_buffer) if self.vertex_count > 0 => vertex_buffer,
+            _ => return Ok(()),
+        };
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_push_constants(ShaderStage::VERTEX, 0, self.view_projection)?;
+        render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0])?;
+        render_pass.draw(self.vertex_count, 1, 0, 0);
+
+        Ok(())
+    }
+
+    fn box_edges(&mut self, corners: &[[f32; 3]; 8], color: Color) {
+        for &(a, b) in BOX_EDGES.iter() {
+            self.line(corners[a], corners[b], color);
+        }
+    }
+}
+
+/// Returns the eight corners of the box from `min` to `max`, mapped by `f`. See `BOX_EDGES`.
+fn box_corners<F: Fn([f32; 3]) -> [f32; 3]>(f: F, min: [f32; 3], max: [f32; 3]) -> [[f32; 3]; 8] {
+    let mut corners = [[0.0; 3]; 8];
+    for (index, corner) in corners.iter_mut().enumerate() {
+        let x = if index & 0b001 != 0 { max[0] } else { min[0] };
+        let y = if index & 0b010 != 0 { max[1] } else { min[1] };
+        let z = if index & 0b100 != 0 { max[2] } else { min[2] };
+        *corner = f([x, y, z]);
+    }
+    corners
+}
+
+/// Transforms a point by a column-major matrix, including the perspective divide
+fn transform_point(m: &[[f32; 4]; 4], p: [f32; 3]) -> [f32; 3] {
+    let mut result = [0.0; 4];
+    for (row, value) in result.iter_mut().enumerate() {
+        *value = m[0][row] * p[0] + m[1][row] * p[1] + m[2][row] * p[2] + m[3][row];
+    }
+    let w = result[3];
+    [result[0] / w, result[1] / w, result[2] / w]
+}
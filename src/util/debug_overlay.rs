@@ -0,0 +1,479 @@
+//! Prints text and frame statistics on top of a rendered frame.
+//!
+//! The overlay uses a built-in 5x7 bitmap font that covers digits, letters, and common punctuation.
+//! Lowercase letters are drawn as uppercase and characters without a glyph are drawn as `?`. Each
+//! glyph is an instance of a single quad, so an entire frame of text is drawn with one draw call.
+//!
+//! ```no_run
+//! # fn example(device: &vki::Device, swapchain: &vki::Swapchain) -> Result<(), vki::Error> {
+//! use vki::util::debug_overlay::DebugOverlay;
+//! use vki::{Color, TextureFormat};
+//!
+//! let mut overlay = DebugOverlay::new(device, TextureFormat::B8G8R8A8UnormSRGB, 1)?;
+//!
+//! let frame = swapchain.acquire_next_image().expect("acquire_next_image");
+//! let mut encoder = device.create_command_encoder()?;
+//! // ... render the frame ...
+//! overlay.print_statistics(8.0, 8.0, device);
+//! overlay.print(8.0, 64.0, Color { r: 1.0, g: 1.0, b: 0.0, a: 1.0 }, "hello");
+//! overlay.draw(device, &mut encoder, &frame)?;
+//! device.get_queue().submit(&[encoder.finish()?])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource,
+    BindingType, BlendDescriptor, BlendFactor, BlendOperation, Buffer, BufferDescriptor, BufferUsage, Color,
+    ColorStateDescriptor, ColorWrite, CommandEncoder, Device, Error, Extent3d, InputStepMode, LoadOp, Origin3d,
+    PipelineLayoutDescriptor, PipelineStageDescriptor, PrimitiveTopology, PushConstantRange, RenderPassDescriptor,
+    RenderPassEncoder, RenderPipeline, RenderPipelineDescriptor, SamplerDescriptor, ShaderModuleDescriptor,
+    ShaderStage, SwapchainImage, Texture, TextureCopyView, TextureDataLayout, TextureDescriptor, TextureFormat,
+    TextureUsage, VertexAttributeDescriptor, VertexBufferLayoutDescriptor, VertexFormat, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use bytemuck::{Pod, Zeroable};
+
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
+
+const VERTEX_SPV: &[u8] = include_bytes!("shaders/debug_overlay.vert.spv");
+const FRAGMENT_SPV: &[u8] = include_bytes!("shaders/debug_overlay.frag.spv");
+
+/// The size of a glyph cell in texels. Glyphs are 5x7 and the remaining column and row separate
+/// adjacent characters and lines. This must match `CELL_SIZE` in the vertex shader.
+const CELL_WIDTH: u32 = 6;
+const CELL_HEIGHT: u32 = 8;
+
+const DEFAULT_COLOR: Color = Color {
+    r: 1.0,
+    g: 1.0,
+    b: 1.0,
+    a: 1.0,
+};
+
+/// The rows of each glyph from top to bottom. The most significant of the five bits is the
+/// leftmost pixel.
+#[rustfmt::skip]
+const GLYPHS: &[(char, [u8; 7])] = &[
+    (' ', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('!', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100]),
+    ('"', [0b01010, 0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('#', [0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010]),
+    ('%', [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011]),
+    ('\'', [0b00100, 0b00100, 0b00100, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('(', [0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010]),
+    (')', [0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000]),
+    ('*', [0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000]),
+    ('+', [0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000]),
+    (',', [0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000]),
+    ('-', [0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000]),
+    ('.', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100]),
+    ('/', [0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    (':', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000]),
+    (';', [0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000]),
+    ('<', [0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010]),
+    ('=', [0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000]),
+    ('>', [0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000]),
+    ('?', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100]),
+    ('A', [0b01110, 0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001]),
+    ('B', [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110]),
+    ('C', [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110]),
+    ('D', [0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('F', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('G', [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111]),
+    ('H', [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('I', [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('J', [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100]),
+    ('K', [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('N', [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('Q', [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('V', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100]),
+    ('W', [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010]),
+    ('X', [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001]),
+    ('Y', [0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100]),
+    ('Z', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111]),
+    ('[', [0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110]),
+    (']', [0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110]),
+    ('_', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111]),
+    ('|', [0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+];
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct GlyphInstance {
+    /// The top left corner of the glyph in pixels
+    position: [f32; 2],
+    /// The index of the glyph in `GLYPHS`
+    glyph: u32,
+    color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct PushConstants {
+    screen_size: [f32; 2],
+    scale: f32,
+}
+
+/// Tracks the time between calls to `DebugOverlay::print_statistics`
+#[derive(Debug)]
+struct FrameTimer {
+    last_frame: Option<Instant>,
+    /// Exponentially smoothed so that the printed value is readable
+    frame_time: Duration,
+}
+
+/// Draws text on top of a frame. See the module documentation for an example.
+///
+/// Text is queued with `print` and drawn with either `draw`, which records its own render pass
+/// into a swapchain image, or `upload` followed by `render` to draw into an existing pass.
+#[derive(Debug)]
+pub struct DebugOverlay {
+    pipeline: RenderPipeline,
+    bind_group: BindGroup,
+    instance_buffer: Option<Buffer>,
+    /// Glyphs queued by `print` since the last `upload`
+    glyphs: Vec<GlyphInstance>,
+    /// The number of glyphs copied by the last `upload`
+    glyph_count: u32,
+    screen_size: [f32; 2],
+    scale: u32,
+    timer: FrameTimer,
+}
+
+impl DebugOverlay {
+    /// Creates an overlay that draws into color attachments with `output_format` and `sample_count`.
+    pub fn new(device: &Device, output_format: TextureFormat, sample_count: u32) -> Result<DebugOverlay, Error> {
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::FRAGMENT,
+                    binding_type: BindingType::Sampler,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStage::FRAGMENT,
+                    binding_type: BindingType::SampledTexture,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+            ],
+        })?;
+
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout.clone()],
+            push_constant_ranges: vec![PushConstantRange {
+                stages: ShaderStage::VERTEX,
+                offset: 0,
+                size: std::mem::size_of::<PushConstants>(),
+            }],
+        })?;
+
+        let font = create_font_texture(device)?;
+
+        let bind_group = device.create_bind_group(BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: vec![
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Sampler(device.create_sampler(SamplerDescriptor::default())?),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(font.create_default_view()?),
+                },
+            ],
+        })?;
+
+        let vertex_stage = PipelineStageDescriptor {
+            module: device.create_shader_module(ShaderModuleDescriptor { code: VERTEX_SPV })?,
+            entry_point: Cow::Borrowed("main"),
+        };
+
+        let fragment_stage = PipelineStageDescriptor {
+            module: device.create_shader_module(ShaderModuleDescriptor { code: FRAGMENT_SPV })?,
+            entry_point: Cow::Borrowed("main"),
+        };
+
+        let color_state = ColorStateDescriptor {
+            format: output_format,
+            color_blend: BlendDescriptor {
+                src_factor: BlendFactor::SrcAlpha,
+                dst_factor: BlendFactor::OneMinusSrcAlpha,
+                operation: BlendOperation::Add,
+            },
+            alpha_blend: BlendDescriptor {
+                src_factor: BlendFactor::One,
+                dst_factor: BlendFactor::OneMinusSrcAlpha,
+                operation: BlendOperation::Add,
+            },
+            write_mask: ColorWrite::ALL,
+        };
+
+        let instance_buffer = VertexBufferLayoutDescriptor {
+            input_slot: 0,
+            stride: std::mem::size_of::<GlyphInstance>(),
+            step_mode: InputStepMode::Instance,
+            step_rate: 1,
+            attributes: vec![
+                VertexAttributeDescriptor {
+                    format: VertexFormat::Float2,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                VertexAttributeDescriptor {
+                    format: VertexFormat::UInt,
+                    offset: 8,
+                    shader_location: 1,
+                },
+                VertexAttributeDescriptor {
+                    format: VertexFormat::Float4,
+                    offset: 12,
+                    shader_location: 2,
+                },
+            ],
+        };
+
+        let pipeline = device.create_render_pipeline(
+            RenderPipelineDescriptor::new(pipeline_layout, vertex_stage)
+                .with_fragment_stage(fragment_stage)
+                .with_primitive_topology(PrimitiveTopology::TriangleStrip)
+                .with_color_state(color_state)
+                .with_vertex_buffer(instance_buffer)
+                .with_sample_count(sample_count),
+        )?;
+
+        Ok(DebugOverlay {
+            pipeline,
+            bind_group,
+            instance_buffer: None,
+            glyphs: Vec::new(),
+            glyph_count: 0,
+            screen_size: [0.0, 0.0],
+            scale: 2,
+            timer: FrameTimer {
+                last_frame: None,
+                frame_time: Duration::from_secs(0),
+            },
+        })
+    }
+
+    /// Sets the number of pixels per font texel. The default is `2`.
+    pub fn set_scale(&mut self, scale: u32) {
+        self.scale = scale.max(1);
+    }
+
+    /// The height of a line of text in pixels
+    pub fn line_height(&self) -> f32 {
+        (CELL_HEIGHT * self.scale) as f32
+    }
+
+    /// Queues `text` with its top left corner at (`x`, `y`) pixels. Newlines start a new line at `x`.
+    pub fn print(&mut self, x: f32, y: f32, color: Color, text: &str) {
+        let advance = (CELL_WIDTH * self.scale) as f32;
+        let line_height = self.line_height();
+        let color = [color.r, color.g, color.b, color.a];
+        let mut position = [x, y];
+        for c in text.chars() {
+            match c {
+                '\n' => {
+                    position = [x, position[1] + line_height];
+                    continue;
+                }
+                ' ' => {}
+                c => self.glyphs.push(GlyphInstance {
+                    position,
+                    glyph: glyph_index(c),
+                    color,
+                }),
+            }
+            position[0] += advance;
+        }
+    }
+
+    /// Queues the frame time and the device's `PoolStatistics` at (`x`, `y`) pixels. The frame
+    /// time is measured between calls, so this should be called once per frame.
+    pub fn print_statistics(&mut self, x: f32, y: f32, device: &Device) {
+        let now = Instant::now();
+        if let Some(last_frame) = self.timer.last_frame {
+            let elapsed = now - last_frame;
+            self.timer.frame_time = if self.timer.frame_time == Duration::from_secs(0) {
+                elapsed
+            } else {
+                self.timer.frame_time.mul_f64(0.9) + elapsed.mul_f64(0.1)
+            };
+        }
+        self.timer.last_frame = Some(now);
+
+        let frame_ms = self.timer.frame_time.as_secs_f64() * 1000.0;
+        let fps = if frame_ms > 0.0 { 1000.0 / frame_ms } else { 0.0 };
+        let pools = device.pool_statistics();
+        let text = format!(
+            "FRAME: {:.2} MS ({:.0} FPS)\nCOMMAND ENCODERS: {} ({} KB)\nRENDER PASSES: {}",
+            frame_ms,
+            fps,
+            pools.command_encoder_count,
+            pools.command_encoder_bytes / 1024,
+            pools.render_pass_count,
+        );
+        self.print(x, y, DEFAULT_COLOR, &text);
+    }
+
+    /// Copies the queued glyphs into the instance buffer and clears the queue. The copy completes
+    /// with the next submission, so this must be called before submitting the command buffer that
+    /// contains the `render` pass.
+    pub fn upload(&mut self, device: &Device, width: u32, height: u32) -> Result<(), Error> {
+        self.screen_size = [width as f32, height as f32];
+        self.glyph_count = self.glyphs.len() as u32;
+
+        if !self.glyphs.is_empty() {
+            let size = std::mem::size_of_val(self.glyphs.as_slice());
+            let too_small = self
+                .instance_buffer
+                .as_ref()
+                .map(|buffer| buffer.size() < size)
+                .unwrap_or(true);
+            if too_small {
+                self.instance_buffer = Some(device.create_buffer(BufferDescriptor {
+                    size: size.next_power_of_two(),
+                    usage: BufferUsage::VERTEX | BufferUsage::COPY_DST,
+                    memory_hint: None,
+                })?);
+            }
+            let instance_buffer = self.instance_buffer.as_ref().expect("instance_buffer");
+            device.get_queue().write_buffer(instance_buffer, 0, &self.glyphs)?;
+        }
+
+        self.glyphs.clear();
+
+        Ok(())
+    }
+
+    /// Records the glyphs of the last `upload` into the render pass.
+    pub fn render(&self, render_pass: &mut RenderPassEncoder) -> Result<(), Error> {
+        let instance_buffer = match self.instance_buffer.as_ref() {
+            Some(instance_buffer) if self.glyph_count > 0 => instance_buffer,
+            _ => return Ok(()),
+        };
+
+        let push_constants = PushConstants {
+            screen_size: self.screen_size,
+            scale: self.scale as f32,
+        };
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_push_constants(ShaderStage::VERTEX, 0, push_constants)?;
+        render_pass.set_bind_group(0, &self.bind_group, None);
+        render_pass.set_vertex_buffers(0, &[instance_buffer.clone()], &[0])?;
+        render_pass.draw(4, self.glyph_count, 0, 0);
+
+        Ok(())
+    }
+
+    /// Uploads the queued glyphs and records a render pass that draws them on top of the contents
+    /// of `frame`.
+    pub fn draw(&mut self, device: &Device, encoder: &mut CommandEncoder, frame: &SwapchainImage) -> Result<(), Error> {
+        let size = frame.texture.size();
+        self.upload(device, size.width, size.height)?;
+
+        let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
+            color_attachments: &[frame.color_attachment(
+                LoadOp::Load,
+                Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 0.0,
+                },
+            )],
+            depth_stencil_attachment: None,
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
+        });
+        self.render(&mut render_pass)?;
+        render_pass.end_pass();
+
+        Ok(())
+    }
+}
+
+fn glyph_index(c: char) -> u32 {
+    let c = c.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .position(|(glyph, _)| *glyph == c)
+        .or_else(|| GLYPHS.iter().position(|(glyph, _)| *glyph == '?'))
+        .expect("glyph") as u32
+}
+
+/// Creates the font atlas with all of the glyphs in a single row
+fn create_font_texture(device: &Device) -> Result<Texture, Error> {
+    let width = CELL_WIDTH * GLYPHS.len() as u32;
+    let height = CELL_HEIGHT;
+
+    // Rows are padded to `COPY_BYTES_PER_ROW_ALIGNMENT`
+    let align = COPY_BYTES_PER_ROW_ALIGNMENT as usize;
+    let bytes_per_row = (width as usize + align - 1) / align * align;
+    let mut texels = vec![0u8; bytes_per_row * height as usize];
+    for (index, (_, rows)) in GLYPHS.iter().enumerate() {
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..5 {
+                if row & (0b10000 >> x) != 0 {
+                    texels[y * bytes_per_row + index * CELL_WIDTH as usize + x] = 255;
+                }
+            }
+        }
+    }
+
+    let texture = device.create_texture(
+        TextureDescriptor::new_2d(TextureFormat::R8Unorm, width, height)
+            .with_usage(TextureUsage::SAMPLED | TextureUsage::COPY_DST),
+    )?;
+
+    device.get_queue().write_texture(
+        TextureCopyView {
+            texture: &texture,
+            mip_level: 0,
+            array_layer: 0,
+            origin: Origin3d { x: 0, y: 0, z: 0 },
+        },
+        &texels,
+        TextureDataLayout {
+            offset: 0,
+            bytes_per_row: bytes_per_row as u32,
+            rows_per_image: 0,
+        },
+        Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+    )?;
+
+    Ok(texture)
+}
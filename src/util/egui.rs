@@ -0,0 +1,443 @@
+//! Renders [egui](https://github.com/emilk/egui) user interfaces.
+//!
+//! The meshes produced by `egui::CtxRef::tessellate` are copied into vertex and index buffers
+//! that are reused between frames and drawn with a single pipeline. The font atlas is uploaded
+//! again whenever its version changes. Other textures can be drawn in the UI by registering a
+//! view with `EguiRenderer::register_texture`.
+//!
+//! The vertex colors are converted from sRGB in the vertex shader, so the output attachment is
+//! expected to use an sRGB format (e.g. `TextureFormat::B8G8R8A8UnormSRGB`).
+//!
+//! ```no_run
+//! # fn example(
+//! #     device: &vki::Device,
+//! #     ctx: &egui::CtxRef,
+//! #     shapes: Vec<egui::epaint::ClippedShape>,
+//! #     frame: &vki::SwapchainImage,
+//! # ) -> Result<(), vki::Error> {
+//! use vki::util::egui::{EguiRenderer, ScreenDescriptor};
+//! use vki::{Color, LoadOp, RenderPassDescriptor, TextureFormat};
+//!
+//! let mut renderer = EguiRenderer::new(device, TextureFormat::B8G8R8A8UnormSRGB, 1)?;
+//! let screen = ScreenDescriptor {
+//!     width: 1280,
+//!     height: 720,
+//!     pixels_per_point: 1.0,
+//! };
+//!
+//! let meshes = ctx.tessellate(shapes);
+//! renderer.update_font_texture(device, &ctx.texture())?;
+//! renderer.upload(device, &meshes, &screen)?;
+//!
+//! let clear_color = Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 };
+//! let mut encoder = device.create_command_encoder()?;
+//! let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
+//!     color_attachments: &[frame.color_attachment(LoadOp::Clear, clear_color)],
+//!     depth_stencil_attachment: None,
+//!     flip_viewport: false,
+//!     viewport: None,
+//!     scissor_rect: None,
+//!     attachmentless: None,
+//! });
+//! renderer.render(&mut render_pass, &screen)?;
+//! render_pass.end_pass();
+//! device.get_queue().submit(&[encoder.finish()?])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+    BindingResource, BindingType, BlendDescriptor, BlendFactor, BlendOperation, Buffer, BufferDescriptor, BufferUsage,
+    ColorStateDescriptor, ColorWrite, Device, Error, Extent3d, FilterMode, IndexFormat, InputStepMode, Origin3d,
+    PipelineLayoutDescriptor, PipelineStageDescriptor, PushConstantRange, RenderPassEncoder, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, SamplerDescriptor, ShaderModuleDescriptor, ShaderStage, TextureCopyView,
+    TextureDataLayout, TextureDescriptor, TextureFormat, TextureUsage, TextureView, VertexAttributeDescriptor,
+    VertexBufferLayoutDescriptor, VertexFormat, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use bytemuck::{Pod, Zeroable};
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+const VERTEX_SPV: &[u8] = include_bytes!("shaders/egui.vert.spv");
+const FRAGMENT_SPV: &[u8] = include_bytes!("shaders/egui.frag.spv");
+
+/// The format of the font atlas. Each texel stores the coverage in all four components, which
+/// matches the premultiplied white that egui uses for text.
+const FONT_FORMAT: TextureFormat = TextureFormat::R8G8B8A8Unorm;
+
+/// The size of the output attachment
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScreenDescriptor {
+    /// The width of the output attachment in pixels
+    pub width: u32,
+    /// The height of the output attachment in pixels
+    pub height: u32,
+    /// The number of pixels per egui point
+    pub pixels_per_point: f32,
+}
+
+impl ScreenDescriptor {
+    fn size_in_points(&self) -> [f32; 2] {
+        [
+            self.width as f32 / self.pixels_per_point,
+            self.height as f32 / self.pixels_per_point,
+        ]
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct Vertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [u8; 4],
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Draw {
+    texture_id: egui::TextureId,
+    /// (x, y, width, height) in pixels
+    scissor_rect: (u32, u32, u32, u32),
+    first_index: u32,
+    index_count: u32,
+    base_vertex: i32,
+}
+
+/// Draws egui meshes into a render pass. See the module documentation for an example.
+#[derive(Debug)]
+pub struct EguiRenderer {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+    /// The version and bind group of the font atlas
+    font_texture: Option<(u64, BindGroup)>,
+    /// Bind groups of the textures referenced by `egui::TextureId::User`
+    textures: HashMap<u64, BindGroup>,
+    next_texture_id: u64,
+    vertex_buffer: Option<Buffer>,
+    index_buffer: Option<Buffer>,
+    draws: Vec<Draw>,
+}
+
+impl EguiRenderer {
+    /// Creates a renderer that draws into color attachments with `output_format` and `sample_count`.
+    pub fn new(device: &Device, output_format: TextureFormat, sample_count: u32) -> Result<EguiRenderer, Error> {
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::FRAGMENT,
+                    binding_type: BindingType::Sampler,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStage::FRAGMENT,
+                    binding_type: BindingType::SampledTexture,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+            ],
+        })?;
+
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout.clone()],
+            push_constant_ranges: vec![PushConstantRange {
+                stages: ShaderStage::VERTEX,
+                offset: 0,
+                size: std::mem::size_of::<[f32; 2]>(),
+            }],
+        })?;
+
+        let sampler = device.create_sampler(SamplerDescriptor {
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..SamplerDescriptor::default()
+        })?;
+
+        let vertex_stage = PipelineStageDescriptor {
+            module: device.create_shader_module(ShaderModuleDescriptor { code: VERTEX_SPV })?,
+            entry_point: Cow::Borrowed("main"),
+        };
+
+        let fragment_stage = PipelineStageDescriptor {
+            module: device.create_shader_module(ShaderModuleDescriptor { code: FRAGMENT_SPV })?,
+            entry_point: Cow::Borrowed("main"),
+        };
+
+        // egui outputs premultiplied alpha
+        let color_state = ColorStateDescriptor {
+            format: output_format,
+            color_blend: BlendDescriptor {
+                src_factor: BlendFactor::One,
+                dst_factor: BlendFactor::OneMinusSrcAlpha,
+                operation: BlendOperation::Add,
+            },
+            alpha_blend: BlendDescriptor {
+                src_factor: BlendFactor::OneMinusDstAlpha,
+                dst_factor: BlendFactor::One,
+                operation: BlendOperation::Add,
+            },
+            write_mask: ColorWrite::ALL,
+        };
+
+        let vertex_buffer = VertexBufferLayoutDescriptor {
+            input_slot: 0,
+            stride: std::mem::size_of::<Vertex>(),
+            step_mode: InputStepMode::Vertex,
+            step_rate: 1,
+            attributes: vec![
+                VertexAttributeDescriptor {
+                    format: VertexFormat::Float2,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                VertexAttributeDescriptor {
+                    format: VertexFormat::Float2,
+                    offset: 8,
+                    shader_location: 1,
+                },
+                VertexAttributeDescriptor {
+                    format: VertexFormat::UChar4Norm,
+                    offset: 16,
+                    shader_location: 2,
+                },
+            ],
+        };
+
+        let pipeline = device.create_render_pipeline(
+            RenderPipelineDescriptor::new(pipeline_layout, vertex_stage)
+                .with_fragment_stage(fragment_stage)
+                .with_color_state(color_state)
+                .with_vertex_buffer(vertex_buffer)
+                .with_index_format(IndexFormat::U32)
+                .with_sample_count(sample_count),
+        )?;
+
+        Ok(EguiRenderer {
+            pipeline,
+            bind_group_layout,
+            sampler,
+            font_texture: None,
+            textures: HashMap::new(),
+            next_texture_id: 0,
+            vertex_buffer: None,
+            index_buffer: None,
+            draws: Vec::new(),
+        })
+    }
+
+    /// Registers a texture that can be drawn with the returned id, e.g. with `egui::Image`. The
+    /// texture is expected to contain premultiplied alpha.
+    pub fn register_texture(&mut self, device: &Device, view: &TextureView) -> Result<egui::TextureId, Error> {
+        let bind_group = self.create_bind_group(device, view)?;
+        let id = self.next_texture_id;
+        self.next_texture_id += 1;
+        self.textures.insert(id, bind_group);
+        Ok(egui::TextureId::User(id))
+    }
+
+    /// Releases a texture registered with `register_texture`.
+    pub fn unregister_texture(&mut self, id: egui::TextureId) {
+        if let egui::TextureId::User(id) = id {
+            self.textures.remove(&id);
+        }
+    }
+
+    /// Uploads the font atlas if its version changed since the last call. The upload completes
+    /// with the next submission.
+    pub fn update_font_texture(&mut self, device: &Device, font_texture: &egui::Texture) -> Result<(), Error> {
+        if let Some((version, _)) = self.font_texture {
+            if version == font_texture.version {
+                return Ok(());
+            }
+        }
+
+        let width = font_texture.width as u32;
+        let height = font_texture.height as u32;
+        let texture = device.create_texture(
+            TextureDescriptor::new_2d(FONT_FORMAT, width, height)
+                .with_usage(TextureUsage::SAMPLED | TextureUsage::COPY_DST),
+        )?;
+
+        // Rows are padded to `COPY_BYTES_PER_ROW_ALIGNMENT`
+        let row_size = 4 * width as usize;
+        let align = COPY_BYTES_PER_ROW_ALIGNMENT as usize;
+        let bytes_per_row = (row_size + align - 1) / align * align;
+        let mut texels = Vec::with_capacity(bytes_per_row * height as usize);
+        for row in font_texture.pixels.chunks(width as usize) {
+            let row_offset = texels.len();
+            texels.extend(row.iter().flat_map(|coverage| std::iter::repeat(*coverage).take(4)));
+            texels.resize(row_offset + bytes_per_row, 0);
+        }
+
+        device.get_queue().write_texture(
+            TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                array_layer: 0,
+                origin: Origin3d { x: 0, y: 0, z: 0 },
+            },
+            &texels,
+            TextureDataLayout {
+                offset: 0,
+                bytes_per_row: bytes_per_row as u32,
+                rows_per_image: 0,
+            },
+            Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+        )?;
+
+        let bind_group = self.create_bind_group(device, &texture.create_default_view()?)?;
+        self.font_texture = Some((font_texture.version, bind_group));
+
+        Ok(())
+    }
+
+    /// Copies the meshes into the vertex and index buffers, growing them as needed. The copies
+    /// complete with the next submission, so this must be called before submitting the command
+    /// buffer that contains the `render` pass.
+    pub fn upload(
+        &mut self,
+        device: &Device,
+        meshes: &[egui::ClippedMesh],
+        screen: &ScreenDescriptor,
+    ) -> Result<(), Error> {
+        self.draws.clear();
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        for egui::ClippedMesh(clip_rect, mesh) in meshes.iter() {
+            let scissor_rect = match scissor_rect(*clip_rect, screen) {
+                Some(scissor_rect) => scissor_rect,
+                None => continue,
+            };
+            if mesh.indices.is_empty() {
+                continue;
+            }
+            self.draws.push(Draw {
+                texture_id: mesh.texture_id,
+                scissor_rect,
+                first_index: indices.len() as u32,
+                index_count: mesh.indices.len() as u32,
+                base_vertex: vertices.len() as i32,
+            });
+            vertices.extend(mesh.vertices.iter().map(|vertex| Vertex {
+                position: [vertex.pos.x, vertex.pos.y],
+                uv: [vertex.uv.x, vertex.uv.y],
+                color: vertex.color.to_array(),
+            }));
+            indices.extend_from_slice(&mesh.indices);
+        }
+
+        if self.draws.is_empty() {
+            return Ok(());
+        }
+
+        let queue = device.get_queue();
+
+        let vertex_buffer = reserve_buffer(device, &mut self.vertex_buffer, BufferUsage::VERTEX, &vertices)?;
+        queue.write_buffer(vertex_buffer, 0, &vertices)?;
+
+        let index_buffer = reserve_buffer(device, &mut self.index_buffer, BufferUsage::INDEX, &indices)?;
+        queue.write_buffer(index_buffer, 0, &indices)?;
+
+        Ok(())
+    }
+
+    /// Records the draws of the last `upload` into the render pass. The screen must match the one
+    /// given to `upload`.
+    pub fn render(&self, render_pass: &mut RenderPassEncoder, screen: &ScreenDescriptor) -> Result<(), Error> {
+        let (vertex_buffer, index_buffer) = match (self.vertex_buffer.as_ref(), self.index_buffer.as_ref()) {
+            (Some(vertex_buffer), Some(index_buffer)) if !self.draws.is_empty() => (vertex_buffer, index_buffer),
+            _ => return Ok(()),
+        };
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_push_constants(ShaderStage::VERTEX, 0, screen.size_in_points())?;
+        render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0])?;
+        render_pass.set_index_buffer(index_buffer, 0);
+        render_pass.set_viewport(0.0, 0.0, screen.width as f32, screen.height as f32, 0.0, 1.0);
+
+        for draw in self.draws.iter() {
+            let bind_group = match draw.texture_id {
+                egui::TextureId::Egui => self.font_texture.as_ref().map(|(_, bind_group)| bind_group),
+                egui::TextureId::User(id) => self.textures.get(&id),
+            };
+            let bind_group = match bind_group {
+                Some(bind_group) => bind_group,
+                None => {
+                    log::warn!("egui texture is not available: {:?}", draw.texture_id);
+                    continue;
+                }
+            };
+            let (x, y, width, height) = draw.scissor_rect;
+            render_pass.set_bind_group(0, bind_group, None);
+            render_pass.set_scissor_rect(x, y, width, height);
+            render_pass.draw_indexed(draw.index_count, 1, draw.first_index, draw.base_vertex, 0);
+        }
+
+        render_pass.set_scissor_rect(0, 0, screen.width, screen.height);
+
+        Ok(())
+    }
+
+    fn create_bind_group(&self, device: &Device, view: &TextureView) -> Result<BindGroup, Error> {
+        device.create_bind_group(BindGroupDescriptor {
+            layout: self.bind_group_layout.clone(),
+            entries: vec![
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Sampler(self.sampler.clone()),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(view.clone()),
+                },
+            ],
+        })
+    }
+}
+
+/// Converts the clip rectangle from points to pixels and clamps it to the screen. Returns `None`
+/// if nothing would be drawn.
+fn scissor_rect(clip_rect: egui::Rect, screen: &ScreenDescriptor) -> Option<(u32, u32, u32, u32)> {
+    let scale = screen.pixels_per_point;
+    let min_x = (clip_rect.min.x * scale).round().max(0.0) as u32;
+    let min_y = (clip_rect.min.y * scale).round().max(0.0) as u32;
+    let max_x = ((clip_rect.max.x * scale).round().max(0.0) as u32).min(screen.width);
+    let max_y = ((clip_rect.max.y * scale).round().max(0.0) as u32).min(screen.height);
+    if min_x >= max_x || min_y >= max_y {
+        None
+    } else {
+        Some((min_x, min_y, max_x - min_x, max_y - min_y))
+    }
+}
+
+/// Returns a buffer large enough for `data`, replacing the existing buffer if it's too small.
+/// Buffers grow to the next power of two to avoid reallocating every frame.
+fn reserve_buffer<'a, T: Pod>(
+    device: &Device,
+    buffer: &'a mut Option<Buffer>,
+    usage: BufferUsage,
+    data: &[T],
+) -> Result<&'a Buffer, Error> {
+    let size = std::mem::size_of_val(data);
+    let too_small = buffer.as_ref().map(|buffer| buffer.size() < size).unwrap_or(true);
+    if too_small {
+        *buffer = Some(device.create_buffer(BufferDescriptor {
+            size: size.next_power_of_two(),
+            usage: usage | BufferUsage::COPY_DST,
+            memory_hint: None,
+        })?);
+    }
+    Ok(buffer.as_ref().expect("buffer"))
+}
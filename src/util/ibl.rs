@@ -0,0 +1,366 @@
+//! Compute based helpers for image based lighting.
+//!
+//! An HDR equirectangular environment map is converted into a cube map, which is then used to
+//! generate a diffuse irradiance map and a prefiltered specular map. The roughness of each
+//! specular mip level is `mip_level / (mip_level_count - 1)`.
+//!
+//! All of the output textures use `TextureFormat::RGBA16Float` and are created with
+//! `TextureUsage::SAMPLED`, so they can be bound directly with a `TextureViewDimension::Cube` view.
+//!
+//! ```no_run
+//! # fn example(device: &vki::Device, equirect: &vki::TextureView) -> Result<(), vki::Error> {
+//! use vki::util::ibl::IblGenerator;
+//!
+//! let generator = IblGenerator::new(device)?;
+//! let mut encoder = device.create_command_encoder()?;
+//! let environment = generator.equirect_to_cube(device, &mut encoder, equirect, 512)?;
+//! let irradiance = generator.irradiance(device, &mut encoder, &environment, 32)?;
+//! let specular = generator.prefilter_specular(device, &mut encoder, &environment, 128, 5)?;
+//! device.get_queue().submit(&[encoder.finish()?])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    AddressMode, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+    BindingResource, BindingType, CommandEncoder, ComputePipeline, ComputePipelineDescriptor, Device, Error, Extent3d,
+    FilterMode, Origin3d, PipelineLayoutDescriptor, PipelineStageDescriptor, PushConstantRange, Sampler,
+    SamplerDescriptor, ShaderModuleDescriptor, ShaderStage, Texture, TextureAspect, TextureBlitView, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureUsage, TextureView, TextureViewDescriptor, TextureViewDimension,
+};
+
+use std::borrow::Cow;
+
+const EQUIRECT_TO_CUBE_SPV: &[u8] = include_bytes!("shaders/ibl.equirect_to_cube.comp.spv");
+const IRRADIANCE_SPV: &[u8] = include_bytes!("shaders/ibl.irradiance.comp.spv");
+const PREFILTER_SPV: &[u8] = include_bytes!("shaders/ibl.prefilter.comp.spv");
+
+/// Must match `local_size_x` and `local_size_y` in the compute shaders
+const WORKGROUP_SIZE: u32 = 8;
+
+/// The format of all generated cube maps
+pub const CUBE_FORMAT: TextureFormat = TextureFormat::RGBA16Float;
+
+/// The default number of azimuth steps used for the irradiance convolution
+pub const DEFAULT_IRRADIANCE_SAMPLE_COUNT: u32 = 64;
+
+/// The default number of GGX importance samples per texel used for the specular prefilter
+pub const DEFAULT_PREFILTER_SAMPLE_COUNT: u32 = 1024;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct PushConstants {
+    roughness: f32,
+    sample_count: u32,
+}
+
+/// Holds the compute pipelines used to generate image based lighting maps.
+///
+/// The generator can be reused for any number of environment maps.
+#[derive(Clone, Debug)]
+pub struct IblGenerator {
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+    equirect_to_cube_pipeline: ComputePipeline,
+    irradiance_pipeline: ComputePipeline,
+    prefilter_pipeline: ComputePipeline,
+    /// The number of azimuth steps used for the irradiance convolution
+    pub irradiance_sample_count: u32,
+    /// The number of GGX importance samples per texel used for the specular prefilter
+    pub prefilter_sample_count: u32,
+}
+
+impl IblGenerator {
+    pub fn new(device: &Device) -> Result<IblGenerator, Error> {
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::COMPUTE,
+                    binding_type: BindingType::Sampler,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStage::COMPUTE,
+                    binding_type: BindingType::SampledTexture,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStage::COMPUTE,
+                    binding_type: BindingType::WriteOnlyStorageTexture,
+                    storage_texture_format: Some(CUBE_FORMAT),
+                    min_binding_size: None,
+                },
+            ],
+        })?;
+
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout.clone()],
+            push_constant_ranges: vec![PushConstantRange {
+                stages: ShaderStage::COMPUTE,
+                offset: 0,
+                size: std::mem::size_of::<PushConstants>(),
+            }],
+        })?;
+
+        let sampler = device.create_sampler(SamplerDescriptor {
+            address_mode_u: AddressMode::Repeat,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            ..SamplerDescriptor::default()
+        })?;
+
+        let create_pipeline = |code: &[u8]| {
+            let module = device.create_shader_module(ShaderModuleDescriptor { code })?;
+            device.create_compute_pipeline(ComputePipelineDescriptor {
+                layout: pipeline_layout.clone(),
+                compute_stage: PipelineStageDescriptor {
+                    module,
+                    entry_point: Cow::Borrowed("main"),
+                },
+                required_subgroup_size: None,
+            })
+        };
+
+        Ok(IblGenerator {
+            equirect_to_cube_pipeline: create_pipeline(EQUIRECT_TO_CUBE_SPV)?,
+            irradiance_pipeline: create_pipeline(IRRADIANCE_SPV)?,
+            prefilter_pipeline: create_pipeline(PREFILTER_SPV)?,
+            bind_group_layout,
+            sampler,
+            irradiance_sample_count: DEFAULT_IRRADIANCE_SAMPLE_COUNT,
+            prefilter_sample_count: DEFAULT_PREFILTER_SAMPLE_COUNT,
+        })
+    }
+
+    /// Converts an equirectangular 2D texture into a cube map with a full mip chain.
+    ///
+    /// The lower mip levels are generated with linear blits so that the result can be used as the
+    /// source for `irradiance` and `prefilter_specular`.
+    pub fn equirect_to_cube(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        equirect: &TextureView,
+        size: u32,
+    ) -> Result<Texture, Error> {
+        let mip_level_count = 32 - size.max(1).leading_zeros();
+        let texture = create_cube_texture(
+            device,
+            size,
+            mip_level_count,
+            TextureUsage::COPY_SRC | TextureUsage::COPY_DST,
+        )?;
+
+        self.dispatch(
+            device,
+            encoder,
+            &self.equirect_to_cube_pipeline,
+            equirect,
+            &texture,
+            0,
+            PushConstants {
+                roughness: 0.0,
+                sample_count: 0,
+            },
+        )?;
+
+        generate_cube_mipmaps(encoder, &texture);
+
+        Ok(texture)
+    }
+
+    /// Generates a diffuse irradiance cube map from an environment cube map.
+    pub fn irradiance(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        environment: &Texture,
+        size: u32,
+    ) -> Result<Texture, Error> {
+        let texture = create_cube_texture(device, size, 1, TextureUsage::NONE)?;
+        let source = cube_view(environment, 0, environment.mip_level_count())?;
+
+        self.dispatch(
+            device,
+            encoder,
+            &self.irradiance_pipeline,
+            &source,
+            &texture,
+            0,
+            PushConstants {
+                roughness: 0.0,
+                sample_count: self.irradiance_sample_count,
+            },
+        )?;
+
+        Ok(texture)
+    }
+
+    /// Generates a prefiltered specular cube map from an environment cube map. Each mip level is
+    /// filtered with an increasing GGX roughness, from `0.0` at the base level to `1.0` at the last.
+    pub fn prefilter_specular(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        environment: &Texture,
+        size: u32,
+        mip_level_count: u32,
+    ) -> Result<Texture, Error> {
+        let max_mip_level_count = 32 - size.max(1).leading_zeros();
+        if mip_level_count == 0 || mip_level_count > max_mip_level_count {
+            return Err(Error::from(format!(
+                "ibl: invalid mip_level_count: {} (maximum for size {} is {})",
+                mip_level_count, size, max_mip_level_count
+            )));
+        }
+
+        let texture = create_cube_texture(device, size, mip_level_count, TextureUsage::NONE)?;
+        let source = cube_view(environment, 0, environment.mip_level_count())?;
+
+        for mip_level in 0..mip_level_count {
+            let roughness = if mip_level_count > 1 {
+                mip_level as f32 / (mip_level_count - 1) as f32
+            } else {
+                0.0
+            };
+            self.dispatch(
+                device,
+                encoder,
+                &self.prefilter_pipeline,
+                &source,
+                &texture,
+                mip_level,
+                PushConstants {
+                    roughness,
+                    sample_count: self.prefilter_sample_count,
+                },
+            )?;
+        }
+
+        Ok(texture)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn dispatch(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        pipeline: &ComputePipeline,
+        source: &TextureView,
+        target: &Texture,
+        mip_level: u32,
+        push_constants: PushConstants,
+    ) -> Result<(), Error> {
+        let target_view = cube_view(target, mip_level, 1)?;
+
+        let bind_group = device.create_bind_group(BindGroupDescriptor {
+            layout: self.bind_group_layout.clone(),
+            entries: vec![
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Sampler(self.sampler.clone()),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(source.clone()),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(target_view),
+                },
+            ],
+        })?;
+
+        let mip_size = (target.size().width >> mip_level).max(1);
+        let workgroup_count = (mip_size + WORKGROUP_SIZE - 1) / WORKGROUP_SIZE;
+
+        let mut compute_pass = encoder.begin_compute_pass();
+        compute_pass.set_pipeline(pipeline);
+        compute_pass.set_bind_group(0, &bind_group, None);
+        compute_pass.set_push_constants(ShaderStage::COMPUTE, 0, push_constants)?;
+        compute_pass.dispatch(workgroup_count, workgroup_count, 6);
+        compute_pass.end_pass();
+
+        Ok(())
+    }
+}
+
+fn create_cube_texture(
+    device: &Device,
+    size: u32,
+    mip_level_count: u32,
+    usage: TextureUsage,
+) -> Result<Texture, Error> {
+    device.create_texture(TextureDescriptor {
+        size: Extent3d {
+            width: size,
+            height: size,
+            depth: 1,
+        },
+        array_layer_count: 6,
+        mip_level_count,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: CUBE_FORMAT,
+        usage: TextureUsage::SAMPLED | TextureUsage::STORAGE | usage,
+    })
+}
+
+fn cube_view(texture: &Texture, base_mip_level: u32, mip_level_count: u32) -> Result<TextureView, Error> {
+    texture.create_view(TextureViewDescriptor {
+        format: CUBE_FORMAT,
+        dimension: TextureViewDimension::Cube,
+        aspect: TextureAspect::COLOR,
+        base_mip_level,
+        mip_level_count,
+        base_array_layer: 0,
+        array_layer_count: 6,
+    })
+}
+
+fn generate_cube_mipmaps(encoder: &mut CommandEncoder, texture: &Texture) {
+    let size = texture.size().width;
+    for face in 0..6 {
+        for mip_level in 1..texture.mip_level_count() {
+            let src_size = (size >> (mip_level - 1)).max(1) as i32;
+            let dst_size = (size >> mip_level).max(1) as i32;
+            encoder.blit_texture_to_texture(
+                TextureBlitView {
+                    texture,
+                    mip_level: mip_level - 1,
+                    array_layer: face,
+                    bounds: [
+                        Origin3d { x: 0, y: 0, z: 0 },
+                        Origin3d {
+                            x: src_size,
+                            y: src_size,
+                            z: 1,
+                        },
+                    ],
+                },
+                TextureBlitView {
+                    texture,
+                    mip_level,
+                    array_layer: face,
+                    bounds: [
+                        Origin3d { x: 0, y: 0, z: 0 },
+                        Origin3d {
+                            x: dst_size,
+                            y: dst_size,
+                            z: 1,
+                        },
+                    ],
+                },
+                FilterMode::Linear,
+            );
+        }
+    }
+}
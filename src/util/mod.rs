@@ -0,0 +1,22 @@
+//! Optional utilities built on top of the core API. Each module is enabled with a cargo feature.
+
+#[cfg(feature = "debug-draw")]
+pub mod debug_draw;
+
+#[cfg(feature = "debug-overlay")]
+pub mod debug_overlay;
+
+#[cfg(feature = "egui")]
+pub mod egui;
+
+#[cfg(feature = "ibl")]
+pub mod ibl;
+
+#[cfg(feature = "texture-atlas")]
+pub mod texture_atlas;
+
+#[cfg(feature = "texture-loader")]
+pub mod texture_loader;
+
+#[cfg(feature = "upload-queue")]
+pub mod upload_queue;
@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec2 v_texcoord;
+layout(location = 1) flat in vec4 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler u_sampler;
+layout(set = 0, binding = 1) uniform texture2D u_font;
+
+void main() {
+    float coverage = texelFetch(sampler2D(u_font, u_sampler), ivec2(v_texcoord), 0).r;
+    if (coverage == 0.0) {
+        discard;
+    }
+    f_color = v_color;
+}
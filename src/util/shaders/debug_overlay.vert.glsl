@@ -0,0 +1,30 @@
+#version 450
+
+// The size of a glyph cell in the font atlas, in texels
+const vec2 CELL_SIZE = vec2(6.0, 8.0);
+
+const vec2 CORNERS[4] = vec2[](vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(0.0, 1.0), vec2(1.0, 1.0));
+
+// Per instance
+layout(location = 0) in vec2 a_position;
+layout(location = 1) in uint a_glyph;
+layout(location = 2) in vec4 a_color;
+
+layout(location = 0) out vec2 v_texcoord;
+layout(location = 1) flat out vec4 v_color;
+
+layout(push_constant) uniform PushConstants {
+    // The size of the screen in pixels
+    vec2 u_screen_size;
+    // The number of pixels per font texel
+    float u_scale;
+};
+
+void main() {
+    vec2 corner = CORNERS[gl_VertexIndex];
+    // The glyphs are stored left to right in a single row
+    v_texcoord = (vec2(float(a_glyph), 0.0) + corner) * CELL_SIZE;
+    v_color = a_color;
+    vec2 position = a_position + corner * CELL_SIZE * u_scale;
+    gl_Position = vec4(2.0 * position / u_screen_size - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,28 @@
+#version 450
+
+layout(location = 0) in vec2 a_position;
+layout(location = 1) in vec2 a_uv;
+layout(location = 2) in vec4 a_color;
+
+layout(location = 0) out vec2 v_uv;
+layout(location = 1) out vec4 v_color;
+
+layout(push_constant) uniform PushConstants {
+    // The size of the screen in points
+    vec2 u_screen_size;
+};
+
+// egui vertex colors are premultiplied sRGB
+vec3 linear_from_srgb(vec3 srgb) {
+    bvec3 cutoff = lessThan(srgb, vec3(0.04045));
+    vec3 lower = srgb / 12.92;
+    vec3 higher = pow((srgb + 0.055) / 1.055, vec3(2.4));
+    return mix(higher, lower, cutoff);
+}
+
+void main() {
+    v_uv = a_uv;
+    v_color = vec4(linear_from_srgb(a_color.rgb), a_color.a);
+    // egui's y axis points down, like Vulkan's clip space
+    gl_Position = vec4(2.0 * a_position / u_screen_size - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,35 @@
+#version 450
+
+layout (local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform sampler u_sampler;
+layout(set = 0, binding = 1) uniform texture2D u_source;
+layout(set = 0, binding = 2, rgba16f) uniform writeonly imageCube u_target;
+
+const float PI = 3.14159265359;
+
+vec3 cube_direction(uvec3 id, vec2 size) {
+    vec2 uv = (vec2(id.xy) + 0.5) / size * 2.0 - 1.0;
+    switch (id.z) {
+        case 0: return normalize(vec3(1.0, -uv.y, -uv.x));
+        case 1: return normalize(vec3(-1.0, -uv.y, uv.x));
+        case 2: return normalize(vec3(uv.x, 1.0, uv.y));
+        case 3: return normalize(vec3(uv.x, -1.0, -uv.y));
+        case 4: return normalize(vec3(uv.x, -uv.y, 1.0));
+        default: return normalize(vec3(-uv.x, -uv.y, -1.0));
+    }
+}
+
+void main() {
+    ivec2 size = imageSize(u_target);
+    if (any(greaterThanEqual(gl_GlobalInvocationID.xy, uvec2(size)))) {
+        return;
+    }
+
+    vec3 direction = cube_direction(gl_GlobalInvocationID, vec2(size));
+    vec2 uv = vec2(atan(direction.z, direction.x) / (2.0 * PI) + 0.5, acos(clamp(direction.y, -1.0, 1.0)) / PI);
+
+    vec4 color = textureLod(sampler2D(u_source, u_sampler), uv, 0.0);
+
+    imageStore(u_target, ivec3(gl_GlobalInvocationID), vec4(color.rgb, 1.0));
+}
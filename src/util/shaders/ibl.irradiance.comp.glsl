@@ -0,0 +1,59 @@
+#version 450
+
+layout (local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform sampler u_sampler;
+layout(set = 0, binding = 1) uniform textureCube u_source;
+layout(set = 0, binding = 2, rgba16f) uniform writeonly imageCube u_target;
+
+layout(push_constant) uniform PushConstants {
+    float u_roughness;
+    uint u_sample_count;
+};
+
+const float PI = 3.14159265359;
+
+vec3 cube_direction(uvec3 id, vec2 size) {
+    vec2 uv = (vec2(id.xy) + 0.5) / size * 2.0 - 1.0;
+    switch (id.z) {
+        case 0: return normalize(vec3(1.0, -uv.y, -uv.x));
+        case 1: return normalize(vec3(-1.0, -uv.y, uv.x));
+        case 2: return normalize(vec3(uv.x, 1.0, uv.y));
+        case 3: return normalize(vec3(uv.x, -1.0, -uv.y));
+        case 4: return normalize(vec3(uv.x, -uv.y, 1.0));
+        default: return normalize(vec3(-uv.x, -uv.y, -1.0));
+    }
+}
+
+void main() {
+    ivec2 size = imageSize(u_target);
+    if (any(greaterThanEqual(gl_GlobalInvocationID.xy, uvec2(size)))) {
+        return;
+    }
+
+    vec3 normal = cube_direction(gl_GlobalInvocationID, vec2(size));
+    vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+    vec3 right = normalize(cross(up, normal));
+    up = cross(normal, right);
+
+    // Uniformly step over the hemisphere; u_sample_count is the number of steps around the azimuth
+    uint phi_steps = max(u_sample_count, 1);
+    uint theta_steps = max(phi_steps / 4, 1);
+    float phi_delta = 2.0 * PI / float(phi_steps);
+    float theta_delta = 0.5 * PI / float(theta_steps);
+
+    vec3 irradiance = vec3(0.0);
+    for (uint p = 0; p < phi_steps; ++p) {
+        float phi = (float(p) + 0.5) * phi_delta;
+        for (uint t = 0; t < theta_steps; ++t) {
+            float theta = (float(t) + 0.5) * theta_delta;
+            vec3 tangent_sample = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 sample_direction = tangent_sample.x * right + tangent_sample.y * up + tangent_sample.z * normal;
+            irradiance += textureLod(samplerCube(u_source, u_sampler), sample_direction, 0.0).rgb * cos(theta) * sin(theta);
+        }
+    }
+
+    irradiance = PI * irradiance / float(phi_steps * theta_steps);
+
+    imageStore(u_target, ivec3(gl_GlobalInvocationID), vec4(irradiance, 1.0));
+}
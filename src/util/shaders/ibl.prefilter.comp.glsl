@@ -0,0 +1,99 @@
+#version 450
+
+layout (local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform sampler u_sampler;
+layout(set = 0, binding = 1) uniform textureCube u_source;
+layout(set = 0, binding = 2, rgba16f) uniform writeonly imageCube u_target;
+
+layout(push_constant) uniform PushConstants {
+    float u_roughness;
+    uint u_sample_count;
+};
+
+const float PI = 3.14159265359;
+
+vec3 cube_direction(uvec3 id, vec2 size) {
+    vec2 uv = (vec2(id.xy) + 0.5) / size * 2.0 - 1.0;
+    switch (id.z) {
+        case 0: return normalize(vec3(1.0, -uv.y, -uv.x));
+        case 1: return normalize(vec3(-1.0, -uv.y, uv.x));
+        case 2: return normalize(vec3(uv.x, 1.0, uv.y));
+        case 3: return normalize(vec3(uv.x, -1.0, -uv.y));
+        case 4: return normalize(vec3(uv.x, -uv.y, 1.0));
+        default: return normalize(vec3(-uv.x, -uv.y, -1.0));
+    }
+}
+
+float radical_inverse_vdc(uint bits) {
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return float(bits) * 2.3283064365386963e-10;
+}
+
+vec2 hammersley(uint i, uint n) {
+    return vec2(float(i) / float(n), radical_inverse_vdc(i));
+}
+
+vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness) {
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+
+    vec3 h = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+    vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, normal));
+    vec3 bitangent = cross(normal, tangent);
+
+    return normalize(tangent * h.x + bitangent * h.y + normal * h.z);
+}
+
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+void main() {
+    ivec2 size = imageSize(u_target);
+    if (any(greaterThanEqual(gl_GlobalInvocationID.xy, uvec2(size)))) {
+        return;
+    }
+
+    // Assume that the view direction is equal to the normal and the reflection direction
+    vec3 normal = cube_direction(gl_GlobalInvocationID, vec2(size));
+
+    float source_size = float(textureSize(samplerCube(u_source, u_sampler), 0).x);
+    float texel_solid_angle = 4.0 * PI / (6.0 * source_size * source_size);
+    float max_lod = float(textureQueryLevels(samplerCube(u_source, u_sampler)) - 1);
+
+    vec3 color = vec3(0.0);
+    float total_weight = 0.0;
+
+    uint sample_count = max(u_sample_count, 1);
+    for (uint i = 0; i < sample_count; ++i) {
+        vec2 xi = hammersley(i, sample_count);
+        vec3 h = importance_sample_ggx(xi, normal, u_roughness);
+        vec3 l = normalize(2.0 * dot(normal, h) * h - normal);
+
+        float n_dot_l = max(dot(normal, l), 0.0);
+        if (n_dot_l > 0.0) {
+            // Sample from a lower mip level to reduce aliasing (GPU Gems 3, chapter 20)
+            float n_dot_h = max(dot(normal, h), 0.0);
+            float pdf = distribution_ggx(n_dot_h, u_roughness) * 0.25 + 0.0001;
+            float sample_solid_angle = 1.0 / (float(sample_count) * pdf + 0.0001);
+            float lod = u_roughness == 0.0 ? 0.0 : 0.5 * log2(sample_solid_angle / texel_solid_angle);
+
+            color += textureLod(samplerCube(u_source, u_sampler), l, clamp(lod, 0.0, max_lod)).rgb * n_dot_l;
+            total_weight += n_dot_l;
+        }
+    }
+
+    imageStore(u_target, ivec3(gl_GlobalInvocationID), vec4(color / max(total_weight, 0.0001), 1.0));
+}
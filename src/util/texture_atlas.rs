@@ -0,0 +1,326 @@
+//! Packs many small images into the layers of a single texture.
+//!
+//! Images are placed in rows ("shelves") sorted by height, and a new array layer is started when
+//! a layer is full. All images are uploaded with a single staging buffer, and each image is
+//! returned with the transform that maps its own texture coordinates into the atlas, so that
+//! hundreds of sprites or icons can share one texture and one bind group.
+//!
+//! ```no_run
+//! # fn example(device: &vki::Device, icons: &[(Vec<u8>, u32, u32)]) -> Result<(), vki::Error> {
+//! use vki::util::texture_atlas::{self, AtlasDescriptor, AtlasImage};
+//! use vki::{TextureFormat, TextureUsage};
+//!
+//! let images: Vec<AtlasImage> = icons
+//!     .iter()
+//!     .map(|(data, width, height)| AtlasImage { data, width: *width, height: *height })
+//!     .collect();
+//!
+//! let descriptor = AtlasDescriptor {
+//!     format: TextureFormat::R8G8B8A8UnormSRGB,
+//!     usage: TextureUsage::SAMPLED,
+//!     layer_size: 1024,
+//!     padding: 1,
+//! };
+//!
+//! let mut encoder = device.create_command_encoder()?;
+//! let atlas = texture_atlas::pack(device, &mut encoder, descriptor, &images)?;
+//! device.get_queue().submit(&[encoder.finish()?])?;
+//!
+//! // In the shader: texture(sampler2DArray(...), vec3(uv_offset + uv * uv_scale, array_layer))
+//! let entry = atlas.entries[0];
+//! println!("{:?} {:?} {}", entry.uv_offset, entry.uv_scale, entry.array_layer);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::imp;
+use crate::{
+    align_to, BufferCopyView, BufferDescriptor, BufferUsage, CommandEncoder, Device, Error, Extent3d, Origin3d,
+    Texture, TextureCopyView, TextureDataLayout, TextureDescriptor, TextureFormat, TextureUsage,
+    COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+/// Staging buffer offsets must be a multiple of the texel size and `4`.
+const STAGING_ALIGNMENT: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AtlasDescriptor {
+    /// The format of the atlas and the images. Compressed formats are not supported.
+    pub format: TextureFormat,
+    /// The usage of the atlas texture. `COPY_DST` is added implicitly.
+    pub usage: TextureUsage,
+    /// The width and height of each array layer in texels. Use the size of the largest image (plus
+    /// padding) to place each image in its own layer.
+    pub layer_size: u32,
+    /// The number of texels around each image that repeat its edge texels. A padding of at least
+    /// `1` prevents neighboring images from bleeding into each other with linear filtering.
+    pub padding: u32,
+}
+
+/// An image of tightly packed texels in the format of the atlas
+#[derive(Clone, Copy, Debug)]
+pub struct AtlasImage<'a> {
+    pub data: &'a [u8],
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The placement of an image in the atlas
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasEntry {
+    pub array_layer: u32,
+    /// The texel offset of the image in its array layer, excluding the padding
+    pub origin: Origin3d,
+    pub size: Extent3d,
+    /// The atlas texture coordinates of the image's top left corner
+    pub uv_offset: [f32; 2],
+    /// The size of the image in atlas texture coordinates. An image texture coordinate `uv` maps to
+    /// `uv_offset + uv * uv_scale` in the atlas.
+    pub uv_scale: [f32; 2],
+}
+
+#[derive(Debug)]
+pub struct TextureAtlas {
+    /// A 2D texture with one array layer per filled layer of the atlas. Views should use
+    /// `TextureViewDimension::D2Array` since the layer count depends on the images.
+    pub texture: Texture,
+    /// The placement of each image, in the order the images were given
+    pub entries: Vec<AtlasEntry>,
+}
+
+/// Packs the images into a new texture and records the upload of all images.
+///
+/// The returned texture is ready for use after the encoder is submitted.
+pub fn pack(
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    descriptor: AtlasDescriptor,
+    images: &[AtlasImage],
+) -> Result<TextureAtlas, Error> {
+    if imp::block_dimensions(descriptor.format) != (1, 1) {
+        return Err(Error::from("texture_atlas: compressed formats are not supported"));
+    }
+    if images.is_empty() {
+        return Err(Error::from("texture_atlas: no images to pack"));
+    }
+
+    let texel_size = imp::pixel_size(descriptor.format) as usize;
+    for image in images.iter() {
+        if image.width == 0 || image.height == 0 {
+            return Err(Error::from("texture_atlas: images must not be empty"));
+        }
+        if image.data.len() < image.width as usize * image.height as usize * texel_size {
+            return Err(Error::from("texture_atlas: image data is too small"));
+        }
+    }
+
+    let sizes: Vec<(u32, u32)> = images.iter().map(|image| (image.width, image.height)).collect();
+    let placements = place(&sizes, descriptor.layer_size, descriptor.padding)?;
+    let layer_count = placements
+        .iter()
+        .map(|placement| placement.array_layer + 1)
+        .max()
+        .unwrap_or(1);
+
+    let texture = device.create_texture(
+        TextureDescriptor::new_2d(descriptor.format, descriptor.layer_size, descriptor.layer_size)
+            .with_array_layer_count(layer_count)
+            .with_usage(descriptor.usage | TextureUsage::COPY_DST),
+    )?;
+
+    // Each padded image is staged separately with its rows padded to `COPY_BYTES_PER_ROW_ALIGNMENT`
+    let padding = descriptor.padding as usize;
+    let mut staging_data = Vec::new();
+    let mut staging_layouts = Vec::with_capacity(images.len());
+    for (image, placement) in images.iter().zip(placements.iter()) {
+        let width = image.width as usize;
+        let height = image.height as usize;
+        let offset = align_to(staging_data.len(), STAGING_ALIGNMENT);
+        let row_size = (width + 2 * padding) * texel_size;
+        let bytes_per_row = align_to(row_size, COPY_BYTES_PER_ROW_ALIGNMENT as usize);
+        staging_data.resize(offset, 0);
+        for y in 0..height + 2 * padding {
+            let src_y = clamp_padded(y, padding, height);
+            let src_row = &image.data[src_y * width * texel_size..(src_y + 1) * width * texel_size];
+            let row_offset = staging_data.len();
+            for x in 0..width + 2 * padding {
+                let src_x = clamp_padded(x, padding, width);
+                staging_data.extend_from_slice(&src_row[src_x * texel_size..(src_x + 1) * texel_size]);
+            }
+            staging_data.resize(row_offset + bytes_per_row, 0);
+        }
+        staging_layouts.push((
+            TextureDataLayout {
+                offset,
+                bytes_per_row: bytes_per_row as u32,
+                rows_per_image: 0,
+            },
+            placement,
+        ));
+    }
+
+    let staging_buffer = device.create_buffer_mapped(BufferDescriptor {
+        usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
+        size: staging_data.len(),
+        memory_hint: None,
+    })?;
+    staging_buffer.copy_from_slice(&staging_data)?;
+    let staging_buffer = staging_buffer.unmap();
+
+    for (layout, placement) in staging_layouts {
+        encoder.copy_buffer_to_texture(
+            BufferCopyView {
+                buffer: &staging_buffer,
+                layout,
+            },
+            TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                array_layer: placement.array_layer,
+                origin: Origin3d {
+                    x: (placement.x - descriptor.padding) as i32,
+                    y: (placement.y - descriptor.padding) as i32,
+                    z: 0,
+                },
+            },
+            Extent3d {
+                width: placement.width + 2 * descriptor.padding,
+                height: placement.height + 2 * descriptor.padding,
+                depth: 1,
+            },
+        )?;
+    }
+
+    let layer_size = descriptor.layer_size as f32;
+    let entries = placements
+        .iter()
+        .map(|placement| AtlasEntry {
+            array_layer: placement.array_layer,
+            origin: Origin3d {
+                x: placement.x as i32,
+                y: placement.y as i32,
+                z: 0,
+            },
+            size: Extent3d {
+                width: placement.width,
+                height: placement.height,
+                depth: 1,
+            },
+            uv_offset: [placement.x as f32 / layer_size, placement.y as f32 / layer_size],
+            uv_scale: [
+                placement.width as f32 / layer_size,
+                placement.height as f32 / layer_size,
+            ],
+        })
+        .collect();
+
+    Ok(TextureAtlas { texture, entries })
+}
+
+/// The placement of an image, excluding the padding
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Placement {
+    array_layer: u32,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+/// Places the images (width, height) in shelves, tallest first. The returned placements are in
+/// the order of `sizes`.
+fn place(sizes: &[(u32, u32)], layer_size: u32, padding: u32) -> Result<Vec<Placement>, Error> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&index| std::cmp::Reverse(sizes[index].1));
+
+    let mut placements = vec![Placement::default(); sizes.len()];
+    let mut array_layer = 0;
+    // The top left corner of the next image in the current shelf and the shelf height, including padding
+    let mut shelf_x = 0;
+    let mut shelf_y = 0;
+    let mut shelf_height = 0;
+
+    for index in order {
+        let (width, height) = sizes[index];
+        let padded_width = width + 2 * padding;
+        let padded_height = height + 2 * padding;
+        if padded_width > layer_size || padded_height > layer_size {
+            return Err(Error::from(format!(
+                "texture_atlas: image ({}x{} with padding {}) does not fit in an atlas layer ({}x{})",
+                width, height, padding, layer_size, layer_size
+            )));
+        }
+
+        if shelf_x + padded_width > layer_size {
+            shelf_x = 0;
+            shelf_y += shelf_height;
+            shelf_height = 0;
+        }
+        if shelf_y + padded_height > layer_size {
+            array_layer += 1;
+            shelf_x = 0;
+            shelf_y = 0;
+            shelf_height = 0;
+        }
+
+        placements[index] = Placement {
+            array_layer,
+            x: shelf_x + padding,
+            y: shelf_y + padding,
+            width,
+            height,
+        };
+
+        shelf_x += padded_width;
+        shelf_height = shelf_height.max(padded_height);
+    }
+
+    Ok(placements)
+}
+
+/// Maps a coordinate in a padded image to the nearest coordinate in the image
+fn clamp_padded(padded: usize, padding: usize, size: usize) -> usize {
+    padded.saturating_sub(padding).min(size - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(a: &Placement, b: &Placement, padding: u32) -> bool {
+        a.array_layer == b.array_layer
+            && a.x < b.x + b.width + 2 * padding
+            && b.x < a.x + a.width + 2 * padding
+            && a.y < b.y + b.height + 2 * padding
+            && b.y < a.y + a.height + 2 * padding
+    }
+
+    #[test]
+    fn place_without_overlap() {
+        let sizes = [(10, 20), (30, 5), (16, 16), (7, 9), (32, 32), (1, 1), (20, 20), (31, 3)];
+        let padding = 1;
+        let placements = place(&sizes, 64, padding).unwrap();
+
+        for (i, a) in placements.iter().enumerate() {
+            assert_eq!(sizes[i], (a.width, a.height));
+            assert!(a.x >= padding && a.x + a.width + padding <= 64);
+            assert!(a.y >= padding && a.y + a.height + padding <= 64);
+            for b in placements[i + 1..].iter() {
+                assert!(!overlaps(a, b, padding), "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn place_spills_into_layers() {
+        let sizes = [(32, 32); 5];
+        let placements = place(&sizes, 64, 0).unwrap();
+        let layers: Vec<u32> = placements.iter().map(|placement| placement.array_layer).collect();
+        assert_eq!(vec![0, 0, 0, 0, 1], layers);
+    }
+
+    #[test]
+    fn place_too_large() {
+        assert!(place(&[(64, 64)], 64, 1).is_err());
+    }
+}
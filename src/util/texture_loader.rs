@@ -0,0 +1,1159 @@
+//! Loads textures from KTX2 and DDS containers.
+//!
+//! Textures and cube maps can also be created from tightly packed texels, e.g. images decoded by
+//! the application, with `create_texture_with_data` and `create_cubemap_with_data`.
+//!
+//! The container is parsed into a `TextureData` that describes each mip level and array layer.
+//! All of the subresources are then uploaded with a single staging buffer.
+//!
+//! KTX2 files containing Basis Universal (ETC1S or UASTC) data can be loaded with
+//! `load_with_transcoder`. The transcoder itself is provided by the application via the
+//! `BasisTranscoder` trait and the target format is selected from the formats supported by the
+//! device.
+//!
+//! ```no_run
+//! # fn example(device: &vki::Device, data: &[u8]) -> Result<(), vki::Error> {
+//! use vki::util::texture_loader;
+//! use vki::TextureUsage;
+//!
+//! let mut encoder = device.create_command_encoder()?;
+//! let texture = texture_loader::load(device, &mut encoder, data, TextureUsage::SAMPLED)?;
+//! device.get_queue().submit(&[encoder.finish()?])?;
+//! # Ok(())
+//! # }
+//! ```
+
+use ash::vk;
+
+use crate::imp;
+use crate::{
+    align_to, BufferCopyView, BufferDescriptor, BufferUsage, CommandEncoder, Device, Error, Extent3d, FilterMode,
+    Origin3d, Texture, TextureBlitView, TextureCopyView, TextureDataLayout, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureFormatFeatures, TextureUsage, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use std::convert::TryInto;
+
+const KTX2_IDENTIFIER: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+const KTX2_HEADER_SIZE: usize = 80;
+const KTX2_LEVEL_INDEX_SIZE: usize = 24;
+const KTX2_SUPERCOMPRESSION_NONE: u32 = 0;
+const KTX2_SUPERCOMPRESSION_BASIS_LZ: u32 = 1;
+const KTX2_SUPERCOMPRESSION_ZSTD: u32 = 2;
+const KHR_DF_MODEL_ETC1S: u8 = 163;
+const KHR_DF_MODEL_UASTC: u8 = 166;
+const KHR_DF_TRANSFER_SRGB: u8 = 2;
+
+const DDS_MAGIC: [u8; 4] = *b"DDS ";
+const DDS_HEADER_SIZE: usize = 128;
+const DDS_HEADER_DXT10_SIZE: usize = 20;
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+const DDS_DIMENSION_TEXTURE3D: u32 = 4;
+const DDS_RESOURCE_MISC_TEXTURECUBE: u32 = 0x4;
+
+/// Staging buffer offsets must be a multiple of the texel block size and `4`.
+const STAGING_ALIGNMENT: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContainerFormat {
+    Ktx2,
+    Dds,
+}
+
+/// A single mip level of a single array layer (or cube face)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureRegion {
+    pub mip_level: u32,
+    pub array_layer: u32,
+    /// The size of the mip level in texels
+    pub size: Extent3d,
+    /// The byte offset of the region in the container data
+    pub offset: usize,
+    /// The byte length of the region in the container data
+    pub len: usize,
+}
+
+/// The parsed contents of a texture container.
+#[derive(Clone, Debug)]
+pub struct TextureData<'a> {
+    pub container: ContainerFormat,
+    pub format: TextureFormat,
+    pub dimension: TextureDimension,
+    pub size: Extent3d,
+    pub mip_level_count: u32,
+    /// The total number of array layers. Cube maps have six layers per cube.
+    pub array_layer_count: u32,
+    pub is_cube: bool,
+    pub regions: Vec<TextureRegion>,
+    pub data: &'a [u8],
+}
+
+impl<'a> TextureData<'a> {
+    /// Returns the bytes of the given region
+    pub fn region_data(&self, region: &TextureRegion) -> &'a [u8] {
+        &self.data[region.offset..region.offset + region.len]
+    }
+
+    pub fn texture_descriptor(&self, usage: TextureUsage) -> TextureDescriptor {
+        TextureDescriptor {
+            size: self.size,
+            array_layer_count: self.array_layer_count,
+            mip_level_count: self.mip_level_count,
+            sample_count: 1,
+            dimension: self.dimension,
+            format: self.format,
+            usage: usage | TextureUsage::COPY_DST,
+        }
+    }
+}
+
+/// Parses a KTX2 or DDS container. The container type is detected from the file identifier.
+pub fn parse(data: &[u8]) -> Result<TextureData, Error> {
+    if data.starts_with(&KTX2_IDENTIFIER) {
+        parse_ktx2(data)
+    } else if data.starts_with(&DDS_MAGIC) {
+        parse_dds(data)
+    } else {
+        Err(Error::from("texture_loader: unrecognized texture container"))
+    }
+}
+
+/// Parses the container and records the upload of all mip levels and layers into a new texture.
+///
+/// The `COPY_DST` usage is added implicitly. The returned texture is ready for use after the
+/// encoder is submitted.
+pub fn load(device: &Device, encoder: &mut CommandEncoder, data: &[u8], usage: TextureUsage) -> Result<Texture, Error> {
+    let texture_data = parse(data)?;
+    upload(device, encoder, &texture_data, usage)
+}
+
+/// Creates a texture for the parsed container and records the upload of all regions.
+pub fn upload(
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    texture_data: &TextureData,
+    usage: TextureUsage,
+) -> Result<Texture, Error> {
+    let texture = device.create_texture(texture_data.texture_descriptor(usage))?;
+    copy_regions(
+        device,
+        encoder,
+        &texture,
+        texture_data.format,
+        &texture_data.regions,
+        texture_data.data,
+    )?;
+    Ok(texture)
+}
+
+/// Creates a 2D texture from tightly packed texels and records the upload of the first mip level.
+///
+/// If `generate_mipmaps` is `true`, the texture has a full mip chain that is generated with
+/// `generate_mipmaps`, which adds the `COPY_SRC` usage. The `COPY_DST` usage is added implicitly.
+#[allow(clippy::too_many_arguments)]
+pub fn create_texture_with_data(
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    data: &[u8],
+    format: TextureFormat,
+    width: u32,
+    height: u32,
+    usage: TextureUsage,
+    generate_mipmaps: bool,
+) -> Result<Texture, Error> {
+    let size = Extent3d {
+        width,
+        height,
+        depth: 1,
+    };
+    let region = TextureRegion {
+        mip_level: 0,
+        array_layer: 0,
+        size,
+        offset: 0,
+        len: region_len(format, size),
+    };
+    if data.len() < region.len {
+        return Err(Error::from("texture_loader: texture data is too small"));
+    }
+
+    let mut descriptor = TextureDescriptor::new_2d(format, width, height).with_usage(usage | TextureUsage::COPY_DST);
+    if generate_mipmaps {
+        descriptor = descriptor.with_full_mip_chain();
+        descriptor.usage |= TextureUsage::COPY_SRC;
+    }
+
+    let texture = device.create_texture(descriptor)?;
+    copy_regions(device, encoder, &texture, format, &[region], data)?;
+    if generate_mipmaps {
+        self::generate_mipmaps(encoder, &texture);
+    }
+
+    Ok(texture)
+}
+
+/// The source images of a cube map. Faces are ordered `+X`, `-X`, `+Y`, `-Y`, `+Z`, `-Z`, which
+/// matches the array layers of a `TextureViewDimension::Cube` view.
+#[derive(Clone, Copy, Debug)]
+pub enum CubemapData<'a> {
+    /// Six square images of tightly packed texels
+    Faces { faces: [&'a [u8]; 6], size: u32 },
+    /// A single image of tightly packed texels with the faces arranged in a cross.
+    ///
+    /// A horizontal cross (4x3 faces) has `+Y` above and `-Y` below `+Z`, with the remaining faces
+    /// in the order `-X`, `+Z`, `+X`, `-Z` along the middle row. A vertical cross (3x4 faces) has
+    /// the same layout but with `-Z` below `-Y`, rotated by 180 degrees.
+    Cross { data: &'a [u8], width: u32, height: u32 },
+}
+
+/// The (column, row) of each face in a horizontal cross
+const HORIZONTAL_CROSS_FACES: [(u32, u32); 6] = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)];
+/// The (column, row) of each face in a vertical cross
+const VERTICAL_CROSS_FACES: [(u32, u32); 6] = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)];
+
+/// Creates a cube map texture with six array layers and records the upload of the faces.
+///
+/// If `generate_mipmaps` is `true`, the texture has a full mip chain that is generated with
+/// `generate_mipmaps`, which adds the `COPY_SRC` usage. The `COPY_DST` usage is added implicitly.
+/// Compressed formats are only supported with `CubemapData::Faces`.
+pub fn create_cubemap_with_data(
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    data: CubemapData,
+    format: TextureFormat,
+    usage: TextureUsage,
+    generate_mipmaps: bool,
+) -> Result<Texture, Error> {
+    let (face_data, size) = match data {
+        CubemapData::Faces { faces, size } => {
+            let face_size = Extent3d {
+                width: size,
+                height: size,
+                depth: 1,
+            };
+            let face_len = region_len(format, face_size);
+            if faces.iter().any(|face| face.len() < face_len) {
+                return Err(Error::from("texture_loader: cube map face data is too small"));
+            }
+            let face_data: Vec<u8> = faces.iter().flat_map(|face| &face[..face_len]).copied().collect();
+            (face_data, size)
+        }
+        CubemapData::Cross { data, width, height } => {
+            if imp::block_dimensions(format) != (1, 1) {
+                return Err(Error::from(
+                    "texture_loader: cube map crosses must use an uncompressed format",
+                ));
+            }
+            extract_cross_faces(data, imp::pixel_size(format) as usize, width, height)?
+        }
+    };
+
+    let face_size = Extent3d {
+        width: size,
+        height: size,
+        depth: 1,
+    };
+    let face_len = region_len(format, face_size);
+    let regions: Vec<TextureRegion> = (0..6)
+        .map(|array_layer| TextureRegion {
+            mip_level: 0,
+            array_layer,
+            size: face_size,
+            offset: array_layer as usize * face_len,
+            len: face_len,
+        })
+        .collect();
+
+    // The CUBE_COMPATIBLE flag is set for square textures with at least six layers
+    let mut descriptor = TextureDescriptor::new_cube(format, size).with_usage(usage | TextureUsage::COPY_DST);
+    if generate_mipmaps {
+        descriptor = descriptor.with_full_mip_chain();
+        descriptor.usage |= TextureUsage::COPY_SRC;
+    }
+
+    let texture = device.create_texture(descriptor)?;
+    copy_regions(device, encoder, &texture, format, &regions, &face_data)?;
+    if generate_mipmaps {
+        self::generate_mipmaps(encoder, &texture);
+    }
+
+    Ok(texture)
+}
+
+/// Records blits that generate mip levels `1..` of every array layer from mip level `0`.
+///
+/// The texture requires the `COPY_SRC` and `COPY_DST` usages, and the format must support linear
+/// filtering (see `CommandEncoder::blit_texture_to_texture`).
+pub fn generate_mipmaps(encoder: &mut CommandEncoder, texture: &Texture) {
+    let size = texture.size();
+    for array_layer in 0..texture.array_layer_count() {
+        let mut mip_width = size.width;
+        let mut mip_height = size.height;
+        for mip_level in 1..texture.mip_level_count() {
+            let src_bounds = [
+                Origin3d { x: 0, y: 0, z: 0 },
+                Origin3d {
+                    x: mip_width as i32,
+                    y: mip_height as i32,
+                    z: 1,
+                },
+            ];
+            mip_width = (mip_width / 2).max(1);
+            mip_height = (mip_height / 2).max(1);
+            let dst_bounds = [
+                Origin3d { x: 0, y: 0, z: 0 },
+                Origin3d {
+                    x: mip_width as i32,
+                    y: mip_height as i32,
+                    z: 1,
+                },
+            ];
+            encoder.blit_texture_to_texture(
+                TextureBlitView {
+                    texture,
+                    mip_level: mip_level - 1,
+                    array_layer,
+                    bounds: src_bounds,
+                },
+                TextureBlitView {
+                    texture,
+                    mip_level,
+                    array_layer,
+                    bounds: dst_bounds,
+                },
+                FilterMode::Linear,
+            );
+        }
+    }
+}
+
+/// Copies the faces out of a horizontal or vertical cross into six tightly packed faces. Returns
+/// the faces and the size of each face in texels.
+fn extract_cross_faces(data: &[u8], texel_size: usize, width: u32, height: u32) -> Result<(Vec<u8>, u32), Error> {
+    let (size, positions, is_vertical) = if width * 3 == height * 4 {
+        (width / 4, HORIZONTAL_CROSS_FACES, false)
+    } else if width * 4 == height * 3 {
+        (width / 3, VERTICAL_CROSS_FACES, true)
+    } else {
+        return Err(Error::from(
+            "texture_loader: cube map cross must have a 4:3 or 3:4 aspect ratio",
+        ));
+    };
+    if size == 0 {
+        return Err(Error::from("texture_loader: cube map cross is empty"));
+    }
+
+    let image_row_size = width as usize * texel_size;
+    if data.len() < image_row_size * height as usize {
+        return Err(Error::from("texture_loader: cube map cross data is too small"));
+    }
+
+    let size = size as usize;
+    let face_row_size = size * texel_size;
+    let mut faces = Vec::with_capacity(6 * size * face_row_size);
+    for (face, &(column, row)) in positions.iter().enumerate() {
+        // The -Z face of a vertical cross is upside down
+        let rotated = is_vertical && face == 5;
+        for y in 0..size {
+            let src_y = if rotated { size - 1 - y } else { y };
+            let row_start = (row as usize * size + src_y) * image_row_size + column as usize * face_row_size;
+            let src_row = &data[row_start..row_start + face_row_size];
+            if rotated {
+                for texel in src_row.chunks(texel_size).rev() {
+                    faces.extend_from_slice(texel);
+                }
+            } else {
+                faces.extend_from_slice(src_row);
+            }
+        }
+    }
+
+    Ok((faces, size as u32))
+}
+
+/// Returns the size of a single tightly packed 2D region
+fn region_len(format: TextureFormat, size: Extent3d) -> usize {
+    let (block_width, block_height) = imp::block_dimensions(format);
+    let blocks_wide = align_to(size.width as usize, block_width as usize) / block_width as usize;
+    let blocks_high = align_to(size.height as usize, block_height as usize) / block_height as usize;
+    blocks_wide * blocks_high * size.depth as usize * imp::pixel_size(format) as usize
+}
+
+/// Records the upload of tightly packed `regions` of `data` into the texture.
+fn copy_regions(
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    texture: &Texture,
+    format: TextureFormat,
+    regions: &[TextureRegion],
+    data: &[u8],
+) -> Result<(), Error> {
+    let (block_width, _) = imp::block_dimensions(format);
+    let block_size = imp::pixel_size(format) as usize;
+
+    // Rows are tightly packed in the container but must be padded to `COPY_BYTES_PER_ROW_ALIGNMENT`
+    // in the staging buffer.
+    let mut staging_data = Vec::new();
+    let mut staging_layouts = Vec::with_capacity(regions.len());
+    for region in regions.iter() {
+        let offset = align_to(staging_data.len(), STAGING_ALIGNMENT);
+        let row_size = align_to(region.size.width as usize, block_width as usize) / block_width as usize * block_size;
+        let bytes_per_row = align_to(row_size, COPY_BYTES_PER_ROW_ALIGNMENT as usize);
+        staging_data.resize(offset, 0);
+        for row in data[region.offset..region.offset + region.len].chunks(row_size) {
+            let row_offset = staging_data.len();
+            staging_data.extend_from_slice(row);
+            staging_data.resize(row_offset + bytes_per_row, 0);
+        }
+        staging_layouts.push(TextureDataLayout {
+            offset,
+            bytes_per_row: bytes_per_row as u32,
+            rows_per_image: 0,
+        });
+    }
+
+    let staging_buffer = device.create_buffer_mapped(BufferDescriptor {
+        usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
+        size: staging_data.len(),
+        memory_hint: None,
+    })?;
+    staging_buffer.copy_from_slice(&staging_data)?;
+    let staging_buffer = staging_buffer.unmap();
+
+    for (region, layout) in regions.iter().zip(staging_layouts) {
+        encoder.copy_buffer_to_texture(
+            BufferCopyView {
+                buffer: &staging_buffer,
+                layout,
+            },
+            TextureCopyView {
+                texture,
+                mip_level: region.mip_level,
+                array_layer: region.array_layer,
+                origin: Origin3d { x: 0, y: 0, z: 0 },
+            },
+            region.size,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The codec used by a Basis Universal KTX2 file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BasisCodec {
+    /// BasisLZ supercompressed ETC1S
+    Etc1s,
+    /// UASTC, optionally zstd supercompressed
+    Uastc,
+}
+
+/// Describes the contents of a Basis Universal KTX2 file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BasisTextureInfo {
+    pub codec: BasisCodec,
+    pub dimension: TextureDimension,
+    pub size: Extent3d,
+    pub mip_level_count: u32,
+    pub layer_count: u32,
+    pub face_count: u32,
+    pub is_srgb: bool,
+}
+
+/// A single image to be transcoded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BasisImage {
+    pub mip_level: u32,
+    pub layer: u32,
+    pub face: u32,
+    /// The size of the mip level in texels
+    pub size: Extent3d,
+}
+
+/// Transcodes Basis Universal data into a format that can be uploaded to the GPU.
+///
+/// This is implemented by the application (e.g. with the `basis-universal` crate) so that the
+/// transcoder library is not a dependency of `vki`.
+pub trait BasisTranscoder {
+    /// Returns `true` if the transcoder is able to produce the `target` format
+    fn supports(&self, codec: BasisCodec, target: TextureFormat) -> bool;
+
+    /// Transcodes a single image from the KTX2 `data`. The returned texels must be tightly packed.
+    fn transcode(&mut self, data: &[u8], image: BasisImage, target: TextureFormat) -> Result<Vec<u8>, Error>;
+}
+
+/// Returns `Some` if the KTX2 container holds Basis Universal data that must be transcoded
+pub fn parse_ktx2_basis(data: &[u8]) -> Result<Option<BasisTextureInfo>, Error> {
+    if !data.starts_with(&KTX2_IDENTIFIER) {
+        return Err(Error::from("texture_loader: invalid KTX2 identifier"));
+    }
+
+    let vk_format = read_u32(data, 12)?;
+    if vk_format != vk::Format::UNDEFINED.as_raw() as u32 {
+        return Ok(None);
+    }
+
+    let supercompression_scheme = read_u32(data, 44)?;
+    let dfd_offset = read_u32(data, 48)? as usize;
+
+    // The basic data format descriptor block follows the total size of the DFD
+    let color_model = read_bytes(data, dfd_offset + 12, 1)?[0];
+    let transfer_function = read_bytes(data, dfd_offset + 14, 1)?[0];
+
+    let codec = match (color_model, supercompression_scheme) {
+        (KHR_DF_MODEL_ETC1S, KTX2_SUPERCOMPRESSION_BASIS_LZ) => BasisCodec::Etc1s,
+        (KHR_DF_MODEL_UASTC, KTX2_SUPERCOMPRESSION_NONE) | (KHR_DF_MODEL_UASTC, KTX2_SUPERCOMPRESSION_ZSTD) => {
+            BasisCodec::Uastc
+        }
+        _ => return Ok(None),
+    };
+
+    let (dimension, size) = dimension_and_size(read_u32(data, 20)?, read_u32(data, 24)?, read_u32(data, 28)?);
+    let mip_level_count = read_u32(data, 40)?.max(1);
+    let layer_count = read_u32(data, 32)?.max(1);
+    let face_count = read_u32(data, 36)?.max(1);
+    validate_mip_level_count(mip_level_count, size)?;
+    subresource_count(data, mip_level_count, array_layer_count(layer_count, face_count)?)?;
+
+    Ok(Some(BasisTextureInfo {
+        codec,
+        dimension,
+        size,
+        mip_level_count,
+        layer_count,
+        face_count,
+        is_srgb: transfer_function == KHR_DF_TRANSFER_SRGB,
+    }))
+}
+
+/// Selects the best transcode target that is supported by the device and the transcoder.
+///
+/// BC7 is preferred, followed by ASTC and ETC2. Uncompressed RGBA is used as a fallback.
+pub fn select_transcode_target(
+    device: &Device,
+    codec: BasisCodec,
+    is_srgb: bool,
+    transcoder: &dyn BasisTranscoder,
+) -> TextureFormat {
+    let extensions = device.extensions();
+    let adapter = device.adapter();
+
+    let candidates = [
+        (
+            extensions.texture_compression_bc,
+            TextureFormat::BC7RGBAUnorm,
+            TextureFormat::BC7RGBAUnormSRGB,
+        ),
+        (
+            extensions.texture_compression_astc_ldr,
+            TextureFormat::ASTC4x4Unorm,
+            TextureFormat::ASTC4x4UnormSRGB,
+        ),
+        (
+            extensions.texture_compression_etc2,
+            TextureFormat::ETC2RGBA8Unorm,
+            TextureFormat::ETC2RGBA8UnormSRGB,
+        ),
+    ];
+
+    for &(enabled, unorm, srgb) in candidates.iter() {
+        let format = if is_srgb { srgb } else { unorm };
+        let supported = adapter
+            .get_texture_format_features(format)
+            .contains(TextureFormatFeatures::SAMPLED);
+        if enabled && supported && transcoder.supports(codec, format) {
+            return format;
+        }
+    }
+
+    if is_srgb {
+        TextureFormat::R8G8B8A8UnormSRGB
+    } else {
+        TextureFormat::R8G8B8A8Unorm
+    }
+}
+
+/// Loads the container like `load`, but transcodes Basis Universal KTX2 data with the given
+/// transcoder. Containers that do not require transcoding are loaded directly.
+pub fn load_with_transcoder(
+    device: &Device,
+    encoder: &mut CommandEncoder,
+    data: &[u8],
+    usage: TextureUsage,
+    transcoder: &mut dyn BasisTranscoder,
+) -> Result<Texture, Error> {
+    let info = if data.starts_with(&KTX2_IDENTIFIER) {
+        parse_ktx2_basis(data)?
+    } else {
+        None
+    };
+
+    let info = match info {
+        Some(info) => info,
+        None => return load(device, encoder, data, usage),
+    };
+
+    if info.dimension == TextureDimension::D3 {
+        return Err(Error::from(
+            "texture_loader: 3D Basis Universal textures are not supported",
+        ));
+    }
+
+    let format = select_transcode_target(device, info.codec, info.is_srgb, transcoder);
+
+    log::debug!("transcoding {:?} texture to {:?}", info.codec, format);
+
+    let mut transcoded = Vec::new();
+    let array_layer_count = array_layer_count(info.layer_count, info.face_count)?;
+    let mut regions = Vec::with_capacity(subresource_count(data, info.mip_level_count, array_layer_count)?);
+
+    for mip_level in 0..info.mip_level_count {
+        let size = mip_level_size(info.size, mip_level);
+        for layer in 0..info.layer_count {
+            for face in 0..info.face_count {
+                let image = BasisImage {
+                    mip_level,
+                    layer,
+                    face,
+                    size,
+                };
+                let texels = transcoder.transcode(data, image, format)?;
+                let len = subresource_byte_length(format, size)?;
+                if texels.len() != len {
+                    return Err(Error::from(format!(
+                        "texture_loader: unexpected transcoded image size: {} (expected {})",
+                        texels.len(),
+                        len
+                    )));
+                }
+                regions.push(TextureRegion {
+                    mip_level,
+                    array_layer: layer * info.face_count + face,
+                    size,
+                    offset: transcoded.len(),
+                    len,
+                });
+                transcoded.extend_from_slice(&texels);
+            }
+        }
+    }
+
+    let texture_data = TextureData {
+        container: ContainerFormat::Ktx2,
+        format,
+        dimension: info.dimension,
+        size: info.size,
+        mip_level_count: info.mip_level_count,
+        array_layer_count: info.layer_count * info.face_count,
+        is_cube: info.face_count == 6,
+        regions,
+        data: &transcoded,
+    };
+
+    upload(device, encoder, &texture_data, usage)
+}
+
+/// Parses a KTX2 container. Supercompressed data is not supported. Basis Universal data must be
+/// loaded with `load_with_transcoder`.
+///
+/// http://github.khronos.org/KTX-Specification/
+pub fn parse_ktx2(data: &[u8]) -> Result<TextureData, Error> {
+    if !data.starts_with(&KTX2_IDENTIFIER) {
+        return Err(Error::from("texture_loader: invalid KTX2 identifier"));
+    }
+
+    let vk_format = read_u32(data, 12)?;
+    let pixel_width = read_u32(data, 20)?;
+    let pixel_height = read_u32(data, 24)?;
+    let pixel_depth = read_u32(data, 28)?;
+    let layer_count = read_u32(data, 32)?;
+    let face_count = read_u32(data, 36)?;
+    let level_count = read_u32(data, 40)?;
+    let supercompression_scheme = read_u32(data, 44)?;
+
+    if supercompression_scheme != KTX2_SUPERCOMPRESSION_NONE {
+        return Err(Error::from(format!(
+            "texture_loader: unsupported KTX2 supercompression scheme: {}",
+            supercompression_scheme
+        )));
+    }
+
+    let vk_format = vk::Format::from_raw(vk_format as i32);
+    let format = imp::texture_format(vk_format)
+        .ok_or_else(|| Error::from(format!("texture_loader: unsupported KTX2 format: {:?}", vk_format)))?;
+
+    if face_count != 1 && face_count != 6 {
+        return Err(Error::from(format!(
+            "texture_loader: invalid KTX2 face count: {}",
+            face_count
+        )));
+    }
+
+    let is_cube = face_count == 6;
+    let layer_count = layer_count.max(1);
+    let mip_level_count = level_count.max(1);
+    let array_layer_count = array_layer_count(layer_count, face_count)?;
+
+    let (dimension, size) = dimension_and_size(pixel_width, pixel_height, pixel_depth);
+    validate_mip_level_count(mip_level_count, size)?;
+
+    if dimension == TextureDimension::D3 && array_layer_count > 1 {
+        return Err(Error::from("texture_loader: 3D texture arrays are not supported"));
+    }
+
+    let mut regions = Vec::with_capacity(subresource_count(data, mip_level_count, array_layer_count)?);
+
+    for mip_level in 0..mip_level_count {
+        let level_index = KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE * mip_level as usize;
+        let level_offset = read_u64(data, level_index)? as usize;
+        let level_len = read_u64(data, level_index + 8)? as usize;
+
+        match level_offset.checked_add(level_len) {
+            Some(level_end) if level_end <= data.len() => {}
+            _ => return Err(Error::from("texture_loader: KTX2 level data is out of bounds")),
+        }
+
+        let mip_size = mip_level_size(size, mip_level);
+        let region_len = level_len / array_layer_count as usize;
+
+        if region_len != subresource_byte_length(format, mip_size)? {
+            return Err(Error::from(format!(
+                "texture_loader: unexpected KTX2 level size: {} (mip level {})",
+                level_len, mip_level
+            )));
+        }
+
+        // Faces are stored within each layer, which matches the array layer ordering of cube arrays
+        for array_layer in 0..array_layer_count {
+            regions.push(TextureRegion {
+                mip_level,
+                array_layer,
+                size: mip_size,
+                offset: level_offset + region_len * array_layer as usize,
+                len: region_len,
+            });
+        }
+    }
+
+    Ok(TextureData {
+        container: ContainerFormat::Ktx2,
+        format,
+        dimension,
+        size,
+        mip_level_count,
+        array_layer_count,
+        is_cube,
+        regions,
+        data,
+    })
+}
+
+/// Parses a DDS container, including the `DX10` extended header.
+///
+/// https://docs.microsoft.com/en-us/windows/win32/direct3ddds/dds-header
+pub fn parse_dds(data: &[u8]) -> Result<TextureData, Error> {
+    if !data.starts_with(&DDS_MAGIC) {
+        return Err(Error::from("texture_loader: invalid DDS magic"));
+    }
+
+    let height = read_u32(data, 12)?;
+    let width = read_u32(data, 16)?;
+    let depth = read_u32(data, 24)?;
+    let mip_map_count = read_u32(data, 28)?;
+    let pixel_format_flags = read_u32(data, 80)?;
+    let four_cc = read_bytes(data, 84, 4)?;
+    let rgb_bit_count = read_u32(data, 88)?;
+    let masks = [
+        read_u32(data, 92)?,
+        read_u32(data, 96)?,
+        read_u32(data, 100)?,
+        read_u32(data, 104)?,
+    ];
+    let caps2 = read_u32(data, 112)?;
+
+    let mut data_offset = DDS_HEADER_SIZE;
+    let mut array_size = 1;
+    let mut is_cube = caps2 & DDSCAPS2_CUBEMAP != 0;
+    let mut is_volume = caps2 & DDSCAPS2_VOLUME != 0;
+
+    let format = if pixel_format_flags & DDPF_FOURCC != 0 {
+        if four_cc == b"DX10" {
+            let dxgi_format = read_u32(data, DDS_HEADER_SIZE)?;
+            let resource_dimension = read_u32(data, DDS_HEADER_SIZE + 4)?;
+            let misc_flag = read_u32(data, DDS_HEADER_SIZE + 8)?;
+            array_size = read_u32(data, DDS_HEADER_SIZE + 12)?.max(1);
+            is_cube = misc_flag & DDS_RESOURCE_MISC_TEXTURECUBE != 0;
+            is_volume = resource_dimension == DDS_DIMENSION_TEXTURE3D;
+            data_offset += DDS_HEADER_DXT10_SIZE;
+            dxgi_texture_format(dxgi_format)
+        } else {
+            four_cc_texture_format(four_cc)
+        }
+    } else if pixel_format_flags & (DDPF_RGB | DDPF_ALPHAPIXELS) != 0 && rgb_bit_count == 32 {
+        match masks {
+            [0x0000_00ff, 0x0000_ff00, 0x00ff_0000, 0xff00_0000] => Some(TextureFormat::R8G8B8A8Unorm),
+            [0x00ff_0000, 0x0000_ff00, 0x0000_00ff, 0xff00_0000] => Some(TextureFormat::B8G8R8A8Unorm),
+            _ => None,
+        }
+    } else {
+        None
+    };
+
+    let format = format.ok_or_else(|| Error::from("texture_loader: unsupported DDS pixel format"))?;
+
+    let face_count = if is_cube { 6 } else { 1 };
+    let mip_level_count = mip_map_count.max(1);
+    let array_layer_count = array_layer_count(array_size, face_count)?;
+
+    let (dimension, size) = if is_volume {
+        dimension_and_size(width, height, depth.max(1))
+    } else {
+        dimension_and_size(width, height, 1)
+    };
+    validate_mip_level_count(mip_level_count, size)?;
+
+    if dimension == TextureDimension::D3 && array_layer_count > 1 {
+        return Err(Error::from("texture_loader: 3D texture arrays are not supported"));
+    }
+
+    let mut regions = Vec::with_capacity(subresource_count(data, mip_level_count, array_layer_count)?);
+    let mut offset = data_offset;
+
+    // DDS stores the full mip chain for each layer (or face) sequentially
+    for array_layer in 0..array_layer_count {
+        for mip_level in 0..mip_level_count {
+            let mip_size = mip_level_size(size, mip_level);
+            let len = subresource_byte_length(format, mip_size)?;
+            regions.push(TextureRegion {
+                mip_level,
+                array_layer,
+                size: mip_size,
+                offset,
+                len,
+            });
+            offset = match offset.checked_add(len) {
+                Some(end) if end <= data.len() => end,
+                _ => return Err(Error::from("texture_loader: DDS data is truncated")),
+            };
+        }
+    }
+
+    Ok(TextureData {
+        container: ContainerFormat::Dds,
+        format,
+        dimension,
+        size,
+        mip_level_count,
+        array_layer_count,
+        is_cube,
+        regions,
+        data,
+    })
+}
+
+fn four_cc_texture_format(four_cc: &[u8]) -> Option<TextureFormat> {
+    let format = match four_cc {
+        b"DXT1" => TextureFormat::BC1RGBAUnorm,
+        b"DXT2" | b"DXT3" => TextureFormat::BC2RGBAUnorm,
+        b"DXT4" | b"DXT5" => TextureFormat::BC3RGBAUnorm,
+        b"ATI1" | b"BC4U" => TextureFormat::BC4RUnorm,
+        b"BC4S" => TextureFormat::BC4RSnorm,
+        b"ATI2" | b"BC5U" => TextureFormat::BC5RGUnorm,
+        b"BC5S" => TextureFormat::BC5RGSnorm,
+        _ => return None,
+    };
+    Some(format)
+}
+
+/// https://docs.microsoft.com/en-us/windows/win32/api/dxgiformat/ne-dxgiformat-dxgi_format
+fn dxgi_texture_format(dxgi_format: u32) -> Option<TextureFormat> {
+    let format = match dxgi_format {
+        2 => TextureFormat::RGBA32Float,
+        3 => TextureFormat::RGBA32Uint,
+        4 => TextureFormat::RGBA32Sint,
+        10 => TextureFormat::RGBA16Float,
+        11 => TextureFormat::RGBA16Unorm,
+        12 => TextureFormat::RGBA16Uint,
+        13 => TextureFormat::RGBA16Snorm,
+        14 => TextureFormat::RGBA16Sint,
+        17 => TextureFormat::RG32Uint,
+        18 => TextureFormat::RG32Sint,
+        28 => TextureFormat::R8G8B8A8Unorm,
+        29 => TextureFormat::R8G8B8A8UnormSRGB,
+        30 => TextureFormat::R8G8B8A8Uint,
+        31 => TextureFormat::R8G8B8A8Snorm,
+        32 => TextureFormat::R8G8B8A8Sint,
+        35 => TextureFormat::R16G16Unorm,
+        40 => TextureFormat::D32Float,
+        42 => TextureFormat::R32Uint,
+        43 => TextureFormat::R32Sint,
+        49 => TextureFormat::R8G8Unorm,
+        50 => TextureFormat::R8G8Uint,
+        56 => TextureFormat::R16Unorm,
+        57 => TextureFormat::R16Uint,
+        61 => TextureFormat::R8Unorm,
+        62 => TextureFormat::R8Uint,
+        63 => TextureFormat::R8Snorm,
+        64 => TextureFormat::R8Sint,
+        71 => TextureFormat::BC1RGBAUnorm,
+        72 => TextureFormat::BC1RGBAUnormSRGB,
+        74 => TextureFormat::BC2RGBAUnorm,
+        75 => TextureFormat::BC2RGBAUnormSRGB,
+        77 => TextureFormat::BC3RGBAUnorm,
+        78 => TextureFormat::BC3RGBAUnormSRGB,
+        80 => TextureFormat::BC4RUnorm,
+        81 => TextureFormat::BC4RSnorm,
+        83 => TextureFormat::BC5RGUnorm,
+        84 => TextureFormat::BC5RGSnorm,
+        87 => TextureFormat::B8G8R8A8Unorm,
+        91 => TextureFormat::B8G8R8A8UnormSRGB,
+        95 => TextureFormat::BC6HRGBUfloat,
+        96 => TextureFormat::BC6HRGBSfloat,
+        98 => TextureFormat::BC7RGBAUnorm,
+        99 => TextureFormat::BC7RGBAUnormSRGB,
+        _ => return None,
+    };
+    Some(format)
+}
+
+fn dimension_and_size(width: u32, height: u32, depth: u32) -> (TextureDimension, Extent3d) {
+    let dimension = if depth > 1 {
+        TextureDimension::D3
+    } else if height == 0 {
+        TextureDimension::D1
+    } else {
+        TextureDimension::D2
+    };
+    let size = Extent3d {
+        width: width.max(1),
+        height: height.max(1),
+        depth: depth.max(1),
+    };
+    (dimension, size)
+}
+
+/// Rejects containers that claim more mip levels than the full mip chain of `size`
+fn validate_mip_level_count(mip_level_count: u32, size: Extent3d) -> Result<(), Error> {
+    let max_dimension = size.width.max(size.height).max(size.depth);
+    let max_mip_level_count = 32 - max_dimension.leading_zeros();
+    if mip_level_count > max_mip_level_count {
+        return Err(Error::from(format!(
+            "texture_loader: invalid mip level count: {} (max: {})",
+            mip_level_count, max_mip_level_count
+        )));
+    }
+    Ok(())
+}
+
+fn mip_level_size(size: Extent3d, mip_level: u32) -> Extent3d {
+    Extent3d {
+        width: (size.width >> mip_level).max(1),
+        height: (size.height >> mip_level).max(1),
+        depth: (size.depth >> mip_level).max(1),
+    }
+}
+
+/// The number of array layers, rejecting header values that overflow
+fn array_layer_count(layer_count: u32, face_count: u32) -> Result<u32, Error> {
+    layer_count.checked_mul(face_count).ok_or_else(|| {
+        Error::from(format!(
+            "texture_loader: invalid array layer count: {} layers with {} faces",
+            layer_count, face_count
+        ))
+    })
+}
+
+/// The number of subresources. Every subresource occupies at least one byte, so counts larger
+/// than the container are rejected before anything is allocated for them.
+fn subresource_count(data: &[u8], mip_level_count: u32, array_layer_count: u32) -> Result<usize, Error> {
+    match (mip_level_count as usize).checked_mul(array_layer_count as usize) {
+        Some(count) if count <= data.len() => Ok(count),
+        _ => Err(Error::from(format!(
+            "texture_loader: invalid subresource count: {} mip levels with {} array layers",
+            mip_level_count, array_layer_count
+        ))),
+    }
+}
+
+/// The tightly packed byte length of a single subresource
+fn subresource_byte_length(format: TextureFormat, size: Extent3d) -> Result<usize, Error> {
+    let (block_width, block_height) = imp::block_dimensions(format);
+    let blocks_wide = (u64::from(size.width) + u64::from(block_width) - 1) / u64::from(block_width);
+    let blocks_high = (u64::from(size.height) + u64::from(block_height) - 1) / u64::from(block_height);
+    blocks_wide
+        .checked_mul(blocks_high)
+        .and_then(|len| len.checked_mul(u64::from(size.depth)))
+        .and_then(|len| len.checked_mul(u64::from(imp::pixel_size(format))))
+        .and_then(|len| len.try_into().ok())
+        .ok_or_else(|| Error::from(format!("texture_loader: subresource size overflows: {:?}", size)))
+}
+
+fn read_bytes(data: &[u8], offset: usize, len: usize) -> Result<&[u8], Error> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| Error::from("texture_loader: unexpected end of data"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    let bytes = read_bytes(data, offset, 4)?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, Error> {
+    let bytes = read_bytes(data, offset, 8)?;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dds_header(width: u32, height: u32, mip_map_count: u32, four_cc: &[u8; 4]) -> Vec<u8> {
+        let mut data = vec![0u8; DDS_HEADER_SIZE];
+        data[0..4].copy_from_slice(&DDS_MAGIC);
+        data[4..8].copy_from_slice(&124u32.to_le_bytes());
+        data[12..16].copy_from_slice(&height.to_le_bytes());
+        data[16..20].copy_from_slice(&width.to_le_bytes());
+        data[28..32].copy_from_slice(&mip_map_count.to_le_bytes());
+        data[76..80].copy_from_slice(&32u32.to_le_bytes());
+        data[80..84].copy_from_slice(&DDPF_FOURCC.to_le_bytes());
+        data[84..88].copy_from_slice(four_cc);
+        data
+    }
+
+    #[test]
+    fn parse_dds_bc1_mip_chain() {
+        let mut data = dds_header(8, 8, 4, b"DXT1");
+        // 8x8 = 4 blocks, 4x4 = 1 block, 2x2 = 1 block, 1x1 = 1 block
+        data.resize(DDS_HEADER_SIZE + 8 * (4 + 1 + 1 + 1), 0);
+
+        let texture_data = parse(&data).unwrap();
+        assert_eq!(ContainerFormat::Dds, texture_data.container);
+        assert_eq!(TextureFormat::BC1RGBAUnorm, texture_data.format);
+        assert_eq!(4, texture_data.mip_level_count);
+        assert_eq!(1, texture_data.array_layer_count);
+
+        let lens: Vec<usize> = texture_data.regions.iter().map(|r| r.len).collect();
+        assert_eq!(vec![32, 8, 8, 8], lens);
+    }
+
+    #[test]
+    fn parse_dds_truncated() {
+        let data = dds_header(8, 8, 1, b"DXT5");
+        assert!(parse(&data).is_err());
+    }
+
+    #[test]
+    fn parse_dds_too_many_mip_levels() {
+        // An 8x8 texture has at most 4 mip levels
+        let mut data = dds_header(8, 8, 5, b"DXT1");
+        data.resize(DDS_HEADER_SIZE + 8 * (4 + 1 + 1 + 1 + 1), 0);
+        assert!(parse(&data).is_err());
+
+        let data = dds_header(8, 8, 33, b"DXT1");
+        assert!(parse(&data).is_err());
+    }
+
+    #[test]
+    fn parse_dds_too_many_array_layers() {
+        // A cube array whose layer count overflows when multiplied by the face count
+        let mut data = dds_header(4, 4, 1, b"DX10");
+        data.resize(DDS_HEADER_SIZE + DDS_HEADER_DXT10_SIZE, 0);
+        data[DDS_HEADER_SIZE..DDS_HEADER_SIZE + 4].copy_from_slice(&71u32.to_le_bytes());
+        data[DDS_HEADER_SIZE + 8..DDS_HEADER_SIZE + 12].copy_from_slice(&DDS_RESOURCE_MISC_TEXTURECUBE.to_le_bytes());
+        data[DDS_HEADER_SIZE + 12..DDS_HEADER_SIZE + 16].copy_from_slice(&u32::max_value().to_le_bytes());
+        assert!(parse(&data).is_err());
+
+        // More array layers than the container has bytes
+        data[DDS_HEADER_SIZE + 12..DDS_HEADER_SIZE + 16].copy_from_slice(&0x1000_0000u32.to_le_bytes());
+        assert!(parse(&data).is_err());
+    }
+
+    #[test]
+    fn parse_ktx2_rgba8() {
+        let mut data = vec![0u8; KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE];
+        data[0..12].copy_from_slice(&KTX2_IDENTIFIER);
+        data[12..16].copy_from_slice(&(vk::Format::R8G8B8A8_UNORM.as_raw() as u32).to_le_bytes());
+        data[20..24].copy_from_slice(&2u32.to_le_bytes());
+        data[24..28].copy_from_slice(&2u32.to_le_bytes());
+        data[36..40].copy_from_slice(&1u32.to_le_bytes());
+        data[40..44].copy_from_slice(&1u32.to_le_bytes());
+        let level_offset = data.len() as u64;
+        data[80..88].copy_from_slice(&level_offset.to_le_bytes());
+        data[88..96].copy_from_slice(&16u64.to_le_bytes());
+        data.resize(data.len() + 16, 0xff);
+
+        let texture_data = parse(&data).unwrap();
+        assert_eq!(ContainerFormat::Ktx2, texture_data.container);
+        assert_eq!(TextureFormat::R8G8B8A8Unorm, texture_data.format);
+        assert_eq!(TextureDimension::D2, texture_data.dimension);
+        assert_eq!(1, texture_data.regions.len());
+        assert_eq!(&[0xff; 16][..], texture_data.region_data(&texture_data.regions[0]));
+    }
+
+    #[test]
+    fn parse_ktx2_level_out_of_bounds() {
+        let mut data = vec![0u8; KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE];
+        data[0..12].copy_from_slice(&KTX2_IDENTIFIER);
+        data[12..16].copy_from_slice(&(vk::Format::R8G8B8A8_UNORM.as_raw() as u32).to_le_bytes());
+        data[20..24].copy_from_slice(&2u32.to_le_bytes());
+        data[24..28].copy_from_slice(&2u32.to_le_bytes());
+        data[36..40].copy_from_slice(&1u32.to_le_bytes());
+        data[40..44].copy_from_slice(&1u32.to_le_bytes());
+        // The end of the level wraps around
+        data[80..88].copy_from_slice(&(u64::max_value() - 7).to_le_bytes());
+        data[88..96].copy_from_slice(&16u64.to_le_bytes());
+
+        assert!(parse(&data).is_err());
+        assert!(read_bytes(&data, usize::max_value(), 2).is_err());
+    }
+
+    #[test]
+    fn parse_ktx2_too_many_array_layers() {
+        let mut data = vec![0u8; KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE];
+        data[0..12].copy_from_slice(&KTX2_IDENTIFIER);
+        data[12..16].copy_from_slice(&(vk::Format::R8G8B8A8_UNORM.as_raw() as u32).to_le_bytes());
+        data[20..24].copy_from_slice(&2u32.to_le_bytes());
+        data[24..28].copy_from_slice(&2u32.to_le_bytes());
+        data[32..36].copy_from_slice(&u32::max_value().to_le_bytes());
+        data[36..40].copy_from_slice(&6u32.to_le_bytes());
+        data[40..44].copy_from_slice(&1u32.to_le_bytes());
+        assert!(parse(&data).is_err());
+
+        data[32..36].copy_from_slice(&0x1000_0000u32.to_le_bytes());
+        assert!(parse(&data).is_err());
+    }
+
+    #[test]
+    fn parse_ktx2_basis_uastc_srgb() {
+        let dfd_offset = KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_SIZE;
+        let mut data = vec![0u8; dfd_offset + 28];
+        data[0..12].copy_from_slice(&KTX2_IDENTIFIER);
+        data[20..24].copy_from_slice(&4u32.to_le_bytes());
+        data[24..28].copy_from_slice(&4u32.to_le_bytes());
+        data[36..40].copy_from_slice(&1u32.to_le_bytes());
+        data[40..44].copy_from_slice(&1u32.to_le_bytes());
+        data[48..52].copy_from_slice(&(dfd_offset as u32).to_le_bytes());
+        data[dfd_offset + 12] = KHR_DF_MODEL_UASTC;
+        data[dfd_offset + 14] = KHR_DF_TRANSFER_SRGB;
+
+        let info = parse_ktx2_basis(&data).unwrap().unwrap();
+        assert_eq!(BasisCodec::Uastc, info.codec);
+        assert_eq!(TextureDimension::D2, info.dimension);
+        assert_eq!(1, info.mip_level_count);
+        assert!(info.is_srgb);
+
+        // Basis data cannot be parsed without a transcoder
+        assert!(parse(&data).is_err());
+    }
+
+    #[test]
+    fn parse_unknown_container() {
+        assert!(parse(b"not a texture").is_err());
+    }
+}
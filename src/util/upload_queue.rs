@@ -0,0 +1,351 @@
+//! Streams texture uploads over several frames.
+//!
+//! `UploadQueue::enqueue` creates the texture immediately and queues its texel data. Each call to
+//! `UploadQueue::submit`, typically once per frame, copies as many rows as fit in the per-frame
+//! byte and time budgets into a staging buffer and submits the copies. Large mip levels are split
+//! by rows, so a single texture never stalls a frame for longer than the budget allows.
+//!
+//! The copies are submitted to the device queue, which is the only queue that vki exposes. Work
+//! submitted after the final copy of a texture observes its contents, and the texture's
+//! `UploadStatus` becomes `Complete` once the device finishes executing that copy.
+//!
+//! ```no_run
+//! # fn example(device: &vki::Device, files: &[Vec<u8>]) -> Result<(), vki::Error> {
+//! use vki::util::texture_loader;
+//! use vki::util::upload_queue::{UploadQueue, UploadQueueDescriptor, UploadStatus};
+//! use vki::TextureUsage;
+//!
+//! let mut upload_queue = UploadQueue::new(device, UploadQueueDescriptor::default());
+//! let mut uploads = Vec::new();
+//! for file in files.iter() {
+//!     let texture_data = texture_loader::parse(file)?;
+//!     uploads.push(upload_queue.enqueue(&texture_data, TextureUsage::SAMPLED)?);
+//! }
+//!
+//! // Each frame
+//! upload_queue.submit()?;
+//! for (id, _texture) in uploads.iter() {
+//!     if upload_queue.status(*id) != UploadStatus::Pending {
+//!         // The texture can be used by command buffers submitted from now on
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::imp;
+use crate::util::texture_loader::TextureData;
+use crate::{
+    align_to, BufferCopyView, BufferDescriptor, BufferUsage, Device, Error, Extent3d, Origin3d, SubmissionIndex,
+    Texture, TextureCopyView, TextureDataLayout, TextureFormat, TextureUsage, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Staging buffer offsets must be a multiple of the texel block size and `4`.
+const STAGING_ALIGNMENT: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UploadQueueDescriptor {
+    /// The maximum number of texel bytes copied by each call to `UploadQueue::submit`. At least one
+    /// row is copied per call, even if the row is larger than the budget.
+    pub bytes_per_frame: usize,
+    /// The maximum CPU time spent staging texels in each call to `UploadQueue::submit`, or `None`
+    /// to limit the transfers by size only.
+    pub time_per_frame: Option<Duration>,
+}
+
+impl Default for UploadQueueDescriptor {
+    /// 16 MiB and 2 ms per frame
+    fn default() -> UploadQueueDescriptor {
+        UploadQueueDescriptor {
+            bytes_per_frame: 16 * 1024 * 1024,
+            time_per_frame: Some(Duration::from_millis(2)),
+        }
+    }
+}
+
+/// Identifies a texture enqueued with `UploadQueue::enqueue`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UploadId(u64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UploadStatus {
+    /// Some of the texels have not been submitted yet
+    Pending,
+    /// All copies have been submitted. Command buffers submitted afterwards observe the texels.
+    Submitted(SubmissionIndex),
+    /// The device has finished executing all copies, or the id is unknown
+    Complete,
+}
+
+/// A texture whose texels are being streamed
+#[derive(Debug)]
+struct PendingUpload {
+    id: UploadId,
+    texture: Texture,
+    format: TextureFormat,
+    /// Tightly packed texels of every region
+    data: Vec<u8>,
+    regions: Vec<PendingRegion>,
+    /// The next region, depth slice, and block row to copy
+    cursor: (usize, u32, u32),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PendingRegion {
+    mip_level: u32,
+    array_layer: u32,
+    size: Extent3d,
+    /// The offset of the region in `PendingUpload::data`
+    offset: usize,
+}
+
+/// A range of block rows within a single depth slice of a region
+#[derive(Clone, Copy, Debug)]
+struct Chunk {
+    region: PendingRegion,
+    slice: u32,
+    first_row: u32,
+    row_count: u32,
+}
+
+/// Streams texture uploads within a per-frame budget. See the module documentation for an example.
+#[derive(Debug)]
+pub struct UploadQueue {
+    device: Device,
+    descriptor: UploadQueueDescriptor,
+    pending: VecDeque<PendingUpload>,
+    /// Uploads whose copies were all submitted, keyed by the submission of the final copy
+    submitted: HashMap<UploadId, SubmissionIndex>,
+    next_id: u64,
+}
+
+impl UploadQueue {
+    pub fn new(device: &Device, descriptor: UploadQueueDescriptor) -> UploadQueue {
+        UploadQueue {
+            device: device.clone(),
+            descriptor,
+            pending: VecDeque::new(),
+            submitted: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Creates a texture for the parsed container and queues the upload of all of its regions. The
+    /// data is copied, so the container can be dropped once this returns.
+    ///
+    /// The `COPY_DST` usage is added implicitly. The texture must not be used until its status is
+    /// no longer `UploadStatus::Pending`.
+    pub fn enqueue(&mut self, texture_data: &TextureData, usage: TextureUsage) -> Result<(UploadId, Texture), Error> {
+        let texture = self.device.create_texture(texture_data.texture_descriptor(usage))?;
+
+        let mut data = Vec::new();
+        let mut regions = Vec::with_capacity(texture_data.regions.len());
+        for region in texture_data.regions.iter() {
+            regions.push(PendingRegion {
+                mip_level: region.mip_level,
+                array_layer: region.array_layer,
+                size: region.size,
+                offset: data.len(),
+            });
+            data.extend_from_slice(texture_data.region_data(region));
+        }
+
+        let id = UploadId(self.next_id);
+        self.next_id += 1;
+
+        if regions.is_empty() {
+            return Ok((id, texture));
+        }
+
+        self.pending.push_back(PendingUpload {
+            id,
+            texture: texture.clone(),
+            format: texture_data.format,
+            data,
+            regions,
+            cursor: (0, 0, 0),
+        });
+
+        Ok((id, texture))
+    }
+
+    /// Returns the number of texel bytes that have not been submitted yet
+    pub fn pending_bytes(&self) -> usize {
+        self.pending
+            .iter()
+            .map(|upload| {
+                let (region_index, slice, row) = upload.cursor;
+                match upload.regions.get(region_index) {
+                    Some(region) => {
+                        let (row_size, rows) = row_layout(upload.format, region.size);
+                        upload.data.len() - region.offset - (slice * rows + row) as usize * row_size
+                    }
+                    None => 0,
+                }
+            })
+            .sum()
+    }
+
+    pub fn status(&self, id: UploadId) -> UploadStatus {
+        if self.pending.iter().any(|upload| upload.id == id) {
+            return UploadStatus::Pending;
+        }
+        match self.submitted.get(&id) {
+            Some(&index) if !self.device.is_submission_complete(index) => UploadStatus::Submitted(index),
+            _ => UploadStatus::Complete,
+        }
+    }
+
+    /// Stages and submits the next rows within the budget. Returns the index of the submission, or
+    /// `None` if nothing was pending.
+    pub fn submit(&mut self) -> Result<Option<SubmissionIndex>, Error> {
+        // Forget the uploads that have completed
+        let device = &self.device;
+        self.submitted.retain(|_, index| !device.is_submission_complete(*index));
+
+        if self.pending.is_empty() {
+            return Ok(None);
+        }
+
+        let start = Instant::now();
+        let mut budget = self.descriptor.bytes_per_frame;
+        let mut staging_data = Vec::new();
+        let mut copies: Vec<(Texture, TextureFormat, Chunk, TextureDataLayout)> = Vec::new();
+        let mut finished = Vec::new();
+
+        while let Some(upload) = self.pending.front_mut() {
+            let out_of_time = match self.descriptor.time_per_frame {
+                Some(time_per_frame) => start.elapsed() >= time_per_frame,
+                None => false,
+            };
+            if !copies.is_empty() && (budget == 0 || out_of_time) {
+                break;
+            }
+
+            let chunk = match next_chunk(upload, budget, copies.is_empty()) {
+                Some(chunk) => chunk,
+                None => break,
+            };
+
+            let (row_size, rows) = row_layout(upload.format, chunk.region.size);
+            let bytes_per_row = align_to(row_size, COPY_BYTES_PER_ROW_ALIGNMENT as usize);
+            let offset = align_to(staging_data.len(), STAGING_ALIGNMENT);
+            staging_data.resize(offset, 0);
+            let src_offset = chunk.region.offset + (chunk.slice * rows + chunk.first_row) as usize * row_size;
+            let src = &upload.data[src_offset..src_offset + chunk.row_count as usize * row_size];
+            for row in src.chunks(row_size) {
+                let row_offset = staging_data.len();
+                staging_data.extend_from_slice(row);
+                staging_data.resize(row_offset + bytes_per_row, 0);
+            }
+
+            copies.push((
+                upload.texture.clone(),
+                upload.format,
+                chunk,
+                TextureDataLayout {
+                    offset,
+                    bytes_per_row: bytes_per_row as u32,
+                    rows_per_image: 0,
+                },
+            ));
+
+            budget = budget.saturating_sub(chunk.row_count as usize * row_size);
+
+            if upload.cursor.0 >= upload.regions.len() {
+                let upload = self.pending.pop_front().expect("pending upload");
+                finished.push(upload.id);
+            }
+        }
+
+        if copies.is_empty() {
+            return Ok(None);
+        }
+
+        let staging_buffer = self.device.create_buffer_mapped(BufferDescriptor {
+            usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
+            size: staging_data.len(),
+            memory_hint: None,
+        })?;
+        staging_buffer.copy_from_slice(&staging_data)?;
+        let staging_buffer = staging_buffer.unmap();
+
+        let mut encoder = self.device.create_command_encoder()?;
+        for (texture, format, chunk, layout) in copies.iter() {
+            let (_, block_height) = imp::block_dimensions(*format);
+            let y = chunk.first_row * block_height;
+            let height = (chunk.row_count * block_height).min(chunk.region.size.height - y);
+            encoder.copy_buffer_to_texture(
+                BufferCopyView {
+                    buffer: &staging_buffer,
+                    layout: *layout,
+                },
+                TextureCopyView {
+                    texture,
+                    mip_level: chunk.region.mip_level,
+                    array_layer: chunk.region.array_layer,
+                    origin: Origin3d {
+                        x: 0,
+                        y: y as i32,
+                        z: chunk.slice as i32,
+                    },
+                },
+                Extent3d {
+                    width: chunk.region.size.width,
+                    height,
+                    depth: 1,
+                },
+            )?;
+        }
+
+        let index = self.device.get_queue().submit(&[encoder.finish()?])?;
+        for id in finished {
+            self.submitted.insert(id, index);
+        }
+
+        Ok(Some(index))
+    }
+}
+
+/// Returns the next chunk of the upload and advances its cursor. The chunk is limited to `budget`
+/// bytes unless `at_least_one_row` is `true`.
+fn next_chunk(upload: &mut PendingUpload, budget: usize, at_least_one_row: bool) -> Option<Chunk> {
+    let (region_index, slice, first_row) = upload.cursor;
+    let region = *upload.regions.get(region_index)?;
+    let (row_size, rows) = row_layout(upload.format, region.size);
+
+    let remaining_rows = rows - first_row;
+    let mut row_count = (budget / row_size.max(1)).min(remaining_rows as usize) as u32;
+    if row_count == 0 {
+        if !at_least_one_row {
+            return None;
+        }
+        row_count = 1;
+    }
+
+    upload.cursor = if first_row + row_count < rows {
+        (region_index, slice, first_row + row_count)
+    } else if slice + 1 < region.size.depth {
+        (region_index, slice + 1, 0)
+    } else {
+        (region_index + 1, 0, 0)
+    };
+
+    Some(Chunk {
+        region,
+        slice,
+        first_row,
+        row_count,
+    })
+}
+
+/// Returns the tightly packed size of a row of blocks and the number of block rows per slice
+fn row_layout(format: TextureFormat, size: Extent3d) -> (usize, u32) {
+    let (block_width, block_height) = imp::block_dimensions(format);
+    let blocks_wide = (size.width + block_width - 1) / block_width;
+    let blocks_high = (size.height + block_height - 1) / block_height;
+    (blocks_wide as usize * imp::pixel_size(format) as usize, blocks_high)
+}
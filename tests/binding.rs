@@ -1,7 +1,9 @@
+use std::num::NonZeroU64;
+
 use vki::{
     BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
-    BufferDescriptor, BufferUsage, BufferViewDescriptor, BufferViewFormat, Extent3d, SamplerDescriptor, ShaderStage,
-    TextureDescriptor, TextureDimension, TextureFormat, TextureUsage,
+    BufferBinding, BufferDescriptor, BufferUsage, BufferViewDescriptor, BufferViewFormat, CompareFunction, Extent3d,
+    SamplerDescriptor, ShaderStage, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage,
 };
 
 pub mod support;
@@ -16,6 +18,8 @@ fn create_bind_group_layout() {
                 binding: 0,
                 visibility: ShaderStage::VERTEX,
                 binding_type: BindingType::UniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
             }],
         };
 
@@ -33,12 +37,14 @@ fn create_bind_group() {
         let buffer_descriptor = BufferDescriptor {
             usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
             size: 1024,
+            memory_hint: None,
         };
         let buffer = device.create_buffer(buffer_descriptor)?;
 
         let texel_buffer_descriptor = BufferDescriptor {
             usage: BufferUsage::STORAGE,
             size: 1024,
+            memory_hint: None,
         };
         let texel_buffer = device.create_buffer(texel_buffer_descriptor)?;
         let texel_buffer_view = texel_buffer.create_view(BufferViewDescriptor {
@@ -101,31 +107,43 @@ fn create_bind_group() {
                     binding: 0,
                     visibility: ShaderStage::VERTEX,
                     binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 1,
                     visibility: ShaderStage::FRAGMENT,
                     binding_type: BindingType::Sampler,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 2,
                     visibility: ShaderStage::FRAGMENT,
                     binding_type: BindingType::SampledTexture,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 3,
                     visibility: ShaderStage::FRAGMENT,
                     binding_type: BindingType::StorageTexelBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 4,
                     visibility: ShaderStage::FRAGMENT,
                     binding_type: BindingType::ReadOnlyStorageTexture,
+                    storage_texture_format: Some(TextureFormat::R8G8B8A8Unorm),
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 5,
                     visibility: ShaderStage::FRAGMENT,
                     binding_type: BindingType::WriteOnlyStorageTexture,
+                    storage_texture_format: Some(TextureFormat::R8G8B8A8Unorm),
+                    min_binding_size: None,
                 },
             ],
         };
@@ -165,3 +183,331 @@ fn create_bind_group() {
         Ok(instance)
     });
 }
+
+#[test]
+fn create_bind_group_storage_texture_format() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        // Storage texture bindings require a format
+        assert!(device
+            .create_bind_group_layout(BindGroupLayoutDescriptor {
+                entries: vec![BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::COMPUTE,
+                    binding_type: BindingType::ReadOnlyStorageTexture,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                }],
+            })
+            .is_err());
+
+        // Other binding types must not specify a format
+        assert!(device
+            .create_bind_group_layout(BindGroupLayoutDescriptor {
+                entries: vec![BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::COMPUTE,
+                    binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: Some(TextureFormat::RGBA32Float),
+                    min_binding_size: None,
+                }],
+            })
+            .is_err());
+
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStage::COMPUTE,
+                binding_type: BindingType::WriteOnlyStorageTexture,
+                storage_texture_format: Some(TextureFormat::RGBA32Float),
+                min_binding_size: None,
+            }],
+        })?;
+
+        let storage_texture = device.create_texture(TextureDescriptor {
+            size: Extent3d {
+                width: 256,
+                height: 256,
+                depth: 1,
+            },
+            array_layer_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::R8G8B8A8Unorm,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsage::STORAGE,
+        })?;
+        let storage_view = storage_texture.create_default_view()?;
+
+        // The view format must match the layout
+        assert!(device
+            .create_bind_group(BindGroupDescriptor {
+                layout: bind_group_layout,
+                entries: vec![BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(storage_view),
+                }],
+            })
+            .is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_bind_group_buffer_offset_alignment() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let alignment = device.limits().min_uniform_buffer_offset_alignment;
+        assert!(alignment.is_power_of_two());
+        assert_eq!(alignment, vki::align_to(1, alignment));
+        assert_eq!(2 * alignment, vki::align_to(alignment + 1, alignment));
+
+        let buffer = device.create_buffer(BufferDescriptor {
+            size: 4 * alignment + 256,
+            usage: BufferUsage::UNIFORM,
+            memory_hint: None,
+        })?;
+
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStage::VERTEX,
+                binding_type: BindingType::UniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
+            }],
+        })?;
+
+        let _bind_group = device.create_bind_group(BindGroupDescriptor {
+            layout: bind_group_layout.clone(),
+            entries: vec![BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::Buffer(buffer.clone(), alignment..alignment + 64),
+            }],
+        })?;
+
+        if alignment > 1 {
+            let result = device.create_bind_group(BindGroupDescriptor {
+                layout: bind_group_layout,
+                entries: vec![BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Buffer(buffer, alignment / 2..alignment + 64),
+                }],
+            });
+            assert!(result.is_err());
+        }
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_bind_group_buffer_binding() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let alignment = device.limits().min_storage_buffer_offset_alignment;
+
+        let buffer = device.create_buffer(BufferDescriptor {
+            size: alignment + 256,
+            usage: BufferUsage::STORAGE,
+            memory_hint: None,
+        })?;
+
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStage::COMPUTE,
+                binding_type: BindingType::StorageBuffer,
+                storage_texture_format: None,
+                min_binding_size: NonZeroU64::new(256),
+            }],
+        })?;
+
+        let create_bind_group = |resource: BindingResource| {
+            device.create_bind_group(BindGroupDescriptor {
+                layout: bind_group_layout.clone(),
+                entries: vec![BindGroupEntry { binding: 0, resource }],
+            })
+        };
+
+        // The whole buffer
+        create_bind_group(BindingResource::buffer(buffer.clone()))?;
+
+        // The rest of the buffer after the offset
+        create_bind_group(BindingResource::BufferBinding(BufferBinding {
+            buffer: buffer.clone(),
+            offset: alignment,
+            size: None,
+        }))?;
+
+        // An explicit size
+        create_bind_group(BindingResource::BufferBinding(BufferBinding {
+            buffer: buffer.clone(),
+            offset: 0,
+            size: NonZeroU64::new(256),
+        }))?;
+
+        // Smaller than the min_binding_size
+        assert!(create_bind_group(BindingResource::BufferBinding(BufferBinding {
+            buffer: buffer.clone(),
+            offset: 0,
+            size: NonZeroU64::new(128),
+        }))
+        .is_err());
+
+        // Exceeds the buffer size
+        assert!(create_bind_group(BindingResource::BufferBinding(BufferBinding {
+            buffer: buffer.clone(),
+            offset: alignment,
+            size: NonZeroU64::new(512),
+        }))
+        .is_err());
+
+        // min_binding_size is only valid for buffer bindings
+        assert!(device
+            .create_bind_group_layout(BindGroupLayoutDescriptor {
+                entries: vec![BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::COMPUTE,
+                    binding_type: BindingType::Sampler,
+                    storage_texture_format: None,
+                    min_binding_size: NonZeroU64::new(256),
+                }],
+            })
+            .is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn bind_group_layout_compatibility() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let uniform_buffer = BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStage::VERTEX,
+            binding_type: BindingType::UniformBuffer,
+            storage_texture_format: None,
+            min_binding_size: None,
+        };
+
+        let sampler = BindGroupLayoutEntry {
+            binding: 1,
+            visibility: ShaderStage::FRAGMENT,
+            binding_type: BindingType::Sampler,
+            storage_texture_format: None,
+            min_binding_size: None,
+        };
+
+        let layout_a = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![uniform_buffer, sampler],
+        })?;
+
+        // Identical entries in a different order
+        let layout_b = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![sampler, uniform_buffer],
+        })?;
+
+        let layout_c = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    visibility: ShaderStage::VERTEX | ShaderStage::FRAGMENT,
+                    ..uniform_buffer
+                },
+                sampler,
+            ],
+        })?;
+
+        let layout_d = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding_type: BindingType::DynamicUniformBuffer,
+                    ..uniform_buffer
+                },
+                sampler,
+            ],
+        })?;
+
+        let layout_e = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![uniform_buffer],
+        })?;
+
+        assert!(layout_a.is_compatible_with(&layout_a));
+        assert!(layout_a.is_compatible_with(&layout_b));
+        assert!(layout_b.is_compatible_with(&layout_a));
+        assert!(!layout_a.is_compatible_with(&layout_c));
+        assert!(!layout_a.is_compatible_with(&layout_d));
+        assert!(!layout_a.is_compatible_with(&layout_e));
+        assert!(!layout_e.is_compatible_with(&layout_a));
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_bind_group_comparison_sampler() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::FRAGMENT,
+                    binding_type: BindingType::ComparisonSampler,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStage::FRAGMENT,
+                    binding_type: BindingType::Sampler,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+            ],
+        })?;
+
+        let sampler = device.create_sampler(SamplerDescriptor::default())?;
+        let comparison_sampler =
+            device.create_sampler(SamplerDescriptor::linear().with_compare_function(CompareFunction::LessEqual))?;
+
+        let _bind_group = device.create_bind_group(BindGroupDescriptor {
+            layout: bind_group_layout.clone(),
+            entries: vec![
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Sampler(comparison_sampler.clone()),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(sampler.clone()),
+                },
+            ],
+        })?;
+
+        // The compare function of each sampler must match its binding type
+        let result = device.create_bind_group(BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: vec![
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Sampler(sampler),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(comparison_sampler),
+                },
+            ],
+        });
+        assert!(result.is_err());
+
+        Ok(instance)
+    });
+}
@@ -1,5 +1,9 @@
 use std::time::Duration;
-use vki::{BufferDescriptor, BufferUsage};
+use vki::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingType,
+    BufferDescriptor, BufferUsage, DeviceDescriptor, DynamicUniformVec, Extensions, MemoryHint, Pod, ShaderStage,
+    TypedBuffer, Zeroable,
+};
 
 pub mod support;
 
@@ -19,6 +23,7 @@ fn create_buffer_vertex_transfer_dst() {
         let descriptor = BufferDescriptor {
             usage: BufferUsage::VERTEX | BufferUsage::COPY_DST,
             size: 1024,
+            memory_hint: None,
         };
 
         let _buffer = device.create_buffer(descriptor)?;
@@ -38,6 +43,7 @@ fn create_buffer_uniform_mapped_write() {
         let descriptor = BufferDescriptor {
             usage: BufferUsage::UNIFORM | BufferUsage::MAP_WRITE,
             size: 1024,
+            memory_hint: None,
         };
 
         let _buffer = device.create_buffer(descriptor)?;
@@ -57,6 +63,7 @@ fn create_buffer_write_staging() {
         let descriptor = BufferDescriptor {
             usage: BufferUsage::COPY_SRC | BufferUsage::MAP_WRITE,
             size: 1024,
+            memory_hint: None,
         };
 
         let _buffer = device.create_buffer(descriptor)?;
@@ -76,6 +83,7 @@ fn create_buffer_read_staging() {
         let descriptor = BufferDescriptor {
             usage: BufferUsage::COPY_DST | BufferUsage::MAP_READ,
             size: 1024,
+            memory_hint: None,
         };
 
         let _buffer = device.create_buffer(descriptor)?;
@@ -95,6 +103,7 @@ fn create_buffer_read_storage() {
         let descriptor = BufferDescriptor {
             usage: BufferUsage::STORAGE | BufferUsage::MAP_READ,
             size: 1024,
+            memory_hint: None,
         };
 
         let _buffer = device.create_buffer(descriptor)?;
@@ -122,6 +131,7 @@ fn create_buffer_mapped() {
         let write_buffer_mapped = device.create_buffer_mapped(BufferDescriptor {
             usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
             size: data_byte_size,
+            memory_hint: None,
         })?;
 
         write_buffer_mapped.copy_from_slice(data)?;
@@ -129,6 +139,7 @@ fn create_buffer_mapped() {
         let read_buffer = device.create_buffer(BufferDescriptor {
             usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
             size: data_byte_size,
+            memory_hint: None,
         })?;
 
         encoder.copy_buffer_to_buffer(&write_buffer_mapped.unmap(), 0, &read_buffer, 0, data_byte_size);
@@ -167,6 +178,7 @@ fn create_buffer_mapped_write_data() {
         let mut write_buffer_mapped = device.create_buffer_mapped(BufferDescriptor {
             usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
             size: data_byte_size,
+            memory_hint: None,
         })?;
 
         let mut write_data = write_buffer_mapped.write::<u32>(0, data.len())?;
@@ -179,6 +191,7 @@ fn create_buffer_mapped_write_data() {
         let read_buffer = device.create_buffer(BufferDescriptor {
             usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
             size: data_byte_size,
+            memory_hint: None,
         })?;
 
         encoder.copy_buffer_to_buffer(&write_buffer_mapped.unmap(), 0, &read_buffer, 0, data_byte_size);
@@ -214,6 +227,7 @@ fn set_sub_data() {
         let read_buffer = device.create_buffer(BufferDescriptor {
             usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
             size: data_byte_size,
+            memory_hint: None,
         })?;
 
         read_buffer.set_sub_data(0, data)?;
@@ -258,6 +272,7 @@ fn set_sub_data_offset() {
         let read_buffer = device.create_buffer(BufferDescriptor {
             usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
             size: (2 * data_byte_size) as _,
+            memory_hint: None,
         })?;
 
         read_buffer.set_sub_data(0, data)?;
@@ -302,6 +317,7 @@ fn mapping_twice_should_fail() {
         let buffer = device.create_buffer(BufferDescriptor {
             usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
             size: data_byte_size,
+            memory_hint: None,
         })?;
 
         let _mapped = buffer.map_read()?;
@@ -311,3 +327,251 @@ fn mapping_twice_should_fail() {
         Ok(instance)
     });
 }
+
+#[test]
+fn create_buffer_memory_hint() {
+    #[cfg(target_os = "linux")]
+    let _guard = LOCK.lock().unwrap();
+
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let hints = [
+            (MemoryHint::Upload, BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC),
+            (MemoryHint::Readback, BufferUsage::MAP_READ | BufferUsage::COPY_DST),
+            (MemoryHint::DeviceOnly, BufferUsage::VERTEX | BufferUsage::COPY_DST),
+            (
+                MemoryHint::DeviceWithHostAccess,
+                BufferUsage::UNIFORM | BufferUsage::MAP_WRITE,
+            ),
+        ];
+
+        for &(memory_hint, usage) in hints.iter() {
+            let _buffer = device.create_buffer(BufferDescriptor {
+                usage,
+                size: 1024,
+                memory_hint: Some(memory_hint),
+            })?;
+        }
+
+        let data: &[u32] = &[1, 2, 3, 4];
+        let mapped_buffer = device.create_buffer_mapped(BufferDescriptor {
+            usage: BufferUsage::UNIFORM | BufferUsage::MAP_WRITE,
+            size: std::mem::size_of_val(data),
+            memory_hint: Some(MemoryHint::DeviceWithHostAccess),
+        })?;
+        mapped_buffer.copy_from_slice(data)?;
+        let _buffer = mapped_buffer.unmap();
+
+        let device_only_mapped = BufferDescriptor {
+            usage: BufferUsage::UNIFORM | BufferUsage::MAP_WRITE,
+            size: 1024,
+            memory_hint: Some(MemoryHint::DeviceOnly),
+        };
+        assert!(device.create_buffer(device_only_mapped).is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_buffer_invalid_descriptor() {
+    #[cfg(target_os = "linux")]
+    let _guard = LOCK.lock().unwrap();
+
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let zero_size = BufferDescriptor {
+            usage: BufferUsage::VERTEX,
+            size: 0,
+            memory_hint: None,
+        };
+        assert!(device.create_buffer(zero_size).is_err());
+
+        let no_usage = BufferDescriptor {
+            usage: BufferUsage::NONE,
+            size: 1024,
+            memory_hint: None,
+        };
+        assert!(device.create_buffer(no_usage).is_err());
+
+        let map_read_write = BufferDescriptor {
+            usage: BufferUsage::MAP_READ | BufferUsage::MAP_WRITE,
+            size: 1024,
+            memory_hint: None,
+        };
+        assert!(device.create_buffer(map_read_write).is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_buffer_transform_feedback() {
+    #[cfg(target_os = "linux")]
+    let _guard = LOCK.lock().unwrap();
+
+    vki::validate(|| {
+        let (instance, adapter, device) = support::init()?;
+
+        let descriptor = BufferDescriptor {
+            usage: BufferUsage::TRANSFORM_FEEDBACK | BufferUsage::VERTEX,
+            size: 1024,
+            memory_hint: None,
+        };
+
+        // The extension was not enabled
+        assert!(device.create_buffer(descriptor).is_err());
+
+        if !adapter.extensions().transform_feedback {
+            log::warn!("transform_feedback is not supported, skipping test");
+            return Ok(instance);
+        }
+
+        let device = adapter.create_device(DeviceDescriptor {
+            extensions: Extensions {
+                transform_feedback: true,
+                ..Extensions::default()
+            },
+            ..DeviceDescriptor::default()
+        })?;
+
+        let _buffer = device.create_buffer(descriptor)?;
+        let _counter_buffer = device.create_buffer(BufferDescriptor {
+            usage: BufferUsage::TRANSFORM_FEEDBACK_COUNTER,
+            size: 16,
+            memory_hint: None,
+        })?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn typed_buffer() {
+    #[cfg(target_os = "linux")]
+    let _guard = LOCK.lock().unwrap();
+
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+        struct Vertex {
+            position: [f32; 3],
+            color: [f32; 4],
+        }
+
+        let vertices = [
+            Vertex {
+                position: [0.0, 0.5, 0.0],
+                color: [1.0, 0.0, 0.0, 1.0],
+            },
+            Vertex {
+                position: [-0.5, -0.5, 0.0],
+                color: [0.0, 1.0, 0.0, 1.0],
+            },
+        ];
+
+        let buffer = device.create_typed_buffer::<Vertex>(3, BufferUsage::MAP_READ | BufferUsage::MAP_WRITE)?;
+        assert_eq!(3, buffer.len());
+        assert_eq!(3 * std::mem::size_of::<Vertex>(), buffer.buffer().size());
+        assert_eq!(
+            std::mem::size_of::<Vertex>()..3 * std::mem::size_of::<Vertex>(),
+            buffer.byte_range(1..3)
+        );
+
+        buffer.write(1, &vertices)?;
+        assert!(buffer.write(2, &vertices).is_err());
+
+        let read_buffer_mapped = buffer.buffer().map_read()?;
+        let read_data = read_buffer_mapped.read::<Vertex>(1, 2)?;
+        assert_eq!(&vertices[..], read_data);
+        drop(read_buffer_mapped);
+
+        let untyped = device.create_buffer(BufferDescriptor {
+            usage: BufferUsage::UNIFORM,
+            size: std::mem::size_of::<Vertex>() + 1,
+            memory_hint: None,
+        })?;
+        assert!(TypedBuffer::<Vertex>::from_buffer(untyped).is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn dynamic_uniform_vec() {
+    #[cfg(target_os = "linux")]
+    let _guard = LOCK.lock().unwrap();
+
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        #[repr(C)]
+        #[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+        struct ObjectSettings {
+            model: [[f32; 4]; 4],
+            color: [f32; 4],
+        }
+
+        let settings = ObjectSettings {
+            model: [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+            color: [1.0, 0.0, 0.0, 1.0],
+        };
+
+        let alignment = device.limits().min_uniform_buffer_offset_alignment;
+
+        let mut uniforms = DynamicUniformVec::<ObjectSettings>::with_capacity(&device, 2)?;
+        assert!(uniforms.is_empty());
+        assert_eq!(2, uniforms.capacity());
+        assert_eq!(0, uniforms.stride() % alignment);
+        assert!(uniforms.stride() >= std::mem::size_of::<ObjectSettings>());
+
+        assert_eq!(0, uniforms.push(settings));
+        assert_eq!(1, uniforms.push(settings));
+        assert_eq!(uniforms.stride(), uniforms.dynamic_offset(1));
+
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStage::VERTEX,
+                binding_type: BindingType::DynamicUniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
+            }],
+        })?;
+
+        let _bind_group = device.create_bind_group(BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: vec![BindGroupEntry {
+                binding: 0,
+                resource: uniforms.binding_resource(),
+            }],
+        })?;
+
+        let mut encoder = device.create_command_encoder()?;
+        assert_eq!(false, uniforms.upload(&mut encoder)?);
+
+        // The buffer is reallocated when the elements no longer fit
+        uniforms.push(settings);
+        assert_eq!(true, uniforms.upload(&mut encoder)?);
+        assert_eq!(3, uniforms.len());
+        assert!(uniforms.capacity() >= 3);
+
+        // The buffer is reused after clearing
+        uniforms.clear();
+        uniforms.push(settings);
+        assert_eq!(false, uniforms.upload(&mut encoder)?);
+
+        device.get_queue().submit(&[encoder.finish()?])?;
+
+        Ok(instance)
+    });
+}
@@ -23,11 +23,15 @@ fn copy_buffer_with_compute_shader() {
                     binding: 0,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::StorageBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 1,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::StorageBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
             ],
         })?;
@@ -43,6 +47,7 @@ fn copy_buffer_with_compute_shader() {
                 module: compute_module,
             },
             layout: pipeline_layout,
+            required_subgroup_size: None,
         })?;
 
         let mut encoder = device.create_command_encoder()?;
@@ -59,6 +64,7 @@ fn copy_buffer_with_compute_shader() {
         let write_buffer_mapped = device.create_buffer_mapped(BufferDescriptor {
             usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC | BufferUsage::STORAGE,
             size: data_byte_size,
+            memory_hint: None,
         })?;
 
         write_buffer_mapped.copy_from_slice(data)?;
@@ -66,6 +72,7 @@ fn copy_buffer_with_compute_shader() {
         let read_buffer = device.create_buffer(BufferDescriptor {
             usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST | BufferUsage::STORAGE,
             size: data_byte_size,
+            memory_hint: None,
         })?;
 
         let bind_group = device.create_bind_group(BindGroupDescriptor {
@@ -123,6 +130,8 @@ fn push_constants() {
                 binding: 0,
                 visibility: ShaderStage::COMPUTE,
                 binding_type: BindingType::StorageBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
             }],
         })?;
 
@@ -141,6 +150,7 @@ fn push_constants() {
                 module: compute_module,
             },
             layout: pipeline_layout,
+            required_subgroup_size: None,
         })?;
 
         let mut encoder = device.create_command_encoder()?;
@@ -148,6 +158,7 @@ fn push_constants() {
         let read_buffer = device.create_buffer(BufferDescriptor {
             usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST | BufferUsage::STORAGE,
             size: data_byte_size,
+            memory_hint: None,
         })?;
 
         let bind_group = device.create_bind_group(BindGroupDescriptor {
@@ -183,6 +194,99 @@ fn push_constants() {
     });
 }
 
+#[test]
+fn push_constants_bytes() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let data: &[u32] = &[1, 2];
+        let data_byte_size = std::mem::size_of::<u32>() * data.len();
+
+        let compute_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/command_buffer.push_constants.comp.spv"),
+        })?;
+
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStage::COMPUTE,
+                binding_type: BindingType::StorageBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
+            }],
+        })?;
+
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout.clone()],
+            push_constant_ranges: vec![PushConstantRange {
+                offset: 0,
+                stages: ShaderStage::COMPUTE,
+                size: data_byte_size,
+            }],
+        })?;
+
+        let pipeline = device.create_compute_pipeline(ComputePipelineDescriptor {
+            compute_stage: PipelineStageDescriptor {
+                entry_point: Cow::Borrowed("main"),
+                module: compute_module,
+            },
+            layout: pipeline_layout,
+            required_subgroup_size: None,
+        })?;
+
+        let mut encoder = device.create_command_encoder()?;
+
+        let read_buffer = device.create_buffer(BufferDescriptor {
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST | BufferUsage::STORAGE,
+            size: data_byte_size,
+            memory_hint: None,
+        })?;
+
+        let bind_group = device.create_bind_group(BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: vec![BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::Buffer(read_buffer.clone(), 0..data_byte_size),
+            }],
+        })?;
+
+        let mut compute_pass = encoder.begin_compute_pass();
+        compute_pass.set_pipeline(&pipeline);
+        compute_pass.set_bind_group(0, &bind_group, None);
+        // Write both values and then update only the second one
+        let initial: Vec<u8> = [data[0], 0].iter().flat_map(|v| v.to_ne_bytes().to_vec()).collect();
+        compute_pass.set_push_constants_bytes(ShaderStage::COMPUTE, 0, &initial)?;
+        compute_pass.set_push_constants_bytes(
+            ShaderStage::COMPUTE,
+            std::mem::size_of::<u32>(),
+            &data[1].to_ne_bytes(),
+        )?;
+        assert!(compute_pass
+            .set_push_constants_bytes(ShaderStage::COMPUTE, 0, &[0u8; 3])
+            .is_err());
+        assert!(compute_pass
+            .set_push_constants_bytes(ShaderStage::COMPUTE, 0, &[])
+            .is_err());
+        compute_pass.dispatch(1, 1, 1);
+        compute_pass.end_pass();
+
+        let queue = device.get_queue();
+
+        queue.submit(&[encoder.finish()?])?;
+
+        let fence = queue.create_fence()?;
+
+        fence.wait(Duration::from_millis(1_000_000_000))?;
+
+        let read_buffer_mapped = read_buffer.map_read()?;
+
+        let read: &[u32] = read_buffer_mapped.read(0, data.len())?;
+        assert_eq!(data, read);
+
+        Ok(instance)
+    });
+}
+
 #[test]
 fn debug_markers() {
     vki::validate(|| {
@@ -202,6 +306,10 @@ fn debug_markers() {
         let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
             color_attachments: &[],
             depth_stencil_attachment: None,
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
         });
         render_pass.push_debug_group("render_pass_encoder::push_debug_group");
         render_pass.push_debug_group("render_pass_encoder::insert_debug_marker");
@@ -215,6 +323,87 @@ fn debug_markers() {
     });
 }
 
+#[test]
+fn debug_scopes() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let mut encoder = device.create_command_encoder()?;
+        {
+            let mut scope = encoder.debug_scope("command_encoder::debug_scope");
+            scope.insert_debug_marker("insert_debug_marker");
+
+            {
+                let mut compute_pass = scope.begin_compute_pass();
+                let mut compute_scope = compute_pass.debug_scope("compute_pass_encoder::debug_scope");
+                let mut nested_scope = compute_scope.debug_scope("compute_pass_encoder::nested_debug_scope");
+                nested_scope.insert_debug_marker("compute_pass_encoder::insert_debug_marker");
+            }
+
+            let mut render_pass = scope.begin_render_pass(RenderPassDescriptor {
+                color_attachments: &[],
+                depth_stencil_attachment: None,
+                flip_viewport: false,
+                viewport: None,
+                scissor_rect: None,
+                attachmentless: None,
+            });
+            {
+                let mut render_scope = render_pass.debug_scope("render_pass_encoder::debug_scope");
+                render_scope.insert_debug_marker("render_pass_encoder::insert_debug_marker");
+            }
+            render_pass.end_pass();
+        }
+
+        let command_buffer = encoder.finish()?;
+        device.get_queue().submit(&[command_buffer])?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn debug_dump() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let buffer = device.create_buffer(BufferDescriptor {
+            usage: BufferUsage::COPY_SRC | BufferUsage::COPY_DST,
+            size: 256,
+            memory_hint: None,
+        })?;
+
+        let mut encoder = device.create_command_encoder()?;
+        encoder.push_debug_group("upload");
+        encoder.copy_buffer_to_buffer(&buffer, 0, &buffer, 128, 64);
+        encoder.pop_debug_group();
+
+        let mut compute_pass = encoder.begin_compute_pass();
+        compute_pass.insert_debug_marker("marker");
+        compute_pass.dispatch(1, 2, 3);
+        compute_pass.end_pass();
+
+        let command_buffer = encoder.finish()?;
+        let dump = command_buffer.debug_dump();
+
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!("CommandBuffer", lines[0]);
+        assert_eq!("  PushDebugGroup \"upload\"", lines[1]);
+        assert!(lines[2].starts_with("    CopyBufferToBuffer "), "{}", lines[2]);
+        assert!(lines[2].ends_with(" size_bytes: 64"), "{}", lines[2]);
+        assert_eq!("  PopDebugGroup", lines[3]);
+        assert_eq!("  BeginComputePass", lines[4]);
+        assert_eq!("    InsertDebugMarker \"marker\"", lines[5]);
+        assert_eq!("    Dispatch x: 1 y: 2 z: 3", lines[6]);
+        assert_eq!("  EndComputePass", lines[7]);
+        assert_eq!(8, lines.len());
+
+        device.get_queue().submit(&[command_buffer])?;
+
+        Ok(instance)
+    });
+}
+
 #[test]
 fn dispatch_indirect() {
     vki::validate(|| {
@@ -230,11 +419,15 @@ fn dispatch_indirect() {
                     binding: 0,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::StorageBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 1,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::StorageBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
             ],
         })?;
@@ -250,6 +443,7 @@ fn dispatch_indirect() {
                 module: compute_module,
             },
             layout: pipeline_layout,
+            required_subgroup_size: None,
         })?;
 
         let mut encoder = device.create_command_encoder()?;
@@ -266,6 +460,7 @@ fn dispatch_indirect() {
         let write_buffer_mapped = device.create_buffer_mapped(BufferDescriptor {
             usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC | BufferUsage::STORAGE,
             size: data_byte_size,
+            memory_hint: None,
         })?;
 
         write_buffer_mapped.copy_from_slice(data)?;
@@ -273,6 +468,7 @@ fn dispatch_indirect() {
         let read_buffer = device.create_buffer(BufferDescriptor {
             usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST | BufferUsage::STORAGE,
             size: data_byte_size,
+            memory_hint: None,
         })?;
 
         let bind_group = device.create_bind_group(BindGroupDescriptor {
@@ -292,6 +488,7 @@ fn dispatch_indirect() {
         let indirect_buffer = device.create_buffer(BufferDescriptor {
             usage: BufferUsage::INDIRECT | BufferUsage::COPY_DST,
             size: std::mem::size_of::<DispatchIndirectCommand>(),
+            memory_hint: None,
         })?;
 
         let cmd = DispatchIndirectCommand { x: 4, y: 1, z: 1 };
@@ -320,3 +517,167 @@ fn dispatch_indirect() {
         Ok(instance)
     });
 }
+
+#[test]
+fn dispatch_indirect_written_in_command_buffer() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let compute_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/command_buffer.copy_buffer_with_compute_shader.comp.spv"),
+        })?;
+
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::COMPUTE,
+                    binding_type: BindingType::StorageBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStage::COMPUTE,
+                    binding_type: BindingType::StorageBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+            ],
+        })?;
+
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout.clone()],
+            push_constant_ranges: vec![],
+        })?;
+
+        let pipeline = device.create_compute_pipeline(ComputePipelineDescriptor {
+            compute_stage: PipelineStageDescriptor {
+                entry_point: Cow::Borrowed("main"),
+                module: compute_module,
+            },
+            layout: pipeline_layout,
+            required_subgroup_size: None,
+        })?;
+
+        let mut encoder = device.create_command_encoder()?;
+
+        let data: &[[f32; 4]] = &[
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 10.0, 11.0, 12.0],
+            [13.0, 14.0, 15.0, 16.0],
+        ];
+        let data_byte_size = std::mem::size_of::<[f32; 4]>() * data.len();
+        let data_byte_size = data_byte_size;
+
+        let write_buffer_mapped = device.create_buffer_mapped(BufferDescriptor {
+            usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC | BufferUsage::STORAGE,
+            size: data_byte_size,
+            memory_hint: None,
+        })?;
+
+        write_buffer_mapped.copy_from_slice(data)?;
+
+        let read_buffer = device.create_buffer(BufferDescriptor {
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST | BufferUsage::STORAGE,
+            size: data_byte_size,
+            memory_hint: None,
+        })?;
+
+        let bind_group = device.create_bind_group(BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: vec![
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Buffer(write_buffer_mapped.unmap(), 0..data_byte_size),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Buffer(read_buffer.clone(), 0..data_byte_size),
+                },
+            ],
+        })?;
+
+        let indirect_buffer = device.create_buffer(BufferDescriptor {
+            usage: BufferUsage::INDIRECT | BufferUsage::COPY_DST,
+            size: std::mem::size_of::<DispatchIndirectCommand>(),
+            memory_hint: None,
+        })?;
+
+        let cmd = DispatchIndirectCommand { x: 4, y: 1, z: 1 };
+
+        let indirect_staging_buffer = device.create_buffer_mapped(BufferDescriptor {
+            usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
+            size: std::mem::size_of::<DispatchIndirectCommand>(),
+            memory_hint: None,
+        })?;
+
+        indirect_staging_buffer.copy_from_slice(&[cmd])?;
+
+        // The arguments are written by the same command buffer that consumes them
+        encoder.copy_buffer_to_buffer(
+            &indirect_staging_buffer.unmap(),
+            0,
+            &indirect_buffer,
+            0,
+            std::mem::size_of::<DispatchIndirectCommand>(),
+        );
+
+        let mut compute_pass = encoder.begin_compute_pass();
+        compute_pass.set_pipeline(&pipeline);
+        compute_pass.set_bind_group(0, &bind_group, None);
+        compute_pass.dispatch_indirect(&indirect_buffer, 0);
+        compute_pass.end_pass();
+
+        let queue = device.get_queue();
+
+        queue.submit(&[encoder.finish()?])?;
+
+        let fence = queue.create_fence()?;
+
+        fence.wait(Duration::from_millis(1_000_000_000))?;
+
+        let read_buffer_mapped = read_buffer.map_read()?;
+
+        let read: &[[f32; 4]] = read_buffer_mapped.read(0, data.len())?;
+        assert_eq!(data, read);
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn push_constants_limit() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let max_push_constants_size = device.limits().max_push_constants_size as usize;
+        assert!(max_push_constants_size >= 128);
+
+        let create_pipeline_layout = |offset, size| {
+            device.create_pipeline_layout(PipelineLayoutDescriptor {
+                bind_group_layouts: vec![],
+                push_constant_ranges: vec![PushConstantRange {
+                    offset,
+                    stages: ShaderStage::COMPUTE,
+                    size,
+                }],
+            })
+        };
+
+        let _pipeline_layout = create_pipeline_layout(0, max_push_constants_size)?;
+        assert!(create_pipeline_layout(4, max_push_constants_size).is_err());
+        assert!(create_pipeline_layout(2, 4).is_err());
+        assert!(create_pipeline_layout(0, 0).is_err());
+
+        let mut encoder = device.create_command_encoder()?;
+        let mut compute_pass = encoder.begin_compute_pass();
+        assert!(compute_pass
+            .set_push_constants(ShaderStage::COMPUTE, max_push_constants_size, 0u32)
+            .is_err());
+        compute_pass.end_pass();
+
+        Ok(instance)
+    });
+}
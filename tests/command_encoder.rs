@@ -1,6 +1,10 @@
+use std::time::Duration;
+
 use vki::{
-    Color, Extent3d, LoadOp, RenderPassColorAttachmentDescriptor, RenderPassDescriptor, StoreOp, TextureDescriptor,
-    TextureDimension, TextureFormat, TextureUsage,
+    AttachmentlessDescriptor, BufferDescriptor, BufferUsage, ClearValue, Color, CommandEncoderDescriptor,
+    ComputePassDescriptor, ComputePassTimestampWrites, Extent3d, LoadOp, QuerySetDescriptor, QueryType,
+    RenderPassColorAttachmentDescriptor, RenderPassDepthStencilAttachmentDescriptor, RenderPassDescriptor, StoreOp,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsage,
 };
 
 pub mod support;
@@ -37,14 +41,19 @@ fn create_command_encoder() {
                 resolve_target: None,
                 load_op: LoadOp::Clear,
                 store_op: StoreOp::Store,
-                clear_color: Color {
+                clear_value: Color {
                     r: 0.2,
                     g: 0.2,
                     b: 0.2,
                     a: 1.0,
-                },
+                }
+                .into(),
             }],
             depth_stencil_attachment: None,
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
         });
         render_pass.end_pass();
 
@@ -86,14 +95,19 @@ fn submit_command_buffer() {
                 resolve_target: None,
                 load_op: LoadOp::Clear,
                 store_op: StoreOp::Store,
-                clear_color: Color {
+                clear_value: Color {
                     r: 0.2,
                     g: 0.2,
                     b: 0.2,
                     a: 1.0,
-                },
+                }
+                .into(),
             }],
             depth_stencil_attachment: None,
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
         });
         render_pass.end_pass();
 
@@ -106,3 +120,497 @@ fn submit_command_buffer() {
         Ok(instance)
     });
 }
+
+#[test]
+fn submit_labeled_command_buffer() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let mut command_encoder = device.create_command_encoder_with_descriptor(CommandEncoderDescriptor {
+            label: Some("labeled shadow pass"),
+            one_time_submit: true,
+        })?;
+
+        let texture = device.create_texture(TextureDescriptor {
+            sample_count: 1,
+            format: TextureFormat::D32Float,
+            usage: TextureUsage::OUTPUT_ATTACHMENT,
+            mip_level_count: 1,
+            dimension: TextureDimension::D2,
+            array_layer_count: 1,
+            size: Extent3d {
+                width: 1024,
+                height: 1024,
+                depth: 1,
+            },
+        })?;
+
+        let texture_view = texture.create_default_view()?;
+
+        let render_pass = command_encoder.begin_render_pass(RenderPassDescriptor {
+            color_attachments: &[],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
+                attachment: &texture_view,
+                depth_load_op: LoadOp::Clear,
+                depth_store_op: StoreOp::Store,
+                clear_depth: 1.0,
+                stencil_load_op: LoadOp::Load,
+                stencil_store_op: StoreOp::Store,
+                clear_stencil: 0,
+            }),
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
+        });
+        render_pass.end_pass();
+
+        let command_buffer = command_encoder.finish()?;
+
+        let queue = device.get_queue();
+
+        queue.submit(&[command_buffer])?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn submit_reusable_command_buffer() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let mut command_encoder = device.create_command_encoder_with_descriptor(CommandEncoderDescriptor {
+            label: Some("reusable"),
+            one_time_submit: false,
+        })?;
+
+        let compute_pass = command_encoder.begin_compute_pass();
+        compute_pass.end_pass();
+
+        let command_buffer = command_encoder.finish()?;
+
+        let queue = device.get_queue();
+
+        queue.submit(std::slice::from_ref(&command_buffer))?;
+        queue.submit(std::slice::from_ref(&command_buffer))?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn submit_reusable_command_buffer_after_resource_changes() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let src = device.create_buffer(BufferDescriptor {
+            size: 4 * std::mem::size_of::<u32>(),
+            usage: BufferUsage::COPY_SRC | BufferUsage::COPY_DST,
+            memory_hint: None,
+        })?;
+
+        let dst = device.create_buffer(BufferDescriptor {
+            size: 4 * std::mem::size_of::<u32>(),
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+            memory_hint: None,
+        })?;
+
+        let mut command_encoder = device.create_command_encoder_with_descriptor(CommandEncoderDescriptor {
+            label: Some("reusable copy"),
+            one_time_submit: false,
+        })?;
+        command_encoder.copy_buffer_to_buffer(&src, 0, &dst, 0, 4 * std::mem::size_of::<u32>());
+        let command_buffer = command_encoder.finish()?;
+
+        let queue = device.get_queue();
+
+        // The staged writes change the usage of the source buffer between the submissions
+        for value in 0..4u32 {
+            queue.write_buffer(&src, 0, &[value; 4])?;
+            let index = queue.submit(std::slice::from_ref(&command_buffer))?;
+            device.wait_for_submission(index, Duration::from_secs(10))?;
+
+            let data = dst.map_read()?;
+            assert_eq!(&[value; 4], data.read::<u32>(0, 4)?);
+        }
+
+        // The recording is reused while the usage is unchanged
+        for _ in 0..2 {
+            let index = queue.submit(std::slice::from_ref(&command_buffer))?;
+            device.wait_for_submission(index, Duration::from_secs(10))?;
+
+            let data = dst.map_read()?;
+            assert_eq!(&[3; 4], data.read::<u32>(0, 4)?);
+        }
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn submit_one_time_command_buffer_twice() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let mut command_encoder = device.create_command_encoder_with_descriptor(CommandEncoderDescriptor {
+            label: Some("one_time_submit"),
+            one_time_submit: true,
+        })?;
+
+        let compute_pass = command_encoder.begin_compute_pass();
+        compute_pass.end_pass();
+
+        let command_buffer = command_encoder.finish()?;
+
+        let queue = device.get_queue();
+
+        queue.submit(std::slice::from_ref(&command_buffer))?;
+        assert!(queue.submit(std::slice::from_ref(&command_buffer)).is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn compute_pass_timestamp_writes() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let query_set = match device.create_query_set(QuerySetDescriptor {
+            query_type: QueryType::Timestamp,
+            count: 2,
+        }) {
+            Ok(query_set) => query_set,
+            Err(e) => {
+                log::warn!("timestamps are not supported, skipping test: {}", e);
+                return Ok(instance);
+            }
+        };
+
+        let buffer = device.create_buffer(BufferDescriptor {
+            size: 2 * std::mem::size_of::<u64>(),
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+            memory_hint: None,
+        })?;
+
+        let mut encoder = device.create_command_encoder()?;
+
+        let compute_pass = encoder.begin_compute_pass_with_descriptor(ComputePassDescriptor {
+            label: Some("compute_pass_timestamp_writes"),
+            timestamp_writes: Some(ComputePassTimestampWrites {
+                query_set: &query_set,
+                beginning_of_pass_write_index: Some(0),
+                end_of_pass_write_index: Some(1),
+            }),
+        })?;
+        compute_pass.end_pass();
+
+        encoder.resolve_query_set(&query_set, 0, 2, &buffer, 0)?;
+
+        let queue = device.get_queue();
+        let index = queue.submit(&[encoder.finish()?])?;
+        device.wait_for_submission(index, Duration::from_secs(10))?;
+
+        let data = buffer.map_read()?;
+        let timestamps = data.read::<u64>(0, 2)?;
+        assert!(timestamps[1] >= timestamps[0]);
+        assert!(queue.timestamp_period() > 0.0);
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn resolve_query_set_invalid() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let query_set = match device.create_query_set(QuerySetDescriptor {
+            query_type: QueryType::Timestamp,
+            count: 2,
+        }) {
+            Ok(query_set) => query_set,
+            Err(e) => {
+                log::warn!("timestamps are not supported, skipping test: {}", e);
+                return Ok(instance);
+            }
+        };
+
+        let buffer = device.create_buffer(BufferDescriptor {
+            size: 4 * std::mem::size_of::<u64>(),
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+            memory_hint: None,
+        })?;
+
+        let uniform_buffer = device.create_buffer(BufferDescriptor {
+            size: 4 * std::mem::size_of::<u64>(),
+            usage: BufferUsage::UNIFORM,
+            memory_hint: None,
+        })?;
+
+        let mut encoder = device.create_command_encoder()?;
+
+        // out of range queries
+        assert!(encoder.resolve_query_set(&query_set, 1, 2, &buffer, 0).is_err());
+        assert!(encoder.resolve_query_set(&query_set, 0, u32::MAX, &buffer, 0).is_err());
+
+        // missing COPY_DST usage
+        assert!(encoder.resolve_query_set(&query_set, 0, 2, &uniform_buffer, 0).is_err());
+
+        // unaligned offset
+        assert!(encoder.resolve_query_set(&query_set, 0, 2, &buffer, 4).is_err());
+
+        // destination too small
+        assert!(encoder.resolve_query_set(&query_set, 0, 2, &buffer, 24).is_err());
+
+        let timestamp_writes = ComputePassTimestampWrites {
+            query_set: &query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(2),
+        };
+        assert!(encoder
+            .begin_compute_pass_with_descriptor(ComputePassDescriptor {
+                label: None,
+                timestamp_writes: Some(timestamp_writes),
+            })
+            .is_err());
+
+        encoder.resolve_query_set(&query_set, 0, 2, &buffer, 16)?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn set_vertex_buffers_offset_out_of_range() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let vertex_buffer = device.create_buffer(BufferDescriptor {
+            usage: BufferUsage::VERTEX,
+            size: 64,
+            memory_hint: None,
+        })?;
+
+        let texture = device.create_texture(TextureDescriptor {
+            sample_count: 1,
+            format: TextureFormat::R8G8B8A8Unorm,
+            usage: TextureUsage::OUTPUT_ATTACHMENT,
+            mip_level_count: 1,
+            dimension: TextureDimension::D2,
+            array_layer_count: 1,
+            size: Extent3d {
+                width: 64,
+                height: 64,
+                depth: 1,
+            },
+        })?;
+
+        let texture_view = texture.create_default_view()?;
+
+        let mut command_encoder = device.create_command_encoder()?;
+
+        let mut render_pass = command_encoder.begin_render_pass(RenderPassDescriptor {
+            color_attachments: &[RenderPassColorAttachmentDescriptor {
+                attachment: &texture_view,
+                resolve_target: None,
+                load_op: LoadOp::Clear,
+                store_op: StoreOp::Store,
+                clear_value: Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 1.0,
+                }
+                .into(),
+            }],
+            depth_stencil_attachment: None,
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
+        });
+
+        assert!(render_pass
+            .set_vertex_buffers(0, &[vertex_buffer.clone()], &[64])
+            .is_err());
+        render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[60])?;
+        render_pass.end_pass();
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn resolve_target_requires_multisampled_attachment() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let descriptor =
+            TextureDescriptor::new_2d(TextureFormat::R8G8B8A8Unorm, 64, 64).with_usage(TextureUsage::OUTPUT_ATTACHMENT);
+        let attachment = device.create_texture(descriptor)?.create_default_view()?;
+        let resolve_target = device.create_texture(descriptor)?.create_default_view()?;
+
+        let mut command_encoder = device.create_command_encoder()?;
+
+        let render_pass = command_encoder.begin_render_pass(RenderPassDescriptor {
+            color_attachments: &[RenderPassColorAttachmentDescriptor {
+                attachment: &attachment,
+                resolve_target: Some(&resolve_target),
+                load_op: LoadOp::Clear,
+                store_op: StoreOp::Store,
+                clear_value: ClearValue::Float([0.0, 0.0, 0.0, 1.0]),
+            }],
+            depth_stencil_attachment: None,
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
+        });
+        render_pass.end_pass();
+
+        assert!(device.get_queue().submit(&[command_encoder.finish()?]).is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn attachmentless_render_pass() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let begin_attachmentless_pass = |width: u32, height: u32| -> Result<_, vki::Error> {
+            let mut command_encoder = device.create_command_encoder()?;
+            let mut render_pass = command_encoder.begin_render_pass(RenderPassDescriptor {
+                color_attachments: &[],
+                depth_stencil_attachment: None,
+                flip_viewport: false,
+                viewport: None,
+                scissor_rect: None,
+                attachmentless: Some(AttachmentlessDescriptor {
+                    width,
+                    height,
+                    sample_count: 1,
+                }),
+            });
+            render_pass.set_scissor_rect(0, 0, width, height);
+            render_pass.end_pass();
+            command_encoder.finish()
+        };
+
+        let queue = device.get_queue();
+
+        let index = queue.submit(&[begin_attachmentless_pass(256, 128)?])?;
+        device.wait_for_submission(index, Duration::from_secs(10))?;
+
+        // The size must not be zero
+        assert!(queue.submit(&[begin_attachmentless_pass(0, 128)?]).is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn set_depth_bias() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let depth_texture = device.create_texture(TextureDescriptor {
+            sample_count: 1,
+            format: TextureFormat::D32Float,
+            usage: TextureUsage::OUTPUT_ATTACHMENT,
+            mip_level_count: 1,
+            dimension: TextureDimension::D2,
+            array_layer_count: 1,
+            size: Extent3d {
+                width: 64,
+                height: 64,
+                depth: 1,
+            },
+        })?;
+
+        let depth_view = depth_texture.create_default_view()?;
+
+        let mut command_encoder = device.create_command_encoder()?;
+
+        let mut render_pass = command_encoder.begin_render_pass(RenderPassDescriptor {
+            color_attachments: &[],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
+                attachment: &depth_view,
+                depth_load_op: LoadOp::Clear,
+                depth_store_op: StoreOp::Store,
+                clear_depth: 1.0,
+                stencil_load_op: LoadOp::Load,
+                stencil_store_op: StoreOp::Store,
+                clear_stencil: 0,
+            }),
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
+        });
+
+        render_pass.set_depth_bias(1.25, 0.0, 1.75);
+        render_pass.end_pass();
+
+        let queue = device.get_queue();
+        let index = queue.submit(&[command_encoder.finish()?])?;
+        device.wait_for_submission(index, Duration::from_secs(10))?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn set_stencil_masks() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let depth_texture = device.create_texture(TextureDescriptor {
+            sample_count: 1,
+            format: TextureFormat::D32FloatS8Uint,
+            usage: TextureUsage::OUTPUT_ATTACHMENT,
+            mip_level_count: 1,
+            dimension: TextureDimension::D2,
+            array_layer_count: 1,
+            size: Extent3d {
+                width: 64,
+                height: 64,
+                depth: 1,
+            },
+        })?;
+
+        let depth_view = depth_texture.create_default_view()?;
+
+        let mut command_encoder = device.create_command_encoder()?;
+
+        let mut render_pass = command_encoder.begin_render_pass(RenderPassDescriptor {
+            color_attachments: &[],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
+                attachment: &depth_view,
+                depth_load_op: LoadOp::Clear,
+                depth_store_op: StoreOp::Store,
+                clear_depth: 1.0,
+                stencil_load_op: LoadOp::Load,
+                stencil_store_op: StoreOp::Store,
+                clear_stencil: 0,
+            }),
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
+        });
+
+        render_pass.set_stencil_compare_mask(0x0f);
+        render_pass.set_stencil_write_mask(0xf0);
+        render_pass.end_pass();
+
+        let queue = device.get_queue();
+        let index = queue.submit(&[command_encoder.finish()?])?;
+        device.wait_for_submission(index, Duration::from_secs(10))?;
+
+        Ok(instance)
+    });
+}
@@ -0,0 +1,268 @@
+use std::thread;
+use std::time::Duration;
+
+use vki::{
+    BufferDescriptor, BufferUsage, Color, Extent3d, LoadOp, RenderPassColorAttachmentDescriptor, RenderPassDescriptor,
+    SamplerDescriptor, StoreOp, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage,
+};
+
+pub mod support;
+
+#[test]
+fn spawn_creation() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let pending_textures: Vec<_> = (0..4)
+            .map(|_| {
+                device.spawn_creation(|creator| {
+                    let texture = creator.create_texture(TextureDescriptor {
+                        usage: TextureUsage::SAMPLED,
+                        size: Extent3d {
+                            width: 1024,
+                            height: 1024,
+                            depth: 1,
+                        },
+                        array_layer_count: 1,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: TextureDimension::D2,
+                        format: TextureFormat::R8G8B8A8Unorm,
+                    })?;
+                    let sampler = creator.create_sampler(SamplerDescriptor::default())?;
+                    Ok((texture, sampler))
+                })
+            })
+            .collect();
+
+        // Creation on the background threads is interleaved with creation on this thread
+        let _buffer = device.create_buffer(BufferDescriptor {
+            size: 1024,
+            usage: BufferUsage::UNIFORM,
+            memory_hint: None,
+        })?;
+
+        for pending in pending_textures {
+            let (_texture, _sampler) = pending.wait()?;
+        }
+
+        let pending_error = device.spawn_creation(|creator| {
+            creator.create_buffer(BufferDescriptor {
+                size: 0,
+                usage: BufferUsage::UNIFORM,
+                memory_hint: None,
+            })
+        });
+        assert!(pending_error.wait().is_err());
+
+        // A panic on the creation thread still finishes the creation
+        let pending_panic = device.spawn_creation(|_creator| -> Result<(), vki::Error> { panic!("creation panic") });
+        while !pending_panic.is_finished() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| pending_panic.wait()));
+        assert!(result.is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn multi_threaded_stress() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let device = device.clone();
+                thread::spawn(move || -> Result<(), vki::Error> {
+                    let queue = device.get_queue();
+                    for _ in 0..16 {
+                        let src = device.create_buffer(BufferDescriptor {
+                            size: 1024,
+                            usage: BufferUsage::COPY_SRC,
+                            memory_hint: None,
+                        })?;
+                        let dst = device.create_buffer(BufferDescriptor {
+                            size: 1024,
+                            usage: BufferUsage::COPY_DST,
+                            memory_hint: None,
+                        })?;
+                        let _texture = device.create_texture(TextureDescriptor {
+                            usage: TextureUsage::SAMPLED,
+                            size: Extent3d {
+                                width: 64,
+                                height: 64,
+                                depth: 1,
+                            },
+                            array_layer_count: 1,
+                            mip_level_count: 1,
+                            sample_count: 1,
+                            dimension: TextureDimension::D2,
+                            format: TextureFormat::R8G8B8A8Unorm,
+                        })?;
+
+                        let mut encoder = device.create_command_encoder()?;
+                        encoder.copy_buffer_to_buffer(&src, 0, &dst, 0, 1024);
+                        let compute_pass = encoder.begin_compute_pass();
+                        compute_pass.end_pass();
+                        queue.submit(&[encoder.finish()?])?;
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+
+        for thread in threads {
+            thread.join().expect("thread panicked")?;
+        }
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn trim_internal_pools() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let command_buffers = (0..3)
+            .map(|_| {
+                let mut encoder = device.create_command_encoder()?;
+                encoder.push_debug_group("pool");
+                encoder.pop_debug_group();
+                encoder.finish()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let before = device.pool_statistics();
+        drop(command_buffers);
+
+        let statistics = device.pool_statistics();
+        assert_eq!(before.command_encoder_count + 3, statistics.command_encoder_count);
+        assert!(statistics.command_encoder_bytes > before.command_encoder_bytes);
+
+        device.trim_internal_pools();
+
+        let statistics = device.pool_statistics();
+        assert_eq!(0, statistics.command_encoder_count);
+        assert_eq!(0, statistics.command_encoder_bytes);
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn discard_command_encoder() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let buffer = device.create_buffer(BufferDescriptor {
+            size: 256,
+            usage: BufferUsage::COPY_SRC | BufferUsage::COPY_DST,
+            memory_hint: None,
+        })?;
+
+        device.trim_internal_pools();
+
+        let mut encoder = device.create_command_encoder()?;
+        encoder.copy_buffer_to_buffer(&buffer, 0, &buffer, 128, 128);
+        encoder.discard();
+
+        let statistics = device.pool_statistics();
+        assert_eq!(1, statistics.command_encoder_count);
+
+        // The discarded state is reused, and dropping an unfinished encoder returns it again
+        let mut encoder = device.create_command_encoder()?;
+        assert_eq!(0, device.pool_statistics().command_encoder_count);
+        encoder.copy_buffer_to_buffer(&buffer, 0, &buffer, 128, 128);
+        drop(encoder);
+
+        assert_eq!(1, device.pool_statistics().command_encoder_count);
+
+        // A finished encoder leaves nothing behind
+        let mut encoder = device.create_command_encoder()?;
+        encoder.copy_buffer_to_buffer(&buffer, 0, &buffer, 128, 128);
+        let command_buffer = encoder.finish()?;
+        assert_eq!(0, device.pool_statistics().command_encoder_count);
+
+        device.get_queue().submit(&[command_buffer])?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn render_pass_cache_eviction() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let formats = [
+            TextureFormat::R8Unorm,
+            TextureFormat::R8Uint,
+            TextureFormat::R8G8Unorm,
+            TextureFormat::R16Uint,
+            TextureFormat::R8G8B8A8Unorm,
+            TextureFormat::B8G8R8A8Unorm,
+            TextureFormat::R32Uint,
+            TextureFormat::RG32Uint,
+            TextureFormat::RGBA16Float,
+            TextureFormat::RGBA32Float,
+        ];
+
+        let mut encoder = device.create_command_encoder()?;
+
+        // 10 formats * 2 load ops * 4 attachment counts = 80 distinct render passes
+        for &format in formats.iter() {
+            let views = (0..4)
+                .map(|_| {
+                    let descriptor =
+                        TextureDescriptor::new_2d(format, 4, 4).with_usage(TextureUsage::OUTPUT_ATTACHMENT);
+                    let texture = device.create_texture(descriptor)?;
+                    texture.create_default_view()
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            for &load_op in [LoadOp::Clear, LoadOp::Load].iter() {
+                for count in 1..=views.len() {
+                    let color_attachments = views[0..count]
+                        .iter()
+                        .map(|view| RenderPassColorAttachmentDescriptor {
+                            attachment: view,
+                            resolve_target: None,
+                            load_op,
+                            store_op: StoreOp::Store,
+                            clear_value: Color {
+                                r: 0.0,
+                                g: 0.0,
+                                b: 0.0,
+                                a: 1.0,
+                            }
+                            .into(),
+                        })
+                        .collect::<Vec<_>>();
+                    let render_pass = encoder.begin_render_pass(RenderPassDescriptor {
+                        color_attachments: &color_attachments,
+                        depth_stencil_attachment: None,
+                        flip_viewport: false,
+                        viewport: None,
+                        scissor_rect: None,
+                        attachmentless: None,
+                    });
+                    render_pass.end_pass();
+                }
+            }
+        }
+
+        let index = device.get_queue().submit(&[encoder.finish()?])?;
+        device.wait_for_submission(index, Duration::from_secs(10))?;
+
+        let statistics = device.pool_statistics();
+        assert!(statistics.render_pass_count > 0);
+        assert!(statistics.render_pass_count <= 64);
+
+        device.trim_internal_pools();
+        assert_eq!(0, device.pool_statistics().render_pass_count);
+
+        Ok(instance)
+    });
+}
@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use vki::FenceError;
+use vki::{DeviceDescriptor, Extensions, FenceError};
 
 pub mod support;
 
@@ -95,3 +95,62 @@ fn timeout() {
         Ok(instance)
     });
 }
+
+#[test]
+fn timeline_values() {
+    vki::validate(|| {
+        let (instance, adapter, _device) = support::init()?;
+
+        if !adapter.extensions().timeline_semaphore {
+            log::warn!("timeline_semaphore is not supported, skipping test");
+            return Ok(instance);
+        }
+
+        let device = adapter.create_device(DeviceDescriptor {
+            extensions: Extensions {
+                timeline_semaphore: true,
+                ..Extensions::default()
+            },
+            ..DeviceDescriptor::default()
+        })?;
+
+        let queue = device.get_queue();
+
+        let producer = queue.create_fence()?;
+        let consumer = queue.create_fence()?;
+        assert_eq!(0, consumer.completed_value()?);
+
+        let encoder = device.create_command_encoder()?;
+        queue.submit_with(&[encoder.finish()?], &[], &[(&consumer, 1)])?;
+        consumer.wait_for_value(1, Duration::from_secs(10))?;
+        assert_eq!(1, consumer.completed_value()?);
+
+        // The submission cannot complete until the host signals the producer
+        let encoder = device.create_command_encoder()?;
+        queue.submit_with(&[encoder.finish()?], &[(&producer, 1)], &[(&consumer, 2)])?;
+        match consumer.wait_for_value(2, Duration::from_millis(10)) {
+            Err(FenceError::Timeout) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        producer.signal_value(1)?;
+        consumer.wait_for_value(2, Duration::from_secs(10))?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn timeline_values_require_extension() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let queue = device.get_queue();
+        let fence = queue.create_fence()?;
+
+        assert!(fence.completed_value().is_err());
+        assert!(queue.submit_with(&[], &[], &[(&fence, 1)]).is_err());
+
+        Ok(instance)
+    });
+}
@@ -0,0 +1,404 @@
+#[macro_use]
+extern crate memoffset;
+
+use std::borrow::Cow;
+use vki::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
+    BlendDescriptor, BufferDescriptor, BufferUsage, ClearValue, Color, ColorStateDescriptor, ColorWrite, CullMode,
+    Device, FrontFace, IndexFormat, InputStepMode, LoadOp, PipelineLayoutDescriptor, PipelineStageDescriptor,
+    PolygonMode, PrimitiveTopology, RasterizationStateDescriptor, RenderPassColorAttachmentDescriptor,
+    RenderPassDescriptor, RenderPipelineDescriptor, ScissorRect, ShaderModuleDescriptor, ShaderStage, StoreOp,
+    TextureDescriptor, TextureFormat, TextureUsage, VertexAttributeDescriptor, VertexBufferLayoutDescriptor,
+    VertexFormat, VertexStateDescriptor,
+};
+
+pub mod support;
+
+use support::golden::{self, OffscreenTarget};
+
+#[test]
+fn clear_color() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let target = OffscreenTarget::new(&device, 64, 64, TextureFormat::R8G8B8A8Unorm)?;
+
+        let mut encoder = device.create_command_encoder()?;
+
+        let render_pass = encoder.begin_render_pass(RenderPassDescriptor {
+            color_attachments: &[RenderPassColorAttachmentDescriptor {
+                attachment: &target.view,
+                resolve_target: None,
+                load_op: LoadOp::Clear,
+                store_op: StoreOp::Store,
+                clear_value: Color {
+                    r: 1.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 1.0,
+                }
+                .into(),
+            }],
+            depth_stencil_attachment: None,
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
+        });
+        render_pass.end_pass();
+
+        device.get_queue().submit(&[encoder.finish()?])?;
+
+        let actual = target.read_rgba8(&device)?;
+        let expected = [255u8, 0, 0, 255].repeat((target.width * target.height) as usize);
+
+        let diff = golden::compare_rgba8(&expected, &actual, golden::DEFAULT_TOLERANCE);
+        assert!(diff.is_match(), "{:?}", diff);
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn clear_uint() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let target = OffscreenTarget::new(&device, 64, 64, TextureFormat::R8G8B8A8Uint)?;
+
+        let mut encoder = device.create_command_encoder()?;
+
+        let render_pass = encoder.begin_render_pass(RenderPassDescriptor {
+            color_attachments: &[RenderPassColorAttachmentDescriptor {
+                attachment: &target.view,
+                resolve_target: None,
+                load_op: LoadOp::Clear,
+                store_op: StoreOp::Store,
+                clear_value: ClearValue::Uint([7, 42, 200, 255]),
+            }],
+            depth_stencil_attachment: None,
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
+        });
+        render_pass.end_pass();
+
+        device.get_queue().submit(&[encoder.finish()?])?;
+
+        let actual = target.read_rgba8(&device)?;
+        let expected = [7u8, 42, 200, 255].repeat((target.width * target.height) as usize);
+
+        let diff = golden::compare_rgba8(&expected, &actual, 0);
+        assert!(diff.is_match(), "{:?}", diff);
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn resolve_partial_color_attachments() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let format = TextureFormat::R8G8B8A8Unorm;
+        let target = OffscreenTarget::new(&device, 64, 64, format)?;
+
+        let multisampled_descriptor = TextureDescriptor::new_2d(format, 64, 64)
+            .with_usage(TextureUsage::OUTPUT_ATTACHMENT)
+            .with_sample_count(4);
+        let unresolved_view = device.create_texture(multisampled_descriptor)?.create_default_view()?;
+        let resolved_view = device.create_texture(multisampled_descriptor)?.create_default_view()?;
+
+        let mut encoder = device.create_command_encoder()?;
+
+        // Only the second color attachment is resolved
+        let render_pass = encoder.begin_render_pass(RenderPassDescriptor {
+            color_attachments: &[
+                RenderPassColorAttachmentDescriptor {
+                    attachment: &unresolved_view,
+                    resolve_target: None,
+                    load_op: LoadOp::Clear,
+                    store_op: StoreOp::Store,
+                    clear_value: ClearValue::Float([1.0, 0.0, 0.0, 1.0]),
+                },
+                RenderPassColorAttachmentDescriptor {
+                    attachment: &resolved_view,
+                    resolve_target: Some(&target.view),
+                    load_op: LoadOp::Clear,
+                    store_op: StoreOp::Store,
+                    clear_value: ClearValue::Float([0.0, 1.0, 0.0, 1.0]),
+                },
+            ],
+            depth_stencil_attachment: None,
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
+        });
+        render_pass.end_pass();
+
+        device.get_queue().submit(&[encoder.finish()?])?;
+
+        let actual = target.read_rgba8(&device)?;
+        let expected = [0u8, 255, 0, 255].repeat((target.width * target.height) as usize);
+
+        let diff = golden::compare_rgba8(&expected, &actual, golden::DEFAULT_TOLERANCE);
+        assert!(diff.is_match(), "{:?}", diff);
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn triangle() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let format = TextureFormat::B8G8R8A8Unorm;
+        let target = OffscreenTarget::new(&device, 128, 128, format)?;
+
+        draw_triangle(&device, &target, false, None)?;
+
+        golden::assert_golden(&device, &target, "triangle", golden::DEFAULT_TOLERANCE)?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn triangle_flip_viewport() {
+    vki::validate(|| {
+        let (instance, adapter, device) = support::init()?;
+
+        if !adapter.supported_features().negative_viewport_height {
+            log::warn!("negative_viewport_height is not supported, skipping test");
+            return Ok(instance);
+        }
+
+        let format = TextureFormat::B8G8R8A8Unorm;
+        let target = OffscreenTarget::new(&device, 128, 128, format)?;
+
+        draw_triangle(&device, &target, true, None)?;
+
+        // The vertices are mirrored along with the viewport, so the image is the same
+        golden::assert_golden(&device, &target, "triangle", golden::DEFAULT_TOLERANCE)?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn triangle_scissor_rect() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let format = TextureFormat::B8G8R8A8Unorm;
+        let full = OffscreenTarget::new(&device, 128, 128, format)?;
+        let target = OffscreenTarget::new(&device, 128, 128, format)?;
+
+        // The initial scissor rect of the pass covers the top half of the target
+        let scissor_rect = ScissorRect {
+            x: 0,
+            y: 0,
+            width: 128,
+            height: 64,
+        };
+        draw_triangle(&device, &full, false, None)?;
+        draw_triangle(&device, &target, false, Some(scissor_rect))?;
+
+        let mut expected = full.read_rgba8(&device)?;
+        let row_size = (target.width * 4) as usize;
+        let black = [0u8, 0, 0, 255].repeat((target.width * (target.height - 64)) as usize);
+        expected[64 * row_size..].copy_from_slice(&black);
+
+        let actual = target.read_rgba8(&device)?;
+        let diff = golden::compare_rgba8(&expected, &actual, golden::DEFAULT_TOLERANCE);
+        assert!(diff.is_match(), "{:?}", diff);
+
+        Ok(instance)
+    });
+}
+
+/// Draws the golden "triangle". With `flip_viewport`, the Y axis of clip space points up, so
+/// the vertices are mirrored to produce the same image.
+fn draw_triangle(
+    device: &Device,
+    target: &OffscreenTarget,
+    flip_viewport: bool,
+    scissor_rect: Option<ScissorRect>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let format = target.format;
+    let y = if flip_viewport { -1.0 } else { 1.0 };
+
+    let vertex_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+        code: include_bytes!("shaders/pipeline.vert.spv"),
+    })?;
+
+    let fragment_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+        code: include_bytes!("shaders/pipeline.frag.spv"),
+    })?;
+
+    let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+        entries: vec![BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStage::VERTEX,
+            binding_type: BindingType::UniformBuffer,
+            storage_texture_format: None,
+            min_binding_size: None,
+        }],
+    })?;
+
+    #[rustfmt::skip]
+    let identity: [f32; 16] = [
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    ];
+
+    let uniform_buffer_size = std::mem::size_of_val(&identity);
+
+    let uniform_buffer = device.create_buffer_mapped(BufferDescriptor {
+        usage: BufferUsage::UNIFORM | BufferUsage::MAP_WRITE,
+        size: uniform_buffer_size,
+        memory_hint: None,
+    })?;
+
+    uniform_buffer.copy_from_slice(&identity)?;
+
+    let bind_group = device.create_bind_group(BindGroupDescriptor {
+        layout: bind_group_layout.clone(),
+        entries: vec![BindGroupEntry {
+            binding: 0,
+            resource: BindingResource::Buffer(uniform_buffer.unmap(), 0..uniform_buffer_size),
+        }],
+    })?;
+
+    let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+        bind_group_layouts: vec![bind_group_layout],
+        push_constant_ranges: vec![],
+    })?;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct Vertex {
+        position: [f32; 3],
+        color: [f32; 3],
+    }
+
+    let vertices = [
+        Vertex {
+            position: [0.0, -0.5 * y, 0.0],
+            color: [1.0, 0.0, 0.0],
+        },
+        Vertex {
+            position: [0.5, 0.5 * y, 0.0],
+            color: [0.0, 1.0, 0.0],
+        },
+        Vertex {
+            position: [-0.5, 0.5 * y, 0.0],
+            color: [0.0, 0.0, 1.0],
+        },
+    ];
+
+    let vertex_buffer = device.create_buffer_mapped(BufferDescriptor {
+        usage: BufferUsage::VERTEX | BufferUsage::MAP_WRITE,
+        size: std::mem::size_of_val(&vertices),
+        memory_hint: None,
+    })?;
+
+    vertex_buffer.copy_from_slice(&vertices)?;
+
+    let vertex_buffer = vertex_buffer.unmap();
+
+    #[rustfmt::skip]
+    let pipeline = device.create_render_pipeline(RenderPipelineDescriptor {
+        layout: pipeline_layout,
+        primitive_topology: PrimitiveTopology::TriangleList,
+        vertex_stage: PipelineStageDescriptor {
+            entry_point: Cow::Borrowed("main"),
+            module: vertex_shader_module,
+        },
+        fragment_stage: Some(PipelineStageDescriptor {
+            entry_point: Cow::Borrowed("main"),
+            module: fragment_shader_module,
+        }),
+        vertex_state: VertexStateDescriptor {
+            index_format: IndexFormat::U16,
+            primitive_restart_enabled: false,
+            vertex_buffers: vec![
+                VertexBufferLayoutDescriptor {
+                    input_slot: 0,
+                    step_mode: InputStepMode::Vertex,
+                    step_rate: 1,
+                    stride: std::mem::size_of::<Vertex>(),
+                    attributes: vec![
+                        VertexAttributeDescriptor {
+                            format: VertexFormat::Float3,
+                            offset: offset_of!(Vertex, position),
+                            shader_location: 0,
+                        },
+                        VertexAttributeDescriptor {
+                            format: VertexFormat::Float3,
+                            offset: offset_of!(Vertex, color),
+                            shader_location: 1,
+                        },
+                    ],
+                }
+            ],
+        },
+        color_states: vec![
+            ColorStateDescriptor {
+                format,
+                write_mask: ColorWrite::ALL,
+                color_blend: BlendDescriptor::OPAQUE,
+                alpha_blend: BlendDescriptor::OPAQUE,
+            }
+        ],
+        depth_stencil_state: None,
+        rasterization_state: RasterizationStateDescriptor {
+            front_face: FrontFace::Ccw,
+            cull_mode: CullMode::None,
+            polygon_mode: PolygonMode::Fill,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            depth_bias_clamp: 0.0,
+        },
+        sample_count: 1,
+        alpha_to_coverage_enabled: false,
+        blend_color: None,
+    })?;
+
+    let mut encoder = device.create_command_encoder()?;
+
+    let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
+        color_attachments: &[RenderPassColorAttachmentDescriptor {
+            attachment: &target.view,
+            resolve_target: None,
+            load_op: LoadOp::Clear,
+            store_op: StoreOp::Store,
+            clear_value: Color {
+                r: 0.0,
+                g: 0.0,
+                b: 0.0,
+                a: 1.0,
+            }
+            .into(),
+        }],
+        depth_stencil_attachment: None,
+        flip_viewport,
+        viewport: None,
+        scissor_rect,
+        attachmentless: None,
+    });
+    render_pass.set_pipeline(&pipeline);
+    render_pass.set_bind_group(0, &bind_group, None);
+    render_pass.set_vertex_buffers(0, &[vertex_buffer], &[0])?;
+    render_pass.draw(3, 1, 0, 0);
+    render_pass.end_pass();
+
+    device.get_queue().submit(&[encoder.finish()?])?;
+
+    Ok(())
+}
@@ -1,4 +1,8 @@
-use vki::{AdapterOptions, DeviceDescriptor, Instance, PowerPreference};
+use vki::{
+    AdapterOptions, BufferDescriptor, BufferUsage, DeviceDescriptor, Extensions, Instance, InstanceDescriptor,
+    PowerPreference, TextureDescriptor, TextureFormat, TextureUsage, ValidationFeatures, ValidationSeverity,
+    VertexFormat,
+};
 
 #[test]
 fn instance_new() {
@@ -10,6 +14,105 @@ fn instance_new() {
     });
 }
 
+#[test]
+fn instance_with_descriptor() {
+    let _ = pretty_env_logger::try_init();
+    vki::validate(|| {
+        let instance = Instance::with_descriptor(InstanceDescriptor {
+            app_name: "instance_with_descriptor",
+            app_version: (1, 2, 3),
+            engine_version: (0, 1, 0),
+            ..InstanceDescriptor::default()
+        })?;
+
+        let result = Instance::with_descriptor(InstanceDescriptor {
+            extra_extensions: &["VK_VKI_not_a_real_extension"],
+            ..InstanceDescriptor::default()
+        });
+        assert!(result.is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn instance_with_entry() {
+    let _ = pretty_env_logger::try_init();
+    vki::validate(|| {
+        let entry = unsafe { ash::Entry::new()? };
+        let instance = Instance::with_descriptor_and_entry(
+            InstanceDescriptor {
+                app_name: "instance_with_entry",
+                ..InstanceDescriptor::default()
+            },
+            entry,
+        )?;
+        let _adapter = instance.request_adapter(AdapterOptions::default())?;
+        Ok(instance)
+    });
+}
+
+#[test]
+fn instance_with_validation_features() {
+    let _ = pretty_env_logger::try_init();
+    vki::validate(|| {
+        let instance = Instance::with_descriptor(InstanceDescriptor {
+            enable_validation: true,
+            validation_features: ValidationFeatures::SYNCHRONIZATION | ValidationFeatures::GPU_ASSISTED,
+            ..InstanceDescriptor::default()
+        })?;
+        let adapter = instance.request_adapter(AdapterOptions::default())?;
+        let _device = adapter.create_device(DeviceDescriptor::default())?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn instance_with_validation_filter() {
+    let _ = pretty_env_logger::try_init();
+    vki::validate(|| {
+        let instance = Instance::with_descriptor(InstanceDescriptor {
+            enable_validation: true,
+            validation_severity: ValidationSeverity::Error,
+            suppressed_validation_messages: &[0x1234_5678],
+            ..InstanceDescriptor::default()
+        })?;
+        let adapter = instance.request_adapter(AdapterOptions::default())?;
+        let _device = adapter.create_device(DeviceDescriptor::default())?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn instance_with_host_allocation_tracking() {
+    let _ = pretty_env_logger::try_init();
+    vki::validate(|| {
+        let instance = Instance::with_descriptor(InstanceDescriptor {
+            track_host_allocations: true,
+            ..InstanceDescriptor::default()
+        })?;
+        let adapter = instance.request_adapter(AdapterOptions::default())?;
+        let device = adapter.create_device(DeviceDescriptor::default())?;
+        let _buffer = device.create_buffer(BufferDescriptor {
+            size: 256,
+            usage: BufferUsage::UNIFORM,
+            memory_hint: None,
+        })?;
+
+        let usage = instance.host_memory_usage().expect("host allocations are tracked");
+        log::info!("host memory usage: {:?}", usage);
+        assert!(usage.allocation_count > 0);
+        assert!(usage.allocated_bytes > 0);
+
+        let untracked = Instance::new()?;
+        assert_eq!(None, untracked.host_memory_usage());
+
+        Ok(instance)
+    });
+}
+
 #[test]
 fn instance_request_adapter() {
     let _ = pretty_env_logger::try_init();
@@ -44,6 +147,121 @@ fn instance_enumerate_adapters() {
     });
 }
 
+#[test]
+fn adapter_supports_vertex_format() {
+    let _ = pretty_env_logger::try_init();
+    vki::validate(|| {
+        let instance = Instance::new()?;
+        let adapter = instance.request_adapter(AdapterOptions::default())?;
+
+        // Required by the Vulkan specification
+        assert!(adapter.supports_vertex_format(VertexFormat::Float3));
+        assert!(adapter.supports_vertex_format(VertexFormat::UShortNorm));
+        assert!(adapter.supports_vertex_format(VertexFormat::ShortNorm));
+        assert!(adapter.supports_vertex_format(VertexFormat::UInt1010102Norm));
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn adapter_supported_sample_counts() {
+    let _ = pretty_env_logger::try_init();
+    vki::validate(|| {
+        let instance = Instance::new()?;
+        let adapter = instance.request_adapter(AdapterOptions::default())?;
+        let device = adapter.create_device(DeviceDescriptor::default())?;
+
+        let format = TextureFormat::R8G8B8A8Unorm;
+        let usage = TextureUsage::OUTPUT_ATTACHMENT;
+        let sample_counts = adapter.supported_sample_counts(format, usage);
+
+        // Color attachments support 1 and 4 samples per the Vulkan specification
+        assert!(sample_counts.contains(&1));
+        assert!(sample_counts.contains(&4));
+        assert!(sample_counts.windows(2).all(|w| w[0] < w[1]));
+
+        let max_sample_count = *sample_counts.last().unwrap();
+        let _texture = device.create_texture(
+            TextureDescriptor::new_2d(format, 64, 64)
+                .with_usage(usage)
+                .with_sample_count(max_sample_count),
+        )?;
+
+        // Compressed formats cannot be multisampled or used as attachments
+        assert!(!adapter
+            .supported_sample_counts(TextureFormat::BC1RGBAUnorm, usage)
+            .contains(&4));
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn adapter_memory_properties() {
+    let _ = pretty_env_logger::try_init();
+    vki::validate(|| {
+        let instance = Instance::new()?;
+        let adapter = instance.request_adapter(AdapterOptions::default())?;
+
+        let memory_properties = adapter.memory_properties();
+        assert!(!memory_properties.memory_heaps.is_empty());
+        assert!(!memory_properties.memory_types.is_empty());
+        for memory_type in memory_properties.memory_types.iter() {
+            assert!((memory_type.heap_index as usize) < memory_properties.memory_heaps.len());
+        }
+
+        let properties = adapter.properties();
+        assert!(properties.limits.max_image_dimension2_d > 0);
+        if let Some(driver) = properties.driver {
+            assert!(!driver.driver_name.is_empty());
+        }
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn adapter_info() {
+    let _ = pretty_env_logger::try_init();
+    vki::validate(|| {
+        let instance = Instance::new()?;
+        let adapters = instance.enumerate_adapters()?;
+
+        for (i, a) in adapters.iter().enumerate() {
+            for b in adapters[i + 1..].iter() {
+                if let (Some(a), Some(b)) = (a.info().device_uuid, b.info().device_uuid) {
+                    assert_ne!(a, b);
+                }
+            }
+        }
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn adapter_portability_features() {
+    let _ = pretty_env_logger::try_init();
+    vki::validate(|| {
+        let instance = Instance::new()?;
+        let adapter = instance.request_adapter(AdapterOptions::default())?;
+
+        // The device must enable `VK_KHR_portability_subset` on portability implementations
+        let _device = adapter.create_device(DeviceDescriptor::default())?;
+
+        if let Some(portability_features) = adapter.portability_features() {
+            let unsupported = portability_features.unsupported_features();
+            assert_eq!(
+                unsupported.contains(&"triangle_fans"),
+                !portability_features.triangle_fans
+            );
+        }
+
+        Ok(instance)
+    });
+}
+
 #[test]
 fn instance_create_device() {
     let _ = pretty_env_logger::try_init();
@@ -56,3 +274,59 @@ fn instance_create_device() {
         Ok(instance)
     });
 }
+
+#[test]
+fn adapter_group_create_device() {
+    let _ = pretty_env_logger::try_init();
+    vki::validate(|| {
+        let instance = Instance::new()?;
+        let adapter_groups = instance.enumerate_adapter_groups()?;
+        assert!(!adapter_groups.is_empty(), "no adapter groups were found");
+
+        let adapter_group = &adapter_groups[0];
+        assert!(!adapter_group.adapters().is_empty());
+
+        let device = adapter_group.create_device(DeviceDescriptor::default())?;
+        assert_eq!(adapter_group.adapters().len() as u32, device.physical_device_count());
+
+        let all_devices = (1 << device.physical_device_count()) - 1;
+        let queue = device.get_queue();
+        let encoder = device.create_command_encoder()?;
+        queue.submit_with_device_mask(&[encoder.finish()?], all_devices)?;
+
+        let encoder = device.create_command_encoder()?;
+        assert!(queue.submit_with_device_mask(&[encoder.finish()?], 0).is_err());
+
+        let encoder = device.create_command_encoder()?;
+        assert!(queue
+            .submit_with_device_mask(&[encoder.finish()?], all_devices + 1)
+            .is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn instance_create_device_with_shader_extensions() {
+    let _ = pretty_env_logger::try_init();
+    vki::validate(|| {
+        let instance = Instance::new()?;
+        let options = AdapterOptions::default();
+        let adapter = instance.request_adapter(options)?;
+        let supported = adapter.extensions();
+        let device = adapter.create_device(DeviceDescriptor {
+            extensions: Extensions {
+                shader_float16: supported.shader_float16,
+                shader_int8: supported.shader_int8,
+                storage_buffer_8bit_access: supported.storage_buffer_8bit_access,
+                storage_buffer_16bit_access: supported.storage_buffer_16bit_access,
+                ..Extensions::default()
+            },
+            ..DeviceDescriptor::default()
+        })?;
+        assert_eq!(supported.shader_float16, device.extensions().shader_float16);
+        assert_eq!(supported.shader_int8, device.extensions().shader_int8);
+
+        Ok(instance)
+    });
+}
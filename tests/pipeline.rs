@@ -6,14 +6,15 @@ extern crate memoffset;
 use std::borrow::Cow;
 use vki::{
     AddressMode, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource,
-    BindingType, BlendDescriptor, BlendFactor, BlendOperation, BufferDescriptor, BufferUsage, BufferViewDescriptor,
-    BufferViewFormat, Color, ColorStateDescriptor, ColorWrite, CompareFunction, ComputePipelineDescriptor, CullMode,
-    DepthStencilStateDescriptor, Extent3d, FilterMode, FrontFace, IndexFormat, InputStepMode, LoadOp,
-    PipelineLayoutDescriptor, PipelineStageDescriptor, PolygonMode, PrimitiveTopology, RasterizationStateDescriptor,
-    RenderPassColorAttachmentDescriptor, RenderPassDescriptor, RenderPipelineDescriptor, SamplerDescriptor,
-    ShaderModuleDescriptor, ShaderStage, StencilOperation, StencilStateFaceDescriptor, StoreOp, Texture,
-    TextureDescriptor, TextureDimension, TextureFormat, TextureUsage, TextureView, VertexAttributeDescriptor,
-    VertexBufferLayoutDescriptor, VertexFormat, VertexStateDescriptor,
+    BindingType, BlendDescriptor, BlendFactor, BlendOperation, BorderColor, BufferDescriptor, BufferUsage,
+    BufferViewDescriptor, BufferViewFormat, Color, ColorStateDescriptor, ColorWrite, CompareFunction,
+    ComputePipelineDescriptor, CullMode, DepthStencilStateDescriptor, DeviceDescriptor, Extensions, Extent3d,
+    FilterMode, FrontFace, IndexFormat, InputStepMode, LoadOp, PipelineLayoutDescriptor, PipelineStageDescriptor,
+    PolygonMode, PrimitiveTopology, RasterizationStateDescriptor, RenderPassColorAttachmentDescriptor,
+    RenderPassDescriptor, RenderPipelineDescriptor, SamplerDescriptor, ShaderModuleDescriptor, ShaderStage,
+    StencilOperation, StencilStateFaceDescriptor, StoreOp, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsage, TextureView, VertexAttributeDescriptor, VertexBufferLayoutDescriptor, VertexFormat, VertexLayout,
+    VertexStateDescriptor,
 };
 
 pub mod support;
@@ -36,16 +37,22 @@ fn create_pipeline_layout() {
                     binding: 0,
                     visibility: ShaderStage::VERTEX,
                     binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 1,
                     visibility: ShaderStage::FRAGMENT,
                     binding_type: BindingType::Sampler,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 2,
                     visibility: ShaderStage::FRAGMENT,
                     binding_type: BindingType::SampledTexture,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
             ],
         };
@@ -62,59 +69,972 @@ fn create_pipeline_layout() {
     });
 }
 
+#[test]
+fn create_pipeline_layout_limits() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let limits = device.limits().clone();
+        assert!(limits.max_bind_groups >= 4);
+
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStage::FRAGMENT,
+                binding_type: BindingType::DynamicUniformBuffer,
+                storage_texture_format: None,
+                min_binding_size: None,
+            }],
+        })?;
+
+        // All of the bind groups supported by the device may be used
+        let max_bind_groups = limits
+            .max_bind_groups
+            .min(limits.max_dynamic_uniform_buffers_per_pipeline_layout);
+        let _pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout.clone(); max_bind_groups as usize],
+            push_constant_ranges: vec![],
+        })?;
+
+        assert!(device
+            .create_pipeline_layout(PipelineLayoutDescriptor {
+                bind_group_layouts: vec![bind_group_layout.clone(); limits.max_bind_groups as usize + 1],
+                push_constant_ranges: vec![],
+            })
+            .is_err());
+
+        Ok(instance)
+    });
+}
+
 #[test]
 fn create_compute_pipeline() {
     vki::validate(|| {
         let (instance, _adapter, device) = support::init()?;
 
-        let shader_module_descriptor = ShaderModuleDescriptor {
-            code: include_bytes!("shaders/pipeline.comp.spv"),
-        };
-        let shader_module = device.create_shader_module(shader_module_descriptor)?;
+        let shader_module_descriptor = ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.comp.spv"),
+        };
+        let shader_module = device.create_shader_module(shader_module_descriptor)?;
+
+        let bind_group_layout_descriptor = BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::COMPUTE,
+                    binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStage::COMPUTE,
+                    binding_type: BindingType::StorageBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+            ],
+        };
+        let bind_group_layout = device.create_bind_group_layout(bind_group_layout_descriptor)?;
+
+        let pipeline_layout_descriptor = PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout],
+            push_constant_ranges: vec![],
+        };
+
+        let pipeline_layout = device.create_pipeline_layout(pipeline_layout_descriptor)?;
+
+        let pipeline_stage_descriptor = PipelineStageDescriptor {
+            entry_point: Cow::Borrowed("main"),
+            module: shader_module,
+        };
+
+        let compute_pipeline_descriptor = ComputePipelineDescriptor {
+            layout: pipeline_layout,
+            compute_stage: pipeline_stage_descriptor,
+            required_subgroup_size: None,
+        };
+
+        let _compute_pipeline = device.create_compute_pipeline(compute_pipeline_descriptor)?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_compute_pipeline_incompatible_layout() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.comp.spv"),
+        })?;
+
+        let create_compute_pipeline = |entry_point: &'static str, entries: Vec<BindGroupLayoutEntry>| {
+            let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor { entries })?;
+            let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+                bind_group_layouts: vec![bind_group_layout],
+                push_constant_ranges: vec![],
+            })?;
+            device.create_compute_pipeline(ComputePipelineDescriptor {
+                layout: pipeline_layout,
+                compute_stage: PipelineStageDescriptor {
+                    entry_point: Cow::Borrowed(entry_point),
+                    module: shader_module.clone(),
+                },
+                required_subgroup_size: None,
+            })
+        };
+
+        let entry = |binding: u32, visibility: ShaderStage, binding_type: BindingType| BindGroupLayoutEntry {
+            binding,
+            visibility,
+            binding_type,
+            storage_texture_format: None,
+            min_binding_size: None,
+        };
+
+        // Dynamic buffers are compatible with the shader's buffers
+        create_compute_pipeline(
+            "main",
+            vec![
+                entry(0, ShaderStage::COMPUTE, BindingType::DynamicUniformBuffer),
+                entry(
+                    1,
+                    ShaderStage::COMPUTE | ShaderStage::FRAGMENT,
+                    BindingType::DynamicStorageBuffer,
+                ),
+            ],
+        )?;
+
+        // Missing binding
+        assert!(
+            create_compute_pipeline("main", vec![entry(0, ShaderStage::COMPUTE, BindingType::UniformBuffer)]).is_err()
+        );
+
+        // Incompatible binding type
+        assert!(create_compute_pipeline(
+            "main",
+            vec![
+                entry(0, ShaderStage::COMPUTE, BindingType::UniformBuffer),
+                entry(1, ShaderStage::COMPUTE, BindingType::UniformBuffer),
+            ]
+        )
+        .is_err());
+
+        // Missing stage visibility
+        assert!(create_compute_pipeline(
+            "main",
+            vec![
+                entry(0, ShaderStage::COMPUTE, BindingType::UniformBuffer),
+                entry(1, ShaderStage::VERTEX, BindingType::StorageBuffer),
+            ]
+        )
+        .is_err());
+
+        // Missing entry point
+        assert!(create_compute_pipeline(
+            "not_main",
+            vec![
+                entry(0, ShaderStage::COMPUTE, BindingType::UniformBuffer),
+                entry(1, ShaderStage::COMPUTE, BindingType::StorageBuffer),
+            ]
+        )
+        .is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_compute_pipeline_required_subgroup_size() {
+    vki::validate(|| {
+        let (instance, adapter, device) = support::init()?;
+
+        let create_pipeline = |device: &vki::Device, required_subgroup_size| {
+            let module = device.create_shader_module(ShaderModuleDescriptor {
+                code: include_bytes!("shaders/pipeline.comp.spv"),
+            })?;
+            let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+                entries: vec![
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStage::COMPUTE,
+                        binding_type: BindingType::UniformBuffer,
+                        storage_texture_format: None,
+                        min_binding_size: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStage::COMPUTE,
+                        binding_type: BindingType::StorageBuffer,
+                        storage_texture_format: None,
+                        min_binding_size: None,
+                    },
+                ],
+            })?;
+            let layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+                bind_group_layouts: vec![bind_group_layout],
+                push_constant_ranges: vec![],
+            })?;
+            device.create_compute_pipeline(ComputePipelineDescriptor {
+                layout,
+                compute_stage: PipelineStageDescriptor {
+                    entry_point: Cow::Borrowed("main"),
+                    module,
+                },
+                required_subgroup_size: Some(required_subgroup_size),
+            })
+        };
+
+        // The extension was not enabled
+        assert!(create_pipeline(&device, 32).is_err());
+
+        let subgroup_properties = match adapter.subgroup_properties() {
+            Some(properties) if adapter.extensions().subgroup_size_control => properties,
+            _ => {
+                log::warn!("subgroup_size_control is not supported, skipping test");
+                return Ok(instance);
+            }
+        };
+
+        if !subgroup_properties
+            .required_subgroup_size_stages
+            .contains(ShaderStage::COMPUTE)
+        {
+            log::warn!("required_subgroup_size is not supported for compute, skipping test");
+            return Ok(instance);
+        }
+
+        let device = adapter.create_device(DeviceDescriptor {
+            extensions: Extensions {
+                subgroup_size_control: true,
+                ..Extensions::default()
+            },
+            ..DeviceDescriptor::default()
+        })?;
+
+        let _compute_pipeline = create_pipeline(&device, subgroup_properties.max_subgroup_size)?;
+
+        // Not a power of two
+        assert!(create_pipeline(&device, subgroup_properties.max_subgroup_size + 1).is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_compute_pipeline_compilation_info() {
+    vki::validate(|| {
+        let (instance, adapter, device) = support::init()?;
+
+        let create_pipeline = |device: &vki::Device| {
+            let module = device.create_shader_module(ShaderModuleDescriptor {
+                code: include_bytes!("shaders/pipeline.comp.spv"),
+            })?;
+            let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+                entries: vec![
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStage::COMPUTE,
+                        binding_type: BindingType::UniformBuffer,
+                        storage_texture_format: None,
+                        min_binding_size: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStage::COMPUTE,
+                        binding_type: BindingType::StorageBuffer,
+                        storage_texture_format: None,
+                        min_binding_size: None,
+                    },
+                ],
+            })?;
+            let layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+                bind_group_layouts: vec![bind_group_layout],
+                push_constant_ranges: vec![],
+            })?;
+            device.create_compute_pipeline(ComputePipelineDescriptor {
+                layout,
+                compute_stage: PipelineStageDescriptor {
+                    entry_point: Cow::Borrowed("main"),
+                    module,
+                },
+                required_subgroup_size: None,
+            })
+        };
+
+        // The extension was not enabled
+        assert_eq!(None, create_pipeline(&device)?.compilation_info());
+
+        if !adapter.supported_extensions().pipeline_creation_feedback {
+            log::warn!("pipeline_creation_feedback is not supported, skipping test");
+            return Ok(instance);
+        }
+
+        let device = adapter.create_device(DeviceDescriptor {
+            extensions: Extensions {
+                pipeline_creation_feedback: true,
+                ..Extensions::default()
+            },
+            ..DeviceDescriptor::default()
+        })?;
+
+        let compute_pipeline = create_pipeline(&device)?;
+        let compilation_info = compute_pipeline.compilation_info().expect("compilation_info");
+        log::info!("compilation_info: {:?}", compilation_info);
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_render_pipeline() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let vertex_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.vert.spv"),
+        })?;
+
+        let fragment_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.frag.spv"),
+        })?;
+
+        #[rustfmt::skip]
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::VERTEX,
+                    binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                }
+            ],
+        })?;
+
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout],
+            push_constant_ranges: vec![],
+        })?;
+
+        #[repr(C)]
+        struct Vertex {
+            position: [f32; 3],
+            normal: [f32; 3],
+        }
+
+        let color_replace = BlendDescriptor {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::Zero,
+            operation: BlendOperation::Add,
+        };
+
+        let stencil_disabled = StencilStateFaceDescriptor {
+            compare: CompareFunction::Always,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op: StencilOperation::Keep,
+        };
+
+        #[rustfmt::skip]
+        let render_pipeline_descriptor = RenderPipelineDescriptor {
+            layout: pipeline_layout,
+            primitive_topology: PrimitiveTopology::TriangleList,
+            vertex_stage: PipelineStageDescriptor {
+                entry_point: Cow::Borrowed("main"),
+                module: vertex_shader_module,
+            },
+            fragment_stage: Some(PipelineStageDescriptor {
+                entry_point: Cow::Borrowed("main"),
+                module: fragment_shader_module,
+            }),
+            vertex_state: VertexStateDescriptor {
+                index_format: IndexFormat::U16,
+                primitive_restart_enabled: false,
+                vertex_buffers: vec![
+                    VertexBufferLayoutDescriptor {
+                        input_slot: 0,
+                        step_mode: InputStepMode::Vertex,
+                        step_rate: 1,
+                        stride: std::mem::size_of::<Vertex>(),
+                        attributes: vec![
+                            VertexAttributeDescriptor {
+                                format: VertexFormat::Float3,
+                                offset: offset_of!(Vertex, position),
+                                shader_location: 0,
+                            },
+                            VertexAttributeDescriptor {
+                                format: VertexFormat::Float3,
+                                offset: offset_of!(Vertex, normal),
+                                shader_location: 1,
+                            },
+                        ],
+                    }
+                ],
+            },
+            color_states: vec![
+                ColorStateDescriptor {
+                    format: TextureFormat::B8G8R8A8Unorm,
+                    write_mask: ColorWrite::ALL,
+                    color_blend: color_replace,
+                    alpha_blend: color_replace,
+                }
+            ],
+            depth_stencil_state: Some(DepthStencilStateDescriptor {
+                format: TextureFormat::D32FloatS8Uint,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Less,
+                stencil_back: stencil_disabled,
+                stencil_front: stencil_disabled,
+                stencil_write_mask: 0,
+                stencil_read_mask: 0,
+            }),
+            rasterization_state: RasterizationStateDescriptor {
+                front_face: FrontFace::Ccw,
+                cull_mode: CullMode::Back,
+                polygon_mode: PolygonMode::Fill,
+                depth_bias: 0,
+                depth_bias_slope_scale: 0.0,
+                depth_bias_clamp: 0.0,
+            },
+            sample_count: 1,
+            alpha_to_coverage_enabled: false,
+            blend_color: None,
+        };
+
+        let _render_pipeline = device.create_render_pipeline(render_pipeline_descriptor)?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_render_pipeline_with_builder() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let vertex_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.vert.spv"),
+        })?;
+
+        let fragment_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.frag.spv"),
+        })?;
+
+        #[rustfmt::skip]
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::VERTEX,
+                    binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                }
+            ],
+        })?;
+
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout],
+            push_constant_ranges: vec![],
+        })?;
+
+        #[repr(C)]
+        #[derive(VertexLayout)]
+        struct Vertex {
+            position: [f32; 3],
+            normal: [f32; 3],
+        }
+
+        let render_pipeline_descriptor = RenderPipelineDescriptor::new(
+            pipeline_layout,
+            PipelineStageDescriptor::new(vertex_shader_module, "main"),
+        )
+        .with_fragment_stage(PipelineStageDescriptor::new(fragment_shader_module, "main"))
+        .with_vertex_buffer(Vertex::vertex_buffer_layout(0, InputStepMode::Vertex))
+        .with_color_state(ColorStateDescriptor::simple(TextureFormat::B8G8R8A8Unorm));
+
+        assert_eq!(
+            PrimitiveTopology::TriangleList,
+            render_pipeline_descriptor.primitive_topology
+        );
+        assert_eq!(1, render_pipeline_descriptor.sample_count);
+        assert_eq!(1, render_pipeline_descriptor.color_states.len());
+        assert_eq!(1, render_pipeline_descriptor.vertex_state.vertex_buffers.len());
+
+        let _render_pipeline = device.create_render_pipeline(render_pipeline_descriptor)?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_render_pipeline_cached() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let vertex_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.vert.spv"),
+        })?;
+
+        let fragment_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.frag.spv"),
+        })?;
+
+        #[rustfmt::skip]
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::VERTEX,
+                    binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                }
+            ],
+        })?;
+
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout],
+            push_constant_ranges: vec![],
+        })?;
+
+        #[repr(C)]
+        #[derive(VertexLayout)]
+        struct Vertex {
+            position: [f32; 3],
+            normal: [f32; 3],
+        }
+
+        let render_pipeline_descriptor = RenderPipelineDescriptor::new(
+            pipeline_layout,
+            PipelineStageDescriptor::new(vertex_shader_module, "main"),
+        )
+        .with_fragment_stage(PipelineStageDescriptor::new(fragment_shader_module, "main"))
+        .with_vertex_buffer(Vertex::vertex_buffer_layout(0, InputStepMode::Vertex))
+        .with_color_state(ColorStateDescriptor::simple(TextureFormat::B8G8R8A8Unorm));
+
+        let render_pipeline_a = device.create_render_pipeline(render_pipeline_descriptor.clone())?;
+        let render_pipeline_b = device.create_render_pipeline(render_pipeline_descriptor.clone())?;
+        assert_eq!(render_pipeline_a, render_pipeline_b);
+
+        let render_pipeline_c = device.create_render_pipeline(
+            render_pipeline_descriptor
+                .clone()
+                .with_rasterization_state(RasterizationStateDescriptor::CULL_BACK),
+        )?;
+        assert_ne!(render_pipeline_a, render_pipeline_c);
+
+        // The cache does not keep the pipeline alive
+        drop(render_pipeline_a);
+        drop(render_pipeline_b);
+        let _render_pipeline_d = device.create_render_pipeline(render_pipeline_descriptor)?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_render_pipeline_primitive_restart() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let vertex_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.vert.spv"),
+        })?;
+
+        let fragment_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.frag.spv"),
+        })?;
+
+        #[rustfmt::skip]
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::VERTEX,
+                    binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                }
+            ],
+        })?;
+
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout],
+            push_constant_ranges: vec![],
+        })?;
+
+        #[repr(C)]
+        struct Vertex {
+            position: [f32; 3],
+            normal: [f32; 3],
+        }
+
+        let color_replace = BlendDescriptor {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::Zero,
+            operation: BlendOperation::Add,
+        };
+
+        let stencil_disabled = StencilStateFaceDescriptor {
+            compare: CompareFunction::Always,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op: StencilOperation::Keep,
+        };
+
+        #[rustfmt::skip]
+        let mut render_pipeline_descriptor = RenderPipelineDescriptor {
+            layout: pipeline_layout,
+            primitive_topology: PrimitiveTopology::TriangleList,
+            vertex_stage: PipelineStageDescriptor {
+                entry_point: Cow::Borrowed("main"),
+                module: vertex_shader_module,
+            },
+            fragment_stage: Some(PipelineStageDescriptor {
+                entry_point: Cow::Borrowed("main"),
+                module: fragment_shader_module,
+            }),
+            vertex_state: VertexStateDescriptor {
+                index_format: IndexFormat::U16,
+                primitive_restart_enabled: true,
+                vertex_buffers: vec![
+                    VertexBufferLayoutDescriptor {
+                        input_slot: 0,
+                        step_mode: InputStepMode::Vertex,
+                        step_rate: 1,
+                        stride: std::mem::size_of::<Vertex>(),
+                        attributes: vec![
+                            VertexAttributeDescriptor {
+                                format: VertexFormat::Float3,
+                                offset: offset_of!(Vertex, position),
+                                shader_location: 0,
+                            },
+                            VertexAttributeDescriptor {
+                                format: VertexFormat::Float3,
+                                offset: offset_of!(Vertex, normal),
+                                shader_location: 1,
+                            },
+                        ],
+                    }
+                ],
+            },
+            color_states: vec![
+                ColorStateDescriptor {
+                    format: TextureFormat::B8G8R8A8Unorm,
+                    write_mask: ColorWrite::ALL,
+                    color_blend: color_replace,
+                    alpha_blend: color_replace,
+                }
+            ],
+            depth_stencil_state: Some(DepthStencilStateDescriptor {
+                format: TextureFormat::D32FloatS8Uint,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Less,
+                stencil_back: stencil_disabled,
+                stencil_front: stencil_disabled,
+                stencil_write_mask: 0,
+                stencil_read_mask: 0,
+            }),
+            rasterization_state: RasterizationStateDescriptor {
+                front_face: FrontFace::Ccw,
+                cull_mode: CullMode::Back,
+                polygon_mode: PolygonMode::Fill,
+                depth_bias: 0,
+                depth_bias_slope_scale: 0.0,
+                depth_bias_clamp: 0.0,
+            },
+            sample_count: 1,
+            alpha_to_coverage_enabled: false,
+            blend_color: None,
+        };
+
+        // Primitive restart is not allowed with list topologies
+        assert!(device
+            .create_render_pipeline(render_pipeline_descriptor.clone())
+            .is_err());
+
+        render_pipeline_descriptor.primitive_topology = PrimitiveTopology::TriangleStrip;
+        let _render_pipeline = device.create_render_pipeline(render_pipeline_descriptor)?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_render_pipeline_blend_color() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let vertex_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.vert.spv"),
+        })?;
+
+        let fragment_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.frag.spv"),
+        })?;
+
+        #[rustfmt::skip]
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::VERTEX,
+                    binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                }
+            ],
+        })?;
+
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout],
+            push_constant_ranges: vec![],
+        })?;
+
+        #[repr(C)]
+        struct Vertex {
+            position: [f32; 3],
+            normal: [f32; 3],
+        }
+
+        let color_replace = BlendDescriptor {
+            src_factor: BlendFactor::BlendColor,
+            dst_factor: BlendFactor::Zero,
+            operation: BlendOperation::Add,
+        };
+
+        let stencil_disabled = StencilStateFaceDescriptor {
+            compare: CompareFunction::Always,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op: StencilOperation::Keep,
+        };
+
+        #[rustfmt::skip]
+        let mut render_pipeline_descriptor = RenderPipelineDescriptor {
+            layout: pipeline_layout,
+            primitive_topology: PrimitiveTopology::TriangleList,
+            vertex_stage: PipelineStageDescriptor {
+                entry_point: Cow::Borrowed("main"),
+                module: vertex_shader_module,
+            },
+            fragment_stage: Some(PipelineStageDescriptor {
+                entry_point: Cow::Borrowed("main"),
+                module: fragment_shader_module,
+            }),
+            vertex_state: VertexStateDescriptor {
+                index_format: IndexFormat::U16,
+                primitive_restart_enabled: false,
+                vertex_buffers: vec![
+                    VertexBufferLayoutDescriptor {
+                        input_slot: 0,
+                        step_mode: InputStepMode::Vertex,
+                        step_rate: 1,
+                        stride: std::mem::size_of::<Vertex>(),
+                        attributes: vec![
+                            VertexAttributeDescriptor {
+                                format: VertexFormat::Float3,
+                                offset: offset_of!(Vertex, position),
+                                shader_location: 0,
+                            },
+                            VertexAttributeDescriptor {
+                                format: VertexFormat::Float3,
+                                offset: offset_of!(Vertex, normal),
+                                shader_location: 1,
+                            },
+                        ],
+                    }
+                ],
+            },
+            color_states: vec![
+                ColorStateDescriptor {
+                    format: TextureFormat::B8G8R8A8Unorm,
+                    write_mask: ColorWrite::ALL,
+                    color_blend: color_replace,
+                    alpha_blend: color_replace,
+                }
+            ],
+            depth_stencil_state: Some(DepthStencilStateDescriptor {
+                format: TextureFormat::D32FloatS8Uint,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Less,
+                stencil_back: stencil_disabled,
+                stencil_front: stencil_disabled,
+                stencil_write_mask: 0,
+                stencil_read_mask: 0,
+            }),
+            rasterization_state: RasterizationStateDescriptor {
+                front_face: FrontFace::Ccw,
+                cull_mode: CullMode::Back,
+                polygon_mode: PolygonMode::Fill,
+                depth_bias: 0,
+                depth_bias_slope_scale: 0.0,
+                depth_bias_clamp: 0.0,
+            },
+            sample_count: 1,
+            alpha_to_coverage_enabled: false,
+            blend_color: None,
+        };
+
+        // BlendFactor::BlendColor requires a blend color
+        assert!(device
+            .create_render_pipeline(render_pipeline_descriptor.clone())
+            .is_err());
+
+        render_pipeline_descriptor.blend_color = Some(Color {
+            r: 0.5,
+            g: 0.5,
+            b: 0.5,
+            a: 1.0,
+        });
+        let _render_pipeline = device.create_render_pipeline(render_pipeline_descriptor)?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_depth_only_render_pipeline() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let vertex_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.vert.spv"),
+        })?;
 
-        let bind_group_layout_descriptor = BindGroupLayoutDescriptor {
+        #[rustfmt::skip]
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
             entries: vec![
                 BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: ShaderStage::COMPUTE,
+                    visibility: ShaderStage::VERTEX,
                     binding_type: BindingType::UniformBuffer,
-                },
-                BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: ShaderStage::COMPUTE,
-                    binding_type: BindingType::StorageBuffer,
-                },
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                }
             ],
-        };
-        let bind_group_layout = device.create_bind_group_layout(bind_group_layout_descriptor)?;
+        })?;
 
-        let pipeline_layout_descriptor = PipelineLayoutDescriptor {
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
             bind_group_layouts: vec![bind_group_layout],
             push_constant_ranges: vec![],
-        };
+        })?;
 
-        let pipeline_layout = device.create_pipeline_layout(pipeline_layout_descriptor)?;
+        #[repr(C)]
+        struct Vertex {
+            position: [f32; 3],
+            normal: [f32; 3],
+        }
 
-        let pipeline_stage_descriptor = PipelineStageDescriptor {
-            entry_point: Cow::Borrowed("main"),
-            module: shader_module,
+        let color_replace = BlendDescriptor {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::Zero,
+            operation: BlendOperation::Add,
         };
 
-        let compute_pipeline_descriptor = ComputePipelineDescriptor {
+        let stencil_disabled = StencilStateFaceDescriptor {
+            compare: CompareFunction::Always,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op: StencilOperation::Keep,
+        };
+
+        #[rustfmt::skip]
+        let mut render_pipeline_descriptor = RenderPipelineDescriptor {
             layout: pipeline_layout,
-            compute_stage: pipeline_stage_descriptor,
+            primitive_topology: PrimitiveTopology::TriangleList,
+            vertex_stage: PipelineStageDescriptor {
+                entry_point: Cow::Borrowed("main"),
+                module: vertex_shader_module,
+            },
+            fragment_stage: None,
+            vertex_state: VertexStateDescriptor {
+                index_format: IndexFormat::U16,
+                primitive_restart_enabled: false,
+                vertex_buffers: vec![
+                    VertexBufferLayoutDescriptor {
+                        input_slot: 0,
+                        step_mode: InputStepMode::Vertex,
+                        step_rate: 1,
+                        stride: std::mem::size_of::<Vertex>(),
+                        attributes: vec![
+                            VertexAttributeDescriptor {
+                                format: VertexFormat::Float3,
+                                offset: offset_of!(Vertex, position),
+                                shader_location: 0,
+                            },
+                            VertexAttributeDescriptor {
+                                format: VertexFormat::Float3,
+                                offset: offset_of!(Vertex, normal),
+                                shader_location: 1,
+                            },
+                        ],
+                    }
+                ],
+            },
+            color_states: vec![
+                ColorStateDescriptor {
+                    format: TextureFormat::B8G8R8A8Unorm,
+                    write_mask: ColorWrite::ALL,
+                    color_blend: color_replace,
+                    alpha_blend: color_replace,
+                }
+            ],
+            depth_stencil_state: Some(DepthStencilStateDescriptor {
+                format: TextureFormat::D32FloatS8Uint,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Less,
+                stencil_back: stencil_disabled,
+                stencil_front: stencil_disabled,
+                stencil_write_mask: 0,
+                stencil_read_mask: 0,
+            }),
+            rasterization_state: RasterizationStateDescriptor {
+                front_face: FrontFace::Ccw,
+                cull_mode: CullMode::Back,
+                polygon_mode: PolygonMode::Fill,
+                depth_bias: 0,
+                depth_bias_slope_scale: 0.0,
+                depth_bias_clamp: 0.0,
+            },
+            sample_count: 1,
+            alpha_to_coverage_enabled: false,
+            blend_color: None,
         };
 
-        let _compute_pipeline = device.create_compute_pipeline(compute_pipeline_descriptor)?;
+        // Color states require a fragment stage
+        assert!(device
+            .create_render_pipeline(render_pipeline_descriptor.clone())
+            .is_err());
+
+        render_pipeline_descriptor.color_states.clear();
+        let _render_pipeline = device.create_render_pipeline(render_pipeline_descriptor)?;
 
         Ok(instance)
     });
 }
 
 #[test]
-fn create_render_pipeline() {
+fn create_render_pipeline_index_format_u8() {
     vki::validate(|| {
-        let (instance, _adapter, device) = support::init()?;
+        let (instance, adapter, _device) = support::init()?;
+
+        let index_type_uint8 = adapter.extensions().index_type_uint8;
+        if !index_type_uint8 {
+            log::warn!("index_type_uint8 is not supported, expecting pipeline creation to fail");
+        }
+
+        let device = adapter.create_device(DeviceDescriptor {
+            extensions: Extensions {
+                index_type_uint8,
+                ..Extensions::default()
+            },
+            ..DeviceDescriptor::default()
+        })?;
 
         let vertex_shader_module = device.create_shader_module(ShaderModuleDescriptor {
             code: include_bytes!("shaders/pipeline.vert.spv"),
@@ -131,6 +1051,8 @@ fn create_render_pipeline() {
                     binding: 0,
                     visibility: ShaderStage::VERTEX,
                     binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 }
             ],
         })?;
@@ -167,16 +1089,154 @@ fn create_render_pipeline() {
                 entry_point: Cow::Borrowed("main"),
                 module: vertex_shader_module,
             },
-            fragment_stage: PipelineStageDescriptor {
+            fragment_stage: Some(PipelineStageDescriptor {
                 entry_point: Cow::Borrowed("main"),
                 module: fragment_shader_module,
+            }),
+            vertex_state: VertexStateDescriptor {
+                index_format: IndexFormat::U8,
+                primitive_restart_enabled: false,
+                vertex_buffers: vec![
+                    VertexBufferLayoutDescriptor {
+                        input_slot: 0,
+                        step_mode: InputStepMode::Vertex,
+                        step_rate: 1,
+                        stride: std::mem::size_of::<Vertex>(),
+                        attributes: vec![
+                            VertexAttributeDescriptor {
+                                format: VertexFormat::Float3,
+                                offset: offset_of!(Vertex, position),
+                                shader_location: 0,
+                            },
+                            VertexAttributeDescriptor {
+                                format: VertexFormat::Float3,
+                                offset: offset_of!(Vertex, normal),
+                                shader_location: 1,
+                            },
+                        ],
+                    }
+                ],
+            },
+            color_states: vec![
+                ColorStateDescriptor {
+                    format: TextureFormat::B8G8R8A8Unorm,
+                    write_mask: ColorWrite::ALL,
+                    color_blend: color_replace,
+                    alpha_blend: color_replace,
+                }
+            ],
+            depth_stencil_state: Some(DepthStencilStateDescriptor {
+                format: TextureFormat::D32FloatS8Uint,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Less,
+                stencil_back: stencil_disabled,
+                stencil_front: stencil_disabled,
+                stencil_write_mask: 0,
+                stencil_read_mask: 0,
+            }),
+            rasterization_state: RasterizationStateDescriptor {
+                front_face: FrontFace::Ccw,
+                cull_mode: CullMode::Back,
+                polygon_mode: PolygonMode::Fill,
+                depth_bias: 0,
+                depth_bias_slope_scale: 0.0,
+                depth_bias_clamp: 0.0,
+            },
+            sample_count: 1,
+            alpha_to_coverage_enabled: false,
+            blend_color: None,
+        };
+
+        let result = device.create_render_pipeline(render_pipeline_descriptor);
+        assert_eq!(index_type_uint8, result.is_ok());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_render_pipeline_step_rate() {
+    vki::validate(|| {
+        let (instance, adapter, _device) = support::init()?;
+
+        let vertex_attribute_divisor = adapter.extensions().vertex_attribute_divisor;
+        if !vertex_attribute_divisor {
+            log::warn!("vertex_attribute_divisor is not supported, expecting pipeline creation to fail");
+        }
+
+        let device = adapter.create_device(DeviceDescriptor {
+            extensions: Extensions {
+                vertex_attribute_divisor,
+                ..Extensions::default()
+            },
+            ..DeviceDescriptor::default()
+        })?;
+
+        let vertex_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.vert.spv"),
+        })?;
+
+        let fragment_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            code: include_bytes!("shaders/pipeline.frag.spv"),
+        })?;
+
+        #[rustfmt::skip]
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::VERTEX,
+                    binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                }
+            ],
+        })?;
+
+        let pipeline_layout = device.create_pipeline_layout(PipelineLayoutDescriptor {
+            bind_group_layouts: vec![bind_group_layout],
+            push_constant_ranges: vec![],
+        })?;
+
+        #[repr(C)]
+        struct Vertex {
+            position: [f32; 3],
+            normal: [f32; 3],
+        }
+
+        let color_replace = BlendDescriptor {
+            src_factor: BlendFactor::One,
+            dst_factor: BlendFactor::Zero,
+            operation: BlendOperation::Add,
+        };
+
+        let stencil_disabled = StencilStateFaceDescriptor {
+            compare: CompareFunction::Always,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op: StencilOperation::Keep,
+        };
+
+        #[rustfmt::skip]
+        let mut render_pipeline_descriptor = RenderPipelineDescriptor {
+            layout: pipeline_layout,
+            primitive_topology: PrimitiveTopology::TriangleList,
+            vertex_stage: PipelineStageDescriptor {
+                entry_point: Cow::Borrowed("main"),
+                module: vertex_shader_module,
             },
+            fragment_stage: Some(PipelineStageDescriptor {
+                entry_point: Cow::Borrowed("main"),
+                module: fragment_shader_module,
+            }),
             vertex_state: VertexStateDescriptor {
                 index_format: IndexFormat::U16,
+                primitive_restart_enabled: false,
                 vertex_buffers: vec![
                     VertexBufferLayoutDescriptor {
                         input_slot: 0,
                         step_mode: InputStepMode::Vertex,
+                        step_rate: 2,
                         stride: std::mem::size_of::<Vertex>(),
                         attributes: vec![
                             VertexAttributeDescriptor {
@@ -220,9 +1280,17 @@ fn create_render_pipeline() {
             },
             sample_count: 1,
             alpha_to_coverage_enabled: false,
+            blend_color: None,
         };
 
-        let _render_pipeline = device.create_render_pipeline(render_pipeline_descriptor)?;
+        // A step rate is only allowed with instance stepping
+        assert!(device
+            .create_render_pipeline(render_pipeline_descriptor.clone())
+            .is_err());
+
+        render_pipeline_descriptor.vertex_state.vertex_buffers[0].step_mode = InputStepMode::Instance;
+        let result = device.create_render_pipeline(render_pipeline_descriptor);
+        assert_eq!(vertex_attribute_divisor, result.is_ok());
 
         Ok(instance)
     });
@@ -248,6 +1316,8 @@ fn create_multi_sample_render_pipeline() {
                     binding: 0,
                     visibility: ShaderStage::VERTEX,
                     binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 }
             ],
         })?;
@@ -257,6 +1327,7 @@ fn create_multi_sample_render_pipeline() {
         let uniform_buffer = device.create_buffer(BufferDescriptor {
             usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
             size: uniform_buffer_size,
+            memory_hint: None,
         })?;
 
         let bind_group = device.create_bind_group(BindGroupDescriptor {
@@ -281,6 +1352,7 @@ fn create_multi_sample_render_pipeline() {
         let vertex_buffer = device.create_buffer(BufferDescriptor {
             usage: BufferUsage::VERTEX,
             size: (3 * std::mem::size_of::<Vertex>()) as _,
+            memory_hint: None,
         })?;
 
         let color_replace = BlendDescriptor {
@@ -299,16 +1371,18 @@ fn create_multi_sample_render_pipeline() {
                 entry_point: Cow::Borrowed("main"),
                 module: vertex_shader_module,
             },
-            fragment_stage: PipelineStageDescriptor {
+            fragment_stage: Some(PipelineStageDescriptor {
                 entry_point: Cow::Borrowed("main"),
                 module: fragment_shader_module,
-            },
+            }),
             vertex_state: VertexStateDescriptor {
                 index_format: IndexFormat::U16,
+                primitive_restart_enabled: false,
                 vertex_buffers: vec![
                     VertexBufferLayoutDescriptor {
                         input_slot: 0,
                         step_mode: InputStepMode::Vertex,
+                        step_rate: 1,
                         stride: std::mem::size_of::<Vertex>(),
                         attributes: vec![
                             VertexAttributeDescriptor {
@@ -344,6 +1418,7 @@ fn create_multi_sample_render_pipeline() {
             },
             sample_count,
             alpha_to_coverage_enabled: false,
+            blend_color: None,
         };
 
         let pipeline = device.create_render_pipeline(render_pipeline_descriptor)?;
@@ -393,22 +1468,27 @@ fn create_multi_sample_render_pipeline() {
         let mut render_pass = encoder.begin_render_pass(RenderPassDescriptor {
             color_attachments: &[RenderPassColorAttachmentDescriptor {
                 attachment: &output_view,
-                clear_color: Color {
+                clear_value: Color {
                     r: 0.1,
                     g: 0.1,
                     b: 0.1,
                     a: 1.0,
-                },
+                }
+                .into(),
                 load_op: LoadOp::Clear,
                 store_op: StoreOp::Store,
                 resolve_target: Some(&frame.view),
             }],
             depth_stencil_attachment: None,
+            flip_viewport: false,
+            viewport: None,
+            scissor_rect: None,
+            attachmentless: None,
         });
 
         render_pass.set_pipeline(&pipeline);
         render_pass.set_bind_group(0, &bind_group, None);
-        render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0]);
+        render_pass.set_vertex_buffers(0, &[vertex_buffer.clone()], &[0])?;
         render_pass.draw(3, 1, 0, 0);
         render_pass.end_pass();
 
@@ -435,26 +1515,36 @@ fn set_bind_group() {
                     binding: 0,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 1,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::StorageBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 2,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::StorageTexelBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 3,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::Sampler,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 4,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::SampledTexture,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
             ],
         };
@@ -475,6 +1565,7 @@ fn set_bind_group() {
         let compute_pipeline_descriptor = ComputePipelineDescriptor {
             layout: pipeline_layout,
             compute_stage: pipeline_stage_descriptor,
+            required_subgroup_size: None,
         };
 
         let compute_pipeline = device.create_compute_pipeline(compute_pipeline_descriptor)?;
@@ -482,14 +1573,17 @@ fn set_bind_group() {
         let uniform_buffer = device.create_buffer(BufferDescriptor {
             size: 1024,
             usage: BufferUsage::UNIFORM,
+            memory_hint: None,
         })?;
         let storage_buffer = device.create_buffer(BufferDescriptor {
             size: 1024,
             usage: BufferUsage::STORAGE,
+            memory_hint: None,
         })?;
         let image_buffer = device.create_buffer(BufferDescriptor {
             size: 1024,
             usage: BufferUsage::STORAGE, // TODO: texel storage
+            memory_hint: None,
         })?;
         let image_buffer_view = image_buffer.create_view(BufferViewDescriptor {
             size: 1024,
@@ -506,6 +1600,8 @@ fn set_bind_group() {
             address_mode_v: AddressMode::ClampToEdge,
             address_mode_w: AddressMode::ClampToEdge,
             compare_function: CompareFunction::Never,
+            border_color: BorderColor::TransparentBlack,
+            max_anisotropy: 1,
         })?;
         let texture = device.create_texture(TextureDescriptor {
             size: Extent3d {
@@ -579,26 +1675,36 @@ fn set_bind_group_out_of_order() {
                     binding: 0,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 1,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::StorageBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 2,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::StorageTexelBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 3,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::Sampler,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 4,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::SampledTexture,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
             ],
         };
@@ -619,6 +1725,7 @@ fn set_bind_group_out_of_order() {
         let compute_pipeline_descriptor = ComputePipelineDescriptor {
             layout: pipeline_layout,
             compute_stage: pipeline_stage_descriptor,
+            required_subgroup_size: None,
         };
 
         let compute_pipeline = device.create_compute_pipeline(compute_pipeline_descriptor)?;
@@ -626,14 +1733,17 @@ fn set_bind_group_out_of_order() {
         let uniform_buffer = device.create_buffer(BufferDescriptor {
             size: 1024,
             usage: BufferUsage::UNIFORM,
+            memory_hint: None,
         })?;
         let storage_buffer = device.create_buffer(BufferDescriptor {
             size: 1024,
             usage: BufferUsage::STORAGE,
+            memory_hint: None,
         })?;
         let image_buffer = device.create_buffer(BufferDescriptor {
             size: 1024,
             usage: BufferUsage::STORAGE, // TODO: texel storage
+            memory_hint: None,
         })?;
         let image_buffer_view = image_buffer.create_view(BufferViewDescriptor {
             size: 1024,
@@ -650,6 +1760,8 @@ fn set_bind_group_out_of_order() {
             address_mode_v: AddressMode::ClampToEdge,
             address_mode_w: AddressMode::ClampToEdge,
             compare_function: CompareFunction::Never,
+            border_color: BorderColor::TransparentBlack,
+            max_anisotropy: 1,
         })?;
         let texture = device.create_texture(TextureDescriptor {
             size: Extent3d {
@@ -725,26 +1837,36 @@ fn set_bind_group_dynamic_offsets() {
                     binding: 0,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::DynamicUniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 1,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::DynamicStorageBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 2,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::StorageTexelBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 3,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::Sampler,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
                 BindGroupLayoutEntry {
                     binding: 4,
                     visibility: ShaderStage::COMPUTE,
                     binding_type: BindingType::SampledTexture,
+                    storage_texture_format: None,
+                    min_binding_size: None,
                 },
             ],
         };
@@ -765,6 +1887,7 @@ fn set_bind_group_dynamic_offsets() {
         let compute_pipeline_descriptor = ComputePipelineDescriptor {
             layout: pipeline_layout,
             compute_stage: pipeline_stage_descriptor,
+            required_subgroup_size: None,
         };
 
         let compute_pipeline = device.create_compute_pipeline(compute_pipeline_descriptor)?;
@@ -772,14 +1895,17 @@ fn set_bind_group_dynamic_offsets() {
         let uniform_buffer = device.create_buffer(BufferDescriptor {
             size: 1024,
             usage: BufferUsage::UNIFORM,
+            memory_hint: None,
         })?;
         let storage_buffer = device.create_buffer(BufferDescriptor {
             size: 1024,
             usage: BufferUsage::STORAGE,
+            memory_hint: None,
         })?;
         let image_buffer = device.create_buffer(BufferDescriptor {
             size: 1024,
             usage: BufferUsage::STORAGE, // TODO: texel storage
+            memory_hint: None,
         })?;
         let image_buffer_view = image_buffer.create_view(BufferViewDescriptor {
             size: 1024,
@@ -796,6 +1922,8 @@ fn set_bind_group_dynamic_offsets() {
             address_mode_v: AddressMode::ClampToEdge,
             address_mode_w: AddressMode::ClampToEdge,
             compare_function: CompareFunction::Never,
+            border_color: BorderColor::TransparentBlack,
+            max_anisotropy: 1,
         })?;
         let texture = device.create_texture(TextureDescriptor {
             size: Extent3d {
@@ -856,3 +1984,109 @@ fn set_bind_group_dynamic_offsets() {
         Ok(instance)
     });
 }
+
+#[test]
+fn derive_vertex_layout() {
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug, VertexLayout)]
+    struct Vertex {
+        position: [f32; 3],
+        normal: [f32; 3],
+        #[vertex(skip)]
+        _pad: u32,
+        #[vertex(format = "UChar4Norm")]
+        color: [u8; 4],
+        #[vertex(location = 5)]
+        joint: [u16; 4],
+        weight: [f32; 4],
+    }
+
+    let layout = Vertex::vertex_buffer_layout(1, InputStepMode::Instance);
+    assert_eq!(1, layout.input_slot);
+    assert_eq!(InputStepMode::Instance, layout.step_mode);
+    assert_eq!(1, layout.step_rate);
+    assert_eq!(std::mem::size_of::<Vertex>(), layout.stride);
+    assert_eq!(
+        vec![
+            VertexAttributeDescriptor {
+                format: VertexFormat::Float3,
+                offset: offset_of!(Vertex, position),
+                shader_location: 0,
+            },
+            VertexAttributeDescriptor {
+                format: VertexFormat::Float3,
+                offset: offset_of!(Vertex, normal),
+                shader_location: 1,
+            },
+            VertexAttributeDescriptor {
+                format: VertexFormat::UChar4Norm,
+                offset: offset_of!(Vertex, color),
+                shader_location: 2,
+            },
+            VertexAttributeDescriptor {
+                format: VertexFormat::UShort4,
+                offset: offset_of!(Vertex, joint),
+                shader_location: 5,
+            },
+            VertexAttributeDescriptor {
+                format: VertexFormat::Float4,
+                offset: offset_of!(Vertex, weight),
+                shader_location: 6,
+            },
+        ],
+        layout.attributes
+    );
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn serde_pipeline_state() {
+    let color_state = ColorStateDescriptor {
+        format: TextureFormat::B8G8R8A8UnormSRGB,
+        color_blend: BlendDescriptor::BLEND,
+        alpha_blend: BlendDescriptor::OPAQUE,
+        write_mask: ColorWrite::RED | ColorWrite::GREEN | ColorWrite::BLUE,
+    };
+    let json = serde_json::to_string(&color_state).unwrap();
+    assert_eq!(color_state, serde_json::from_str(&json).unwrap());
+
+    let depth_stencil_state = DepthStencilStateDescriptor {
+        format: TextureFormat::D32Float,
+        depth_write_enabled: true,
+        depth_compare: CompareFunction::Less,
+        stencil_front: StencilStateFaceDescriptor::IGNORE,
+        stencil_back: StencilStateFaceDescriptor::IGNORE,
+        stencil_read_mask: 0,
+        stencil_write_mask: 0,
+    };
+    let json = serde_json::to_string(&depth_stencil_state).unwrap();
+    assert_eq!(depth_stencil_state, serde_json::from_str(&json).unwrap());
+
+    let rasterization_state = RasterizationStateDescriptor {
+        cull_mode: CullMode::Back,
+        ..RasterizationStateDescriptor::default()
+    };
+    let json = serde_json::to_string(&rasterization_state).unwrap();
+    assert_eq!(rasterization_state, serde_json::from_str(&json).unwrap());
+}
+
+#[test]
+fn pipeline_state_presets() {
+    assert_eq!(
+        RasterizationStateDescriptor::CULL_NONE,
+        RasterizationStateDescriptor::default()
+    );
+    assert_eq!(CullMode::Back, RasterizationStateDescriptor::CULL_BACK.cull_mode);
+    assert_eq!(
+        DepthStencilStateDescriptor::LESS_WRITE,
+        DepthStencilStateDescriptor::default()
+    );
+
+    let read_only = DepthStencilStateDescriptor::READ_ONLY.with_format(TextureFormat::D32FloatS8Uint);
+    assert_eq!(TextureFormat::D32FloatS8Uint, read_only.format);
+    assert!(!read_only.depth_write_enabled);
+
+    let blended = ColorStateDescriptor::blended(TextureFormat::B8G8R8A8UnormSRGB);
+    assert_eq!(BlendDescriptor::BLEND, blended.color_blend);
+    assert_eq!(ColorWrite::ALL, blended.write_mask);
+}
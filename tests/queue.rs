@@ -0,0 +1,194 @@
+use std::time::Duration;
+
+use vki::{
+    BufferDescriptor, BufferUsage, DeviceDescriptor, Extent3d, Origin3d, QueueGlobalPriority, TextureCopyView,
+    TextureDataLayout, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage,
+};
+
+pub mod support;
+
+#[test]
+fn submission_index() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let queue = device.get_queue();
+
+        let buffer_a = device.create_buffer(BufferDescriptor {
+            size: 1024,
+            usage: BufferUsage::COPY_SRC | BufferUsage::COPY_DST,
+            memory_hint: None,
+        })?;
+
+        let buffer_b = device.create_buffer(BufferDescriptor {
+            size: 1024,
+            usage: BufferUsage::COPY_SRC | BufferUsage::COPY_DST,
+            memory_hint: None,
+        })?;
+
+        let mut encoder = device.create_command_encoder()?;
+        encoder.copy_buffer_to_buffer(&buffer_a, 0, &buffer_b, 0, 1024);
+        let first = queue.submit(&[encoder.finish()?])?;
+
+        let mut encoder = device.create_command_encoder()?;
+        encoder.copy_buffer_to_buffer(&buffer_b, 0, &buffer_a, 0, 1024);
+        let second = queue.submit(&[encoder.finish()?])?;
+
+        assert!(first < second);
+
+        // An empty submission returns the most recent index
+        assert_eq!(second, queue.submit(&[])?);
+
+        device.wait_for_submission(second, Duration::from_secs(10))?;
+        assert!(device.is_submission_complete(first));
+        assert!(device.is_submission_complete(second));
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn queue_priority() {
+    vki::validate(|| {
+        let (instance, adapter, _device) = support::init()?;
+
+        let device = adapter.create_device(
+            DeviceDescriptor::default()
+                .with_queue_priority(0.5)
+                .with_global_priority(QueueGlobalPriority::Low),
+        )?;
+
+        let queue = device.get_queue();
+        let index = queue.submit(&[])?;
+        device.wait_for_submission(index, Duration::from_secs(10))?;
+
+        assert!(adapter
+            .create_device(DeviceDescriptor::default().with_queue_priority(2.0))
+            .is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn clone_queue() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let queue = device.get_queue();
+        let cloned = queue.clone();
+
+        let thread = std::thread::spawn(move || -> Result<_, vki::Error> {
+            let device = cloned.device();
+            let buffer_a = device.create_buffer(BufferDescriptor {
+                size: 1024,
+                usage: BufferUsage::COPY_SRC,
+                memory_hint: None,
+            })?;
+            let buffer_b = device.create_buffer(BufferDescriptor {
+                size: 1024,
+                usage: BufferUsage::COPY_DST,
+                memory_hint: None,
+            })?;
+            let mut encoder = device.create_command_encoder()?;
+            encoder.copy_buffer_to_buffer(&buffer_a, 0, &buffer_b, 0, 1024);
+            cloned.submit(&[encoder.finish()?])
+        });
+
+        let cloned_index = thread.join().expect("thread panicked")?;
+        let index = queue.submit(&[])?;
+        assert!(cloned_index <= index);
+
+        device.wait_for_submission(index, Duration::from_secs(10))?;
+        assert!(device.is_submission_complete(cloned_index));
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn write_buffer_and_texture() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let queue = device.get_queue();
+
+        let buffer = device.create_buffer(BufferDescriptor {
+            size: 64,
+            usage: BufferUsage::COPY_SRC | BufferUsage::COPY_DST,
+            memory_hint: None,
+        })?;
+
+        let read_buffer = device.create_buffer(BufferDescriptor {
+            size: 64,
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+            memory_hint: None,
+        })?;
+
+        let texture = device.create_texture(TextureDescriptor {
+            usage: TextureUsage::COPY_DST | TextureUsage::SAMPLED,
+            size: Extent3d {
+                width: 4,
+                height: 1,
+                depth: 1,
+            },
+            array_layer_count: 1,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::R8G8B8A8Unorm,
+        })?;
+
+        queue.write_buffer(&buffer, 0, &[1u32; 8])?;
+        // Overlaps the previous write
+        queue.write_buffer(&buffer, 16, &[2u32; 4])?;
+        queue.write_buffer(&buffer, 32, &[3u32; 8])?;
+
+        let texture_copy_view = || TextureCopyView {
+            texture: &texture,
+            mip_level: 0,
+            array_layer: 0,
+            origin: Origin3d { x: 0, y: 0, z: 0 },
+        };
+        let texture_data_layout = TextureDataLayout {
+            offset: 0,
+            bytes_per_row: 0,
+            rows_per_image: 0,
+        };
+        let size = Extent3d {
+            width: 4,
+            height: 1,
+            depth: 1,
+        };
+        queue.write_texture(texture_copy_view(), &[255u8; 16], texture_data_layout, size)?;
+
+        // The data is too small for the write
+        assert!(queue
+            .write_texture(texture_copy_view(), &[255u8; 8], texture_data_layout, size)
+            .is_err());
+
+        // Exceeds the buffer size
+        assert!(queue.write_buffer(&buffer, 48, &[0u32; 8]).is_err());
+
+        // Missing COPY_DST usage
+        let src_only = device.create_buffer(BufferDescriptor {
+            size: 64,
+            usage: BufferUsage::COPY_SRC,
+            memory_hint: None,
+        })?;
+        assert!(queue.write_buffer(&src_only, 0, &[0u32; 4]).is_err());
+
+        // The pending writes are uploaded before the command buffer executes
+        let mut encoder = device.create_command_encoder()?;
+        encoder.copy_buffer_to_buffer(&buffer, 0, &read_buffer, 0, 64);
+        let index = queue.submit(&[encoder.finish()?])?;
+        device.wait_for_submission(index, Duration::from_secs(10))?;
+
+        let mapped = read_buffer.map_read()?;
+        let data = mapped.read::<u32>(0, 16)?;
+        assert_eq!(&[1, 1, 1, 1, 2, 2, 2, 2], &data[0..8]);
+        assert_eq!(&[3; 8], &data[8..16]);
+
+        Ok(instance)
+    });
+}
@@ -1,4 +1,4 @@
-use vki::SamplerDescriptor;
+use vki::{AddressMode, BorderColor, CompareFunction, DeviceDescriptor, Extensions, FilterMode, SamplerDescriptor};
 
 pub mod support;
 
@@ -11,3 +11,70 @@ fn create_sampler() {
         Ok(instance)
     });
 }
+
+#[test]
+fn create_sampler_with_builder() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+        let descriptor = SamplerDescriptor::linear()
+            .with_address_mode(AddressMode::Repeat)
+            .with_compare_function(CompareFunction::LessEqual);
+        assert_eq!(FilterMode::Linear, descriptor.mag_filter);
+        assert_eq!(FilterMode::Linear, descriptor.min_filter);
+        assert_eq!(FilterMode::Linear, descriptor.mipmap_filter);
+        assert_eq!(AddressMode::Repeat, descriptor.address_mode_w);
+        let _sampler = device.create_sampler(descriptor)?;
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_sampler_clamp_to_border() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+        let descriptor = SamplerDescriptor::linear()
+            .with_address_mode(AddressMode::ClampToBorder)
+            .with_border_color(BorderColor::OpaqueWhite)
+            .with_compare_function(CompareFunction::LessEqual);
+        assert_eq!(BorderColor::TransparentBlack, SamplerDescriptor::default().border_color);
+        assert_eq!(BorderColor::OpaqueWhite, descriptor.border_color);
+        let _sampler = device.create_sampler(descriptor)?;
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_sampler_with_anisotropy() {
+    vki::validate(|| {
+        let (instance, adapter, device) = support::init()?;
+
+        // The extension was not requested by the default device
+        let descriptor = SamplerDescriptor::linear().with_max_anisotropy(4);
+        assert!(device.create_sampler(descriptor).is_err());
+        assert!(device
+            .create_sampler(SamplerDescriptor::linear().with_max_anisotropy(0))
+            .is_err());
+
+        let extensions = adapter.supported_extensions();
+        let features = adapter.supported_features();
+        if extensions.anisotropic_filtering {
+            assert!(features.max_anisotropy >= 1);
+            let device = adapter.create_device(DeviceDescriptor {
+                extensions: Extensions {
+                    anisotropic_filtering: true,
+                    ..Extensions::default()
+                },
+                ..DeviceDescriptor::default()
+            })?;
+            let max_anisotropy = features.max_anisotropy.min(16);
+            let _sampler = device.create_sampler(SamplerDescriptor::linear().with_max_anisotropy(max_anisotropy))?;
+            assert!(device
+                .create_sampler(SamplerDescriptor::linear().with_max_anisotropy(features.max_anisotropy + 1))
+                .is_err());
+        } else {
+            assert_eq!(1, features.max_anisotropy);
+        }
+
+        Ok(instance)
+    });
+}
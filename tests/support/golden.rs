@@ -0,0 +1,256 @@
+//! Golden image comparisons for rendering tests.
+//!
+//! Reference images are stored as PNG files in `tests/golden`. A missing reference image is a test
+//! failure. Set `VKI_UPDATE_GOLDEN=1` to write new reference images or to overwrite existing ones after
+//! an intentional rendering change.
+//!
+//! When a comparison fails, the rendered image is written next to the reference with an `.actual.png`
+//! extension so that it can be inspected.
+
+use vki::{
+    BufferCopyView, BufferDescriptor, BufferUsage, Device, Extent3d, Origin3d, Texture, TextureCopyView,
+    TextureDataLayout, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage, TextureView,
+    COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The default per-channel tolerance. Software rasterizers (e.g. lavapipe) and hardware drivers
+/// may differ slightly in rounding and edge coverage.
+pub const DEFAULT_TOLERANCE: u8 = 2;
+
+/// An offscreen color target suitable for rendering and reading back.
+pub struct OffscreenTarget {
+    pub texture: Texture,
+    pub view: TextureView,
+    pub format: TextureFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl OffscreenTarget {
+    /// Creates a single sampled 2D color target. Only 8-bit RGBA and BGRA formats are supported.
+    pub fn new(
+        device: &Device,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+    ) -> Result<OffscreenTarget, Box<dyn Error>> {
+        assert!(
+            is_supported_format(format),
+            "unsupported golden image format: {:?}",
+            format
+        );
+
+        let texture = device.create_texture(TextureDescriptor {
+            size: Extent3d {
+                width,
+                height,
+                depth: 1,
+            },
+            array_layer_count: 1,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::COPY_SRC,
+        })?;
+
+        let view = texture.create_default_view()?;
+
+        Ok(OffscreenTarget {
+            texture,
+            view,
+            format,
+            width,
+            height,
+        })
+    }
+
+    /// Copies the target into a host visible buffer, waits for the copy to complete, and returns
+    /// the texels in RGBA order.
+    pub fn read_rgba8(&self, device: &Device) -> Result<Vec<u8>, Box<dyn Error>> {
+        let row_size = self.width * 4;
+        let alignment = COPY_BYTES_PER_ROW_ALIGNMENT;
+        let bytes_per_row = (row_size + alignment - 1) / alignment * alignment;
+        let byte_length = (bytes_per_row * self.height) as usize;
+
+        let buffer = device.create_buffer(BufferDescriptor {
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+            size: byte_length,
+            memory_hint: None,
+        })?;
+
+        let mut encoder = device.create_command_encoder()?;
+
+        encoder.copy_texture_to_buffer(
+            TextureCopyView {
+                texture: &self.texture,
+                mip_level: 0,
+                array_layer: 0,
+                origin: Origin3d { x: 0, y: 0, z: 0 },
+            },
+            BufferCopyView {
+                buffer: &buffer,
+                layout: TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row,
+                    rows_per_image: self.height,
+                },
+            },
+            Extent3d {
+                width: self.width,
+                height: self.height,
+                depth: 1,
+            },
+        )?;
+
+        let queue = device.get_queue();
+        queue.submit(&[encoder.finish()?])?;
+
+        let fence = queue.create_fence()?;
+        fence.wait(Duration::from_secs(10))?;
+
+        let mapped = buffer.map_read()?;
+        let mut data = Vec::with_capacity((row_size * self.height) as usize);
+        for row in mapped.read::<u8>(0, byte_length)?.chunks_exact(bytes_per_row as usize) {
+            data.extend_from_slice(&row[..row_size as usize]);
+        }
+
+        if is_bgra(self.format) {
+            for texel in data.chunks_exact_mut(4) {
+                texel.swap(0, 2);
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+fn is_supported_format(format: TextureFormat) -> bool {
+    match format {
+        TextureFormat::R8G8B8A8Unorm
+        | TextureFormat::R8G8B8A8UnormSRGB
+        | TextureFormat::R8G8B8A8Uint
+        | TextureFormat::B8G8R8A8Unorm
+        | TextureFormat::B8G8R8A8UnormSRGB => true,
+        _ => false,
+    }
+}
+
+fn is_bgra(format: TextureFormat) -> bool {
+    match format {
+        TextureFormat::B8G8R8A8Unorm | TextureFormat::B8G8R8A8UnormSRGB => true,
+        _ => false,
+    }
+}
+
+/// The result of comparing two RGBA8 images of the same size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageDiff {
+    /// The number of texels with at least one channel outside of the tolerance
+    pub mismatched_texels: usize,
+    /// The largest per-channel difference found
+    pub max_channel_delta: u8,
+}
+
+impl ImageDiff {
+    pub fn is_match(&self) -> bool {
+        self.mismatched_texels == 0
+    }
+}
+
+/// Compares two RGBA8 images channel by channel.
+pub fn compare_rgba8(expected: &[u8], actual: &[u8], tolerance: u8) -> ImageDiff {
+    assert_eq!(expected.len(), actual.len(), "image sizes differ");
+
+    let mut diff = ImageDiff {
+        mismatched_texels: 0,
+        max_channel_delta: 0,
+    };
+
+    for (e, a) in expected.chunks_exact(4).zip(actual.chunks_exact(4)) {
+        let mut mismatch = false;
+        for (e, a) in e.iter().zip(a.iter()) {
+            let delta = if e > a { e - a } else { a - e };
+            diff.max_channel_delta = diff.max_channel_delta.max(delta);
+            mismatch |= delta > tolerance;
+        }
+        if mismatch {
+            diff.mismatched_texels += 1;
+        }
+    }
+
+    diff
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn update_golden() -> bool {
+    match std::env::var("VKI_UPDATE_GOLDEN").as_ref().map(|s| s.as_str()) {
+        Ok("1") | Ok("true") => true,
+        Ok(_) | Err(_) => false,
+    }
+}
+
+/// Reads back the target and compares it against `tests/golden/<name>.png`.
+///
+/// Panics if the reference image does not exist or if the images do not match within the given
+/// per-channel `tolerance`. With `VKI_UPDATE_GOLDEN=1`, the reference image is written instead.
+pub fn assert_golden(
+    device: &Device,
+    target: &OffscreenTarget,
+    name: &str,
+    tolerance: u8,
+) -> Result<(), Box<dyn Error>> {
+    let actual = target.read_rgba8(device)?;
+
+    let dir = golden_dir();
+    let path = dir.join(format!("{}.png", name));
+
+    if update_golden() {
+        log::warn!("writing golden image: {}", path.display());
+        std::fs::create_dir_all(&dir)?;
+        image::save_buffer(&path, &actual, target.width, target.height, image::ColorType::Rgba8)?;
+        return Ok(());
+    }
+
+    assert!(
+        path.exists(),
+        "golden image not found: {} (set VKI_UPDATE_GOLDEN=1 to create it)",
+        path.display()
+    );
+
+    let expected = image::open(&path)?.to_rgba();
+    assert_eq!(
+        (target.width, target.height),
+        expected.dimensions(),
+        "golden image size mismatch: {}",
+        path.display()
+    );
+
+    let diff = compare_rgba8(&expected, &actual, tolerance);
+
+    if !diff.is_match() {
+        let actual_path = dir.join(format!("{}.actual.png", name));
+        image::save_buffer(
+            &actual_path,
+            &actual,
+            target.width,
+            target.height,
+            image::ColorType::Rgba8,
+        )?;
+        panic!(
+            "golden image mismatch: {} ({:?}); see {}",
+            path.display(),
+            diff,
+            actual_path.display()
+        );
+    }
+
+    Ok(())
+}
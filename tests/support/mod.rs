@@ -3,9 +3,11 @@
 //       reported as unused.
 #![allow(dead_code)]
 
+pub mod golden;
+
 use vki::{
-    Adapter, AdapterOptions, Device, DeviceDescriptor, Instance, PowerPreference, PresentMode, Surface, Swapchain,
-    SwapchainDescriptor, TextureFormat, TextureUsage,
+    Adapter, AdapterOptions, Device, DeviceDescriptor, Instance, InstanceDescriptor, PowerPreference, PresentMode,
+    Surface, Swapchain, SwapchainDescriptor, TextureFormat, TextureUsage, ValidationFeatures,
 };
 
 use winit::dpi::LogicalSize;
@@ -34,8 +36,8 @@ macro_rules! skip_if_no_display {
 /// Setup validation and logging. This is called automatically
 /// by `init` and `init_with_window`.
 pub fn init_environment() {
-    // NOTE: This is currently *also* set via instance creation, but that should probably
-    //       be configurable. However, we *always* want it enabled for tests.
+    // NOTE: This is currently *also* set via `instance_descriptor`, but we *always* want it
+    //       enabled for tests, including those that create the instance directly.
     std::env::set_var("VK_INSTANCE_LAYERS", "VK_LAYER_KHRONOS_validation");
 
     let _ = pretty_env_logger::try_init();
@@ -53,11 +55,21 @@ fn select_power_preference() -> PowerPreference {
     }
 }
 
+/// Enables validation with synchronization validation so that missing barriers are reported
+pub fn instance_descriptor() -> InstanceDescriptor<'static> {
+    InstanceDescriptor {
+        app_name: "vki-tests",
+        enable_validation: true,
+        validation_features: ValidationFeatures::SYNCHRONIZATION,
+        ..InstanceDescriptor::default()
+    }
+}
+
 pub fn init() -> Result<(Instance, Adapter, Device), Box<dyn std::error::Error>> {
     init_environment();
     let power_preference = select_power_preference();
     log::debug!("power_preference: {:?}", power_preference);
-    let instance = Instance::new()?;
+    let instance = Instance::with_descriptor(instance_descriptor())?;
     let adapter = instance.request_adapter(AdapterOptions { power_preference })?;
     log::info!("adapter: {}", adapter.name());
     let device = adapter.create_device(DeviceDescriptor::default())?;
@@ -69,7 +81,7 @@ pub fn init_with_window(
     window: &Window,
 ) -> Result<(Instance, Adapter, Device, Surface, Swapchain), Box<dyn std::error::Error>> {
     init_environment();
-    let instance = Instance::new()?;
+    let instance = Instance::with_descriptor(instance_descriptor())?;
     let adapter = instance.request_adapter(AdapterOptions::default())?;
     let surface = instance.create_surface(window)?;
     let device = adapter.create_device(DeviceDescriptor::default().with_surface_support(&surface))?;
@@ -85,6 +97,9 @@ pub fn swapchain_descriptor<'a>(surface: &'a Surface) -> SwapchainDescriptor<'a>
         format: TextureFormat::B8G8R8A8Unorm,
         usage: TextureUsage::OUTPUT_ATTACHMENT,
         present_mode: PresentMode::Fifo,
+        sample_count: 1,
+        depth_format: None,
+        view_formats: &[],
     }
 }
 
@@ -3,7 +3,7 @@
 extern crate objc;
 
 use log;
-use vki::{AdapterOptions, DeviceDescriptor, Instance};
+use vki::{AdapterOptions, DeviceDescriptor, Instance, SurfaceDescriptor};
 
 #[macro_use]
 pub mod support;
@@ -56,3 +56,30 @@ fn glfw_surface() {
         Ok(instance)
     });
 }
+
+#[test]
+fn recreate_surface() {
+    skip_if_no_display!();
+
+    let _ = pretty_env_logger::try_init();
+    vki::validate(|| {
+        let instance = Instance::new()?;
+        let adapter = instance.request_adapter(AdapterOptions::default())?;
+
+        let event_loop = support::new_event_loop();
+        let window = winit::window::WindowBuilder::new()
+            .with_inner_size(winit::dpi::LogicalSize::<f32> {
+                width: 1024 as _,
+                height: 768 as _,
+            })
+            .with_visible(false)
+            .build(&event_loop)?;
+
+        let surface = instance.create_surface(&window)?;
+        let surface = instance.recreate_surface(&surface, &surface.descriptor())?;
+        let surface = instance.recreate_surface(&surface, &SurfaceDescriptor::from_window(&window))?;
+        let _device = adapter.create_device(DeviceDescriptor::default().with_surface_support(&surface))?;
+
+        Ok(instance)
+    });
+}
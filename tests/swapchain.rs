@@ -1,4 +1,7 @@
-use vki::{AdapterOptions, DeviceDescriptor, Instance};
+use vki::{
+    AdapterOptions, BufferDescriptor, BufferUsage, Color, DeviceDescriptor, Extensions, FrameScheduler,
+    FrameSchedulerDescriptor, Instance, LoadOp, RenderPassDescriptor, SwapchainDescriptor, TextureFormat, TextureUsage,
+};
 
 use winit::dpi::LogicalSize;
 use winit::event::{Event, WindowEvent};
@@ -36,6 +39,127 @@ fn create_swapchain() {
     });
 }
 
+#[test]
+fn create_swapchain_with_storage_usage() {
+    skip_if_no_display!();
+
+    #[cfg(target_os = "linux")]
+    let _guard = LOCK.lock().unwrap();
+
+    vki::validate(|| {
+        support::init_environment();
+        let (_event_loop, window) = support::headless_window()?;
+        let instance = Instance::new()?;
+        let adapter = instance.request_adapter(AdapterOptions::default())?;
+        let surface = instance.create_surface(&window)?;
+        let device = adapter.create_device(DeviceDescriptor::default().with_surface_support(&surface))?;
+
+        let format = TextureFormat::B8G8R8A8Unorm;
+        if !device.get_supported_swapchain_formats(&surface)?.contains(&format) {
+            return Ok(instance);
+        }
+
+        let supported_usage = device.get_supported_swapchain_usage(&surface, format)?;
+        assert!(supported_usage.contains(TextureUsage::OUTPUT_ATTACHMENT));
+
+        let swapchain_descriptor = SwapchainDescriptor {
+            format,
+            usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::STORAGE,
+            ..support::swapchain_descriptor(&surface)
+        };
+
+        let result = device.create_swapchain(swapchain_descriptor, None);
+        if supported_usage.contains(TextureUsage::STORAGE) {
+            let swapchain = result?;
+            let frame = swapchain.acquire_next_image()?;
+            assert!(frame.texture.usage().contains(TextureUsage::STORAGE));
+        } else {
+            assert!(result.is_err());
+        }
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_swapchain_with_view_formats() {
+    skip_if_no_display!();
+
+    #[cfg(target_os = "linux")]
+    let _guard = LOCK.lock().unwrap();
+
+    vki::validate(|| {
+        support::init_environment();
+        let (_event_loop, window) = support::headless_window()?;
+        let instance = Instance::new()?;
+        let adapter = instance.request_adapter(AdapterOptions::default())?;
+        let surface = instance.create_surface(&window)?;
+
+        let view_formats = &[TextureFormat::B8G8R8A8UnormSRGB];
+        let swapchain_descriptor = SwapchainDescriptor {
+            format: TextureFormat::B8G8R8A8Unorm,
+            view_formats,
+            ..support::swapchain_descriptor(&surface)
+        };
+
+        // The extension was not enabled
+        let device = adapter.create_device(DeviceDescriptor::default().with_surface_support(&surface))?;
+        assert!(device.create_swapchain(swapchain_descriptor, None).is_err());
+        drop(device);
+
+        if !adapter.extensions().swapchain_mutable_format {
+            log::warn!("swapchain_mutable_format is not supported, skipping test");
+            return Ok(instance);
+        }
+
+        let device = adapter.create_device(DeviceDescriptor {
+            extensions: Extensions {
+                swapchain_mutable_format: true,
+                ..Extensions::default()
+            },
+            ..DeviceDescriptor::default().with_surface_support(&surface)
+        })?;
+        let swapchain = device.create_swapchain(swapchain_descriptor, None)?;
+
+        assert_eq!(
+            vec![TextureFormat::B8G8R8A8Unorm, TextureFormat::B8G8R8A8UnormSRGB],
+            swapchain.view_formats()
+        );
+        assert!(swapchain
+            .acquire_next_image_with_view_format(TextureFormat::R8G8B8A8Unorm)
+            .is_err());
+
+        let queue = device.get_queue();
+
+        // Alternate between linear and sRGB views of the same images
+        for i in 0..4 {
+            let view_format = if i % 2 == 0 {
+                TextureFormat::B8G8R8A8Unorm
+            } else {
+                TextureFormat::B8G8R8A8UnormSRGB
+            };
+            let frame = swapchain.acquire_next_image_with_view_format(view_format)?;
+            assert_eq!(view_format, frame.view.format());
+
+            let mut encoder = device.create_command_encoder()?;
+            let render_pass = encoder.begin_render_pass(RenderPassDescriptor {
+                color_attachments: &[frame.color_attachment(LoadOp::Clear, Color::default())],
+                depth_stencil_attachment: None,
+                flip_viewport: false,
+                viewport: None,
+                scissor_rect: None,
+                attachmentless: None,
+            });
+            render_pass.end_pass();
+
+            queue.submit(&[encoder.finish()?])?;
+            queue.present(frame)?;
+        }
+
+        Ok(instance)
+    });
+}
+
 #[test]
 fn recreate_swapchain_without_old() {
     skip_if_no_display!();
@@ -103,6 +227,98 @@ fn present() {
     });
 }
 
+#[test]
+fn external_layout() {
+    skip_if_no_display!();
+
+    #[cfg(target_os = "linux")]
+    let _guard = LOCK.lock().unwrap();
+
+    vki::validate(|| {
+        let (_event_loop, window) = support::headless_window()?;
+        let (instance, _adapter, device, _surface, swapchain) = support::init_with_window(&window)?;
+
+        let frame = swapchain.acquire_next_image()?;
+        assert_ne!(ash::vk::Image::null(), frame.raw_image());
+
+        // An external library rendered into the image and left it ready for presentation
+        frame.set_external_layout(ash::vk::ImageLayout::PRESENT_SRC_KHR)?;
+        assert_eq!(ash::vk::ImageLayout::PRESENT_SRC_KHR, frame.current_layout());
+
+        assert!(frame
+            .set_external_layout(ash::vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .is_err());
+
+        // No external commands were actually recorded, so let vki discard the contents
+        frame.set_external_layout(ash::vk::ImageLayout::UNDEFINED)?;
+
+        let queue = device.get_queue();
+        queue.present(frame)?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn frame_scheduler() {
+    skip_if_no_display!();
+
+    #[cfg(target_os = "linux")]
+    let _guard = LOCK.lock().unwrap();
+
+    vki::validate(|| {
+        let (_event_loop, window) = support::headless_window()?;
+        let (instance, _adapter, device, _surface, swapchain) = support::init_with_window(&window)?;
+
+        let mut frame_scheduler = FrameScheduler::new(
+            &device,
+            FrameSchedulerDescriptor {
+                frames_in_flight: swapchain.image_count(),
+                staging_buffer_size: 1024,
+            },
+        )?;
+        assert_eq!(swapchain.image_count(), frame_scheduler.frames_in_flight());
+
+        let uniform_buffer = device.create_buffer(BufferDescriptor {
+            size: 256,
+            usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
+            memory_hint: None,
+        })?;
+
+        for i in 0..(3 * frame_scheduler.frames_in_flight()) {
+            let mut frame = frame_scheduler.begin_frame(&swapchain)?;
+            assert_eq!(i as usize % swapchain.image_count() as usize, frame.index());
+
+            frame.write_buffer(&uniform_buffer, 0, &[i as f32; 16])?;
+            frame.write_buffer(&uniform_buffer, 64, &[i; 3])?;
+            assert!(frame.write_buffer(&uniform_buffer, 0, &[0u8; 1024]).is_err());
+
+            let color_attachment = frame.image.color_attachment(
+                LoadOp::Clear,
+                Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 1.0,
+                },
+            );
+            let render_pass = frame.encoder.begin_render_pass(RenderPassDescriptor {
+                color_attachments: &[color_attachment],
+                depth_stencil_attachment: None,
+                flip_viewport: false,
+                viewport: None,
+                scissor_rect: None,
+                attachmentless: None,
+            });
+            render_pass.end_pass();
+
+            frame_scheduler.end_frame(frame)?;
+        }
+
+        Ok(instance)
+    });
+}
+
 #[test]
 #[cfg_attr(target_os = "linux", ignore)] // TODO: winit eventloop-2.0 is eating events right now
 fn recreate_after_resize() {
@@ -1,7 +1,7 @@
 use vki::{
-    BufferCopyView, BufferDescriptor, BufferUsage, Extent3d, FilterMode, Origin3d, TextureAspect, TextureBlitView,
-    TextureCopyView, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage, TextureViewDescriptor,
-    TextureViewDimension,
+    BufferCopyView, BufferDescriptor, BufferUsage, Extent3d, FilterMode, Origin3d, SparsePage, SparseTextureBind,
+    TextureAspect, TextureBlitView, TextureCopyView, TextureDataLayout, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureFormatFeatures, TextureUsage, TextureViewDescriptor, TextureViewDimension,
 };
 
 pub mod support;
@@ -31,6 +31,75 @@ fn create_texture() {
     });
 }
 
+#[test]
+fn create_texture_with_builder() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let descriptor = TextureDescriptor::new_2d(TextureFormat::R8G8B8A8Unorm, 1024, 512)
+            .with_usage(TextureUsage::SAMPLED | TextureUsage::OUTPUT_ATTACHMENT)
+            .with_full_mip_chain();
+
+        assert_eq!(
+            Extent3d {
+                width: 1024,
+                height: 512,
+                depth: 1,
+            },
+            descriptor.size
+        );
+        assert_eq!(11, descriptor.mip_level_count);
+        assert_eq!(1, descriptor.array_layer_count);
+        assert_eq!(1, descriptor.sample_count);
+        assert_eq!(TextureDimension::D2, descriptor.dimension);
+
+        let _texture = device.create_texture(descriptor)?;
+
+        let cube_descriptor = TextureDescriptor::new_cube(TextureFormat::R8G8B8A8Unorm, 256);
+        assert_eq!(6, cube_descriptor.array_layer_count);
+        let _cube_texture = device.create_texture(cube_descriptor)?;
+
+        let volume_descriptor = TextureDescriptor::new_3d(TextureFormat::R8Unorm, 32, 32, 32).with_full_mip_chain();
+        assert_eq!(TextureDimension::D3, volume_descriptor.dimension);
+        assert_eq!(6, volume_descriptor.mip_level_count);
+        let _volume_texture = device.create_texture(volume_descriptor)?;
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_integer_attachment_textures() {
+    vki::validate(|| {
+        let (instance, adapter, device) = support::init()?;
+
+        let formats = [
+            TextureFormat::R32Uint,
+            TextureFormat::R32Sint,
+            TextureFormat::RG32Uint,
+            TextureFormat::RG32Sint,
+            TextureFormat::RGBA32Uint,
+            TextureFormat::RGBA32Sint,
+        ];
+
+        for &format in formats.iter() {
+            // Color attachment and storage support are required by the Vulkan spec for these formats
+            let features = adapter.get_texture_format_features(format);
+            assert!(features.contains(TextureFormatFeatures::COLOR_ATTACHMENT | TextureFormatFeatures::STORAGE));
+            assert!(!features.contains(TextureFormatFeatures::COLOR_ATTACHMENT_BLEND));
+
+            let texture =
+                device
+                    .create_texture(TextureDescriptor::new_2d(format, 64, 64).with_usage(
+                        TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::STORAGE | TextureUsage::COPY_SRC,
+                    ))?;
+            let _view = texture.create_default_view()?;
+        }
+
+        Ok(instance)
+    });
+}
+
 #[test]
 fn create_default_texture_view() {
     vki::validate(|| {
@@ -302,6 +371,7 @@ fn copy_buffer_to_texture() {
         let buffer1 = device.create_buffer(BufferDescriptor {
             size: (width * height) as usize * std::mem::size_of::<f32>(),
             usage: BufferUsage::COPY_SRC,
+            memory_hint: None,
         })?;
 
         let texture1 = device.create_texture(TextureDescriptor {
@@ -316,9 +386,11 @@ fn copy_buffer_to_texture() {
 
         let src = BufferCopyView {
             buffer: &buffer1,
-            row_length: width, // TODO row_pitch
-            image_height: height,
-            offset: 0,
+            layout: TextureDataLayout {
+                offset: 0,
+                bytes_per_row: width * 4,
+                rows_per_image: height,
+            },
         };
 
         let dst = TextureCopyView {
@@ -330,7 +402,25 @@ fn copy_buffer_to_texture() {
 
         let mut encoder = device.create_command_encoder()?;
 
-        encoder.copy_buffer_to_texture(src, dst, size);
+        let unaligned = BufferCopyView {
+            layout: TextureDataLayout {
+                bytes_per_row: width * 4 + 4,
+                ..src.layout
+            },
+            ..src.clone()
+        };
+        assert!(encoder.copy_buffer_to_texture(unaligned, dst.clone(), size).is_err());
+
+        let too_few_rows = BufferCopyView {
+            layout: TextureDataLayout {
+                rows_per_image: height - 1,
+                ..src.layout
+            },
+            ..src.clone()
+        };
+        assert!(encoder.copy_buffer_to_texture(too_few_rows, dst.clone(), size).is_err());
+
+        encoder.copy_buffer_to_texture(src, dst, size)?;
 
         let command_buffers = &[encoder.finish()?];
 
@@ -358,6 +448,7 @@ fn copy_texture_to_buffer() {
         let buffer1 = device.create_buffer(BufferDescriptor {
             size: (width * height) as usize * std::mem::size_of::<f32>(),
             usage: BufferUsage::COPY_DST,
+            memory_hint: None,
         })?;
 
         let texture1 = device.create_texture(TextureDescriptor {
@@ -372,9 +463,11 @@ fn copy_texture_to_buffer() {
 
         let dst = BufferCopyView {
             buffer: &buffer1,
-            row_length: width, // TODO row_pitch
-            image_height: height,
-            offset: 0,
+            layout: TextureDataLayout {
+                offset: 0,
+                bytes_per_row: width * 4,
+                rows_per_image: height,
+            },
         };
 
         let src = TextureCopyView {
@@ -386,7 +479,7 @@ fn copy_texture_to_buffer() {
 
         let mut encoder = device.create_command_encoder()?;
 
-        encoder.copy_texture_to_buffer(src, dst, size);
+        encoder.copy_texture_to_buffer(src, dst, size)?;
 
         let command_buffers = &[encoder.finish()?];
 
@@ -402,3 +495,207 @@ fn copy_texture_to_buffer() {
         Ok(instance)
     })
 }
+
+#[test]
+fn copy_buffer_to_texture_layers() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let (width, height, depth) = (64, 64, 1);
+        let size = Extent3d { width, height, depth };
+        let array_layer_count = 6;
+
+        let layer_size = (width * height) as usize * std::mem::size_of::<u32>();
+
+        let buffer = device.create_buffer(BufferDescriptor {
+            size: layer_size * array_layer_count as usize,
+            usage: BufferUsage::COPY_SRC,
+            memory_hint: None,
+        })?;
+
+        let texture = device.create_texture(TextureDescriptor {
+            usage: TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+            sample_count: 1,
+            format: TextureFormat::R8G8B8A8Unorm,
+            dimension: TextureDimension::D2,
+            size,
+            array_layer_count,
+            mip_level_count: 1,
+        })?;
+
+        let src = BufferCopyView {
+            buffer: &buffer,
+            layout: TextureDataLayout {
+                offset: 0,
+                bytes_per_row: width * 4,
+                rows_per_image: 0,
+            },
+        };
+
+        let dst = TextureCopyView {
+            texture: &texture,
+            mip_level: 0,
+            array_layer: 0,
+            origin: Origin3d { x: 0, y: 0, z: 0 },
+        };
+
+        let mut encoder = device.create_command_encoder()?;
+
+        encoder.copy_buffer_to_texture_layers(src.clone(), dst.clone(), size, array_layer_count)?;
+
+        // too many layers for the texture
+        assert!(encoder
+            .copy_buffer_to_texture_layers(src.clone(), dst.clone(), size, array_layer_count + 1)
+            .is_err());
+
+        // the buffer is too small when starting at an offset
+        let src_offset = BufferCopyView {
+            layout: TextureDataLayout {
+                offset: 256,
+                ..src.layout
+            },
+            ..src
+        };
+        assert!(encoder
+            .copy_buffer_to_texture_layers(src_offset, dst, size, array_layer_count)
+            .is_err());
+
+        let queue = device.get_queue();
+
+        queue.submit(&[encoder.finish()?])?;
+
+        Ok(instance)
+    })
+}
+
+#[test]
+fn create_texture_invalid_descriptor() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let descriptor = TextureDescriptor {
+            usage: TextureUsage::SAMPLED,
+            size: Extent3d {
+                width: 16,
+                height: 16,
+                depth: 1,
+            },
+            array_layer_count: 1,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::R8G8B8A8Unorm,
+        };
+
+        assert!(device.create_texture(descriptor).is_ok());
+
+        // log2(16) + 1 = 5 mip levels at most
+        assert!(device
+            .create_texture(TextureDescriptor {
+                mip_level_count: 6,
+                ..descriptor
+            })
+            .is_err());
+
+        assert!(device
+            .create_texture(TextureDescriptor {
+                dimension: TextureDimension::D3,
+                array_layer_count: 2,
+                ..descriptor
+            })
+            .is_err());
+
+        assert!(device
+            .create_texture(TextureDescriptor {
+                sample_count: 4,
+                mip_level_count: 2,
+                ..descriptor
+            })
+            .is_err());
+
+        assert!(device
+            .create_texture(TextureDescriptor {
+                usage: TextureUsage::STORAGE,
+                format: TextureFormat::R8G8B8A8UnormSRGB,
+                ..descriptor
+            })
+            .is_err());
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn create_sparse_texture_and_bind_tiles() {
+    vki::validate(|| {
+        let (instance, adapter, device) = support::init()?;
+
+        let descriptor = TextureDescriptor {
+            usage: TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+            size: Extent3d {
+                width: 1024,
+                height: 1024,
+                depth: 1,
+            },
+            array_layer_count: 1,
+            mip_level_count: 11,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::R8G8B8A8Unorm,
+        };
+
+        if !adapter.supports_sparse_residency() {
+            assert!(device.create_sparse_texture(descriptor).is_err());
+            return Ok(instance);
+        }
+
+        let texture = device.create_sparse_texture(descriptor)?;
+        let tile_size = texture.sparse_tile_size().expect("sparse_tile_size");
+        assert!(texture.sparse_mip_tail_first_level().is_some());
+
+        let page_pool = texture.create_sparse_page_pool(4)?;
+        assert_eq!(4, page_pool.page_count());
+
+        let queue = device.get_queue();
+
+        let bind = SparseTextureBind {
+            texture: &texture,
+            mip_level: 0,
+            array_layer: 0,
+            origin: Origin3d {
+                x: tile_size.width as i32,
+                y: 0,
+                z: 0,
+            },
+            page: Some(SparsePage {
+                pool: &page_pool,
+                index: 1,
+            }),
+        };
+
+        queue.bind_sparse(&[bind])?;
+
+        let misaligned = SparseTextureBind {
+            origin: Origin3d { x: 1, y: 0, z: 0 },
+            ..bind
+        };
+        assert!(queue.bind_sparse(&[misaligned]).is_err());
+
+        let out_of_range = SparseTextureBind {
+            page: Some(SparsePage {
+                pool: &page_pool,
+                index: 4,
+            }),
+            ..bind
+        };
+        assert!(queue.bind_sparse(&[out_of_range]).is_err());
+
+        queue.bind_sparse(&[SparseTextureBind { page: None, ..bind }])?;
+
+        let non_sparse_texture = device.create_texture(descriptor)?;
+        assert!(non_sparse_texture.sparse_tile_size().is_none());
+        assert!(non_sparse_texture.create_sparse_page_pool(1).is_err());
+
+        Ok(instance)
+    });
+}
@@ -0,0 +1,453 @@
+use std::time::Duration;
+
+use vki::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
+    BufferCopyView, BufferDescriptor, BufferUsage, Color, Extent3d, FilterMode, LoadOp, Origin3d, RenderPassDescriptor,
+    ShaderStage, TextureBlitView, TextureCopyView, TextureDataLayout, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsage,
+};
+
+#[macro_use]
+pub mod support;
+
+// The tests in this file are run with synchronization validation (see `support::instance_descriptor`)
+// so that a missing or incorrect barrier is reported as a validation error.
+
+// TODO: Concurrent EventLoop creation hangs or segfaults in winit on x11
+#[cfg(target_os = "linux")]
+lazy_static::lazy_static! {
+    static ref LOCK: std::sync::Mutex::<()> = std::sync::Mutex::new(());
+}
+
+#[test]
+fn sampled_storage_sampled() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let (width, height, depth) = (64, 64, 1);
+        let size = Extent3d { width, height, depth };
+        let format = TextureFormat::R8G8B8A8Unorm;
+
+        let texture = device.create_texture(TextureDescriptor {
+            usage: TextureUsage::SAMPLED | TextureUsage::STORAGE | TextureUsage::COPY_SRC | TextureUsage::COPY_DST,
+            sample_count: 1,
+            format,
+            dimension: TextureDimension::D2,
+            size,
+            array_layer_count: 1,
+            mip_level_count: 1,
+        })?;
+
+        let texture_view = texture.create_default_view()?;
+
+        let data: Vec<u8> = (0..width * height * 4).map(|i| (i % 251) as u8).collect();
+
+        let upload_buffer = device.create_buffer_mapped(BufferDescriptor {
+            usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
+            size: data.len(),
+            memory_hint: None,
+        })?;
+        upload_buffer.copy_from_slice(&data)?;
+        let upload_buffer = upload_buffer.unmap();
+
+        let read_buffer = device.create_buffer(BufferDescriptor {
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+            size: data.len(),
+            memory_hint: None,
+        })?;
+
+        let sampled_bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStage::COMPUTE,
+                binding_type: BindingType::SampledTexture,
+                storage_texture_format: None,
+                min_binding_size: None,
+            }],
+        })?;
+
+        let storage_bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStage::COMPUTE,
+                binding_type: BindingType::WriteOnlyStorageTexture,
+                storage_texture_format: Some(format),
+                min_binding_size: None,
+            }],
+        })?;
+
+        let sampled_bind_group = device.create_bind_group(BindGroupDescriptor {
+            layout: sampled_bind_group_layout,
+            entries: vec![BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(texture_view.clone()),
+            }],
+        })?;
+
+        let storage_bind_group = device.create_bind_group(BindGroupDescriptor {
+            layout: storage_bind_group_layout,
+            entries: vec![BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(texture_view.clone()),
+            }],
+        })?;
+
+        let buffer_copy_view = |buffer| BufferCopyView {
+            buffer,
+            layout: TextureDataLayout {
+                offset: 0,
+                bytes_per_row: width * 4,
+                rows_per_image: height,
+            },
+        };
+
+        let texture_copy_view = TextureCopyView {
+            texture: &texture,
+            mip_level: 0,
+            array_layer: 0,
+            origin: Origin3d { x: 0, y: 0, z: 0 },
+        };
+
+        let queue = device.get_queue();
+
+        // copy -> sampled -> storage -> sampled, all within one command buffer
+        let mut encoder = device.create_command_encoder()?;
+        encoder.copy_buffer_to_texture(buffer_copy_view(&upload_buffer), texture_copy_view.clone(), size)?;
+        for bind_group in [&sampled_bind_group, &storage_bind_group, &sampled_bind_group].iter() {
+            let mut compute_pass = encoder.begin_compute_pass();
+            compute_pass.set_bind_group(0, bind_group, None);
+            compute_pass.end_pass();
+        }
+        queue.submit(&[encoder.finish()?])?;
+
+        // The texture's last usage is carried over to the next command buffer
+        let mut encoder = device.create_command_encoder()?;
+        for bind_group in [&storage_bind_group, &sampled_bind_group].iter() {
+            let mut compute_pass = encoder.begin_compute_pass();
+            compute_pass.set_bind_group(0, bind_group, None);
+            compute_pass.end_pass();
+        }
+        encoder.copy_texture_to_buffer(texture_copy_view, buffer_copy_view(&read_buffer), size)?;
+        queue.submit(&[encoder.finish()?])?;
+
+        let fence = queue.create_fence()?;
+        fence.wait(Duration::from_millis(1_000_000_000))?;
+
+        // None of the layout transitions may discard the contents of the texture
+        let read_buffer_mapped = read_buffer.map_read()?;
+        let read: &[u8] = read_buffer_mapped.read(0, data.len())?;
+        assert_eq!(&data[..], read);
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn generate_mipmaps_per_mip_transitions() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        let (width, height, depth) = (256, 256, 1);
+        let mip_level_count = 9;
+        let color = [255u8, 0, 128, 255];
+
+        let texture = device.create_texture(TextureDescriptor {
+            usage: TextureUsage::SAMPLED | TextureUsage::COPY_SRC | TextureUsage::COPY_DST,
+            sample_count: 1,
+            format: TextureFormat::R8G8B8A8Unorm,
+            dimension: TextureDimension::D2,
+            size: Extent3d { width, height, depth },
+            array_layer_count: 1,
+            mip_level_count,
+        })?;
+
+        let data: Vec<u8> = color
+            .iter()
+            .cloned()
+            .cycle()
+            .take((width * height * 4) as usize)
+            .collect();
+
+        let upload_buffer = device.create_buffer_mapped(BufferDescriptor {
+            usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
+            size: data.len(),
+            memory_hint: None,
+        })?;
+        upload_buffer.copy_from_slice(&data)?;
+        let upload_buffer = upload_buffer.unmap();
+
+        // One 256 byte row per texel row of each mip that is read back
+        let read_mip_levels = [4, 8];
+        let read_row_count: u32 = read_mip_levels.iter().map(|mip_level| height >> mip_level).sum();
+        let read_buffer = device.create_buffer(BufferDescriptor {
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+            size: read_row_count as usize * 256,
+            memory_hint: None,
+        })?;
+
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStage::COMPUTE,
+                binding_type: BindingType::SampledTexture,
+                storage_texture_format: None,
+                min_binding_size: None,
+            }],
+        })?;
+
+        let bind_group = device.create_bind_group(BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: vec![BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(texture.create_default_view()?),
+            }],
+        })?;
+
+        let mut encoder = device.create_command_encoder()?;
+
+        encoder.copy_buffer_to_texture(
+            BufferCopyView {
+                buffer: &upload_buffer,
+                layout: TextureDataLayout {
+                    offset: 0,
+                    bytes_per_row: width * 4,
+                    rows_per_image: height,
+                },
+            },
+            TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                array_layer: 0,
+                origin: Origin3d { x: 0, y: 0, z: 0 },
+            },
+            Extent3d { width, height, depth },
+        )?;
+
+        // Each blit reads from a mip level that was written by the previous blit, so every mip
+        // is transitioned individually between the transfer layouts
+        for mip_level in 1..mip_level_count {
+            let mip_bounds = |mip_level: u32| {
+                [
+                    Origin3d { x: 0, y: 0, z: 0 },
+                    Origin3d {
+                        x: (width >> mip_level).max(1) as i32,
+                        y: (height >> mip_level).max(1) as i32,
+                        z: 1,
+                    },
+                ]
+            };
+            let src = TextureBlitView {
+                texture: &texture,
+                mip_level: mip_level - 1,
+                array_layer: 0,
+                bounds: mip_bounds(mip_level - 1),
+            };
+            let dst = TextureBlitView {
+                texture: &texture,
+                mip_level,
+                array_layer: 0,
+                bounds: mip_bounds(mip_level),
+            };
+            encoder.blit_texture_to_texture(src, dst, FilterMode::Linear);
+        }
+
+        // The mips are in different layouts at this point and are all transitioned for sampling
+        let mut compute_pass = encoder.begin_compute_pass();
+        compute_pass.set_bind_group(0, &bind_group, None);
+        compute_pass.end_pass();
+
+        let mut offset = 0;
+        for &mip_level in read_mip_levels.iter() {
+            let mip_width = (width >> mip_level).max(1);
+            let mip_height = (height >> mip_level).max(1);
+            encoder.copy_texture_to_buffer(
+                TextureCopyView {
+                    texture: &texture,
+                    mip_level,
+                    array_layer: 0,
+                    origin: Origin3d { x: 0, y: 0, z: 0 },
+                },
+                BufferCopyView {
+                    buffer: &read_buffer,
+                    layout: TextureDataLayout {
+                        offset,
+                        bytes_per_row: 256,
+                        rows_per_image: mip_height,
+                    },
+                },
+                Extent3d {
+                    width: mip_width,
+                    height: mip_height,
+                    depth: 1,
+                },
+            )?;
+            offset += mip_height as usize * 256;
+        }
+
+        let queue = device.get_queue();
+        queue.submit(&[encoder.finish()?])?;
+
+        let fence = queue.create_fence()?;
+        fence.wait(Duration::from_millis(1_000_000_000))?;
+
+        // Filtering a single color produces the same color at every mip level
+        let read_buffer_mapped = read_buffer.map_read()?;
+        let mut offset = 0;
+        for &mip_level in read_mip_levels.iter() {
+            let mip_width = (width >> mip_level).max(1) as usize;
+            let mip_height = (height >> mip_level).max(1) as usize;
+            for row in 0..mip_height {
+                let texels: &[[u8; 4]] = read_buffer_mapped.read((offset + row * 256) / 4, mip_width)?;
+                for texel in texels.iter() {
+                    assert_eq!(&color, texel, "mip_level: {}, row: {}", mip_level, row);
+                }
+            }
+            offset += mip_height * 256;
+        }
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn buffer_ranges_transition_independently() {
+    vki::validate(|| {
+        let (instance, _adapter, device) = support::init()?;
+
+        // Two ranges of the same buffer that are used differently within each pass
+        let range_size = 256;
+        let size = range_size * 2;
+
+        let data: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+
+        let upload_buffer = device.create_buffer_mapped(BufferDescriptor {
+            usage: BufferUsage::MAP_WRITE | BufferUsage::COPY_SRC,
+            size,
+            memory_hint: None,
+        })?;
+        upload_buffer.copy_from_slice(&data)?;
+        let upload_buffer = upload_buffer.unmap();
+
+        let buffer = device.create_buffer(BufferDescriptor {
+            usage: BufferUsage::UNIFORM | BufferUsage::STORAGE | BufferUsage::COPY_SRC | BufferUsage::COPY_DST,
+            size,
+            memory_hint: None,
+        })?;
+
+        let read_buffer = device.create_buffer(BufferDescriptor {
+            usage: BufferUsage::MAP_READ | BufferUsage::COPY_DST,
+            size,
+            memory_hint: None,
+        })?;
+
+        let bind_group_layout = device.create_bind_group_layout(BindGroupLayoutDescriptor {
+            entries: vec![
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStage::COMPUTE,
+                    binding_type: BindingType::UniformBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStage::COMPUTE,
+                    binding_type: BindingType::StorageBuffer,
+                    storage_texture_format: None,
+                    min_binding_size: None,
+                },
+            ],
+        })?;
+
+        let bind_group = device.create_bind_group(BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: vec![
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Buffer(buffer.clone(), 0..range_size),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Buffer(buffer.clone(), range_size..size),
+                },
+            ],
+        })?;
+
+        let mut encoder = device.create_command_encoder()?;
+        encoder.copy_buffer_to_buffer(&upload_buffer, 0, &buffer, 0, size);
+
+        let mut compute_pass = encoder.begin_compute_pass();
+        compute_pass.set_bind_group(0, &bind_group, None);
+        compute_pass.end_pass();
+
+        // Only the uniform range is written, while the storage range keeps its last usage
+        encoder.copy_buffer_to_buffer(&upload_buffer, 0, &buffer, 0, range_size);
+
+        let mut compute_pass = encoder.begin_compute_pass();
+        compute_pass.set_bind_group(0, &bind_group, None);
+        compute_pass.end_pass();
+
+        encoder.copy_buffer_to_buffer(&buffer, 0, &read_buffer, 0, size);
+
+        let queue = device.get_queue();
+        queue.submit(&[encoder.finish()?])?;
+
+        let fence = queue.create_fence()?;
+        fence.wait(Duration::from_millis(1_000_000_000))?;
+
+        let read_buffer_mapped = read_buffer.map_read()?;
+        let read: &[u8] = read_buffer_mapped.read(0, size)?;
+        assert_eq!(&data[..], read);
+
+        Ok(instance)
+    });
+}
+
+#[test]
+fn swapchain_present_round_trips() {
+    skip_if_no_display!();
+
+    #[cfg(target_os = "linux")]
+    let _guard = LOCK.lock().unwrap();
+
+    vki::validate(|| {
+        let (_event_loop, window) = support::headless_window()?;
+        let (instance, _adapter, device, _surface, swapchain) = support::init_with_window(&window)?;
+
+        let queue = device.get_queue();
+
+        // Enough frames for every swapchain image to go from PRESENT to OUTPUT_ATTACHMENT and back
+        // more than once
+        for i in 0..8 {
+            let frame = swapchain.acquire_next_image()?;
+
+            let mut encoder = device.create_command_encoder()?;
+            let load_op = if i % 2 == 0 { LoadOp::Clear } else { LoadOp::Load };
+            let render_pass = encoder.begin_render_pass(RenderPassDescriptor {
+                color_attachments: &[frame.color_attachment(
+                    load_op,
+                    Color {
+                        r: 0.0,
+                        g: 0.0,
+                        b: 0.0,
+                        a: 1.0,
+                    },
+                )],
+                depth_stencil_attachment: None,
+                flip_viewport: false,
+                viewport: None,
+                scissor_rect: None,
+                attachmentless: None,
+            });
+            render_pass.end_pass();
+
+            queue.submit(&[encoder.finish()?])?;
+            queue.present(frame)?;
+        }
+
+        let fence = queue.create_fence()?;
+        fence.wait(Duration::from_millis(1_000_000_000))?;
+
+        Ok(instance)
+    });
+}
@@ -0,0 +1,147 @@
+//! Derive macros for `vki`. Use the re-exports from `vki` rather than depending on this crate directly.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, Lit, Meta, NestedMeta};
+
+/// Implements `vki::VertexLayout` for a `#[repr(C)]` struct with named fields.
+///
+/// Each field becomes a vertex attribute. The attribute format is derived from the field type
+/// via `vki::VertexAttributeFormat` and shader locations are assigned in declaration order.
+///
+/// Field attributes:
+///
+/// * `#[vertex(format = "UChar4Norm")]` overrides the `VertexFormat` of the field
+/// * `#[vertex(location = 4)]` sets the shader location; following fields continue from it
+/// * `#[vertex(skip)]` excludes the field (e.g. padding)
+#[proc_macro_derive(VertexLayout, attributes(vertex))]
+pub fn derive_vertex_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match vertex_layout(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct FieldOptions {
+    format: Option<Ident>,
+    location: Option<u32>,
+    skip: bool,
+}
+
+fn field_options(field: &syn::Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("vertex")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(syn::Error::new(meta.span(), "expected `#[vertex(...)]`")),
+        };
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => {
+                    options.skip = true;
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("format") => match &nv.lit {
+                    Lit::Str(s) => options.format = Some(Ident::new(&s.value(), s.span())),
+                    lit => return Err(syn::Error::new(lit.span(), "expected a `VertexFormat` variant name")),
+                },
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("location") => match &nv.lit {
+                    Lit::Int(i) => options.location = Some(i.base10_parse()?),
+                    lit => return Err(syn::Error::new(lit.span(), "expected an integer shader location")),
+                },
+                nested => return Err(syn::Error::new(nested.span(), "unknown vertex attribute option")),
+            }
+        }
+    }
+    Ok(options)
+}
+
+fn is_repr_c(input: &DeriveInput) -> bool {
+    input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("repr"))
+        .any(|attr| {
+            if let Ok(Meta::List(list)) = attr.parse_meta() {
+                list.nested.iter().any(|nested| match nested {
+                    NestedMeta::Meta(Meta::Path(path)) => path.is_ident("C"),
+                    _ => false,
+                })
+            } else {
+                false
+            }
+        })
+}
+
+fn vertex_layout(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(
+            input.generics.span(),
+            "VertexLayout can not be derived for generic types",
+        ));
+    }
+
+    if !is_repr_c(input) {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "VertexLayout requires `#[repr(C)]` for a stable field layout",
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new(input.span(), "VertexLayout requires named fields")),
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "VertexLayout can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut shader_location = 0;
+    let mut attributes = Vec::with_capacity(fields.len());
+    for field in fields.iter() {
+        let options = field_options(field)?;
+        if options.skip {
+            continue;
+        }
+        if let Some(location) = options.location {
+            shader_location = location;
+        }
+        let field_name = field.ident.as_ref().expect("named field");
+        let field_type = &field.ty;
+        let format = match options.format {
+            Some(format) => quote_spanned!(format.span()=> ::vki::VertexFormat::#format),
+            None => quote_spanned!(field_type.span()=> <#field_type as ::vki::VertexAttributeFormat>::FORMAT),
+        };
+        attributes.push(quote! {
+            ::vki::VertexAttributeDescriptor {
+                format: #format,
+                offset: unsafe { ::std::ptr::addr_of!((*base).#field_name) as usize - base as usize },
+                shader_location: #shader_location,
+            }
+        });
+        shader_location += 1;
+    }
+
+    Ok(quote! {
+        impl ::vki::VertexLayout for #name {
+            #[allow(unused_variables)]
+            fn vertex_attributes() -> ::std::vec::Vec<::vki::VertexAttributeDescriptor> {
+                let uninit = ::std::mem::MaybeUninit::<#name>::uninit();
+                let base = uninit.as_ptr();
+                ::std::vec![#(#attributes),*]
+            }
+        }
+    })
+}